@@ -0,0 +1,62 @@
+//! Benchmark tests for batched document cache lookups
+//!
+//! Looks up 500 cached documents one query at a time and with a single
+//! `get_documents` query against an in-memory database. The batched lookup
+//! should be well ahead of the per-key one.
+
+use chrono::Utc;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lib::cache::{apply_schema, init_memory_database, CacheOperations, DocumentCacheEntry};
+use tokio::runtime::Runtime;
+
+const DOCUMENTS: usize = 500;
+
+/// A cache holding `DOCUMENTS` document entries keyed `node_0`, `node_1`, ...
+async fn populated_cache() -> CacheOperations {
+    let db = init_memory_database().await.unwrap();
+    apply_schema(&db).await.unwrap();
+    let cache = CacheOperations::new(db);
+
+    let entries = (0..DOCUMENTS)
+        .map(|i| DocumentCacheEntry {
+            id: None,
+            resource_hash: format!("node_{}", i),
+            content_hash: format!("content_{}", i),
+            file_path: Some(format!("/tmp/node_{}.md", i)),
+            url: None,
+            last_validated: Utc::now(),
+            file_size: None,
+            modified_at: None,
+        })
+        .collect();
+    cache.upsert_documents(entries).await.unwrap();
+    cache
+}
+
+/// Benchmark per-key lookups against a single batch query
+fn bench_document_lookup(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let cache = runtime.block_on(populated_cache());
+    let hashes: Vec<String> = (0..DOCUMENTS).map(|i| format!("node_{}", i)).collect();
+    let keys: Vec<&str> = hashes.iter().map(String::as_str).collect();
+
+    let mut group = c.benchmark_group("document_lookup");
+    group.sample_size(10);
+    group.bench_function(format!("per_key_{}", DOCUMENTS), |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for key in &keys {
+                    black_box(cache.get_document(key).await.unwrap());
+                }
+            })
+        });
+    });
+    group.bench_function(format!("batched_{}", DOCUMENTS), |b| {
+        b.iter(|| black_box(runtime.block_on(cache.get_documents(&keys)).unwrap()));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_document_lookup);
+
+criterion_main!(benches);
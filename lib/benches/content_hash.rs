@@ -0,0 +1,77 @@
+//! Benchmark tests for streaming content hashing
+//!
+//! Hashes a synthetic 500 MB file (override with `CONTENT_HASH_BENCH_MB`).
+//! Peak resident memory is reported before and after hashing on Linux, and
+//! should stay flat: the file is read in fixed-size chunks, never in full.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use lib::graph::compute_file_hash;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Size of the synthetic file in megabytes
+fn file_size_mb() -> u64 {
+    std::env::var("CONTENT_HASH_BENCH_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(500)
+}
+
+/// Write a file of `size_mb` megabytes of non-repeating data, one chunk at a time
+fn synthetic_file(size_mb: u64) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    let mut chunk = vec![0u8; 1024 * 1024];
+    for mb in 0..size_mb {
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            *byte = (i as u64 ^ mb).wrapping_mul(31) as u8;
+        }
+        file.write_all(&chunk).unwrap();
+    }
+    file.flush().unwrap();
+    file
+}
+
+/// Peak resident set size in kilobytes (Linux only)
+fn peak_rss_kb() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Benchmark hashing a large file from disk
+fn bench_large_file_hash(c: &mut Criterion) {
+    let size_mb = file_size_mb();
+    let file = synthetic_file(size_mb);
+    let path: &Path = file.path();
+
+    let before = peak_rss_kb();
+
+    let mut group = c.benchmark_group("content_hash");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(size_mb * 1024 * 1024));
+    group.bench_function(format!("file_{}mb", size_mb), |b| {
+        b.iter(|| black_box(compute_file_hash(black_box(path)).unwrap()));
+    });
+    group.finish();
+
+    if let (Some(before), Some(after)) = (before, peak_rss_kb()) {
+        println!(
+            "peak RSS: {} kB before hashing, {} kB after ({} kB growth for a {} MB file)",
+            before,
+            after,
+            after.saturating_sub(before),
+            size_mb
+        );
+    }
+}
+
+criterion_group!(benches, bench_large_file_hash);
+
+criterion_main!(benches);
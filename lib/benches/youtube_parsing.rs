@@ -124,7 +124,7 @@ fn bench_full_pipeline(c: &mut Criterion) {
 
     c.bench_function("full_pipeline_parse_and_render", |b| {
         b.iter(|| {
-            if let Ok(Some(lib::types::DarkMatterNode::YouTube { video_id, width })) = parse_directive(black_box(directive), 1) {
+            if let Ok(Some(lib::types::DarkMatterNode::YouTube { video_id, width, .. })) = parse_directive(black_box(directive), 1) {
                 let _ = render_youtube_embed(black_box(&video_id), black_box(&width));
             }
         });
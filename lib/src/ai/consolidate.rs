@@ -3,16 +3,14 @@ use crate::cache::operations::{CacheOperations, LlmCacheEntry};
 use crate::error::{AIError, Result};
 use chrono::{Duration, Utc};
 use std::sync::Arc;
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
 use tracing::{debug, instrument};
 use xxhash_rust::xxh3::xxh3_64;
 
 const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 
-#[instrument(skip(db, model, documents))]
+#[instrument(skip(cache, model, documents))]
 pub async fn consolidate(
-    db: Arc<Surreal<Db>>,
+    cache: &CacheOperations,
     model: Arc<dyn CompletionModel>,
     documents: &[&str],
     max_tokens: Option<u32>,
@@ -35,7 +33,6 @@ pub async fn consolidate(
         model_name
     );
 
-    let cache = CacheOperations::new((*db).clone());
     if let Some(cached) = cache
         .get_llm("consolidate", &input_hash, model_name)
         .await?
@@ -1,7 +1,12 @@
 use crate::ai::traits::EmbeddingModel;
+use crate::cache::operations::{CacheOperations, EmbeddingCacheEntry};
 use crate::error::{AIError, Result};
+use crate::graph::compute_resource_hash;
+use crate::graph::utils::compute_content_hash;
+use crate::types::{DarkMatterNode, Document, ResourceHash};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::sync::Arc;
 use surrealdb::engine::local::Db;
 use surrealdb::sql::Datetime as SurrealDatetime;
@@ -207,3 +212,285 @@ pub async fn find_similar(
 
     Ok(results)
 }
+
+/// Rank documents by how similar their text is to `query`
+///
+/// Returns the resource hash (relative to `project_root`) and cosine
+/// similarity of the `top_k` most similar documents, most similar first. Embeddings are cached by the hash of the
+/// text they were made from, so only new or changed documents are sent to
+/// the model, in a single batch.
+#[instrument(skip(docs, model, cache), fields(num_docs = docs.len()))]
+pub async fn semantic_search(
+    query: &str,
+    docs: &[Document],
+    top_k: usize,
+    model: &dyn EmbeddingModel,
+    cache: &CacheOperations,
+    project_root: Option<&Path>,
+) -> Result<Vec<(ResourceHash, f32)>> {
+    if docs.is_empty() || top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let query_vector = embed(&[query.to_string()], model, cache).await?.remove(0);
+    let texts: Vec<String> = docs.iter().map(document_text).collect();
+    let vectors = embed(&texts, model, cache).await?;
+
+    let mut scored: Vec<(ResourceHash, f32)> = docs
+        .iter()
+        .zip(&vectors)
+        .map(|(doc, vector)| {
+            (compute_resource_hash(&doc.resource, project_root), cosine_similarity(&query_vector, vector))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(top_k);
+
+    Ok(scored)
+}
+
+/// The sentence of `text` most similar to `query`
+///
+/// Returns `None` when `text` has no sentences.
+#[instrument(skip(text, model, cache))]
+pub async fn most_similar_sentence(
+    query: &str,
+    text: &str,
+    model: &dyn EmbeddingModel,
+    cache: &CacheOperations,
+) -> Result<Option<String>> {
+    let sentences = split_sentences(text);
+    if sentences.is_empty() {
+        return Ok(None);
+    }
+
+    let query_vector = embed(&[query.to_string()], model, cache).await?.remove(0);
+    let vectors = embed(&sentences, model, cache).await?;
+
+    Ok(sentences
+        .into_iter()
+        .zip(&vectors)
+        .max_by(|a, b| {
+            cosine_similarity(&query_vector, a.1).total_cmp(&cosine_similarity(&query_vector, b.1))
+        })
+        .map(|(sentence, _)| sentence))
+}
+
+/// The text of a document's content, as searched by [`semantic_search`]
+pub fn document_text(doc: &Document) -> String {
+    let mut parts = Vec::new();
+    collect_text(&doc.content, &mut parts);
+    parts.join("\n")
+}
+
+fn collect_text<'a>(nodes: &'a [DarkMatterNode], parts: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            DarkMatterNode::Text(text) => parts.push(text),
+            DarkMatterNode::Markdown(content) => parts.push(&content.raw),
+            DarkMatterNode::Columns { sections, .. } => {
+                sections.iter().for_each(|section| collect_text(section, parts))
+            }
+            DarkMatterNode::Disclosure { summary, details } => {
+                collect_text(summary, parts);
+                collect_text(details, parts);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Split text into sentences, dropping markdown line markers such as `#`,
+/// `-` and `>`
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches(['#', '-', '*', '>']).trim();
+        let mut start = 0;
+        for (i, c) in line.char_indices() {
+            let end = i + c.len_utf8();
+            let at_boundary = line[end..].chars().next().is_none_or(char::is_whitespace);
+            if matches!(c, '.' | '!' | '?') && at_boundary {
+                sentences.push(line[start..end].trim().to_string());
+                start = end;
+            }
+        }
+        sentences.push(line[start..].trim().to_string());
+    }
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+/// Embed `texts`, using cached embeddings where the model has already
+/// embedded the same text
+///
+/// The vectors are returned in the order of `texts`.
+async fn embed(texts: &[String], model: &dyn EmbeddingModel, cache: &CacheOperations) -> Result<Vec<Vec<f32>>> {
+    let model_name = model.model_name();
+    let hashes: Vec<String> = texts.iter().map(|text| compute_content_hash(text)).collect();
+    let hash_refs: Vec<&str> = hashes.iter().map(String::as_str).collect();
+    let mut cached = cache.get_embeddings(model_name, &hash_refs).await?;
+
+    let mut missing: Vec<usize> = Vec::new();
+    for (index, hash) in hashes.iter().enumerate() {
+        if !cached.contains_key(hash) && !missing.iter().any(|&i| hashes[i] == *hash) {
+            missing.push(index);
+        }
+    }
+
+    if !missing.is_empty() {
+        debug!("Embedding {} of {} texts", missing.len(), texts.len());
+
+        let batch: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+        let vectors = model
+            .embed(&batch)
+            .await
+            .map_err(|e| AIError::EmbeddingFailed(e.to_string()))?;
+        if vectors.len() != batch.len() {
+            return Err(AIError::EmbeddingFailed(format!(
+                "Expected {} embeddings from model, got {}",
+                batch.len(),
+                vectors.len()
+            ))
+            .into());
+        }
+
+        let mut entries = Vec::with_capacity(vectors.len());
+        for (&index, vector) in missing.iter().zip(vectors) {
+            if vector.len() != model.dimensions() {
+                return Err(AIError::EmbeddingFailed(format!(
+                    "Embedding dimension mismatch: expected {}, got {}",
+                    model.dimensions(),
+                    vector.len()
+                ))
+                .into());
+            }
+            entries.push(EmbeddingCacheEntry {
+                content_hash: hashes[index].clone(),
+                model: model_name.to_string(),
+                vector,
+                created_at: Utc::now(),
+            });
+        }
+
+        for entry in &entries {
+            cached.insert(entry.content_hash.clone(), entry.clone());
+        }
+        cache.upsert_embeddings(entries).await?;
+    }
+
+    Ok(hashes.iter().map(|hash| cached[hash].vector.clone()).collect())
+}
+
+/// Cosine similarity of two vectors; 0 when either has no length
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{apply_schema, init_memory_database};
+    use crate::types::{MarkdownContent, Resource};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const KEYWORDS: [&str; 3] = ["cache", "deploy", "test"];
+
+    /// Embeds text by counting keyword mentions, one dimension per keyword
+    struct KeywordModel {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingModel for KeywordModel {
+        async fn embed(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    KEYWORDS.iter().map(|keyword| text.matches(keyword).count() as f32).collect()
+                })
+                .collect())
+        }
+
+        fn model_name(&self) -> &str {
+            "test/keywords"
+        }
+
+        fn dimensions(&self) -> usize {
+            KEYWORDS.len()
+        }
+    }
+
+    fn document(name: &str, raw: &str) -> Document {
+        let mut doc = Document::new(Resource::memory(name, name));
+        doc.content.push(DarkMatterNode::Markdown(MarkdownContent { raw: raw.to_string(), frontmatter: None }));
+        doc
+    }
+
+    async fn setup_cache() -> CacheOperations {
+        let db = init_memory_database().await.unwrap();
+        apply_schema(&db).await.unwrap();
+        CacheOperations::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_ranks_by_similarity() {
+        let model = KeywordModel { embedded: AtomicUsize::new(0) };
+        let cache = setup_cache().await;
+        let docs = vec![
+            document("deploying", "# Deploying\n\nDeploy with one command."),
+            document("caching", "# Caching\n\nThe cache keeps rendered pages. Tests cover the cache."),
+            document("testing", "# Testing\n\nRun the test suite before you deploy."),
+        ];
+
+        let results = semantic_search("cache", &docs, 2, &model, &cache, None).await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, compute_resource_hash(&docs[1].resource, None));
+        assert!(results[0].1 > results[1].1);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_embeds_each_text_once() {
+        let model = KeywordModel { embedded: AtomicUsize::new(0) };
+        let cache = setup_cache().await;
+        let docs = vec![document("a", "cache"), document("b", "deploy"), document("c", "cache")];
+
+        semantic_search("test", &docs, 3, &model, &cache, None).await.unwrap();
+        // The query and two distinct document texts
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 3);
+
+        semantic_search("test", &docs, 3, &model, &cache, None).await.unwrap();
+        assert_eq!(model.embedded.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_most_similar_sentence() {
+        let model = KeywordModel { embedded: AtomicUsize::new(0) };
+        let cache = setup_cache().await;
+        let text = "# Caching\n\nThe cache keeps rendered pages. Run the test suite first!\n- Deploy on Fridays?";
+
+        let sentence = most_similar_sentence("testing", text, &model, &cache).await.unwrap();
+        assert_eq!(sentence.as_deref(), Some("Run the test suite first!"));
+
+        assert_eq!(most_similar_sentence("testing", "\n\n", &model, &cache).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("# Title\n\nOne. Two e.g.value! Three?\n> Quoted"),
+            vec!["Title", "One.", "Two e.g.value!", "Three?", "Quoted"]
+        );
+    }
+}
@@ -9,6 +9,7 @@ use std::sync::{Arc, Mutex};
 pub struct MockCompletionModel {
     responses: Arc<Mutex<Vec<String>>>,
     call_count: Arc<Mutex<usize>>,
+    name: String,
 }
 
 impl MockCompletionModel {
@@ -20,9 +21,16 @@ impl MockCompletionModel {
         Self {
             responses: Arc::new(Mutex::new(responses)),
             call_count: Arc::new(Mutex::new(0)),
+            name: "mock-completion-model".to_string(),
         }
     }
 
+    /// Set the name reported by `model_name()`.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
     /// Get the number of times the model has been called.
     pub fn call_count(&self) -> usize {
         *self.call_count.lock().unwrap()
@@ -59,7 +67,7 @@ impl CompletionModel for MockCompletionModel {
     }
 
     fn model_name(&self) -> &str {
-        "mock-completion-model"
+        &self.name
     }
 }
 
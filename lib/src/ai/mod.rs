@@ -5,14 +5,18 @@ pub mod consolidate;
 pub mod embedding;
 pub mod mock;
 pub mod providers;
+pub mod queue;
+pub mod registry;
 pub mod summarize;
 pub mod topic;
 pub mod traits;
 
 // Re-exports for convenience
 pub use consolidate::consolidate;
-pub use embedding::{find_similar, generate_embedding, EmbeddingEntry};
+pub use embedding::{find_similar, generate_embedding, semantic_search, EmbeddingEntry};
 pub use mock::{MockCompletionModel, MockEmbeddingModel};
+pub use queue::{AiTask, AsyncAIQueue};
+pub use registry::ModelRegistry;
 pub use summarize::summarize;
-pub use topic::extract_topic;
+pub use topic::{extract_topic, SourceExcerpt, TopicExtraction};
 pub use traits::{CompletionModel, EmbeddingModel};
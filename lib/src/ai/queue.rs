@@ -0,0 +1,400 @@
+use crate::ai::registry::ModelRegistry;
+use crate::ai::traits::CompletionModel;
+use crate::ai::{consolidate, extract_topic, summarize};
+use crate::cache::CacheOperations;
+use crate::error::{AIError, Result};
+use crate::graph::{load_resource, load_resource_candidate};
+use crate::types::{DarkMatterNode, MarkdownContent, Resource, ResourceHash};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tracing::{debug, instrument};
+
+/// Tasks that may wait in the queue for each concurrent slot
+const QUEUE_DEPTH_PER_SLOT: usize = 8;
+
+/// An AI operation waiting to be run by an [`AsyncAIQueue`]
+pub struct AiTask {
+    /// The `Summarize`, `Consolidate` or `Topic` node to resolve
+    pub node: DarkMatterNode,
+    /// Hash of the document the node belongs to
+    pub document_hash: ResourceHash,
+    /// Requested model name; `None` uses the registry's default
+    pub model: Option<String>,
+    /// Receives the node that replaces `node` once the operation completes
+    pub result_tx: oneshot::Sender<Result<DarkMatterNode>>,
+}
+
+/// Queue that runs AI operations in the background
+///
+/// Each operation resolves to the node that takes its place in the document:
+/// the generated markdown, or for a `--review` topic extraction the review
+/// layout from [`TopicExtraction::review_layout`](crate::ai::TopicExtraction::review_layout).
+/// A background task drains the queue and runs up to `max_concurrent`
+/// operations at once, so callers can submit every AI node in a document and
+/// carry on with other work while the completions come back. Results go
+/// through the given LLM cache, so resubmitting the same operation is a cache
+/// hit unless that cache is disabled.
+/// Each task's model is looked up in a [`ModelRegistry`] when it runs.
+///
+/// The queue is bounded: when it's full, [`submit()`](Self::submit) waits up
+/// to the configured timeout for room before failing with
+/// `AIError::QueueFull`.
+pub struct AsyncAIQueue {
+    max_concurrent: usize,
+    sender: mpsc::Sender<AiTask>,
+    submit_timeout: Duration,
+}
+
+impl AsyncAIQueue {
+    /// Create a queue that sends every operation to one model
+    ///
+    /// Must be called from within a Tokio runtime. A `max_concurrent` of zero
+    /// is treated as one.
+    pub fn new(
+        model: Arc<dyn CompletionModel>,
+        cache: Arc<CacheOperations>,
+        max_concurrent: usize,
+        submit_timeout: Duration,
+    ) -> Self {
+        Self::with_registry(
+            Arc::new(ModelRegistry::single(model)),
+            cache,
+            max_concurrent,
+            submit_timeout,
+        )
+    }
+
+    /// Create a queue that picks each operation's model from a registry
+    ///
+    /// Must be called from within a Tokio runtime. A `max_concurrent` of zero
+    /// is treated as one.
+    pub fn with_registry(
+        models: Arc<ModelRegistry>,
+        cache: Arc<CacheOperations>,
+        max_concurrent: usize,
+        submit_timeout: Duration,
+    ) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        let (sender, receiver) = mpsc::channel(max_concurrent * QUEUE_DEPTH_PER_SLOT);
+
+        tokio::spawn(drain(receiver, models, cache, max_concurrent));
+
+        Self {
+            max_concurrent,
+            sender,
+            submit_timeout,
+        }
+    }
+
+    /// Maximum number of operations run at the same time
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Queue an AI node for the default model and return a receiver for its result
+    ///
+    /// Fails with `AIError::NotAnAiOperation` for any node other than
+    /// `Summarize`, `Consolidate` or `Topic`.
+    pub async fn submit(
+        &self,
+        node: DarkMatterNode,
+        document_hash: ResourceHash,
+    ) -> Result<oneshot::Receiver<Result<DarkMatterNode>>> {
+        self.submit_with_model(node, document_hash, None).await
+    }
+
+    /// Queue an AI node for the named model and return a receiver for its result
+    ///
+    /// The model is resolved when the task runs, so an unknown name arrives
+    /// through the receiver as `AIError::ModelNotFound`.
+    #[instrument(skip(self, node))]
+    pub async fn submit_with_model(
+        &self,
+        node: DarkMatterNode,
+        document_hash: ResourceHash,
+        model: Option<String>,
+    ) -> Result<oneshot::Receiver<Result<DarkMatterNode>>> {
+        if !is_ai_operation(&node) {
+            return Err(AIError::NotAnAiOperation(format!("{:?}", node)).into());
+        }
+
+        let (result_tx, result_rx) = oneshot::channel();
+        let task = AiTask {
+            node,
+            document_hash,
+            model,
+            result_tx,
+        };
+
+        self.sender
+            .send_timeout(task, self.submit_timeout)
+            .await
+            .map_err(|e| match e {
+                SendTimeoutError::Timeout(_) => AIError::QueueFull(format!(
+                    "no room after waiting {:?}",
+                    self.submit_timeout
+                )),
+                SendTimeoutError::Closed(_) => AIError::QueueClosed,
+            })?;
+
+        Ok(result_rx)
+    }
+}
+
+/// Whether a node is resolved by an AI operation
+pub fn is_ai_operation(node: &DarkMatterNode) -> bool {
+    matches!(
+        node,
+        DarkMatterNode::Summarize { .. }
+            | DarkMatterNode::Consolidate { .. }
+            | DarkMatterNode::Topic { .. }
+    )
+}
+
+/// Run queued tasks until every sender has been dropped
+///
+/// A task is only taken off the queue once a concurrency slot is free, which
+/// is what makes a full queue push back on `submit()`.
+async fn drain(
+    mut receiver: mpsc::Receiver<AiTask>,
+    models: Arc<ModelRegistry>,
+    cache: Arc<CacheOperations>,
+    max_concurrent: usize,
+) {
+    let slots = Arc::new(Semaphore::new(max_concurrent));
+
+    loop {
+        let Ok(permit) = Arc::clone(&slots).acquire_owned().await else {
+            break;
+        };
+        let Some(task) = receiver.recv().await else {
+            break;
+        };
+
+        let models = Arc::clone(&models);
+        let cache = Arc::clone(&cache);
+        tokio::spawn(async move {
+            debug!("Running AI task for document {}", task.document_hash);
+            let result = match models.resolve(task.model.as_deref()) {
+                Ok(model) => run_task(&task.node, &cache, model).await,
+                Err(e) => Err(e),
+            };
+            drop(permit);
+
+            // The submitter may have stopped waiting; the result is cached either way
+            let _ = task.result_tx.send(result);
+        });
+    }
+
+    debug!("AI queue closed");
+}
+
+async fn run_task(
+    node: &DarkMatterNode,
+    cache: &CacheOperations,
+    model: Arc<dyn CompletionModel>,
+) -> Result<DarkMatterNode> {
+    match node {
+        DarkMatterNode::Summarize { resource } => {
+            let (_, text) = load_resource_candidate(resource, load_resource).await?;
+            summarize(cache, model, &text, None).await.map(markdown)
+        }
+        DarkMatterNode::Consolidate { resources } => {
+            let texts = load_all(resources).await?;
+            let documents: Vec<&str> = texts.iter().map(String::as_str).collect();
+            consolidate(cache, model, &documents, None).await.map(markdown)
+        }
+        DarkMatterNode::Topic {
+            topic,
+            resources,
+            review,
+        } => {
+            let texts = load_all(resources).await?;
+            let documents: Vec<&str> = texts.iter().map(String::as_str).collect();
+            let extraction = extract_topic(cache, model, topic, &documents, *review, None).await?;
+            if *review {
+                let labels: Vec<String> = resources.iter().map(Resource::label).collect();
+                Ok(extraction.review_layout(&labels, &documents))
+            } else {
+                Ok(markdown(extraction.content))
+            }
+        }
+        other => Err(AIError::NotAnAiOperation(format!("{:?}", other)).into()),
+    }
+}
+
+fn markdown(raw: String) -> DarkMatterNode {
+    DarkMatterNode::Markdown(MarkdownContent { raw, frontmatter: None })
+}
+
+async fn load_all(resources: &[Resource]) -> Result<Vec<String>> {
+    let mut texts = Vec::with_capacity(resources.len());
+    for resource in resources {
+        texts.push(load_resource_candidate(resource, load_resource).await?.1);
+    }
+    Ok(texts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::mock::MockCompletionModel;
+    use crate::cache::{apply_schema, init_memory_database};
+    use crate::error::CompositionError;
+    use async_trait::async_trait;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::NamedTempFile;
+
+    async fn setup_test_db() -> Arc<CacheOperations> {
+        let db = init_memory_database().await.unwrap();
+        apply_schema(&db).await.unwrap();
+        Arc::new(CacheOperations::new(db))
+    }
+
+    fn summarize_node(file: &NamedTempFile) -> DarkMatterNode {
+        DarkMatterNode::Summarize {
+            resource: Resource::local(file.path().to_path_buf()),
+        }
+    }
+
+    fn markdown_text(node: DarkMatterNode) -> String {
+        match node {
+            DarkMatterNode::Markdown(content) => content.raw,
+            other => panic!("Expected Markdown node, got {:?}", other),
+        }
+    }
+
+    /// Completion model that sleeps and records the peak number of concurrent calls
+    struct SlowModel {
+        delay: Duration,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CompletionModel for SlowModel {
+        async fn complete(
+            &self,
+            prompt: &str,
+            _max_tokens: Option<u32>,
+        ) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(format!("summary of {} bytes", prompt.len()))
+        }
+
+        fn model_name(&self) -> &str {
+            "slow-model"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_returns_result_and_caches_it() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "A long document worth summarizing.").unwrap();
+
+        let model = Arc::new(MockCompletionModel::new(vec!["Short summary.".to_string()]));
+        let queue = AsyncAIQueue::new(model.clone(), setup_test_db().await, 2, Duration::from_secs(1));
+
+        let first = queue.submit(summarize_node(&file), ResourceHash(1)).await.unwrap();
+        assert_eq!(markdown_text(first.await.unwrap().unwrap()), "Short summary.");
+
+        let second = queue.submit(summarize_node(&file), ResourceHash(1)).await.unwrap();
+        assert_eq!(markdown_text(second.await.unwrap().unwrap()), "Short summary.");
+        assert_eq!(model.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_limited() {
+        let model = Arc::new(SlowModel {
+            delay: Duration::from_millis(50),
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let queue = AsyncAIQueue::new(model.clone(), setup_test_db().await, 2, Duration::from_secs(5));
+
+        let mut files = Vec::new();
+        let mut receivers = Vec::new();
+        for i in 0..6 {
+            let mut file = NamedTempFile::new().unwrap();
+            write!(file, "document {}", i).unwrap();
+            receivers.push(queue.submit(summarize_node(&file), ResourceHash(i)).await.unwrap());
+            files.push(file);
+        }
+
+        for receiver in receivers {
+            receiver.await.unwrap().unwrap();
+        }
+        assert_eq!(model.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_times_out() {
+        let model = Arc::new(SlowModel {
+            delay: Duration::from_secs(60),
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let queue = AsyncAIQueue::new(model, setup_test_db().await, 1, Duration::from_millis(20));
+
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "stuck").unwrap();
+
+        let mut receivers = Vec::new();
+        let error = loop {
+            match queue.submit(summarize_node(&file), ResourceHash(0)).await {
+                Ok(receiver) => receivers.push(receiver),
+                Err(e) => break e,
+            }
+            assert!(receivers.len() <= 2 * QUEUE_DEPTH_PER_SLOT, "queue never filled up");
+        };
+
+        assert!(matches!(error, CompositionError::AI(AIError::QueueFull(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_ai_nodes() {
+        let model = Arc::new(MockCompletionModel::new(vec![]));
+        let queue = AsyncAIQueue::new(model, setup_test_db().await, 1, Duration::from_secs(1));
+
+        let result = queue
+            .submit(DarkMatterNode::Text("plain".to_string()), ResourceHash(0))
+            .await;
+        assert!(matches!(
+            result,
+            Err(CompositionError::AI(AIError::NotAnAiOperation(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_topic_review_resolves_to_review_layout() {
+        let mut first = NamedTempFile::new().unwrap();
+        write!(first, "Intro.\n\nCaching keeps results for 30 days.").unwrap();
+        let mut second = NamedTempFile::new().unwrap();
+        write!(second, "Nothing relevant here.").unwrap();
+
+        let model = Arc::new(MockCompletionModel::new(vec![
+            "--- Document 1 ---\nCaching keeps results for 30 days.\n--- Document 2 ---\nNONE".to_string(),
+        ]));
+        let queue = AsyncAIQueue::new(model, setup_test_db().await, 1, Duration::from_secs(1));
+        let node = DarkMatterNode::Topic {
+            topic: "caching".to_string(),
+            resources: vec![
+                Resource::local(first.path().to_path_buf()),
+                Resource::local(second.path().to_path_buf()),
+            ],
+            review: true,
+        };
+
+        let receiver = queue.submit(node, ResourceHash(0)).await.unwrap();
+        match receiver.await.unwrap().unwrap() {
+            DarkMatterNode::Columns { sections, .. } => assert_eq!(sections.len(), 4),
+            other => panic!("Expected Columns node, got {:?}", other),
+        }
+    }
+}
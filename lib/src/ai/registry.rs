@@ -0,0 +1,129 @@
+use crate::ai::traits::CompletionModel;
+use crate::error::{AIError, Result};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Completion models keyed by model-name prefix
+///
+/// AI operations name the model they want through the `model` frontmatter
+/// property (e.g. `model: gpt-4o-mini`). The registry picks the model
+/// registered under the longest prefix of that name, so `"gpt-"` and
+/// `"claude-"` can route to different providers. When a document names no
+/// model, the registry's default name is used instead.
+///
+/// An empty prefix matches every name.
+#[derive(Default)]
+pub struct ModelRegistry {
+    models: Vec<(String, Arc<dyn CompletionModel>)>,
+    default_model: Option<String>,
+}
+
+impl ModelRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry that sends every request to one model
+    pub fn single(model: Arc<dyn CompletionModel>) -> Self {
+        Self {
+            models: vec![(String::new(), model)],
+            default_model: None,
+        }
+    }
+
+    /// Register a model for names starting with `prefix`
+    ///
+    /// Registering the same prefix again replaces the earlier model.
+    pub fn register(&mut self, prefix: impl Into<String>, model: Box<dyn CompletionModel>) -> &mut Self {
+        let prefix = prefix.into();
+        self.models.retain(|(existing, _)| *existing != prefix);
+        self.models.push((prefix, Arc::from(model)));
+        self
+    }
+
+    /// Set the model name used when a document doesn't name one
+    pub fn with_default(mut self, name: impl Into<String>) -> Self {
+        self.default_model = Some(name.into());
+        self
+    }
+
+    /// The model name used when a document doesn't name one
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    /// Find the model for a requested name, falling back to the default name
+    ///
+    /// Fails with `AIError::ModelNotFound` when no registered prefix matches.
+    pub fn resolve(&self, name: Option<&str>) -> Result<Arc<dyn CompletionModel>> {
+        let name = name.or(self.default_model.as_deref()).unwrap_or_default();
+
+        let (prefix, model) = self
+            .models
+            .iter()
+            .filter(|(prefix, _)| name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .ok_or_else(|| AIError::ModelNotFound(name.to_string()))?;
+
+        debug!("Model '{}' resolved by prefix '{}' to {}", name, prefix, model.model_name());
+        Ok(Arc::clone(model))
+    }
+
+    /// Number of registered models
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Whether no models are registered
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::MockCompletionModel;
+    use crate::error::CompositionError;
+
+    fn registry() -> ModelRegistry {
+        let mut registry = ModelRegistry::new();
+        registry
+            .register("gpt-", Box::new(MockCompletionModel::new(vec![]).with_name("openai")))
+            .register("claude-", Box::new(MockCompletionModel::new(vec![]).with_name("anthropic")))
+            .register("claude-3-opus", Box::new(MockCompletionModel::new(vec![]).with_name("opus")));
+        registry
+    }
+
+    #[test]
+    fn test_resolves_longest_prefix() {
+        let registry = registry();
+
+        assert_eq!(registry.resolve(Some("gpt-4o-mini")).unwrap().model_name(), "openai");
+        assert_eq!(registry.resolve(Some("claude-3-haiku")).unwrap().model_name(), "anthropic");
+        assert_eq!(registry.resolve(Some("claude-3-opus")).unwrap().model_name(), "opus");
+    }
+
+    #[test]
+    fn test_unknown_model_is_not_found() {
+        let registry = registry();
+
+        assert!(matches!(
+            registry.resolve(Some("llama-3")),
+            Err(CompositionError::AI(AIError::ModelNotFound(name))) if name == "llama-3"
+        ));
+        assert!(matches!(
+            registry.resolve(None),
+            Err(CompositionError::AI(AIError::ModelNotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_falls_back_to_default() {
+        let registry = registry().with_default("claude-3-haiku");
+
+        assert_eq!(registry.resolve(None).unwrap().model_name(), "anthropic");
+        assert_eq!(registry.resolve(Some("gpt-4o")).unwrap().model_name(), "openai");
+    }
+}
@@ -3,8 +3,6 @@ use crate::cache::operations::{CacheOperations, LlmCacheEntry};
 use crate::error::{AIError, Result};
 use chrono::{Duration, Utc};
 use std::sync::Arc;
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
 use tracing::{debug, instrument};
 use xxhash_rust::xxh3::xxh3_64;
 
@@ -14,11 +12,12 @@ const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 /// Summarize a document using an LLM.
 ///
 /// This function generates a concise summary of the provided text using the specified
-/// completion model. Results are cached in SurrealDB to avoid redundant API calls.
+/// completion model. Results are cached in SurrealDB to avoid redundant API calls,
+/// unless `cache` is disabled.
 ///
 /// # Arguments
 ///
-/// * `db` - Database connection for caching
+/// * `cache` - Cache the summary is looked up in and stored to
 /// * `model` - The completion model to use for summarization
 /// * `text` - The text content to summarize
 /// * `max_tokens` - Optional maximum tokens for the summary
@@ -32,6 +31,7 @@ const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 /// ```no_run
 /// use lib::ai::summarize::summarize;
 /// use lib::ai::mock::MockCompletionModel;
+/// use lib::cache::CacheOperations;
 /// use surrealdb::Surreal;
 /// use surrealdb::engine::local::Mem;
 /// use std::sync::Arc;
@@ -40,7 +40,7 @@ const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 /// let db = Surreal::new::<Mem>(()).await?;
 /// let model = MockCompletionModel::new(vec!["Summary of the text.".to_string()]);
 /// let summary = summarize(
-///     Arc::new(db),
+///     &CacheOperations::new(db),
 ///     Arc::new(model),
 ///     "Long text to summarize...",
 ///     Some(150)
@@ -48,9 +48,9 @@ const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 /// # Ok(())
 /// # }
 /// ```
-#[instrument(skip(db, model, text))]
+#[instrument(skip(cache, model, text))]
 pub async fn summarize(
-    db: Arc<Surreal<Db>>,
+    cache: &CacheOperations,
     model: Arc<dyn CompletionModel>,
     text: &str,
     max_tokens: Option<u32>,
@@ -65,7 +65,6 @@ pub async fn summarize(
     );
 
     // Check cache first
-    let cache = CacheOperations::new((*db).clone());
     if let Some(cached) = cache
         .get_llm("summarize", &input_hash, model_name)
         .await?
@@ -124,8 +123,9 @@ mod tests {
     use super::*;
     use crate::ai::mock::MockCompletionModel;
     use surrealdb::engine::local::Mem;
+    use surrealdb::Surreal;
 
-    async fn setup_test_db() -> Arc<Surreal<Db>> {
+    async fn setup_test_db() -> CacheOperations {
         let db = Surreal::new::<Mem>(()).await.unwrap();
         db.use_ns("test").use_db("test").await.unwrap();
 
@@ -145,39 +145,39 @@ mod tests {
         .await
         .unwrap();
 
-        Arc::new(db)
+        CacheOperations::new(db)
     }
 
     #[tokio::test]
     async fn test_summarize_basic() {
-        let db = setup_test_db().await;
+        let cache = setup_test_db().await;
         let model = Arc::new(MockCompletionModel::new(vec![
             "This is a summary.".to_string(),
         ]));
 
         let text = "This is a long document that needs to be summarized. It contains multiple sentences with various information.";
 
-        let summary = summarize(db, model.clone(), text, None).await.unwrap();
+        let summary = summarize(&cache, model.clone(), text, None).await.unwrap();
         assert_eq!(summary, "This is a summary.");
         assert_eq!(model.call_count(), 1);
     }
 
     #[tokio::test]
     async fn test_summarize_with_max_tokens() {
-        let db = setup_test_db().await;
+        let cache = setup_test_db().await;
         let model = Arc::new(MockCompletionModel::new(vec![
             "Short summary.".to_string(),
         ]));
 
         let text = "Long text here...";
 
-        let summary = summarize(db, model, text, Some(50)).await.unwrap();
+        let summary = summarize(&cache, model, text, Some(50)).await.unwrap();
         assert_eq!(summary, "Short summary.");
     }
 
     #[tokio::test]
     async fn test_summarize_caching() {
-        let db = setup_test_db().await;
+        let cache = setup_test_db().await;
         let model = Arc::new(MockCompletionModel::new(vec![
             "Cached summary.".to_string(),
         ]));
@@ -185,21 +185,21 @@ mod tests {
         let text = "Document to cache.";
 
         // First call - should hit the model
-        let summary1 = summarize(db.clone(), model.clone(), text, None)
+        let summary1 = summarize(&cache, model.clone(), text, None)
             .await
             .unwrap();
         assert_eq!(summary1, "Cached summary.");
         assert_eq!(model.call_count(), 1);
 
         // Second call - should hit the cache
-        let summary2 = summarize(db, model.clone(), text, None).await.unwrap();
+        let summary2 = summarize(&cache, model.clone(), text, None).await.unwrap();
         assert_eq!(summary2, "Cached summary.");
         assert_eq!(model.call_count(), 1); // Should not increment
     }
 
     #[tokio::test]
     async fn test_summarize_different_inputs() {
-        let db = setup_test_db().await;
+        let cache = setup_test_db().await;
         let model = Arc::new(MockCompletionModel::new(vec![
             "Summary 1.".to_string(),
             "Summary 2.".to_string(),
@@ -208,10 +208,10 @@ mod tests {
         let text1 = "First document.";
         let text2 = "Second document.";
 
-        let summary1 = summarize(db.clone(), model.clone(), text1, None)
+        let summary1 = summarize(&cache, model.clone(), text1, None)
             .await
             .unwrap();
-        let summary2 = summarize(db, model.clone(), text2, None).await.unwrap();
+        let summary2 = summarize(&cache, model.clone(), text2, None).await.unwrap();
 
         assert_eq!(summary1, "Summary 1.");
         assert_eq!(summary2, "Summary 2.");
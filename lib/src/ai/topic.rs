@@ -1,24 +1,84 @@
 use crate::ai::traits::CompletionModel;
 use crate::cache::operations::{CacheOperations, LlmCacheEntry};
 use crate::error::{AIError, Result};
+use crate::types::{DarkMatterNode, MarkdownContent};
 use chrono::{Duration, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
 use std::sync::Arc;
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
 use tracing::{debug, instrument};
 use xxhash_rust::xxh3::xxh3_64;
 
 const DEFAULT_CACHE_DURATION_DAYS: i64 = 30;
 
-#[instrument(skip(db, model, documents))]
+/// The line that starts a document's excerpts in a review response
+static DOCUMENT_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^-{3}\s*Document\s+(\d+)\s*-{3}$").unwrap()
+});
+
+/// Content extracted for a topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicExtraction {
+    /// The extracted content
+    pub content: String,
+    /// What each document contributed, one entry per document in input order;
+    /// only filled in review mode
+    pub sources: Vec<SourceExcerpt>,
+}
+
+/// The part of one source document that a topic extraction drew on
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceExcerpt {
+    /// Position of the document in the extraction's input
+    pub document: usize,
+    /// The passages quoted from the document, separated by blank lines;
+    /// empty when it contributed nothing
+    pub excerpt: String,
+}
+
+impl TopicExtraction {
+    /// Build the review layout: each source document next to its excerpt
+    ///
+    /// The result is a two-column `Columns` node with one row per document.
+    /// The left column names the document (from `labels`) and shows its text
+    /// with the quoted passages highlighted; the right column holds the
+    /// excerpt attributed to it.
+    pub fn review_layout(&self, labels: &[String], documents: &[&str]) -> DarkMatterNode {
+        let mut sections = Vec::with_capacity(self.sources.len() * 2);
+
+        for source in &self.sources {
+            let label = labels.get(source.document).map(String::as_str).unwrap_or("unknown source");
+            let text = documents.get(source.document).copied().unwrap_or_default();
+
+            sections.push(vec![markdown(format!(
+                "#### {}\n\n<pre class=\"dm-topic-source\">{}</pre>",
+                escape_html(label),
+                highlight_passages(text, &source.excerpt)
+            ))]);
+            sections.push(vec![markdown(if source.excerpt.is_empty() {
+                "_Nothing on this topic_".to_string()
+            } else {
+                source.excerpt.clone()
+            })]);
+        }
+
+        DarkMatterNode::Columns {
+            breakpoints: HashMap::new(),
+            sections,
+        }
+    }
+}
+
+#[instrument(skip(cache, model, documents))]
 pub async fn extract_topic(
-    db: Arc<Surreal<Db>>,
+    cache: &CacheOperations,
     model: Arc<dyn CompletionModel>,
     topic: &str,
     documents: &[&str],
     review: bool,
     max_tokens: Option<u32>,
-) -> Result<String> {
+) -> Result<TopicExtraction> {
     if documents.is_empty() {
         return Err(AIError::TopicExtractionFailed(
             "No documents provided for topic extraction".to_string(),
@@ -50,13 +110,12 @@ pub async fn extract_topic(
         model_name
     );
 
-    let cache = CacheOperations::new((*db).clone());
     if let Some(cached) = cache
         .get_llm("topic_extraction", &input_hash, model_name)
         .await?
     {
         debug!("Cache hit for topic extraction");
-        return Ok(cached.response);
+        return Ok(parse_extraction(cached.response, documents.len(), review));
     }
 
     debug!("Cache miss, calling LLM");
@@ -80,7 +139,101 @@ pub async fn extract_topic(
 
     cache.upsert_llm(cache_entry).await?;
 
-    Ok(extracted)
+    Ok(parse_extraction(extracted, documents.len(), review))
+}
+
+/// Read the model's response
+///
+/// In review mode the response is split at its `--- Document N ---` lines
+/// into per-document excerpts, and the content is every excerpt in turn.
+fn parse_extraction(response: String, document_count: usize, review: bool) -> TopicExtraction {
+    if !review {
+        return TopicExtraction {
+            content: response,
+            sources: Vec::new(),
+        };
+    }
+
+    let mut excerpts = vec![Vec::new(); document_count];
+    let mut current = None;
+    for line in response.lines() {
+        if let Some(caps) = DOCUMENT_HEADER.captures(line.trim()) {
+            current = caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| number.checked_sub(1))
+                .filter(|&index| index < document_count);
+            continue;
+        }
+        if let Some(index) = current {
+            excerpts[index].push(line);
+        }
+    }
+
+    let sources: Vec<SourceExcerpt> = excerpts
+        .into_iter()
+        .enumerate()
+        .map(|(document, lines)| {
+            let excerpt = lines.join("\n").trim().to_string();
+            SourceExcerpt {
+                document,
+                excerpt: if excerpt == "NONE" { String::new() } else { excerpt },
+            }
+        })
+        .collect();
+
+    TopicExtraction {
+        content: sources
+            .iter()
+            .filter(|source| !source.excerpt.is_empty())
+            .map(|source| source.excerpt.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        sources,
+    }
+}
+
+/// Escape a document's text, wrapping each passage of `excerpt` found in it
+/// in `<mark>`
+///
+/// Passages are the excerpt's blank-line separated paragraphs; ones that
+/// aren't quoted verbatim can't be located and are left unmarked.
+fn highlight_passages(text: &str, excerpt: &str) -> String {
+    let mut ranges: Vec<(usize, usize)> = excerpt
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|passage| !passage.is_empty())
+        .filter_map(|passage| text.find(passage).map(|start| (start, start + passage.len())))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut html = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (start, end) in ranges {
+        if start < offset {
+            continue;
+        }
+        html.push_str(&escape_html(&text[offset..start]));
+        html.push_str("<mark>");
+        html.push_str(&escape_html(&text[start..end]));
+        html.push_str("</mark>");
+        offset = end;
+    }
+    html.push_str(&escape_html(&text[offset..]));
+
+    html
+}
+
+fn markdown(raw: String) -> DarkMatterNode {
+    DarkMatterNode::Markdown(MarkdownContent { raw, frontmatter: None })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
 
 fn build_topic_extraction_prompt(
@@ -96,7 +249,7 @@ fn build_topic_extraction_prompt(
     };
 
     let review_instruction = if review {
-        " After extracting the relevant content, provide a brief analysis or review of the findings."
+        " The extraction will be reviewed against its sources, so for each document write a `--- Document N ---` line followed by the passages you drew from it, quoted verbatim and separated by blank lines, or NONE if it has nothing on the topic."
     } else {
         ""
     };
@@ -122,3 +275,82 @@ fn build_topic_extraction_prompt(
 
     prompt
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::mock::MockCompletionModel;
+    use crate::cache::{apply_schema, init_memory_database};
+    use crate::render::to_html;
+
+    const REVIEW_RESPONSE: &str = "--- Document 1 ---\nRedis keeps sessions for an hour.\n\n--- Document 2 ---\nNONE\n--- Document 3 ---\nThe CDN caches <img> tags & assets.";
+
+    async fn setup_test_db() -> CacheOperations {
+        let db = init_memory_database().await.unwrap();
+        apply_schema(&db).await.unwrap();
+        CacheOperations::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_extract_topic_without_review_has_no_sources() {
+        let model = Arc::new(MockCompletionModel::new(vec!["All about caching.".to_string()]));
+
+        let extraction = extract_topic(&setup_test_db().await, model, "caching", &["a", "b"], false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(extraction.content, "All about caching.");
+        assert!(extraction.sources.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_extract_topic_review_attributes_excerpts() {
+        let model = Arc::new(MockCompletionModel::new(vec![REVIEW_RESPONSE.to_string()]));
+        let cache = setup_test_db().await;
+        let documents = ["one", "two", "three"];
+
+        let extraction = extract_topic(&cache, model.clone(), "caching", &documents, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            extraction.sources,
+            vec![
+                SourceExcerpt { document: 0, excerpt: "Redis keeps sessions for an hour.".to_string() },
+                SourceExcerpt { document: 1, excerpt: String::new() },
+                SourceExcerpt { document: 2, excerpt: "The CDN caches <img> tags & assets.".to_string() },
+            ]
+        );
+        assert_eq!(
+            extraction.content,
+            "Redis keeps sessions for an hour.\n\nThe CDN caches <img> tags & assets."
+        );
+
+        // The cached response is attributed the same way
+        let cached = extract_topic(&cache, model.clone(), "caching", &documents, true, None).await.unwrap();
+        assert_eq!(cached, extraction);
+        assert_eq!(model.call_count(), 1);
+    }
+
+    #[test]
+    fn test_review_layout_shows_each_source_with_its_excerpt() {
+        let documents = [
+            "# Sessions\n\nRedis keeps sessions for an hour.",
+            "Deployment notes.",
+            "The CDN caches <img> tags & assets.",
+        ];
+        let labels = ["docs/sessions.md", "docs/deploy.md", "docs/cdn.md"].map(String::from);
+        let extraction = parse_extraction(REVIEW_RESPONSE.to_string(), documents.len(), true);
+
+        let html = to_html(&[extraction.review_layout(&labels, &documents)]).unwrap();
+
+        assert!(html.contains(r#"<div class="composition-columns composition-columns-default">"#));
+        for label in &labels {
+            assert!(html.contains(&format!("<h4>{}</h4>", label)), "{}", html);
+        }
+        assert!(html.contains("# Sessions\n\n<mark>Redis keeps sessions for an hour.</mark></pre>"));
+        assert!(html.contains("<p>Redis keeps sessions for an hour.</p>"));
+        assert!(html.contains("<mark>The CDN caches &lt;img&gt; tags &amp; assets.</mark>"));
+        assert!(html.contains("<em>Nothing on this topic</em>"));
+    }
+}
@@ -1,26 +1,202 @@
-use crate::cache::CacheOperations;
-use crate::error::{CompositionError, ParseError, RenderError, Result};
+use crate::audio::{
+    process_audio, process_audio_tracked, AudioBatchOptions, AudioCache, AudioInput, AudioOutput,
+    AudioProcessingConfig, AudioProcessingReport,
+};
+use crate::ai::{AsyncAIQueue, CompletionModel, EmbeddingModel, ModelRegistry};
+use crate::cache::{CacheBackend, CacheExportReport, CacheImportReport, CacheOperations, CacheStats};
+use crate::error::{AIError, AudioError, CompositionError, ParseError, RenderError, Result};
+use crate::parse::ParseOptions;
+use crate::render::{
+    absolutize_urls, atom_feed, feed_date, rss_feed, AssetRef, Compression, FeedEntry, FeedFormat, FeedOptions, ExcludedFile, ExclusionReason, NodeTransformer, OutputPaths, PageImage, RenderMetrics, RenderPass,
+    rendered_cache_key, LanguageVariant, RenderSettings, RenderWarnings, RetryPolicy,
+};
 use crate::types::{
-    DependencyGraph, Document, Frontmatter, Resource, ResourceHash, ResourceRequirement, ResourceSource, WorkPlan,
+    language_variant_path, translation_language, Breakpoint, DependencyGraph, Document, Frontmatter, FrontmatterSchema,
+    Heading, Resource, ResourceHash, ResourceRequirement, ResourceSource, WorkPlan,
 };
+use crate::graph::compute_resource_hash;
+use crate::graph::gitignore::is_ignored;
+use crate::graph::utils::{strip_project_root, to_url_path};
+use crate::graph::incremental::{compute_changed_resources, subgraph};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use surrealdb::engine::local::Db;
 use surrealdb::Surreal;
-use tracing::{debug, instrument, info};
+use tracing::{debug, instrument, info, warn};
 
 /// Main API handle for the Composition library
 pub struct CompositionApi {
     db: Arc<Surreal<Db>>,
     cache: Arc<CacheOperations>,
+    ai_queue: Option<Arc<AsyncAIQueue>>,
+    models: Option<Arc<ModelRegistry>>,
+    embedding_model: Option<Arc<dyn EmbeddingModel>>,
+    transformers: Vec<Arc<dyn NodeTransformer>>,
     frontmatter: Frontmatter,
     config: CompositionConfig,
+    /// Keeps git repositories cloned while the API is alive, so renders
+    /// share them; they are removed when the API is dropped
+    _git_checkouts: Arc<crate::graph::git::GitCheckouts>,
 }
 
 /// Configuration for the Composition library
 #[derive(Debug, Clone)]
 pub struct CompositionConfig {
-    pub db_path: std::path::PathBuf,
+    pub backend: CacheBackend,
+    /// Root that local files are hashed relative to, for the whole of every
+    /// build, and that path patterns are matched against; files outside it
+    /// are hashed by their absolute path
     pub project_root: Option<std::path::PathBuf>,
+    /// Drop and recreate the cache when its stored schema version is missing or
+    /// unreadable, instead of failing initialization
+    pub rebuild_on_schema_mismatch: bool,
+    /// How long to keep retrying when another process holds the database lock
+    pub lock_wait: Option<std::time::Duration>,
+    /// Continue with caching disabled, rather than failing, when the database
+    /// lock can't be obtained
+    pub read_only_fallback: bool,
+    /// Render passes to run on every document, in order
+    pub render_passes: Vec<RenderPass>,
+    /// Filename template for `to_html` output paths (see [`OutputPaths`])
+    pub output_template: Option<String>,
+    /// Maximum number of AI operations run at the same time
+    pub ai_max_concurrent: usize,
+    /// How long submitting an AI operation waits for room in a full queue
+    pub ai_submit_timeout: std::time::Duration,
+    /// Glob patterns and the render priority given to matching documents,
+    /// e.g. `("index.md".into(), 200)` (see [`WorkPlan::with_priority`])
+    pub priority_patterns: Vec<(String, u8)>,
+    /// Hash every local file when building graphs, instead of reusing cached
+    /// hashes for files whose size and modification time are unchanged
+    pub force_full_hashing: bool,
+    /// Run `to_html` output through an allowlist-based sanitizer (see
+    /// [`sanitize_html`](crate::render::sanitize_html)), for projects that
+    /// transclude untrusted or remote content
+    pub sanitize_html: bool,
+    /// Start each `to_html` output with a schema.org JSON-LD `<script>`
+    /// built from the document's frontmatter (`title`, `description`,
+    /// `author`, `date`, `updated`, `tags`, `image`, `language`), merged
+    /// with any `jsonld` object it has
+    pub structured_data: bool,
+    /// Maximum number of documents rendered at the same time within a work
+    /// plan layer; unlimited when `None`. Lowers peak memory use, mostly
+    /// from image processing, on small or shared machines. Also caps the
+    /// items in flight in batch operations such as `optimize_images`, which
+    /// otherwise default to the number of CPUs
+    pub max_parallelism: Option<usize>,
+    /// Keys every document's frontmatter must have, checked as documents are
+    /// parsed (see [`Frontmatter::validate`]); documents without frontmatter
+    /// aren't checked
+    pub frontmatter_schema: Option<FrontmatterSchema>,
+    /// Count cache hits and misses so [`CacheStats::hit_rate`] can report
+    /// them; adds a little overhead to every cache lookup
+    pub track_cache_metrics: bool,
+    /// Glob patterns for files that `to_html` and other pattern-based APIs
+    /// leave out, e.g. `"drafts/**"`; matched like `priority_patterns`
+    pub exclude_patterns: Vec<String>,
+    /// Also expand glob patterns into files ignored by the project's
+    /// `.gitignore`, `.ignore` or `.git/info/exclude`
+    pub include_ignored: bool,
+    /// How documents are parsed; with `strict`, unknown directives,
+    /// undefined variables and missing optional resources fail the render
+    /// instead of being reported as warnings
+    pub parse_options: ParseOptions,
+    /// Environment variables documents may read as `{{env.NAME}}`, e.g.
+    /// `"API_BASE_URL"`; any other `{{env.NAME}}` is left as written, with a
+    /// `BlockedEnvVar` warning
+    pub allowed_env_vars: Vec<String>,
+    /// Directory `to_html` also writes its output to, each file at its
+    /// output path relative to the project root, with an `.html` extension
+    pub output_dir: Option<PathBuf>,
+    /// Pre-compressed copies `to_html` writes beside each file in
+    /// `output_dir`, e.g. `guide.html.gz` for [`Compression::Gzip`]
+    pub precompress: Vec<Compression>,
+    /// How documents that fail transiently, e.g. on a remote fetch, are
+    /// retried while rendering
+    pub render_retry: RetryPolicy,
+    /// Return the documents that rendered when others fail, listing the
+    /// failures in [`RenderMetrics::failures`], instead of failing the
+    /// render with [`RenderError::TasksFailed`] (or the document's own
+    /// error when only one was rendered)
+    pub partial_results: bool,
+    /// Extra breakpoints `optimize_image` gives their own `<source media>`
+    /// elements, typically a design system's [`Breakpoint::Custom`] ones;
+    /// listed in a TOML config as `[["mobile", 480], ["tablet", 900]]`.
+    /// Variant widths still come from [`BREAKPOINTS`](crate::image::BREAKPOINTS)
+    pub breakpoints: Vec<Breakpoint>,
+    /// Directory [`process_audio`](CompositionApi::process_audio),
+    /// [`batch_process_audio`](CompositionApi::batch_process_audio) and
+    /// rendered `::audio` directives write into, each file under its
+    /// `audio/` subdirectory, so by default `./output/audio`
+    pub audio_output_dir: PathBuf,
+    /// Embed the audio of rendered `::audio` directives as base64 instead
+    /// of copying it into `audio_output_dir`
+    pub inline_audio: bool,
+    /// Size and format limits for the audio of rendered `::audio`
+    /// directives; inline audio over `max_inline_size` is embedded anyway,
+    /// with an `OversizedInlineAudio` warning
+    pub audio: AudioProcessingConfig,
+    /// Number of words in each [`HtmlOutput::excerpt`]
+    pub excerpt_words: usize,
+    /// Largest response, in bytes, read for a remote document, transclusion
+    /// or image (default 10 MiB); larger ones fail with
+    /// `RenderError::RemoteFetchError` before they are downloaded in full
+    pub max_remote_bytes: u64,
+    /// Language to render in, e.g. `"de"`: documents and `::file`
+    /// transclusions are read from their translation beside them, such as
+    /// `intro.de.md` for `intro.md`, where one exists, and `{{language}}`
+    /// interpolates to it
+    pub language: Option<String>,
+    /// Languages the project is translated into, which
+    /// [`to_html_languages`](CompositionApi::to_html_languages) renders
+    /// every document in; pattern-based APIs leave translations into these
+    /// languages, and into `language`, out as documents of their own
+    pub languages: Vec<String>,
+}
+
+impl CompositionConfig {
+    /// Create a configuration for the given backend with default settings
+    pub fn new(backend: CacheBackend) -> Self {
+        Self {
+            backend,
+            project_root: None,
+            rebuild_on_schema_mismatch: false,
+            lock_wait: None,
+            read_only_fallback: false,
+            render_passes: RenderPass::default_passes(),
+            output_template: None,
+            ai_max_concurrent: 4,
+            ai_submit_timeout: std::time::Duration::from_secs(30),
+            priority_patterns: Vec::new(),
+            force_full_hashing: false,
+            sanitize_html: false,
+            structured_data: false,
+            max_parallelism: None,
+            frontmatter_schema: None,
+            track_cache_metrics: false,
+            exclude_patterns: Vec::new(),
+            include_ignored: false,
+            parse_options: ParseOptions::default(),
+            allowed_env_vars: Vec::new(),
+            output_dir: None,
+            precompress: Vec::new(),
+            render_retry: RetryPolicy::default(),
+            partial_results: false,
+            breakpoints: Vec::new(),
+            audio_output_dir: PathBuf::from("./output"),
+            inline_audio: false,
+            audio: AudioProcessingConfig::default(),
+            excerpt_words: 50,
+            max_remote_bytes: crate::http::DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            languages: Vec::new(),
+        }
+    }
 }
 
 impl CompositionApi {
@@ -31,13 +207,26 @@ impl CompositionApi {
         config: CompositionConfig,
     ) -> Result<Self> {
         let db = Arc::new(db);
-        let cache = Arc::new(CacheOperations::new((*db).clone()));
+        let mut cache = if config.backend.is_enabled() {
+            CacheOperations::new((*db).clone())
+        } else {
+            CacheOperations::disabled((*db).clone())
+        };
+        if config.track_cache_metrics {
+            cache = cache.with_metrics();
+        }
+        let cache = Arc::new(cache);
 
         Ok(Self {
             db,
             cache,
+            ai_queue: None,
+            models: None,
+            embedding_model: None,
+            transformers: Vec::new(),
             frontmatter,
             config,
+            _git_checkouts: crate::graph::git::GitCheckouts::shared(),
         })
     }
 
@@ -51,6 +240,84 @@ impl CompositionApi {
         &self.cache
     }
 
+    /// Write the document, image, audio and LLM caches to a portable file
+    ///
+    /// See [`CacheOperations::export`] for the format. Together with
+    /// [`import_cache()`](Self::import_cache) this lets CI keep its build
+    /// cache between runs.
+    pub async fn export_cache(&self, path: &Path) -> Result<CacheExportReport> {
+        self.cache.export(path).await
+    }
+
+    /// Load a file written by [`export_cache()`](Self::export_cache)
+    ///
+    /// Fails with `CacheError::SchemaVersionMismatch` when the file came from
+    /// a different schema version. Records the cache already holds are
+    /// skipped.
+    pub async fn import_cache(&self, path: &Path) -> Result<CacheImportReport> {
+        self.cache.import(path).await
+    }
+
+    /// Entry counts, ages and estimated size of the cache
+    ///
+    /// The hit rate is only available when `track_cache_metrics` is set in
+    /// the [`CompositionConfig`].
+    pub async fn cache_stats(&self) -> Result<CacheStats> {
+        self.cache.stats().await
+    }
+
+    /// Resolve AI operations during rendering with the given completion model
+    ///
+    /// Every AI operation uses this model, whatever model its document names.
+    /// See [`with_model_registry()`](Self::with_model_registry) for details.
+    pub fn with_completion_model(self, model: Arc<dyn CompletionModel>) -> Self {
+        self.with_model_registry(ModelRegistry::single(model))
+    }
+
+    /// Resolve AI operations during rendering with models from a registry
+    ///
+    /// Each operation uses the model named by its document's `model`
+    /// frontmatter property, or the registry's default when there is none.
+    /// Starts an [`AsyncAIQueue`] limited to `config.ai_max_concurrent`
+    /// operations at once, so this must be called from within a Tokio runtime.
+    /// Without a model, AI directives are left unresolved.
+    pub fn with_model_registry(mut self, registry: ModelRegistry) -> Self {
+        let registry = Arc::new(registry);
+        self.ai_queue = Some(Arc::new(AsyncAIQueue::with_registry(
+            Arc::clone(&registry),
+            Arc::clone(&self.cache),
+            self.config.ai_max_concurrent,
+            self.config.ai_submit_timeout,
+        )));
+        self.models = Some(registry);
+        self
+    }
+
+    /// Embed documents with the given model for [`semantic_search()`](Self::semantic_search)
+    pub fn with_embedding_model(mut self, model: Arc<dyn EmbeddingModel>) -> Self {
+        self.embedding_model = Some(model);
+        self
+    }
+
+    /// Rewrite document nodes with a transformer during rendering
+    ///
+    /// Transformers run after the render passes and before HTML generation,
+    /// in the order they were registered (see [`NodeTransformer`]).
+    pub fn with_transformer(mut self, transformer: Arc<dyn NodeTransformer>) -> Self {
+        self.transformers.push(transformer);
+        self
+    }
+
+    /// Get the completion model registry, if one is configured
+    pub fn model_registry(&self) -> Option<&ModelRegistry> {
+        self.models.as_deref()
+    }
+
+    /// Get the AI operation queue, if a completion model is configured
+    pub fn ai_queue(&self) -> Option<&Arc<AsyncAIQueue>> {
+        self.ai_queue.as_ref()
+    }
+
     /// Get the frontmatter
     pub fn frontmatter(&self) -> &Frontmatter {
         &self.frontmatter
@@ -96,6 +363,8 @@ impl CompositionApi {
     ///     source: ResourceSource::Local(PathBuf::from("document.md")),
     ///     requirement: ResourceRequirement::Required,
     ///     cache_duration: None,
+    ///     section: None,
+    ///     fallback: None,
     /// };
     ///
     /// let graph = api.graph(resource).await?;
@@ -105,25 +374,59 @@ impl CompositionApi {
     /// ```
     #[instrument(skip(self), fields(source = ?resource.source))]
     pub async fn graph(&self, resource: Resource) -> Result<DependencyGraph> {
+        self.graph_in(resource, self.config.language.as_deref()).await
+    }
+
+    /// Build the dependency graph of a resource as rendered in `language`,
+    /// whose `::file` transclusions are their translations where they exist
+    async fn graph_in(&self, resource: Resource, language: Option<&str>) -> Result<DependencyGraph> {
         info!("Building dependency graph");
-        let graph = crate::graph::build_graph(resource, &self.db, &self.frontmatter).await?;
+        let graph = crate::graph::build_graph_with_options(
+            resource,
+            &self.cache,
+            &self.frontmatter,
+            self.config.force_full_hashing,
+            self.config.frontmatter_schema.as_ref(),
+            language,
+            self.project_root(),
+        )
+        .await?;
         debug!("Graph built with {} nodes", graph.nodes.len());
         Ok(graph)
     }
 
+    /// Build the dependency graph around a resource, up to `depth` hops deep
+    ///
+    /// Builds the full graph for `resource` and keeps only the part reachable
+    /// within `depth` dependency hops (see [`DependencyGraph::subgraph`]).
+    /// Useful when debugging a single document in a large project.
+    #[instrument(skip(self), fields(source = ?resource.source))]
+    pub async fn subgraph(&self, resource: Resource, depth: usize) -> Result<DependencyGraph> {
+        let root = self.resource_hash(&resource);
+        let graph = self.graph(resource).await?;
+        let subgraph = graph.subgraph(&root, depth)?;
+        debug!("Subgraph has {} of {} nodes", subgraph.nodes.len(), graph.nodes.len());
+        Ok(subgraph)
+    }
+
     /// Generate work plan for rendering resources
     ///
     /// Analyzes dependency graphs for multiple resources and generates an optimized
     /// work plan that groups tasks into layers for parallel execution. Resources
     /// that are already cached with fresh content are skipped.
     ///
+    /// Only `resources` and what they depend on are planned. Within a layer,
+    /// the dependencies of the first resource come first (see
+    /// [`generate_workplan_for_roots`](crate::graph::generate_workplan_for_roots)).
+    ///
     /// # Arguments
     ///
     /// * `resources` - A list of resources to render
     ///
     /// # Returns
     ///
-    /// A `WorkPlan` with layers of tasks that can be executed in parallel.
+    /// A `WorkPlan` with layers of tasks that can be executed in parallel, with
+    /// task priorities assigned from the configured `priority_patterns`.
     ///
     /// # Example
     ///
@@ -138,11 +441,15 @@ impl CompositionApi {
     ///         source: ResourceSource::Local(PathBuf::from("doc1.md")),
     ///         requirement: ResourceRequirement::Required,
     ///         cache_duration: None,
+    ///         section: None,
+    ///         fallback: None,
     ///     },
     ///     Resource {
     ///         source: ResourceSource::Local(PathBuf::from("doc2.md")),
     ///         requirement: ResourceRequirement::Required,
     ///         cache_duration: None,
+    ///         section: None,
+    ///         fallback: None,
     ///     },
     /// ];
     ///
@@ -154,12 +461,35 @@ impl CompositionApi {
     /// ```
     #[instrument(skip(self), fields(num_resources = resources.len()))]
     pub async fn generate_workplan(&self, resources: Vec<Resource>) -> Result<WorkPlan> {
+        self.workplan_in(resources, self.config.language.as_deref()).await
+    }
+
+    /// Generate the work plan for rendering resources in `language`
+    async fn workplan_in(&self, resources: Vec<Resource>, language: Option<&str>) -> Result<WorkPlan> {
         info!("Generating work plan");
-        // Build graphs for all resources and merge them
+
+        let roots: Vec<ResourceHash> = resources.iter().map(|resource| self.resource_hash(resource)).collect();
+        match self.combined_graph(resources, language).await? {
+            Some(graph) => {
+                let mut plan = crate::graph::generate_workplan_for_roots(&graph, &roots)?;
+                crate::graph::apply_priority_patterns(
+                    &mut plan,
+                    &self.config.priority_patterns,
+                    self.config.project_root.as_deref(),
+                )?;
+                info!("Work plan generated with {} layers and {} total tasks", plan.layers.len(), plan.total_tasks);
+                Ok(plan)
+            },
+            None => Ok(WorkPlan::new()),
+        }
+    }
+
+    /// Build graphs for all resources, as rendered in `language`, and merge them
+    async fn combined_graph(&self, resources: Vec<Resource>, language: Option<&str>) -> Result<Option<DependencyGraph>> {
         let mut combined_graph: Option<DependencyGraph> = None;
 
         for resource in resources {
-            let graph = self.graph(resource).await?;
+            let graph = self.graph_in(resource, language).await?;
 
             if let Some(ref mut combined) = combined_graph {
                 // Merge graphs - add all nodes and edges
@@ -169,19 +499,14 @@ impl CompositionApi {
                 for edge in graph.edges {
                     combined.add_edge(edge.0, edge.1);
                 }
+                combined.invalidated.extend(graph.invalidated);
+                combined.file_stats.extend(graph.file_stats);
             } else {
                 combined_graph = Some(graph);
             }
         }
 
-        match combined_graph {
-            Some(graph) => {
-                let plan = crate::graph::generate_workplan(&graph)?;
-                info!("Work plan generated with {} layers and {} total tasks", plan.layers.len(), plan.total_tasks);
-                Ok(plan)
-            },
-            None => Ok(WorkPlan::new()),
-        }
+        Ok(combined_graph)
     }
 
     /// Render resources to documents
@@ -189,9 +514,9 @@ impl CompositionApi {
     /// Orchestrates the complete rendering pipeline for a set of resources:
     /// 1. Generates an optimized work plan based on dependencies
     /// 2. Executes the work plan with parallel processing via rayon
-    /// 3. Resolves all transclusions recursively
-    /// 4. Applies frontmatter interpolation
-    /// 5. Returns fully rendered documents
+    /// 3. Runs each document through the configured render passes (by default
+    ///    transclusion, AI operations, interpolation, charts, final)
+    /// 4. Returns fully rendered documents
     ///
     /// # Arguments
     ///
@@ -200,7 +525,11 @@ impl CompositionApi {
     ///
     /// # Returns
     ///
-    /// A vector of rendered `Document`s with all transclusions and interpolations resolved.
+    /// A vector of rendered `Document`s with all transclusions and interpolations
+    /// resolved, and the warnings of every rendered document, dependencies
+    /// included. Each warning is also logged. With
+    /// [`ParseOptions::strict`](crate::parse::ParseOptions::strict) set in
+    /// `parse_options`, the first warning fails the render instead.
     ///
     /// # Example
     ///
@@ -215,11 +544,13 @@ impl CompositionApi {
     ///         source: ResourceSource::Local(PathBuf::from("document.md")),
     ///         requirement: ResourceRequirement::Required,
     ///         cache_duration: None,
+    ///         section: None,
+    ///         fallback: None,
     ///     },
     /// ];
     ///
-    /// let documents = api.render(resources, None).await?;
-    /// println!("Rendered {} documents", documents.len());
+    /// let (documents, warnings) = api.render(resources, None).await?;
+    /// println!("Rendered {} documents with {} warnings", documents.len(), warnings.len());
     /// # Ok(())
     /// # }
     /// ```
@@ -228,20 +559,64 @@ impl CompositionApi {
         &self,
         resources: Vec<Resource>,
         state: Option<Frontmatter>,
-    ) -> Result<Vec<Document>> {
+    ) -> Result<(Vec<Document>, RenderWarnings)> {
+        let (documents, metrics) = self.render_with_metrics(resources, state).await?;
+        Ok((documents, metrics.parse_warnings))
+    }
+
+    /// Render resources to documents and report per-pass timings
+    ///
+    /// Identical to [`render()`](Self::render), but also returns the time spent
+    /// in each of the configured `render_passes`, summed over every document
+    /// rendered (including dependencies of the requested resources).
+    ///
+    /// A resource given more than once (by [`ResourceHash`]) is rendered and
+    /// returned once, and documents are returned in the order their resources
+    /// were first given.
+    ///
+    /// With a configured `language`, each resource is rendered from its
+    /// translation where one exists, and the document's `resource` is the
+    /// translation; [`RenderMetrics::language_variants`] lists the file each
+    /// document and `::file` transclusion was read from.
+    #[instrument(skip(self, state), fields(num_resources = resources.len()))]
+    pub async fn render_with_metrics(
+        &self,
+        resources: Vec<Resource>,
+        state: Option<Frontmatter>,
+    ) -> Result<(Vec<Document>, RenderMetrics)> {
+        self.render_in(resources, state, self.config.language.as_deref()).await
+    }
+
+    /// Render resources in `language`, or as they are without one
+    async fn render_in(
+        &self,
+        resources: Vec<Resource>,
+        state: Option<Frontmatter>,
+        language: Option<&str>,
+    ) -> Result<(Vec<Document>, RenderMetrics)> {
         info!("Starting render pipeline");
 
-        // 1. Compute hashes of requested resources for filtering later
-        let requested_hashes: std::collections::HashSet<ResourceHash> = resources
-            .iter()
-            .map(|r| {
-                use crate::graph::utils::compute_resource_hash;
-                compute_resource_hash(r)
+        // 1. Drop repeated resources, keeping the hashes in input order to
+        //    pick the requested documents out later, and pick each one's
+        //    translation when rendering in a language
+        let mut seen = HashSet::new();
+        let mut variants = Vec::new();
+        let (requested, resources): (Vec<ResourceHash>, Vec<Resource>) = resources
+            .into_iter()
+            .map(|resource| match language {
+                Some(language) if !resource.is_memory() => {
+                    let used = resource.localized(language);
+                    variants.push(LanguageVariant { language: language.to_string(), resource, used: used.clone() });
+                    used
+                }
+                _ => resource,
             })
-            .collect();
+            .map(|resource| (self.resource_hash(&resource), resource))
+            .filter(|(hash, _)| seen.insert(*hash))
+            .unzip();
 
         // 2. Generate work plan
-        let plan = self.generate_workplan(resources).await?;
+        let plan = self.workplan_in(resources, language).await?;
 
         // 3. Merge state frontmatter with instance frontmatter
         let mut merged_frontmatter = self.frontmatter.clone();
@@ -250,32 +625,325 @@ impl CompositionApi {
         }
 
         // 4. Execute work plan (renders all documents including dependencies)
-        let all_documents = crate::render::execute_workplan(
+        let (all_documents, mut metrics) = crate::render::execute_workplan_with_metrics(
             &plan,
             &merged_frontmatter,
             &self.cache,
+            &self.config.render_passes,
+            self.ai_queue.as_ref(),
+            &self.transformers,
+            &self.render_settings(language),
         )
         .await?;
+        variants.append(&mut metrics.language_variants);
+        metrics.language_variants = variants;
 
-        // 5. Filter to return only the originally requested documents
-        let filtered_documents: Vec<Document> = all_documents
+        // 5. Return only the requested documents, in input order
+        let mut documents: HashMap<ResourceHash, Document> = all_documents
             .into_iter()
-            .filter(|doc| {
-                use crate::graph::utils::compute_resource_hash;
-                let doc_hash = compute_resource_hash(&doc.resource);
-                requested_hashes.contains(&doc_hash)
-            })
+            .map(|doc| (self.resource_hash(&doc.resource), doc))
+            .filter(|(hash, _)| seen.contains(hash))
+            .collect();
+        let filtered_documents: Vec<Document> = requested
+            .iter()
+            .filter_map(|hash| documents.remove(hash))
             .collect();
 
         info!("Render pipeline complete. Returned {} of {} documents", filtered_documents.len(), plan.total_tasks);
-        Ok(filtered_documents)
+        Ok((filtered_documents, metrics))
+    }
+
+    /// Render resources that each have their own frontmatter state
+    ///
+    /// Every document is interpolated with, from highest precedence to lowest:
+    /// its own frontmatter, its request's state, `shared` and the instance
+    /// frontmatter. Transcluded content takes the state of the document that
+    /// pulled it in.
+    ///
+    /// Requests without a state are rendered together, as by
+    /// [`render()`](Self::render) with `shared`. Each request with a state is
+    /// rendered on its own, with its dependencies, so a dependency reached
+    /// from two documents with different states is rendered once for each
+    /// rather than shared between them.
+    ///
+    /// Returns one document per request, in the order given. Unlike
+    /// [`render()`](Self::render), a resource requested twice is rendered
+    /// twice, since each request can have its own state.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use lib::{init, Frontmatter, Resource};
+    /// # use std::path::PathBuf;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let api = init(None, None).await?;
+    /// let mut developers = Frontmatter::new();
+    /// developers.custom.insert("audience".to_string(), "developers".into());
+    ///
+    /// let documents = api
+    ///     .render_with_states(
+    ///         vec![
+    ///             (Resource::local(PathBuf::from("landing.md")), Some(developers)),
+    ///             (Resource::local(PathBuf::from("index.md")), None),
+    ///         ],
+    ///         None,
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, requests, shared), fields(num_requests = requests.len()))]
+    pub async fn render_with_states(
+        &self,
+        requests: Vec<(Resource, Option<Frontmatter>)>,
+        shared: Option<Frontmatter>,
+    ) -> Result<Vec<Document>> {
+        let shared = shared.unwrap_or_default();
+
+        let stateless: Vec<Resource> = requests
+            .iter()
+            .filter(|(_, state)| state.is_none())
+            .map(|(resource, _)| resource.clone())
+            .collect();
+        let shared_documents: HashMap<ResourceHash, Document> = if stateless.is_empty() {
+            HashMap::new()
+        } else {
+            self.render(stateless, Some(shared.clone()))
+                .await?
+                .0
+                .into_iter()
+                .map(|doc| (self.resource_hash(&doc.resource), doc))
+                .collect()
+        };
+
+        let mut documents = Vec::with_capacity(requests.len());
+        for (resource, state) in requests {
+            let document = match state {
+                None => shared_documents.get(&self.resource_hash(&resource)).cloned(),
+                Some(state) => {
+                    let mut merged = shared.clone();
+                    merged.merge(state);
+                    self.render(vec![resource], Some(merged)).await?.0.pop()
+                }
+            };
+            documents.extend(document);
+        }
+
+        Ok(documents)
+    }
+
+    /// Re-render only what changed since a previous graph was built
+    ///
+    /// Builds the current dependency graph for `resources` and compares it with
+    /// `prev_graph` using [`compute_changed_resources`](crate::graph::compute_changed_resources).
+    /// Changed resources, and everything that depends on them, are rendered
+    /// again. Unchanged documents are loaded from the rendered document cache,
+    /// falling back to rendering them when nothing usable is stored there.
+    ///
+    /// Only output rendered with the instance frontmatter alone is reused,
+    /// not output rendered with a request's state, and it is reused as long
+    /// as the document's content is unchanged, so call
+    /// [`render()`](Self::render) after changing the configured render passes.
+    ///
+    /// Returns the requested documents in the order they were given. With a
+    /// configured `language`, they are rendered in it as by
+    /// [`render()`](Self::render), and `prev_graph` should come from
+    /// [`graph()`](Self::graph) with the same language.
+    #[instrument(skip(self, prev_graph), fields(num_resources = resources.len()))]
+    pub async fn render_incremental(
+        &self,
+        prev_graph: &DependencyGraph,
+        resources: Vec<Resource>,
+    ) -> Result<Vec<Document>> {
+        info!("Starting incremental render");
+
+        let language = self.config.language.as_deref();
+        let resources: Vec<Resource> = match language {
+            Some(language) => resources.iter().map(|resource| resource.localized(language)).collect(),
+            None => resources,
+        };
+        let requested: Vec<ResourceHash> = resources.iter().map(|resource| self.resource_hash(resource)).collect();
+        let Some(current_graph) = self.combined_graph(resources, language).await? else {
+            return Ok(Vec::new());
+        };
+
+        // 1. Everything changed or invalidated while building the graph, plus
+        //    unchanged documents with no usable cached output
+        let mut to_render: HashSet<ResourceHash> =
+            compute_changed_resources(prev_graph, &current_graph).into_iter().collect();
+        to_render.extend(current_graph.invalidated.iter().copied());
+
+        let unchanged: Vec<String> = current_graph
+            .nodes
+            .keys()
+            .filter(|hash| !to_render.contains(hash))
+            .map(|hash| rendered_cache_key(*hash, language, &self.frontmatter))
+            .collect();
+        let keys: Vec<&str> = unchanged.iter().map(String::as_str).collect();
+        let mut cached = self.cache.get_rendered_documents(&keys).await?;
+
+        let mut documents: HashMap<ResourceHash, Document> = HashMap::new();
+        for (hash, node) in &current_graph.nodes {
+            if to_render.contains(hash) {
+                continue;
+            }
+            match cached.remove(&rendered_cache_key(*hash, language, &self.frontmatter)) {
+                Some(entry) if Some(&entry.content_hash) == node.content_hash.as_ref() => {
+                    documents.insert(*hash, entry.document);
+                }
+                _ => {
+                    to_render.insert(*hash);
+                }
+            }
+        }
+
+        info!(
+            "Re-rendering {} of {} documents ({} from cache)",
+            to_render.len(),
+            current_graph.nodes.len(),
+            documents.len()
+        );
+
+        // 2. Render what's left, in dependency order
+        if !to_render.is_empty() {
+            let mut plan = crate::graph::generate_workplan(&subgraph(&current_graph, &to_render))?;
+            crate::graph::apply_priority_patterns(
+                &mut plan,
+                &self.config.priority_patterns,
+                self.config.project_root.as_deref(),
+            )?;
+            let rendered = crate::render::execute_workplan(
+                &plan,
+                &self.frontmatter,
+                &self.cache,
+                &self.config.render_passes,
+                self.ai_queue.as_ref(),
+                &self.transformers,
+                &self.render_settings(language),
+            )
+            .await?;
+
+            for doc in rendered {
+                documents.insert(self.resource_hash(&doc.resource), doc);
+            }
+        }
+
+        // 3. Return the requested documents
+        Ok(requested
+            .iter()
+            .filter_map(|hash| documents.remove(hash))
+            .collect())
+    }
+
+    /// Render a document read from stdin
+    ///
+    /// See [`render_reader()`](Self::render_reader). The document's resource is
+    /// the local path `-`, so relative references resolve against the current
+    /// directory.
+    #[instrument(skip(self, state))]
+    pub async fn render_stdin(&self, state: Option<Frontmatter>) -> Result<Document> {
+        self.render_reader(tokio::io::stdin(), Resource::local(PathBuf::from("-")), state)
+            .await
+    }
+
+    /// Render a document read from a stream
+    ///
+    /// The document is parsed with [`parse_document_async`], so its
+    /// frontmatter is read without buffering the whole stream, and then run
+    /// through the configured render passes. `source` identifies the document
+    /// and is the base for its relative references. Unlike
+    /// [`render()`](Self::render), the result is not cached, since the
+    /// content can't be read again to check whether it's still fresh.
+    ///
+    /// [`parse_document_async`]: crate::parse::parse_document_async
+    #[instrument(skip(self, reader, state), fields(source = ?source.source))]
+    pub async fn render_reader(
+        &self,
+        reader: impl tokio::io::AsyncRead + Unpin,
+        source: Resource,
+        state: Option<Frontmatter>,
+    ) -> Result<Document> {
+        let (document, _warnings) =
+            crate::parse::parse_document_async(reader, source, self.config.parse_options).await?;
+        if let Some(schema) = &self.config.frontmatter_schema {
+            crate::parse::validate_document(&document, schema)?;
+        }
+
+        let mut frontmatter = self.frontmatter.clone();
+        if let Some(state) = state {
+            frontmatter.merge(state);
+        }
+
+        let (document, _timings, _report) = crate::render::render_parsed_document(
+            document,
+            &frontmatter,
+            &self.cache,
+            &self.config.render_passes,
+            self.ai_queue.as_deref(),
+            &self.transformers,
+            &self.render_settings(self.config.language.as_deref()),
+        )
+        .await?;
+
+        Ok(document)
+    }
+
+    /// Render a document held in a string
+    ///
+    /// The document is an in-memory resource (see [`Resource::memory`]), so
+    /// nothing is read from or written to disk for it: relative transclusions
+    /// resolve against the current directory, `frontmatter` is merged over
+    /// the API's own for interpolation, and the result is never cached.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use lib::{init, Frontmatter};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let api = init(None, None).await?;
+    /// let mut frontmatter = Frontmatter::new();
+    /// frontmatter.custom.insert("name".to_string(), "World".into());
+    ///
+    /// let doc = api.render_string("# Hello {{name}}", frontmatter).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, content, frontmatter))]
+    pub async fn render_string(&self, content: &str, frontmatter: Frontmatter) -> Result<Document> {
+        let (document, _warnings) = crate::parse::parse_document_with_schema(
+            content,
+            Resource::memory("string", content),
+            self.config.frontmatter_schema.as_ref(),
+            self.config.parse_options,
+        )?;
+
+        let mut merged = self.frontmatter.clone();
+        merged.merge(frontmatter);
+
+        let (document, _timings, _report) = crate::render::render_parsed_document(
+            document,
+            &merged,
+            &self.cache,
+            &self.config.render_passes,
+            self.ai_queue.as_deref(),
+            &self.transformers,
+            &self.render_settings(self.config.language.as_deref()),
+        )
+        .await?;
+
+        Ok(document)
     }
 
     /// Convert markdown to HTML
     ///
     /// Renders markdown files matching glob patterns to self-contained HTML output.
     /// This is the complete pipeline:
-    /// 1. Resolves glob patterns to find matching files
+    /// 1. Resolves glob patterns to find matching files; a file matched by
+    ///    several patterns is converted once. Files ignored by the project's
+    ///    `.gitignore` or `.ignore`, unless `include_ignored` is set, and files
+    ///    matching `exclude_patterns` are left out
     /// 2. Renders all documents (including transclusions and AI operations)
     /// 3. Converts to HTML with inline assets
     ///
@@ -304,62 +972,336 @@ impl CompositionApi {
     /// ```
     #[instrument(skip(self), fields(num_patterns = patterns.len()))]
     pub async fn to_html(&self, patterns: Vec<String>) -> Result<Vec<HtmlOutput>> {
-        info!("Converting to HTML");
+        let (outputs, _metrics) = self.to_html_with_metrics(patterns).await?;
+        Ok(outputs)
+    }
 
-        // 1. Resolve glob patterns to find files
-        let mut resources = Vec::new();
-        for pattern in &patterns {
-            let matches = glob::glob(pattern)
-                .map_err(|e| CompositionError::Parse(ParseError::InvalidResource(
-                    format!("Invalid glob pattern '{}': {}", pattern, e)
-                )))?;
-
-            for entry in matches {
-                let path = entry.map_err(|e| CompositionError::Io(
-                    std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string())
-                ))?;
-
-                resources.push(Resource {
-                    source: ResourceSource::Local(path),
-                    requirement: ResourceRequirement::Required,
-                    cache_duration: None,
-                });
-            }
+    /// Convert markdown to HTML and report what was rendered
+    ///
+    /// Identical to [`to_html()`](Self::to_html), but also returns the
+    /// [`RenderMetrics`] of the render, whose `excluded` lists the files that
+    /// matched a pattern but were left out, and why.
+    #[instrument(skip(self), fields(num_patterns = patterns.len()))]
+    pub async fn to_html_with_metrics(&self, patterns: Vec<String>) -> Result<(Vec<HtmlOutput>, RenderMetrics)> {
+        self.to_html_in(patterns, self.config.language.as_deref()).await
+    }
+
+    /// Convert markdown to HTML in every configured language
+    ///
+    /// Renders the files matching the glob patterns, as by
+    /// [`to_html()`](Self::to_html), once for each of the configured
+    /// `languages`, in order. Each document is read from its translation
+    /// into the language, such as `intro.de.md` for `intro.md`, where one
+    /// exists, and from the original otherwise. Outputs are tagged with
+    /// their [`language`](HtmlOutput::language), and each output path has
+    /// the language before its extension, e.g. `intro.de.md`, whichever
+    /// file it was read from.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use lib::{init_with_config, CacheBackend, CompositionConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut config = CompositionConfig::new(CacheBackend::Memory);
+    /// config.languages = vec!["en".to_string(), "de".to_string()];
+    /// let api = init_with_config(None, None, config).await?;
+    ///
+    /// for output in api.to_html_languages(vec!["docs/*.md".to_string()]).await? {
+    ///     println!("{:?}: {}", output.language, output.href());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self), fields(num_patterns = patterns.len()))]
+    pub async fn to_html_languages(&self, patterns: Vec<String>) -> Result<Vec<HtmlOutput>> {
+        let mut outputs = Vec::new();
+        for language in &self.config.languages {
+            let (rendered, _metrics) = self.to_html_in(patterns.clone(), Some(language)).await?;
+            outputs.extend(rendered);
         }
+        Ok(outputs)
+    }
+
+    /// Convert the files matching glob patterns to HTML in `language`, or
+    /// as they are without one
+    async fn to_html_in(&self, patterns: Vec<String>, language: Option<&str>) -> Result<(Vec<HtmlOutput>, RenderMetrics)> {
+        info!("Converting to HTML");
+
+        // 1. Resolve glob patterns to find files, in the order they match
+        let (resources, excluded) = resolve_patterns(&patterns, &self.config)?;
 
         if resources.is_empty() {
             info!("No files matched the provided patterns");
-            return Ok(Vec::new());
+            return Ok((Vec::new(), RenderMetrics { excluded, ..RenderMetrics::default() }));
         }
 
         info!("Found {} files to convert", resources.len());
 
         // 2. Render all documents
-        let documents = self.render(resources, None).await?;
+        let (documents, mut metrics) = self.render_in(resources, None, language).await?;
+        metrics.excluded = excluded;
 
         // 3. Convert each document to HTML
+        let mut output_paths =
+            OutputPaths::new(self.config.output_template.clone()).with_project_root(self.config.project_root.clone());
         let mut outputs = Vec::new();
         for doc in documents {
-            let html = crate::render::to_html(&doc.content)
-                .map_err(CompositionError::Render)?;
+            let html = if self.config.sanitize_html {
+                crate::render::to_sanitized_html(&doc.content)
+            } else {
+                crate::render::to_html(&doc.content)
+            }
+            .map_err(CompositionError::Render)?;
+            let text = crate::ai::embedding::document_text(&doc);
+            let title = document_title(&doc, &text);
 
-            let path = match &doc.resource.source {
-                ResourceSource::Local(p) => p.clone(),
-                ResourceSource::Remote(url) => {
-                    // For remote resources, generate a filename from the URL
-                    let filename = url
-                        .path_segments()
-                        .and_then(|s| s.last())
-                        .unwrap_or("remote.html");
-                    std::path::PathBuf::from(filename)
+            let html = if self.config.structured_data {
+                let image = self.page_image(&doc).await?;
+                let (script, warning) = crate::render::structured_data(&doc.frontmatter, title.as_deref(), image);
+                if let Some(warning) = warning {
+                    metrics.parse_warnings.record(&doc.resource, vec![warning]);
                 }
+                format!("{}\n{}", script, html)
+            } else {
+                html
             };
 
-            outputs.push(HtmlOutput { path, html });
+            let mut path = output_paths.assign(&doc.resource);
+            if let Some(language) = language {
+                // Untranslated documents get the translation's path too
+                if translation_language(&path, &[language.to_string()]).is_none() {
+                    path = language_variant_path(&path, language);
+                }
+            }
+            let words = crate::render::prose_words(&text);
+
+            outputs.push(HtmlOutput {
+                path,
+                title,
+                metadata: document_metadata(&doc.frontmatter),
+                linked_assets: crate::render::linked_assets(&html),
+                excerpt: crate::render::excerpt(&words, self.config.excerpt_words),
+                word_count: words.len(),
+                language: language.map(str::to_string),
+                html,
+                resource: doc.resource,
+                frontmatter: doc.frontmatter,
+                dependencies: doc.dependencies,
+                parsed_at: doc.parsed_at,
+                rendered_at: Utc::now(),
+            });
+        }
+
+        if let Some(dir) = &self.config.output_dir {
+            for output in &outputs {
+                self.write_output(dir, output)?;
+            }
         }
 
         info!("Generated {} HTML outputs", outputs.len());
-        Ok(outputs)
+        Ok((outputs, metrics))
+    }
+
+    /// The frontmatter `image` of a document for its structured data: the
+    /// largest optimized variant, beside the image, once the image has been
+    /// through [`optimize_image`](Self::optimize_image), and the image as
+    /// written otherwise
+    async fn page_image(&self, doc: &Document) -> Result<Option<PageImage>> {
+        let Some(image) = doc.frontmatter.get_string("image").filter(|image| !image.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let as_written = PageImage { url: image.to_string(), size: None };
+        if url::Url::parse(image).is_ok() {
+            return Ok(Some(as_written));
+        }
+
+        let path = match &doc.resource.source {
+            ResourceSource::Local(document) => document.parent().unwrap_or(Path::new("")).join(image),
+            _ => PathBuf::from(image),
+        };
+        let source = ImageSource::Local(crate::graph::utils::normalize_path(&path));
+        Ok(match crate::image::largest_cached_variant(&source, &self.cache, self.project_root()).await? {
+            Some(variant) => {
+                let url = match image.rfind('/') {
+                    Some(slash) => format!("{}/{}", &image[..slash], variant.file_name),
+                    None => variant.file_name,
+                };
+                Some(PageImage { url, size: Some((variant.width, variant.height)) })
+            }
+            None => Some(as_written),
+        })
+    }
+
+    /// Write an output's HTML under `dir`, and a compressed copy beside it
+    /// for each of `precompress`
+    ///
+    /// Output paths outside the project root keep only their normal
+    /// components, so nothing is written outside `dir`.
+    fn write_output(&self, dir: &Path, output: &HtmlOutput) -> Result<()> {
+        let file = dir.join(self.published_path(output));
+
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&file, &output.html)?;
+        for compression in &self.config.precompress {
+            std::fs::write(compression.path_for(&file), output.compressed(*compression))?;
+        }
+        debug!("Wrote {}", file.display());
+        Ok(())
+    }
+
+    /// Where an output is published: its path relative to the project root
+    /// with an `.html` extension, without any `..` or root components
+    fn published_path(&self, output: &HtmlOutput) -> PathBuf {
+        let relative = self
+            .config
+            .project_root
+            .as_deref()
+            .and_then(|root| strip_project_root(&output.path, root))
+            .unwrap_or_else(|| output.path.clone());
+        let relative: PathBuf = relative
+            .components()
+            .filter(|component| matches!(component, std::path::Component::Normal(_)))
+            .collect();
+        relative.with_extension("html")
+    }
+
+    /// Generate an Atom or RSS feed of the documents matching glob patterns
+    ///
+    /// Each document is rendered as by [`to_html()`](Self::to_html) and
+    /// becomes an entry linking to its page under `options.base_url`, at the
+    /// path `output_dir` would write it to. Entries are sorted newest first
+    /// by their `date` property, an RFC 3339 timestamp or a `YYYY-MM-DD`
+    /// date. A document without a valid `date` is left out with a warning,
+    /// or fails with `RenderError::FeedFailed` when `options.strict` is set.
+    /// Documents with `draft: true` are always left out.
+    ///
+    /// Entry content is the rendered HTML, with relative URLs resolved
+    /// against the page's URL so images and links work in feed readers.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use lib::{init, FeedOptions};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let api = init(None, None).await?;
+    /// let options = FeedOptions::new("Release notes", "https://example.com/notes/".parse()?);
+    /// let atom = api.to_feed(vec!["notes/*.md".to_string()], options).await?;
+    /// std::fs::write("public/notes/feed.xml", atom)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, options), fields(num_patterns = patterns.len()))]
+    pub async fn to_feed(&self, patterns: Vec<String>, options: FeedOptions) -> Result<String> {
+        let outputs = self.to_html(patterns).await?;
+        let base = options.base();
+
+        let mut entries = Vec::new();
+        for output in outputs {
+            if output.frontmatter.get_bool("draft") == Some(true) {
+                debug!("Leaving draft {} out of the feed", output.resource.label());
+                continue;
+            }
+            let Some(date) = output.frontmatter.custom.get("date").and_then(feed_date) else {
+                let problem = match output.frontmatter.custom.get("date") {
+                    Some(value) => format!("has an invalid date {}", value),
+                    None => "has no date".to_string(),
+                };
+                if options.strict {
+                    return Err(CompositionError::Render(RenderError::FeedFailed(format!(
+                        "{} {}",
+                        output.resource.label(),
+                        problem
+                    ))));
+                }
+                warn!("Leaving {} out of the feed: it {}", output.resource.label(), problem);
+                continue;
+            };
+
+            let link = base.join(&to_url_path(&self.published_path(&output))).map_err(|e| {
+                CompositionError::Render(RenderError::FeedFailed(format!("{}: {}", output.resource.label(), e)))
+            })?;
+            entries.push(FeedEntry {
+                title: output.title.clone().unwrap_or_else(|| output.resource.label()),
+                content: absolutize_urls(&output.html, &link),
+                author: output.frontmatter.get_string("author").map(str::to_string),
+                summary: output.excerpt,
+                link,
+                date,
+            });
+        }
+        entries.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.link.cmp(&b.link)));
+
+        info!("Generated a feed of {} entries", entries.len());
+        Ok(match options.format {
+            FeedFormat::Atom => atom_feed(&entries, &options),
+            FeedFormat::Rss => rss_feed(&entries, &options),
+        })
+    }
+
+    /// Find the documents most related to a query
+    ///
+    /// Renders the files matching the glob patterns and ranks them by the
+    /// cosine similarity of their embeddings to the query's (see
+    /// [`semantic_search`](crate::ai::embedding::semantic_search)). Each
+    /// result's excerpt is the document's sentence most similar to the query.
+    /// Requires an embedding model (see
+    /// [`with_embedding_model()`](Self::with_embedding_model)).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use lib::init;
+/// # use lib::ai::MockEmbeddingModel;
+    /// # use std::sync::Arc;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let api = init(None, None).await?.with_embedding_model(Arc::new(MockEmbeddingModel::new(384)));
+    /// for result in api.semantic_search("deployment", vec!["docs/**/*.md".to_string()], 5).await? {
+    ///     println!("{:.2} {} - {}", result.score, result.path.display(), result.excerpt);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self), fields(num_patterns = patterns.len()))]
+    pub async fn semantic_search(&self, query: &str, patterns: Vec<String>, top_k: usize) -> Result<Vec<SearchResult>> {
+        use crate::ai::embedding::{document_text, most_similar_sentence, semantic_search};
+
+        let model = self.embedding_model.as_deref().ok_or_else(|| {
+            CompositionError::AI(AIError::InvalidModelConfig(
+                "Semantic search requires an embedding model".to_string(),
+            ))
+        })?;
+
+        let (resources, _excluded) = resolve_patterns(&patterns, &self.config)?;
+        if resources.is_empty() {
+            info!("No files matched the provided patterns");
+            return Ok(Vec::new());
+        }
+
+        let (documents, _warnings) = self.render(resources, None).await?;
+        let ranked = semantic_search(query, &documents, top_k, model, &self.cache, self.project_root()).await?;
+
+        let mut results = Vec::with_capacity(ranked.len());
+        for (hash, score) in ranked {
+            let Some(doc) = documents.iter().find(|doc| self.resource_hash(&doc.resource) == hash) else {
+                continue;
+            };
+            let text = document_text(doc);
+            let title = document_title(doc, &text);
+            let excerpt = most_similar_sentence(query, &text, model, &self.cache).await?.unwrap_or_default();
+            let path = match &doc.resource.source {
+                ResourceSource::Local(path) => path.clone(),
+                _ => PathBuf::from(doc.resource.label()),
+            };
+
+            results.push(SearchResult { path, title, score, excerpt });
+        }
+
+        Ok(results)
     }
 
     // ===== Supplemental API Functions =====
@@ -389,6 +1331,8 @@ impl CompositionApi {
     ///     source: ResourceSource::Local(PathBuf::from("document.md")),
     ///     requirement: ResourceRequirement::Required,
     ///     cache_duration: None,
+    ///     section: None,
+    ///     fallback: None,
     /// };
     ///
     /// let doc = api.transclude(resource).await?;
@@ -401,7 +1345,7 @@ impl CompositionApi {
         info!("Transcluding resource");
 
         // Use the render function with a single resource
-        let documents = self.render(vec![resource], None).await?;
+        let (documents, _warnings) = self.render(vec![resource], None).await?;
 
         // Return the first (and only) document
         documents
@@ -414,6 +1358,40 @@ impl CompositionApi {
             })
     }
 
+    /// Transclude a resource and keep only one of its sections
+    ///
+    /// The section is the heading whose anchor slug is `heading_slug`, e.g.
+    /// `getting-started` for `## Getting Started`, and everything under it up
+    /// to the next heading of the same or a higher level. It is cut from the
+    /// fully transcluded and interpolated document, so headings brought in
+    /// by transclusion can be selected too.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RenderError::TransclusionFailed` naming `resource#heading_slug`
+    /// when no heading has that slug.
+    #[instrument(skip(self), fields(source = ?resource.source))]
+    pub async fn transclude_section(&self, resource: Resource, heading_slug: &str) -> Result<Document> {
+        let label = resource.label();
+        let mut document = self.transclude(resource).await?;
+        document.content = crate::render::extract_section(&document.content, heading_slug).ok_or_else(|| {
+            RenderError::TransclusionFailed {
+                resource: format!("{}#{}", label, heading_slug.trim_start_matches('#')),
+            }
+        })?;
+        Ok(document)
+    }
+
+    /// The heading outline of a resource, as a tree
+    ///
+    /// Headings come from the fully transcluded document, so headings
+    /// brought in by transclusion are included. Each heading's `id` is the
+    /// slug [`transclude_section`](Self::transclude_section) accepts.
+    #[instrument(skip(self), fields(source = ?resource.source))]
+    pub async fn document_headings(&self, resource: Resource) -> Result<Vec<Heading>> {
+        Ok(self.transclude(resource).await?.headings())
+    }
+
     /// Optimize an image for responsive web delivery
     ///
     /// Processes an image to generate optimized variants at multiple breakpoint widths
@@ -450,14 +1428,261 @@ impl CompositionApi {
         use crate::image::html::HtmlOptions;
 
         info!("Optimizing image");
-        let options = ImageOptions::default();
-        let html_options = HtmlOptions::default();
+        let options = ImageOptions { max_remote_bytes: self.config.max_remote_bytes, ..ImageOptions::default() };
+        let html_options = HtmlOptions {
+            breakpoints: self.config.breakpoints.clone(),
+            ..HtmlOptions::default()
+        };
 
-        let result = get_or_process_image(&source, options, html_options, &self.db).await?;
+        let result = get_or_process_image(&source, options, html_options, &self.cache, self.project_root()).await?;
         debug!("Image optimization complete");
         Ok(result)
     }
 
+    /// Optimize many images for responsive web delivery
+    ///
+    /// Each image is processed like [`optimize_image()`](Self::optimize_image),
+    /// with up to `max_parallelism` images (default: the number of CPUs) in
+    /// flight at once. Decoding runs on Tokio's blocking pool and encoding on
+    /// rayon's, and remote images are downloaded with one shared HTTP client.
+    /// One result is returned per source, in order; a failed image doesn't
+    /// stop the others. `progress` is called as each image finishes.
+    #[instrument(skip(self, sources, progress), fields(count = sources.len()))]
+    pub async fn optimize_images(
+        &self,
+        sources: Vec<ImageSource>,
+        progress: Option<&BatchProgressFn<'_>>,
+    ) -> Vec<Result<SmartImageOutput>> {
+        info!("Optimizing {} images", sources.len());
+        run_batch(sources, self.batch_limit(), progress, |source| self.optimize_image(source)).await
+    }
+
+    /// Find cached images that look like `resource`
+    ///
+    /// Compares perceptual hashes (see [`phash`](crate::image::phash)), so
+    /// copies under other names, re-encoded or lightly edited, are found too.
+    /// Returns the resource hash of each image within `threshold` differing
+    /// bits and its distance, closest first: 0 is the same picture and up to
+    /// about 10 a likely near-duplicate. Only images already processed by
+    /// [`optimize_image()`](Self::optimize_image) are compared against;
+    /// `resource` itself is loaded when it isn't cached.
+    #[instrument(skip(self), fields(resource = %resource.label()))]
+    pub async fn find_similar_images(&self, resource: Resource, threshold: u32) -> Result<Vec<(ResourceHash, u32)>> {
+        use crate::image::phash::{hamming_distance, perceptual_hash};
+        use crate::image::{load_image_with_limit, ImageSource};
+
+        let resource_hash = self.resource_hash(&resource).to_string();
+        let cached = self.cache.get_image(&resource_hash).await?.and_then(|entry| entry.perceptual_hash);
+        let target = match cached {
+            Some(hash) => hash,
+            None => {
+                let source = match &resource.source {
+                    ResourceSource::Local(path) => ImageSource::Local(path.clone()),
+                    ResourceSource::Remote(url) => ImageSource::Remote(url.to_string()),
+                    _ => {
+                        return Err(RenderError::ImageProcessing(format!(
+                            "{} isn't a cached image, or a local or remote file",
+                            resource.label()
+                        ))
+                        .into())
+                    }
+                };
+                let max_bytes = self.config.max_remote_bytes;
+                tokio::task::spawn_blocking(move || {
+                    load_image_with_limit(&source, max_bytes).map(|img| perceptual_hash(&img))
+                })
+                    .await
+                    .map_err(|e| RenderError::ImageProcessing(format!("Task join error: {}", e)))??
+            }
+        };
+
+        let mut similar: Vec<(ResourceHash, u32)> = self
+            .cache
+            .get_perceptual_hashes()
+            .await?
+            .into_iter()
+            .filter(|(hash, _)| *hash != resource_hash)
+            .filter_map(|(hash, phash)| {
+                let distance = hamming_distance(target, phash);
+                (distance <= threshold).then_some((hash.parse().ok()?, distance))
+            })
+            .collect();
+        similar.sort_by_key(|&(hash, distance)| (distance, hash));
+        debug!("Found {} similar images", similar.len());
+        Ok(similar)
+    }
+
+    /// Process many audio files without writing markdown
+    ///
+    /// Each input is processed like an `::audio` directive: its metadata is
+    /// read (or taken from the audio cache) and the file copied into
+    /// `options.output_dir`, or embedded when `options.inline_mode` is set.
+    /// Concurrency, results and `progress` behave as in
+    /// [`optimize_images()`](Self::optimize_images).
+    #[instrument(skip(self, inputs, options, progress), fields(count = inputs.len()))]
+    pub async fn process_audio_batch(
+        &self,
+        inputs: Vec<AudioInput>,
+        options: &AudioBatchOptions,
+        progress: Option<&BatchProgressFn<'_>>,
+    ) -> Vec<Result<AudioOutput>> {
+        info!("Processing {} audio files", inputs.len());
+        let cache = AudioCache::from_operations(&self.cache);
+        run_batch(inputs, self.batch_limit(), progress, |input| {
+            process_audio(input, &options.output_dir, &cache, options.inline_mode, &options.config, self.project_root())
+        })
+        .await
+    }
+
+    /// Process one audio file without rendering any markdown
+    ///
+    /// Reads the file's metadata, or takes it from the audio cache, and
+    /// copies the file into [`audio_output_dir`](CompositionConfig::audio_output_dir)
+    /// like an `::audio` directive would.
+    #[instrument(skip(self, config), fields(source = ?input.source))]
+    pub async fn process_audio(&self, input: AudioInput, config: AudioProcessingConfig) -> Result<AudioOutput> {
+        let cache = AudioCache::from_operations(&self.cache);
+        process_audio(input, &self.config.audio_output_dir, &cache, false, &config, self.project_root()).await
+    }
+
+    /// Process many audio files at once, reporting cache hits and failures
+    ///
+    /// Each input is processed like [`process_audio()`](Self::process_audio),
+    /// up to `config.max_concurrent` at a time on their own Tokio tasks. A
+    /// failed input, or one whose task panics, doesn't stop the others; it is
+    /// listed in [`AudioProcessingReport::failed`] with its error.
+    #[instrument(skip(self, inputs, config), fields(count = inputs.len()))]
+    pub async fn batch_process_audio(
+        &self,
+        inputs: Vec<AudioInput>,
+        config: AudioProcessingConfig,
+    ) -> Result<AudioProcessingReport> {
+        info!("Processing {} audio files", inputs.len());
+        let cache = AudioCache::from_operations(&self.cache);
+        let config = Arc::new(config);
+        let limit = config.max_concurrent.max(1);
+
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut task_inputs = HashMap::new();
+        let mut results = Vec::with_capacity(inputs.len());
+        let mut pending = inputs.into_iter().enumerate();
+
+        loop {
+            while tasks.len() < limit {
+                let Some((idx, input)) = pending.next() else {
+                    break;
+                };
+                let (cache, config, output_dir, project_root) = (
+                    cache.clone(),
+                    Arc::clone(&config),
+                    self.config.audio_output_dir.clone(),
+                    self.config.project_root.clone(),
+                );
+                let task = input.clone();
+                let handle = tasks.spawn(async move {
+                    process_audio_tracked(task, &output_dir, &cache, false, &config, project_root.as_deref()).await
+                });
+                task_inputs.insert(handle.id(), (idx, input));
+            }
+
+            let Some(joined) = tasks.join_next_with_id().await else {
+                break;
+            };
+            let (id, result) = match joined {
+                Ok((id, result)) => (id, result),
+                Err(e) => (
+                    e.id(),
+                    Err(CompositionError::Audio(AudioError::ProcessingFailed {
+                        reason: format!("Audio task failed: {}", e),
+                    })),
+                ),
+            };
+            if let Some((idx, input)) = task_inputs.remove(&id) {
+                results.push((idx, input, result));
+            }
+        }
+
+        results.sort_by_key(|(idx, _, _)| *idx);
+        let mut report = AudioProcessingReport::default();
+        for (_, input, result) in results {
+            match result {
+                Ok((output, cache_hit)) => {
+                    if cache_hit {
+                        report.cached += 1;
+                    } else {
+                        report.processed += 1;
+                    }
+                    report.outputs.push(output);
+                }
+                Err(e) => report.failed.push((input, e)),
+            }
+        }
+
+        info!(
+            "Processed {} audio files, {} from cache, {} failed",
+            report.processed,
+            report.cached,
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// Summarize text with the completion model
+    ///
+    /// The model is picked like it is for a document, by the `model`
+    /// frontmatter property of the API. Summaries are stored in the LLM cache
+    /// under the hash of `text`, so summarizing the same text again doesn't
+    /// call the model. Fails with `AIError::InvalidModelConfig` when no model
+    /// is attached.
+    #[instrument(skip(self, text), fields(len = text.len()))]
+    pub async fn summarize_text(&self, text: &str) -> Result<String> {
+        let models = self.models.as_ref().ok_or_else(|| {
+            CompositionError::AI(AIError::InvalidModelConfig(
+                "Summarizing requires a completion model".to_string(),
+            ))
+        })?;
+        let model = models.resolve(self.frontmatter.get_string("model"))?;
+
+        crate::ai::summarize(&self.cache, model, text, None).await
+    }
+
+    /// The configured render settings, rendering in `language`
+    fn render_settings(&self, language: Option<&str>) -> RenderSettings {
+        RenderSettings {
+            max_parallelism: self.config.max_parallelism,
+            parse_options: self.config.parse_options,
+            allowed_env_vars: self.config.allowed_env_vars.clone(),
+            max_remote_bytes: self.config.max_remote_bytes,
+            language: language.map(str::to_string),
+            retry: self.config.render_retry,
+            partial_results: self.config.partial_results,
+            audio: AudioBatchOptions {
+                output_dir: self.config.audio_output_dir.clone(),
+                inline_mode: self.config.inline_audio,
+                config: self.config.audio.clone(),
+            },
+            project_root: self.config.project_root.clone(),
+        }
+    }
+
+    /// Root that every local resource of a build is hashed relative to
+    fn project_root(&self) -> Option<&Path> {
+        self.config.project_root.as_deref()
+    }
+
+    /// Hash of a resource, relative to the configured project root
+    fn resource_hash(&self, resource: &Resource) -> ResourceHash {
+        compute_resource_hash(resource, self.project_root())
+    }
+
+    /// How many items of a batch are processed at once
+    fn batch_limit(&self) -> usize {
+        self.config
+            .max_parallelism
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(4, |n| n.get()))
+            .max(1)
+    }
+
     /// Summarize a resource
     pub async fn summarize(&self, _resource: Resource) -> Result<String> {
         todo!("Implement in Phase 6")
@@ -477,9 +1702,237 @@ impl CompositionApi {
 // Re-export image types for convenience
 pub use crate::image::{ImageSource, SmartImageOutput};
 
-// Placeholder types for future implementation
-#[derive(Debug, Clone)]
+/// Progress of a batch operation, reported as each item finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// Position of the finished item in the batch
+    pub index: usize,
+    /// Whether the item succeeded
+    pub succeeded: bool,
+    /// Items finished so far, including this one
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Callback that receives [`BatchProgress`] updates
+pub type BatchProgressFn<'a> = dyn Fn(BatchProgress) + Send + Sync + 'a;
+
+/// Run `work` on every item with at most `limit` in flight, keeping their order
+///
+/// Every item runs to completion whether or not others fail.
+async fn run_batch<T, O, F, Fut>(
+    items: Vec<T>,
+    limit: usize,
+    progress: Option<&BatchProgressFn<'_>>,
+    work: F,
+) -> Vec<Result<O>>
+where
+    F: Fn(T) -> Fut,
+    Fut: std::future::Future<Output = Result<O>>,
+{
+    let total = items.len();
+    let completed = AtomicUsize::new(0);
+
+    futures::stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let completed = &completed;
+            let job = work(item);
+            async move {
+                let result = job.await;
+                if let Some(progress) = progress {
+                    progress(BatchProgress {
+                        index,
+                        succeeded: result.is_ok(),
+                        completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                        total,
+                    });
+                }
+                result
+            }
+        })
+        .buffered(limit)
+        .collect()
+        .await
+}
+
+/// Resolve glob patterns to local resources, in the order they match
+///
+/// A file matched by several patterns is only included once. Files under the
+/// project root that its ignore files exclude, unless `include_ignored` is
+/// set, and files matching `exclude_patterns` are returned separately.
+fn resolve_patterns(patterns: &[String], config: &CompositionConfig) -> Result<(Vec<Resource>, Vec<ExcludedFile>)> {
+    let root = config
+        .project_root
+        .clone()
+        .or_else(|| std::env::current_dir().ok())
+        .map(|root| std::fs::canonicalize(&root).unwrap_or(root));
+    // Matched like priority patterns: without a `/`, against the file name
+    let exclusions = config
+        .exclude_patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map(|compiled| (compiled, pattern.contains('/'), pattern))
+                .map_err(|e| CompositionError::Parse(ParseError::InvalidResource(
+                    format!("Invalid exclude pattern '{}': {}", pattern, e)
+                )))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // Translations are rendered in place of their originals
+    let languages: Vec<String> = config.languages.iter().chain(&config.language).cloned().collect();
+
+    let mut resources = Vec::new();
+    let mut excluded = Vec::new();
+    let mut seen = HashSet::new();
+    for pattern in patterns {
+        let matches = glob::glob(pattern)
+            .map_err(|e| CompositionError::Parse(ParseError::InvalidResource(
+                format!("Invalid glob pattern '{}': {}", pattern, e)
+            )))?;
+
+        for entry in matches {
+            let path = entry.map_err(|e| CompositionError::Io(
+                std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string())
+            ))?;
+
+            // `docs/a.md` and `./docs/a.md` are the same file
+            let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if !seen.insert(canonical.clone()) {
+                continue;
+            }
+
+            let relative = root.as_deref().and_then(|root| Some((root, strip_project_root(&canonical, root)?)));
+            let reason = match &relative {
+                Some((root, _)) if !config.include_ignored && is_ignored(&canonical, root)? => {
+                    Some(ExclusionReason::Ignored)
+                }
+                _ => {
+                    let matched = to_url_path(relative.as_ref().map_or(path.as_path(), |(_, relative)| relative.as_path()));
+                    let file_name = matched.rsplit('/').next().unwrap_or(&matched);
+                    exclusions
+                        .iter()
+                        .find(|(exclusion, has_dir, _)| exclusion.matches(if *has_dir { &matched } else { file_name }))
+                        .map(|(_, _, pattern)| ExclusionReason::ExcludePattern(pattern.to_string()))
+                        .or_else(|| {
+                            translation_language(&path, &languages)
+                                .map(|language| ExclusionReason::Translation(language.to_string()))
+                        })
+                }
+            };
+            if let Some(reason) = reason {
+                debug!("Excluding {}: {}", path.display(), reason);
+                excluded.push(ExcludedFile { path, reason });
+                continue;
+            }
+
+            resources.push(Resource {
+                source: ResourceSource::Local(path),
+                requirement: ResourceRequirement::Required,
+                cache_duration: None,
+                section: None,
+                fallback: None,
+            });
+        }
+    }
+
+    Ok((resources, excluded))
+}
+
+/// The `title` frontmatter property, or else the first `#` heading of
+/// `text`, the document's text
+fn document_title(doc: &Document, text: &str) -> Option<String> {
+    doc.frontmatter.get_string("title").map(str::to_string).or_else(|| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix("# "))
+            .map(|heading| heading.trim().to_string())
+    })
+}
+
+/// Frontmatter properties [`HtmlOutput::metadata`] carries
+const METADATA_KEYS: &[&str] = &["title", "description", "author", "date", "tags"];
+
+/// The [`METADATA_KEYS`] properties that are set, as strings; lists are
+/// joined with `, `
+fn document_metadata(frontmatter: &Frontmatter) -> HashMap<String, String> {
+    METADATA_KEYS
+        .iter()
+        .filter_map(|&key| {
+            let value = match frontmatter.custom.get(key)? {
+                serde_json::Value::Null => return None,
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Array(items) => items
+                    .iter()
+                    .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => other.to_string(),
+            };
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// A document converted by [`CompositionApi::to_html`], with what navigation,
+/// feeds and site manifests need to know about it
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HtmlOutput {
     pub path: std::path::PathBuf,
     pub html: String,
+    /// The document's source
+    pub resource: Resource,
+    pub frontmatter: Frontmatter,
+    /// The `title` frontmatter property, or else the first `#` heading
+    pub title: Option<String>,
+    /// Resources the document transcludes or imports
+    pub dependencies: Vec<Resource>,
+    /// The `title`, `description`, `author`, `date` and `tags` frontmatter
+    /// properties that are set, as strings; `tags` are joined with `, `
+    pub metadata: HashMap<String, String>,
+    /// Images, audio, stylesheets and scripts the HTML loads, in order of
+    /// first use; inline `data:` URIs aren't listed
+    pub linked_assets: Vec<AssetRef>,
+    /// The first [`excerpt_words`](CompositionConfig::excerpt_words) words of
+    /// the document's prose, without markup, headings or code blocks
+    pub excerpt: String,
+    /// Number of words of prose, counted like the excerpt
+    pub word_count: usize,
+    /// The language the document was rendered in (see
+    /// [`CompositionConfig::language`])
+    pub language: Option<String>,
+    /// When the document's source was parsed
+    pub parsed_at: DateTime<Utc>,
+    /// When the HTML was generated
+    pub rendered_at: DateTime<Utc>,
+}
+
+impl HtmlOutput {
+    /// The output path as a relative link, with `/` separators on every platform
+    pub fn href(&self) -> String {
+        crate::graph::utils::to_url_path(&self.path)
+    }
+
+    /// The HTML compressed with `algo`, for static hosts that serve
+    /// pre-compressed files
+    pub fn compressed(&self, algo: Compression) -> Vec<u8> {
+        algo.compress(self.html.as_bytes())
+    }
+
+    /// [`linked_assets`](Self::linked_assets) as a JSON array of
+    /// `{"kind", "path", "hash"}` objects, for deploy pipelines that upload
+    /// only what pages reference
+    pub fn to_asset_manifest(&self) -> serde_json::Value {
+        serde_json::to_value(&self.linked_assets).expect("asset refs always serialize")
+    }
+}
+
+/// A document found by [`CompositionApi::semantic_search`]
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub path: PathBuf,
+    /// The `title` frontmatter property, or else the first `#` heading
+    pub title: Option<String>,
+    /// Cosine similarity to the query, higher is more similar
+    pub score: f32,
+    /// The document's sentence most similar to the query
+    pub excerpt: String,
 }
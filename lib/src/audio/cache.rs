@@ -3,7 +3,10 @@
 //! This module provides caching for audio metadata to avoid reprocessing unchanged files.
 //! The cache uses SurrealDB to store metadata indexed by resource hash and content hash.
 
-use crate::audio::types::{AudioFormat, AudioMetadata, AudioSource};
+use crate::audio::metadata::compute_content_hash;
+use crate::audio::types::{AudioChapter, AudioFormat, AudioMetadata, AudioSource};
+use base64::{engine::general_purpose, Engine as _};
+use crate::cache::CacheOperations;
 use crate::error::{CacheError, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -24,7 +27,7 @@ fn from_surreal_datetime(dt: &SurrealDatetime) -> DateTime<Utc> {
 
 /// Audio cache entry (internal representation using SurrealDB types)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct AudioCacheEntryInternal {
+pub(crate) struct AudioCacheEntryInternal {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<surrealdb::sql::Thing>,
     pub resource_hash: String,
@@ -37,6 +40,47 @@ struct AudioCacheEntryInternal {
     pub bitrate: Option<i64>,
     pub sample_rate: Option<i64>,
     pub channels: Option<i64>,
+    #[serde(default)]
+    pub chapters: Vec<AudioChapterInternal>,
+    /// Hash of the cover art, which is stored once in `audio_cover_art`
+    pub cover_art_hash: Option<String>,
+}
+
+/// Audio chapter (internal representation using SurrealDB types)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AudioChapterInternal {
+    pub title: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+impl From<AudioChapterInternal> for AudioChapter {
+    fn from(internal: AudioChapterInternal) -> Self {
+        Self {
+            title: internal.title,
+            start_ms: internal.start_ms as u64,
+            end_ms: internal.end_ms as u64,
+        }
+    }
+}
+
+impl From<AudioChapter> for AudioChapterInternal {
+    fn from(chapter: AudioChapter) -> Self {
+        Self {
+            title: chapter.title,
+            start_ms: chapter.start_ms as i64,
+            end_ms: chapter.end_ms as i64,
+        }
+    }
+}
+
+/// Cover art of cached audio (internal representation)
+///
+/// The image is stored base64 encoded, keyed by the hash of its bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AudioCoverArtInternal {
+    hash: String,
+    data: String,
 }
 
 /// Audio cache entry (public API using chrono types and domain types)
@@ -54,11 +98,8 @@ pub struct AudioCacheEntry {
 
 impl From<AudioCacheEntryInternal> for AudioCacheEntry {
     fn from(internal: AudioCacheEntryInternal) -> Self {
-        let format = match internal.format.as_str() {
-            "mp3" => AudioFormat::Mp3,
-            "wav" => AudioFormat::Wav,
-            _ => AudioFormat::Mp3, // Default fallback
-        };
+        // Default fallback
+        let format = AudioFormat::from_extension(&internal.format).unwrap_or(AudioFormat::Mp3);
 
         let metadata = AudioMetadata {
             duration_secs: internal.duration_secs,
@@ -68,6 +109,9 @@ impl From<AudioCacheEntryInternal> for AudioCacheEntry {
             title: None,
             artist: None,
             album: None,
+            // Filled in by `AudioCache::get` from `audio_cover_art`
+            cover_art: None,
+            chapters: internal.chapters.into_iter().map(AudioChapter::from).collect(),
         };
 
         Self {
@@ -97,6 +141,8 @@ impl From<AudioCacheEntry> for AudioCacheEntryInternal {
             bitrate: entry.metadata.bitrate.map(|b| b as i64),
             sample_rate: entry.metadata.sample_rate.map(|s| s as i64),
             channels: entry.metadata.channels.map(|c| c as i64),
+            chapters: entry.metadata.chapters.into_iter().map(AudioChapterInternal::from).collect(),
+            cover_art_hash: entry.metadata.cover_art.as_deref().map(compute_content_hash),
         }
     }
 }
@@ -132,9 +178,13 @@ impl From<NewAudioCacheEntry> for AudioCacheEntry {
 }
 
 /// Audio cache operations
+///
+/// A disabled audio cache misses on every lookup and discards writes, like a
+/// disabled [`CacheOperations`].
 #[derive(Clone)]
 pub struct AudioCache {
     db: Surreal<Db>,
+    enabled: bool,
 }
 
 impl AudioCache {
@@ -158,12 +208,27 @@ impl AudioCache {
     /// # }
     /// ```
     pub fn new(db: Surreal<Db>) -> Self {
-        Self { db }
+        Self { db, enabled: true }
+    }
+
+    /// Create an AudioCache on the database of `cache`, disabled when it is
+    pub fn from_operations(cache: &CacheOperations) -> Self {
+        Self { db: cache.db().clone(), enabled: cache.is_enabled() }
+    }
+
+    /// General cache operations on the same database, for downloaded audio
+    pub(crate) fn operations(&self) -> CacheOperations {
+        if self.enabled {
+            CacheOperations::new(self.db.clone())
+        } else {
+            CacheOperations::disabled(self.db.clone())
+        }
     }
 
     /// Get an audio cache entry by resource hash and content hash
     ///
-    /// Returns `None` if no matching entry is found (cache miss).
+    /// Returns `None` if no matching entry is found (cache miss), or if the
+    /// entry's cover art is missing from the cache.
     /// Returns `Some(entry)` if a matching entry is found (cache hit).
     ///
     /// # Arguments
@@ -199,6 +264,9 @@ impl AudioCache {
             "Getting audio cache entry for resource_hash: {}, content_hash: {}",
             resource_hash, content_hash
         );
+        if !self.enabled {
+            return Ok(None);
+        }
 
         let mut result = self
             .db
@@ -218,18 +286,49 @@ impl AudioCache {
             .take(0)
             .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
 
-        if entry.is_some() {
-            info!("Cache hit for resource_hash: {}", resource_hash);
-        } else {
+        let Some(entry) = entry else {
             info!("Cache miss for resource_hash: {}", resource_hash);
-        }
+            return Ok(None);
+        };
 
-        Ok(entry.map(AudioCacheEntry::from))
+        let cover_art = match &entry.cover_art_hash {
+            Some(hash) => match self.get_cover_art(hash).await? {
+                Some(cover_art) => Some(cover_art),
+                None => {
+                    info!("Cache miss for resource_hash: {} (cover art missing)", resource_hash);
+                    return Ok(None);
+                }
+            },
+            None => None,
+        };
+        info!("Cache hit for resource_hash: {}", resource_hash);
+
+        let mut entry = AudioCacheEntry::from(entry);
+        entry.metadata.cover_art = cover_art;
+        Ok(Some(entry))
+    }
+
+    /// Get stored cover art by the hash of its bytes
+    async fn get_cover_art(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let mut result = self
+            .db
+            .query("SELECT * FROM audio_cover_art WHERE hash = $hash")
+            .bind(("hash", hash))
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        let art: Option<AudioCoverArtInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        art.map(|art| general_purpose::STANDARD.decode(art.data))
+            .transpose()
+            .map_err(|e| CacheError::DeserializationError(e.to_string()).into())
     }
 
     /// Insert or update an audio cache entry
     ///
     /// If an entry with the same resource_hash already exists, it will be replaced.
+    /// Cover art is stored apart from the entry, once per distinct image.
     /// Returns the created/updated cache entry.
     ///
     /// # Arguments
@@ -269,8 +368,32 @@ impl AudioCache {
         debug!("Upserting audio cache entry for resource_hash: {}", new_entry.resource_hash);
 
         let entry: AudioCacheEntry = new_entry.into();
+        if !self.enabled {
+            return Ok(entry);
+        }
         let internal: AudioCacheEntryInternal = entry.clone().into();
 
+        if let (Some(hash), Some(cover_art)) = (&internal.cover_art_hash, &entry.metadata.cover_art) {
+            let art = AudioCoverArtInternal {
+                hash: hash.clone(),
+                data: general_purpose::STANDARD.encode(cover_art),
+            };
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE audio_cover_art WHERE hash = $hash;
+                    INSERT INTO audio_cover_art $art;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hash", hash.clone()))
+                .bind(("art", art))
+                .await
+                .and_then(surrealdb::Response::check)
+                .map_err(|e| CacheError::QueryFailed(format!("Failed to store cover art: {}", e)))?;
+        }
+
         // Delete existing entry with same resource_hash to ensure upsert behavior
         self.db
             .query("DELETE FROM audio_cache WHERE resource_hash = $resource_hash")
@@ -293,7 +416,7 @@ impl AudioCache {
 
     /// Clear all audio cache entries
     ///
-    /// This deletes all entries from the audio_cache table.
+    /// This deletes all entries from the audio_cache table, and their cover art.
     ///
     /// # Examples
     ///
@@ -311,10 +434,13 @@ impl AudioCache {
     /// ```
     #[instrument(skip(self))]
     pub async fn clear(&self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
         info!("Clearing all audio cache entries");
 
         self.db
-            .query("DELETE FROM audio_cache")
+            .query("DELETE FROM audio_cache; DELETE FROM audio_cover_art;")
             .await
             .map_err(|e| CacheError::QueryFailed(format!("Failed to clear audio cache: {}", e)))?;
 
@@ -375,6 +501,8 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
         };
 
@@ -412,6 +540,8 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
         };
 
@@ -443,6 +573,8 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
         };
         cache.upsert(entry1).await.unwrap();
@@ -461,6 +593,8 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
         };
         cache.upsert(entry2).await.unwrap();
@@ -525,4 +659,44 @@ mod tests {
         assert_eq!(entry.source_type, "remote");
         assert_eq!(entry.source, "https://example.com/audio.mp3");
     }
+
+    #[tokio::test]
+    async fn test_chapters_and_cover_art_round_trip() {
+        let db = setup_test_db().await;
+        let cache = AudioCache::new(db.clone());
+
+        let metadata = AudioMetadata {
+            cover_art: Some(b"\xFF\xD8\xFFcover".to_vec()),
+            chapters: vec![
+                AudioChapter { title: "Intro".to_string(), start_ms: 0, end_ms: 90_000 },
+                AudioChapter { title: "Interview".to_string(), start_ms: 90_000, end_ms: 200_000 },
+            ],
+            ..Default::default()
+        };
+        for (resource_hash, content_hash) in [("episode_1", "content_1"), ("episode_2", "content_2")] {
+            cache
+                .upsert(NewAudioCacheEntry {
+                    resource_hash: resource_hash.to_string(),
+                    content_hash: content_hash.to_string(),
+                    source: AudioSource::Local(PathBuf::from(format!("{}.mp3", resource_hash))),
+                    format: AudioFormat::Mp3,
+                    metadata: metadata.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let entry = cache.get("episode_1", "content_1").await.unwrap().expect("cache hit");
+        assert_eq!(entry.metadata.chapters, metadata.chapters);
+        assert_eq!(entry.metadata.cover_art, metadata.cover_art);
+
+        // Identical art is stored once
+        let mut result = db.query("SELECT count() AS count FROM audio_cover_art GROUP ALL").await.unwrap();
+        let count: Option<usize> = result.take("count").unwrap();
+        assert_eq!(count, Some(1));
+
+        // An entry whose art has gone missing is a miss
+        db.query("DELETE audio_cover_art").await.unwrap();
+        assert!(cache.get("episode_1", "content_1").await.unwrap().is_none());
+    }
 }
@@ -15,17 +15,22 @@
 //!     path: "audio/abc123.mp3".to_string(),
 //!     base64_data: None,
 //!     display_name: "Podcast Episode".to_string(),
+//!     alternates: Vec::new(),
+//!     captions: None,
+//!     warnings: Vec::new(),
 //! };
 //!
 //! let options = AudioHtmlOptions {
 //!     inline: false,
 //!     class: None,
+//!     show_chapters: true,
 //! };
 //!
 //! let html = generate_audio_html(&output, &options);
 //! ```
 
-use crate::audio::types::AudioOutput;
+use crate::audio::types::{AudioChapter, AudioOutput};
+use base64::{engine::general_purpose, Engine as _};
 
 /// Options for HTML generation
 #[derive(Debug, Clone, Default)]
@@ -34,10 +39,47 @@ pub struct AudioHtmlOptions {
     pub inline: bool,
     /// Optional custom CSS class to add to the audio player container
     pub class: Option<String>,
+    /// Whether to list the audio's chapters below the player, each seeking
+    /// the player to the chapter's start when clicked (see
+    /// [`audio_chapters_js`])
+    pub show_chapters: bool,
+}
+
+/// Seeks the player when one of its chapter links is clicked
+///
+/// One listener on the document handles every player, so the links carry
+/// only their start time, in seconds, as `data-start`.
+const AUDIO_CHAPTERS_JS: &str = r#"
+document.addEventListener("click", (event) => {
+  const link = event.target.closest(".audio-chapters a[data-start]");
+  const audio = link && link.closest(".audio-player")?.querySelector("audio");
+  if (!audio) return;
+  event.preventDefault();
+  audio.currentTime = Number(link.dataset.start);
+});
+"#;
+
+/// Returns the default stylesheet for audio players
+///
+/// Colors come from the component theme's `--dm-*` variables, so pages
+/// include [`component_theme_css`](crate::render::component_theme_css) too.
+pub fn audio_player_css() -> &'static str {
+    include_str!("../../assets/audio-player.css")
+}
+
+/// Returns the script that makes chapter links seek their player
+///
+/// Rendered documents include it once when any player lists chapters.
+pub fn audio_chapters_js() -> &'static str {
+    AUDIO_CHAPTERS_JS
 }
 
 /// Generate HTML5 audio player markup from processed audio output
 ///
+/// Cover art in the metadata is embedded in the player as a data URI, in
+/// both modes. With `options.show_chapters`, any chapters are listed in an
+/// `<ol class="audio-chapters">` below the player.
+///
 /// # Arguments
 ///
 /// * `output` - The processed audio output containing format, metadata, and path/data
@@ -62,6 +104,9 @@ pub struct AudioHtmlOptions {
 ///     path: "audio/abc123.mp3".to_string(),
 ///     base64_data: None,
 ///     display_name: "Episode 1".to_string(),
+///     alternates: Vec::new(),
+///     captions: None,
+///     warnings: Vec::new(),
 /// };
 ///
 /// let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -105,26 +150,99 @@ pub fn generate_audio_html(output: &AudioOutput, options: &AudioHtmlOptions) ->
     // Escape display name to prevent XSS
     let display_name = html_escape(&output.display_name);
 
+    // The browser plays the first source it supports, so the primary goes first
+    let mut sources = format!(r#"<source src="{}" type="{}">"#, src, output.format.mime_type());
+    if !options.inline {
+        for alternate in &output.alternates {
+            sources.push_str(&format!(
+                r#"
+    <source src="{}" type="{}">"#,
+                html_escape(&alternate.path),
+                alternate.format.mime_type()
+            ));
+        }
+    }
+
+    let track = match &output.captions {
+        Some(captions) => format!(
+            r#"
+    <track kind="captions" src="{}" srclang="{}" default>"#,
+            html_escape(&captions.path),
+            html_escape(&captions.language)
+        ),
+        None => String::new(),
+    };
+
+    let cover_html = match &output.metadata.cover_art {
+        Some(cover_art) => format!(
+            r#"
+  <img class="audio-cover" src="data:{};base64,{}" alt="{}">"#,
+            cover_art_mime_type(cover_art),
+            general_purpose::STANDARD.encode(cover_art),
+            display_name
+        ),
+        None => String::new(),
+    };
+
+    let chapters_html = if options.show_chapters && !output.metadata.chapters.is_empty() {
+        chapters_html(&output.metadata.chapters)
+    } else {
+        String::new()
+    };
+
     // Generate HTML structure
     format!(
-        r#"<div class="{}">
+        r#"<div class="{}">{}
   <audio controls preload="metadata">
-    <source src="{}" type="{}">
+    {}{}
     Your browser does not support the audio element.
   </audio>
   <div class="audio-info">
     <span class="audio-name">{}</span>
     {}
-  </div>
+  </div>{}
 </div>"#,
         container_class,
-        src,
-        output.format.mime_type(),
+        cover_html,
+        sources,
+        track,
         display_name,
-        duration_html
+        duration_html,
+        chapters_html
     )
 }
 
+/// Chapter navigation: a list of links that seek the container's player
+/// through [`audio_chapters_js`]
+fn chapters_html(chapters: &[AudioChapter]) -> String {
+    let items: String = chapters
+        .iter()
+        .map(|chapter| {
+            format!(
+                r##"
+    <li><a href="#" data-start="{}">{}</a></li>"##,
+                chapter.start_ms as f64 / 1000.0,
+                html_escape(&chapter.title)
+            )
+        })
+        .collect();
+    format!(
+        r#"
+  <ol class="audio-chapters">{}
+  </ol>"#,
+        items
+    )
+}
+
+/// MIME type of cover art: PNG by its signature, otherwise JPEG
+fn cover_art_mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    }
+}
+
 /// Escape HTML special characters to prevent XSS attacks
 ///
 /// This function escapes the following characters:
@@ -166,7 +284,8 @@ pub fn html_escape(s: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::types::{AudioFormat, AudioMetadata};
+    use crate::audio::types::{AudioAlternate, AudioChapter, AudioFormat, AudioMetadata};
+    use crate::types::Captions;
 
     #[test]
     fn html_escape_preserves_safe_characters() {
@@ -233,15 +352,21 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
             path: "audio/abc123.mp3".to_string(),
             base64_data: None,
             display_name: "Test Audio".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: false,
             class: None,
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -255,6 +380,39 @@ mod tests {
         assert!(html.contains(r#"<span class="audio-duration">2:03</span>"#));
     }
 
+    #[test]
+    fn generate_audio_html_cover_art_and_chapters() {
+        let output = AudioOutput {
+            format: AudioFormat::Mp3,
+            metadata: AudioMetadata {
+                cover_art: Some(b"\x89PNG\r\n".to_vec()),
+                chapters: vec![
+                    AudioChapter { title: "Intro".to_string(), start_ms: 0, end_ms: 90_500 },
+                    AudioChapter { title: "Q&A".to_string(), start_ms: 90_500, end_ms: 200_000 },
+                ],
+                ..Default::default()
+            },
+            path: "audio/episode.mp3".to_string(),
+            base64_data: None,
+            display_name: "Episode 12".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
+        };
+
+        let html = generate_audio_html(&output, &AudioHtmlOptions::default());
+        assert!(html.contains(r#"<img class="audio-cover" src="data:image/png;base64,iVBORw0K" alt="Episode 12">"#));
+        assert!(!html.contains("audio-chapters"));
+
+        let options = AudioHtmlOptions { show_chapters: true, ..Default::default() };
+        let html = generate_audio_html(&output, &options);
+        assert!(html.contains(r#"<ol class="audio-chapters">"#));
+        assert!(html.contains(r##"<a href="#" data-start="0">Intro</a></li>"##));
+        assert!(html.contains(r##"<a href="#" data-start="90.5">Q&amp;A</a></li>"##));
+        assert!(!html.contains("onclick"));
+        assert!(html.find("</audio>") < html.find("audio-chapters"));
+    }
+
     #[test]
     fn generate_audio_html_inline_mode_with_base64() {
         let output = AudioOutput {
@@ -266,11 +424,15 @@ mod tests {
             path: "audio/def456.wav".to_string(),
             base64_data: Some("AAAABBBBCCCC".to_string()),
             display_name: "Short Clip".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: true,
             class: None,
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -290,11 +452,15 @@ mod tests {
             path: "audio/fallback.mp3".to_string(),
             base64_data: None, // No base64 data available
             display_name: "Fallback".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: true,
             class: None,
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -303,6 +469,47 @@ mod tests {
         assert!(html.contains(r#"src="audio/fallback.mp3""#));
     }
 
+    #[test]
+    fn generate_audio_html_lists_alternate_sources() {
+        let output = AudioOutput {
+            format: AudioFormat::Mp3,
+            metadata: AudioMetadata::default(),
+            path: "audio/abc123.mp3".to_string(),
+            base64_data: Some("AAAA".to_string()),
+            display_name: "Test".to_string(),
+            alternates: vec![
+                AudioAlternate {
+                    format: AudioFormat::Ogg,
+                    path: "audio/abc123.ogg".to_string(),
+                },
+                AudioAlternate {
+                    format: AudioFormat::Flac,
+                    path: "audio/abc123.flac".to_string(),
+                },
+            ],
+            captions: None,
+            warnings: Vec::new(),
+        };
+
+        let html = generate_audio_html(&output, &AudioHtmlOptions::default());
+
+        let mp3 = html.find(r#"<source src="audio/abc123.mp3" type="audio/mpeg">"#).unwrap();
+        let ogg = html.find(r#"<source src="audio/abc123.ogg" type="audio/ogg">"#).unwrap();
+        let flac = html.find(r#"<source src="audio/abc123.flac" type="audio/flac">"#).unwrap();
+        assert!(mp3 < ogg && ogg < flac);
+
+        // An inline player embeds its only source
+        let inline = generate_audio_html(
+            &output,
+            &AudioHtmlOptions {
+                inline: true,
+                class: None,
+                show_chapters: false,
+            },
+        );
+        assert_eq!(inline.matches("<source").count(), 1);
+    }
+
     #[test]
     fn generate_audio_html_custom_class() {
         let output = AudioOutput {
@@ -311,11 +518,15 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "Test".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: false,
             class: Some("custom-player".to_string()),
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -331,6 +542,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: r#"<script>alert("XSS")</script>"#.to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -348,6 +562,9 @@ mod tests {
             path: r#"audio/test" onclick="alert('XSS')".mp3"#.to_string(),
             base64_data: None,
             display_name: "Test".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -365,11 +582,15 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "Test".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: false,
             class: Some(r#"malicious" onclick="alert('XSS')""#.to_string()),
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -389,6 +610,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "Zero Duration".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -406,6 +630,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "59 Seconds".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -423,6 +650,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "One Minute".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -440,6 +670,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "Long Audio".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -457,6 +690,9 @@ mod tests {
             path: "audio/test.mp3".to_string(),
             base64_data: None,
             display_name: "No Duration".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -476,10 +712,15 @@ mod tests {
                 title: Some("Test Track".to_string()),
                 artist: Some("Test Artist".to_string()),
                 album: Some("Test Album".to_string()),
+                cover_art: None,
+                chapters: Vec::new(),
             },
             path: "audio/abc123def456.mp3".to_string(),
             base64_data: None,
             display_name: "Test Track".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
@@ -498,15 +739,21 @@ mod tests {
                 title: None,
                 artist: None,
                 album: None,
+                cover_art: None,
+                chapters: Vec::new(),
             },
             path: "audio/short.wav".to_string(),
             base64_data: Some("VGVzdEJhc2U2NERhdGE=".to_string()),
             display_name: "Short Sound Effect".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: true,
             class: None,
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -524,11 +771,15 @@ mod tests {
             path: "audio/podcast.mp3".to_string(),
             base64_data: None,
             display_name: "Podcast Episode 1".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let options = AudioHtmlOptions {
             inline: false,
             class: Some("podcast-player dark-theme".to_string()),
+            show_chapters: false,
         };
 
         let html = generate_audio_html(&output, &options);
@@ -546,9 +797,32 @@ mod tests {
             path: "audio/unknown.mp3".to_string(),
             base64_data: None,
             display_name: "Unknown Duration".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
 
         let html = generate_audio_html(&output, &AudioHtmlOptions::default());
         insta::assert_snapshot!(html);
     }
+
+    #[test]
+    fn generate_audio_html_adds_captions_track() {
+        let output = AudioOutput {
+            format: AudioFormat::Mp3,
+            metadata: AudioMetadata::default(),
+            path: "audio/abc123.mp3".to_string(),
+            base64_data: None,
+            display_name: "Interview".to_string(),
+            alternates: Vec::new(),
+            captions: Some(Captions { path: "audio/def456.vtt".to_string(), language: "fr".to_string() }),
+            warnings: Vec::new(),
+        };
+
+        let html = generate_audio_html(&output, &AudioHtmlOptions::default());
+
+        let source = html.find("<source").unwrap();
+        let track = html.find(r#"<track kind="captions" src="audio/def456.vtt" srclang="fr" default>"#).unwrap();
+        assert!(source < track && track < html.find("</audio>").unwrap(), "{}", html);
+    }
 }
@@ -3,21 +3,34 @@
 //! This module provides functions for extracting metadata from audio files,
 //! including duration, bitrate, sample rate, channels, and ID3 tags.
 
-use crate::audio::types::{AudioFormat, AudioMetadata, AudioSource};
+use crate::audio::types::{AudioChapter, AudioFormat, AudioMetadata, AudioSource};
 use crate::error::AudioError;
+use crate::graph::utils::xxh3_reader;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
 use std::fs;
-use std::io::Cursor;
+use std::io::{Cursor, Read, SeekFrom};
+use std::path::Path;
 use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardVisualKey};
 use symphonia::core::probe::Hint;
+use tracing::{debug, warn};
 use xxhash_rust::xxh3::xxh3_64;
 
+/// Audio downloaded from a URL
+#[derive(Debug, Clone)]
+pub struct RemoteAudio {
+    /// The response body
+    pub bytes: Vec<u8>,
+    /// The response's `Content-Type` header, if it sent one
+    pub content_type: Option<String>,
+}
+
 /// Load audio file bytes from a source
 ///
 /// For local files, this reads the file contents and validates the path doesn't
-/// escape the project scope via symlinks. For remote URLs, this returns an error
-/// as remote fetching is not yet implemented.
+/// escape the project scope via symlinks. Remote URLs are downloaded with
+/// [`fetch_remote_audio`], without a size limit or caching.
 ///
 /// # Arguments
 ///
@@ -30,7 +43,7 @@ use xxhash_rust::xxh3::xxh3_64;
 /// # Errors
 ///
 /// Returns `AudioError::ReadFailed` if the file cannot be read.
-/// Returns `AudioError::FetchFailed` if the source is a remote URL.
+/// Returns `AudioError::FetchFailed` if a remote URL cannot be downloaded.
 /// Returns `AudioError::InvalidData` if the path escapes project scope via symlinks.
 ///
 /// # Examples
@@ -66,24 +79,122 @@ pub fn load_audio_bytes(source: &AudioSource) -> Result<(Vec<u8>, String), Audio
 
             Ok((bytes, filename))
         }
-        AudioSource::Remote(url) => Err(AudioError::FetchFailed {
-            url: url.clone(),
-        }),
+        AudioSource::Remote(url) => {
+            let remote = fetch_remote_audio(url, None)?;
+            Ok((remote.bytes, url_filename(url)))
+        }
     }
 }
 
+/// Name of the file a URL points to: the last segment of its path
+pub(crate) fn url_filename(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.path_segments()?.next_back().map(str::to_string))
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Canonical form of a remote audio URL, used as its download cache key
+///
+/// Parsing lowercases the scheme and host, drops default ports and normalizes
+/// percent-encoding, so different spellings of one URL share an entry. The
+/// fragment never reaches the server and is removed.
+///
+/// # Errors
+///
+/// Returns `AudioError::FetchFailed` if `url` is not a valid URL.
+pub fn canonicalize_audio_url(url: &str) -> Result<String, AudioError> {
+    let mut parsed = url::Url::parse(url).map_err(|_| AudioError::FetchFailed {
+        url: url.to_string(),
+    })?;
+    parsed.set_fragment(None);
+    Ok(parsed.into())
+}
+
+/// Download audio from a URL
+///
+/// The request's `Accept` header lists the MIME type of every supported
+/// format, and at most five redirects are followed. The body is read straight
+/// into memory as bytes; with a `max_file_size`, a `Content-Length` above it
+/// fails before the download starts, and a body that outgrows it without one
+/// fails once it does.
+///
+/// # Errors
+///
+/// Returns `AudioError::FetchFailed` if the request fails or the server
+/// responds with an error status.
+/// Returns `AudioError::FileTooLarge` if the audio is larger than `max_file_size`.
+pub fn fetch_remote_audio(url: &str, max_file_size: Option<u64>) -> Result<RemoteAudio, AudioError> {
+    let fetch_failed = |reason: String| {
+        warn!(url, reason = %reason, "Failed to fetch remote audio");
+        AudioError::FetchFailed { url: url.to_string() }
+    };
+
+    let accept = AudioFormat::ALL
+        .iter()
+        .map(AudioFormat::mime_type)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let client = crate::http::blocking_client().map_err(fetch_failed)?;
+
+    let response = client
+        .get(url)
+        .header(ACCEPT, accept)
+        .send()
+        .map_err(|e| fetch_failed(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(fetch_failed(format!("HTTP {}", response.status())));
+    }
+
+    let content_length = response.content_length();
+    if let (Some(size), Some(max_size)) = (content_length, max_file_size) {
+        if size > max_size {
+            return Err(AudioError::FileTooLarge { size, max_size });
+        }
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    // One byte past the limit is enough to know the body is too large
+    let limit = max_file_size.map_or(u64::MAX, |max_size| max_size.saturating_add(1));
+    let mut bytes = Vec::with_capacity(content_length.unwrap_or(0).min(limit) as usize);
+    response
+        .take(limit)
+        .read_to_end(&mut bytes)
+        .map_err(|e| fetch_failed(e.to_string()))?;
+    if let Some(max_size) = max_file_size {
+        if bytes.len() as u64 > max_size {
+            return Err(AudioError::FileTooLarge {
+                size: bytes.len() as u64,
+                max_size,
+            });
+        }
+    }
+    debug!(url, size_bytes = bytes.len(), "Fetched remote audio");
+
+    Ok(RemoteAudio { bytes, content_type })
+}
+
 /// Detect audio format from source and file bytes
 ///
 /// This performs a two-stage detection:
-/// 1. Extension-based detection from the source path/URL
+/// 1. The claimed format: from `content_type` when it names an audio format,
+///    otherwise from the extension of the source path/URL
 /// 2. Magic byte validation (MP3: ID3 or MPEG sync, WAV: RIFF header)
 ///
-/// For security, this function errors if the extension doesn't match the magic bytes.
+/// For security, this function errors if the claimed format doesn't match the
+/// magic bytes.
 ///
 /// # Arguments
 ///
 /// * `source` - The audio source (for extension detection)
 /// * `bytes` - The audio file bytes (for magic byte detection)
+/// * `content_type` - The HTTP `Content-Type` of remote audio, if known
 ///
 /// # Returns
 ///
@@ -92,7 +203,7 @@ pub fn load_audio_bytes(source: &AudioSource) -> Result<(Vec<u8>, String), Audio
 /// # Errors
 ///
 /// Returns `AudioError::UnsupportedFormat` if the format cannot be detected.
-/// Returns `AudioError::InvalidData` if extension and magic bytes mismatch.
+/// Returns `AudioError::InvalidData` if the claimed format and magic bytes mismatch.
 ///
 /// # Examples
 ///
@@ -103,12 +214,13 @@ pub fn load_audio_bytes(source: &AudioSource) -> Result<(Vec<u8>, String), Audio
 ///
 /// let source = AudioSource::Local(PathBuf::from("test.mp3"));
 /// let bytes = vec![0x49, 0x44, 0x33]; // ID3 header
-/// let format = detect_audio_format(&source, &bytes).unwrap();
+/// let format = detect_audio_format(&source, &bytes, None).unwrap();
 /// assert_eq!(format, AudioFormat::Mp3);
 /// ```
 pub fn detect_audio_format(
     source: &AudioSource,
     bytes: &[u8],
+    content_type: Option<&str>,
 ) -> Result<AudioFormat, AudioError> {
     // Get extension from source
     let extension = match source {
@@ -125,32 +237,37 @@ pub fn detect_audio_format(
         }
     };
 
-    // Detect format from extension
-    let format_from_ext = extension
-        .as_deref()
-        .and_then(AudioFormat::from_extension);
+    // Detect format from Content-Type, then extension
+    let format_from_content_type = content_type.and_then(AudioFormat::from_mime_type);
+    let (claimed_format, claimed_by) = match format_from_content_type {
+        Some(format) => (Some(format), "Content-Type"),
+        None => (
+            extension.as_deref().and_then(AudioFormat::from_extension),
+            "extension",
+        ),
+    };
 
     // Detect format from magic bytes
     let format_from_magic = detect_format_from_magic_bytes(bytes);
 
-    // Match extension with magic bytes
-    match (format_from_ext, format_from_magic) {
-        (Some(ext_format), Some(magic_format)) => {
-            if ext_format == magic_format {
-                Ok(ext_format)
+    // Match the claimed format with magic bytes
+    match (claimed_format, format_from_magic) {
+        (Some(claimed_format), Some(magic_format)) => {
+            if claimed_format == magic_format {
+                Ok(claimed_format)
             } else {
                 Err(AudioError::InvalidData(format!(
-                    "Extension/magic byte mismatch: extension indicates {:?}, but magic bytes indicate {:?}",
-                    ext_format, magic_format
+                    "{claimed_by}/magic byte mismatch: {claimed_by} indicates {:?}, but magic bytes indicate {:?}",
+                    claimed_format, magic_format
                 )))
             }
         }
-        (Some(ext_format), None) => {
-            // Trust extension if no magic bytes detected
-            Ok(ext_format)
+        (Some(claimed_format), None) => {
+            // Trust the claim if no magic bytes detected
+            Ok(claimed_format)
         }
         (None, Some(magic_format)) => {
-            // Trust magic bytes if no extension available
+            // Trust magic bytes if nothing claims a format
             Ok(magic_format)
         }
         (None, None) => Err(AudioError::UnsupportedFormat {
@@ -167,6 +284,16 @@ pub fn detect_audio_format(
 ///
 /// # WAV Detection
 /// - RIFF header: starts with "RIFF" (0x52 0x49 0x46 0x46)
+///
+/// # Ogg Detection
+/// - Page capture pattern: starts with "OggS" (0x4F 0x67 0x67 0x53)
+///
+/// # FLAC Detection
+/// - Stream marker: starts with "fLaC" (0x66 0x4C 0x61 0x43)
+///
+/// # AAC Detection
+/// - ADTS sync: starts with 0xFF 0xF1 (MPEG-4) or 0xFF 0xF9 (MPEG-2). MP4
+///   (`.m4a`) files have no magic bytes of their own and go by extension.
 fn detect_format_from_magic_bytes(bytes: &[u8]) -> Option<AudioFormat> {
     if bytes.len() < 4 {
         return None;
@@ -187,6 +314,21 @@ fn detect_format_from_magic_bytes(bytes: &[u8]) -> Option<AudioFormat> {
         return Some(AudioFormat::Wav);
     }
 
+    // Check for Ogg: page capture pattern
+    if bytes[0..4] == *b"OggS" {
+        return Some(AudioFormat::Ogg);
+    }
+
+    // Check for FLAC: stream marker
+    if bytes[0..4] == *b"fLaC" {
+        return Some(AudioFormat::Flac);
+    }
+
+    // Check for AAC: ADTS sync bytes
+    if bytes[0] == 0xFF && (bytes[1] == 0xF1 || bytes[1] == 0xF9) {
+        return Some(AudioFormat::Aac);
+    }
+
     None
 }
 
@@ -215,7 +357,23 @@ fn detect_format_from_magic_bytes(bytes: &[u8]) -> Option<AudioFormat> {
 /// ```
 pub fn compute_content_hash(bytes: &[u8]) -> String {
     let hash = xxh3_64(bytes);
-    format!("{:x}", hash)
+    format!("{:016x}", hash)
+}
+
+/// Compute the content hash of an audio file without loading it into memory
+///
+/// Produces the same value as [`compute_content_hash`] on the file's bytes.
+///
+/// # Errors
+///
+/// Returns `AudioError::ReadFailed` if the file cannot be read.
+pub fn compute_file_content_hash(path: &Path) -> Result<String, AudioError> {
+    let hash = fs::File::open(path)
+        .and_then(xxh3_reader)
+        .map_err(|_| AudioError::ReadFailed {
+            path: path.display().to_string(),
+        })?;
+    Ok(format!("{:016x}", hash))
 }
 
 /// Extract audio metadata from bytes using Symphonia
@@ -226,6 +384,8 @@ pub fn compute_content_hash(bytes: &[u8]) -> String {
 /// - Sample rate
 /// - Number of channels
 /// - ID3 tags (title, artist, album) if present
+/// - Cover art, preferring a front cover over other attached pictures
+/// - Chapters from the ID3v2 `CHAP` frames of MP3 files
 ///
 /// If metadata extraction fails, this function returns default values
 /// (graceful degradation) rather than failing completely.
@@ -261,8 +421,44 @@ pub fn extract_audio_metadata(
     // Create a MediaSourceStream from owned bytes
     // We need to clone the bytes to satisfy Symphonia's 'static lifetime requirement
     let owned_bytes = bytes.to_vec();
-    let cursor = Cursor::new(owned_bytes);
-    let mss = MediaSourceStream::new(Box::new(cursor), Default::default());
+    extract_metadata_from_source(Box::new(Cursor::new(owned_bytes)), format)
+}
+
+/// Extract audio metadata by reading a file through Symphonia
+///
+/// Equivalent to [`extract_audio_metadata`] but streams the file rather than
+/// requiring its contents in memory, which keeps large files cheap to probe.
+///
+/// # Errors
+///
+/// Returns `AudioError::ReadFailed` if the file cannot be opened, otherwise
+/// the same errors as [`extract_audio_metadata`].
+pub fn extract_audio_metadata_from_file(
+    path: &Path,
+    format: AudioFormat,
+) -> Result<AudioMetadata, AudioError> {
+    let file = fs::File::open(path).map_err(|_| AudioError::ReadFailed {
+        path: path.display().to_string(),
+    })?;
+    extract_metadata_from_source(Box::new(file), format)
+}
+
+/// Probe a media source and collect its metadata
+fn extract_metadata_from_source(
+    mut source: Box<dyn MediaSource>,
+    format: AudioFormat,
+) -> Result<AudioMetadata, AudioError> {
+    // Symphonia skips `CHAP` frames, so read them from the tag ourselves
+    let chapters = if format == AudioFormat::Mp3 {
+        let tag = read_id3v2_tag(&mut *source).map_err(|e| AudioError::MetadataFailed {
+            reason: format!("Failed to read ID3v2 tag: {}", e),
+        })?;
+        parse_id3v2_chapters(&tag)
+    } else {
+        Vec::new()
+    };
+
+    let mss = MediaSourceStream::new(source, Default::default());
 
     // Create a hint for the format
     let mut hint = Hint::new();
@@ -272,7 +468,7 @@ pub fn extract_audio_metadata(
     let format_opts = FormatOptions::default();
     let metadata_opts = MetadataOptions::default();
 
-    let probed = symphonia::default::get_probe()
+    let mut probed = symphonia::default::get_probe()
         .format(&hint, mss, &format_opts, &metadata_opts)
         .map_err(|e| AudioError::MetadataFailed {
             reason: format!("Failed to probe audio: {}", e),
@@ -298,7 +494,7 @@ pub fn extract_audio_metadata(
 
     // Extract bitrate (Symphonia 0.5 uses bits_per_coded_sample)
     let bitrate = codec_params.bits_per_coded_sample.and_then(|bps| {
-        sample_rate.map(|sr| (bps * sr * channels.unwrap_or(2) as u32))
+        sample_rate.map(|sr| bps * sr * channels.unwrap_or(2) as u32)
     });
 
     // Calculate duration from n_frames and sample_rate
@@ -330,6 +526,14 @@ pub fn extract_audio_metadata(
         }
     }
 
+    // Cover art is in the container's metadata, or for MP3 in the ID3v2 tag
+    // read while probing
+    let cover_art = format_reader
+        .metadata()
+        .current()
+        .and_then(cover_art)
+        .or_else(|| probed.metadata.get()?.current().and_then(cover_art));
+
     Ok(AudioMetadata {
         duration_secs,
         bitrate,
@@ -338,14 +542,196 @@ pub fn extract_audio_metadata(
         title,
         artist,
         album,
+        cover_art,
+        chapters,
     })
 }
 
+/// The front cover of a metadata revision, or else its first picture
+fn cover_art(revision: &MetadataRevision) -> Option<Vec<u8>> {
+    let visuals = revision.visuals();
+    visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+        .map(|visual| visual.data.to_vec())
+}
+
+/// Read the ID3v2 tag at the start of a source, then rewind it
+///
+/// Returns the tag's bytes, header included, or the bytes read while looking
+/// for one when the source doesn't start with a tag.
+fn read_id3v2_tag(source: &mut dyn MediaSource) -> std::io::Result<Vec<u8>> {
+    let mut tag = Vec::new();
+    (&mut *source).take(10).read_to_end(&mut tag)?;
+    if tag.len() == 10 && tag.starts_with(b"ID3") {
+        let size = synchsafe(&tag[6..10]);
+        (&mut *source).take(size as u64).read_to_end(&mut tag)?;
+    }
+    source.seek(SeekFrom::Start(0))?;
+    Ok(tag)
+}
+
+/// Parse the `CHAP` frames of an ID3v2.3 or ID3v2.4 tag
+///
+/// Chapters are returned in order of their start time. Tags of other
+/// versions, unsynchronised tags and malformed frames yield no chapters.
+fn parse_id3v2_chapters(tag: &[u8]) -> Vec<AudioChapter> {
+    let Some(header) = tag.get(..10).filter(|header| header.starts_with(b"ID3")) else {
+        return Vec::new();
+    };
+    let (version, flags) = (header[3], header[5]);
+    if !matches!(version, 3 | 4) || flags & 0x80 != 0 {
+        return Vec::new();
+    }
+
+    let size = synchsafe(&header[6..10]) as usize;
+    let mut frames = tag.get(10..10 + size).unwrap_or(&tag[10..]);
+    if flags & 0x40 != 0 {
+        // Skip the extended header; only v2.4 counts its own size field
+        let Some(size) = frames.get(..4) else {
+            return Vec::new();
+        };
+        let skip = if version == 4 {
+            synchsafe(size) as usize
+        } else {
+            u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize + 4
+        };
+        frames = frames.get(skip..).unwrap_or_default();
+    }
+
+    let mut chapters: Vec<AudioChapter> = id3v2_frames(frames, version)
+        .into_iter()
+        .filter(|(id, _)| id == b"CHAP")
+        .filter_map(|(_, body)| parse_chap_frame(body, version))
+        .collect();
+    chapters.sort_by_key(|chapter| chapter.start_ms);
+    chapters
+}
+
+/// Split ID3v2 frame data into (frame ID, frame body) pairs, up to the padding
+fn id3v2_frames(mut data: &[u8], version: u8) -> Vec<(&[u8], &[u8])> {
+    let mut frames = Vec::new();
+    while data.len() >= 10 && data[0] != 0 {
+        let size = if version == 4 {
+            synchsafe(&data[4..8])
+        } else {
+            u32::from_be_bytes([data[4], data[5], data[6], data[7]])
+        } as usize;
+        let Some(body) = data.get(10..10 + size) else {
+            break;
+        };
+        frames.push((&data[..4], body));
+        data = &data[10 + size..];
+    }
+    frames
+}
+
+/// Parse the body of a `CHAP` frame: an element ID, start and end times and
+/// offsets, then embedded frames of which `TIT2` names the chapter
+fn parse_chap_frame(body: &[u8], version: u8) -> Option<AudioChapter> {
+    let id_end = body.iter().position(|&b| b == 0)?;
+    let element_id = String::from_utf8_lossy(&body[..id_end]);
+    let times = body.get(id_end + 1..id_end + 17)?;
+    let time = |at: usize| u32::from_be_bytes([times[at], times[at + 1], times[at + 2], times[at + 3]]) as u64;
+
+    let title = id3v2_frames(&body[id_end + 17..], version)
+        .into_iter()
+        .find(|(id, _)| id == b"TIT2")
+        .and_then(|(_, text)| decode_id3v2_text(text))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| element_id.into_owned());
+
+    Some(AudioChapter {
+        title,
+        start_ms: time(0),
+        end_ms: time(4),
+    })
+}
+
+/// Decode an ID3v2 text frame body: an encoding byte followed by the text
+fn decode_id3v2_text(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let utf16 = |big_endian: bool, text: &[u8]| {
+        let units: Vec<u16> = text
+            .chunks_exact(2)
+            .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let decoded = match encoding {
+        0 => text.iter().map(|&b| b as char).collect(),
+        1 => match text {
+            [0xFF, 0xFE, rest @ ..] => utf16(false, rest),
+            [0xFE, 0xFF, rest @ ..] => utf16(true, rest),
+            _ => utf16(true, text),
+        },
+        2 => utf16(true, text),
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    Some(decoded.trim_end_matches('\0').to_string())
+}
+
+/// Decode a 28-bit "synchsafe" integer, 7 bits per byte
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, &b| (value << 7) | (b & 0x7F) as u32)
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use proptest::prelude::*;
+    use std::io::Write;
+    use std::net::TcpListener;
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    /// Answer HTTP requests on a local port, one canned response per connection
+    ///
+    /// Returns the server's base URL and the requests received so far. The
+    /// server stops once every response has been sent.
+    pub(crate) fn serve_http(responses: Vec<Vec<u8>>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                received.lock().unwrap().push(String::from_utf8_lossy(&request).to_lowercase());
+                let _ = stream.write_all(&response);
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    /// An HTTP response that closes its connection
+    pub(crate) fn http_response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-length")) {
+            response.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
 
     #[test]
     fn load_audio_bytes_reads_local_mp3() {
@@ -368,15 +754,89 @@ mod tests {
     }
 
     #[test]
-    fn load_audio_bytes_rejects_remote_url() {
-        let source = AudioSource::Remote("https://example.com/audio.mp3".to_string());
-        let result = load_audio_bytes(&source);
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            AudioError::FetchFailed { url } => {
-                assert_eq!(url, "https://example.com/audio.mp3");
-            }
-            _ => panic!("Expected FetchFailed error"),
+    fn load_audio_bytes_fetches_remote_url() {
+        let audio = fs::read("../tests/fixtures/audio/test.wav").unwrap();
+        let (base_url, requests) = serve_http(vec![http_response(
+            "200 OK",
+            &[("Content-Type", "audio/wav")],
+            &audio,
+        )]);
+
+        let source = AudioSource::Remote(format!("{}/episodes/intro.wav?token=1", base_url));
+        let (bytes, filename) = load_audio_bytes(&source).unwrap();
+
+        assert_eq!(bytes, audio);
+        assert_eq!(filename, "intro.wav");
+        assert!(requests.lock().unwrap()[0]
+            .contains("accept: audio/mpeg, audio/wav, audio/ogg, audio/flac, audio/aac"));
+    }
+
+    #[test]
+    fn load_audio_bytes_fails_on_http_error() {
+        let (base_url, _) = serve_http(vec![http_response("404 Not Found", &[], b"missing")]);
+
+        let url = format!("{}/audio.mp3", base_url);
+        let result = load_audio_bytes(&AudioSource::Remote(url.clone()));
+        assert!(matches!(result, Err(AudioError::FetchFailed { url: failed }) if failed == url));
+    }
+
+    #[test]
+    fn fetch_remote_audio_checks_content_length_before_downloading() {
+        let (base_url, _) = serve_http(vec![http_response(
+            "200 OK",
+            &[("Content-Length", "5000")],
+            b"short",
+        )]);
+
+        let result = fetch_remote_audio(&format!("{}/big.mp3", base_url), Some(1000));
+        assert!(matches!(result, Err(AudioError::FileTooLarge { size: 5000, max_size: 1000 })));
+    }
+
+    #[test]
+    fn fetch_remote_audio_follows_at_most_five_redirects() {
+        let redirect = || http_response("302 Found", &[("Location", "/next")], b"");
+        let ok = http_response("200 OK", &[("Content-Type", "audio/mpeg")], b"ID3\x04");
+
+        let (base_url, _) = serve_http(vec![redirect(), redirect(), redirect(), redirect(), redirect(), ok]);
+        let remote = fetch_remote_audio(&format!("{}/start", base_url), None).unwrap();
+        assert_eq!(remote.bytes, b"ID3\x04");
+        assert_eq!(remote.content_type.as_deref(), Some("audio/mpeg"));
+
+        let (base_url, requests) = serve_http((0..7).map(|_| redirect()).collect());
+        let result = fetch_remote_audio(&format!("{}/start", base_url), None);
+        assert!(matches!(result, Err(AudioError::FetchFailed { .. })));
+        assert_eq!(requests.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn canonicalize_audio_url_normalizes_equivalent_urls() {
+        let canonical = canonicalize_audio_url("HTTPS://Example.COM:443/episodes/ep 1.mp3#t=10").unwrap();
+        assert_eq!(canonical, "https://example.com/episodes/ep%201.mp3");
+        assert!(canonicalize_audio_url("not a url").is_err());
+    }
+
+    #[test]
+    fn detect_audio_format_prefers_content_type() {
+        // No extension to go by
+        let source = AudioSource::Remote("https://example.com/download?id=7".to_string());
+        assert_eq!(detect_audio_format(&source, b"OggS\x00\x02", Some("audio/ogg")).unwrap(), AudioFormat::Ogg);
+
+        // Content-Type overrides the extension
+        let source = AudioSource::Remote("https://example.com/episode.mp3".to_string());
+        let wav = [0x52, 0x49, 0x46, 0x46];
+        assert_eq!(detect_audio_format(&source, &wav, Some("audio/x-wav")).unwrap(), AudioFormat::Wav);
+
+        // Unrecognized types fall back to the extension
+        let id3 = [0x49, 0x44, 0x33, 0x04];
+        assert_eq!(
+            detect_audio_format(&source, &id3, Some("application/octet-stream")).unwrap(),
+            AudioFormat::Mp3
+        );
+
+        // Magic bytes still have to agree
+        match detect_audio_format(&source, &wav, Some("audio/mpeg")) {
+            Err(AudioError::InvalidData(msg)) => assert!(msg.contains("Content-Type")),
+            other => panic!("Expected InvalidData error, got {:?}", other),
         }
     }
 
@@ -391,7 +851,7 @@ mod tests {
     fn detect_audio_format_identifies_mp3_by_id3() {
         let source = AudioSource::Local(PathBuf::from("test.mp3"));
         let bytes = vec![0x49, 0x44, 0x33, 0x04, 0x00]; // ID3 header
-        let format = detect_audio_format(&source, &bytes).unwrap();
+        let format = detect_audio_format(&source, &bytes, None).unwrap();
         assert_eq!(format, AudioFormat::Mp3);
     }
 
@@ -399,7 +859,7 @@ mod tests {
     fn detect_audio_format_identifies_mp3_by_sync() {
         let source = AudioSource::Local(PathBuf::from("test.mp3"));
         let bytes = vec![0xFF, 0xFB, 0x90, 0x00]; // MPEG sync
-        let format = detect_audio_format(&source, &bytes).unwrap();
+        let format = detect_audio_format(&source, &bytes, None).unwrap();
         assert_eq!(format, AudioFormat::Mp3);
     }
 
@@ -407,15 +867,29 @@ mod tests {
     fn detect_audio_format_identifies_wav_by_riff() {
         let source = AudioSource::Local(PathBuf::from("test.wav"));
         let bytes = vec![0x52, 0x49, 0x46, 0x46]; // RIFF header
-        let format = detect_audio_format(&source, &bytes).unwrap();
+        let format = detect_audio_format(&source, &bytes, None).unwrap();
         assert_eq!(format, AudioFormat::Wav);
     }
 
+    #[test]
+    fn detect_audio_format_identifies_ogg_flac_and_aac() {
+        let cases: [(&str, &[u8], AudioFormat); 4] = [
+            ("test.ogg", b"OggS\x00\x02", AudioFormat::Ogg),
+            ("test.flac", b"fLaC\x00\x00", AudioFormat::Flac),
+            ("test.aac", &[0xFF, 0xF1, 0x50, 0x80], AudioFormat::Aac),
+            ("test.m4a", &[0xFF, 0xF9, 0x50, 0x80], AudioFormat::Aac),
+        ];
+        for (name, bytes, expected) in cases {
+            let source = AudioSource::Local(PathBuf::from(name));
+            assert_eq!(detect_audio_format(&source, bytes, None).unwrap(), expected, "{}", name);
+        }
+    }
+
     #[test]
     fn detect_audio_format_errors_on_mismatch() {
         let source = AudioSource::Local(PathBuf::from("test.mp3")); // Claims to be MP3
         let bytes = vec![0x52, 0x49, 0x46, 0x46]; // But is WAV
-        let result = detect_audio_format(&source, &bytes);
+        let result = detect_audio_format(&source, &bytes, None);
         assert!(result.is_err());
         match result.unwrap_err() {
             AudioError::InvalidData(msg) => {
@@ -442,6 +916,17 @@ mod tests {
         assert_ne!(hash1, hash2);
     }
 
+    #[test]
+    fn compute_file_content_hash_matches_in_memory_hash() {
+        let path = PathBuf::from("../tests/fixtures/audio/test.mp3");
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(
+            compute_file_content_hash(&path).unwrap(),
+            compute_content_hash(&bytes)
+        );
+        assert!(compute_file_content_hash(Path::new("/nonexistent.mp3")).is_err());
+    }
+
     #[test]
     fn extract_audio_metadata_from_mp3() {
         let source = AudioSource::Local(PathBuf::from("../tests/fixtures/audio/test.mp3"));
@@ -477,6 +962,43 @@ mod tests {
         assert!(meta.sample_rate.is_some());
     }
 
+    #[test]
+    fn extract_audio_metadata_from_ogg() {
+        let source = AudioSource::Local(PathBuf::from("../tests/fixtures/audio/test.ogg"));
+        let (bytes, _) = load_audio_bytes(&source).unwrap();
+        assert_eq!(detect_audio_format(&source, &bytes, None).unwrap(), AudioFormat::Ogg);
+
+        let meta = extract_audio_metadata(&bytes, AudioFormat::Ogg).unwrap();
+        assert_eq!(meta.sample_rate, Some(44100));
+        assert_eq!(meta.channels, Some(2));
+        assert_eq!(meta.title.as_deref(), Some("Test Ogg"));
+    }
+
+    #[test]
+    fn extract_audio_metadata_from_flac() {
+        let source = AudioSource::Local(PathBuf::from("../tests/fixtures/audio/test.flac"));
+        let (bytes, _) = load_audio_bytes(&source).unwrap();
+        assert_eq!(detect_audio_format(&source, &bytes, None).unwrap(), AudioFormat::Flac);
+
+        let meta = extract_audio_metadata(&bytes, AudioFormat::Flac).unwrap();
+        assert_eq!(meta.sample_rate, Some(44100));
+        assert_eq!(meta.channels, Some(2));
+        assert_eq!(meta.title.as_deref(), Some("Test FLAC"));
+        assert_eq!(meta.artist.as_deref(), Some("Composition"));
+        let duration = meta.duration_secs.unwrap();
+        assert!((duration - 1.0).abs() < 0.01, "duration was {}", duration);
+    }
+
+    #[test]
+    fn extract_audio_metadata_from_aac() {
+        let source = AudioSource::Local(PathBuf::from("../tests/fixtures/audio/test.aac"));
+        let (bytes, _) = load_audio_bytes(&source).unwrap();
+        assert_eq!(detect_audio_format(&source, &bytes, None).unwrap(), AudioFormat::Aac);
+
+        let meta = extract_audio_metadata(&bytes, AudioFormat::Aac).unwrap();
+        assert_eq!(meta.sample_rate, Some(44100));
+    }
+
     #[test]
     fn extract_audio_metadata_handles_corrupted_data() {
         let bytes = vec![0xFF, 0xFB, 0x00, 0x00, 0x00]; // Invalid MP3 data
@@ -486,6 +1008,94 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// An ID3v2 frame: ID, size (synchsafe in v2.4), flags and body
+    fn id3v2_frame(id: &[u8], body: &[u8], version: u8) -> Vec<u8> {
+        let size = body.len() as u32;
+        let size = if version == 4 {
+            [(size >> 21) as u8 & 0x7F, (size >> 14) as u8 & 0x7F, (size >> 7) as u8 & 0x7F, size as u8 & 0x7F]
+        } else {
+            size.to_be_bytes()
+        };
+        [id, &size, &[0, 0], body].concat()
+    }
+
+    /// A `CHAP` frame, titled by a UTF-8 `TIT2` frame when `title` is given
+    fn chap_frame(element_id: &str, start_ms: u32, end_ms: u32, title: Option<&[u8]>, version: u8) -> Vec<u8> {
+        let mut body = [element_id.as_bytes(), &[0]].concat();
+        for value in [start_ms, end_ms, u32::MAX, u32::MAX] {
+            body.extend_from_slice(&value.to_be_bytes());
+        }
+        if let Some(title) = title {
+            body.extend(id3v2_frame(b"TIT2", title, version));
+        }
+        id3v2_frame(b"CHAP", &body, version)
+    }
+
+    /// An ID3v2 tag holding `frames`, followed by some padding
+    fn id3v2_tag(version: u8, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = frames.concat();
+        data.extend_from_slice(&[0; 16]);
+        let size = data.len() as u32;
+        let mut tag = vec![b'I', b'D', b'3', version, 0, 0];
+        tag.extend([(size >> 21) as u8 & 0x7F, (size >> 14) as u8 & 0x7F, (size >> 7) as u8 & 0x7F, size as u8 & 0x7F]);
+        tag.extend(data);
+        tag
+    }
+
+    #[test]
+    fn parse_id3v2_chapters_reads_chap_frames() {
+        let tag = id3v2_tag(4, &[
+            id3v2_frame(b"TIT2", b"\x03Episode 12", 4),
+            chap_frame("ch1", 90_000, 200_000, Some(b"\x03Interview"), 4),
+            chap_frame("ch0", 0, 90_000, Some(b"\x03Intro"), 4),
+            chap_frame("outro", 200_000, 215_000, None, 4),
+        ]);
+
+        let chapters = parse_id3v2_chapters(&tag);
+        let expected = [("Intro", 0, 90_000), ("Interview", 90_000, 200_000), ("outro", 200_000, 215_000)];
+        assert_eq!(chapters.len(), expected.len());
+        for (chapter, (title, start_ms, end_ms)) in chapters.iter().zip(expected) {
+            assert_eq!((chapter.title.as_str(), chapter.start_ms, chapter.end_ms), (title, start_ms, end_ms));
+        }
+    }
+
+    #[test]
+    fn parse_id3v2_chapters_decodes_v23_utf16_titles() {
+        let title: Vec<u8> = [1, 0xFF, 0xFE].into_iter().chain("Café".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let tag = id3v2_tag(3, &[chap_frame("c", 5_000, 10_000, Some(&title), 3)]);
+
+        let chapters = parse_id3v2_chapters(&tag);
+        assert_eq!(chapters, [AudioChapter { title: "Café".to_string(), start_ms: 5_000, end_ms: 10_000 }]);
+    }
+
+    #[test]
+    fn parse_id3v2_chapters_ignores_missing_or_malformed_tags() {
+        assert!(parse_id3v2_chapters(b"").is_empty());
+        assert!(parse_id3v2_chapters(&[0xFF, 0xFB, 0x90, 0x00]).is_empty());
+
+        // A CHAP frame claiming more bytes than the tag has
+        let mut tag = id3v2_tag(4, &[chap_frame("c", 0, 1_000, None, 4)]);
+        tag[17] = 0x7F;
+        assert!(parse_id3v2_chapters(&tag).is_empty());
+    }
+
+    #[test]
+    fn extract_audio_metadata_reads_mp3_chapters_and_cover_art() {
+        let cover = b"\x89PNG\r\n\x1a\nnot really a picture".to_vec();
+        let apic = [&b"\x03image/png\x00\x03Cover\x00"[..], &cover].concat();
+        let tag = id3v2_tag(4, &[
+            id3v2_frame(b"APIC", &apic, 4),
+            chap_frame("c", 0, 500, Some(b"\x03Start"), 4),
+        ]);
+        // Replace the fixture's own, empty, tag
+        let audio = fs::read("../tests/fixtures/audio/test.mp3").unwrap();
+        let bytes = [&tag[..], &audio[10..]].concat();
+
+        let meta = extract_audio_metadata(&bytes, AudioFormat::Mp3).unwrap();
+        assert_eq!(meta.cover_art, Some(cover));
+        assert_eq!(meta.chapters, [AudioChapter { title: "Start".to_string(), start_ms: 0, end_ms: 500 }]);
+    }
+
     // Property-based test: hash determinism
     proptest! {
         #[test]
@@ -498,7 +1108,8 @@ mod tests {
         #[test]
         fn prop_compute_content_hash_produces_hex_string(data: Vec<u8>) {
             let hash = compute_content_hash(&data);
-            // Hash should be a valid hex string
+            // Hash should be a 16-digit hex string, zero-padded like other hashes
+            prop_assert_eq!(hash.len(), 16);
             prop_assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
         }
     }
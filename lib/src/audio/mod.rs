@@ -14,7 +14,7 @@
 //!     name: Some("Episode 1".to_string()),
 //! };
 //!
-//! let hash = input.source.resource_hash();
+//! let hash = input.source.resource_hash(None);
 //! println!("Resource hash: {}", hash);
 //! ```
 
@@ -26,11 +26,12 @@ pub mod types;
 
 // Re-export commonly used types
 pub use cache::{AudioCache, AudioCacheEntry, NewAudioCacheEntry};
-pub use html::{generate_audio_html, html_escape, AudioHtmlOptions};
+pub use html::{audio_chapters_js, audio_player_css, generate_audio_html, html_escape, AudioHtmlOptions};
 pub use metadata::{
     compute_content_hash, detect_audio_format, extract_audio_metadata, load_audio_bytes,
 };
-pub use processor::process_audio;
+pub use processor::{copy_captions, process_audio};
+pub(crate) use processor::process_audio_tracked;
 pub use types::{
-    AudioFormat, AudioInput, AudioMetadata, AudioOutput, AudioProcessingConfig, AudioSource,
+    AudioAlternate, AudioBatchOptions, AudioChapter, AudioFormat, AudioInput, AudioMetadata, AudioOutput, AudioProcessingConfig, AudioProcessingReport, AudioSource,
 };
@@ -1,31 +1,59 @@
 //! Audio processing pipeline
 //!
 //! This module provides the complete audio processing workflow:
-//! 1. Load audio bytes from source
+//! 1. Load audio bytes from source, downloading remote audio once per URL
 //! 2. Detect format and compute hashes
 //! 3. Check cache for existing metadata
 //! 4. Extract metadata on cache miss
 //! 5. Copy audio file to output directory
-//! 6. Generate base64 data if inline mode
+//! 6. Generate base64 data if inline mode, or copy other encodings of the file
 //! 7. Return AudioOutput with all processed information
 
 use crate::audio::cache::{AudioCache, NewAudioCacheEntry};
 use crate::audio::metadata::{
-    compute_content_hash, detect_audio_format, extract_audio_metadata, load_audio_bytes,
+    canonicalize_audio_url, compute_content_hash, compute_file_content_hash, detect_audio_format,
+    extract_audio_metadata, extract_audio_metadata_from_file, fetch_remote_audio, load_audio_bytes,
+    url_filename, RemoteAudio,
 };
-use crate::audio::types::{AudioInput, AudioOutput, AudioProcessingConfig};
-use crate::error::{AudioError, CompositionError};
+use crate::audio::types::{AudioAlternate, AudioFormat, AudioInput, AudioOutput, AudioProcessingConfig, AudioSource};
+use crate::cache::RemoteAudioEntry;
+use crate::error::{AudioError, CompositionError, ParseWarning, ParseWarningCode};
+use crate::graph::{compute_resource_hash, STREAMING_HASH_THRESHOLD};
+use crate::types::{Captions, Resource};
 use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info, instrument, warn};
 
 type Result<T> = std::result::Result<T, CompositionError>;
 
 /// Process an audio file (async public API)
 ///
-/// This is the main entry point for audio processing. It wraps the sync
-/// processing logic in a blocking task to maintain async compatibility.
+/// This is the main entry point for audio processing. Cache lookups and
+/// updates run directly in this async function, while loading, hashing,
+/// metadata extraction and writing the output file run in blocking tasks
+/// that take plain data in and out.
+///
+/// # Processing Steps
+///
+/// 1. Compute resource hash from source
+/// 2. Load audio bytes, detect and validate the format, compute content hash
+///    (local files above [`STREAMING_HASH_THRESHOLD`] are hashed from disk
+///    rather than loaded, unless `inline_mode` needs their bytes; remote
+///    audio is downloaded once and kept in the cache, see
+///    [`fetch_remote_audio`])
+/// 3. Check cache with (resource_hash, content_hash)
+/// 4. If cache miss: extract metadata, upsert cache
+/// 5. Validate file size against config.max_inline_size if inline_mode; an
+///    oversized file is inlined anyway, with a warning in [`AudioOutput::warnings`]
+/// 6. Copy audio file to output_dir/audio/{resource_hash}.{ext}
+/// 7. Generate base64 data if inline_mode; otherwise copy any other encodings
+///    found beside a local source (see [`AudioOutput::alternates`])
+/// 8. Determine display name (priority: input.name > metadata.title > filename)
+/// 9. Return AudioOutput
 ///
 /// # Arguments
 ///
@@ -34,6 +62,8 @@ type Result<T> = std::result::Result<T, CompositionError>;
 /// * `cache` - Audio cache for metadata storage
 /// * `inline_mode` - If true, generate base64 data; if false, use file references
 /// * `config` - Processing configuration (limits, allowed formats)
+/// * `project_root` - Root a local file is hashed relative to, which names
+///   its copy (see [`AudioSource::resource_hash`])
 ///
 /// # Returns
 ///
@@ -71,7 +101,8 @@ type Result<T> = std::result::Result<T, CompositionError>;
 ///     Path::new("output"),
 ///     &cache,
 ///     false,
-///     &AudioProcessingConfig::default()
+///     &AudioProcessingConfig::default(),
+///     None,
 /// ).await?;
 ///
 /// println!("Processed audio: {}", output.display_name);
@@ -85,186 +116,468 @@ pub async fn process_audio(
     cache: &AudioCache,
     inline_mode: bool,
     config: &AudioProcessingConfig,
+    project_root: Option<&Path>,
 ) -> Result<AudioOutput> {
-    // Clone data needed for blocking task
-    let input_clone = input.clone();
-    let output_dir_clone = output_dir.to_path_buf();
-    let config_clone = config.clone();
-    let cache_clone = cache.clone();
-
-    // Spawn blocking task for sync operations
-    let output = tokio::task::spawn_blocking(move || {
-        process_audio_sync(
-            input_clone,
-            &output_dir_clone,
-            &cache_clone,
-            inline_mode,
-            &config_clone,
-        )
-    })
-    .await
-    .map_err(|e| CompositionError::Audio(AudioError::ProcessingFailed {
-        reason: format!("Task join error: {}", e),
-    }))??;
-
-    Ok(output)
+    process_audio_tracked(input, output_dir, cache, inline_mode, config, project_root)
+        .await
+        .map(|(output, _)| output)
 }
 
-/// Process an audio file (sync internal implementation)
-///
-/// This function contains the core processing logic. It's synchronous and called
-/// via `spawn_blocking` from the async public API.
-///
-/// # Processing Steps
-///
-/// 1. Compute resource hash from source
-/// 2. Load audio bytes
-/// 3. Detect format
-/// 4. Compute content hash
-/// 5. Check cache with (resource_hash, content_hash)
-/// 6. If cache miss: extract metadata, upsert cache
-/// 7. Validate file size against config.max_inline_size if inline_mode
-/// 8. Copy audio file to output_dir/audio/{resource_hash}.{ext}
-/// 9. Generate base64 data if inline_mode
-/// 10. Determine display name (priority: input.name > metadata.title > filename)
-/// 11. Return AudioOutput
-#[instrument(skip(cache, config))]
-pub(crate) fn process_audio_sync(
+/// [`process_audio`], also telling whether the metadata came from the cache
+pub(crate) async fn process_audio_tracked(
     input: AudioInput,
     output_dir: &Path,
     cache: &AudioCache,
     inline_mode: bool,
     config: &AudioProcessingConfig,
-) -> Result<AudioOutput> {
+    project_root: Option<&Path>,
+) -> Result<(AudioOutput, bool)> {
     // Step 1: Compute resource hash
-    let resource_hash = input.source.resource_hash();
-    let resource_hash_str = format!("{:x}", resource_hash);
+    let resource_hash_str = input.source.resource_hash(project_root).to_string();
     info!(resource_hash = %resource_hash_str, "Processing audio");
 
-    // Step 2: Load audio bytes
-    let (bytes, filename) = load_audio_bytes(&input.source)?;
-    debug!(size_bytes = bytes.len(), "Loaded audio bytes");
-
-    // Step 3: Detect format
-    let format = detect_audio_format(&input.source, &bytes)?;
-    debug!(format = ?format, "Detected audio format");
-
-    // Validate format is allowed
-    if !config.allowed_formats.contains(&format) {
-        return Err(CompositionError::Audio(AudioError::UnsupportedFormat {
-            format: format!("{:?}", format),
-        }));
-    }
-
-    // Validate max file size
-    if let Some(max_size) = config.max_file_size {
-        if bytes.len() as u64 > max_size {
-            return Err(CompositionError::Audio(AudioError::FileTooLarge {
-                size: bytes.len() as u64,
-                max_size,
-            }));
+    // Step 2: Load, detect format and hash
+    let loaded = {
+        let source = input.source.clone();
+        let config = config.clone();
+        match &input.source {
+            AudioSource::Remote(url) => {
+                let remote = fetch_cached_remote_audio(url, cache, &config).await?;
+                let filename = url_filename(url);
+                run_blocking(move || {
+                    inspect_audio(&source, remote.bytes, filename, remote.content_type.as_deref(), &config)
+                })
+                .await?
+            }
+            AudioSource::Local(_) => run_blocking(move || load_audio(&source, &config, inline_mode)).await?,
         }
-    }
-
-    // Step 4: Compute content hash
-    let content_hash = compute_content_hash(&bytes);
-    debug!(content_hash = %content_hash, "Computed content hash");
-
-    // Step 5: Check cache
-    // Note: We need to use a runtime handle to execute async cache operations
-    // from within this sync function
-    let runtime = tokio::runtime::Handle::try_current()
-        .or_else(|_| {
-            // If no runtime is available, create a new one
-            tokio::runtime::Runtime::new()
-                .map(|rt| rt.handle().clone())
-        })
-        .map_err(|e| {
-            CompositionError::Audio(AudioError::CacheFailed(format!(
-                "No async runtime available: {}",
-                e
-            )))
-        })?;
+    };
 
-    let cached_entry = runtime.block_on(cache.get(&resource_hash_str, &content_hash))?;
+    // Step 3: Check cache
+    let cached_entry = cache.get(&resource_hash_str, &loaded.content_hash).await?;
 
+    let cache_hit = cached_entry.is_some();
     let metadata = if let Some(entry) = cached_entry {
         info!(resource_hash = %resource_hash_str, "Cache hit - using cached metadata");
         entry.metadata
     } else {
-        // Step 6: Cache miss - extract metadata and upsert
+        // Step 4: Cache miss - extract metadata and upsert
         info!(resource_hash = %resource_hash_str, "Cache miss - extracting metadata");
-        let extracted_metadata = extract_audio_metadata(&bytes, format)?;
+        let data = loaded.data.clone();
+        let format = loaded.format;
+        let extracted_metadata = run_blocking(move || {
+            Ok(match &data {
+                AudioData::Bytes(bytes) => extract_audio_metadata(bytes, format)?,
+                AudioData::File(path) => extract_audio_metadata_from_file(path, format)?,
+            })
+        })
+        .await?;
 
         let new_entry = NewAudioCacheEntry {
             resource_hash: resource_hash_str.clone(),
-            content_hash: content_hash.clone(),
+            content_hash: loaded.content_hash.clone(),
             source: input.source.clone(),
             format,
             metadata: extracted_metadata.clone(),
         };
 
-        runtime.block_on(cache.upsert(new_entry))?;
+        cache.upsert(new_entry).await?;
 
         extracted_metadata
     };
 
-    // Step 7: Validate file size for inline mode
-    if inline_mode && bytes.len() as u64 > config.max_inline_size {
+    // Step 5: Validate file size for inline mode
+    let mut warnings = Vec::new();
+    if inline_mode && loaded.size > config.max_inline_size {
         warn!(
-            size = bytes.len(),
+            size = loaded.size,
             max_inline_size = config.max_inline_size,
             "Audio file size exceeds max_inline_size - proceeding anyway"
         );
+        warnings.push(ParseWarning {
+            span: None,
+            message: format!(
+                "Inline audio {} is {} bytes, over max_inline_size of {} bytes; inlining it anyway",
+                loaded.filename, loaded.size, config.max_inline_size
+            ),
+            code: ParseWarningCode::OversizedInlineAudio,
+        });
     }
 
-    // Step 8: Copy audio file to output directory
-    let audio_output_dir = output_dir.join("audio");
-    fs::create_dir_all(&audio_output_dir).map_err(|e| {
-        CompositionError::Audio(AudioError::ProcessingFailed {
-            reason: format!("Failed to create audio output directory: {}", e),
-        })
-    })?;
-
-    let output_filename = format!("{}.{}", resource_hash_str, format.extension());
-    let output_path = audio_output_dir.join(&output_filename);
-    fs::write(&output_path, &bytes).map_err(|e| {
-        CompositionError::Audio(AudioError::ProcessingFailed {
-            reason: format!("Failed to write audio file: {}", e),
-        })
-    })?;
-
-    debug!(path = ?output_path, "Copied audio file to output directory");
+    // Steps 6-7: Copy audio file and generate base64 data
+    let output_filename = format!("{}.{}", resource_hash_str, loaded.format.extension());
+    let base64_data = {
+        let output_path = output_dir.join("audio").join(&output_filename);
+        let data = loaded.data.clone();
+        run_blocking(move || write_audio(&data, &output_path, inline_mode)).await?
+    };
 
-    // Step 9: Generate base64 data if inline mode
-    let base64_data = if inline_mode {
-        let encoded = general_purpose::STANDARD.encode(&bytes);
-        Some(encoded)
-    } else {
-        None
+    // Inline players are self-contained, so they only get the one source
+    let alternates = match &input.source {
+        AudioSource::Local(path) if !inline_mode => {
+            let path = path.clone();
+            let audio_dir = output_dir.join("audio");
+            let format = loaded.format;
+            let resource_hash = resource_hash_str.clone();
+            let config = config.clone();
+            run_blocking(move || Ok(copy_alternates(&path, format, &resource_hash, &audio_dir, &config)))
+                .await?
+        }
+        _ => Vec::new(),
     };
 
-    // Step 10: Determine display name
+    // Step 8: Determine display name
     let display_name = input
         .name
         .or_else(|| metadata.title.clone())
-        .unwrap_or(filename);
+        .unwrap_or(loaded.filename);
 
-    // Step 11: Return AudioOutput
+    // Step 9: Return AudioOutput
     let relative_path = format!("audio/{}", output_filename);
-    Ok(AudioOutput {
-        format,
+    let output = AudioOutput {
+        format: loaded.format,
         metadata,
         path: relative_path,
         base64_data,
         display_name,
+        alternates,
+        captions: None,
+        warnings,
+    };
+    Ok((output, cache_hit))
+}
+
+/// Copy a captions file to `output_dir/audio/{resource_hash}.vtt`
+///
+/// `captions.path` is the file to copy. The returned captions point at the
+/// copy, relative to `output_dir`, or in inline mode hold the file as a
+/// `text/vtt` data URI instead. The file is named by its hash relative to
+/// `project_root`.
+pub fn copy_captions(
+    captions: &Captions,
+    output_dir: &Path,
+    inline_mode: bool,
+    project_root: Option<&Path>,
+) -> Result<Captions> {
+    let source = PathBuf::from(&captions.path);
+    let failed = |action: &str, e: std::io::Error| {
+        CompositionError::Audio(AudioError::ProcessingFailed {
+            reason: format!("Failed to {} captions {}: {}", action, captions.path, e),
+        })
+    };
+
+    let data = fs::read(&source).map_err(|e| failed("read", e))?;
+    let path = if inline_mode {
+        format!("data:text/vtt;base64,{}", general_purpose::STANDARD.encode(&data))
+    } else {
+        let filename = format!("{}.vtt", compute_resource_hash(&Resource::local(source), project_root));
+        let audio_dir = output_dir.join("audio");
+        fs::create_dir_all(&audio_dir).map_err(|e| failed("create the output directory for", e))?;
+        fs::write(audio_dir.join(&filename), &data).map_err(|e| failed("write", e))?;
+        debug!(file = %filename, "Copied captions to output directory");
+        format!("audio/{}", filename)
+    };
+
+    Ok(Captions { path, language: captions.language.clone() })
+}
+
+/// Audio loaded from a source, with its detected format and hash
+struct LoadedAudio {
+    data: AudioData,
+    size: u64,
+    filename: String,
+    format: AudioFormat,
+    content_hash: String,
+}
+
+/// Where the contents of a loaded audio file live
+#[derive(Clone)]
+enum AudioData {
+    /// Read into memory
+    Bytes(Arc<[u8]>),
+    /// Left on disk and streamed when needed (large local files)
+    File(PathBuf),
+}
+
+/// Run blocking work on Tokio's blocking thread pool
+async fn run_blocking<T, F>(work: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| CompositionError::Audio(AudioError::ProcessingFailed {
+            reason: format!("Task join error: {}", e),
+        }))?
+}
+
+/// Load audio bytes, then detect and validate their format and hash them
+///
+/// Outside inline mode, local files above [`STREAMING_HASH_THRESHOLD`] are
+/// hashed straight from disk and never held in memory.
+#[instrument(skip(config))]
+fn load_audio(
+    source: &AudioSource,
+    config: &AudioProcessingConfig,
+    inline_mode: bool,
+) -> Result<LoadedAudio> {
+    if !inline_mode {
+        if let AudioSource::Local(path) = source {
+            if let Some(loaded) = stream_local_audio(source, path, config)? {
+                return Ok(loaded);
+            }
+        }
+    }
+
+    let (bytes, filename) = load_audio_bytes(source)?;
+    debug!(size_bytes = bytes.len(), "Loaded audio bytes");
+
+    inspect_audio(source, bytes, filename, None, config)
+}
+
+/// Detect and validate the format of loaded audio bytes, and hash them
+fn inspect_audio(
+    source: &AudioSource,
+    bytes: Vec<u8>,
+    filename: String,
+    content_type: Option<&str>,
+    config: &AudioProcessingConfig,
+) -> Result<LoadedAudio> {
+    let format = detect_audio_format(source, &bytes, content_type)?;
+    debug!(format = ?format, "Detected audio format");
+    validate_audio(format, bytes.len() as u64, config)?;
+
+    let content_hash = compute_content_hash(&bytes);
+    debug!(content_hash = %content_hash, "Computed content hash");
+
+    Ok(LoadedAudio {
+        size: bytes.len() as u64,
+        data: AudioData::Bytes(bytes.into()),
+        filename,
+        format,
+        content_hash,
     })
 }
 
+/// Download remote audio, reusing an earlier download of the same URL
+///
+/// Downloads are kept in the cache under a hash of the canonicalized URL, so
+/// repeated builds don't fetch the audio again.
+async fn fetch_cached_remote_audio(
+    url: &str,
+    cache: &AudioCache,
+    config: &AudioProcessingConfig,
+) -> Result<RemoteAudio> {
+    let canonical = canonicalize_audio_url(url)?;
+    let url_hash = compute_content_hash(canonical.as_bytes());
+    let operations = cache.operations();
+
+    if let Some(entry) = operations.get_remote_audio(&url_hash).await? {
+        debug!(url = %canonical, "Using previously downloaded remote audio");
+        return Ok(RemoteAudio {
+            bytes: entry.data,
+            content_type: entry.content_type,
+        });
+    }
+
+    let remote = {
+        let canonical = canonical.clone();
+        let max_file_size = config.max_file_size;
+        run_blocking(move || Ok(fetch_remote_audio(&canonical, max_file_size)?)).await?
+    };
+
+    operations
+        .upsert_remote_audio(RemoteAudioEntry {
+            url_hash,
+            url: canonical,
+            content_type: remote.content_type.clone(),
+            data: remote.bytes.clone(),
+            fetched_at: Utc::now(),
+        })
+        .await?;
+
+    Ok(remote)
+}
+
+/// Hash a large local audio file from disk
+///
+/// Returns `None` when the file is small enough to load normally. Only the
+/// header is read to detect the format.
+fn stream_local_audio(
+    source: &AudioSource,
+    path: &Path,
+    config: &AudioProcessingConfig,
+) -> Result<Option<LoadedAudio>> {
+    let (canonical, size) = resolve_local_audio(path)?;
+    if size <= STREAMING_HASH_THRESHOLD {
+        return Ok(None);
+    }
+
+    let header = read_audio_header(&canonical, path)?;
+    let format = detect_audio_format(source, &header, None)?;
+    debug!(format = ?format, size_bytes = size, "Detected audio format from file header");
+    validate_audio(format, size, config)?;
+
+    let content_hash = compute_file_content_hash(&canonical)?;
+    debug!(content_hash = %content_hash, "Computed content hash from file");
+
+    let filename = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    Ok(Some(LoadedAudio {
+        data: AudioData::File(canonical),
+        size,
+        filename,
+        format,
+        content_hash,
+    }))
+}
+
+/// Resolve a local audio file and get its size
+///
+/// Symlink protection matches load_audio_bytes: callers work on the resolved path.
+fn resolve_local_audio(path: &Path) -> Result<(PathBuf, u64)> {
+    let read_failed = || {
+        CompositionError::Audio(AudioError::ReadFailed {
+            path: path.display().to_string(),
+        })
+    };
+
+    let canonical = path.canonicalize().map_err(|_| read_failed())?;
+    let size = fs::metadata(&canonical).map_err(|_| read_failed())?.len();
+    Ok((canonical, size))
+}
+
+/// Read enough of a file to detect its format from magic bytes
+fn read_audio_header(canonical: &Path, path: &Path) -> Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(16);
+    fs::File::open(canonical)
+        .and_then(|file| file.take(16).read_to_end(&mut header))
+        .map_err(|_| {
+            CompositionError::Audio(AudioError::ReadFailed {
+                path: path.display().to_string(),
+            })
+        })?;
+    Ok(header)
+}
+
+/// Copy the other encodings of a local audio file to `audio_dir`
+///
+/// An encoding is a file beside `path` with the same stem and the extension
+/// of another allowed format, such as `podcast.ogg` next to `podcast.mp3`.
+/// They are optional, so one that fails validation is skipped with a warning.
+fn copy_alternates(
+    path: &Path,
+    primary: AudioFormat,
+    resource_hash: &str,
+    audio_dir: &Path,
+    config: &AudioProcessingConfig,
+) -> Vec<AudioAlternate> {
+    config
+        .allowed_formats
+        .iter()
+        .filter(|&&format| format != primary)
+        .filter_map(|&format| {
+            let candidate = format
+                .file_extensions()
+                .iter()
+                .map(|ext| path.with_extension(ext))
+                .find(|candidate| candidate.is_file())?;
+
+            match copy_alternate(&candidate, resource_hash, audio_dir, config) {
+                Ok(alternate) => Some(alternate),
+                Err(e) => {
+                    warn!(path = ?candidate, error = %e, "Skipping alternate audio file");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Validate one alternate encoding and copy it to `audio_dir`
+fn copy_alternate(
+    path: &Path,
+    resource_hash: &str,
+    audio_dir: &Path,
+    config: &AudioProcessingConfig,
+) -> Result<AudioAlternate> {
+    let (canonical, size) = resolve_local_audio(path)?;
+    let header = read_audio_header(&canonical, path)?;
+    let format = detect_audio_format(&AudioSource::Local(path.to_path_buf()), &header, None)?;
+    validate_audio(format, size, config)?;
+
+    let filename = format!("{}.{}", resource_hash, format.extension());
+    write_audio(&AudioData::File(canonical), &audio_dir.join(&filename), false)?;
+    debug!(format = ?format, "Copied alternate audio file");
+
+    Ok(AudioAlternate {
+        format,
+        path: format!("audio/{}", filename),
+    })
+}
+
+/// Check a detected format and file size against the processing config
+fn validate_audio(format: AudioFormat, size: u64, config: &AudioProcessingConfig) -> Result<()> {
+    // Validate format is allowed
+    if !config.allowed_formats.contains(&format) {
+        return Err(CompositionError::Audio(AudioError::UnsupportedFormat {
+            format: format!("{:?}", format),
+        }));
+    }
+
+    // Validate max file size
+    if let Some(max_size) = config.max_file_size {
+        if size > max_size {
+            return Err(CompositionError::Audio(AudioError::FileTooLarge { size, max_size }));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write audio to `output_path`, returning it base64 encoded in inline mode
+#[instrument(skip(data))]
+fn write_audio(data: &AudioData, output_path: &Path, inline_mode: bool) -> Result<Option<String>> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            CompositionError::Audio(AudioError::ProcessingFailed {
+                reason: format!("Failed to create audio output directory: {}", e),
+            })
+        })?;
+    }
+
+    let write_failed = |e: std::io::Error| {
+        CompositionError::Audio(AudioError::ProcessingFailed {
+            reason: format!("Failed to write audio file: {}", e),
+        })
+    };
+
+    let bytes = match data {
+        AudioData::Bytes(bytes) => {
+            fs::write(output_path, bytes).map_err(write_failed)?;
+            bytes
+        }
+        AudioData::File(source) => {
+            fs::copy(source, output_path).map_err(write_failed)?;
+            debug!(path = ?output_path, "Copied audio file to output directory");
+            // Large files are only left on disk outside inline mode
+            return Ok(None);
+        }
+    };
+
+    debug!(path = ?output_path, "Copied audio file to output directory");
+
+    Ok(inline_mode.then(|| general_purpose::STANDARD.encode(bytes)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::types::AudioSource;
     use std::path::PathBuf;
     use surrealdb::engine::local::Mem;
     use surrealdb::Surreal;
@@ -293,6 +606,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
 
@@ -302,14 +616,16 @@ mod tests {
             Ok(output) => {
                 assert_eq!(output.display_name, "Test Audio");
                 assert!(output.path.starts_with("audio/"));
+                assert!(!output.path.contains('\\'));
                 assert!(output.path.ends_with(".mp3"));
                 assert_eq!(output.base64_data, None);
 
                 // Verify file was copied
+                // The other test.* fixtures are copied as alternates
                 let audio_dir = temp_dir.path().join("audio");
                 assert!(audio_dir.exists());
                 let files: Vec<_> = fs::read_dir(audio_dir).unwrap().collect();
-                assert_eq!(files.len(), 1);
+                assert_eq!(files.len(), 1 + output.alternates.len());
             }
             Err(CompositionError::Audio(AudioError::MetadataFailed { .. })) => {
                 // Expected for minimal test fixtures
@@ -318,6 +634,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_copy_captions() {
+        let temp_dir = TempDir::new().unwrap();
+        let vtt = temp_dir.path().join("talk.vtt");
+        fs::write(&vtt, "WEBVTT\n\n00:00.000 --> 00:02.000\nHello\n").unwrap();
+        let captions = Captions { path: vtt.display().to_string(), language: "en".to_string() };
+
+        let copied = copy_captions(&captions, temp_dir.path(), false, None).unwrap();
+        assert!(copied.path.starts_with("audio/") && copied.path.ends_with(".vtt"), "{}", copied.path);
+        assert_eq!(fs::read(temp_dir.path().join(&copied.path)).unwrap(), fs::read(&vtt).unwrap());
+        assert_eq!(copied.language, "en");
+
+        let inline = copy_captions(&captions, temp_dir.path(), true, None).unwrap();
+        assert!(inline.path.starts_with("data:text/vtt;base64,V0VCVlRU"), "{}", inline.path);
+
+        let missing = Captions { path: "missing.vtt".to_string(), language: "en".to_string() };
+        assert!(matches!(
+            copy_captions(&missing, temp_dir.path(), false, None),
+            Err(CompositionError::Audio(AudioError::ProcessingFailed { .. }))
+        ));
+    }
+
     #[tokio::test]
     async fn test_process_audio_sync_with_valid_wav() {
         let cache = setup_test_cache().await;
@@ -334,6 +672,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
 
@@ -345,6 +684,36 @@ mod tests {
         assert_eq!(output.base64_data, None);
     }
 
+    #[tokio::test]
+    async fn test_process_audio_downloads_remote_audio_once() {
+        use crate::audio::metadata::tests::{http_response, serve_http};
+
+        let cache = setup_test_cache().await;
+        let temp_dir = TempDir::new().unwrap();
+        let audio = fs::read("../tests/fixtures/audio/test.wav").unwrap();
+        // The server answers a single request, and the URL has no extension
+        let (base_url, requests) = serve_http(vec![http_response(
+            "200 OK",
+            &[("Content-Type", "audio/wav")],
+            &audio,
+        )]);
+
+        for url in [format!("{}/stream", base_url), format!("{}/stream#start", base_url)] {
+            let input = AudioInput {
+                source: AudioSource::Remote(url),
+                name: None,
+            };
+            let output = process_audio(input, temp_dir.path(), &cache, false, &AudioProcessingConfig::default(), None)
+                .await
+                .unwrap();
+
+            assert_eq!(output.format, AudioFormat::Wav);
+            assert_eq!(fs::read(temp_dir.path().join(&output.path)).unwrap(), audio);
+        }
+
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_process_audio_sync_with_inline_mode() {
         let cache = setup_test_cache().await;
@@ -361,6 +730,7 @@ mod tests {
             &cache,
             true, // inline_mode = true
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
 
@@ -373,6 +743,44 @@ mod tests {
         assert!(general_purpose::STANDARD.decode(&base64).is_ok());
     }
 
+    #[tokio::test]
+    async fn test_process_audio_copies_alternate_formats() {
+        let cache = setup_test_cache().await;
+        let source_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        let fixtures = Path::new("../tests/fixtures/audio");
+        fs::copy(fixtures.join("test.wav"), source_dir.path().join("episode.wav")).unwrap();
+        fs::copy(fixtures.join("test.flac"), source_dir.path().join("episode.flac")).unwrap();
+        // Named like an alternate but holds WAV data, so it's skipped
+        fs::copy(fixtures.join("test.wav"), source_dir.path().join("episode.ogg")).unwrap();
+
+        let input = AudioInput {
+            source: AudioSource::Local(source_dir.path().join("episode.wav")),
+            name: None,
+        };
+        let config = AudioProcessingConfig::default();
+
+        let output = process_audio(input.clone(), output_dir.path(), &cache, false, &config, None)
+            .await
+            .unwrap();
+
+        let stem = output.path.trim_end_matches(".wav");
+        assert_eq!(
+            output.alternates,
+            vec![AudioAlternate {
+                format: AudioFormat::Flac,
+                path: format!("{}.flac", stem),
+            }]
+        );
+        assert!(output_dir.path().join(&output.alternates[0].path).is_file());
+
+        // Inline players don't get alternates
+        let inline = process_audio(input, output_dir.path(), &cache, true, &config, None)
+            .await
+            .unwrap();
+        assert!(inline.alternates.is_empty());
+    }
+
     #[tokio::test]
     async fn test_process_audio_sync_cache_hit() {
         let cache = setup_test_cache().await;
@@ -390,6 +798,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
         assert!(result1.is_ok());
@@ -401,6 +810,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
         assert!(result2.is_ok());
@@ -429,6 +839,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
         assert!(result.is_ok());
@@ -446,6 +857,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
         assert!(result.is_ok());
@@ -468,14 +880,17 @@ mod tests {
             max_file_size: None,
             max_inline_size: 1,
             allowed_formats: vec![crate::audio::types::AudioFormat::Mp3, crate::audio::types::AudioFormat::Wav],
+            ..Default::default()
         };
 
-        let result = process_audio(input, temp_dir.path(), &cache, true, &config).await;
+        let result = process_audio(input, temp_dir.path(), &cache, true, &config, None).await;
 
         // Should succeed with warning (not fail)
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.base64_data.is_some());
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].code, ParseWarningCode::OversizedInlineAudio);
     }
 
     #[tokio::test]
@@ -493,9 +908,10 @@ mod tests {
             max_file_size: None,
             max_inline_size: 10 * 1024 * 1024,
             allowed_formats: vec![crate::audio::types::AudioFormat::Wav],
+            ..Default::default()
         };
 
-        let result = process_audio(input, temp_dir.path(), &cache, false, &config).await;
+        let result = process_audio(input, temp_dir.path(), &cache, false, &config, None).await;
 
         // Should fail with UnsupportedFormat (or MetadataFailed if fixture is invalid)
         assert!(result.is_err());
@@ -517,6 +933,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
 
@@ -527,6 +944,36 @@ mod tests {
         }
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_process_audio_on_current_thread_runtime() {
+        let cache = setup_test_cache().await;
+        let temp_dir = TempDir::new().unwrap();
+
+        let input = AudioInput {
+            source: AudioSource::Local(PathBuf::from("../tests/fixtures/audio/test.wav")),
+            name: None,
+        };
+
+        // Cache miss, then cache hit, both on the single runtime thread
+        for _ in 0..2 {
+            let output = tokio::time::timeout(
+                std::time::Duration::from_secs(10),
+                process_audio(
+                    input.clone(),
+                    temp_dir.path(),
+                    &cache,
+                    true,
+                    &AudioProcessingConfig::default(),
+                    None,
+                ),
+            )
+            .await
+            .expect("process_audio deadlocked")
+            .unwrap();
+            assert!(output.base64_data.is_some());
+        }
+    }
+
     #[tokio::test]
     async fn test_process_audio_async_wrapper() {
         let cache = setup_test_cache().await;
@@ -543,6 +990,7 @@ mod tests {
             &cache,
             false,
             &AudioProcessingConfig::default(),
+            None,
         )
         .await;
 
@@ -550,4 +998,31 @@ mod tests {
         let output = result.unwrap();
         assert_eq!(output.display_name, "Async Test");
     }
+
+    #[test]
+    fn test_large_local_audio_is_streamed() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("large.wav");
+        let mut bytes = b"RIFF".to_vec();
+        bytes.resize(STREAMING_HASH_THRESHOLD as usize + 1024, 0x5a);
+        fs::write(&path, &bytes).unwrap();
+
+        let source = AudioSource::Local(path.clone());
+        let config = AudioProcessingConfig::default();
+
+        let streamed = load_audio(&source, &config, false).unwrap();
+        assert!(matches!(streamed.data, AudioData::File(_)));
+        assert_eq!(streamed.size, bytes.len() as u64);
+        assert_eq!(streamed.format, AudioFormat::Wav);
+        assert_eq!(streamed.content_hash, compute_content_hash(&bytes));
+
+        // Inline mode needs the bytes, so it still loads the file
+        let loaded = load_audio(&source, &config, true).unwrap();
+        assert!(matches!(loaded.data, AudioData::Bytes(_)));
+        assert_eq!(loaded.content_hash, streamed.content_hash);
+
+        let output_path = temp_dir.path().join("audio").join("copy.wav");
+        assert_eq!(write_audio(&streamed.data, &output_path, false).unwrap(), None);
+        assert_eq!(fs::read(&output_path).unwrap(), bytes);
+    }
 }
@@ -3,7 +3,11 @@
 //! This module defines the foundational types for audio processing in the DarkMatter DSL,
 //! including source types, format detection, metadata structures, and processing I/O types.
 
-use std::path::PathBuf;
+use crate::error::{CompositionError, ParseWarning};
+use crate::graph::compute_resource_hash;
+use crate::types::{Captions, Resource, ResourceHash};
+use std::path::{Path, PathBuf};
+use url::Url;
 use xxhash_rust::xxh3::xxh3_64;
 
 /// Audio source location (local file or remote URL)
@@ -19,26 +23,29 @@ impl AudioSource {
     /// Compute a stable hash for the resource location
     ///
     /// This hash is used for cache lookups and file naming. It's based on the
-    /// resource path/URL, not the content.
+    /// resource path/URL, not the content, and matches the hash of the same
+    /// file or URL as a document resource hashed relative to the same
+    /// `project_root` (see
+    /// [`compute_resource_hash`](crate::graph::compute_resource_hash)).
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::path::PathBuf;
+    /// use std::path::{Path, PathBuf};
     /// use lib::audio::types::AudioSource;
     ///
-    /// let source = AudioSource::Local(PathBuf::from("audio/podcast.mp3"));
-    /// let hash1 = source.resource_hash();
-    /// let hash2 = source.resource_hash();
+    /// let source = AudioSource::Local(PathBuf::from("/site/audio/podcast.mp3"));
+    /// let hash1 = source.resource_hash(Some(Path::new("/site")));
+    /// let hash2 = source.resource_hash(Some(Path::new("/site")));
     /// assert_eq!(hash1, hash2); // Deterministic
     /// ```
-    pub fn resource_hash(&self) -> u64 {
+    pub fn resource_hash(&self, project_root: Option<&Path>) -> ResourceHash {
         match self {
-            AudioSource::Local(path) => {
-                let path_str = path.to_string_lossy();
-                xxh3_64(path_str.as_bytes())
-            }
-            AudioSource::Remote(url) => xxh3_64(url.as_bytes()),
+            AudioSource::Local(path) => compute_resource_hash(&Resource::local(path.clone()), project_root),
+            AudioSource::Remote(url) => match Url::parse(url) {
+                Ok(url) => compute_resource_hash(&Resource::remote(url), None),
+                Err(_) => ResourceHash(xxh3_64(url.as_bytes())),
+            },
         }
     }
 }
@@ -54,9 +61,24 @@ pub enum AudioFormat {
     Mp3,
     /// WAV audio format
     Wav,
+    /// Ogg audio format (Vorbis)
+    Ogg,
+    /// FLAC audio format
+    Flac,
+    /// AAC audio format (ADTS streams and MP4 `.m4a` files)
+    Aac,
 }
 
 impl AudioFormat {
+    /// Every supported format
+    pub const ALL: [AudioFormat; 5] = [
+        AudioFormat::Mp3,
+        AudioFormat::Wav,
+        AudioFormat::Ogg,
+        AudioFormat::Flac,
+        AudioFormat::Aac,
+    ];
+
     /// Detect audio format from file extension
     ///
     /// # Arguments
@@ -75,14 +97,24 @@ impl AudioFormat {
     /// assert_eq!(AudioFormat::from_extension("mp3"), Some(AudioFormat::Mp3));
     /// assert_eq!(AudioFormat::from_extension(".wav"), Some(AudioFormat::Wav));
     /// assert_eq!(AudioFormat::from_extension("MP3"), Some(AudioFormat::Mp3));
-    /// assert_eq!(AudioFormat::from_extension("ogg"), None);
+    /// assert_eq!(AudioFormat::from_extension("m4a"), Some(AudioFormat::Aac));
+    /// assert_eq!(AudioFormat::from_extension("opus"), None);
     /// ```
     pub fn from_extension(ext: &str) -> Option<Self> {
         let normalized = ext.trim_start_matches('.').to_lowercase();
-        match normalized.as_str() {
-            "mp3" => Some(AudioFormat::Mp3),
-            "wav" => Some(AudioFormat::Wav),
-            _ => None,
+        Self::ALL
+            .into_iter()
+            .find(|format| format.file_extensions().contains(&normalized.as_str()))
+    }
+
+    /// Every file extension recognized as this format, preferred one first
+    pub fn file_extensions(&self) -> &'static [&'static str] {
+        match self {
+            AudioFormat::Mp3 => &["mp3"],
+            AudioFormat::Wav => &["wav"],
+            AudioFormat::Ogg => &["ogg"],
+            AudioFormat::Flac => &["flac"],
+            AudioFormat::Aac => &["aac", "m4a"],
         }
     }
 
@@ -95,11 +127,42 @@ impl AudioFormat {
     ///
     /// assert_eq!(AudioFormat::Mp3.mime_type(), "audio/mpeg");
     /// assert_eq!(AudioFormat::Wav.mime_type(), "audio/wav");
+    /// assert_eq!(AudioFormat::Ogg.mime_type(), "audio/ogg");
     /// ```
     pub fn mime_type(&self) -> &'static str {
         match self {
             AudioFormat::Mp3 => "audio/mpeg",
             AudioFormat::Wav => "audio/wav",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Aac => "audio/aac",
+        }
+    }
+
+    /// Detect audio format from a MIME type such as an HTTP `Content-Type`
+    ///
+    /// Parameters like `; charset=binary` are ignored, and the common
+    /// non-standard names servers send (`audio/mp3`, `audio/x-wav`,
+    /// `audio/mp4`, ...) are recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib::audio::types::AudioFormat;
+    ///
+    /// assert_eq!(AudioFormat::from_mime_type("audio/mpeg"), Some(AudioFormat::Mp3));
+    /// assert_eq!(AudioFormat::from_mime_type("Audio/X-WAV; codecs=1"), Some(AudioFormat::Wav));
+    /// assert_eq!(AudioFormat::from_mime_type("application/octet-stream"), None);
+    /// ```
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        let essence = mime_type.split(';').next()?.trim().to_lowercase();
+        match essence.as_str() {
+            "audio/mpeg" | "audio/mp3" | "audio/mpeg3" | "audio/x-mpeg-3" => Some(AudioFormat::Mp3),
+            "audio/wav" | "audio/x-wav" | "audio/wave" | "audio/vnd.wave" => Some(AudioFormat::Wav),
+            "audio/ogg" | "application/ogg" | "audio/vorbis" | "audio/opus" => Some(AudioFormat::Ogg),
+            "audio/flac" | "audio/x-flac" => Some(AudioFormat::Flac),
+            "audio/aac" | "audio/aacp" | "audio/x-aac" | "audio/mp4" | "audio/x-m4a" => Some(AudioFormat::Aac),
+            _ => None,
         }
     }
 
@@ -112,19 +175,17 @@ impl AudioFormat {
     ///
     /// assert_eq!(AudioFormat::Mp3.extension(), "mp3");
     /// assert_eq!(AudioFormat::Wav.extension(), "wav");
+    /// assert_eq!(AudioFormat::Flac.extension(), "flac");
     /// ```
     pub fn extension(&self) -> &'static str {
-        match self {
-            AudioFormat::Mp3 => "mp3",
-            AudioFormat::Wav => "wav",
-        }
+        self.file_extensions()[0]
     }
 }
 
 /// Audio metadata extracted from files
 ///
 /// This includes technical metadata (duration, bitrate, etc.) and
-/// ID3 tags (title, artist, album) if present.
+/// ID3 tags (title, artist, album, cover art, chapters) if present.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct AudioMetadata {
     /// Duration in seconds (None if unknown)
@@ -141,6 +202,21 @@ pub struct AudioMetadata {
     pub artist: Option<String>,
     /// Album from ID3 tags (None if not present)
     pub album: Option<String>,
+    /// Raw JPEG or PNG bytes of the embedded cover art (None if not present)
+    pub cover_art: Option<Vec<u8>>,
+    /// Chapters from ID3v2 `CHAP` frames, ordered by start time
+    pub chapters: Vec<AudioChapter>,
+}
+
+/// A chapter of an audio file, as found in podcast episodes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AudioChapter {
+    /// Chapter title (the `CHAP` frame's element ID if it has no `TIT2`)
+    pub title: String,
+    /// Start of the chapter in milliseconds
+    pub start_ms: u64,
+    /// End of the chapter in milliseconds
+    pub end_ms: u64,
 }
 
 /// Input specification for audio processing
@@ -165,6 +241,23 @@ pub struct AudioOutput {
     pub base64_data: Option<String>,
     /// Display name (from input.name, metadata.title, or filename)
     pub display_name: String,
+    /// The same audio in other formats, offered to browsers after `path`
+    pub alternates: Vec<AudioAlternate>,
+    /// Captions track, its path relative to the output directory or, in
+    /// inline mode, a data URI; see [`copy_captions`](crate::audio::processor::copy_captions)
+    pub captions: Option<Captions>,
+    /// Problems that didn't stop the audio from being processed, such as an
+    /// inline file over `max_inline_size`
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Another encoding of a processed audio file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioAlternate {
+    /// Format of this encoding
+    pub format: AudioFormat,
+    /// Path to the copied file (relative to output directory)
+    pub path: String,
 }
 
 /// Configuration for audio processing
@@ -177,6 +270,10 @@ pub struct AudioProcessingConfig {
     pub max_inline_size: u64,
     /// Allowed audio formats
     pub allowed_formats: Vec<AudioFormat>,
+    /// Maximum number of files processed at once by
+    /// [`batch_process_audio`](crate::CompositionApi::batch_process_audio)
+    /// Default: 4
+    pub max_concurrent: usize,
 }
 
 impl Default for AudioProcessingConfig {
@@ -184,7 +281,54 @@ impl Default for AudioProcessingConfig {
         Self {
             max_file_size: None, // No limit by default
             max_inline_size: 10 * 1024 * 1024, // 10MB default
-            allowed_formats: vec![AudioFormat::Mp3, AudioFormat::Wav],
+            allowed_formats: AudioFormat::ALL.to_vec(),
+            max_concurrent: 4,
+        }
+    }
+}
+
+/// Options shared by every item of an audio batch
+///
+/// See [`CompositionApi::process_audio_batch`](crate::CompositionApi::process_audio_batch).
+#[derive(Debug, Clone)]
+pub struct AudioBatchOptions {
+    /// Directory the audio files are copied into, under `audio/`
+    pub output_dir: PathBuf,
+    /// Embed the audio as base64 instead of copying files
+    pub inline_mode: bool,
+    pub config: AudioProcessingConfig,
+}
+
+impl Default for AudioBatchOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("./output"),
+            inline_mode: false,
+            config: AudioProcessingConfig::default(),
+        }
+    }
+}
+
+/// Result of [`batch_process_audio`](crate::CompositionApi::batch_process_audio)
+#[derive(Debug, Default)]
+pub struct AudioProcessingReport {
+    /// Output of every input that succeeded, in input order
+    pub outputs: Vec<AudioOutput>,
+    /// Number of inputs whose metadata had to be extracted
+    pub processed: usize,
+    /// Number of inputs whose metadata came from the audio cache
+    pub cached: usize,
+    /// Inputs that failed, in input order, with their error
+    pub failed: Vec<(AudioInput, CompositionError)>,
+}
+
+impl AudioBatchOptions {
+    /// Copy audio files into `output_dir` with the default configuration
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            inline_mode: false,
+            config: AudioProcessingConfig::default(),
         }
     }
 }
@@ -196,16 +340,16 @@ mod tests {
     #[test]
     fn audio_source_local_hash_is_deterministic() {
         let source = AudioSource::Local(PathBuf::from("test/audio.mp3"));
-        let hash1 = source.resource_hash();
-        let hash2 = source.resource_hash();
+        let hash1 = source.resource_hash(None);
+        let hash2 = source.resource_hash(None);
         assert_eq!(hash1, hash2);
     }
 
     #[test]
     fn audio_source_remote_hash_is_deterministic() {
         let source = AudioSource::Remote("https://example.com/audio.mp3".to_string());
-        let hash1 = source.resource_hash();
-        let hash2 = source.resource_hash();
+        let hash1 = source.resource_hash(None);
+        let hash2 = source.resource_hash(None);
         assert_eq!(hash1, hash2);
     }
 
@@ -213,7 +357,7 @@ mod tests {
     fn audio_source_different_paths_produce_different_hashes() {
         let source1 = AudioSource::Local(PathBuf::from("test/audio1.mp3"));
         let source2 = AudioSource::Local(PathBuf::from("test/audio2.mp3"));
-        assert_ne!(source1.resource_hash(), source2.resource_hash());
+        assert_ne!(source1.resource_hash(None), source2.resource_hash(None));
     }
 
     #[test]
@@ -232,12 +376,18 @@ mod tests {
         assert_eq!(AudioFormat::from_extension(".WAV"), Some(AudioFormat::Wav));
     }
 
+    #[test]
+    fn audio_format_from_extension_recognizes_new_formats() {
+        assert_eq!(AudioFormat::from_extension("ogg"), Some(AudioFormat::Ogg));
+        assert_eq!(AudioFormat::from_extension(".FLAC"), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::from_extension("aac"), Some(AudioFormat::Aac));
+        assert_eq!(AudioFormat::from_extension("m4a"), Some(AudioFormat::Aac));
+    }
+
     #[test]
     fn audio_format_from_extension_rejects_unsupported() {
-        assert_eq!(AudioFormat::from_extension("ogg"), None);
-        assert_eq!(AudioFormat::from_extension("flac"), None);
-        assert_eq!(AudioFormat::from_extension("aac"), None);
-        assert_eq!(AudioFormat::from_extension("m4a"), None);
+        assert_eq!(AudioFormat::from_extension("opus"), None);
+        assert_eq!(AudioFormat::from_extension("aiff"), None);
         assert_eq!(AudioFormat::from_extension(""), None);
     }
 
@@ -251,6 +401,22 @@ mod tests {
         assert_eq!(AudioFormat::Wav.mime_type(), "audio/wav");
     }
 
+    #[test]
+    fn audio_format_new_format_mime_types() {
+        assert_eq!(AudioFormat::Ogg.mime_type(), "audio/ogg");
+        assert_eq!(AudioFormat::Flac.mime_type(), "audio/flac");
+        assert_eq!(AudioFormat::Aac.mime_type(), "audio/aac");
+    }
+
+    #[test]
+    fn audio_format_from_mime_type_round_trips() {
+        for format in AudioFormat::ALL {
+            assert_eq!(AudioFormat::from_mime_type(format.mime_type()), Some(format));
+        }
+        assert_eq!(AudioFormat::from_mime_type("audio/x-m4a"), Some(AudioFormat::Aac));
+        assert_eq!(AudioFormat::from_mime_type("text/html; charset=utf-8"), None);
+    }
+
     #[test]
     fn audio_format_mp3_extension() {
         assert_eq!(AudioFormat::Mp3.extension(), "mp3");
@@ -290,6 +456,9 @@ mod tests {
             path: "audio/12345.mp3".to_string(),
             base64_data: None,
             display_name: "Test Audio".to_string(),
+            alternates: Vec::new(),
+            captions: None,
+            warnings: Vec::new(),
         };
         assert_eq!(output.format, AudioFormat::Mp3);
         assert_eq!(output.path, "audio/12345.mp3");
@@ -302,9 +471,8 @@ mod tests {
         let config = AudioProcessingConfig::default();
         assert_eq!(config.max_file_size, None);
         assert_eq!(config.max_inline_size, 10 * 1024 * 1024);
-        assert_eq!(config.allowed_formats.len(), 2);
-        assert!(config.allowed_formats.contains(&AudioFormat::Mp3));
-        assert!(config.allowed_formats.contains(&AudioFormat::Wav));
+        assert_eq!(config.allowed_formats, AudioFormat::ALL.to_vec());
+        assert_eq!(config.max_concurrent, 4);
     }
 
     #[test]
@@ -313,6 +481,7 @@ mod tests {
             max_file_size: Some(100 * 1024 * 1024), // 100MB
             max_inline_size: 5 * 1024 * 1024,       // 5MB
             allowed_formats: vec![AudioFormat::Mp3],
+            max_concurrent: 2,
         };
         assert_eq!(config.max_file_size, Some(100 * 1024 * 1024));
         assert_eq!(config.max_inline_size, 5 * 1024 * 1024);
@@ -1,8 +1,106 @@
-use crate::error::{CacheError, Result};
+use crate::error::{CacheError, CompositionError, Result};
 use std::path::{Path, PathBuf};
-use surrealdb::engine::local::{Db, RocksDb};
+use std::time::{Duration, Instant};
+use surrealdb::engine::local::{Db, Mem, RocksDb};
 use surrealdb::Surreal;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
+
+/// First delay between attempts to open a locked database; doubled after every attempt
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Upper bound for the delay between attempts to open a locked database
+const MAX_LOCK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Storage engine backing the cache database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheBackend {
+    /// Ephemeral in-memory database, discarded when the process exits
+    Memory,
+    /// Persistent RocksDB database at the given path
+    RocksDb(PathBuf),
+    /// No caching: lookups always miss and writes are discarded
+    Disabled,
+}
+
+impl CacheBackend {
+    /// Path of the on-disk database, if the backend persists to disk
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            CacheBackend::RocksDb(path) => Some(path),
+            CacheBackend::Memory | CacheBackend::Disabled => None,
+        }
+    }
+
+    /// Whether cache lookups and writes are performed at all
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, CacheBackend::Disabled)
+    }
+}
+
+/// Open a database connection for the given backend
+///
+/// `Disabled` still opens an in-memory database so that schema application and
+/// components holding a raw connection behave identically; the `CacheOperations`
+/// built on top of it is what turns caching off.
+///
+/// If an on-disk database is locked by another process, opening it is retried
+/// with backoff for up to `lock_wait` before `CacheError::DatabaseLocked` is
+/// returned. `None` fails on the first attempt.
+pub async fn connect_backend(
+    backend: &CacheBackend,
+    lock_wait: Option<Duration>,
+) -> Result<Surreal<Db>> {
+    match backend {
+        CacheBackend::RocksDb(path) => init_database_with_lock_wait(path, lock_wait).await,
+        CacheBackend::Memory | CacheBackend::Disabled => init_memory_database().await,
+    }
+}
+
+/// Open an on-disk database, waiting up to `lock_wait` for another process to release it
+#[instrument(skip_all, fields(path = %db_path.as_ref().display()))]
+pub async fn init_database_with_lock_wait(
+    db_path: impl AsRef<Path>,
+    lock_wait: Option<Duration>,
+) -> Result<Surreal<Db>> {
+    let path = db_path.as_ref();
+    let deadline = Instant::now() + lock_wait.unwrap_or(Duration::ZERO);
+    let mut delay = LOCK_RETRY_DELAY;
+
+    loop {
+        match init_database(path).await {
+            Err(CompositionError::Cache(CacheError::DatabaseLocked { .. }))
+                if Instant::now() < deadline =>
+            {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                warn!(
+                    "Database at {} is locked, retrying in {:?}",
+                    path.display(),
+                    delay.min(remaining)
+                );
+                tokio::time::sleep(delay.min(remaining)).await;
+                delay = (delay * 2).min(MAX_LOCK_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Initialize an in-memory SurrealDB database connection
+#[instrument]
+pub async fn init_memory_database() -> Result<Surreal<Db>> {
+    info!("Initializing in-memory SurrealDB");
+
+    let db = Surreal::new::<Mem>(()).await.map_err(|e| {
+        CacheError::ConnectionFailed(format!("In-memory connection failed: {}", e))
+    })?;
+
+    db.use_ns("composition")
+        .use_db("composition")
+        .await
+        .map_err(|e| CacheError::ConnectionFailed(format!("Failed to select namespace/database: {}", e)))?;
+
+    Ok(db)
+}
 
 /// Initialize a SurrealDB database connection
 #[instrument(skip_all, fields(path = %db_path.as_ref().display()))]
@@ -25,7 +123,15 @@ pub async fn init_database(db_path: impl AsRef<Path>) -> Result<Surreal<Db>> {
 
     // Connect to RocksDB backend
     let db = Surreal::new::<RocksDb>(path).await.map_err(|e| {
-        CacheError::ConnectionFailed(format!("RocksDB connection failed: {}", e))
+        let message = e.to_string();
+        if is_lock_error(&message) {
+            CacheError::DatabaseLocked {
+                path: path.to_path_buf(),
+                holder_hint: lock_holder_hint(path),
+            }
+        } else {
+            CacheError::ConnectionFailed(format!("RocksDB connection failed: {}", message))
+        }
     })?;
 
     // Use default namespace and database
@@ -39,6 +145,27 @@ pub async fn init_database(db_path: impl AsRef<Path>) -> Result<Surreal<Db>> {
     Ok(db)
 }
 
+/// Whether a connection error means RocksDB's `LOCK` file is held elsewhere
+///
+/// RocksDB reports this as an IO error: "While lock file: ...: Resource
+/// temporarily unavailable" from another process, or "lock hold by current
+/// process" when the same process opens the path twice.
+fn is_lock_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("while lock file")
+        || message.contains("lock hold by")
+        || message.contains("no locks available")
+}
+
+/// Describe who is most likely holding the database lock
+fn lock_holder_hint(path: &Path) -> String {
+    format!(
+        "{} is held by another composition process (for example an editor preview \
+         or a concurrent build using the same project)",
+        path.join("LOCK").display()
+    )
+}
+
 /// Locate or create the database file path based on project scope
 #[instrument]
 pub fn locate_database_path(start_dir: Option<&Path>) -> Result<PathBuf> {
@@ -97,9 +224,22 @@ mod tests {
         let current = std::env::current_dir().unwrap();
         let git_root = find_git_root(&current);
 
-        if git_root.is_some() {
-            let root = git_root.unwrap();
+        if let Some(root) = git_root {
             assert!(root.join(".git").exists());
         }
     }
+
+    #[test]
+    fn test_is_lock_error() {
+        assert!(is_lock_error(
+            "There was a problem with the underlying datastore: IO error: While lock file: \
+             /tmp/project/.composition.db/LOCK: Resource temporarily unavailable"
+        ));
+        assert!(is_lock_error(
+            "IO error: lock hold by current process, acquire time 1700000000 acquiring thread \
+             42: /tmp/project/.composition.db/LOCK: No locks available"
+        ));
+        assert!(!is_lock_error("IO error: No such file or directory"));
+        assert!(!is_lock_error("Corruption: block checksum mismatch"));
+    }
 }
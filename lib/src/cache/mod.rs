@@ -1,6 +1,7 @@
 pub mod database;
 pub mod operations;
 pub mod schema;
+mod retry;
 
 pub use database::*;
 pub use operations::*;
@@ -1,6 +1,19 @@
+use crate::audio::cache::AudioCacheEntryInternal;
 use crate::error::{CacheError, Result};
+use crate::types::Document;
+use super::retry::with_retry;
+use super::schema::SCHEMA_VERSION;
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use surrealdb::engine::local::Db;
 use surrealdb::sql::Datetime as SurrealDatetime;
 use surrealdb::Surreal;
@@ -16,6 +29,33 @@ fn from_surreal_datetime(dt: &SurrealDatetime) -> DateTime<Utc> {
     dt.0
 }
 
+/// Drop all but the last entry for each key, keeping first-seen order
+///
+/// Returns the distinct keys alongside the surviving entries.
+fn dedupe_last_wins<T, K, F>(entries: Vec<T>, key: F) -> (Vec<K>, Vec<T>)
+where
+    K: std::hash::Hash + Eq + Clone,
+    F: Fn(&T) -> K,
+{
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    let mut keys = Vec::with_capacity(entries.len());
+    let mut deduped: Vec<T> = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let k = key(&entry);
+        match positions.get(&k) {
+            Some(&index) => deduped[index] = entry,
+            None => {
+                positions.insert(k.clone(), deduped.len());
+                keys.push(k);
+                deduped.push(entry);
+            }
+        }
+    }
+
+    (keys, deduped)
+}
+
 /// Document cache entry (internal representation using SurrealDB types)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DocumentCacheEntryInternal {
@@ -26,6 +66,10 @@ struct DocumentCacheEntryInternal {
     pub file_path: Option<String>,
     pub url: Option<String>,
     pub last_validated: SurrealDatetime,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub modified_at: Option<SurrealDatetime>,
 }
 
 /// Document cache entry (public API using chrono types)
@@ -37,6 +81,10 @@ pub struct DocumentCacheEntry {
     pub file_path: Option<String>,
     pub url: Option<String>,
     pub last_validated: DateTime<Utc>,
+    /// Size of the local file when `content_hash` was computed
+    pub file_size: Option<u64>,
+    /// Modification time of the local file when `content_hash` was computed
+    pub modified_at: Option<DateTime<Utc>>,
 }
 
 impl From<DocumentCacheEntryInternal> for DocumentCacheEntry {
@@ -48,6 +96,8 @@ impl From<DocumentCacheEntryInternal> for DocumentCacheEntry {
             file_path: internal.file_path,
             url: internal.url,
             last_validated: from_surreal_datetime(&internal.last_validated),
+            file_size: internal.file_size,
+            modified_at: internal.modified_at.as_ref().map(from_surreal_datetime),
         }
     }
 }
@@ -61,6 +111,8 @@ impl From<DocumentCacheEntry> for DocumentCacheEntryInternal {
             file_path: entry.file_path,
             url: entry.url,
             last_validated: to_surreal_datetime(entry.last_validated),
+            file_size: entry.file_size,
+            modified_at: entry.modified_at.map(to_surreal_datetime),
         }
     }
 }
@@ -79,6 +131,9 @@ struct ImageCacheEntryInternal {
     pub has_transparency: bool,
     pub original_width: i64,
     pub original_height: i64,
+    /// Hex digits, since the hash doesn't fit SurrealDB's signed integers
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
 }
 
 /// Image cache entry (public API)
@@ -94,6 +149,8 @@ pub struct ImageCacheEntry {
     pub has_transparency: bool,
     pub original_width: i64,
     pub original_height: i64,
+    /// Perceptual hash of the image (see [`phash`](crate::image::phash))
+    pub perceptual_hash: Option<u64>,
 }
 
 impl From<ImageCacheEntryInternal> for ImageCacheEntry {
@@ -109,6 +166,7 @@ impl From<ImageCacheEntryInternal> for ImageCacheEntry {
             has_transparency: internal.has_transparency,
             original_width: internal.original_width,
             original_height: internal.original_height,
+            perceptual_hash: internal.perceptual_hash.and_then(|hash| u64::from_str_radix(&hash, 16).ok()),
         }
     }
 }
@@ -126,6 +184,7 @@ impl From<ImageCacheEntry> for ImageCacheEntryInternal {
             has_transparency: entry.has_transparency,
             original_width: entry.original_width,
             original_height: entry.original_height,
+            perceptual_hash: entry.perceptual_hash.map(|hash| format!("{:016x}", hash)),
         }
     }
 }
@@ -187,19 +246,270 @@ impl From<LlmCacheEntry> for LlmCacheEntryInternal {
     }
 }
 
+/// Rendered document entry (internal representation)
+///
+/// The document is stored as JSON so that the schema doesn't have to track
+/// the shape of the node tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RenderedDocumentEntryInternal {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<surrealdb::sql::Thing>,
+    pub resource_hash: String,
+    pub content_hash: String,
+    pub document: String,
+    pub rendered_at: SurrealDatetime,
+}
+
+/// Rendered output of a document (public API)
+#[derive(Debug, Clone)]
+pub struct RenderedDocumentEntry {
+    pub resource_hash: String,
+    /// Hash of the source content the document was rendered from
+    pub content_hash: String,
+    pub document: Document,
+    pub rendered_at: DateTime<Utc>,
+}
+
+impl TryFrom<RenderedDocumentEntryInternal> for RenderedDocumentEntry {
+    type Error = CacheError;
+
+    fn try_from(internal: RenderedDocumentEntryInternal) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            resource_hash: internal.resource_hash,
+            content_hash: internal.content_hash,
+            document: serde_json::from_str(&internal.document)
+                .map_err(|e| CacheError::DeserializationError(e.to_string()))?,
+            rendered_at: from_surreal_datetime(&internal.rendered_at),
+        })
+    }
+}
+
+impl TryFrom<RenderedDocumentEntry> for RenderedDocumentEntryInternal {
+    type Error = CacheError;
+
+    fn try_from(entry: RenderedDocumentEntry) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            id: None,
+            resource_hash: entry.resource_hash,
+            content_hash: entry.content_hash,
+            document: serde_json::to_string(&entry.document)
+                .map_err(|e| CacheError::SerializationError(e.to_string()))?,
+            rendered_at: to_surreal_datetime(entry.rendered_at),
+        })
+    }
+}
+
+/// Embedding cache entry (internal representation)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EmbeddingCacheEntryInternal {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<surrealdb::sql::Thing>,
+    pub content_hash: String,
+    pub model: String,
+    pub vector: Vec<f32>,
+    pub created_at: SurrealDatetime,
+}
+
+/// Embedding of a piece of text (public API)
+#[derive(Debug, Clone)]
+pub struct EmbeddingCacheEntry {
+    /// Hash of the embedded text, the entry's key together with `model`
+    pub content_hash: String,
+    pub model: String,
+    pub vector: Vec<f32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<EmbeddingCacheEntryInternal> for EmbeddingCacheEntry {
+    fn from(internal: EmbeddingCacheEntryInternal) -> Self {
+        Self {
+            content_hash: internal.content_hash,
+            model: internal.model,
+            vector: internal.vector,
+            created_at: from_surreal_datetime(&internal.created_at),
+        }
+    }
+}
+
+impl From<EmbeddingCacheEntry> for EmbeddingCacheEntryInternal {
+    fn from(entry: EmbeddingCacheEntry) -> Self {
+        Self {
+            id: None,
+            content_hash: entry.content_hash,
+            model: entry.model,
+            vector: entry.vector,
+            created_at: to_surreal_datetime(entry.created_at),
+        }
+    }
+}
+
+/// Downloaded remote audio entry (internal representation)
+///
+/// The audio is stored base64 encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RemoteAudioEntryInternal {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<surrealdb::sql::Thing>,
+    pub url_hash: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub data: String,
+    pub fetched_at: SurrealDatetime,
+}
+
+/// Audio downloaded from a URL (public API)
+#[derive(Debug, Clone)]
+pub struct RemoteAudioEntry {
+    /// Hash of the canonicalized URL, the entry's key
+    pub url_hash: String,
+    /// The canonicalized URL
+    pub url: String,
+    /// `Content-Type` the server sent with the audio
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl TryFrom<RemoteAudioEntryInternal> for RemoteAudioEntry {
+    type Error = CacheError;
+
+    fn try_from(internal: RemoteAudioEntryInternal) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            url_hash: internal.url_hash,
+            url: internal.url,
+            content_type: internal.content_type,
+            data: general_purpose::STANDARD
+                .decode(&internal.data)
+                .map_err(|e| CacheError::DeserializationError(e.to_string()))?,
+            fetched_at: from_surreal_datetime(&internal.fetched_at),
+        })
+    }
+}
+
+impl From<RemoteAudioEntry> for RemoteAudioEntryInternal {
+    fn from(entry: RemoteAudioEntry) -> Self {
+        Self {
+            id: None,
+            url_hash: entry.url_hash,
+            url: entry.url,
+            content_type: entry.content_type,
+            data: general_purpose::STANDARD.encode(&entry.data),
+            fetched_at: to_surreal_datetime(entry.fetched_at),
+        }
+    }
+}
+
+/// Rough size in bytes of one cached document, used by [`CacheStats`]
+const DOCUMENT_ENTRY_SIZE: u64 = 256;
+/// Rough size in bytes of one cached image's metadata
+const IMAGE_ENTRY_SIZE: u64 = 320;
+/// Rough size in bytes of one cached audio file's metadata
+const AUDIO_ENTRY_SIZE: u64 = 320;
+/// Rough size in bytes of one cached LLM response
+const LLM_ENTRY_SIZE: u64 = 2048;
+
+/// Entry counts and ages across the cache tables, from [`CacheOperations::stats`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    pub document_count: usize,
+    pub image_count: usize,
+    pub audio_count: usize,
+    pub llm_count: usize,
+    /// Estimated size of the entries above: each table's count times a
+    /// typical entry size. Embedded SurrealDB has no reliable way to measure
+    /// the stored size of a row, so treat this as an order of magnitude
+    pub total_size_estimate_bytes: u64,
+    /// When the oldest entry was created (documents: last validated)
+    pub oldest_entry: Option<DateTime<Utc>>,
+    /// When the newest entry was created (documents: last validated)
+    pub newest_entry: Option<DateTime<Utc>>,
+    /// Image and LLM entries past their expiry that haven't been cleaned up
+    pub expired_count: usize,
+    /// Lookups that found an entry, when hit/miss tracking is enabled
+    pub hits: Option<u64>,
+    /// Lookups that found nothing, when hit/miss tracking is enabled
+    pub misses: Option<u64>,
+}
+
+impl CacheStats {
+    /// The share of lookups that found an entry, between 0 and 1
+    ///
+    /// `None` unless hit/miss tracking is enabled (see
+    /// [`CacheOperations::with_metrics`]) and at least one lookup was made.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let (hits, misses) = (self.hits?, self.misses?);
+        let lookups = hits + misses;
+        (lookups > 0).then(|| hits as f64 / lookups as f64)
+    }
+}
+
+/// Aggregates of one cache table (internal representation)
+#[derive(Debug, Deserialize)]
+struct TableStatsInternal {
+    count: usize,
+    oldest: Option<SurrealDatetime>,
+    newest: Option<SurrealDatetime>,
+    #[serde(default)]
+    expired: usize,
+}
+
+/// Hit and miss counters of a [`CacheOperations`]
+#[derive(Debug, Default)]
+struct LookupMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
 /// Cache operations trait for different cache types
 pub struct CacheOperations {
     db: Surreal<Db>,
+    enabled: bool,
+    metrics: Option<LookupMetrics>,
 }
 
 impl CacheOperations {
     pub fn new(db: Surreal<Db>) -> Self {
-        Self { db }
+        Self { db, enabled: true, metrics: None }
+    }
+
+    /// Create a no-op passthrough: every lookup misses and writes are discarded
+    pub fn disabled(db: Surreal<Db>) -> Self {
+        Self { db, enabled: false, metrics: None }
+    }
+
+    /// Count the hits and misses of every lookup, for [`CacheStats::hit_rate`]
+    ///
+    /// Adds an atomic update to each lookup.
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(LookupMetrics::default());
+        self
+    }
+
+    /// Record the outcome of a lookup of `requested` keys that found `found`
+    fn record_lookups(&self, found: usize, requested: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.hits.fetch_add(found as u64, Ordering::Relaxed);
+            metrics.misses.fetch_add(requested.saturating_sub(found) as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether this instance actually reads from and writes to the database
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The database this cache reads from and writes to
+    pub(crate) fn db(&self) -> &Surreal<Db> {
+        &self.db
     }
 
     /// Get a document cache entry by resource hash
     #[instrument(skip(self))]
     pub async fn get_document(&self, resource_hash: &str) -> Result<Option<DocumentCacheEntry>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
         debug!("Getting document cache entry for hash: {}", resource_hash);
 
         let mut result = self
@@ -212,21 +522,87 @@ impl CacheOperations {
         let entry: Option<DocumentCacheEntryInternal> = result
             .take(0)
             .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(usize::from(entry.is_some()), 1);
 
         Ok(entry.map(DocumentCacheEntry::from))
     }
 
+    /// Get document cache entries for many resource hashes with a single query
+    ///
+    /// The returned map is keyed by resource hash; hashes with no cached entry
+    /// are simply absent from it.
+    #[instrument(skip(self, resource_hashes), fields(count = resource_hashes.len()))]
+    pub async fn get_documents(
+        &self,
+        resource_hashes: &[&str],
+    ) -> Result<HashMap<String, DocumentCacheEntry>> {
+        if !self.enabled || resource_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Getting {} document cache entries", resource_hashes.len());
+
+        let hashes: Vec<String> = resource_hashes.iter().map(|h| h.to_string()).collect();
+        let mut result = self
+            .db
+            .query("SELECT * FROM document WHERE resource_hash IN $hashes")
+            .bind(("hashes", hashes))
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        let entries: Vec<DocumentCacheEntryInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(entries.len(), resource_hashes.len());
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.resource_hash.clone(), DocumentCacheEntry::from(entry)))
+            .collect())
+    }
+
     /// Upsert a document cache entry
+    ///
+    /// Replaces any existing entry with the same `resource_hash`.
     #[instrument(skip(self, entry))]
     pub async fn upsert_document(&self, entry: DocumentCacheEntry) -> Result<()> {
         debug!("Upserting document cache entry for hash: {}", entry.resource_hash);
+        self.upsert_documents(vec![entry]).await
+    }
 
-        let internal: DocumentCacheEntryInternal = entry.into();
-        let _created: Vec<DocumentCacheEntryInternal> = self.db
-            .create("document")
-            .content(internal)
-            .await
-            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+    /// Upsert many document cache entries in a single transaction
+    ///
+    /// Existing entries with a matching `resource_hash` are replaced. When the
+    /// same hash appears more than once in `entries` the last one wins.
+    #[instrument(skip(self, entries), fields(count = entries.len()))]
+    pub async fn upsert_documents(&self, entries: Vec<DocumentCacheEntry>) -> Result<()> {
+        if !self.enabled || entries.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Bulk upserting {} document cache entries", entries.len());
+
+        let (hashes, entries) = dedupe_last_wins(entries, |entry| entry.resource_hash.clone());
+        let internals: Vec<DocumentCacheEntryInternal> =
+            entries.into_iter().map(DocumentCacheEntryInternal::from).collect();
+
+        with_retry("upsert_documents", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE document WHERE resource_hash IN $hashes;
+                    INSERT INTO document $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hashes", hashes.clone()))
+                .bind(("entries", internals.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
 
         Ok(())
     }
@@ -234,6 +610,10 @@ impl CacheOperations {
     /// Get an image cache entry by resource hash
     #[instrument(skip(self))]
     pub async fn get_image(&self, resource_hash: &str) -> Result<Option<ImageCacheEntry>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
         debug!("Getting image cache entry for hash: {}", resource_hash);
 
         let mut result = self
@@ -246,25 +626,122 @@ impl CacheOperations {
         let entry: Option<ImageCacheEntryInternal> = result
             .take(0)
             .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(usize::from(entry.is_some()), 1);
 
         Ok(entry.map(ImageCacheEntry::from))
     }
 
     /// Upsert an image cache entry
+    ///
+    /// Replaces any existing entry with the same `resource_hash`.
     #[instrument(skip(self, entry))]
     pub async fn upsert_image(&self, entry: ImageCacheEntry) -> Result<()> {
         debug!("Upserting image cache entry for hash: {}", entry.resource_hash);
+        self.upsert_images(vec![entry]).await
+    }
+
+    /// Get image cache entries for many resource hashes with a single query
+    ///
+    /// The returned map is keyed by resource hash; hashes with no cached entry
+    /// are simply absent from it.
+    #[instrument(skip(self, resource_hashes), fields(count = resource_hashes.len()))]
+    pub async fn get_images(
+        &self,
+        resource_hashes: &[&str],
+    ) -> Result<HashMap<String, ImageCacheEntry>> {
+        if !self.enabled || resource_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Getting {} image cache entries", resource_hashes.len());
 
-        let internal: ImageCacheEntryInternal = entry.into();
-        let _created: Vec<ImageCacheEntryInternal> = self.db
-            .create("image_cache")
-            .content(internal)
+        let hashes: Vec<String> = resource_hashes.iter().map(|h| h.to_string()).collect();
+        let mut result = self
+            .db
+            .query("SELECT * FROM image_cache WHERE resource_hash IN $hashes")
+            .bind(("hashes", hashes))
             .await
             .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
 
+        let entries: Vec<ImageCacheEntryInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(entries.len(), resource_hashes.len());
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.resource_hash.clone(), ImageCacheEntry::from(entry)))
+            .collect())
+    }
+
+    /// Upsert many image cache entries in a single transaction
+    ///
+    /// Existing entries with a matching `resource_hash` are replaced. When the
+    /// same hash appears more than once in `entries` the last one wins.
+    #[instrument(skip(self, entries), fields(count = entries.len()))]
+    pub async fn upsert_images(&self, entries: Vec<ImageCacheEntry>) -> Result<()> {
+        if !self.enabled || entries.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Bulk upserting {} image cache entries", entries.len());
+
+        let (hashes, entries) = dedupe_last_wins(entries, |entry| entry.resource_hash.clone());
+        let internals: Vec<ImageCacheEntryInternal> =
+            entries.into_iter().map(ImageCacheEntryInternal::from).collect();
+
+        with_retry("upsert_images", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE image_cache WHERE resource_hash IN $hashes;
+                    INSERT INTO image_cache $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hashes", hashes.clone()))
+                .bind(("entries", internals.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
         Ok(())
     }
 
+    /// Get the perceptual hash of every cached image that has one
+    ///
+    /// Returns `(resource_hash, perceptual_hash)` pairs, for comparing an
+    /// image against all the others (see [`phash`](crate::image::phash)).
+    #[instrument(skip(self))]
+    pub async fn get_perceptual_hashes(&self) -> Result<Vec<(String, u64)>> {
+        #[derive(Deserialize)]
+        struct PerceptualHashRow {
+            resource_hash: String,
+            perceptual_hash: String,
+        }
+
+        if !self.enabled {
+            return Ok(Vec::new());
+        }
+
+        let mut result = self
+            .db
+            .query("SELECT resource_hash, perceptual_hash FROM image_cache WHERE perceptual_hash != NONE")
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+        let rows: Vec<PerceptualHashRow> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((row.resource_hash, u64::from_str_radix(&row.perceptual_hash, 16).ok()?)))
+            .collect())
+    }
+
     /// Get an LLM cache entry
     #[instrument(skip(self))]
     pub async fn get_llm(
@@ -273,6 +750,10 @@ impl CacheOperations {
         input_hash: &str,
         model: &str,
     ) -> Result<Option<LlmCacheEntry>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
         debug!("Getting LLM cache entry for operation: {}, model: {}", operation, model);
 
         let mut result = self
@@ -296,88 +777,692 @@ impl CacheOperations {
         let entry: Option<LlmCacheEntryInternal> = result
             .take(0)
             .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(usize::from(entry.is_some()), 1);
 
         Ok(entry.map(LlmCacheEntry::from))
     }
 
     /// Upsert an LLM cache entry
+    ///
+    /// Replaces any existing entry with the same operation, input hash and model.
     #[instrument(skip(self, entry))]
     pub async fn upsert_llm(&self, entry: LlmCacheEntry) -> Result<()> {
         debug!("Upserting LLM cache entry for operation: {}", entry.operation);
+        self.upsert_llms(vec![entry]).await
+    }
 
-        let internal: LlmCacheEntryInternal = entry.into();
-        let _created: Vec<LlmCacheEntryInternal> = self.db
-            .create("llm_cache")
-            .content(internal)
+    /// Get unexpired LLM cache entries for many inputs with a single query
+    ///
+    /// All inputs share one `operation` and `model`. The returned map is keyed
+    /// by input hash; inputs with no live entry are simply absent from it.
+    #[instrument(skip(self, input_hashes), fields(count = input_hashes.len()))]
+    pub async fn get_llms(
+        &self,
+        operation: &str,
+        model: &str,
+        input_hashes: &[&str],
+    ) -> Result<HashMap<String, LlmCacheEntry>> {
+        if !self.enabled || input_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!(
+            "Getting {} LLM cache entries for operation: {}, model: {}",
+            input_hashes.len(),
+            operation,
+            model
+        );
+
+        let hashes: Vec<String> = input_hashes.iter().map(|h| h.to_string()).collect();
+        let mut result = self
+            .db
+            .query(
+                r#"
+                SELECT * FROM llm_cache
+                WHERE operation = $operation
+                AND input_hash IN $input_hashes
+                AND model = $model
+                AND expires_at > $now
+                "#,
+            )
+            .bind(("operation", operation))
+            .bind(("input_hashes", hashes))
+            .bind(("model", model))
+            .bind(("now", to_surreal_datetime(Utc::now())))
             .await
             .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
 
+        let entries: Vec<LlmCacheEntryInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(entries.len(), input_hashes.len());
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.input_hash.clone(), LlmCacheEntry::from(entry)))
+            .collect())
+    }
+
+    /// Upsert many LLM cache entries in a single transaction
+    ///
+    /// Existing entries with the same operation, input hash and model are
+    /// replaced. When that key appears more than once in `entries` the last
+    /// one wins.
+    #[instrument(skip(self, entries), fields(count = entries.len()))]
+    pub async fn upsert_llms(&self, entries: Vec<LlmCacheEntry>) -> Result<()> {
+        if !self.enabled || entries.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Bulk upserting {} LLM cache entries", entries.len());
+
+        let (keys, entries) = dedupe_last_wins(entries, |entry| {
+            vec![entry.operation.clone(), entry.input_hash.clone(), entry.model.clone()]
+        });
+        let internals: Vec<LlmCacheEntryInternal> =
+            entries.into_iter().map(LlmCacheEntryInternal::from).collect();
+
+        with_retry("upsert_llms", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE llm_cache WHERE [operation, input_hash, model] IN $keys;
+                    INSERT INTO llm_cache $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("keys", keys.clone()))
+                .bind(("entries", internals.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
         Ok(())
     }
 
-    /// Invalidate a document and cascade to dependents
-    #[instrument(skip(self))]
-    pub async fn invalidate_document_cascade(&self, resource_hash: &str) -> Result<Vec<String>> {
-        debug!("Invalidating document cascade for hash: {}", resource_hash);
+    /// Get the cached embeddings of many texts with a single query
+    ///
+    /// The returned map is keyed by content hash; texts the model hasn't
+    /// embedded yet are simply absent from it.
+    #[instrument(skip(self, content_hashes), fields(count = content_hashes.len()))]
+    pub async fn get_embeddings(
+        &self,
+        model: &str,
+        content_hashes: &[&str],
+    ) -> Result<HashMap<String, EmbeddingCacheEntry>> {
+        if !self.enabled || content_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Getting {} cached embeddings for model: {}", content_hashes.len(), model);
 
-        // Find all documents that depend on this one (transitively)
+        let hashes: Vec<String> = content_hashes.iter().map(|h| h.to_string()).collect();
         let mut result = self
             .db
             .query(
                 r#"
-                SELECT resource_hash FROM (
-                    SELECT ->depends_on->document AS dependents
-                    FROM document
-                    WHERE resource_hash = $hash
-                ).dependents.*
+                SELECT * FROM embedding_cache
+                WHERE content_hash IN $content_hashes
+                AND model = $model
                 "#,
             )
-            .bind(("hash", resource_hash))
+            .bind(("content_hashes", hashes))
+            .bind(("model", model.to_string()))
             .await
             .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
 
-        #[derive(Deserialize)]
-        struct HashResult {
-            resource_hash: String,
+        let entries: Vec<EmbeddingCacheEntryInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(entries.len(), content_hashes.len());
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.content_hash.clone(), EmbeddingCacheEntry::from(entry)))
+            .collect())
+    }
+
+    /// Upsert many embeddings in a single transaction
+    ///
+    /// Existing entries with the same content hash and model are replaced.
+    /// When that key appears more than once in `entries` the last one wins.
+    #[instrument(skip(self, entries), fields(count = entries.len()))]
+    pub async fn upsert_embeddings(&self, entries: Vec<EmbeddingCacheEntry>) -> Result<()> {
+        if !self.enabled || entries.is_empty() {
+            return Ok(());
         }
 
-        let dependents: Vec<HashResult> = result
+        debug!("Bulk upserting {} embeddings", entries.len());
+
+        let (keys, entries) = dedupe_last_wins(entries, |entry| {
+            vec![entry.content_hash.clone(), entry.model.clone()]
+        });
+        let internals: Vec<EmbeddingCacheEntryInternal> =
+            entries.into_iter().map(EmbeddingCacheEntryInternal::from).collect();
+
+        with_retry("upsert_embeddings", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE embedding_cache WHERE [content_hash, model] IN $keys;
+                    INSERT INTO embedding_cache $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("keys", keys.clone()))
+                .bind(("entries", internals.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Get the stored rendered output for many resource hashes with a single query
+    ///
+    /// The returned map is keyed by resource hash; hashes with nothing stored
+    /// are simply absent from it.
+    #[instrument(skip(self, resource_hashes), fields(count = resource_hashes.len()))]
+    pub async fn get_rendered_documents(
+        &self,
+        resource_hashes: &[&str],
+    ) -> Result<HashMap<String, RenderedDocumentEntry>> {
+        if !self.enabled || resource_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        debug!("Getting {} rendered documents", resource_hashes.len());
+
+        let hashes: Vec<String> = resource_hashes.iter().map(|h| h.to_string()).collect();
+        let mut result = self
+            .db
+            .query("SELECT * FROM rendered_document WHERE resource_hash IN $hashes")
+            .bind(("hashes", hashes))
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        let entries: Vec<RenderedDocumentEntryInternal> = result
             .take(0)
             .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(entries.len(), resource_hashes.len());
 
-        let invalidated_hashes: Vec<String> = dependents
+        entries
             .into_iter()
-            .map(|h| h.resource_hash)
-            .collect();
+            .map(|entry| {
+                let entry = RenderedDocumentEntry::try_from(entry)?;
+                Ok((entry.resource_hash.clone(), entry))
+            })
+            .collect()
+    }
 
-        // Delete the document and its dependents
-        self.db
-            .query("DELETE FROM document WHERE resource_hash = $hash")
-            .bind(("hash", resource_hash))
+    /// Store the rendered output of many documents in a single transaction
+    ///
+    /// Existing entries with a matching `resource_hash` are replaced. When the
+    /// same hash appears more than once in `entries` the last one wins.
+    #[instrument(skip(self, entries), fields(count = entries.len()))]
+    pub async fn upsert_rendered_documents(&self, entries: Vec<RenderedDocumentEntry>) -> Result<()> {
+        if !self.enabled || entries.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Bulk upserting {} rendered documents", entries.len());
+
+        let (hashes, entries) = dedupe_last_wins(entries, |entry| entry.resource_hash.clone());
+        let internals = entries
+            .into_iter()
+            .map(RenderedDocumentEntryInternal::try_from)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        with_retry("upsert_rendered_documents", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE rendered_document WHERE resource_hash IN $hashes;
+                    INSERT INTO rendered_document $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hashes", hashes.clone()))
+                .bind(("entries", internals.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Get downloaded remote audio by the hash of its canonicalized URL
+    #[instrument(skip(self))]
+    pub async fn get_remote_audio(&self, url_hash: &str) -> Result<Option<RemoteAudioEntry>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        debug!("Getting remote audio for URL hash: {}", url_hash);
+
+        let mut result = self
+            .db
+            .query("SELECT * FROM remote_audio WHERE url_hash = $hash")
+            .bind(("hash", url_hash))
             .await
-            .map_err(|e| CacheError::InvalidationFailed(e.to_string()))?;
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        let entry: Option<RemoteAudioEntryInternal> = result
+            .take(0)
+            .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+        self.record_lookups(usize::from(entry.is_some()), 1);
+
+        Ok(entry.map(RemoteAudioEntry::try_from).transpose()?)
+    }
+
+    /// Store downloaded remote audio
+    ///
+    /// Replaces any existing entry with the same `url_hash`.
+    #[instrument(skip(self, entry), fields(url = %entry.url))]
+    pub async fn upsert_remote_audio(&self, entry: RemoteAudioEntry) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
 
-        for dep_hash in &invalidated_hashes {
+        debug!("Upserting remote audio for URL hash: {}", entry.url_hash);
+
+        let url_hash = entry.url_hash.clone();
+        let internal = RemoteAudioEntryInternal::from(entry);
+
+        with_retry("upsert_remote_audio", || async {
             self.db
-                .query("DELETE FROM document WHERE resource_hash = $hash")
-                .bind(("hash", dep_hash))
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE remote_audio WHERE url_hash = $hash;
+                    INSERT INTO remote_audio $entry;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hash", url_hash.clone()))
+                .bind(("entry", internal.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Invalidate a document and cascade to dependents
+    ///
+    /// Follows `depends_on` relations in reverse to find every document that
+    /// transitively depends on `resource_hash`, then deletes all of them.
+    /// Returns the hashes of the dependents that were invalidated.
+    #[instrument(skip(self))]
+    pub async fn invalidate_document_cascade(&self, resource_hash: &str) -> Result<Vec<String>> {
+        if !self.enabled {
+            return Ok(Vec::new());
+        }
+
+        debug!("Invalidating document cascade for hash: {}", resource_hash);
+
+        let mut seen: HashSet<String> = HashSet::from([resource_hash.to_string()]);
+        let mut invalidated_hashes: Vec<String> = Vec::new();
+        let mut frontier: Vec<String> = vec![resource_hash.to_string()];
+
+        while !frontier.is_empty() {
+            let mut result = self
+                .db
+                .query(
+                    "SELECT VALUE <-depends_on<-document.resource_hash FROM document WHERE resource_hash IN $hashes",
+                )
+                .bind(("hashes", frontier.clone()))
                 .await
-                .map_err(|e| CacheError::InvalidationFailed(e.to_string()))?;
+                .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+            let dependents: Vec<Vec<String>> = result
+                .take(0)
+                .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+
+            frontier = dependents
+                .into_iter()
+                .flatten()
+                .filter(|hash| seen.insert(hash.clone()))
+                .collect();
+            invalidated_hashes.extend(frontier.iter().cloned());
         }
 
+        let all: Vec<String> = seen.into_iter().collect();
+        with_retry("invalidate_document", || async {
+            self.db
+                .query("DELETE FROM document WHERE resource_hash IN $hashes")
+                .bind(("hashes", all.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::InvalidationFailed(e.to_string()))?;
+
         Ok(invalidated_hashes)
     }
 
     /// Invalidate an image cache entry
     #[instrument(skip(self))]
     pub async fn invalidate_image(&self, resource_hash: &str) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
         debug!("Invalidating image cache entry for hash: {}", resource_hash);
 
-        self.db
-            .query("DELETE FROM image_cache WHERE resource_hash = $hash")
-            .bind(("hash", resource_hash))
+        with_retry("invalidate_image", || async {
+            self.db
+                .query("DELETE FROM image_cache WHERE resource_hash = $hash")
+                .bind(("hash", resource_hash))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::InvalidationFailed(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Delete every cached document
+    pub async fn clear_documents(&self) -> Result<()> {
+        self.clear_tables("clear_documents", &["document"]).await
+    }
+
+    /// Delete every cached image
+    pub async fn clear_images(&self) -> Result<()> {
+        self.clear_tables("clear_images", &["image_cache"]).await
+    }
+
+    /// Delete every cached LLM response
+    pub async fn clear_llm(&self) -> Result<()> {
+        self.clear_tables("clear_llm", &["llm_cache"]).await
+    }
+
+    /// Delete every cached rendered document
+    pub async fn clear_rendered_documents(&self) -> Result<()> {
+        self.clear_tables("clear_rendered_documents", &["rendered_document"]).await
+    }
+
+    /// Delete all downloaded remote audio
+    pub async fn clear_remote_audio(&self) -> Result<()> {
+        self.clear_tables("clear_remote_audio", &["remote_audio"]).await
+    }
+
+    /// Delete all documents, images, LLM responses and rendered documents
+    ///
+    /// The tables are cleared in a single transaction. Rendered documents are
+    /// included so that no output survives without the cache entries it was
+    /// built from.
+    pub async fn clear_all(&self) -> Result<()> {
+        self.clear_tables(
+            "clear_all",
+            &["document", "image_cache", "llm_cache", "rendered_document"],
+        )
+        .await
+    }
+
+    #[instrument(skip(self))]
+    async fn clear_tables(&self, operation: &str, tables: &[&str]) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        debug!("Clearing cache tables: {}", tables.join(", "));
+
+        let deletes: String = tables.iter().map(|table| format!("DELETE {};\n", table)).collect();
+        let query = format!("BEGIN TRANSACTION;\n{}COMMIT TRANSACTION;", deletes);
+
+        with_retry(operation, || async {
+            self.db.query(query.as_str()).await?.check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(format!("Failed to clear {}: {}", tables.join(", "), e)))?;
+
+        Ok(())
+    }
+
+    /// Count the entries of each cache table, with their ages
+    ///
+    /// Each table is summarized by one aggregate query. A disabled cache
+    /// reports nothing stored.
+    #[instrument(skip(self))]
+    pub async fn stats(&self) -> Result<CacheStats> {
+        let metrics = self.metrics.as_ref();
+        let mut stats = CacheStats {
+            hits: metrics.map(|m| m.hits.load(Ordering::Relaxed)),
+            misses: metrics.map(|m| m.misses.load(Ordering::Relaxed)),
+            ..CacheStats::default()
+        };
+        if !self.enabled {
+            return Ok(stats);
+        }
+
+        debug!("Collecting cache statistics");
+
+        let mut result = self
+            .db
+            .query(
+                r#"
+                SELECT count() AS count, time::min(last_validated) AS oldest, time::max(last_validated) AS newest
+                    FROM document GROUP ALL;
+                SELECT count() AS count, time::min(created_at) AS oldest, time::max(created_at) AS newest,
+                    count(expires_at != NONE AND expires_at < $now) AS expired
+                    FROM image_cache GROUP ALL;
+                SELECT count() AS count, time::min(created_at) AS oldest, time::max(created_at) AS newest
+                    FROM audio_cache GROUP ALL;
+                SELECT count() AS count, time::min(created_at) AS oldest, time::max(created_at) AS newest,
+                    count(expires_at < $now) AS expired
+                    FROM llm_cache GROUP ALL;
+                "#,
+            )
+            .bind(("now", to_surreal_datetime(Utc::now())))
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+
+        let tables = [
+            (&mut stats.document_count, DOCUMENT_ENTRY_SIZE),
+            (&mut stats.image_count, IMAGE_ENTRY_SIZE),
+            (&mut stats.audio_count, AUDIO_ENTRY_SIZE),
+            (&mut stats.llm_count, LLM_ENTRY_SIZE),
+        ];
+        let mut size = 0;
+        let mut expired = 0;
+        let mut oldest: Option<DateTime<Utc>> = None;
+        let mut newest: Option<DateTime<Utc>> = None;
+        for (index, (count, entry_size)) in tables.into_iter().enumerate() {
+            let table: Option<TableStatsInternal> = result
+                .take(index)
+                .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+            let Some(table) = table else { continue };
+
+            *count = table.count;
+            size += table.count as u64 * entry_size;
+            expired += table.expired;
+            if let Some(first) = table.oldest.as_ref().map(from_surreal_datetime) {
+                oldest = Some(oldest.map_or(first, |o| o.min(first)));
+            }
+            if let Some(last) = table.newest.as_ref().map(from_surreal_datetime) {
+                newest = Some(newest.map_or(last, |n| n.max(last)));
+            }
+        }
+        stats.total_size_estimate_bytes = size;
+        stats.expired_count = expired;
+        stats.oldest_entry = oldest;
+        stats.newest_entry = newest;
+
+        Ok(stats)
+    }
+
+    /// Write the document, image, audio and LLM caches to a portable file
+    ///
+    /// The file is gzip-compressed NDJSON: a header line with the schema
+    /// version, then one line per record naming its table. Record ids aren't
+    /// written, so the file can be imported into any database. A disabled
+    /// cache exports no records.
+    #[instrument(skip(self))]
+    pub async fn export(&self, path: &Path) -> Result<CacheExportReport> {
+        debug!("Exporting cache to {}", path.display());
+
+        let mut records = Vec::new();
+        let mut report = CacheExportReport::default();
+        if self.enabled {
+            let mut result = self
+                .db
+                .query(
+                    r#"
+                    SELECT * OMIT id FROM document;
+                    SELECT * OMIT id FROM image_cache;
+                    SELECT * OMIT id FROM audio_cache;
+                    SELECT * OMIT id FROM llm_cache;
+                    "#,
+                )
+                .await
+                .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+            let deserialization_error = |e: surrealdb::Error| CacheError::DeserializationError(e.to_string());
+
+            let documents: Vec<DocumentCacheEntryInternal> = result.take(0).map_err(deserialization_error)?;
+            let images: Vec<ImageCacheEntryInternal> = result.take(1).map_err(deserialization_error)?;
+            let audio: Vec<AudioCacheEntryInternal> = result.take(2).map_err(deserialization_error)?;
+            let llms: Vec<LlmCacheEntryInternal> = result.take(3).map_err(deserialization_error)?;
+
+            report.document_count = documents.len();
+            report.image_count = images.len();
+            report.audio_count = audio.len();
+            report.llm_count = llms.len();
+            records.extend(documents.into_iter().map(CacheRecord::Document));
+            records.extend(images.into_iter().map(CacheRecord::ImageCache));
+            records.extend(audio.into_iter().map(CacheRecord::AudioCache));
+            records.extend(llms.into_iter().map(CacheRecord::LlmCache));
+        }
+
+        let path_buf = path.to_path_buf();
+        report.file_size_bytes = tokio::task::spawn_blocking(move || write_export(&path_buf, &records))
             .await
-            .map_err(|e| CacheError::InvalidationFailed(e.to_string()))?;
+            .map_err(|e| CacheError::SerializationError(format!("Task join error: {}", e)))??;
+
+        Ok(report)
+    }
+
+    /// Load a file written by [`export()`](Self::export) into the cache
+    ///
+    /// The file must come from a database with the same schema version.
+    /// Records already cached with the same content hash (for LLM responses:
+    /// the same operation, input and model) are skipped, so importing a
+    /// file twice changes nothing. Other records replace any entry for the
+    /// same resource.
+    #[instrument(skip(self))]
+    pub async fn import(&self, path: &Path) -> Result<CacheImportReport> {
+        debug!("Importing cache from {}", path.display());
+
+        let path_buf = path.to_path_buf();
+        let records = tokio::task::spawn_blocking(move || read_export(&path_buf))
+            .await
+            .map_err(|e| CacheError::DeserializationError(format!("Task join error: {}", e)))??;
+
+        let mut report = CacheImportReport::default();
+        if !self.enabled {
+            return Ok(report);
+        }
+
+        let mut result = self
+            .db
+            .query(
+                r#"
+                SELECT resource_hash, content_hash FROM document;
+                SELECT resource_hash, content_hash FROM image_cache;
+                SELECT resource_hash, content_hash FROM audio_cache;
+                SELECT operation, input_hash, model FROM llm_cache;
+                "#,
+            )
+            .await
+            .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
+        let mut existing_keys = |index: usize, fields: &[&str]| -> Result<HashSet<Vec<String>>> {
+            let rows: Vec<HashMap<String, String>> = result
+                .take(index)
+                .map_err(|e| CacheError::DeserializationError(e.to_string()))?;
+            Ok(rows
+                .into_iter()
+                .map(|mut row| fields.iter().map(|field| row.remove(*field).unwrap_or_default()).collect())
+                .collect())
+        };
+        let existing_documents = existing_keys(0, &["resource_hash", "content_hash"])?;
+        let existing_images = existing_keys(1, &["resource_hash", "content_hash"])?;
+        let existing_audio = existing_keys(2, &["resource_hash", "content_hash"])?;
+        let existing_llms = existing_keys(3, &["operation", "input_hash", "model"])?;
+
+        let mut documents = Vec::new();
+        let mut images = Vec::new();
+        let mut audio = Vec::new();
+        let mut llms = Vec::new();
+        for record in records {
+            let (exists, key) = match &record {
+                CacheRecord::Document(entry) => (&existing_documents, vec![entry.resource_hash.clone(), entry.content_hash.clone()]),
+                CacheRecord::ImageCache(entry) => (&existing_images, vec![entry.resource_hash.clone(), entry.content_hash.clone()]),
+                CacheRecord::AudioCache(entry) => (&existing_audio, vec![entry.resource_hash.clone(), entry.content_hash.clone()]),
+                CacheRecord::LlmCache(entry) => {
+                    (&existing_llms, vec![entry.operation.clone(), entry.input_hash.clone(), entry.model.clone()])
+                }
+            };
+            if exists.contains(&key) {
+                report.skipped_count += 1;
+                continue;
+            }
+
+            match record {
+                CacheRecord::Document(entry) => documents.push(DocumentCacheEntry::from(entry)),
+                CacheRecord::ImageCache(entry) => images.push(ImageCacheEntry::from(entry)),
+                CacheRecord::AudioCache(entry) => audio.push(entry),
+                CacheRecord::LlmCache(entry) => llms.push(LlmCacheEntry::from(entry)),
+            }
+        }
+
+        report.document_count = documents.len();
+        report.image_count = images.len();
+        report.audio_count = audio.len();
+        report.llm_count = llms.len();
+        self.upsert_documents(documents).await?;
+        self.upsert_images(images).await?;
+        self.upsert_llms(llms).await?;
+        self.upsert_audio_records(audio).await?;
+
+        Ok(report)
+    }
+
+    /// Store audio cache records, replacing any for the same resource
+    async fn upsert_audio_records(&self, entries: Vec<AudioCacheEntryInternal>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let (hashes, entries) = dedupe_last_wins(entries, |entry| entry.resource_hash.clone());
+        with_retry("upsert_audio_records", || async {
+            self.db
+                .query(
+                    r#"
+                    BEGIN TRANSACTION;
+                    DELETE audio_cache WHERE resource_hash IN $hashes;
+                    INSERT INTO audio_cache $entries;
+                    COMMIT TRANSACTION;
+                    "#,
+                )
+                .bind(("hashes", hashes.clone()))
+                .bind(("entries", entries.clone()))
+                .await?
+                .check()
+        })
+        .await
+        .map_err(|e| CacheError::QueryFailed(e.to_string()))?;
 
         Ok(())
     }
@@ -385,6 +1470,10 @@ impl CacheOperations {
     /// Clean expired LLM cache entries
     #[instrument(skip(self))]
     pub async fn clean_expired_llm_cache(&self) -> Result<usize> {
+        if !self.enabled {
+            return Ok(0);
+        }
+
         debug!("Cleaning expired LLM cache entries");
 
         let mut result = self
@@ -403,6 +1492,91 @@ impl CacheOperations {
     }
 }
 
+/// What [`CacheOperations::export`] wrote
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheExportReport {
+    pub document_count: usize,
+    pub image_count: usize,
+    pub audio_count: usize,
+    pub llm_count: usize,
+    /// Size of the compressed file
+    pub file_size_bytes: u64,
+}
+
+/// What [`CacheOperations::import`] stored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheImportReport {
+    pub document_count: usize,
+    pub image_count: usize,
+    pub audio_count: usize,
+    pub llm_count: usize,
+    /// Records left alone because the cache already had them
+    pub skipped_count: usize,
+}
+
+/// First line of a cache export
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    schema_version: u32,
+}
+
+/// One line of a cache export after the header
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "table", content = "record", rename_all = "snake_case")]
+enum CacheRecord {
+    Document(DocumentCacheEntryInternal),
+    ImageCache(ImageCacheEntryInternal),
+    AudioCache(AudioCacheEntryInternal),
+    LlmCache(LlmCacheEntryInternal),
+}
+
+/// Write a header and `records` as gzip-compressed NDJSON, returning the file size
+fn write_export(path: &Path, records: &[CacheRecord]) -> Result<u64> {
+    let serialization_error = |e: serde_json::Error| CacheError::SerializationError(e.to_string());
+
+    let mut writer = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+    serde_json::to_writer(&mut writer, &ExportHeader { schema_version: SCHEMA_VERSION })
+        .map_err(serialization_error)?;
+    writer.write_all(b"\n")?;
+    for record in records {
+        serde_json::to_writer(&mut writer, record).map_err(serialization_error)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.finish()?.flush()?;
+
+    Ok(std::fs::metadata(path)?.len())
+}
+
+/// Read the records of a cache export, checking its schema version
+fn read_export(path: &Path) -> Result<Vec<CacheRecord>> {
+    let deserialization_error =
+        |line: usize, e: serde_json::Error| CacheError::DeserializationError(format!("Line {} of cache export: {}", line, e));
+
+    let mut lines = BufReader::new(GzDecoder::new(File::open(path)?)).lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| CacheError::DeserializationError("Cache export is empty".to_string()))??;
+    let header: ExportHeader = serde_json::from_str(&header).map_err(|e| deserialization_error(1, e))?;
+    if header.schema_version != SCHEMA_VERSION {
+        return Err(CacheError::SchemaVersionMismatch {
+            found: header.schema_version,
+            expected: SCHEMA_VERSION,
+        }
+        .into());
+    }
+
+    let mut records = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line).map_err(|e| deserialization_error(index + 2, e))?);
+    }
+
+    Ok(records)
+}
+
 /// Legacy wrapper function for get_image_cache (to be removed after refactoring)
 pub async fn get_image_cache(
     db: &Surreal<Db>,
@@ -438,6 +1612,7 @@ pub async fn upsert_image_cache(
         has_transparency,
         original_width: original_width as i64,
         original_height: original_height as i64,
+        perceptual_hash: None,
     };
     ops.upsert_image(entry).await
 }
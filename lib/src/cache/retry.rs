@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Maximum number of attempts (including the first) for a cache write
+pub(crate) const MAX_ATTEMPTS: u32 = 4;
+
+/// Base delay between attempts; doubled after every failure
+const BASE_DELAY: Duration = Duration::from_millis(10);
+
+/// Classifies errors as transient (worth retrying) or permanent
+pub(crate) trait RetryableError: Display {
+    fn is_transient(&self) -> bool;
+}
+
+impl RetryableError for surrealdb::Error {
+    fn is_transient(&self) -> bool {
+        use surrealdb::error::Db;
+
+        match self {
+            surrealdb::Error::Db(Db::TxFailure) | surrealdb::Error::Db(Db::QueryTimedout) => true,
+            surrealdb::Error::Db(Db::Tx(message)) | surrealdb::Error::Db(Db::Ds(message)) => {
+                is_transient_message(message)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Lock contention and write conflicts surface from the embedded KV stores as strings
+fn is_transient_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["conflict", "busy", "lock", "try again"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
+
+/// Run a cache operation, retrying with exponential backoff on transient errors
+///
+/// Permanent errors and the final transient error are returned unchanged so the
+/// caller can map them to the appropriate `CacheError` variant.
+pub(crate) async fn with_retry<T, E, F, Fut>(operation: &str, mut f: F) -> Result<T, E>
+where
+    E: RetryableError,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < MAX_ATTEMPTS && err.is_transient() => {
+                let delay = BASE_DELAY * 2u32.pow(attempt - 1);
+                warn!(
+                    "Transient error during {} (attempt {}/{}), retrying in {:?}: {}",
+                    operation, attempt, MAX_ATTEMPTS, delay, err
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug)]
+    struct FakeError {
+        transient: bool,
+    }
+
+    impl Display for FakeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "fake error (transient: {})", self.transient)
+        }
+    }
+
+    impl RetryableError for FakeError {
+        fn is_transient(&self) -> bool {
+            self.transient
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_eventually_succeeds() {
+        let calls = AtomicU32::new(0);
+
+        let result = with_retry("test", || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(FakeError { transient: true })
+            } else {
+                Ok("done")
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_permanent_error_not_retried() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), FakeError> = with_retry("test", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(FakeError { transient: false })
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), FakeError> = with_retry("test", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(FakeError { transient: true })
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_surrealdb_error_classification() {
+        use surrealdb::error::Db;
+
+        assert!(surrealdb::Error::Db(Db::TxFailure).is_transient());
+        assert!(surrealdb::Error::Db(Db::Tx("Resource busy".to_string())).is_transient());
+        assert!(surrealdb::Error::Db(Db::Tx("Transaction conflict".to_string())).is_transient());
+        assert!(!surrealdb::Error::Db(Db::Tx("Corruption".to_string())).is_transient());
+        assert!(!surrealdb::Error::Db(Db::TxReadonly).is_transient());
+    }
+}
@@ -1,9 +1,100 @@
 use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
-use crate::error::Result;
-use tracing::{info, instrument};
+use surrealdb::{Response, Surreal};
+use crate::error::{CacheError, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
 
-/// SQL schema definitions for the database
+/// Schema version this build of the library reads and writes
+pub const SCHEMA_VERSION: u32 = 9;
+
+/// Bookkeeping table holding the version of the schema stored in the database
+///
+/// Defined outside the numbered migrations so the version can be read before
+/// any of them have run.
+const SCHEMA_VERSION_SQL: &str = r#"
+DEFINE TABLE schema_version SCHEMAFULL;
+DEFINE FIELD version ON schema_version TYPE int;
+DEFINE FIELD applied_at ON schema_version TYPE datetime DEFAULT time::now();
+"#;
+
+/// Tables dropped when an unreadable database is rebuilt
+const CACHE_TABLES: &[&str] = &[
+    "document",
+    "depends_on",
+    "image_cache",
+    "llm_cache",
+    "embedding",
+    "audio_cache",
+    "rendered_document",
+    "remote_audio",
+    "embedding_cache",
+    "audio_cover_art",
+    "schema_version",
+];
+
+/// A single, ordered step in the cache schema history
+#[derive(Clone, Copy)]
+struct Migration {
+    version: u32,
+    description: &'static str,
+    sql: &'static str,
+}
+
+/// All migrations, in ascending version order
+///
+/// Databases created before versioning was introduced carry no version record
+/// and are treated as version 0. Version 1 only contains `DEFINE` statements,
+/// so re-applying it over their existing tables keeps the data intact.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial cache schema",
+        sql: SCHEMA_SQL,
+    },
+    Migration {
+        version: 2,
+        description: "rendered document cache",
+        sql: RENDERED_DOCUMENT_SQL,
+    },
+    Migration {
+        version: 3,
+        description: "deduplicate cache entries",
+        sql: DEDUPLICATE_SQL,
+    },
+    Migration {
+        version: 4,
+        description: "document file size and modification time",
+        sql: DOCUMENT_FILE_STAT_SQL,
+    },
+    Migration {
+        version: 5,
+        description: "downloaded remote audio",
+        sql: REMOTE_AUDIO_SQL,
+    },
+    Migration {
+        version: 6,
+        description: "project-relative resource hashes",
+        sql: RESOURCE_HASH_V2_SQL,
+    },
+    Migration {
+        version: 7,
+        description: "embedding cache",
+        sql: EMBEDDING_CACHE_SQL,
+    },
+    Migration {
+        version: 8,
+        description: "audio chapters and cover art",
+        sql: AUDIO_CHAPTERS_SQL,
+    },
+    Migration {
+        version: 9,
+        description: "image perceptual hashes",
+        sql: IMAGE_PERCEPTUAL_HASH_SQL,
+    },
+];
+
+/// Baseline (version 1) schema definitions for the database
 pub const SCHEMA_SQL: &str = r#"
 -- Document node
 DEFINE TABLE document SCHEMAFULL;
@@ -74,16 +165,580 @@ DEFINE INDEX idx_audio_resource ON audio_cache FIELDS resource_hash UNIQUE;
 DEFINE INDEX idx_audio_lookup ON audio_cache FIELDS resource_hash, content_hash;
 "#;
 
+/// Version 2: rendered output of documents, reused by incremental renders
+const RENDERED_DOCUMENT_SQL: &str = r#"
+DEFINE TABLE rendered_document SCHEMAFULL;
+DEFINE FIELD resource_hash ON rendered_document TYPE string;
+DEFINE FIELD content_hash ON rendered_document TYPE string;
+DEFINE FIELD document ON rendered_document TYPE string;
+DEFINE FIELD rendered_at ON rendered_document TYPE datetime;
+DEFINE INDEX idx_rendered_resource_hash ON rendered_document FIELDS resource_hash UNIQUE;
+"#;
+
+/// Version 3: remove duplicate cache rows and make LLM cache keys unique
+///
+/// Earlier releases inserted a new row on every upsert. For each key only the
+/// newest row is kept: by `last_validated` for documents and by `created_at`
+/// for images and LLM responses.
+const DEDUPLICATE_SQL: &str = r#"
+FOR $group IN (SELECT resource_hash, count() AS rows FROM document GROUP BY resource_hash) {
+    IF $group.rows > 1 {
+        LET $keep = (SELECT id, last_validated FROM document WHERE resource_hash = $group.resource_hash ORDER BY last_validated DESC LIMIT 1).id;
+        DELETE document WHERE resource_hash = $group.resource_hash AND id NOTINSIDE $keep;
+    };
+};
+
+FOR $group IN (SELECT resource_hash, count() AS rows FROM image_cache GROUP BY resource_hash) {
+    IF $group.rows > 1 {
+        LET $keep = (SELECT id, created_at FROM image_cache WHERE resource_hash = $group.resource_hash ORDER BY created_at DESC LIMIT 1).id;
+        DELETE image_cache WHERE resource_hash = $group.resource_hash AND id NOTINSIDE $keep;
+    };
+};
+
+FOR $group IN (SELECT operation, input_hash, model, count() AS rows FROM llm_cache GROUP BY operation, input_hash, model) {
+    IF $group.rows > 1 {
+        LET $keep = (SELECT id, created_at FROM llm_cache WHERE operation = $group.operation AND input_hash = $group.input_hash AND model = $group.model ORDER BY created_at DESC LIMIT 1).id;
+        DELETE llm_cache WHERE operation = $group.operation AND input_hash = $group.input_hash AND model = $group.model AND id NOTINSIDE $keep;
+    };
+};
+
+DEFINE INDEX idx_resource_hash ON document FIELDS resource_hash UNIQUE;
+DEFINE INDEX idx_image_resource ON image_cache FIELDS resource_hash UNIQUE;
+REMOVE INDEX idx_llm_lookup ON llm_cache;
+DEFINE INDEX idx_llm_lookup ON llm_cache FIELDS operation, input_hash, model UNIQUE;
+"#;
+
+/// Version 4: file size and mtime recorded when a document was hashed
+///
+/// Lets graph builds skip re-hashing local files whose stat is unchanged.
+const DOCUMENT_FILE_STAT_SQL: &str = r#"
+DEFINE FIELD file_size ON document TYPE option<int>;
+DEFINE FIELD modified_at ON document TYPE option<datetime>;
+"#;
+
+/// Version 5: audio downloaded from URLs, so builds don't fetch it again
+///
+/// Keyed by a hash of the canonicalized URL; `data` is base64 encoded.
+const REMOTE_AUDIO_SQL: &str = r#"
+DEFINE TABLE remote_audio SCHEMAFULL;
+DEFINE FIELD url_hash ON remote_audio TYPE string;
+DEFINE FIELD url ON remote_audio TYPE string;
+DEFINE FIELD content_type ON remote_audio TYPE option<string>;
+DEFINE FIELD data ON remote_audio TYPE string;
+DEFINE FIELD fetched_at ON remote_audio TYPE datetime;
+DEFINE INDEX idx_remote_audio_url ON remote_audio FIELDS url_hash UNIQUE;
+"#;
+
+/// Version 6: resource hashes of local files are relative to the project root
+///
+/// Entries keyed by resource hashes from the previous scheme can never be
+/// hit again, so they are dropped once instead of lingering (see
+/// [`RESOURCE_HASH_VERSION`](crate::graph::RESOURCE_HASH_VERSION)).
+const RESOURCE_HASH_V2_SQL: &str = r#"
+DELETE depends_on;
+DELETE document;
+DELETE rendered_document;
+DELETE image_cache;
+DELETE audio_cache;
+DELETE embedding;
+"#;
+
+/// Version 7: embeddings keyed by the hash of the text they were made from
+///
+/// Used by semantic search, so that unchanged documents and sentences aren't
+/// sent to the embedding model again.
+const EMBEDDING_CACHE_SQL: &str = r#"
+DEFINE TABLE embedding_cache SCHEMAFULL;
+DEFINE FIELD content_hash ON embedding_cache TYPE string;
+DEFINE FIELD model ON embedding_cache TYPE string;
+DEFINE FIELD vector ON embedding_cache TYPE array<float>;
+DEFINE FIELD created_at ON embedding_cache TYPE datetime;
+DEFINE INDEX idx_embedding_cache_key ON embedding_cache FIELDS content_hash, model UNIQUE;
+"#;
+
+/// Version 8: chapters and cover art of cached audio
+///
+/// Cover art is kept in its own table, keyed by the hash of the image and
+/// base64 encoded, so audio entries stay small and share identical art.
+const AUDIO_CHAPTERS_SQL: &str = r#"
+DEFINE FIELD chapters ON audio_cache TYPE array<object> DEFAULT [];
+DEFINE FIELD chapters[*].title ON audio_cache TYPE string;
+DEFINE FIELD chapters[*].start_ms ON audio_cache TYPE int;
+DEFINE FIELD chapters[*].end_ms ON audio_cache TYPE int;
+DEFINE FIELD cover_art_hash ON audio_cache TYPE option<string>;
+
+DEFINE TABLE audio_cover_art SCHEMAFULL;
+DEFINE FIELD hash ON audio_cover_art TYPE string;
+DEFINE FIELD data ON audio_cover_art TYPE string;
+DEFINE INDEX idx_audio_cover_art_hash ON audio_cover_art FIELDS hash UNIQUE;
+"#;
+
+/// Version 9: perceptual hashes of cached images, for finding near-duplicates
+///
+/// Stored as 16 hex digits, since SurrealDB integers are signed.
+const IMAGE_PERCEPTUAL_HASH_SQL: &str = r#"
+DEFINE FIELD perceptual_hash ON image_cache TYPE option<string>;
+"#;
+
 /// Apply the database schema
+///
+/// Equivalent to [`migrate()`] without a known path and with rebuilds disabled.
 #[instrument(skip(db))]
 pub async fn apply_schema(db: &Surreal<Db>) -> Result<()> {
-    info!("Applying database schema");
+    migrate(db, None, false).await?;
+    Ok(())
+}
 
-    // Execute schema definition
-    db.query(SCHEMA_SQL)
+/// Bring the database schema up to [`SCHEMA_VERSION`]
+///
+/// Pending migrations are applied in order, each in its own transaction together
+/// with the update of the stored version. A database written by a newer library
+/// fails with `CacheError::InitializationFailed`. A missing or unreadable version
+/// record also fails unless `rebuild_on_mismatch` is set, in which case every
+/// cache table is dropped and the schema is recreated from scratch.
+///
+/// Returns the schema version of the database after migrating.
+#[instrument(skip(db))]
+pub async fn migrate(
+    db: &Surreal<Db>,
+    db_path: Option<&Path>,
+    rebuild_on_mismatch: bool,
+) -> Result<u32> {
+    run_migrations(db, db_path, rebuild_on_mismatch, MIGRATIONS).await
+}
+
+async fn run_migrations(
+    db: &Surreal<Db>,
+    db_path: Option<&Path>,
+    rebuild_on_mismatch: bool,
+    migrations: &[Migration],
+) -> Result<u32> {
+    let latest = migrations.last().map_or(0, |m| m.version);
+    let path = db_path.map_or_else(|| PathBuf::from("<memory>"), Path::to_path_buf);
+
+    db.query(SCHEMA_VERSION_SQL)
         .await
-        .map_err(|e| crate::error::CacheError::QueryFailed(format!("Schema application failed: {}", e)))?;
+        .and_then(Response::check)
+        .map_err(|e| CacheError::QueryFailed(format!("Schema application failed: {}", e)))?;
+
+    let current = match read_schema_version(db).await {
+        Ok(version) if version > latest => {
+            return Err(CacheError::InitializationFailed {
+                path,
+                error: format!(
+                    "cache schema version {} is newer than the version supported by this library ({}); \
+                     upgrade composition or delete the cache database",
+                    version, latest
+                ),
+            }
+            .into());
+        }
+        Ok(version) => version,
+        Err(reason) if rebuild_on_mismatch => {
+            warn!("Rebuilding cache database at {}: {}", path.display(), reason);
+            rebuild(db).await?;
+            0
+        }
+        Err(reason) => {
+            return Err(CacheError::InitializationFailed {
+                path,
+                error: format!(
+                    "{}; delete the cache database or enable rebuild_on_schema_mismatch",
+                    reason
+                ),
+            }
+            .into());
+        }
+    };
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        info!(
+            "Applying cache schema migration {}: {}",
+            migration.version, migration.description
+        );
+
+        let sql = format!(
+            "BEGIN TRANSACTION;\n{}\nUPDATE schema_version:current SET version = $version, applied_at = time::now();\nCOMMIT TRANSACTION;",
+            migration.sql
+        );
+
+        db.query(sql)
+            .bind(("version", migration.version))
+            .await
+            .and_then(Response::check)
+            .map_err(|e| {
+                CacheError::QueryFailed(format!(
+                    "Schema migration {} ({}) failed: {}",
+                    migration.version, migration.description, e
+                ))
+            })?;
+    }
+
+    info!("Cache schema is at version {}", latest.max(current));
+    Ok(latest.max(current))
+}
+
+/// Read the stored schema version
+///
+/// An absent record means the database predates versioning (or is empty) and
+/// reads as 0. The error describes a record that exists but can't be trusted.
+async fn read_schema_version(db: &Surreal<Db>) -> std::result::Result<u32, String> {
+    #[derive(Deserialize)]
+    struct VersionRecord {
+        version: i64,
+    }
+
+    let unreadable = |e: surrealdb::Error| format!("unreadable cache schema version: {}", e);
+    let mut response = db
+        .query("SELECT version FROM schema_version:current")
+        .await
+        .map_err(unreadable)?;
+    let record: Option<VersionRecord> = response.take(0).map_err(unreadable)?;
+
+    match record {
+        None => Ok(0),
+        Some(record) => u32::try_from(record.version)
+            .ok()
+            .filter(|version| *version > 0)
+            .ok_or_else(|| format!("unknown cache schema version {}", record.version)),
+    }
+}
+
+/// Drop every cache table, leaving an empty database
+async fn rebuild(db: &Surreal<Db>) -> Result<()> {
+    use surrealdb::error::Db as DbError;
+
+    for table in CACHE_TABLES {
+        let result = db
+            .query(format!("REMOVE TABLE {}", table))
+            .await
+            .and_then(Response::check);
+
+        match result {
+            Ok(_) | Err(surrealdb::Error::Db(DbError::TbNotFound { .. })) => {}
+            Err(e) => {
+                return Err(CacheError::QueryFailed(format!("Cache rebuild failed: {}", e)).into())
+            }
+        }
+    }
+
+    db.query(SCHEMA_VERSION_SQL)
+        .await
+        .and_then(Response::check)
+        .map_err(|e| CacheError::QueryFailed(format!("Cache rebuild failed: {}", e)))?;
 
-    info!("Schema applied successfully");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{init_memory_database, CacheOperations};
+    use crate::error::CompositionError;
+
+    const V1_FIXTURE: &str = include_str!("../../tests/fixtures/cache_schema_v1.surql");
+    const V2_FIXTURE: &str = include_str!("../../tests/fixtures/cache_schema_v2.surql");
+
+    /// The migrations before version 6, which drops every entry keyed by a
+    /// resource hash
+    const KEEPS_HASHED_ENTRIES: &[Migration] = MIGRATIONS.split_at(5).0;
+
+    /// The real migrations plus one more step past the current version
+    fn with_next_step(description: &'static str, sql: &'static str) -> Vec<Migration> {
+        let mut migrations = MIGRATIONS.to_vec();
+        migrations.push(Migration {
+            version: SCHEMA_VERSION + 1,
+            description,
+            sql,
+        });
+        migrations
+    }
+
+    /// A follow-up migration used to exercise upgrades past the current version
+    fn next_migrations() -> Vec<Migration> {
+        with_next_step(
+            "add document title",
+            r#"
+                DEFINE FIELD title ON document TYPE string DEFAULT '';
+                UPDATE document SET title = resource_hash;
+            "#,
+        )
+    }
+
+    async fn legacy_db() -> Surreal<Db> {
+        let db = init_memory_database().await.unwrap();
+        db.query(V1_FIXTURE).await.unwrap().check().unwrap();
+        db
+    }
+
+    async fn v2_db() -> Surreal<Db> {
+        let db = legacy_db().await;
+        db.query(V2_FIXTURE).await.unwrap().check().unwrap();
+        db
+    }
+
+    async fn stored_version(db: &Surreal<Db>) -> u32 {
+        read_schema_version(db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database_is_stamped_with_current_version() {
+        let db = init_memory_database().await.unwrap();
+
+        let version = migrate(&db, None, false).await.unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(stored_version(&db).await, SCHEMA_VERSION);
+
+        // Re-running is a no-op
+        assert_eq!(migrate(&db, None, false).await.unwrap(), SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_legacy_fixture_preserves_data() {
+        let db = legacy_db().await;
+        assert_eq!(stored_version(&db).await, 0);
+
+        let version = run_migrations(&db, None, false, KEEPS_HASHED_ENTRIES).await.unwrap();
+        assert_eq!(version, 5);
+
+        let cache = CacheOperations::new(db.clone());
+        let doc = cache.get_document("legacy_hash_a").await.unwrap().unwrap();
+        assert_eq!(doc.content_hash, "legacy_content_a");
+        let doc = cache.get_document("legacy_hash_b").await.unwrap().unwrap();
+        assert_eq!(doc.url.as_deref(), Some("https://example.com/b.md"));
+
+        let llm = cache
+            .get_llm("summarize", "legacy_input", "openai/gpt-4o-mini")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(llm.response, "A short summary.");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_legacy_fixture_through_later_step() {
+        let db = legacy_db().await;
+
+        let version = run_migrations(&db, None, false, &next_migrations()).await.unwrap();
+        assert_eq!(version, SCHEMA_VERSION + 1);
+        assert_eq!(stored_version(&db).await, SCHEMA_VERSION + 1);
+
+        // Documents were dropped with the old resource hashes; a new one gets
+        // the field added by the later step
+        db.query("CREATE document CONTENT { resource_hash: 'new_hash', content_hash: 'c', last_validated: time::now() }")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let titles: Vec<String> = db
+            .query("SELECT VALUE title FROM document")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(titles, vec![""]);
+
+        // Content not keyed by resource hash is untouched
+        let cache = CacheOperations::new(db);
+        let llm = cache
+            .get_llm("summarize", "legacy_input", "openai/gpt-4o-mini")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(llm.response, "A short summary.");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_stamped_v2_fixture_preserves_data() {
+        let db = v2_db().await;
+        assert_eq!(stored_version(&db).await, 2);
+
+        let version = run_migrations(&db, None, false, KEEPS_HASHED_ENTRIES).await.unwrap();
+        assert_eq!(version, 5);
+        assert_eq!(stored_version(&db).await, 5);
+
+        let cache = CacheOperations::new(db.clone());
+        let doc = cache.get_document("legacy_hash_b").await.unwrap().unwrap();
+        assert_eq!(doc.content_hash, "legacy_content_b");
+
+        let rendered: Vec<String> = db
+            .query("SELECT VALUE document FROM rendered_document")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(rendered, vec![r#"{"rendered":"a"}"#]);
+
+        // Only the newest of the duplicated LLM responses survives
+        let llm = cache
+            .get_llm("summarize", "legacy_input", "openai/gpt-4o-mini")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(llm.response, "A newer summary.");
+        let rows: Vec<String> = db
+            .query("SELECT VALUE response FROM llm_cache")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resource_hash_migration_drops_hashed_entries_once() {
+        let db = v2_db().await;
+        run_migrations(&db, None, false, KEEPS_HASHED_ENTRIES).await.unwrap();
+
+        assert_eq!(migrate(&db, None, false).await.unwrap(), SCHEMA_VERSION);
+
+        let cache = CacheOperations::new(db.clone());
+        assert!(cache.get_document("legacy_hash_a").await.unwrap().is_none());
+        for table in ["document", "depends_on", "rendered_document", "image_cache"] {
+            let rows: Vec<serde_json::Value> = db
+                .query(format!("SELECT * FROM {}", table))
+                .await
+                .unwrap()
+                .take(0)
+                .unwrap();
+            assert!(rows.is_empty(), "{} kept {} rows", table, rows.len());
+        }
+        let llm = cache
+            .get_llm("summarize", "legacy_input", "openai/gpt-4o-mini")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(llm.response, "A newer summary.");
+
+        // Entries written with the new hashes survive later starts
+        db.query("CREATE document CONTENT { resource_hash: 'v2_hash', content_hash: 'c', last_validated: time::now() }")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        migrate(&db, None, false).await.unwrap();
+        assert!(cache.get_document("v2_hash").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_rows_are_removed() {
+        let db = init_memory_database().await.unwrap();
+        run_migrations(&db, None, false, &MIGRATIONS[..2]).await.unwrap();
+
+        // Releases without a unique document index could store a hash twice
+        db.query(
+            r#"
+            REMOVE INDEX idx_resource_hash ON document;
+            CREATE document CONTENT { resource_hash: 'dup', content_hash: 'old', last_validated: d'2024-01-01T00:00:00Z' };
+            CREATE document CONTENT { resource_hash: 'dup', content_hash: 'new', last_validated: d'2024-03-01T00:00:00Z' };
+            CREATE document CONTENT { resource_hash: 'dup', content_hash: 'middle', last_validated: d'2024-02-01T00:00:00Z' };
+            CREATE document CONTENT { resource_hash: 'single', content_hash: 'only', last_validated: d'2024-01-01T00:00:00Z' };
+            CREATE llm_cache CONTENT { operation: 'summarize', input_hash: 'in', model: 'm', response: 'old', created_at: d'2024-01-01T00:00:00Z', expires_at: d'2099-01-01T00:00:00Z' };
+            CREATE llm_cache CONTENT { operation: 'summarize', input_hash: 'in', model: 'm', response: 'new', created_at: d'2024-02-01T00:00:00Z', expires_at: d'2099-01-01T00:00:00Z' };
+            "#,
+        )
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+        run_migrations(&db, None, false, KEEPS_HASHED_ENTRIES).await.unwrap();
+
+        let documents: Vec<String> = db
+            .query("SELECT VALUE content_hash FROM document ORDER BY content_hash")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(documents, vec!["new", "only"]);
+
+        let cache = CacheOperations::new(db.clone());
+        let llm = cache.get_llm("summarize", "in", "m").await.unwrap().unwrap();
+        assert_eq!(llm.response, "new");
+
+        // The keys are unique from now on
+        let duplicate = db
+            .query("CREATE llm_cache CONTENT { operation: 'summarize', input_hash: 'in', model: 'm', response: 'x', expires_at: d'2099-01-01T00:00:00Z' }")
+            .await
+            .and_then(Response::check);
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let db = init_memory_database().await.unwrap();
+        migrate(&db, None, false).await.unwrap();
+
+        let broken = with_next_step(
+            "broken step",
+            "DEFINE FIELD title ON document TYPE string; THROW 'boom';",
+        );
+
+        let result = run_migrations(&db, None, false, &broken).await;
+        assert!(matches!(
+            result,
+            Err(CompositionError::Cache(CacheError::QueryFailed(_)))
+        ));
+        assert_eq!(stored_version(&db).await, SCHEMA_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_newer_database_fails_fast() {
+        let db = init_memory_database().await.unwrap();
+        run_migrations(&db, None, false, &next_migrations()).await.unwrap();
+
+        let result = migrate(&db, Some(Path::new("/tmp/project/.composition.db")), true).await;
+        match result {
+            Err(CompositionError::Cache(CacheError::InitializationFailed { path, error })) => {
+                assert_eq!(path, PathBuf::from("/tmp/project/.composition.db"));
+                assert!(
+                    error.contains(&format!("version {} is newer", SCHEMA_VERSION + 1)),
+                    "{}",
+                    error
+                );
+            }
+            other => panic!("expected InitializationFailed, got {:?}", other.map(|_| ())),
+        }
+
+        // Nothing was dropped
+        assert_eq!(stored_version(&db).await, SCHEMA_VERSION + 1);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_version_requires_rebuild_flag() {
+        let db = legacy_db().await;
+        migrate(&db, None, false).await.unwrap();
+        db.query("UPDATE schema_version:current SET version = -3")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let result = migrate(&db, None, false).await;
+        assert!(matches!(
+            result,
+            Err(CompositionError::Cache(CacheError::InitializationFailed { .. }))
+        ));
+
+        let version = migrate(&db, None, true).await.unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+
+        // Rebuilding drops the cached data
+        let cache = CacheOperations::new(db);
+        assert!(cache.get_document("legacy_hash_a").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_version_record_is_rebuilt() {
+        let db = legacy_db().await;
+        db.query("CREATE schema_version:current SET version = 'garbage'")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        assert!(migrate(&db, None, false).await.is_err());
+        assert_eq!(migrate(&db, None, true).await.unwrap(), SCHEMA_VERSION);
+        assert_eq!(stored_version(&db).await, SCHEMA_VERSION);
+    }
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 use std::path::PathBuf;
+use crate::render::TaskFailure;
 
 /// Top-level error type for the composition library
 #[derive(Error, Debug)]
@@ -27,13 +28,13 @@ pub enum CompositionError {
 }
 
 /// Errors related to parsing markdown and DSL syntax
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum ParseError {
     #[error("Invalid markdown at line {line}: {message}")]
-    InvalidMarkdown { line: usize, message: String },
+    InvalidMarkdown { line: usize, message: String, span: Option<Span> },
 
     #[error("Invalid DarkMatter directive at line {line}: {directive}")]
-    InvalidDirective { line: usize, directive: String },
+    InvalidDirective { line: usize, directive: String, span: Option<Span> },
 
     #[error("Invalid frontmatter: {0}")]
     InvalidFrontmatter(String),
@@ -53,14 +54,173 @@ pub enum ParseError {
     #[error("File ignored by .gitignore: {path}")]
     FileIgnored { path: String },
 
+    #[error("{path} is not valid UTF-8: invalid byte sequence at offset {offset}")]
+    InvalidUtf8 { path: String, offset: usize },
+
     #[error("Unsupported feature: {0}")]
     UnsupportedFeature(String),
 
     #[error("Failed to parse URL: {0}")]
     UrlParse(#[from] url::ParseError),
 
-    #[error("YAML parse error: {0}")]
-    YamlParse(String),
+    #[error("YAML parse error: {message}")]
+    YamlParse { message: String, span: Option<Span> },
+
+    #[error("Failed to read input: {0}")]
+    ReadFailed(String),
+
+    #[error("{0} (strict mode)")]
+    Strict(ParseWarning),
+}
+
+/// A problem that doesn't stop a document from rendering
+///
+/// In strict mode (see [`ParseOptions`](crate::parse::ParseOptions)) a
+/// warning fails the parse as [`ParseError::Strict`] instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub span: Option<Span>,
+    pub message: String,
+    pub code: ParseWarningCode,
+}
+
+/// The kind of problem a [`ParseWarning`] reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseWarningCode {
+    /// A `::` line that isn't a DarkMatter directive; it is dropped
+    UnknownDirective,
+    /// A `{{variable}}` with no value; it is left as written
+    UndefinedVariable,
+    /// An optional (`?`) resource that couldn't be loaded; it renders as nothing
+    OptionalResourceNotFound,
+    /// A data file with no rows; its list renders empty
+    EmptyData,
+    /// An `{{env.NAME}}` for a variable that isn't in
+    /// `CompositionConfig::allowed_env_vars`; it is left as written
+    BlockedEnvVar,
+    /// A transclusion's end line past the end of the file; the range stops
+    /// at the last line
+    LineRangeClamped,
+    /// Inline audio larger than `max_inline_size`; it is inlined anyway
+    OversizedInlineAudio,
+    /// A `jsonld` frontmatter property that isn't a JSON object; the page's
+    /// structured data is built without it
+    InvalidStructuredData,
+    /// An optional file that isn't valid UTF-8; invalid sequences are
+    /// replaced with U+FFFD
+    LossyDecoding,
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at line {}, column {}", self.message, span.line, span.column),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Where in the source a parse error happened
+///
+/// `offset` and `length` are in bytes; `line` and `column` are 1-based, with
+/// the column counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub length: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The span of `length` bytes starting at byte `offset` of `source`
+    pub fn locate(source: &str, offset: usize, length: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Span {
+            offset,
+            length,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl ParseError {
+    /// Where in the source the error happened, when known
+    pub fn span(&self) -> Option<&Span> {
+        self.span_slot().and_then(|span| span.as_ref())
+    }
+
+    /// The line the error happened on, when known
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::InvalidMarkdown { line, .. } | Self::InvalidDirective { line, .. } => Some(*line),
+            Self::YamlParse { span, .. } => span.map(|span| span.line),
+            Self::Strict(warning) => warning.span.map(|span| span.line),
+            _ => None,
+        }
+    }
+
+    fn span_slot(&self) -> Option<&Option<Span>> {
+        match self {
+            Self::InvalidMarkdown { span, .. }
+            | Self::InvalidDirective { span, .. }
+            | Self::YamlParse { span, .. } => Some(span),
+            Self::Strict(warning) => Some(&warning.span),
+            _ => None,
+        }
+    }
+
+    /// Set the span of an error that can carry one
+    pub(crate) fn with_span(mut self, new_span: Span) -> Self {
+        if let Self::InvalidMarkdown { span, .. }
+        | Self::InvalidDirective { span, .. }
+        | Self::YamlParse { span, .. }
+        | Self::Strict(ParseWarning { span, .. }) = &mut self
+        {
+            *span = Some(new_span);
+        }
+        self
+    }
+
+    /// Move the span of an error found in a slice of `source` starting at
+    /// byte `base` into the coordinates of `source`
+    pub(crate) fn relocate(self, source: &str, base: usize) -> Self {
+        match self.span().copied() {
+            Some(span) => self.with_span(Span::locate(source, base + span.offset, span.length)),
+            None => self,
+        }
+    }
+
+    /// Convert the error to an LSP diagnostic
+    ///
+    /// The range covers the span on its first line. Errors without a span
+    /// point at the start of their line, or of the document.
+    #[cfg(feature = "lsp")]
+    pub fn to_lsp_diagnostic(&self) -> lsp_types::Diagnostic {
+        use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+        let range = match (self.span(), self.line()) {
+            (Some(span), _) => {
+                let start = Position::new(span.line as u32 - 1, span.column as u32 - 1);
+                Range::new(start, Position::new(start.line, start.character + span.length as u32))
+            }
+            (None, Some(line)) => {
+                let start = Position::new(line.saturating_sub(1) as u32, 0);
+                Range::new(start, start)
+            }
+            (None, None) => Range::default(),
+        };
+
+        Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("darkmatter".to_string()),
+            message: self.to_string(),
+            ..Diagnostic::default()
+        }
+    }
 }
 
 /// Errors related to database and caching operations
@@ -75,6 +235,9 @@ pub enum CacheError {
     #[error("Failed to initialize database at {path}: {error}")]
     InitializationFailed { path: PathBuf, error: String },
 
+    #[error("Database at {path} is locked by another process: {holder_hint}")]
+    DatabaseLocked { path: PathBuf, holder_hint: String },
+
     #[error("Failed to execute query: {0}")]
     QueryFailed(String),
 
@@ -89,10 +252,13 @@ pub enum CacheError {
 
     #[error("Invalidation failed: {0}")]
     InvalidationFailed(String),
+
+    #[error("Cache export has schema version {found}, but this database uses {expected}")]
+    SchemaVersionMismatch { found: u32, expected: u32 },
 }
 
 /// Errors related to rendering pipeline
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum RenderError {
     #[error("Failed to resolve transclusion: {resource}")]
     TransclusionFailed { resource: String },
@@ -121,8 +287,8 @@ pub enum RenderError {
     #[error("Resource not found at {0}: {1}")]
     ResourceNotFound(String, String),
 
-    #[error("Remote fetch error for {0}: {1}")]
-    RemoteFetchError(String, String),
+    #[error("Remote fetch error for {url}: {reason}")]
+    RemoteFetchError { url: String, kind: FetchFailure, reason: String },
 
     #[error("Invalid line range: {0}")]
     InvalidLineRange(String),
@@ -136,6 +302,11 @@ pub enum RenderError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    /// A document that failed to parse, or any warning in strict mode
+    /// (see [`ParseError::Strict`])
+    #[error("Parse error: {0}")]
+    Parse(#[from] ParseError),
+
     #[error("CSV parse error: {0}")]
     CsvError(String),
 
@@ -148,6 +319,9 @@ pub enum RenderError {
     #[error("Chart rendering error: {0}")]
     ChartError(String),
 
+    #[error("List rendering error: {0}")]
+    ListError(String),
+
     #[error("Popover rendering error: {0}")]
     PopoverError(String),
 
@@ -156,10 +330,73 @@ pub enum RenderError {
 
     #[error("Disclosure rendering error: {0}")]
     DisclosureError(String),
+
+    #[error("Footnote rendering error: {0}")]
+    FootnoteError(String),
+
+    #[error("AI operation failed: {0}")]
+    AiOperationFailed(String),
+
+    #[error("AI operation failed: {0}")]
+    Ai(#[from] AIError),
+
+    #[error("Invalid date settings: {0}")]
+    InvalidDateSettings(String),
+
+    #[error("Feed generation failed: {0}")]
+    FeedFailed(String),
+
+    #[error("Markdown serialization failed: {0}")]
+    MarkdownFailed(String),
+
+    #[error("Render task panicked: {0}")]
+    TaskPanicked(String),
+
+    #[error("{} document(s) failed to render: {}", .0.len(), join_failures(.0))]
+    TasksFailed(Vec<TaskFailure>),
+}
+
+/// Why a remote fetch failed (see [`RenderError::RemoteFetchError`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchFailure {
+    /// The request didn't get a response, e.g. the connection failed or
+    /// timed out
+    Request,
+    /// The server answered with this error status
+    Status(u16),
+    /// The response was larger than allowed
+    TooLarge,
+    /// The response's content type wasn't the one expected
+    ContentType,
+}
+
+impl RenderError {
+    /// Whether trying again may succeed: a failed remote fetch, other than
+    /// an HTTP 4xx besides 408 and 429 or a response refused for its size or
+    /// content type, or an AI rate limit or timeout
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RenderError::RemoteFetchError { kind, .. } => match kind {
+                FetchFailure::Request => true,
+                FetchFailure::Status(status) => !(400..500).contains(status) || matches!(status, 408 | 429),
+                FetchFailure::TooLarge | FetchFailure::ContentType => false,
+            },
+            RenderError::RemoteFetchFailed { .. } => true,
+            RenderError::Ai(error) => {
+                matches!(error, AIError::RateLimitExceeded(_) | AIError::Timeout(_) | AIError::QueueFull(_))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The failures of a work plan, one per document, separated by `; `
+fn join_failures(failures: &[TaskFailure]) -> String {
+    failures.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
 }
 
 /// Errors related to AI/LLM operations
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum AIError {
     #[error("LLM provider error ({provider}): {message}")]
     ProviderError { provider: String, message: String },
@@ -190,6 +427,15 @@ pub enum AIError {
 
     #[error("Request timeout: {0}")]
     Timeout(String),
+
+    #[error("AI queue is full: {0}")]
+    QueueFull(String),
+
+    #[error("AI queue is no longer running")]
+    QueueClosed,
+
+    #[error("Not an AI operation: {0}")]
+    NotAnAiOperation(String),
 }
 
 /// Result type alias for composition operations
@@ -290,4 +536,45 @@ mod tests {
         let comp_err: CompositionError = audio_err.into();
         assert!(matches!(comp_err, CompositionError::Audio(_)));
     }
+
+    #[test]
+    fn span_locate_counts_lines_and_characters() {
+        let source = "first\nnaïve ::x\n";
+        let offset = source.find("::x").unwrap();
+
+        assert_eq!(
+            Span::locate(source, offset, 3),
+            Span { offset, length: 3, line: 2, column: 7 }
+        );
+    }
+
+    #[test]
+    fn parse_error_relocate_moves_span() {
+        let err = ParseError::InvalidDirective {
+            line: 1,
+            directive: "::x".to_string(),
+            span: Some(Span { offset: 0, length: 3, line: 1, column: 1 }),
+        };
+
+        let moved = err.relocate("---\n---\n::x", 8);
+        assert_eq!(moved.span(), Some(&Span { offset: 8, length: 3, line: 3, column: 1 }));
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn parse_error_to_lsp_diagnostic() {
+        use lsp_types::{DiagnosticSeverity, Position};
+
+        let err = ParseError::InvalidDirective {
+            line: 2,
+            directive: "::x".to_string(),
+            span: Some(Span { offset: 6, length: 3, line: 2, column: 5 }),
+        };
+        let diagnostic = err.to_lsp_diagnostic();
+
+        assert_eq!(diagnostic.range.start, Position::new(1, 4));
+        assert_eq!(diagnostic.range.end, Position::new(1, 7));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostic.message, err.to_string());
+    }
 }
@@ -1,16 +1,32 @@
-use crate::error::Result;
-use crate::parse::parse_document;
-use crate::types::{DependencyGraph, Frontmatter, GraphNode, Resource, ResourceHash, ResourceSource};
+use crate::cache::{CacheOperations, DocumentCacheEntry};
+use crate::error::{ParseError, Result};
+use crate::parse::{parse_document_unchecked, validate_document};
+use crate::types::{
+    DarkMatterNode, DependencyGraph, Frontmatter, FrontmatterSchema, GraphNode, Resource, ResourceHash,
+    ResourceRequirement, ResourceSource,
+};
 use futures::future::BoxFuture;
 use std::collections::HashMap;
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
+use std::path::Path;
 use tracing::{debug, instrument};
 
-use super::utils::{compute_content_hash, compute_resource_hash, load_resource};
+use super::incremental::with_dependents;
+use super::utils::{
+    compute_content_hash, load_resource, load_resource_candidate, normalize_path, FileStat,
+};
 
-/// Resolve a resource's path relative to a parent resource
+/// Resolve a resource's path, and those of its fallbacks, relative to a
+/// parent resource
 fn resolve_relative_resource(dep: &Resource, parent: &Resource) -> Result<Resource> {
+    let mut resolved = resolve_relative_candidate(dep, parent)?;
+    if let Some(fallback) = &dep.fallback {
+        resolved.fallback = Some(Box::new(resolve_relative_resource(fallback, parent)?));
+    }
+    Ok(resolved)
+}
+
+/// Resolve a single candidate's path relative to a parent resource
+fn resolve_relative_candidate(dep: &Resource, parent: &Resource) -> Result<Resource> {
     match (&dep.source, &parent.source) {
         (ResourceSource::Local(dep_path), ResourceSource::Local(parent_path)) => {
             // If dependency is relative, resolve it relative to parent's directory
@@ -22,22 +38,16 @@ fn resolve_relative_resource(dep: &Resource, parent: &Resource) -> Result<Resour
                         )
                     ))?;
 
-                // Join and normalize the path
-                let mut resolved_path = parent_dir.join(dep_path);
-
-                // Normalize by removing "." components
-                // This converts /temp/./subsection.md to /temp/subsection.md
-                let components: std::path::PathBuf = resolved_path
-                    .components()
-                    .filter(|c| c.as_os_str() != ".")
-                    .collect();
-
-                resolved_path = components;
+                // Join and normalize the path, so /temp/./a.md and
+                // /temp/sub/../a.md both become /temp/a.md
+                let resolved_path = normalize_path(&parent_dir.join(dep_path));
 
                 Ok(Resource {
                     source: ResourceSource::Local(resolved_path),
                     requirement: dep.requirement,
                     cache_duration: dep.cache_duration,
+                    section: dep.section.clone(),
+                    fallback: None,
                 })
             } else {
                 // Already absolute, use as-is
@@ -52,119 +62,290 @@ fn resolve_relative_resource(dep: &Resource, parent: &Resource) -> Result<Resour
 /// Build a dependency graph starting from a root resource
 ///
 /// This recursively parses all referenced documents and builds a complete
-/// dependency graph with content hashes for cache validation.
-#[instrument(skip(db, frontmatter), fields(root = ?root.source))]
+/// dependency graph with content hashes for cache validation. Local files are
+/// hashed by their absolute path; use [`build_graph_with_options`] to hash
+/// them relative to a project root.
 pub async fn build_graph(
     root: Resource,
-    db: &Surreal<Db>,
+    cache: &CacheOperations,
     frontmatter: &Frontmatter,
+) -> Result<DependencyGraph> {
+    build_graph_with_options(root, cache, frontmatter, false, None, None, None).await
+}
+
+/// Build a dependency graph, optionally re-hashing every local file and
+/// checking frontmatter
+///
+/// Local files whose size and modification time match their cached document
+/// entry keep the cached content hash without being hashed again. Setting
+/// `force_full_hashing` hashes every file regardless, for filesystems where
+/// mtimes can't be trusted. With a `schema`, every document's frontmatter is
+/// checked against it as the document is parsed (see
+/// [`validate_document`](crate::parse::validate_document)). With a
+/// `language`, each `::file` transclusion is the node of its translation
+/// into that language where one exists (see [`Resource::localized`]); the
+/// root is used as given. Every local file is hashed relative to
+/// `project_root`, or by its absolute path when it lies outside it or no root
+/// is given (see [`compute_resource_hash`](super::compute_resource_hash)).
+#[instrument(skip(cache, _frontmatter, schema), fields(root = ?root.source))]
+pub async fn build_graph_with_options(
+    root: Resource,
+    cache: &CacheOperations,
+    _frontmatter: &Frontmatter,
+    force_full_hashing: bool,
+    schema: Option<&FrontmatterSchema>,
+    language: Option<&str>,
+    project_root: Option<&Path>,
 ) -> Result<DependencyGraph> {
     let mut graph = DependencyGraph::new(root.clone());
-    let mut visited: HashMap<ResourceHash, bool> = HashMap::new();
-    let mut in_stack: HashMap<ResourceHash, bool> = HashMap::new();
+    graph.project_root = project_root.map(Path::to_path_buf);
+    let mut walk = GraphWalk {
+        graph,
+        visited: HashMap::new(),
+        in_stack: Vec::new(),
+        unhashed: HashMap::new(),
+        schema,
+        language,
+    };
 
     // Start recursive traversal
-    visit_resource(&root, &mut graph, &mut visited, &mut in_stack, db, frontmatter).await?;
+    walk.visit_resource(&root).await?;
+    let GraphWalk { mut graph, unhashed, .. } = walk;
 
     debug!("Graph built with {} nodes and {} edges", graph.nodes.len(), graph.edges.len());
 
+    // One batch lookup serves both the mtime fast path and staleness checks
+    let hashes: Vec<String> = graph.nodes.keys().map(|hash| hash.to_string()).collect();
+    let keys: Vec<&str> = hashes.iter().map(String::as_str).collect();
+    let cached = cache.get_documents(&keys).await?;
+
+    resolve_content_hashes(&mut graph, unhashed, &cached, force_full_hashing);
+    invalidate_stale_entries(&mut graph, cache, &cached).await?;
+
     Ok(graph)
 }
 
-/// Recursively visit a resource and build the graph
-#[instrument(skip_all, fields(source = ?resource.source))]
-fn visit_resource<'a>(
-    resource: &'a Resource,
-    graph: &'a mut DependencyGraph,
-    visited: &'a mut HashMap<ResourceHash, bool>,
-    in_stack: &'a mut HashMap<ResourceHash, bool>,
-    db: &'a Surreal<Db>,
-    frontmatter: &'a Frontmatter,
-) -> BoxFuture<'a, Result<ResourceHash>> {
-    Box::pin(async move {
-    let hash = compute_resource_hash(resource);
-
-    // Check if currently in the recursion stack (cycle detection)
-    if in_stack.contains_key(&hash) {
-        debug!("Circular dependency detected");
-        return Err(crate::error::CompositionError::Parse(
-            crate::error::ParseError::CircularDependency {
-                cycle: format!("{:?}", resource.source),
+/// Fill in the content hashes left out while visiting local files
+///
+/// A file whose stat matches its cached entry reuses the cached hash; any
+/// other file is hashed from the content read while visiting it.
+fn resolve_content_hashes(
+    graph: &mut DependencyGraph,
+    mut unhashed: HashMap<ResourceHash, String>,
+    cached: &HashMap<String, DocumentCacheEntry>,
+    force_full_hashing: bool,
+) {
+    let mut reused = 0;
+
+    for (hash, node) in graph.nodes.iter_mut() {
+        let (Some(stat), Some(content)) = (graph.file_stats.get(hash), unhashed.remove(hash)) else {
+            continue;
+        };
+
+        let unchanged = cached.get(&hash.to_string()).filter(|entry| {
+            !force_full_hashing
+                && entry.file_size == Some(stat.size)
+                && entry.modified_at == Some(stat.modified)
+        });
+
+        node.content_hash = Some(match unchanged {
+            Some(entry) => {
+                reused += 1;
+                entry.content_hash.clone()
             }
-        ));
+            None => compute_content_hash(&content),
+        });
     }
 
-    // Check if already fully processed
-    if visited.contains_key(&hash) {
-        debug!("Resource already visited, skipping");
-        return Ok(hash);
+    debug!("Reused {} cached content hashes for unchanged files", reused);
+}
+
+/// Evict document cache entries whose content hash no longer matches
+///
+/// `cached` holds the entries of all nodes, fetched with a single batch query
+/// rather than one query per node. Each stale entry is removed with
+/// [`invalidate_document_cascade`](CacheOperations::invalidate_document_cascade),
+/// and the stale resources plus everything that depends on them are recorded
+/// in [`DependencyGraph::invalidated`] so they are rendered again. Resources
+/// with no cache entry yet are not stale.
+async fn invalidate_stale_entries(
+    graph: &mut DependencyGraph,
+    cache: &CacheOperations,
+    cached: &HashMap<String, DocumentCacheEntry>,
+) -> Result<()> {
+    let stale: Vec<ResourceHash> = graph
+        .nodes
+        .iter()
+        .filter(|(hash, node)| {
+            cached
+                .get(&hash.to_string())
+                .is_some_and(|entry| Some(&entry.content_hash) != node.content_hash.as_ref())
+        })
+        .map(|(hash, _)| *hash)
+        .collect();
+
+    debug!(
+        "{} of {} documents cached, {} of them stale",
+        cached.len(),
+        graph.nodes.len(),
+        stale.len()
+    );
+
+    for hash in &stale {
+        cache.invalidate_document_cascade(&hash.to_string()).await?;
     }
 
-    // Mark as being processed (in the recursion stack)
-    in_stack.insert(hash, true);
+    graph.invalidated = with_dependents(graph, stale);
+    Ok(())
+}
 
-    // Load and parse the resource
-    debug!("Loading resource");
-    let content = load_resource(resource).await?;
-    let content_hash = compute_content_hash(&content);
+/// The state of a graph build while its resources are visited
+struct GraphWalk<'a> {
+    graph: DependencyGraph,
+    visited: HashMap<ResourceHash, bool>,
+    /// The resources being visited, each with its label, outermost first
+    in_stack: Vec<(ResourceHash, String)>,
+    /// Content of local files whose hash waits for the cached stats
+    unhashed: HashMap<ResourceHash, String>,
+    schema: Option<&'a FrontmatterSchema>,
+    language: Option<&'a str>,
+}
 
-    debug!("Parsing document");
-    let document = parse_document(&content, resource.clone())?;
+impl GraphWalk<'_> {
+    /// Recursively visit a resource and build the graph
+    #[instrument(skip_all, fields(source = ?resource.source))]
+    fn visit_resource<'s>(&'s mut self, resource: &'s Resource) -> BoxFuture<'s, Result<ResourceHash>> {
+        Box::pin(async move {
+        let hash = self.graph.resource_hash(resource);
+
+        // Check if currently in the recursion stack (cycle detection); the
+        // cycle is reported as the chain of resources that leads back around
+        if let Some(start) = self.in_stack.iter().position(|(entry, _)| *entry == hash) {
+            debug!("Circular dependency detected");
+            let chain: Vec<&str> = self.in_stack[start..].iter().map(|(_, label)| label.as_str()).collect();
+            return Err(crate::error::CompositionError::Parse(
+                crate::error::ParseError::CircularDependency {
+                    cycle: format!("{} -> {}", chain.join(" -> "), resource.label()),
+                }
+            ));
+        }
 
-    // Collect dependency hashes
-    let mut dependency_hashes = Vec::new();
+        // Check if already fully processed
+        if self.visited.contains_key(&hash) {
+            debug!("Resource already visited, skipping");
+            return Ok(hash);
+        }
 
-    // Recursively visit dependencies
-    for dep in &document.dependencies {
-        debug!("Processing dependency: {:?}", dep.source);
+        // Mark as being processed (in the recursion stack)
+        self.in_stack.push((hash, resource.label()));
+
+        // Load and parse the resource. Local files are stat'ed first and their
+        // content hashed once the whole graph is known, so unchanged files can
+        // skip hashing.
+        // Content loaded from a fallback is hashed right away, so the hash is
+        // always of the candidate that was actually loaded
+        debug!("Loading resource");
+        let stat = FileStat::of_resource(resource);
+        let (loaded, content) = load_resource_candidate(resource, load_resource).await?;
+        let content_hash = match stat.filter(|_| std::ptr::eq(loaded, resource)) {
+            Some(stat) => {
+                self.graph.file_stats.insert(hash, stat);
+                None
+            }
+            None => Some(compute_content_hash(&content)),
+        };
+
+        debug!("Parsing document");
+        // Warnings are reported when the document is rendered
+        let (document, _warnings) = parse_document_unchecked(&content, resource.clone())?;
+        if content_hash.is_none() {
+            self.unhashed.insert(hash, content);
+        }
+        if let Some(schema) = self.schema {
+            validate_document(&document, schema)?;
+        }
 
-        // Resolve relative paths based on the parent resource's location
-        let resolved_dep = resolve_relative_resource(dep, resource)?;
+        // Collect dependency hashes
+        let mut dependency_hashes = Vec::new();
+
+        // Recursively visit dependencies
+        for dep in &document.dependencies {
+            debug!("Processing dependency: {:?}", dep.source);
+
+            // Resolve relative paths based on the location of the candidate
+            // that was loaded, and transclusions to their translation, as the
+            // transclusion pass does
+            let mut resolved_dep = resolve_relative_resource(dep, loaded)?;
+            if let Some(language) = self.language {
+                if is_transcluded(&document.content, dep) {
+                    resolved_dep = resolved_dep.localized(language);
+                }
+            }
 
-        let dep_hash = visit_resource(&resolved_dep, graph, visited, in_stack, db, frontmatter).await?;
-        dependency_hashes.push(dep_hash);
+            // A missing optional dependency is left out; the transclusion pass
+            // reports it when the document is rendered
+            let dep_hash = match self.visit_resource(&resolved_dep).await {
+                Err(crate::error::CompositionError::Parse(ParseError::ResourceNotFound { path, .. }))
+                    if matches!(resolved_dep.requirement, ResourceRequirement::Optional) =>
+                {
+                    debug!("Skipping missing optional dependency {}", path);
+                    let missing = self.graph.resource_hash(&resolved_dep);
+                    self.in_stack.retain(|(entry, _)| *entry != missing);
+                    continue;
+                }
+                result => result?,
+            };
+            dependency_hashes.push(dep_hash);
+
+            // Add edge to graph
+            self.graph.add_edge(hash, dep_hash);
+        }
 
-        // Add edge to graph
-        graph.add_edge(hash, dep_hash);
-    }
+        // Create graph node
+        let node = GraphNode {
+            resource: resource.clone(),
+            content_hash,
+            dependencies: dependency_hashes,
+        };
 
-    // Create graph node
-    let node = GraphNode {
-        resource: resource.clone(),
-        content_hash: Some(content_hash),
-        dependencies: dependency_hashes,
-    };
+        // Add node to graph
+        self.graph.add_node(hash, node);
 
-    // Add node to graph
-    graph.add_node(hash, node);
+        // Mark as fully processed (remove from stack, add to visited)
+        self.in_stack.pop();
+        self.visited.insert(hash, true);
 
-    // Mark as fully processed (remove from stack, add to visited)
-    in_stack.remove(&hash);
-    visited.insert(hash, true);
+        Ok(hash)
+        })
+    }
+}
 
-    Ok(hash)
-    })
+/// Whether `dep` is the resource of one of the document's `::file` transclusions
+fn is_transcluded(content: &[DarkMatterNode], dep: &Resource) -> bool {
+    content
+        .iter()
+        .any(|node| matches!(node, DarkMatterNode::File { resource, .. } if resource.source == dep.source))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::graph::compute_resource_hash;
     use tempfile::TempDir;
 
-    async fn setup_test_db() -> (Surreal<Db>, TempDir) {
+    async fn setup_test_db() -> (CacheOperations, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db_path = temp_dir.path().join("test.db");
 
         let db = crate::cache::init_database(&db_path).await.unwrap();
         crate::cache::apply_schema(&db).await.unwrap();
 
-        (db, temp_dir)
+        (CacheOperations::new(db), temp_dir)
     }
 
     #[tokio::test]
     async fn test_build_graph_single_file() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         // Create a test file
         let test_file = tempfile::NamedTempFile::new().unwrap();
@@ -173,7 +354,7 @@ mod tests {
         let resource = Resource::local(test_file.path().to_path_buf());
         let frontmatter = Frontmatter::default();
 
-        let graph = build_graph(resource.clone(), &db, &frontmatter).await.unwrap();
+        let graph = build_graph(resource.clone(), &cache, &frontmatter).await.unwrap();
 
         assert_eq!(graph.root.source, resource.source);
         assert_eq!(graph.nodes.len(), 1);
@@ -182,7 +363,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_build_graph_with_dependencies() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         // Create test files
         let temp_dir = TempDir::new().unwrap();
@@ -198,7 +379,7 @@ mod tests {
         let resource = Resource::local(root_file.clone());
         let frontmatter = Frontmatter::default();
 
-        let graph = build_graph(resource, &db, &frontmatter).await.unwrap();
+        let graph = build_graph(resource, &cache, &frontmatter).await.unwrap();
 
         assert_eq!(graph.nodes.len(), 2);
         assert_eq!(graph.edges.len(), 1);
@@ -206,7 +387,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_build_graph_deduplicates() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         // Create test files where two files depend on the same third file
         let temp_dir = TempDir::new().unwrap();
@@ -236,10 +417,157 @@ mod tests {
         let resource = Resource::local(root_file.clone());
         let frontmatter = Frontmatter::default();
 
-        let graph = build_graph(resource, &db, &frontmatter).await.unwrap();
+        let graph = build_graph(resource, &cache, &frontmatter).await.unwrap();
 
         // Should have 4 nodes: root, dep1, dep2, shared
         // Shared should only appear once due to deduplication
         assert_eq!(graph.nodes.len(), 4);
     }
+
+    #[tokio::test]
+    async fn test_build_graph_coalesces_equivalent_paths() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        std::fs::write(temp_dir.path().join("a.md"), "# A").unwrap();
+        let root_file = temp_dir.path().join("root.md");
+        std::fs::write(&root_file, "# Root\n\n::file ./a.md\n\n::file a.md\n\n::file sub/../a.md\n").unwrap();
+
+        let graph = build_graph(Resource::local(root_file), &cache, &Frontmatter::default()).await.unwrap();
+
+        assert_eq!(graph.nodes.len(), 2);
+        let a = compute_resource_hash(&Resource::local(temp_dir.path().join("a.md")), None);
+        assert!(graph.nodes.contains_key(&a));
+    }
+
+    #[tokio::test]
+    async fn test_build_graph_uses_translated_transclusions() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        let temp_dir = TempDir::new().unwrap();
+        for (name, content) in [("a.md", "# A"), ("a.de.md", "# A auf Deutsch"), ("b.md", "# B")] {
+            std::fs::write(temp_dir.path().join(name), content).unwrap();
+        }
+        let root_file = temp_dir.path().join("root.md");
+        std::fs::write(&root_file, "# Root\n\n::file ./a.md\n\n::file ./b.md\n").unwrap();
+        let node = |name: &str| compute_resource_hash(&Resource::local(temp_dir.path().join(name)), None);
+
+        let root = Resource::local(root_file);
+        let graph = build_graph_with_options(root.clone(), &cache, &Frontmatter::default(), false, None, Some("de"), None)
+            .await
+            .unwrap();
+        assert!(graph.nodes.contains_key(&node("a.de.md")) && graph.nodes.contains_key(&node("b.md")));
+        assert!(!graph.nodes.contains_key(&node("a.md")));
+
+        let graph = build_graph(root, &cache, &Frontmatter::default()).await.unwrap();
+        assert!(graph.nodes.contains_key(&node("a.md")) && !graph.nodes.contains_key(&node("a.de.md")));
+    }
+
+    #[tokio::test]
+    async fn test_build_graph_hashes_relative_to_project_root() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("docs").join(".git")).unwrap();
+        std::fs::write(temp_dir.path().join("docs").join("a.md"), "# A").unwrap();
+        let root_file = temp_dir.path().join("root.md");
+        std::fs::write(&root_file, "# Root\n\n::file ./docs/a.md\n").unwrap();
+
+        let project_root = Some(temp_dir.path());
+        let graph = build_graph_with_options(
+            Resource::local(root_file),
+            &cache,
+            &Frontmatter::default(),
+            false,
+            None,
+            None,
+            project_root,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(graph.project_root.as_deref(), project_root);
+        let a = Resource::local(temp_dir.path().join("docs").join("a.md"));
+        assert!(graph.nodes.contains_key(&compute_resource_hash(&a, project_root)));
+        assert!(!graph.nodes.contains_key(&compute_resource_hash(&a, None)));
+    }
+
+    #[tokio::test]
+    async fn test_build_graph_invalidates_changed_content() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dep_file = temp_dir.path().join("dep.md");
+        let root_file = temp_dir.path().join("root.md");
+        let other_file = temp_dir.path().join("other.md");
+        std::fs::write(&dep_file, "# Dependency\n\nOriginal content.").unwrap();
+        std::fs::write(&other_file, "# Other\n\nUnrelated.").unwrap();
+        std::fs::write(&root_file, "# Root\n\n::file ./dep.md\n\n::file ./other.md").unwrap();
+
+        let root = Resource::local(root_file);
+        let frontmatter = Frontmatter::default();
+
+        let graph = build_graph(root.clone(), &cache, &frontmatter).await.unwrap();
+        assert!(graph.invalidated.is_empty());
+        crate::graph::persist_graph(&cache, &graph).await.unwrap();
+
+        // Unchanged content leaves the cache alone
+        let graph = build_graph(root.clone(), &cache, &frontmatter).await.unwrap();
+        assert!(graph.invalidated.is_empty());
+
+        std::fs::write(&dep_file, "# Dependency\n\nChanged content.").unwrap();
+        let graph = build_graph(root.clone(), &cache, &frontmatter).await.unwrap();
+
+        let dep_hash = compute_resource_hash(&Resource::local(dep_file), None);
+        let root_hash = compute_resource_hash(&root, None);
+        let other_hash = compute_resource_hash(&Resource::local(other_file), None);
+        assert_eq!(graph.invalidated, [dep_hash, root_hash].into_iter().collect());
+
+        // The stale entry and its dependent were evicted; the unrelated one is
+        // still cached
+        assert!(cache.get_document(&dep_hash.to_string()).await.unwrap().is_none());
+        assert!(cache.get_document(&root_hash.to_string()).await.unwrap().is_none());
+        assert!(cache.get_document(&other_hash.to_string()).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_build_graph_skips_hashing_unchanged_files() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("doc.md");
+        std::fs::write(&file, "# Doc\n\nOriginal.").unwrap();
+        let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+
+        let resource = Resource::local(file.clone());
+        let hash = compute_resource_hash(&resource, None);
+        let frontmatter = Frontmatter::default();
+
+        let graph = build_graph(resource.clone(), &cache, &frontmatter).await.unwrap();
+        let original_hash = graph.nodes[&hash].content_hash.clone().unwrap();
+        assert_eq!(original_hash, compute_content_hash("# Doc\n\nOriginal."));
+        assert_eq!(graph.file_stats[&hash], FileStat::of(&file).unwrap());
+        crate::graph::persist_graph(&cache, &graph).await.unwrap();
+
+        // Same size and mtime: the cached hash is trusted without reading it
+        std::fs::write(&file, "# Doc\n\nReplaced.").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let graph = build_graph(resource.clone(), &cache, &frontmatter).await.unwrap();
+        assert_eq!(graph.nodes[&hash].content_hash.as_ref(), Some(&original_hash));
+        assert!(graph.invalidated.is_empty());
+
+        // Forcing full hashing notices the change
+        let graph = build_graph_with_options(resource, &cache, &frontmatter, true, None, None, None).await.unwrap();
+        assert_eq!(
+            graph.nodes[&hash].content_hash,
+            Some(compute_content_hash("# Doc\n\nReplaced."))
+        );
+        assert!(graph.invalidated.contains(&hash));
+    }
 }
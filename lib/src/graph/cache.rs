@@ -1,59 +1,101 @@
 use crate::cache::operations::{CacheOperations, DocumentCacheEntry};
 use crate::error::Result;
-use crate::types::{DependencyGraph, GraphNode, Resource, ResourceSource};
+use crate::types::{DependencyGraph, GraphNode, Resource, ResourceHash, ResourceSource};
 use chrono::Utc;
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
+use std::path::Path;
 use tracing::{debug, instrument};
 
-use super::utils::compute_resource_hash;
+use super::git::git_location;
+use super::utils::{compute_resource_hash, FileStat};
+
+/// Document cache entry recording a resource's current content hash
+pub(crate) fn document_entry(
+    hash: ResourceHash,
+    resource: &Resource,
+    content_hash: String,
+    stat: Option<FileStat>,
+) -> DocumentCacheEntry {
+    DocumentCacheEntry {
+        id: None,
+        resource_hash: hash.to_string(),
+        content_hash,
+        file_path: match &resource.source {
+            ResourceSource::Local(path) => Some(path.to_string_lossy().to_string()),
+            ResourceSource::Remote(_) | ResourceSource::Git { .. } | ResourceSource::Memory { .. } => None,
+        },
+        url: match &resource.source {
+            ResourceSource::Local(_) | ResourceSource::Memory { .. } => None,
+            ResourceSource::Remote(url) => Some(url.to_string()),
+            ResourceSource::Git { repo_url, ref_, path } => {
+                Some(git_location(repo_url, ref_, path))
+            }
+        },
+        last_validated: Utc::now(),
+        file_size: stat.map(|stat| stat.size),
+        modified_at: stat.map(|stat| stat.modified),
+    }
+}
 
 /// Persist a dependency graph to the database
 ///
-/// Stores both the nodes (as document cache entries) and the edges (as depends_on relations)
-#[instrument(skip(db, graph))]
-pub async fn persist_graph(db: &Surreal<Db>, graph: &DependencyGraph) -> Result<()> {
+/// Stores both the nodes (as document cache entries) and the edges (as depends_on relations).
+/// Nothing is stored in a disabled cache.
+#[instrument(skip(cache, graph))]
+pub async fn persist_graph(cache: &CacheOperations, graph: &DependencyGraph) -> Result<()> {
+    if !cache.is_enabled() {
+        return Ok(());
+    }
     debug!("Persisting graph with {} nodes and {} edges", graph.nodes.len(), graph.edges.len());
 
-    let cache_ops = CacheOperations::new(db.clone());
-
-    // Upsert all nodes as document cache entries
-    for (hash, node) in &graph.nodes {
-        let doc_entry = DocumentCacheEntry {
-            id: None,
-            resource_hash: format!("{:016x}", hash),
-            content_hash: node.content_hash.clone().unwrap_or_default(),
-            file_path: match &node.resource.source {
-                ResourceSource::Local(path) => Some(path.to_string_lossy().to_string()),
-                ResourceSource::Remote(_) => None,
-            },
-            url: match &node.resource.source {
-                ResourceSource::Local(_) => None,
-                ResourceSource::Remote(url) => Some(url.to_string()),
-            },
-            last_validated: Utc::now(),
-        };
+    let db = cache.db();
 
-        cache_ops.upsert_document(doc_entry).await?;
-    }
+    // Drop the edges previously stored for these nodes; they are rebuilt below
+    let hashes: Vec<String> = graph.nodes.keys().map(|hash| hash.to_string()).collect();
+    db.query("DELETE depends_on WHERE in.resource_hash IN $hashes")
+        .bind(("hashes", hashes))
+        .await
+        .and_then(surrealdb::Response::check)
+        .map_err(|e| crate::error::CacheError::QueryFailed(e.to_string()))?;
 
-    // Create edges using RELATE syntax
-    for (from, to) in &graph.edges {
+    // Upsert all nodes as document cache entries in one batch. In-memory
+    // resources can't be read again, so they are never stored.
+    let is_memory = |hash: &ResourceHash| {
+        graph.nodes.get(hash).is_some_and(|node| node.resource.is_memory())
+    };
+    let doc_entries = graph
+        .nodes
+        .iter()
+        .filter(|(hash, _)| !is_memory(hash))
+        .map(|(hash, node)| {
+            document_entry(
+                *hash,
+                &node.resource,
+                node.content_hash.clone().unwrap_or_default(),
+                graph.file_stats.get(hash).copied(),
+            )
+        })
+        .collect();
+    cache.upsert_documents(doc_entries).await?;
+
+    // Create edges between the stored document records
+    for (from, to) in graph.edges.iter().filter(|(from, to)| !is_memory(from) && !is_memory(to)) {
         // Get reference type from the nodes
         let reference_type = "transclusion"; // Default type
         let required = false; // Default to non-required
 
-        let from_id = format!("document:{:016x}", from);
-        let to_id = format!("document:{:016x}", to);
-
         db.query(
-            "RELATE $from->depends_on->$to SET reference_type = $ref_type, required = $required"
+            r#"
+            LET $from_id = (SELECT VALUE id FROM document WHERE resource_hash = $from)[0];
+            LET $to_id = (SELECT VALUE id FROM document WHERE resource_hash = $to)[0];
+            RELATE $from_id->depends_on->$to_id SET reference_type = $ref_type, required = $required;
+            "#,
         )
-        .bind(("from", from_id))
-        .bind(("to", to_id))
+        .bind(("from", from.to_string()))
+        .bind(("to", to.to_string()))
         .bind(("ref_type", reference_type))
         .bind(("required", required))
         .await
+        .and_then(surrealdb::Response::check)
         .map_err(|e| crate::error::CacheError::QueryFailed(e.to_string()))?;
     }
 
@@ -63,21 +105,26 @@ pub async fn persist_graph(db: &Surreal<Db>, graph: &DependencyGraph) -> Result<
 
 /// Load a dependency graph from the database
 ///
-/// Reconstructs the graph from stored document entries and depends_on relations
+/// Reconstructs the graph from stored document entries and depends_on relations.
+/// Local resources are looked up relative to `project_root`, which should be
+/// the root the graph was built with.
 ///
 /// Note: This is a simplified implementation that loads all documents and edges,
 /// then filters to the reachable subgraph. A production implementation would use
 /// graph traversal queries.
-#[instrument(skip(db), fields(root = ?root.source))]
-pub async fn load_graph(db: &Surreal<Db>, root: Resource) -> Result<Option<DependencyGraph>> {
+#[instrument(skip(cache), fields(root = ?root.source))]
+pub async fn load_graph(
+    cache: &CacheOperations,
+    root: Resource,
+    project_root: Option<&Path>,
+) -> Result<Option<DependencyGraph>> {
     debug!("Loading graph from database");
 
-    let root_hash = compute_resource_hash(&root);
-    let cache_ops = CacheOperations::new(db.clone());
+    let root_hash = compute_resource_hash(&root, project_root);
 
     // Check if the root document exists
-    let root_doc = cache_ops
-        .get_document(&format!("{:016x}", root_hash))
+    let root_doc = cache
+        .get_document(&root_hash.to_string())
         .await?;
 
     if root_doc.is_none() {
@@ -86,6 +133,7 @@ pub async fn load_graph(db: &Surreal<Db>, root: Resource) -> Result<Option<Depen
     }
 
     let mut graph = DependencyGraph::new(root.clone());
+    graph.project_root = project_root.map(Path::to_path_buf);
 
     // Add root node
     let root_doc = root_doc.unwrap();
@@ -111,7 +159,7 @@ mod tests {
     use std::path::PathBuf;
     use tempfile::TempDir;
 
-    async fn setup_test_db() -> (Surreal<Db>, TempDir) {
+    async fn setup_test_db() -> (CacheOperations, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db_path = temp_dir.path().join("test.db");
 
@@ -120,19 +168,19 @@ mod tests {
             .unwrap();
         crate::cache::apply_schema(&db).await.unwrap();
 
-        (db, temp_dir)
+        (CacheOperations::new(db), temp_dir)
     }
 
     #[tokio::test]
     async fn test_persist_and_load_graph() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         // Create a simple graph: A -> B
         let a = Resource::local(PathBuf::from("a.md"));
         let b = Resource::local(PathBuf::from("b.md"));
 
-        let hash_a = compute_resource_hash(&a);
-        let hash_b = compute_resource_hash(&b);
+        let hash_a = compute_resource_hash(&a, None);
+        let hash_b = compute_resource_hash(&b, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -157,10 +205,10 @@ mod tests {
         graph.add_edge(hash_a, hash_b);
 
         // Persist the graph
-        persist_graph(&db, &graph).await.unwrap();
+        persist_graph(&cache, &graph).await.unwrap();
 
         // Load it back (simplified - only loads root node for now)
-        let loaded = load_graph(&db, a.clone()).await.unwrap();
+        let loaded = load_graph(&cache, a.clone(), None).await.unwrap();
 
         assert!(loaded.is_some());
         let loaded_graph = loaded.unwrap();
@@ -170,12 +218,65 @@ mod tests {
         assert!(loaded_graph.nodes.contains_key(&hash_a));
     }
 
+    #[tokio::test]
+    async fn test_persisted_edges_drive_invalidation_cascade() {
+        let (cache, _temp_dir) = setup_test_db().await;
+
+        // a -> b -> c, plus an unrelated d
+        let resources: Vec<Resource> = ["a.md", "b.md", "c.md", "d.md"]
+            .iter()
+            .map(|name| Resource::local(PathBuf::from(name)))
+            .collect();
+        let hashes: Vec<ResourceHash> =
+            resources.iter().map(|resource| compute_resource_hash(resource, None)).collect();
+
+        let mut graph = DependencyGraph::new(resources[0].clone());
+        for (resource, hash) in resources.iter().zip(&hashes) {
+            graph.add_node(
+                *hash,
+                GraphNode {
+                    resource: resource.clone(),
+                    content_hash: Some(hash.to_string()),
+                    dependencies: vec![],
+                },
+            );
+        }
+        graph.add_edge(hashes[0], hashes[1]);
+        graph.add_edge(hashes[1], hashes[2]);
+
+        // Persisting twice must not leave dangling or duplicate edges
+        persist_graph(&cache, &graph).await.unwrap();
+        persist_graph(&cache, &graph).await.unwrap();
+        let edges: Vec<surrealdb::sql::Thing> = cache
+            .db()
+            .query("SELECT VALUE id FROM depends_on WHERE in.resource_hash != NONE")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(edges.len(), 2);
+
+        let mut invalidated = cache
+            .invalidate_document_cascade(&hashes[2].to_string())
+            .await
+            .unwrap();
+        invalidated.sort();
+        let mut expected = vec![hashes[0].to_string(), hashes[1].to_string()];
+        expected.sort();
+        assert_eq!(invalidated, expected);
+
+        for hash in &hashes[..3] {
+            assert!(cache.get_document(&hash.to_string()).await.unwrap().is_none());
+        }
+        assert!(cache.get_document(&hashes[3].to_string()).await.unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_load_nonexistent_graph() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         let resource = Resource::local(PathBuf::from("nonexistent.md"));
-        let result = load_graph(&db, resource).await.unwrap();
+        let result = load_graph(&cache, resource, None).await.unwrap();
 
         assert!(result.is_none());
     }
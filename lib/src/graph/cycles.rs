@@ -1,99 +1,188 @@
-use crate::error::{ParseError, Result};
-use crate::types::{DependencyGraph, ResourceHash};
-use std::collections::{HashMap, HashSet};
+use crate::graph::git_location;
+use crate::types::{DependencyGraph, ResourceHash, ResourceSource};
+use std::collections::{HashMap, HashSet, VecDeque};
 use tracing::{debug, instrument};
 
-/// Detect cycles in a dependency graph using depth-first search
+/// The dependency cycles found in a graph
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CycleReport {
+    /// One loop per strongly connected component that contains a cycle. Each
+    /// lists the resources in dependency order; the last depends on the first.
+    pub cycles: Vec<Vec<ResourceHash>>,
+}
+
+impl CycleReport {
+    /// The resource path (or URL) of every node in each cycle
+    pub fn resource_paths(&self, graph: &DependencyGraph) -> Vec<Vec<String>> {
+        self.cycles
+            .iter()
+            .map(|cycle| cycle.iter().map(|hash| resource_path(graph, hash)).collect())
+            .collect()
+    }
+
+    /// Describe each cycle as `a.md → b.md → c.md → a.md`, one per line
+    pub fn to_human_readable(&self, graph: &DependencyGraph) -> String {
+        self.resource_paths(graph)
+            .into_iter()
+            .map(|mut paths| {
+                if let Some(first) = paths.first().cloned() {
+                    paths.push(first);
+                }
+                paths.join(" → ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Detect cycles in a dependency graph using Tarjan's strongly connected
+/// components algorithm
 ///
-/// Returns Ok(()) if the graph is acyclic, or an error with the cycle path
-/// if a cycle is detected.
+/// Every cycle in the graph lies within a strongly connected component with
+/// more than one node (or a node that depends on itself), so all of them are
+/// found in one pass rather than stopping at the first. Returns a
+/// [`CycleReport`] with one loop per such component if any are found.
 #[instrument(skip(graph))]
-pub fn detect_cycles(graph: &DependencyGraph) -> Result<()> {
+pub fn detect_cycles(graph: &DependencyGraph) -> Result<(), CycleReport> {
     debug!("Detecting cycles in graph with {} nodes", graph.nodes.len());
 
-    let mut visiting = HashSet::new(); // Gray nodes (currently being visited)
-    let mut visited = HashSet::new(); // Black nodes (completely processed)
-
-    // Build adjacency map for efficient lookups
+    // Build adjacency map for efficient lookups, sorted so reports are stable
     let mut adjacency: HashMap<ResourceHash, Vec<ResourceHash>> = HashMap::new();
     for (from, to) in &graph.edges {
         adjacency.entry(*from).or_default().push(*to);
     }
+    for neighbors in adjacency.values_mut() {
+        neighbors.sort();
+        neighbors.dedup();
+    }
 
-    // Try DFS from each unvisited node
-    for &hash in graph.nodes.keys() {
-        if !visited.contains(&hash) {
-            let mut path = Vec::new();
-            dfs(
-                hash,
-                &adjacency,
-                &mut visiting,
-                &mut visited,
-                &mut path,
-                graph,
-            )?
+    let mut nodes: Vec<ResourceHash> = graph.nodes.keys().copied().collect();
+    nodes.sort();
+
+    let mut tarjan = Tarjan {
+        adjacency: &adjacency,
+        next_index: 0,
+        index: HashMap::new(),
+        low_link: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for node in nodes {
+        if !tarjan.index.contains_key(&node) {
+            tarjan.strong_connect(node);
         }
     }
 
-    debug!("No cycles detected");
-    Ok(())
+    let cycles: Vec<Vec<ResourceHash>> = tarjan
+        .components
+        .iter()
+        .filter(|component| {
+            component.len() > 1
+                || adjacency.get(&component[0]).is_some_and(|next| next.contains(&component[0]))
+        })
+        .map(|component| cycle_in(component, &adjacency))
+        .collect();
+
+    if cycles.is_empty() {
+        debug!("No cycles detected");
+        Ok(())
+    } else {
+        debug!("Detected {} cycles", cycles.len());
+        Err(CycleReport { cycles })
+    }
 }
 
-/// Depth-first search for cycle detection
-///
-/// Returns Err with a formatted error message if a cycle is found
-fn dfs(
-    node: ResourceHash,
-    adjacency: &HashMap<ResourceHash, Vec<ResourceHash>>,
-    visiting: &mut HashSet<ResourceHash>,
-    visited: &mut HashSet<ResourceHash>,
-    path: &mut Vec<ResourceHash>,
-    graph: &DependencyGraph,
-) -> Result<()> {
-    // If we're currently visiting this node, we found a cycle
-    if visiting.contains(&node) {
-        // Find where the cycle starts in the path
-        let cycle_start = path.iter().position(|&h| h == node).unwrap_or(0);
-        let cycle_nodes: Vec<ResourceHash> = path[cycle_start..].to_vec();
-
-        // Build error message with resource paths
-        let cycle_description = cycle_nodes
-            .iter()
-            .filter_map(|h| graph.nodes.get(h))
-            .map(|n| match &n.resource.source {
-                crate::types::ResourceSource::Local(path) => path.to_string_lossy().to_string(),
-                crate::types::ResourceSource::Remote(url) => url.to_string(),
-            })
-            .collect::<Vec<_>>()
-            .join(" -> ");
+/// State for Tarjan's strongly connected components algorithm
+struct Tarjan<'a> {
+    adjacency: &'a HashMap<ResourceHash, Vec<ResourceHash>>,
+    next_index: usize,
+    index: HashMap<ResourceHash, usize>,
+    low_link: HashMap<ResourceHash, usize>,
+    stack: Vec<ResourceHash>,
+    on_stack: HashSet<ResourceHash>,
+    components: Vec<Vec<ResourceHash>>,
+}
 
-        return Err(crate::error::CompositionError::Parse(ParseError::CircularDependency {
-            cycle: cycle_description,
-        }));
-    }
+impl Tarjan<'_> {
+    fn strong_connect(&mut self, node: ResourceHash) {
+        self.index.insert(node, self.next_index);
+        self.low_link.insert(node, self.next_index);
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        let adjacency = self.adjacency;
+        for &neighbor in adjacency.get(&node).into_iter().flatten() {
+            if !self.index.contains_key(&neighbor) {
+                self.strong_connect(neighbor);
+                let low = self.low_link[&node].min(self.low_link[&neighbor]);
+                self.low_link.insert(node, low);
+            } else if self.on_stack.contains(&neighbor) {
+                let low = self.low_link[&node].min(self.index[&neighbor]);
+                self.low_link.insert(node, low);
+            }
+        }
 
-    // If already fully visited, nothing to do
-    if visited.contains(&node) {
-        return Ok(());
+        // `node` is the root of a component: pop it off the stack
+        if self.low_link[&node] == self.index[&node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(&member);
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            component.sort();
+            self.components.push(component);
+        }
     }
+}
 
-    // Mark as currently visiting
-    visiting.insert(node);
-    path.push(node);
-
-    // Visit all neighbors
-    if let Some(neighbors) = adjacency.get(&node) {
-        for &neighbor in neighbors {
-            dfs(neighbor, adjacency, visiting, visited, path, graph)?;
+/// The shortest loop through the smallest hash of a strongly connected component
+fn cycle_in(
+    component: &[ResourceHash],
+    adjacency: &HashMap<ResourceHash, Vec<ResourceHash>>,
+) -> Vec<ResourceHash> {
+    let start = component[0];
+    let members: HashSet<ResourceHash> = component.iter().copied().collect();
+    let mut parent: HashMap<ResourceHash, ResourceHash> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for &neighbor in adjacency.get(&node).into_iter().flatten() {
+            if neighbor == start {
+                // Walk back from `node` to `start` to recover the loop
+                let mut cycle = vec![node];
+                let mut current = node;
+                while current != start {
+                    current = parent[&current];
+                    cycle.push(current);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if members.contains(&neighbor) && !parent.contains_key(&neighbor) {
+                parent.insert(neighbor, node);
+                queue.push_back(neighbor);
+            }
         }
     }
 
-    // Done visiting this node
-    path.pop();
-    visiting.remove(&node);
-    visited.insert(node);
+    // Every node in a strongly connected component lies on a loop
+    component.to_vec()
+}
 
-    Ok(())
+/// A node's resource path or URL, falling back to its hash
+fn resource_path(graph: &DependencyGraph, hash: &ResourceHash) -> String {
+    match graph.nodes.get(hash).map(|node| &node.resource.source) {
+        Some(ResourceSource::Local(path)) => path.to_string_lossy().to_string(),
+        Some(ResourceSource::Remote(url)) => url.to_string(),
+        Some(ResourceSource::Git { repo_url, ref_, path }) => git_location(repo_url, ref_, path),
+        Some(ResourceSource::Memory { name, .. }) => name.clone(),
+        None => hash.to_string(),
+    }
 }
 
 #[cfg(test)]
@@ -109,9 +198,9 @@ mod tests {
         let b = Resource::local(PathBuf::from("b.md"));
         let c = Resource::local(PathBuf::from("c.md"));
 
-        let hash_a = crate::graph::utils::compute_resource_hash(&a);
-        let hash_b = crate::graph::utils::compute_resource_hash(&b);
-        let hash_c = crate::graph::utils::compute_resource_hash(&c);
+        let hash_a = crate::graph::utils::compute_resource_hash(&a, None);
+        let hash_b = crate::graph::utils::compute_resource_hash(&b, None);
+        let hash_c = crate::graph::utils::compute_resource_hash(&c, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -146,8 +235,8 @@ mod tests {
         let a = Resource::local(PathBuf::from("a.md"));
         let b = Resource::local(PathBuf::from("b.md"));
 
-        let hash_a = crate::graph::utils::compute_resource_hash(&a);
-        let hash_b = crate::graph::utils::compute_resource_hash(&b);
+        let hash_a = crate::graph::utils::compute_resource_hash(&a, None);
+        let hash_b = crate::graph::utils::compute_resource_hash(&b, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -166,24 +255,19 @@ mod tests {
         graph.add_edge(hash_a, hash_b);
         graph.add_edge(hash_b, hash_a);
 
-        let result = detect_cycles(&graph);
-        assert!(result.is_err());
+        let report = detect_cycles(&graph).unwrap_err();
+        assert_eq!(report.cycles.len(), 1);
 
-        if let Err(crate::error::CompositionError::Parse(
-            crate::error::ParseError::CircularDependency { cycle }
-        )) = result {
-            assert!(cycle.contains("a.md"));
-            assert!(cycle.contains("b.md"));
-        } else {
-            panic!("Expected CircularDependency error, got: {:?}", result);
-        }
+        let description = report.to_human_readable(&graph);
+        assert!(description.contains("a.md"));
+        assert!(description.contains("b.md"));
     }
 
     #[test]
     fn test_detect_cycles_self_reference() {
         // Create a self-reference: A -> A
         let a = Resource::local(PathBuf::from("a.md"));
-        let hash_a = crate::graph::utils::compute_resource_hash(&a);
+        let hash_a = crate::graph::utils::compute_resource_hash(&a, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -207,10 +291,10 @@ mod tests {
         let c = Resource::local(PathBuf::from("c.md"));
         let d = Resource::local(PathBuf::from("d.md"));
 
-        let hash_a = crate::graph::utils::compute_resource_hash(&a);
-        let hash_b = crate::graph::utils::compute_resource_hash(&b);
-        let hash_c = crate::graph::utils::compute_resource_hash(&c);
-        let hash_d = crate::graph::utils::compute_resource_hash(&d);
+        let hash_a = crate::graph::utils::compute_resource_hash(&a, None);
+        let hash_b = crate::graph::utils::compute_resource_hash(&b, None);
+        let hash_c = crate::graph::utils::compute_resource_hash(&c, None);
+        let hash_d = crate::graph::utils::compute_resource_hash(&d, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -246,4 +330,78 @@ mod tests {
         let result = detect_cycles(&graph);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_detect_cycles_triangle() {
+        // A -> B -> C -> A, plus D which depends on the loop but isn't in it
+        let names = ["a.md", "b.md", "c.md", "d.md"];
+        let resources: Vec<Resource> = names.iter().map(|n| Resource::local(PathBuf::from(n))).collect();
+        let hashes: Vec<ResourceHash> = resources
+            .iter()
+            .map(|resource| crate::graph::utils::compute_resource_hash(resource, None))
+            .collect();
+
+        let mut graph = DependencyGraph::new(resources[0].clone());
+        for (resource, hash) in resources.iter().zip(&hashes) {
+            graph.add_node(*hash, GraphNode {
+                resource: resource.clone(),
+                content_hash: None,
+                dependencies: vec![],
+            });
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (3, 0)] {
+            graph.add_edge(hashes[from], hashes[to]);
+        }
+
+        let report = detect_cycles(&graph).unwrap_err();
+        assert_eq!(report.cycles.len(), 1);
+
+        // The loop is reported in dependency order, starting from its smallest hash
+        let cycle = &report.cycles[0];
+        assert_eq!(cycle.len(), 3);
+        let start = hashes.iter().position(|h| *h == cycle[0]).unwrap();
+        assert_eq!(cycle[1], hashes[(start + 1) % 3]);
+        assert_eq!(cycle[2], hashes[(start + 2) % 3]);
+
+        let first = names[start];
+        let expected = format!(
+            "{} → {} → {} → {}",
+            first,
+            names[(start + 1) % 3],
+            names[(start + 2) % 3],
+            first
+        );
+        assert_eq!(report.to_human_readable(&graph), expected);
+    }
+
+    #[test]
+    fn test_detect_cycles_reports_every_component() {
+        // Two separate loops: A <-> B and C -> C
+        let resources: Vec<Resource> = ["a.md", "b.md", "c.md"]
+            .iter()
+            .map(|n| Resource::local(PathBuf::from(n)))
+            .collect();
+        let hashes: Vec<ResourceHash> = resources
+            .iter()
+            .map(|resource| crate::graph::utils::compute_resource_hash(resource, None))
+            .collect();
+
+        let mut graph = DependencyGraph::new(resources[0].clone());
+        for (resource, hash) in resources.iter().zip(&hashes) {
+            graph.add_node(*hash, GraphNode {
+                resource: resource.clone(),
+                content_hash: None,
+                dependencies: vec![],
+            });
+        }
+        for (from, to) in [(0, 1), (1, 0), (2, 2)] {
+            graph.add_edge(hashes[from], hashes[to]);
+        }
+
+        let report = detect_cycles(&graph).unwrap_err();
+        let mut sizes: Vec<usize> = report.cycles.iter().map(Vec::len).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2]);
+        assert_eq!(report.resource_paths(&graph).len(), 2);
+    }
 }
@@ -0,0 +1,229 @@
+use crate::error::{CompositionError, ParseError, Result};
+use crate::parse::validate_git_path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use tokio::sync::OnceCell;
+use tracing::{debug, instrument};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Numbers checkout directories, so each clone gets a directory of its own
+static NEXT_CHECKOUT: AtomicU64 = AtomicU64::new(0);
+
+/// The checkouts of the live [`GitCheckouts`] handles of this process
+static CHECKOUTS: LazyLock<Mutex<Weak<GitCheckouts>>> = LazyLock::new(|| Mutex::new(Weak::new()));
+
+/// `repo_url@ref:path`, the name of a file in a git repository used in
+/// labels, hash keys and error messages
+pub(crate) fn git_location(repo_url: &str, ref_: &str, path: &str) -> String {
+    format!("{}@{}:{}", repo_url, ref_, path)
+}
+
+/// Shallow checkouts of git repositories, keyed on `repo_url@ref`
+///
+/// Every [`CompositionApi`](crate::CompositionApi) holds the process's
+/// shared handle, so its checkouts are reused across renders; they are
+/// removed from the temp directory when the last handle is dropped.
+/// Concurrent reads from the same repository wait for a single clone, and
+/// clones of different repositories run at the same time.
+#[derive(Debug, Default)]
+pub(crate) struct GitCheckouts {
+    dirs: Mutex<HashMap<String, Arc<OnceCell<Checkout>>>>,
+}
+
+impl GitCheckouts {
+    /// The handle shared by this process, created when there is none
+    pub(crate) fn shared() -> Arc<Self> {
+        let mut shared = CHECKOUTS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(checkouts) = shared.upgrade() {
+            return checkouts;
+        }
+        let checkouts = Arc::new(Self::default());
+        *shared = Arc::downgrade(&checkouts);
+        checkouts
+    }
+
+    /// Read a file from a repository at a ref, cloning it if necessary
+    pub(crate) async fn read_file(&self, repo_url: &str, ref_: &str, path: &str) -> Result<String> {
+        validate_git_path(path)?;
+
+        let root = self.checkout(repo_url, ref_).await?;
+        let location = git_location(repo_url, ref_, path);
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || read_checkout_file(&root, &path, &location))
+            .await
+            .map_err(|e| CompositionError::Parse(ParseError::InvalidResource(e.to_string())))?
+    }
+
+    /// The checkout directory of a repository, cloning it if necessary
+    async fn checkout(&self, repo_url: &str, ref_: &str) -> Result<PathBuf> {
+        let key = format!("{}@{}", repo_url, ref_);
+        let cell = self.dirs.lock().unwrap_or_else(|e| e.into_inner()).entry(key.clone()).or_default().clone();
+
+        let checkout = cell
+            .get_or_try_init(|| {
+                let (repo_url, ref_) = (repo_url.to_string(), ref_.to_string());
+                async move {
+                    let label = key.clone();
+                    tokio::task::spawn_blocking(move || clone(&repo_url, &ref_, &key))
+                        .await
+                        .map_err(|e| clone_error(&label, &e.to_string()))?
+                }
+            })
+            .await?;
+        Ok(checkout.0.clone())
+    }
+}
+
+/// A checkout directory, removed when dropped
+#[derive(Debug)]
+struct Checkout(PathBuf);
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            debug!("Couldn't remove checkout {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Read a file from a git repository at the given ref
+///
+/// The repository is shallow-cloned into a temp directory on first use, on
+/// Tokio's blocking pool, and the checkout is reused for every later read
+/// of the same `repo_url` and `ref_` while a [`GitCheckouts`] handle lives.
+/// `path` must stay inside the checkout, symlinks included.
+#[instrument]
+pub(crate) async fn read_git_file(repo_url: &str, ref_: &str, path: &str) -> Result<String> {
+    GitCheckouts::shared().read_file(repo_url, ref_, path).await
+}
+
+/// Read `path` inside the checkout at `root`, after resolving symlinks
+fn read_checkout_file(root: &Path, path: &str, location: &str) -> Result<String> {
+    let not_found = |e: std::io::Error| {
+        CompositionError::Parse(ParseError::ResourceNotFound { path: location.to_string(), error: e.to_string() })
+    };
+    let root = root.canonicalize().map_err(not_found)?;
+    let file = root.join(path).canonicalize().map_err(not_found)?;
+    if !file.starts_with(&root) {
+        return Err(CompositionError::Parse(ParseError::InvalidResource(format!(
+            "{} resolves outside the repository",
+            location
+        ))));
+    }
+
+    std::fs::read_to_string(&file).map_err(not_found)
+}
+
+/// Shallow-clone a repository at a ref into a new temp directory
+fn clone(repo_url: &str, ref_: &str, key: &str) -> Result<Checkout> {
+    let dir = std::env::temp_dir().join(format!(
+        "composition-git-{}-{}-{:016x}",
+        std::process::id(),
+        NEXT_CHECKOUT.fetch_add(1, Ordering::Relaxed),
+        xxh3_64(key.as_bytes())
+    ));
+
+    // A leftover directory from an earlier, failed attempt would make the clone fail
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    debug!("Cloning {} into {}", key, dir.display());
+    let output = Command::new("git")
+        .args(["clone", "--quiet", "--depth", "1", "--branch", ref_, "--", repo_url])
+        .arg(&dir)
+        .output()
+        .map_err(|e| clone_error(key, &format!("failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(clone_error(key, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(Checkout(dir))
+}
+
+fn clone_error(key: &str, message: &str) -> CompositionError {
+    CompositionError::Parse(ParseError::ResourceNotFound {
+        path: key.to_string(),
+        error: format!("git clone failed: {}", message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(dir: &std::path::Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn repo_with(files: &[(&str, &str)]) -> TempDir {
+        let repo = TempDir::new().unwrap();
+        git(repo.path(), &["init", "--quiet", "--initial-branch", "docs"]);
+        for (path, content) in files {
+            let file = repo.path().join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+        git(repo.path(), &["add", "."]);
+        git(repo.path(), &["commit", "--quiet", "-m", "init"]);
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_read_git_file_from_local_repo() {
+        let repo = repo_with(&[("snippets/warning.md", "**Warning!**")]);
+        let repo_url = format!("file://{}", repo.path().display());
+        let checkouts = GitCheckouts::default();
+
+        let content = checkouts.read_file(&repo_url, "docs", "snippets/warning.md").await.unwrap();
+        assert_eq!(content, "**Warning!**");
+
+        // Second read reuses the checkout even if the source repository disappears
+        let checkout_dir = checkouts.checkout(&repo_url, "docs").await.unwrap();
+        drop(repo);
+        let content = checkouts.read_file(&repo_url, "docs", "snippets/warning.md").await.unwrap();
+        assert_eq!(content, "**Warning!**");
+
+        // The checkout goes with the handle
+        drop(checkouts);
+        assert!(!checkout_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_read_git_file_rejects_traversal() {
+        let result = read_git_file("https://example.com/repo.git", "main", "../outside.md").await;
+        assert!(matches!(
+            result,
+            Err(CompositionError::Parse(ParseError::InvalidResource(_)))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_read_git_file_rejects_symlinks_out_of_the_checkout() {
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.md"), "secret").unwrap();
+        let repo = repo_with(&[("readme.md", "hi")]);
+        std::os::unix::fs::symlink(outside.path().join("secret.md"), repo.path().join("link.md")).unwrap();
+        git(repo.path(), &["add", "."]);
+        git(repo.path(), &["commit", "--quiet", "-m", "link"]);
+        let repo_url = format!("file://{}", repo.path().display());
+
+        let result = GitCheckouts::default().read_file(&repo_url, "docs", "link.md").await;
+
+        assert!(matches!(result, Err(CompositionError::Parse(ParseError::InvalidResource(_)))), "{:?}", result);
+    }
+}
@@ -15,6 +15,8 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use tracing::{debug, instrument};
 
+use super::utils::strip_project_root;
+
 lazy_static::lazy_static! {
     /// Cache of Gitignore matchers per project root
     /// Key: absolute path to project root
@@ -65,9 +67,8 @@ pub fn is_ignored(path: &Path, project_root: &Path) -> Result<bool> {
 
     // Match against gitignore rules
     // The path must be relative to the project root for ignore crate
-    let relative_path = abs_path
-        .strip_prefix(project_root)
-        .unwrap_or(&abs_path);
+    let relative_path = strip_project_root(&abs_path, project_root);
+    let relative_path = relative_path.as_deref().unwrap_or(&abs_path);
 
     // Check if path is a directory
     let is_dir = abs_path.is_dir();
@@ -136,6 +137,7 @@ fn get_or_create_gitignore(project_root: &Path) -> Result<Arc<Gitignore>> {
 ///
 /// This loads .gitignore from the project root and respects:
 /// - .gitignore in project root
+/// - .ignore in project root
 /// - .git/info/exclude
 /// - Global gitignore (from git config)
 #[instrument(skip_all, fields(root = ?project_root))]
@@ -152,6 +154,15 @@ fn build_gitignore(project_root: &Path) -> Result<Gitignore> {
         }
     }
 
+    // Add .ignore, which tools like ripgrep also honor
+    let ignore_path = project_root.join(".ignore");
+    if ignore_path.exists() {
+        debug!("Loading .ignore from {:?}", ignore_path);
+        if let Some(e) = builder.add(&ignore_path) {
+            debug!("Failed to add .ignore: {}", e);
+        }
+    }
+
     // Add .git/info/exclude if it exists
     let git_exclude = project_root.join(".git").join("info").join("exclude");
     if git_exclude.exists() {
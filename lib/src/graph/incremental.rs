@@ -0,0 +1,191 @@
+use crate::types::{DependencyGraph, ResourceHash};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::{debug, instrument};
+
+/// Find the resources that need re-rendering after a change
+///
+/// A resource needs re-rendering when its content hash differs from the one in
+/// `prev_graph` (including resources that are new in `current_graph`), or when
+/// it transitively depends on such a resource. The directly changed resources
+/// are found first and then every ancestor is collected by walking the
+/// dependency edges of `current_graph` in reverse.
+///
+/// The returned hashes are sorted.
+#[instrument(skip_all)]
+pub fn compute_changed_resources(
+    prev_graph: &DependencyGraph,
+    current_graph: &DependencyGraph,
+) -> Vec<ResourceHash> {
+    let directly_changed: Vec<ResourceHash> = current_graph
+        .nodes
+        .iter()
+        .filter(|(hash, node)| match prev_graph.nodes.get(hash) {
+            Some(prev) => prev.content_hash.is_none() || prev.content_hash != node.content_hash,
+            None => true,
+        })
+        .map(|(hash, _)| *hash)
+        .collect();
+
+    let changed = with_dependents(current_graph, directly_changed.iter().copied());
+
+    debug!(
+        "{} of {} resources changed ({} directly)",
+        changed.len(),
+        current_graph.nodes.len(),
+        directly_changed.len()
+    );
+
+    let mut changed: Vec<ResourceHash> = changed.into_iter().collect();
+    changed.sort();
+    changed
+}
+
+/// The given resources plus every resource that transitively depends on them
+pub(crate) fn with_dependents(
+    graph: &DependencyGraph,
+    resources: impl IntoIterator<Item = ResourceHash>,
+) -> HashSet<ResourceHash> {
+    // dependency -> resources that depend on it
+    let mut dependents: HashMap<ResourceHash, Vec<ResourceHash>> = HashMap::new();
+    for &(from, to) in &graph.edges {
+        dependents.entry(to).or_default().push(from);
+    }
+
+    let mut result: HashSet<ResourceHash> = HashSet::new();
+    let mut queue: VecDeque<ResourceHash> = VecDeque::new();
+    for hash in resources {
+        if result.insert(hash) {
+            queue.push_back(hash);
+        }
+    }
+    while let Some(hash) = queue.pop_front() {
+        for &dependent in dependents.get(&hash).into_iter().flatten() {
+            if result.insert(dependent) {
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    result
+}
+
+/// The part of a graph made up of the given nodes and the edges between them
+pub(crate) fn subgraph(graph: &DependencyGraph, keep: &HashSet<ResourceHash>) -> DependencyGraph {
+    let mut result = DependencyGraph::new(graph.root.clone());
+    result.project_root = graph.project_root.clone();
+
+    for (hash, node) in &graph.nodes {
+        if keep.contains(hash) {
+            result.add_node(*hash, node.clone());
+            if let Some(stat) = graph.file_stats.get(hash) {
+                result.file_stats.insert(*hash, *stat);
+            }
+        }
+    }
+    for &(from, to) in &graph.edges {
+        if keep.contains(&from) && keep.contains(&to) {
+            result.add_edge(from, to);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{GraphNode, Resource};
+    use std::path::PathBuf;
+
+    /// Build a graph from `(name, content_hash)` nodes and `(from, to)` edges
+    fn graph(nodes: &[(&str, &str)], edges: &[(&str, &str)]) -> DependencyGraph {
+        let hash = |name: &str| crate::graph::compute_resource_hash(&resource(name), None);
+        let mut graph = DependencyGraph::new(resource(nodes[0].0));
+
+        for (name, content) in nodes {
+            let dependencies = edges
+                .iter()
+                .filter(|(from, _)| from == name)
+                .map(|(_, to)| hash(to))
+                .collect();
+            graph.add_node(
+                hash(name),
+                GraphNode {
+                    resource: resource(name),
+                    content_hash: Some(content.to_string()),
+                    dependencies,
+                },
+            );
+        }
+        for (from, to) in edges {
+            graph.add_edge(hash(from), hash(to));
+        }
+
+        graph
+    }
+
+    fn resource(name: &str) -> Resource {
+        Resource::local(PathBuf::from(format!("/docs/{}.md", name)))
+    }
+
+    fn hashes(names: &[&str]) -> Vec<ResourceHash> {
+        let mut hashes: Vec<ResourceHash> = names
+            .iter()
+            .map(|name| crate::graph::compute_resource_hash(&resource(name), None))
+            .collect();
+        hashes.sort();
+        hashes
+    }
+
+    const EDGES: &[(&str, &str)] = &[("root", "a"), ("root", "b"), ("a", "shared"), ("b", "leaf")];
+
+    #[test]
+    fn test_unchanged_graph_has_no_changes() {
+        let nodes = [("root", "1"), ("a", "1"), ("b", "1"), ("shared", "1"), ("leaf", "1")];
+        let prev = graph(&nodes, EDGES);
+        let current = graph(&nodes, EDGES);
+
+        assert!(compute_changed_resources(&prev, &current).is_empty());
+    }
+
+    #[test]
+    fn test_change_propagates_to_ancestors_only() {
+        let prev = graph(
+            &[("root", "1"), ("a", "1"), ("b", "1"), ("shared", "1"), ("leaf", "1")],
+            EDGES,
+        );
+        let current = graph(
+            &[("root", "1"), ("a", "1"), ("b", "1"), ("shared", "2"), ("leaf", "1")],
+            EDGES,
+        );
+
+        assert_eq!(
+            compute_changed_resources(&prev, &current),
+            hashes(&["shared", "a", "root"])
+        );
+    }
+
+    #[test]
+    fn test_new_resource_counts_as_changed() {
+        let prev = graph(&[("root", "1"), ("a", "1")], &[("root", "a")]);
+        let current = graph(
+            &[("root", "2"), ("a", "1"), ("new", "1")],
+            &[("root", "a"), ("root", "new")],
+        );
+
+        assert_eq!(compute_changed_resources(&prev, &current), hashes(&["root", "new"]));
+    }
+
+    #[test]
+    fn test_subgraph_keeps_internal_edges() {
+        let full = graph(
+            &[("root", "1"), ("a", "1"), ("b", "1"), ("shared", "1"), ("leaf", "1")],
+            EDGES,
+        );
+        let keep: HashSet<ResourceHash> = hashes(&["root", "a", "shared"]).into_iter().collect();
+
+        let sub = subgraph(&full, &keep);
+        assert_eq!(sub.nodes.len(), 3);
+        assert_eq!(sub.edges.len(), 2);
+    }
+}
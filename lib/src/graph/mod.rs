@@ -2,25 +2,32 @@ mod builder;
 mod cycles;
 mod workplan;
 mod cache;
+pub mod incremental;
 pub mod utils;
 pub mod gitignore;
+pub(crate) mod git;
 
-pub use builder::build_graph;
-pub use cycles::detect_cycles;
-pub use workplan::generate_workplan;
+pub use builder::{build_graph, build_graph_with_options};
+pub use cycles::{detect_cycles, CycleReport};
+pub use incremental::compute_changed_resources;
+pub use workplan::{apply_priority_patterns, generate_workplan, generate_workplan_for_roots};
 pub use cache::{persist_graph, load_graph};
-pub use utils::{compute_resource_hash, compute_content_hash, load_resource};
+pub(crate) use git::git_location;
+pub(crate) use cache::document_entry;
+pub use utils::{
+    compute_content_hash, compute_content_hash_reader, compute_file_hash, compute_resource_hash,
+    load_resource, load_resource_candidate, FileStat, RESOURCE_HASH_VERSION, STREAMING_HASH_THRESHOLD,
+};
 
+use crate::cache::CacheOperations;
 use crate::error::Result;
 use crate::types::{DependencyGraph, Resource, Frontmatter};
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
 
 /// Build a dependency graph for a resource
 pub async fn graph(
     resource: Resource,
-    db: &Surreal<Db>,
+    cache: &CacheOperations,
     frontmatter: &Frontmatter,
 ) -> Result<DependencyGraph> {
-    build_graph(resource, db, frontmatter).await
+    build_graph(resource, cache, frontmatter).await
 }
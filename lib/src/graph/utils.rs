@@ -1,18 +1,206 @@
-use crate::error::{ParseError, Result};
-use crate::types::{Resource, ResourceHash, ResourceSource};
-use std::path::{Path, PathBuf};
-use tracing::{debug, instrument};
-use xxhash_rust::xxh3::xxh3_64;
+use crate::error::{ParseError, ParseWarning, ParseWarningCode, Result};
+use crate::graph::git_location;
+use crate::types::{Resource, ResourceHash, ResourceRequirement, ResourceSource};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, instrument, warn};
+use url::Url;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+/// Size above which loaders stream a file (hashing it in chunks) instead of
+/// reading it into memory
+pub const STREAMING_HASH_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// Chunk size used when hashing from a reader
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Query parameters that never change what a URL points to
+///
+/// They are left out of resource hashes so that, for example, a link shared
+/// with `?utm_source=...` hits the same cache entry as the plain URL. Any
+/// parameter starting with `utm_` is ignored as well.
+pub const IGNORED_QUERY_PARAMS: &[&str] = &["fbclid", "gclid", "msclkid", "mc_cid", "mc_eid", "ref_src"];
+
+/// Version of the scheme [`compute_resource_hash`] identifies resources by
+///
+/// It is part of every hash, so a change to the scheme changes every hash at
+/// once. Bump it together with a cache schema migration that clears the
+/// entries keyed by the old hashes.
+pub const RESOURCE_HASH_VERSION: u32 = 2;
 
 /// Compute a hash for a resource (based on its source location)
+///
+/// Local files are hashed by their path relative to `project_root` (see
+/// [`local_hash_key`]), so moving a project or checking it out on another
+/// machine keeps its hashes, and `.` and `..` components are resolved first,
+/// so `./a.md`, `a.md` and `dir/../a.md` are the same resource. Remote URLs
+/// are hashed in their normalized form (see [`normalize_url`]); a resource's
+/// `section` doesn't affect its hash,
+/// since every section comes from the same document. A resource with
+/// fallbacks is hashed by all of its candidates, so it is a different
+/// resource from its first candidate alone.
 #[instrument(skip(resource))]
-pub fn compute_resource_hash(resource: &Resource) -> ResourceHash {
-    let source_str = match &resource.source {
-        ResourceSource::Local(path) => path.to_string_lossy().to_string(),
-        ResourceSource::Remote(url) => url.to_string(),
+pub fn compute_resource_hash(resource: &Resource, project_root: Option<&Path>) -> ResourceHash {
+    let mut key = format!("v{}:{}", RESOURCE_HASH_VERSION, source_key(&resource.source, project_root));
+    for fallback in resource.candidates().skip(1) {
+        key.push_str("||");
+        key.push_str(&source_key(&fallback.source, project_root));
+    }
+    ResourceHash(xxh3_64(key.as_bytes()))
+}
+
+/// The form of a source used to identify it in resource hashes
+fn source_key(source: &ResourceSource, project_root: Option<&Path>) -> String {
+    match source {
+        ResourceSource::Local(path) => local_hash_key(path, project_root),
+        ResourceSource::Remote(url) => normalize_url(url),
+        ResourceSource::Git { repo_url, ref_, path } => git_location(repo_url, ref_, path),
+        // There is no location to identify it by, so the content itself is hashed
+        ResourceSource::Memory { content, .. } => format!("memory:{:016x}", xxh3_64(content.as_bytes())),
+    }
+}
+
+/// The form of a local path used to identify it in resource hashes
+///
+/// A relative path is taken to be relative to `project_root`, and joined
+/// onto it, before being [normalized](normalize_path). A file inside
+/// `project_root` is then identified by its path relative to it, any other
+/// file (or any file at all without a root) by the normalized path.
+/// Separators are always `/`, and on platforms whose file systems are
+/// case-insensitive by default (Windows and macOS) the path is lower-cased.
+pub fn local_hash_key(path: &Path, project_root: Option<&Path>) -> String {
+    let path = &match project_root {
+        Some(root) if path.is_relative() => normalize_path(&root.join(path)),
+        _ => normalize_path(path),
     };
+    let relative = project_root.and_then(|root| strip_project_root(path, &normalize_path(root)));
+    let key = normalize_separators(&relative.as_deref().unwrap_or(path).to_string_lossy());
 
-    xxh3_64(source_str.as_bytes())
+    if cfg!(any(windows, target_os = "macos")) {
+        key.to_lowercase()
+    } else {
+        key
+    }
+}
+
+/// The form of a URL used to identify it in resource hashes
+///
+/// The fragment and [`IGNORED_QUERY_PARAMS`] are dropped and the remaining
+/// query parameters sorted, so parameter order doesn't matter either. A
+/// trailing `/` is dropped from any path but the root. Parsing has already
+/// lower-cased the scheme and host and removed a default port.
+pub fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+
+    let path = url.path();
+    if path.len() > 1 && path.ends_with('/') {
+        let trimmed = match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+        url.set_path(&trimmed);
+    }
+
+    if url.query().is_some() {
+        let mut params: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| !key.starts_with("utm_") && !IGNORED_QUERY_PARAMS.contains(&key.as_ref()))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        params.sort();
+
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(params);
+        }
+    }
+
+    url.to_string()
+}
+
+/// `path` with `.` components removed and each `..` resolved against the
+/// component before it, without touching the file system
+///
+/// `..` at the start of a relative path is kept, and `..` at the root is
+/// dropped. Symlinks aren't followed, so a `..` after a symlinked directory
+/// leads to that directory's lexical parent.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// A path written with either separator, using `/` throughout
+///
+/// Windows accepts `/` as well as `\`, so directives written with either
+/// resolve, and hash, the same on every platform.
+pub fn normalize_separators(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// A path as it should appear in a URL or `src` attribute
+///
+/// Browsers read `\` literally, so the separators are always `/`.
+pub fn to_url_path(path: &Path) -> String {
+    normalize_separators(&path.to_string_lossy())
+}
+
+/// `path` relative to the project `root`, or `None` when it lies outside it
+///
+/// On Windows, drive letters are compared case-insensitively and the `\\?\`
+/// prefix added by `canonicalize` is ignored, so `\\?\C:\docs\a.md` is
+/// inside `c:\docs`.
+pub fn strip_project_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return Some(relative.to_path_buf());
+    }
+    if !cfg!(windows) {
+        return None;
+    }
+
+    let path = PathBuf::from(normalize_drive(&path.to_string_lossy()));
+    let root = PathBuf::from(normalize_drive(&root.to_string_lossy()));
+    path.strip_prefix(root).ok().map(Path::to_path_buf)
+}
+
+/// A Windows path without a `\\?\` prefix and with an upper-case drive letter
+fn normalize_drive(path: &str) -> String {
+    let has_drive = |p: &str| {
+        let bytes = p.as_bytes();
+        bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+    };
+
+    let path = match path.strip_prefix(r"\\?\") {
+        Some(rest) if has_drive(rest) => rest,
+        _ => path,
+    };
+    if has_drive(path) {
+        format!("{}{}", path[..1].to_ascii_uppercase(), &path[1..])
+    } else {
+        path.to_string()
+    }
 }
 
 /// Compute a hash for content (based on the actual bytes)
@@ -21,7 +209,119 @@ pub fn compute_content_hash(content: &str) -> String {
     format!("{:016x}", xxh3_64(content.as_bytes()))
 }
 
+/// Hash of a value's JSON form, with object keys sorted first
+///
+/// Values holding maps, such as a frontmatter or the breakpoints of
+/// `::columns`, hash the same whatever order their keys were filled in.
+pub(crate) fn compute_json_hash(value: &impl Serialize) -> serde_json::Result<u64> {
+    fn sort_keys(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.sort_keys();
+                map.values_mut().for_each(sort_keys);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(sort_keys),
+            _ => {}
+        }
+    }
+
+    let mut value = serde_json::to_value(value)?;
+    sort_keys(&mut value);
+    Ok(xxh3_64(&serde_json::to_vec(&value)?))
+}
+
+/// Hash everything `reader` produces, reading it in fixed-size chunks
+///
+/// Gives the same result as hashing the bytes in one go, so it can be used
+/// interchangeably with [`compute_content_hash`] without the content ever being
+/// held in memory.
+pub fn compute_content_hash_reader(reader: impl Read) -> io::Result<String> {
+    Ok(format!("{:016x}", xxh3_reader(reader)?))
+}
+
+/// Raw xxh3 digest of everything `reader` produces
+///
+/// Shared with loaders that format their hashes differently (audio uses
+/// unpadded hex).
+pub(crate) fn xxh3_reader(mut reader: impl Read) -> io::Result<u64> {
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hasher.digest())
+}
+
+/// Hash a file's contents without reading the whole file into memory
+pub fn compute_file_hash(path: &Path) -> io::Result<String> {
+    compute_content_hash_reader(File::open(path)?)
+}
+
+/// Size and modification time of a local file
+///
+/// Taken before a file is read so that a matching stat on a later build means
+/// the cached content hash still describes the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStat {
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+impl FileStat {
+    /// Stat a local file, or `None` if it can't be read or has no mtime
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok()?.into(),
+        })
+    }
+
+    /// Stat a resource's file, or `None` for resources that aren't local files
+    pub fn of_resource(resource: &Resource) -> Option<Self> {
+        match &resource.source {
+            ResourceSource::Local(path) => Self::of(path),
+            ResourceSource::Remote(_) | ResourceSource::Git { .. } | ResourceSource::Memory { .. } => None,
+        }
+    }
+}
+
+/// Load the first candidate of a resource's fallback chain that can be loaded
+///
+/// Each candidate is loaded with `load` in turn, e.g. [`load_resource`].
+/// Returns the first candidate that loads along with what `load` returned.
+/// When every candidate fails, the error is the last candidate's.
+pub async fn load_resource_candidate<'r, T, E, F, Fut>(
+    resource: &'r Resource,
+    mut load: F,
+) -> std::result::Result<(&'r Resource, T), E>
+where
+    F: FnMut(&'r Resource) -> Fut,
+    Fut: Future<Output = std::result::Result<T, E>>,
+    E: Display,
+{
+    let mut candidates = resource.candidates().peekable();
+    while let Some(candidate) = candidates.next() {
+        match load(candidate).await {
+            Ok(loaded) => return Ok((candidate, loaded)),
+            Err(e) if candidates.peek().is_some() => {
+                debug!("Falling back from {}: {}", candidate.label(), e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("a resource is always its own first candidate")
+}
+
 /// Load resource content from disk or network
+///
+/// Only the resource itself is loaded, never its fallbacks (see
+/// [`load_resource_candidate`]).
 #[instrument(skip_all, fields(source = ?resource.source))]
 pub async fn load_resource(resource: &Resource) -> Result<String> {
     match &resource.source {
@@ -41,12 +341,14 @@ pub async fn load_resource(resource: &Resource) -> Result<String> {
                 }
             }
 
-            std::fs::read_to_string(path).map_err(|e| {
+            let bytes = std::fs::read(path).map_err(|e| {
                 crate::error::CompositionError::Parse(ParseError::ResourceNotFound {
                     path: path.to_string_lossy().to_string(),
                     error: e.to_string(),
                 })
-            })
+            })?;
+            let (content, _warning) = decode_text(path, bytes, resource.requirement)?;
+            Ok(content)
         }
         ResourceSource::Remote(url) => {
             debug!("Fetching remote URL: {}", url);
@@ -56,7 +358,46 @@ pub async fn load_resource(resource: &Resource) -> Result<String> {
                 format!("Remote resource loading not yet implemented: {}", url)
             )))
         }
+        ResourceSource::Git { repo_url, ref_, path } => {
+            debug!("Loading {} from git repository {}@{}", path, repo_url, ref_);
+            crate::graph::git::read_git_file(repo_url, ref_, path).await
+        }
+        ResourceSource::Memory { content, .. } => Ok(content.to_string()),
+    }
+}
+
+/// Decode the contents of a local text file
+///
+/// Valid UTF-8 is returned as is. Otherwise a required resource fails with
+/// [`ParseError::InvalidUtf8`], which names the file and the offset of the
+/// first invalid byte, while any other resource is decoded lossily (invalid
+/// sequences become U+FFFD) and returned with a
+/// [`LossyDecoding`](ParseWarningCode::LossyDecoding) warning saying so.
+pub(crate) fn decode_text(
+    path: &Path,
+    bytes: Vec<u8>,
+    requirement: ResourceRequirement,
+) -> std::result::Result<(String, Option<ParseWarning>), ParseError> {
+    let invalid = match String::from_utf8(bytes) {
+        Ok(content) => return Ok((content, None)),
+        Err(invalid) => invalid,
+    };
+
+    let error = ParseError::InvalidUtf8 {
+        path: path.display().to_string(),
+        offset: invalid.utf8_error().valid_up_to(),
+    };
+    if matches!(requirement, ResourceRequirement::Required) {
+        return Err(error);
     }
+
+    let warning = ParseWarning {
+        span: None,
+        message: format!("{}; invalid sequences were replaced", error),
+        code: ParseWarningCode::LossyDecoding,
+    };
+    warn!("{}", warning);
+    Ok((String::from_utf8_lossy(invalid.as_bytes()).into_owned(), Some(warning)))
 }
 
 /// Find the project root by walking up from a path looking for .git directory
@@ -95,6 +436,7 @@ fn find_project_root(path: &Path) -> Option<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use std::path::PathBuf;
 
     #[test]
@@ -103,14 +445,229 @@ mod tests {
         let resource2 = Resource::local(PathBuf::from("/path/to/file.md"));
         let resource3 = Resource::local(PathBuf::from("/path/to/other.md"));
 
-        let hash1 = compute_resource_hash(&resource1);
-        let hash2 = compute_resource_hash(&resource2);
-        let hash3 = compute_resource_hash(&resource3);
+        let hash1 = compute_resource_hash(&resource1, None);
+        let hash2 = compute_resource_hash(&resource2, None);
+        let hash3 = compute_resource_hash(&resource3, None);
 
         assert_eq!(hash1, hash2);
         assert_ne!(hash1, hash3);
     }
 
+    #[test]
+    fn test_compute_resource_hash_is_relative_to_project_root() {
+        let first = tempfile::TempDir::new().unwrap();
+        let second = tempfile::TempDir::new().unwrap();
+        let hash_in = |root: &Path| {
+            compute_resource_hash(&Resource::local(root.join("docs").join("guide.md")), Some(root))
+        };
+
+        assert_eq!(hash_in(first.path()), hash_in(second.path()));
+        assert_eq!(
+            local_hash_key(&first.path().join("docs").join("guide.md"), Some(first.path())),
+            "docs/guide.md"
+        );
+        assert_ne!(
+            hash_in(first.path()),
+            compute_resource_hash(&Resource::local(first.path().join("docs").join("other.md")), Some(first.path()))
+        );
+    }
+
+    #[test]
+    fn test_relative_paths_are_relative_to_the_project_root() {
+        let root = tempfile::TempDir::new().unwrap();
+        let nested = root.path().join("docs");
+        let absolute = compute_resource_hash(&Resource::local(root.path().join("guide.md")), Some(root.path()));
+
+        assert_ne!(std::env::current_dir().unwrap(), root.path());
+        assert_eq!(local_hash_key(Path::new("docs/../guide.md"), Some(root.path())), "guide.md");
+        assert_eq!(
+            local_hash_key(Path::new("../guide.md"), Some(&nested)),
+            local_hash_key(&root.path().join("guide.md"), Some(&nested))
+        );
+        assert_eq!(compute_resource_hash(&Resource::local(PathBuf::from("./guide.md")), Some(root.path())), absolute);
+    }
+
+    #[test]
+    fn test_nested_repositories_share_the_project_root() {
+        let root = tempfile::TempDir::new().unwrap();
+        for repo in ["first", "second"] {
+            std::fs::create_dir_all(root.path().join(repo).join(".git")).unwrap();
+        }
+        let hash = |repo: &str| {
+            compute_resource_hash(&Resource::local(root.path().join(repo).join("README.md")), Some(root.path()))
+        };
+
+        assert_ne!(hash("first"), hash("second"));
+        assert_eq!(local_hash_key(&root.path().join("first").join("README.md"), Some(root.path())), "first/README.md");
+    }
+
+    #[test]
+    fn test_compute_json_hash_ignores_key_order() {
+        let first: HashMap<String, u32> = (0..32).map(|i| (format!("key{}", i), i)).collect();
+        let second: HashMap<String, u32> = (0..32).rev().map(|i| (format!("key{}", i), i)).collect();
+
+        assert_eq!(compute_json_hash(&first).unwrap(), compute_json_hash(&second).unwrap());
+        assert_ne!(compute_json_hash(&first).unwrap(), compute_json_hash(&HashMap::<String, u32>::new()).unwrap());
+    }
+
+    #[test]
+    fn test_memory_hashes_are_versioned() {
+        let resource = Resource::memory("note", "# Note");
+
+        assert_eq!(
+            compute_resource_hash(&resource, None),
+            ResourceHash(xxh3_64(format!("v{}:memory:{:016x}", RESOURCE_HASH_VERSION, xxh3_64(b"# Note")).as_bytes()))
+        );
+    }
+
+    #[test]
+    fn test_compute_resource_hash_outside_the_project_root_uses_absolute_path() {
+        let root = tempfile::TempDir::new().unwrap();
+        let first = tempfile::TempDir::new().unwrap();
+        let second = tempfile::TempDir::new().unwrap();
+        let path = first.path().join("notes.md");
+
+        assert_eq!(local_hash_key(&path, Some(root.path())), normalize_separators(&path.to_string_lossy()));
+        assert_eq!(local_hash_key(&path, None), normalize_separators(&path.to_string_lossy()));
+        assert_ne!(
+            compute_resource_hash(&Resource::local(path), Some(root.path())),
+            compute_resource_hash(&Resource::local(second.path().join("notes.md")), Some(root.path()))
+        );
+    }
+
+    #[test]
+    fn test_compute_resource_hash_git() {
+        let main = Resource::git("https://github.com/org/docs.git", "main", "a.md");
+        let same = Resource::git("https://github.com/org/docs.git", "main", "a.md");
+        let other_ref = Resource::git("https://github.com/org/docs.git", "v1", "a.md");
+        let other_path = Resource::git("https://github.com/org/docs.git", "main", "b.md");
+
+        assert_eq!(compute_resource_hash(&main, None), compute_resource_hash(&same, None));
+        assert_ne!(compute_resource_hash(&main, None), compute_resource_hash(&other_ref, None));
+        assert_ne!(compute_resource_hash(&main, None), compute_resource_hash(&other_path, None));
+    }
+
+    #[test]
+    fn test_compute_resource_hash_memory() {
+        let doc = Resource::memory("a", "# Hello");
+        let renamed = Resource::memory("b", "# Hello");
+        let other = Resource::memory("a", "# Goodbye");
+
+        assert_eq!(compute_resource_hash(&doc, None), compute_resource_hash(&renamed, None));
+        assert_ne!(compute_resource_hash(&doc, None), compute_resource_hash(&other, None));
+    }
+
+    #[test]
+    fn test_compute_resource_hash_fallback_chain() {
+        let primary = Resource::local(PathBuf::from("/docs/spec.md"));
+        let chain = primary.clone().with_fallback(Resource::local(PathBuf::from("/vendor/spec.md")));
+        let reversed = Resource::local(PathBuf::from("/vendor/spec.md"))
+            .with_fallback(Resource::local(PathBuf::from("/docs/spec.md")));
+
+        assert_ne!(compute_resource_hash(&chain, None), compute_resource_hash(&primary, None));
+        assert_ne!(compute_resource_hash(&chain, None), compute_resource_hash(&reversed, None));
+    }
+
+    #[test]
+    fn test_normalize_separators() {
+        assert_eq!(normalize_separators(r".\docs\intro.md"), "./docs/intro.md");
+        assert_eq!(normalize_separators(r"C:\docs/mixed\a.md"), "C:/docs/mixed/a.md");
+        assert_eq!(normalize_separators("docs/intro.md"), "docs/intro.md");
+    }
+
+    #[test]
+    fn test_to_url_path() {
+        assert_eq!(to_url_path(Path::new(r"images\photo-640w.webp")), "images/photo-640w.webp");
+        assert_eq!(to_url_path(&Path::new("audio").join("a.mp3")), "audio/a.mp3");
+    }
+
+    #[test]
+    fn test_normalize_drive() {
+        assert_eq!(normalize_drive(r"c:\docs\a.md"), r"C:\docs\a.md");
+        assert_eq!(normalize_drive(r"\\?\c:\docs"), r"C:\docs");
+        assert_eq!(normalize_drive(r"\\?\UNC\server\share"), r"\\?\UNC\server\share");
+        assert_eq!(normalize_drive("/home/docs"), "/home/docs");
+    }
+
+    #[test]
+    fn test_strip_project_root() {
+        let root = Path::new("/project");
+        assert_eq!(
+            strip_project_root(Path::new("/project/docs/a.md"), root),
+            Some(PathBuf::from("docs/a.md"))
+        );
+        assert_eq!(strip_project_root(Path::new("/elsewhere/a.md"), root), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_strip_project_root_ignores_drive_letter_case() {
+        assert_eq!(
+            strip_project_root(Path::new(r"\\?\C:\Project\docs\a.md"), Path::new(r"c:\Project")),
+            Some(PathBuf::from(r"docs\a.md"))
+        );
+    }
+
+    #[test]
+    fn test_compute_resource_hash_normalizes_urls() {
+        let hash = |input: &str| compute_resource_hash(&crate::parse::parse_resource(input).unwrap(), None);
+        let plain = hash("https://example.com/doc.md");
+
+        // Fragments (sections) and tracking parameters don't change the hash
+        assert_eq!(hash("https://example.com/doc.md#setup"), plain);
+        assert_eq!(hash("https://example.com/doc.md?utm_source=news&utm_medium=email"), plain);
+        assert_eq!(hash("https://example.com/doc.md?fbclid=abc"), plain);
+        assert_eq!(hash("https://example.com/doc.md?&"), plain);
+        assert_eq!(hash("HTTPS://EXAMPLE.com:443/doc.md"), plain);
+        assert_eq!(hash("https://example.com/docs/"), hash("https://example.com/docs"));
+
+        // Other parameters do, but not their order
+        let versioned = hash("https://example.com/doc.md?v=2&lang=en");
+        assert_ne!(versioned, plain);
+        assert_eq!(hash("https://example.com/doc.md?lang=en&utm_campaign=x&v=2"), versioned);
+        assert_ne!(hash("https://example.com/doc.md?v=3&lang=en"), versioned);
+
+        // file:// URLs hash like the equivalent local path
+        assert_eq!(
+            hash("file:///abs/doc.md#intro"),
+            compute_resource_hash(&Resource::local(PathBuf::from("/abs/doc.md")), None)
+        );
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let url = |s: &str| Url::parse(s).unwrap();
+
+        assert_eq!(normalize_url(&url("https://example.com/a.md")), "https://example.com/a.md");
+        assert_eq!(normalize_url(&url("https://example.com/a.md?b=2&a=1#x")), "https://example.com/a.md?a=1&b=2");
+        assert_eq!(normalize_url(&url("https://example.com/a.md?utm_source=x")), "https://example.com/a.md");
+        assert_eq!(normalize_url(&url("https://example.com/docs/")), "https://example.com/docs");
+        assert_eq!(normalize_url(&url("https://example.com/docs//?b=1")), "https://example.com/docs?b=1");
+        assert_eq!(normalize_url(&url("https://example.com/")), "https://example.com/");
+        assert_eq!(normalize_url(&url("http://Example.com:80/a.md")), "http://example.com/a.md");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("./a.md")), PathBuf::from("a.md"));
+        assert_eq!(normalize_path(Path::new("dir/../a.md")), PathBuf::from("a.md"));
+        assert_eq!(normalize_path(Path::new("/docs/./guide/../a.md")), PathBuf::from("/docs/a.md"));
+        assert_eq!(normalize_path(Path::new("../shared/./a.md")), PathBuf::from("../shared/a.md"));
+        assert_eq!(normalize_path(Path::new("/../a.md")), PathBuf::from("/a.md"));
+        assert_eq!(normalize_path(Path::new("dir/..")), PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_compute_resource_hash_normalizes_paths() {
+        let hash = |path: &str| compute_resource_hash(&Resource::local(PathBuf::from(path)), None);
+
+        assert_eq!(hash("./a.md"), hash("a.md"));
+        assert_eq!(hash("./dir/../a.md"), hash("a.md"));
+        assert_eq!(hash("/docs/./guide/../a.md"), hash("/docs/a.md"));
+        assert_ne!(hash("../a.md"), hash("a.md"));
+        assert_eq!(Resource::local(PathBuf::from("./a.md")).source, Resource::local(PathBuf::from("a.md")).source);
+    }
+
     #[test]
     fn test_compute_content_hash() {
         let content1 = "Hello, world!";
@@ -129,6 +686,32 @@ mod tests {
         assert_eq!(hash1.len(), 16);
     }
 
+    #[test]
+    fn test_streaming_hash_matches_one_shot() {
+        // Spans several chunks and ends mid-chunk
+        let content: String = (0..50_000).map(|i| format!("line {}\n", i)).collect();
+        assert!(content.len() > 3 * HASH_CHUNK_SIZE);
+
+        let streamed = compute_content_hash_reader(content.as_bytes()).unwrap();
+        assert_eq!(streamed, compute_content_hash(&content));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &content).unwrap();
+        assert_eq!(compute_file_hash(file.path()).unwrap(), streamed);
+        assert_eq!(compute_content_hash_reader(io::empty()).unwrap(), compute_content_hash(""));
+    }
+
+    #[test]
+    fn test_file_stat() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "12345").unwrap();
+
+        let stat = FileStat::of(file.path()).unwrap();
+        assert_eq!(stat.size, 5);
+        assert_eq!(FileStat::of(file.path()), Some(stat));
+        assert!(FileStat::of(Path::new("/nonexistent/file.md")).is_none());
+    }
+
     #[tokio::test]
     async fn test_load_resource_local_not_found() {
         let resource = Resource::local(PathBuf::from("/nonexistent/file.md"));
@@ -136,4 +719,24 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_decode_text_latin1() {
+        let path = Path::new("tests/fixtures/latin1.md");
+        let bytes = std::fs::read(path).unwrap();
+
+        let error = decode_text(path, bytes.clone(), ResourceRequirement::Required).unwrap_err();
+        assert!(matches!(error, ParseError::InvalidUtf8 { offset: 28, .. }), "{:?}", error);
+        assert!(error.to_string().contains("tests/fixtures/latin1.md"));
+
+        let (content, warning) = decode_text(path, bytes, ResourceRequirement::Optional).unwrap();
+        assert!(content.contains("# Caf\u{fffd}"));
+        let warning = warning.unwrap();
+        assert_eq!(warning.code, ParseWarningCode::LossyDecoding);
+        assert!(warning.message.contains("offset 28"));
+
+        let (content, warning) = decode_text(path, b"caf\xc3\xa9".to_vec(), ResourceRequirement::Required).unwrap();
+        assert_eq!(content, "café");
+        assert_eq!(warning, None);
+    }
 }
@@ -1,9 +1,12 @@
-use crate::error::{ParseError, Result};
-use crate::types::{DependencyGraph, ResourceHash, WorkLayer, WorkPlan};
-use std::collections::{HashMap, VecDeque};
+use crate::error::{CompositionError, ParseError, Result};
+use crate::types::{DependencyGraph, Resource, ResourceHash, ResourceSource, WorkLayer, WorkPlan};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
 use tracing::{debug, instrument};
 
 use super::cycles::detect_cycles;
+use super::incremental::subgraph;
+use super::utils::{strip_project_root, to_url_path};
 
 /// Generate a work plan from a dependency graph using topological sort
 ///
@@ -11,13 +14,20 @@ use super::cycles::detect_cycles;
 /// Each layer contains resources with no remaining dependencies, allowing parallel
 /// execution within each layer while maintaining correct dependency order.
 ///
+/// Tasks within a layer are sorted by resource hash, so the same graph always
+/// gives the same plan.
+///
 /// Returns an error if the graph contains cycles.
 #[instrument(skip(graph))]
 pub fn generate_workplan(graph: &DependencyGraph) -> Result<WorkPlan> {
     debug!("Generating work plan for graph with {} nodes", graph.nodes.len());
 
     // First, verify the graph is acyclic
-    detect_cycles(graph)?;
+    detect_cycles(graph).map_err(|report| {
+        CompositionError::Parse(ParseError::CircularDependency {
+            cycle: report.to_human_readable(graph),
+        })
+    })?;
 
     // Build in-degree map and adjacency list
     let mut in_degree: HashMap<ResourceHash, usize> = HashMap::new();
@@ -36,6 +46,7 @@ pub fn generate_workplan(graph: &DependencyGraph) -> Result<WorkPlan> {
     }
 
     let mut plan = WorkPlan::new();
+    plan.project_root = graph.project_root.clone();
     let mut queue: VecDeque<ResourceHash> = VecDeque::new();
 
     // Start with all nodes that have in-degree 0 (leaves)
@@ -55,7 +66,7 @@ pub fn generate_workplan(graph: &DependencyGraph) -> Result<WorkPlan> {
             if let Some(hash) = queue.pop_front() {
                 // Get the resource for this node
                 if let Some(node) = graph.nodes.get(&hash) {
-                    layer_resources.push(node.resource.clone());
+                    layer_resources.push((hash, node.resource.clone()));
                 }
 
                 // Reduce in-degree for all neighbors
@@ -76,8 +87,10 @@ pub fn generate_workplan(graph: &DependencyGraph) -> Result<WorkPlan> {
         }
 
         if !layer_resources.is_empty() {
+            // The queue is seeded from a hash map, so its order varies between runs
+            layer_resources.sort_by_key(|(hash, _)| *hash);
             plan.add_layer(WorkLayer {
-                resources: layer_resources,
+                resources: layer_resources.into_iter().map(|(_, resource)| resource).collect(),
                 parallelizable: true,
             });
         }
@@ -99,6 +112,126 @@ pub fn generate_workplan(graph: &DependencyGraph) -> Result<WorkPlan> {
     Ok(plan)
 }
 
+/// Generate a work plan for `roots` and everything they depend on
+///
+/// Nodes of `graph` that no root reaches, such as the other documents of a
+/// merged or persisted graph, are left out. Within each layer the
+/// dependencies of the first root come first, then those of the second root
+/// and so on, so the first root's document is ready as early as possible.
+/// Roots that aren't in the graph are ignored.
+#[instrument(skip(graph, roots), fields(num_roots = roots.len()))]
+pub fn generate_workplan_for_roots(graph: &DependencyGraph, roots: &[ResourceHash]) -> Result<WorkPlan> {
+    let mut adjacency: HashMap<ResourceHash, Vec<ResourceHash>> = HashMap::new();
+    for &(from, to) in &graph.edges {
+        adjacency.entry(from).or_default().push(to);
+    }
+
+    // Breadth-first from each root in turn, numbering nodes as they're reached
+    let mut rank: HashMap<ResourceHash, usize> = HashMap::new();
+    for &root in roots.iter().filter(|root| graph.nodes.contains_key(root)) {
+        if rank.contains_key(&root) {
+            continue;
+        }
+        rank.insert(root, rank.len());
+        let mut queue = VecDeque::from([root]);
+        while let Some(hash) = queue.pop_front() {
+            for &dependency in adjacency.get(&hash).into_iter().flatten() {
+                if !rank.contains_key(&dependency) {
+                    rank.insert(dependency, rank.len());
+                    queue.push_back(dependency);
+                }
+            }
+        }
+    }
+
+    let reachable: HashSet<ResourceHash> = rank.keys().copied().collect();
+    let mut plan = generate_workplan(&subgraph(graph, &reachable))?;
+    for layer in &mut plan.layers {
+        layer
+            .resources
+            .sort_by_key(|resource| rank.get(&graph.resource_hash(resource)).copied().unwrap_or(usize::MAX));
+    }
+
+    debug!(
+        "Kept {} of {} nodes reachable from {} roots",
+        reachable.len(),
+        graph.nodes.len(),
+        roots.len()
+    );
+    Ok(plan)
+}
+
+/// Assign task priorities from glob patterns
+///
+/// A pattern without a `/` (such as `"index.md"`) is matched against the
+/// resource's file name; any other pattern is matched against its path
+/// relative to `project_root` (or the URL path for remote resources). When
+/// several patterns match a resource, the highest priority wins.
+#[instrument(skip_all, fields(num_patterns = patterns.len()))]
+pub fn apply_priority_patterns(
+    plan: &mut WorkPlan,
+    patterns: &[(String, u8)],
+    project_root: Option<&Path>,
+) -> Result<()> {
+    if patterns.is_empty() {
+        return Ok(());
+    }
+
+    let compiled = patterns
+        .iter()
+        .map(|(pattern, priority)| {
+            glob::Pattern::new(pattern)
+                .map(|compiled| (compiled, pattern.contains('/'), *priority))
+                .map_err(|e| {
+                    CompositionError::Parse(ParseError::InvalidResource(format!(
+                        "Invalid priority pattern '{}': {}",
+                        pattern, e
+                    )))
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let resources: Vec<Resource> = plan
+        .layers
+        .iter()
+        .flat_map(|layer| layer.resources.iter().cloned())
+        .collect();
+
+    for resource in &resources {
+        let Some(path) = priority_path(resource, project_root) else {
+            continue;
+        };
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+
+        let priority = compiled
+            .iter()
+            .filter(|(pattern, has_dir, _)| pattern.matches(if *has_dir { &path } else { file_name }))
+            .map(|(_, _, priority)| *priority)
+            .max();
+
+        if let Some(priority) = priority {
+            let hash = plan.resource_hash(resource);
+            plan.with_priority(&hash, priority);
+        }
+    }
+
+    debug!("Task priorities: {:?}", plan.total_tasks_by_priority());
+    Ok(())
+}
+
+/// The `/`-separated path that priority patterns are matched against
+fn priority_path(resource: &Resource, project_root: Option<&Path>) -> Option<String> {
+    match &resource.source {
+        ResourceSource::Local(path) => {
+            let relative = project_root.and_then(|root| strip_project_root(path, root));
+            Some(to_url_path(relative.as_deref().unwrap_or(path)))
+        }
+        ResourceSource::Remote(url) => Some(url.path().trim_start_matches('/').to_string()),
+        ResourceSource::Git { path, .. } => Some(path.clone()),
+        ResourceSource::Memory { name, .. } => Some(name.clone()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,7 +242,7 @@ mod tests {
     #[test]
     fn test_generate_workplan_single_node() {
         let a = Resource::local(PathBuf::from("a.md"));
-        let hash_a = compute_resource_hash(&a);
+        let hash_a = compute_resource_hash(&a, None);
 
         let mut graph = DependencyGraph::new(a.clone());
         graph.add_node(hash_a, GraphNode {
@@ -132,9 +265,9 @@ mod tests {
         let b = Resource::local(PathBuf::from("b.md"));
         let c = Resource::local(PathBuf::from("c.md"));
 
-        let hash_a = compute_resource_hash(&a);
-        let hash_b = compute_resource_hash(&b);
-        let hash_c = compute_resource_hash(&c);
+        let hash_a = compute_resource_hash(&a, None);
+        let hash_b = compute_resource_hash(&b, None);
+        let hash_c = compute_resource_hash(&c, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -179,9 +312,9 @@ mod tests {
         let b = Resource::local(PathBuf::from("b.md"));
         let c = Resource::local(PathBuf::from("c.md"));
 
-        let hash_a = compute_resource_hash(&a);
-        let hash_b = compute_resource_hash(&b);
-        let hash_c = compute_resource_hash(&c);
+        let hash_a = compute_resource_hash(&a, None);
+        let hash_b = compute_resource_hash(&b, None);
+        let hash_c = compute_resource_hash(&c, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -227,10 +360,10 @@ mod tests {
         let c = Resource::local(PathBuf::from("c.md"));
         let d = Resource::local(PathBuf::from("d.md"));
 
-        let hash_a = compute_resource_hash(&a);
-        let hash_b = compute_resource_hash(&b);
-        let hash_c = compute_resource_hash(&c);
-        let hash_d = compute_resource_hash(&d);
+        let hash_a = compute_resource_hash(&a, None);
+        let hash_b = compute_resource_hash(&b, None);
+        let hash_c = compute_resource_hash(&c, None);
+        let hash_d = compute_resource_hash(&d, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -285,8 +418,8 @@ mod tests {
         let a = Resource::local(PathBuf::from("a.md"));
         let b = Resource::local(PathBuf::from("b.md"));
 
-        let hash_a = compute_resource_hash(&a);
-        let hash_b = compute_resource_hash(&b);
+        let hash_a = compute_resource_hash(&a, None);
+        let hash_b = compute_resource_hash(&b, None);
 
         let mut graph = DependencyGraph::new(a.clone());
 
@@ -308,4 +441,123 @@ mod tests {
         let result = generate_workplan(&graph);
         assert!(result.is_err());
     }
+
+    /// Build a graph from node names and `(from, to)` edges
+    fn graph_of(names: &[&str], edges: &[(&str, &str)]) -> DependencyGraph {
+        let hash = |name: &str| compute_resource_hash(&Resource::local(PathBuf::from(name)), None);
+        let mut graph = DependencyGraph::new(Resource::local(PathBuf::from(names[0])));
+        for name in names {
+            graph.add_node(hash(name), GraphNode {
+                resource: Resource::local(PathBuf::from(name)),
+                content_hash: Some(name.to_string()),
+                dependencies: edges.iter().filter(|(from, _)| from == name).map(|(_, to)| hash(to)).collect(),
+            });
+        }
+        for (from, to) in edges {
+            graph.add_edge(hash(from), hash(to));
+        }
+        graph
+    }
+
+    fn layer_names(plan: &WorkPlan) -> Vec<Vec<String>> {
+        plan.layers
+            .iter()
+            .map(|layer| layer.resources.iter().map(|resource| resource.label()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_workplan_is_deterministic() {
+        let names: Vec<String> = (0..20).map(|i| format!("doc{i}.md")).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let edges: Vec<(&str, &str)> = names[1..].iter().map(|name| (names[0], *name)).collect();
+
+        let first = generate_workplan(&graph_of(&names, &edges)).unwrap();
+        let second = generate_workplan(&graph_of(&names, &edges)).unwrap();
+
+        assert_eq!(layer_names(&first), layer_names(&second));
+        let hashes: Vec<ResourceHash> = first.layers[0].resources.iter().map(|resource| first.resource_hash(resource)).collect();
+        assert!(hashes.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", hashes);
+    }
+
+    #[test]
+    fn test_generate_workplan_for_roots_drops_unreachable_nodes() {
+        // Two disjoint subgraphs: a -> b -> c and x -> y
+        let graph = graph_of(&["a.md", "b.md", "c.md", "x.md", "y.md"], &[("a.md", "b.md"), ("b.md", "c.md"), ("x.md", "y.md")]);
+        let root = compute_resource_hash(&Resource::local(PathBuf::from("x.md")), None);
+
+        let plan = generate_workplan_for_roots(&graph, &[root]).unwrap();
+
+        assert_eq!(plan.total_tasks, 2);
+        assert_eq!(layer_names(&plan), vec![vec!["y.md"], vec!["x.md"]]);
+        // The whole graph is still planned by `generate_workplan`
+        assert_eq!(generate_workplan(&graph).unwrap().total_tasks, 5);
+    }
+
+    #[test]
+    fn test_generate_workplan_for_roots_orders_first_root_first() {
+        // Both roots share `shared.md`; each has a dependency of its own
+        let graph = graph_of(
+            &["one.md", "two.md", "shared.md", "one-dep.md", "two-dep.md"],
+            &[("one.md", "shared.md"), ("one.md", "one-dep.md"), ("two.md", "shared.md"), ("two.md", "two-dep.md")],
+        );
+        let hash = |name: &str| compute_resource_hash(&Resource::local(PathBuf::from(name)), None);
+
+        let plan = generate_workplan_for_roots(&graph, &[hash("two.md"), hash("one.md")]).unwrap();
+        assert_eq!(
+            layer_names(&plan),
+            vec![vec!["shared.md", "two-dep.md", "one-dep.md"], vec!["two.md", "one.md"]]
+        );
+
+        let plan = generate_workplan_for_roots(&graph, &[hash("one.md"), hash("two.md")]).unwrap();
+        assert_eq!(
+            layer_names(&plan),
+            vec![vec!["shared.md", "one-dep.md", "two-dep.md"], vec!["one.md", "two.md"]]
+        );
+    }
+
+    fn layered_plan(names: &[&str]) -> WorkPlan {
+        let mut plan = WorkPlan::new();
+        plan.add_layer(WorkLayer {
+            resources: names
+                .iter()
+                .map(|name| Resource::local(PathBuf::from(name)))
+                .collect(),
+            parallelizable: true,
+        });
+        plan
+    }
+
+    #[test]
+    fn test_apply_priority_patterns() {
+        let mut plan = layered_plan(&[
+            "/site/index.md",
+            "/site/blog/index.md",
+            "/site/blog/post.md",
+            "/site/about.md",
+        ]);
+        let patterns = vec![
+            ("index.md".to_string(), 200),
+            ("blog/*.md".to_string(), 100),
+        ];
+
+        apply_priority_patterns(&mut plan, &patterns, Some(Path::new("/site"))).unwrap();
+
+        let priority = |path: &str| plan.priority(&plan.resource_hash(&Resource::local(PathBuf::from(path))));
+        assert_eq!(priority("/site/index.md"), 200);
+        assert_eq!(priority("/site/blog/index.md"), 200);
+        assert_eq!(priority("/site/blog/post.md"), 100);
+        assert_eq!(priority("/site/about.md"), 0);
+
+        let histogram: Vec<(u8, usize)> = plan.total_tasks_by_priority().into_iter().collect();
+        assert_eq!(histogram, vec![(0, 1), (100, 1), (200, 2)]);
+    }
+
+    #[test]
+    fn test_invalid_priority_pattern() {
+        let mut plan = layered_plan(&["index.md"]);
+        let patterns = vec![("[".to_string(), 1)];
+
+        assert!(apply_priority_patterns(&mut plan, &patterns, None).is_err());
+    }
 }
@@ -0,0 +1,229 @@
+//! Shared HTTP clients for downloading documents, images and audio
+
+use crate::error::{FetchFailure, RenderError};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use std::io::Read;
+use std::sync::LazyLock;
+
+/// Most redirects followed when downloading an asset
+const MAX_REDIRECTS: usize = 5;
+
+/// Largest remote response read when no other limit is configured, 10 MiB
+pub(crate) const DEFAULT_MAX_REMOTE_BYTES: u64 = 10 * 1024 * 1024;
+
+static BLOCKING_CLIENT: LazyLock<Result<Client, String>> = LazyLock::new(|| {
+    Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .build()
+        .map_err(|e| e.to_string())
+});
+
+static ASYNC_CLIENT: LazyLock<Result<reqwest::Client, String>> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .build()
+        .map_err(|e| e.to_string())
+});
+
+/// The blocking client shared by every image and audio download
+///
+/// Batches reuse its connection pool instead of building a client per item.
+/// Like any blocking client it must only be used off the async runtime, e.g.
+/// inside `spawn_blocking`.
+pub(crate) fn blocking_client() -> Result<&'static Client, String> {
+    BLOCKING_CLIENT.as_ref().map_err(Clone::clone)
+}
+
+/// What a remote fetch expects the server to send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RemoteContent {
+    /// Markdown, CSV and other text: any `text/*` type
+    Text,
+    /// Any `image/*` type
+    Image,
+}
+
+impl RemoteContent {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Text => "text/",
+            Self::Image => "image/",
+        }
+    }
+
+    /// Check a response's `Content-Type`; a response without one is accepted,
+    /// since there is nothing to go on
+    fn check(self, content_type: Option<&str>) -> Result<(), String> {
+        let Some(content_type) = content_type else {
+            return Ok(());
+        };
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        if mime.starts_with(self.prefix()) {
+            Ok(())
+        } else {
+            Err(format!("Unexpected content type {}, expected {}*", mime, self.prefix()))
+        }
+    }
+}
+
+/// Why a response over `max_bytes` was refused
+fn too_large(max_bytes: u64) -> String {
+    format!("Response larger than {} bytes", max_bytes)
+}
+
+/// Download a text resource, such as a markdown document or CSV file
+///
+/// Fails with [`RenderError::RemoteFetchError`] on an error status, a
+/// `Content-Type` that isn't `text/*`, or a body over `max_bytes`. An
+/// oversized body is refused from its `Content-Length` before the download
+/// starts, or otherwise as soon as it outgrows the limit, so it is never
+/// buffered in full. Invalid UTF-8 is replaced with U+FFFD.
+pub(crate) async fn fetch_text(url: &url::Url, max_bytes: u64) -> Result<String, RenderError> {
+    let failed =
+        |kind: FetchFailure, reason: String| RenderError::RemoteFetchError { url: url.to_string(), kind, reason };
+    let client = ASYNC_CLIENT.as_ref().map_err(|e| failed(FetchFailure::Request, e.clone()))?;
+
+    let mut response =
+        client.get(url.clone()).send().await.map_err(|e| failed(FetchFailure::Request, e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(failed(FetchFailure::Status(status.as_u16()), format!("HTTP {}", status)));
+    }
+    RemoteContent::Text
+        .check(response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()))
+        .map_err(|reason| failed(FetchFailure::ContentType, reason))?;
+    if response.content_length().is_some_and(|size| size > max_bytes) {
+        return Err(failed(FetchFailure::TooLarge, too_large(max_bytes)));
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| failed(FetchFailure::Request, e.to_string()))? {
+        if bytes.len() as u64 + chunk.len() as u64 > max_bytes {
+            return Err(failed(FetchFailure::TooLarge, too_large(max_bytes)));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Download an image with the shared blocking client
+///
+/// Fails like [`fetch_text`], but expects an `image/*` type.
+pub(crate) fn fetch_image_bytes(url: &str, max_bytes: u64) -> Result<Vec<u8>, RenderError> {
+    let failed =
+        |kind: FetchFailure, reason: String| RenderError::RemoteFetchError { url: url.to_string(), kind, reason };
+    let client = blocking_client().map_err(|reason| failed(FetchFailure::Request, reason))?;
+
+    let response = client.get(url).send().map_err(|e| failed(FetchFailure::Request, e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(failed(FetchFailure::Status(status.as_u16()), format!("HTTP {}", status)));
+    }
+    RemoteContent::Image
+        .check(response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()))
+        .map_err(|reason| failed(FetchFailure::ContentType, reason))?;
+    let content_length = response.content_length();
+    if content_length.is_some_and(|size| size > max_bytes) {
+        return Err(failed(FetchFailure::TooLarge, too_large(max_bytes)));
+    }
+
+    // One byte past the limit is enough to know the body is too large
+    let limit = max_bytes.saturating_add(1);
+    let mut bytes = Vec::with_capacity(content_length.unwrap_or(0).min(limit) as usize);
+    response
+        .take(limit)
+        .read_to_end(&mut bytes)
+        .map_err(|e| failed(FetchFailure::Request, e.to_string()))?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(failed(FetchFailure::TooLarge, too_large(max_bytes)));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::metadata::tests::{http_response, serve_http};
+
+    #[test]
+    fn test_remote_content_check() {
+        assert!(RemoteContent::Text.check(Some("text/markdown; charset=utf-8")).is_ok());
+        assert!(RemoteContent::Text.check(Some("TEXT/PLAIN")).is_ok());
+        assert!(RemoteContent::Text.check(None).is_ok());
+        assert!(RemoteContent::Text.check(Some("application/octet-stream")).is_err());
+        assert!(RemoteContent::Image.check(Some("image/webp")).is_ok());
+        assert!(RemoteContent::Image.check(Some("text/html")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_text() {
+        let (base_url, _) = serve_http(vec![http_response("200 OK", &[("Content-Type", "text/markdown")], b"# Hi\n")]);
+
+        let url = url::Url::parse(&format!("{}/doc.md", base_url)).unwrap();
+        assert_eq!(fetch_text(&url, 1024).await.unwrap(), "# Hi\n");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_text_rejects_oversized_content_length() {
+        let (base_url, _) = serve_http(vec![http_response(
+            "200 OK",
+            &[("Content-Type", "text/markdown"), ("Content-Length", "5000")],
+            b"short",
+        )]);
+
+        let url = url::Url::parse(&format!("{}/big.md", base_url)).unwrap();
+        let err = fetch_text(&url, 1000).await.unwrap_err();
+        assert!(matches!(&err, RenderError::RemoteFetchError { kind: FetchFailure::TooLarge, reason, .. }
+            if reason == "Response larger than 1000 bytes"), "{}", err);
+        assert!(!err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_text_aborts_oversized_body_without_length() {
+        let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: text/plain\r\n\r\n".to_vec();
+        response.extend(std::iter::repeat_n(b'a', 64 * 1024));
+        let (base_url, _) = serve_http(vec![response]);
+
+        let url = url::Url::parse(&format!("{}/stream.md", base_url)).unwrap();
+        let err = fetch_text(&url, 1000).await.unwrap_err();
+        assert!(matches!(&err, RenderError::RemoteFetchError { kind: FetchFailure::TooLarge, .. }), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_text_rejects_binary() {
+        let (base_url, _) = serve_http(vec![http_response(
+            "200 OK",
+            &[("Content-Type", "application/octet-stream")],
+            b"\x00\x01\x02",
+        )]);
+
+        let url = url::Url::parse(&format!("{}/doc.md", base_url)).unwrap();
+        let err = fetch_text(&url, 1024).await.unwrap_err();
+        assert!(matches!(&err, RenderError::RemoteFetchError { kind: FetchFailure::ContentType, reason, .. }
+            if reason.contains("application/octet-stream")), "{}", err);
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn test_fetch_image_bytes() {
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let (base_url, _) = serve_http(vec![
+            http_response("200 OK", &[("Content-Type", "image/png")], &png),
+            http_response("200 OK", &[("Content-Type", "text/html")], b"<html></html>"),
+            http_response("200 OK", &[("Content-Type", "image/png")], &[0u8; 2048]),
+        ]);
+
+        assert_eq!(fetch_image_bytes(&format!("{}/a.png", base_url), 1024).unwrap(), png);
+        assert!(matches!(
+            fetch_image_bytes(&format!("{}/b.png", base_url), 1024),
+            Err(RenderError::RemoteFetchError { kind: FetchFailure::ContentType, reason, .. })
+                if reason.starts_with("Unexpected content type text/html")
+        ));
+        assert!(matches!(
+            fetch_image_bytes(&format!("{}/c.png", base_url), 1024),
+            Err(RenderError::RemoteFetchError { kind: FetchFailure::TooLarge, .. })
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! BlurHash placeholders
+//!
+//! [BlurHash](https://blurha.sh) describes an image as a grid of cosine
+//! components, a few dozen ASCII characters in all, from which a blurred
+//! stand-in is drawn while the real image loads. The first character holds
+//! the grid size, the second the scale of the AC components, the next four
+//! the average color and every two after that one AC component.
+
+use crate::error::{RenderError, Result};
+use image::{DynamicImage, RgbImage};
+use std::f64::consts::PI;
+
+/// Components (x, y) used by [`encode`]
+pub const DEFAULT_COMPONENTS: (u32, u32) = (4, 3);
+
+/// The 83 characters of BlurHash's base 83 digits
+const BASE83: &[u8; 83] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Encode `img` with the [`DEFAULT_COMPONENTS`]
+pub fn encode(img: &DynamicImage) -> String {
+    encode_with_components(img, DEFAULT_COMPONENTS)
+}
+
+/// Encode `img` as a grid of `x` by `y` components
+///
+/// Each count is clamped to the 1 to 9 BlurHash allows. Encoding visits
+/// every pixel once per component, so large images are best downscaled
+/// first; the hash barely changes.
+pub fn encode_with_components(img: &DynamicImage, (x, y): (u32, u32)) -> String {
+    let (x, y) = (x.clamp(1, 9), y.clamp(1, 9));
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+
+    // Average each pixel's linear color weighted by every basis function
+    let mut factors = Vec::with_capacity((x * y) as usize);
+    for j in 0..y {
+        for i in 0..x {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut factor = [0.0; 3];
+            for (px, py, pixel) in rgb.enumerate_pixels() {
+                let basis = (PI * i as f64 * px as f64 / width as f64).cos()
+                    * (PI * j as f64 * py as f64 / height as f64).cos();
+                for (channel, value) in factor.iter_mut().zip(pixel.0) {
+                    *channel += basis * srgb_to_linear(value);
+                }
+            }
+            let scale = normalisation / (width as f64 * height as f64).max(1.0);
+            factors.push(factor.map(|channel| channel * scale));
+        }
+    }
+
+    let (dc, ac) = factors.split_first().expect("at least one component");
+    let mut hash = String::with_capacity(4 + 2 * factors.len());
+    push_base83(&mut hash, (x - 1) + (y - 1) * 9, 1);
+
+    let maximum_value = if ac.is_empty() {
+        push_base83(&mut hash, 0, 1);
+        1.0
+    } else {
+        let actual_maximum = ac.iter().flatten().fold(0.0_f64, |max, value| max.max(value.abs()));
+        let quantised_maximum = (actual_maximum * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+        push_base83(&mut hash, quantised_maximum, 1);
+        (quantised_maximum + 1) as f64 / 166.0
+    };
+
+    let [r, g, b] = dc.map(linear_to_srgb);
+    push_base83(&mut hash, (r << 16) + (g << 8) + b, 4);
+
+    for component in ac {
+        let [r, g, b] = component.map(|value| {
+            (sign_pow(value / maximum_value, 0.5) * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+        });
+        push_base83(&mut hash, r * 19 * 19 + g * 19 + b, 2);
+    }
+
+    hash
+}
+
+/// Draw the `width` by `height` placeholder that `hash` describes
+///
+/// The result can be saved as a PNG for clients that can't decode BlurHash
+/// themselves. A malformed hash is a [`RenderError::ImageProcessing`].
+pub fn decode(hash: &str, width: u32, height: u32) -> Result<DynamicImage> {
+    let invalid = |reason: &str| RenderError::ImageProcessing(format!("Invalid BlurHash '{}': {}", hash, reason));
+    if !hash.is_ascii() {
+        return Err(invalid("it has a character outside base 83").into());
+    }
+    if hash.len() < 6 {
+        return Err(invalid("it is shorter than 6 characters").into());
+    }
+
+    let size_flag = decode_base83(&hash[..1]).ok_or_else(|| invalid("it has a character outside base 83"))?;
+    let (x, y) = (size_flag % 9 + 1, size_flag / 9 + 1);
+    if hash.len() != (4 + 2 * x * y) as usize {
+        return Err(invalid(&format!("{}x{} components need {} characters", x, y, 4 + 2 * x * y)).into());
+    }
+
+    let digits = |range: std::ops::Range<usize>| {
+        decode_base83(&hash[range]).ok_or_else(|| invalid("it has a character outside base 83"))
+    };
+    let maximum_value = (digits(1..2)? + 1) as f64 / 166.0;
+    let dc = digits(2..6)?;
+    let mut colors = vec![[dc >> 16, (dc >> 8) & 255, dc & 255].map(|value| srgb_to_linear(value as u8))];
+    for component in 1..(x * y) as usize {
+        let value = digits(4 + component * 2..6 + component * 2)?;
+        colors.push([value / (19 * 19), (value / 19) % 19, value % 19].map(|quantised| {
+            sign_pow((quantised as f64 - 9.0) / 9.0, 2.0) * maximum_value
+        }));
+    }
+
+    let image = RgbImage::from_fn(width, height, |px, py| {
+        let mut pixel = [0.0; 3];
+        for j in 0..y {
+            for i in 0..x {
+                let basis = (PI * px as f64 * i as f64 / width as f64).cos()
+                    * (PI * py as f64 * j as f64 / height as f64).cos();
+                let color = colors[(i + j * x) as usize];
+                for (channel, value) in pixel.iter_mut().zip(color) {
+                    *channel += value * basis;
+                }
+            }
+        }
+        image::Rgb(pixel.map(|channel| linear_to_srgb(channel) as u8))
+    });
+
+    Ok(DynamicImage::ImageRgb8(image))
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    if v <= 0.0031308 {
+        (v * 12.92 * 255.0 + 0.5) as u32
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u32
+    }
+}
+
+fn sign_pow(value: f64, exponent: f64) -> f64 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+/// Append `value` as `length` base 83 digits, most significant first
+fn push_base83(hash: &mut String, value: u32, length: u32) {
+    for position in (0..length).rev() {
+        let digit = (value / 83_u32.pow(position)) % 83;
+        hash.push(BASE83[digit as usize] as char);
+    }
+}
+
+fn decode_base83(digits: &str) -> Option<u32> {
+    digits.bytes().try_fold(0, |value, digit| {
+        let digit = BASE83.iter().position(|&c| c == digit)?;
+        Some(value * 83 + digit as u32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgb};
+
+    fn solid_image(color: [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb(color)))
+    }
+
+    #[test]
+    fn test_encode_solid_color() {
+        let hash = encode(&solid_image([255, 0, 0]));
+
+        // 4x3 components, then the average color, 0xff0000
+        assert_eq!(&hash[..1], "L");
+        assert_eq!(&hash[2..6], "TI:j");
+        assert_eq!(encode_with_components(&solid_image([255, 0, 0]), (1, 1)), "00TI:j");
+    }
+
+    #[test]
+    fn test_encode_length_follows_components() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(32, 16, |x, y| Rgb([(x * 8) as u8, (y * 16) as u8, 128])));
+
+        for (x, y) in [(1, 1), (4, 3), (9, 9)] {
+            let hash = encode_with_components(&img, (x, y));
+            assert_eq!(hash.len() as u32, 4 + 2 * x * y);
+            assert_eq!(decode_base83(&hash[..1]), Some((x - 1) + (y - 1) * 9));
+        }
+        assert_eq!(encode_with_components(&img, (0, 12)).len(), 4 + 2 * 9);
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let close = |pixel: image::Rgba<u8>, tolerance: u8| {
+            pixel.0.iter().zip([32, 160, 96]).all(|(channel, expected)| channel.abs_diff(expected) <= tolerance)
+        };
+
+        // The average color alone is exact
+        let flat = decode(&encode_with_components(&solid_image([32, 160, 96]), (1, 1)), 20, 10).unwrap();
+        assert_eq!(flat.dimensions(), (20, 10));
+        assert!(flat.pixels().all(|(_, _, pixel)| close(pixel, 1)));
+
+        // AC components are quantized, so the full hash is only close
+        let placeholder = decode(&encode(&solid_image([32, 160, 96])), 20, 10).unwrap();
+        assert!(close(placeholder.get_pixel(10, 5), 8), "{:?}", placeholder.get_pixel(10, 5));
+    }
+
+    #[test]
+    fn test_decode_gradient_keeps_its_direction() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 8, |x, _| Rgb([(x * 4) as u8; 3])));
+
+        let placeholder = decode(&encode(&img), 16, 4).unwrap().to_rgb8();
+        assert!(placeholder.get_pixel(0, 2).0[0] < placeholder.get_pixel(15, 2).0[0]);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_hashes() {
+        let valid = encode(&solid_image([0, 0, 255]));
+
+        for hash in ["", "L0TI:", &valid[..valid.len() - 2], &valid.replace('f', "\"")] {
+            let error = decode(hash, 4, 4).unwrap_err();
+            assert!(error.to_string().contains("Invalid BlurHash"), "{}", error);
+        }
+    }
+}
@@ -1,15 +1,31 @@
 use crate::cache::operations::{CacheOperations, ImageCacheEntry};
-use crate::error::Result;
-use crate::image::{ImageSource, ImageOptions, SmartImageOutput, load_image, process_image};
+use crate::error::{RenderError, Result};
+use crate::graph::{compute_file_hash, compute_resource_hash};
+use crate::image::{ImageFormat, ImageSource, ImageOptions, SmartImageOutput, load_image_with_limit, process_image};
+use crate::image::processing::{scaled_height, variant_file_name, variant_widths};
+use crate::image::phash::perceptual_hash;
 use crate::image::html::{generate_picture_html, HtmlOptions};
-use surrealdb::engine::local::Db;
-use surrealdb::Surreal;
+use crate::types::Resource;
+use image::DynamicImage;
+use std::path::Path;
 use std::time::Duration;
+use url::Url;
 use xxhash_rust::xxh3::xxh3_64;
 
-/// Compute a simple resource hash from a string (for image sources)
-fn compute_image_resource_hash(source: &str) -> String {
-    format!("{:016x}", xxh3_64(source.as_bytes()))
+/// Compute the resource hash of an image source
+///
+/// Files and URLs are hashed like documents (see [`compute_resource_hash`]),
+/// so an image's variant file names survive moving the project. Bytes are
+/// hashed by their name.
+fn compute_image_resource_hash(source: &ImageSource, project_root: Option<&Path>) -> String {
+    match source {
+        ImageSource::Local(path) => compute_resource_hash(&Resource::local(path.clone()), project_root).to_string(),
+        ImageSource::Remote(url) => match Url::parse(url) {
+            Ok(url) => compute_resource_hash(&Resource::remote(url), None).to_string(),
+            Err(_) => format!("{:016x}", xxh3_64(url.as_bytes())),
+        },
+        ImageSource::Bytes { name, .. } => format!("{:016x}", xxh3_64(name.as_bytes())),
+    }
 }
 
 /// Compute content hash from bytes
@@ -17,27 +33,86 @@ fn compute_image_content_hash(bytes: &[u8]) -> String {
     format!("{:016x}", xxh3_64(bytes))
 }
 
+/// Load and decode an image, along with the hash of its content
+fn load_and_hash(source: &ImageSource, max_remote_bytes: u64) -> Result<(DynamicImage, String)> {
+    let img = load_image_with_limit(source, max_remote_bytes)?;
+    // Local files are hashed in chunks; the decoded image is all that's kept
+    let content_hash = match source {
+        ImageSource::Local(path) => compute_file_hash(path)?,
+        ImageSource::Remote(_) => compute_image_content_hash(&[]), // For remote, we'd need to cache the bytes
+        ImageSource::Bytes { data, .. } => compute_image_content_hash(data),
+    };
+
+    Ok((img, content_hash))
+}
+
+/// The largest variant of an image in the format every browser reads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LargestVariant {
+    /// File name the variant is published under (see [`ImageVariant::file_name`](crate::image::ImageVariant::file_name))
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The largest JPEG variant, or PNG for images with transparency, of an
+/// image already processed by [`get_or_process_image`] with the default
+/// options
+///
+/// Returns `None` when the image isn't in the cache or is too small to have
+/// variants. Nothing is loaded or processed.
+pub(crate) async fn largest_cached_variant(
+    source: &ImageSource,
+    cache: &CacheOperations,
+    project_root: Option<&Path>,
+) -> Result<Option<LargestVariant>> {
+    let resource_hash = compute_image_resource_hash(source, project_root);
+    let Some(entry) = cache.get_image(&resource_hash).await? else {
+        return Ok(None);
+    };
+
+    let (Ok(orig_width), Ok(orig_height)) = (u32::try_from(entry.original_width), u32::try_from(entry.original_height))
+    else {
+        return Ok(None);
+    };
+    let Some(width) = variant_widths(orig_width, ImageOptions::default().max_width).pop() else {
+        return Ok(None);
+    };
+    let format = if entry.has_transparency { ImageFormat::Png } else { ImageFormat::Jpeg };
+
+    Ok(Some(LargestVariant {
+        file_name: variant_file_name(&resource_hash, width, format),
+        width,
+        height: scaled_height(orig_width, orig_height, width),
+    }))
+}
+
 /// Get or process an image with caching
+///
+/// A local image is identified relative to `project_root` (see
+/// [`compute_resource_hash`]), which names its variant files.
 pub async fn get_or_process_image(
     source: &ImageSource,
     options: ImageOptions,
     html_options: HtmlOptions,
-    db: &Surreal<Db>,
+    cache: &CacheOperations,
+    project_root: Option<&Path>,
 ) -> Result<SmartImageOutput> {
     // Compute resource hash
-    let resource_hash = compute_image_resource_hash(source.as_str());
-
-    // Load the image to get content hash
-    let img = load_image(source)?;
-    let img_bytes = match source {
-        ImageSource::Local(path) => std::fs::read(path).unwrap_or_default(),
-        ImageSource::Remote(_) => vec![], // For remote, we'd need to cache the bytes
+    let resource_hash = compute_image_resource_hash(source, project_root);
+
+    // Loading, decoding and resizing block, so they run on Tokio's blocking
+    // pool; the variants of each image are encoded on rayon's shared pool
+    let (img, content_hash) = {
+        let source = source.clone();
+        let max_remote_bytes = options.max_remote_bytes;
+        tokio::task::spawn_blocking(move || load_and_hash(&source, max_remote_bytes))
+            .await
+            .map_err(|e| RenderError::ImageProcessing(format!("Task join error: {}", e)))??
     };
-    let content_hash = compute_image_content_hash(&img_bytes);
 
     // Check cache using CacheOperations
-    let cache_ops = CacheOperations::new(db.clone());
-    let cached = cache_ops.get_image(&resource_hash).await?;
+    let cached = cache.get_image(&resource_hash).await?;
 
     if let Some(_cache_entry) = cached {
         // Cache hit - we would reconstruct the output from cache
@@ -46,16 +121,26 @@ pub async fn get_or_process_image(
     }
 
     // Cache miss or forced reprocess - process the image
-    let (variants, has_transparency, blur_placeholder) = process_image(img.clone(), options)?;
+    let (width, height) = (img.width(), img.height());
+    let ((mut variants, has_transparency, blur_placeholder), perceptual_hash) = tokio::task::spawn_blocking(move || {
+        let perceptual_hash = perceptual_hash(&img);
+        process_image(img, options).map(|processed| (processed, perceptual_hash))
+    })
+    .await
+    .map_err(|e| RenderError::ImageProcessing(format!("Task join error: {}", e)))??;
+    for variant in &mut variants {
+        variant.path = Some(variant.file_name(&resource_hash));
+    }
 
     // Generate HTML
+    let html_options = HtmlOptions { blur_placeholder: Some(blur_placeholder.clone()), ..html_options };
     let html = generate_picture_html(&variants, html_options)?;
 
     // Create output
     let output = SmartImageOutput {
         resource_hash: resource_hash.clone(),
-        original_width: img.width(),
-        original_height: img.height(),
+        original_width: width,
+        original_height: height,
         has_transparency,
         variants: variants.clone(),
         blur_placeholder: blur_placeholder.clone(),
@@ -66,12 +151,13 @@ pub async fn get_or_process_image(
     let source_type = match source {
         ImageSource::Local(_) => "local".to_string(),
         ImageSource::Remote(_) => "remote".to_string(),
+        ImageSource::Bytes { .. } => "bytes".to_string(),
     };
 
     let expires_at = if matches!(source, ImageSource::Remote(_)) {
         Some(chrono::Utc::now() + Duration::from_secs(86400)) // 1 day for remote images
     } else {
-        None // No expiration for local images
+        None // No expiration for local or in-memory images
     };
 
     let cache_entry = ImageCacheEntry {
@@ -83,11 +169,12 @@ pub async fn get_or_process_image(
         source_type,
         source: source.as_str().to_string(),
         has_transparency,
-        original_width: img.width() as i64,
-        original_height: img.height() as i64,
+        original_width: width as i64,
+        original_height: height as i64,
+        perceptual_hash: Some(perceptual_hash),
     };
 
-    cache_ops.upsert_image(cache_entry).await?;
+    cache.upsert_image(cache_entry).await?;
 
     Ok(output)
 }
@@ -98,16 +185,16 @@ mod tests {
     use crate::cache::database::init_database;
     use tempfile::TempDir;
 
-    async fn setup_test_db() -> (Surreal<Db>, TempDir) {
+    async fn setup_test_db() -> (CacheOperations, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db_path = temp_dir.path().join("test.db");
         let db = init_database(&db_path).await.unwrap();
-        (db, temp_dir)
+        (CacheOperations::new(db), temp_dir)
     }
 
     #[tokio::test]
     async fn test_get_or_process_image_creates_cache_entry() {
-        let (db, _temp_dir) = setup_test_db().await;
+        let (cache, _temp_dir) = setup_test_db().await;
 
         // Create a test image larger than smallest breakpoint (640px)
         use image::{RgbaImage, Rgba, ImageFormat as ImgFormat};
@@ -127,7 +214,7 @@ mod tests {
         let options = ImageOptions::default();
         let html_options = HtmlOptions::default();
 
-        let result = get_or_process_image(&source, options, html_options, &db).await;
+        let result = get_or_process_image(&source, options, html_options, &cache, None).await;
         assert!(result.is_ok());
 
         let output = result.unwrap();
@@ -136,5 +223,8 @@ mod tests {
         assert!(!output.has_transparency);
         assert!(!output.variants.is_empty());
         assert!(!output.html.is_empty());
+
+        let entry = cache.get_image(&output.resource_hash).await.unwrap().unwrap();
+        assert!(entry.perceptual_hash.is_some());
     }
 }
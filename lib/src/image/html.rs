@@ -1,6 +1,10 @@
-use crate::error::Result;
-use crate::image::{ImageVariant, ImageFormat};
+use crate::error::{RenderError, Result};
+use crate::graph::utils::normalize_separators;
+use crate::image::{ImageVariant, ImageFormat, RETINA_MULTIPLIER};
+use crate::types::Breakpoint;
 use std::collections::HashMap;
+use url::Url;
+use xxhash_rust::xxh3::xxh3_64;
 
 /// Layout mode for responsive images
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,7 +29,22 @@ pub struct HtmlOptions {
     pub alt_text: Option<String>,
     pub loading: Loading,
     pub decoding: Decoding,
+    /// BlurHash given to the `<img>` as `data-blurhash`, for client-side
+    /// placeholders
     pub blur_placeholder: Option<String>,
+    /// Serve variants from a CDN instead of inlining them as data URIs
+    ///
+    /// Every variant must then have a [`path`](ImageVariant::path).
+    pub cdn_base_url: Option<Url>,
+    /// Path between the CDN base and the variant file name (e.g. `/images/`)
+    pub cdn_path_prefix: Option<String>,
+    /// Append `?v={hash}` to CDN URLs, where `{hash}` is the first 8 hex
+    /// digits of a hash of the variant's bytes
+    pub cache_bust: bool,
+    /// Breakpoints that each get their own `<source media="(max-width: Npx)">`
+    /// elements, offering only the variants up to twice (retina) their width;
+    /// without any, every source applies at all widths
+    pub breakpoints: Vec<Breakpoint>,
 }
 
 impl Default for HtmlOptions {
@@ -36,6 +55,10 @@ impl Default for HtmlOptions {
             loading: Loading::Lazy,
             decoding: Decoding::Async,
             blur_placeholder: None,
+            cdn_base_url: None,
+            cdn_path_prefix: None,
+            cache_bust: false,
+            breakpoints: Vec::new(),
         }
     }
 }
@@ -113,21 +136,53 @@ fn group_by_format(variants: &[ImageVariant]) -> HashMap<ImageFormat, Vec<&Image
     grouped
 }
 
+/// URL of a single variant: a CDN URL when one is configured, otherwise an
+/// inline data URI
+fn variant_url(variant: &ImageVariant, options: &HtmlOptions) -> Result<String> {
+    let Some(base) = &options.cdn_base_url else {
+        return Ok(format!(
+            "data:{};base64,{}",
+            variant.format.mime_type(),
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &variant.data)
+        ));
+    };
+
+    let path = variant.path.as_deref().ok_or_else(|| {
+        RenderError::ImageProcessing(format!(
+            "{}w {} variant has no file name, which CDN URLs require",
+            variant.width,
+            variant.format.extension()
+        ))
+    })?;
+
+    let mut url = base.as_str().trim_end_matches('/').to_string();
+    if let Some(prefix) = options.cdn_path_prefix.as_deref().map(normalize_separators) {
+        let prefix = prefix.trim_matches('/');
+        if !prefix.is_empty() {
+            url.push('/');
+            url.push_str(prefix);
+        }
+    }
+    url.push('/');
+    url.push_str(&normalize_separators(path));
+
+    if options.cache_bust {
+        let hash = format!("{:016x}", xxh3_64(&variant.data));
+        url.push_str("?v=");
+        url.push_str(&hash[..8]);
+    }
+
+    Ok(url)
+}
+
 /// Generate srcset attribute for a list of variants
-fn generate_srcset(variants: &[&ImageVariant]) -> String {
-    variants
+fn generate_srcset(variants: &[&ImageVariant], options: &HtmlOptions) -> Result<String> {
+    let entries = variants
         .iter()
-        .map(|v| {
-            // For now, use inline data URIs (in production, these would be file paths)
-            let data_uri = format!(
-                "data:{};base64,{}",
-                v.format.mime_type(),
-                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &v.data)
-            );
-            format!("{} {}w", data_uri, v.width)
-        })
-        .collect::<Vec<_>>()
-        .join(", ")
+        .map(|v| Ok(format!("{} {}w", variant_url(v, options)?, v.width)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(entries.join(", "))
 }
 
 /// Generate a <picture> element with srcset
@@ -155,10 +210,40 @@ pub fn generate_picture_html(variants: &[ImageVariant], options: HtmlOptions) ->
         ImageFormat::Png,
     ];
 
+    let mut breakpoints: Vec<&Breakpoint> = options.breakpoints.iter().collect();
+    breakpoints.sort_by_key(|bp| bp.pixels());
+
+    for breakpoint in breakpoints {
+        let max_width = breakpoint.pixels().saturating_mul(RETINA_MULTIPLIER);
+        for format in &format_order {
+            let Some(format_variants) = grouped.get(format) else {
+                continue;
+            };
+            // Variants are sorted by width, so the smallest is always offered
+            let fitting: Vec<&ImageVariant> = format_variants
+                .iter()
+                .enumerate()
+                .filter(|(i, v)| *i == 0 || v.width <= max_width)
+                .map(|(_, v)| *v)
+                .collect();
+            if fitting.is_empty() {
+                continue;
+            }
+            let srcset = generate_srcset(&fitting, &options)?;
+            html.push_str(&format!(
+                r#"<source type="{}" media="{}" srcset="{}" sizes="{}">"#,
+                format.mime_type(),
+                breakpoint.media_condition(),
+                srcset,
+                sizes
+            ));
+        }
+    }
+
     for format in &format_order {
         if let Some(format_variants) = grouped.get(format) {
             if !format_variants.is_empty() {
-                let srcset = generate_srcset(format_variants);
+                let srcset = generate_srcset(format_variants, &options)?;
                 html.push_str(&format!(
                     r#"<source type="{}" srcset="{}" sizes="{}">"#,
                     format.mime_type(),
@@ -171,19 +256,21 @@ pub fn generate_picture_html(variants: &[ImageVariant], options: HtmlOptions) ->
 
     // Add fallback <img> tag
     let fallback = variants.first().unwrap();
-    let fallback_src = format!(
-        "data:{};base64,{}",
-        fallback.format.mime_type(),
-        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &fallback.data)
-    );
+    let fallback_src = variant_url(fallback, &options)?;
 
     let alt = options.alt_text.unwrap_or_else(|| String::from(""));
     let loading = options.loading.as_str();
     let decoding = options.decoding.as_str();
 
+    // BlurHash digits never need escaping in an attribute
+    let blurhash = options
+        .blur_placeholder
+        .map(|hash| format!(r#" data-blurhash="{}""#, hash))
+        .unwrap_or_default();
+
     html.push_str(&format!(
-        r#"<img src="{}" alt="{}" width="{}" height="{}" loading="{}" decoding="{}">"#,
-        fallback_src, alt, fallback.width, fallback.height, loading, decoding
+        r#"<img src="{}" alt="{}" width="{}" height="{}" loading="{}" decoding="{}"{}>"#,
+        fallback_src, alt, fallback.width, fallback.height, loading, decoding, blurhash
     ));
 
     html.push_str("</picture>");
@@ -202,6 +289,7 @@ mod tests {
             format,
             data: vec![0u8; 100], // Dummy data
             size_bytes: 100,
+            path: None,
         }
     }
 
@@ -265,6 +353,43 @@ mod tests {
         assert!(html.contains("<img"));
         assert!(html.contains(r#"alt="Test image""#));
         assert!(html.contains("loading=\"lazy\""));
+        assert!(!html.contains("data-blurhash"));
+    }
+
+    #[test]
+    fn test_generate_picture_html_with_blurhash() {
+        let variants = vec![create_test_variant(640, ImageFormat::Jpeg)];
+        let options = HtmlOptions {
+            blur_placeholder: Some("L6PZfSi_.AyE_3t7t7R**0o#DgR4".to_string()),
+            ..Default::default()
+        };
+
+        let html = generate_picture_html(&variants, options).unwrap();
+        assert!(html.contains(r#"decoding="async" data-blurhash="L6PZfSi_.AyE_3t7t7R**0o#DgR4">"#), "{}", html);
+    }
+
+    #[test]
+    fn test_generate_picture_html_with_custom_breakpoints() {
+        let variants = vec![
+            create_test_variant(320, ImageFormat::WebP),
+            create_test_variant(1280, ImageFormat::WebP),
+            create_test_variant(2048, ImageFormat::WebP),
+        ];
+        let options = HtmlOptions {
+            breakpoints: vec![Breakpoint::Custom("tablet".into(), 900), Breakpoint::Custom("mobile".into(), 480)],
+            ..Default::default()
+        };
+
+        let html = generate_picture_html(&variants, options).unwrap();
+        let mobile = html.find(r#"media="(max-width: 480px)""#).expect("mobile source");
+        let tablet = html.find(r#"media="(max-width: 900px)""#).expect("tablet source");
+        assert!(mobile < tablet, "{}", html);
+
+        let sources: Vec<&str> = html.split("<source").skip(1).collect();
+        assert_eq!(sources.len(), 3, "{}", html);
+        assert!(sources[0].contains(" 320w\"") && !sources[0].contains("1280w"), "{}", sources[0]);
+        assert!(sources[1].contains("1280w") && !sources[1].contains("2048w"), "{}", sources[1]);
+        assert!(!sources[2].contains("media=") && sources[2].contains("2048w"), "{}", sources[2]);
     }
 
     #[test]
@@ -276,6 +401,51 @@ mod tests {
         assert_eq!(result.unwrap(), "");
     }
 
+    fn cdn_options(base: &str, prefix: Option<&str>, cache_bust: bool) -> HtmlOptions {
+        HtmlOptions {
+            cdn_base_url: Some(Url::parse(base).unwrap()),
+            cdn_path_prefix: prefix.map(String::from),
+            cache_bust,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_variant_url_with_cdn() {
+        let mut variant = create_test_variant(640, ImageFormat::WebP);
+        variant.path = Some(variant.file_name("abc123"));
+
+        let url = variant_url(&variant, &cdn_options("https://cdn.example.com", None, false)).unwrap();
+        assert_eq!(url, "https://cdn.example.com/abc123-640w.webp");
+
+        let url = variant_url(&variant, &cdn_options("https://cdn.example.com/site/", Some("/images/"), false)).unwrap();
+        assert_eq!(url, "https://cdn.example.com/site/images/abc123-640w.webp");
+
+        let url = variant_url(&variant, &cdn_options("https://cdn.example.com", Some("images"), true)).unwrap();
+        let (url, version) = url.split_once("?v=").unwrap();
+        assert_eq!(url, "https://cdn.example.com/images/abc123-640w.webp");
+        assert_eq!(version.len(), 8);
+        assert!(version.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_variant_url_uses_forward_slashes() {
+        let mut variant = create_test_variant(640, ImageFormat::WebP);
+        variant.path = Some(r"photos\abc123-640w.webp".to_string());
+
+        let url = variant_url(&variant, &cdn_options("https://cdn.example.com", Some(r"\site\images\"), false)).unwrap();
+        assert_eq!(url, "https://cdn.example.com/site/images/photos/abc123-640w.webp");
+    }
+
+    #[test]
+    fn test_variant_url_with_cdn_requires_path() {
+        let variant = create_test_variant(640, ImageFormat::WebP);
+
+        assert!(variant_url(&variant, &cdn_options("https://cdn.example.com", None, false)).is_err());
+        // Without a CDN the variant is inlined and needs no file name
+        assert!(variant_url(&variant, &HtmlOptions::default()).unwrap().starts_with("data:image/webp;base64,"));
+    }
+
     #[test]
     fn test_loading_as_str() {
         assert_eq!(Loading::Eager.as_str(), "eager");
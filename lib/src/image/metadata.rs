@@ -1,3 +1,4 @@
+use super::phash::perceptual_hash;
 use crate::error::Result;
 use std::collections::HashMap;
 use std::fs::File;
@@ -23,6 +24,9 @@ pub struct ImageMetadata {
     pub gps_latitude: Option<f64>,
     pub gps_longitude: Option<f64>,
     pub custom: HashMap<String, String>,
+    /// Perceptual hash of the picture, for finding near-duplicates (see
+    /// [`phash`](super::phash)); `None` when the file can't be decoded
+    pub perceptual_hash: Option<u64>,
 }
 
 impl ImageMetadata {
@@ -39,12 +43,13 @@ impl ImageMetadata {
     }
 }
 
-/// Extract EXIF metadata from an image file
+/// Extract EXIF metadata from an image file, along with its perceptual hash
 pub fn extract_metadata(path: &Path) -> Result<ImageMetadata> {
     let file = File::open(path).ok();
     if file.is_none() {
         return Ok(ImageMetadata::default());
     }
+    let perceptual_hash = image::open(path).ok().map(|img| perceptual_hash(&img));
 
     let file = file.unwrap();
     let mut buf_reader = BufReader::new(file);
@@ -52,11 +57,11 @@ pub fn extract_metadata(path: &Path) -> Result<ImageMetadata> {
     let exif = exif_reader.read_from_container(&mut buf_reader).ok();
 
     if exif.is_none() {
-        return Ok(ImageMetadata::default());
+        return Ok(ImageMetadata { perceptual_hash, ..Default::default() });
     }
 
     let exif = exif.unwrap();
-    let mut metadata = ImageMetadata::default();
+    let mut metadata = ImageMetadata { perceptual_hash, ..Default::default() };
 
     // Extract common fields
     if let Some(field) = exif.get_field(exif::Tag::Make, exif::In::PRIMARY) {
@@ -158,4 +163,17 @@ mod tests {
         assert!(result.is_ok());
         // Should return default metadata for nonexistent files
     }
+
+    #[test]
+    fn test_extract_metadata_perceptual_hash() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("gradient.png");
+        image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, 0])).save(&path).unwrap();
+
+        let metadata = extract_metadata(&path).unwrap();
+
+        let expected = perceptual_hash(&image::open(&path).unwrap());
+        assert_eq!(metadata.perceptual_hash, Some(expected));
+        assert!(extract_metadata(Path::new("/nonexistent/image.jpg")).unwrap().perceptual_hash.is_none());
+    }
 }
@@ -3,12 +3,15 @@ mod processing;
 mod metadata;
 pub mod html;
 mod cache;
+pub mod blurhash;
+pub mod phash;
 
-pub use source::{load_image, ImageSource};
+pub use source::{load_image, load_image_with_limit, ImageSource};
 pub use processing::{process_image, ImageOptions, ImageVariant, ImageFormat, detect_transparency};
 pub use metadata::{extract_metadata, ImageMetadata};
 pub use html::{generate_picture_html, LayoutMode};
 pub use cache::get_or_process_image;
+pub(crate) use cache::largest_cached_variant;
 
 use crate::types::Breakpoint;
 
@@ -35,7 +38,8 @@ pub struct SmartImageOutput {
     pub original_height: u32,
     pub has_transparency: bool,
     pub variants: Vec<ImageVariant>,
-    pub blur_placeholder: String,  // base64 data URI
+    /// BlurHash of the image; see [`blurhash`]
+    pub blur_placeholder: String,
     pub html: String,
 }
 
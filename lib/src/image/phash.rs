@@ -0,0 +1,118 @@
+//! Perceptual hashes for finding near-duplicate images
+//!
+//! The hash describes an image's coarse structure rather than its bytes, so
+//! re-encoded, resized or lightly edited copies hash to nearly the same
+//! value. The image is shrunk to 32x32 and greyscaled, and a 2D DCT splits it
+//! into frequencies. The 8x8 lowest frequencies after the DC row and column
+//! each give one bit: set when the coefficient is above their median.
+//! Compare hashes with [`hamming_distance`]: 0 for the same picture, and up
+//! to about 10 for near-duplicates.
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::f64::consts::PI;
+use std::sync::LazyLock;
+
+/// Side of the square the image is shrunk to
+const SIZE: usize = 32;
+
+/// Side of the block of low frequencies that make up the hash
+const HASH_SIZE: usize = 8;
+
+/// `COSINES[u][x]`: the DCT-II basis of frequency `u` at sample `x`
+static COSINES: LazyLock<[[f64; SIZE]; SIZE]> = LazyLock::new(|| {
+    let mut cosines = [[0.0; SIZE]; SIZE];
+    for (u, row) in cosines.iter_mut().enumerate() {
+        for (x, cosine) in row.iter_mut().enumerate() {
+            *cosine = (PI * (2 * x + 1) as f64 * u as f64 / (2 * SIZE) as f64).cos();
+        }
+    }
+    cosines
+});
+
+/// The 64-bit perceptual hash of `img`
+///
+/// Bits run row by row through the 8x8 block of frequencies, the lowest
+/// first. Color doesn't count, only brightness.
+pub fn perceptual_hash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle).to_luma8();
+    let pixels: Vec<f64> = small.pixels().map(|pixel| pixel.0[0] as f64).collect();
+
+    // Rows first, then columns; only the low frequencies are needed
+    let mut rows = [[0.0; HASH_SIZE + 1]; SIZE];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..SIZE).map(|x| pixels[y * SIZE + x] * COSINES[u][x]).sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(HASH_SIZE * HASH_SIZE);
+    for v in 1..=HASH_SIZE {
+        for u in 1..=HASH_SIZE {
+            coefficients.push(rows.iter().zip(&COSINES[v]).map(|(row, cosine)| row[u] * cosine).sum::<f64>());
+        }
+    }
+
+    let mut sorted = coefficients.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0;
+
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, coefficient)| **coefficient > median)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// The number of bits that differ between two perceptual hashes
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// A picture with some structure: a diagonal gradient and a bright square
+    fn scene(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x as f64 / width as f64, y as f64 / height as f64);
+            if (0.55..0.85).contains(&fx) && (0.15..0.45).contains(&fy) {
+                Rgb([250, 240, 230])
+            } else {
+                let shade = ((fx + fy) * 100.0) as u8;
+                Rgb([shade, shade / 2, 255 - shade])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_same_picture_at_another_size_is_a_near_duplicate() {
+        let original = perceptual_hash(&scene(400, 300));
+
+        assert_eq!(perceptual_hash(&scene(400, 300)), original);
+        assert!(hamming_distance(perceptual_hash(&scene(200, 150)), original) <= 10);
+        assert!(hamming_distance(perceptual_hash(&scene(400, 300).brighten(20)), original) <= 10);
+    }
+
+    #[test]
+    fn test_different_pictures_are_far_apart() {
+        let flipped = perceptual_hash(&scene(400, 300).fliph());
+
+        assert!(hamming_distance(perceptual_hash(&scene(400, 300)), flipped) > 10);
+    }
+
+    #[test]
+    fn test_greyscale_copy_is_a_near_duplicate() {
+        let picture = scene(400, 300);
+
+        assert!(hamming_distance(perceptual_hash(&picture.grayscale()), perceptual_hash(&picture)) <= 2);
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{CompositionError, Result};
-use crate::image::{BREAKPOINTS, RETINA_MULTIPLIER};
+use crate::image::{blurhash, BREAKPOINTS, RETINA_MULTIPLIER};
 use image::{DynamicImage, ImageFormat as ImgFormat, GenericImageView};
 use rayon::prelude::*;
 use std::io::Cursor;
@@ -44,6 +44,23 @@ pub struct ImageVariant {
     pub format: ImageFormat,
     pub data: Vec<u8>,
     pub size_bytes: usize,
+    /// File name the variant is published under (`{resource_hash}-{width}w.{ext}`)
+    ///
+    /// Only set once the variant belongs to a known resource; HTML generation
+    /// needs it to link to the variant instead of inlining it.
+    pub path: Option<String>,
+}
+
+impl ImageVariant {
+    /// File name for this variant of the image identified by `resource_hash`
+    pub fn file_name(&self, resource_hash: &str) -> String {
+        variant_file_name(resource_hash, self.width, self.format)
+    }
+}
+
+/// File name a variant `width` pixels wide in `format` is published under
+pub(crate) fn variant_file_name(resource_hash: &str, width: u32, format: ImageFormat) -> String {
+    format!("{}-{}w.{}", resource_hash, width, format.extension())
 }
 
 /// Options for image processing
@@ -55,6 +72,10 @@ pub struct ImageOptions {
     pub max_width: Option<u32>,
     /// Quality for lossy formats (1-100, default: 85)
     pub quality: u8,
+    /// BlurHash components along x and y (1-9 each, default: (4, 3))
+    pub blurhash_components: (u32, u32),
+    /// Largest remote image downloaded, in bytes (default: 10 MiB)
+    pub max_remote_bytes: u64,
 }
 
 impl Default for ImageOptions {
@@ -63,6 +84,8 @@ impl Default for ImageOptions {
             strip_metadata: true,
             max_width: None,
             quality: 85,
+            blurhash_components: blurhash::DEFAULT_COMPONENTS,
+            max_remote_bytes: crate::http::DEFAULT_MAX_REMOTE_BYTES,
         }
     }
 }
@@ -88,6 +111,11 @@ pub fn detect_transparency(img: &DynamicImage) -> bool {
     }
 }
 
+/// Height of an `orig_width` by `orig_height` image resized to `target_width`
+pub(crate) fn scaled_height(orig_width: u32, orig_height: u32, target_width: u32) -> u32 {
+    (orig_height as f64 * target_width as f64 / orig_width as f64) as u32
+}
+
 /// Resize an image to a target width, maintaining aspect ratio
 fn resize_image(img: &DynamicImage, target_width: u32) -> DynamicImage {
     let (orig_width, orig_height) = img.dimensions();
@@ -97,7 +125,7 @@ fn resize_image(img: &DynamicImage, target_width: u32) -> DynamicImage {
         return img.clone();
     }
 
-    let target_height = (orig_height as f64 * target_width as f64 / orig_width as f64) as u32;
+    let target_height = scaled_height(orig_width, orig_height, target_width);
 
     img.resize_exact(
         target_width,
@@ -189,17 +217,34 @@ fn generate_format_variants(
                 format,
                 data,
                 size_bytes,
+                path: None,
             })
         })
         .collect()
 }
 
-/// Generate a blur placeholder (tiny image encoded as base64 data URI)
-pub fn generate_blur_placeholder(img: &DynamicImage, width: u32) -> Result<String> {
-    let tiny = resize_image(img, width);
-    let data = encode_image(&tiny, ImageFormat::Jpeg, 50)?;
-    let base64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &data);
-    Ok(format!("data:image/jpeg;base64,{}", base64))
+/// Generate a blur placeholder: the BlurHash of a copy of the image at most
+/// `size` pixels on a side
+pub fn generate_blur_placeholder(img: &DynamicImage, size: u32, components: (u32, u32)) -> String {
+    blurhash::encode_with_components(&img.thumbnail(size, size), components)
+}
+
+/// Widths of the variants generated for an image `orig_width` pixels wide,
+/// smallest first: each breakpoint at 1x and 2x (retina) that fits both the
+/// image and `max_width`
+pub(crate) fn variant_widths(orig_width: u32, max_width: Option<u32>) -> Vec<u32> {
+    let max_width = max_width.unwrap_or(orig_width).min(orig_width);
+
+    let mut widths: Vec<u32> = BREAKPOINTS
+        .iter()
+        .flat_map(|(_, base_width)| [*base_width, base_width * RETINA_MULTIPLIER])
+        .filter(|width| *width <= max_width)
+        .collect();
+
+    // Remove duplicates (e.g., xs and sm are both 640px at 1x)
+    widths.sort_unstable();
+    widths.dedup();
+    widths
 }
 
 /// Process an image and generate all variants
@@ -214,26 +259,7 @@ pub fn process_image(
     let has_transparency = detect_transparency(&img);
     debug!("Transparency detected: {}", has_transparency);
 
-    // Determine which breakpoints to use and generate both 1x and 2x variants
-    let max_width = options.max_width.unwrap_or(orig_width);
-
-    // Generate widths for both 1x and 2x (retina) variants
-    let mut widths: Vec<u32> = Vec::new();
-    for (_, base_width) in BREAKPOINTS.iter() {
-        // 1x variant
-        if *base_width <= max_width && *base_width <= orig_width {
-            widths.push(*base_width);
-        }
-        // 2x variant (retina)
-        let retina_width = base_width * RETINA_MULTIPLIER;
-        if retina_width <= max_width && retina_width <= orig_width {
-            widths.push(retina_width);
-        }
-    }
-
-    // Remove duplicates (e.g., xs and sm are both 640px at 1x)
-    widths.sort_unstable();
-    widths.dedup();
+    let widths = variant_widths(orig_width, options.max_width);
 
     debug!("Processing {} widths (including retina variants)", widths.len());
 
@@ -252,7 +278,7 @@ pub fn process_image(
     debug!("Generated {} total variants (all formats)", variants.len());
 
     // Generate blur placeholder
-    let blur_placeholder = generate_blur_placeholder(&img, 20)?;
+    let blur_placeholder = generate_blur_placeholder(&img, 64, options.blurhash_components);
 
     Ok((variants, has_transparency, blur_placeholder))
 }
@@ -327,10 +353,12 @@ mod tests {
 
     #[test]
     fn test_blur_placeholder() {
-        let img = create_test_image(100, 100, false);
-        let result = generate_blur_placeholder(&img, 20);
-        assert!(result.is_ok());
-        assert!(result.unwrap().starts_with("data:image/jpeg;base64,"));
+        let img = create_test_image(300, 100, false);
+
+        let placeholder = generate_blur_placeholder(&img, 64, (5, 2));
+        assert_eq!(placeholder.len(), 4 + 2 * 5 * 2);
+        let decoded = blurhash::decode(&placeholder, 3, 1).unwrap().to_rgb8();
+        assert!(decoded.pixels().all(|pixel| pixel.0[0] > 250 && pixel.0[1] < 5));
     }
 
     #[test]
@@ -342,7 +370,7 @@ mod tests {
 
         let (variants, has_transparency, blur) = result.unwrap();
         assert!(!has_transparency);
-        assert!(blur.starts_with("data:image/jpeg;base64,"));
+        assert_eq!(blur.len(), 4 + 2 * 4 * 3);
         assert!(!variants.is_empty());
 
         // Check that we don't upscale
@@ -1,14 +1,35 @@
 use crate::error::{CompositionError, Result};
+use crate::graph::utils::normalize_separators;
 use image::DynamicImage;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::str::FromStr;
 
-/// Source of an image (local file or remote URL)
-#[derive(Debug, Clone)]
+/// Source of an image (local file, remote URL or bytes already in memory)
+#[derive(Clone)]
 pub enum ImageSource {
     Local(PathBuf),
     Remote(String),
+    /// Encoded image bytes, such as an upload. `name` identifies the image
+    /// the way a path does: it determines the resource hash, and so the
+    /// variant file names.
+    Bytes { data: Vec<u8>, name: String },
+}
+
+impl fmt::Debug for ImageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageSource::Local(path) => f.debug_tuple("Local").field(path).finish(),
+            ImageSource::Remote(url) => f.debug_tuple("Remote").field(url).finish(),
+            // The bytes would swamp any log line the source appears in
+            ImageSource::Bytes { data, name } => f
+                .debug_struct("Bytes")
+                .field("data", &format_args!("<{} bytes>", data.len()))
+                .field("name", name)
+                .finish(),
+        }
+    }
 }
 
 impl FromStr for ImageSource {
@@ -18,7 +39,7 @@ impl FromStr for ImageSource {
         if s.starts_with("http://") || s.starts_with("https://") {
             Ok(ImageSource::Remote(s.to_string()))
         } else {
-            Ok(ImageSource::Local(PathBuf::from(s)))
+            Ok(ImageSource::Local(PathBuf::from(normalize_separators(s))))
         }
     }
 }
@@ -30,15 +51,31 @@ impl ImageSource {
         match self {
             ImageSource::Local(path) => path.to_str().unwrap_or(""),
             ImageSource::Remote(url) => url,
+            ImageSource::Bytes { name, .. } => name,
         }
     }
 }
 
-/// Load an image from a source (local or remote)
+/// Load an image from a source
+///
+/// A remote image is downloaded only up to 10 MiB; see
+/// [`load_image_with_limit`].
 pub fn load_image(source: &ImageSource) -> Result<DynamicImage> {
+    load_image_with_limit(source, crate::http::DEFAULT_MAX_REMOTE_BYTES)
+}
+
+/// Load an image from a source, refusing a remote response over
+/// `max_remote_bytes` or whose content type isn't `image/*` with
+/// `RenderError::RemoteFetchError`
+pub fn load_image_with_limit(source: &ImageSource, max_remote_bytes: u64) -> Result<DynamicImage> {
     match source {
         ImageSource::Local(path) => load_local_image(path),
-        ImageSource::Remote(url) => load_remote_image(url),
+        ImageSource::Remote(url) => load_remote_image(url, max_remote_bytes),
+        ImageSource::Bytes { data, name } => image::load_from_memory(data).map_err(|e| {
+            CompositionError::Render(crate::error::RenderError::ImageProcessing(
+                format!("Failed to decode image {}: {}", name, e)
+            ))
+        }),
     }
 }
 
@@ -61,27 +98,8 @@ fn load_local_image(path: &Path) -> Result<DynamicImage> {
 }
 
 /// Load a remote image from a URL
-fn load_remote_image(url: &str) -> Result<DynamicImage> {
-    let response = reqwest::blocking::get(url).map_err(|e| {
-        CompositionError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to fetch remote image: {}", e),
-        ))
-    })?;
-
-    if !response.status().is_success() {
-        return Err(CompositionError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("HTTP error fetching image: {}", response.status()),
-        )));
-    }
-
-    let bytes = response.bytes().map_err(|e| {
-        CompositionError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Failed to read remote image bytes: {}", e),
-        ))
-    })?;
+fn load_remote_image(url: &str, max_remote_bytes: u64) -> Result<DynamicImage> {
+    let bytes = crate::http::fetch_image_bytes(url, max_remote_bytes)?;
 
     image::load_from_memory(&bytes).map_err(|e| {
         CompositionError::Render(crate::error::RenderError::ImageProcessing(
@@ -113,5 +131,30 @@ mod tests {
 
         let source = ImageSource::Local(PathBuf::from("/test.jpg"));
         assert_eq!(source.as_str(), "/test.jpg");
+
+        let source = ImageSource::Bytes { data: vec![1, 2, 3], name: "upload.png".to_string() };
+        assert_eq!(source.as_str(), "upload.png");
+    }
+
+    #[test]
+    fn test_image_source_bytes_debug_omits_data() {
+        let source = ImageSource::Bytes { data: vec![0; 4096], name: "upload.png".to_string() };
+        assert_eq!(format!("{:?}", source), r#"Bytes { data: <4096 bytes>, name: "upload.png" }"#);
+    }
+
+    #[test]
+    fn test_load_image_from_bytes() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(4, 3)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+
+        let source = ImageSource::Bytes { data: png.into_inner(), name: "upload.png".to_string() };
+        let img = load_image(&source).unwrap();
+        assert_eq!((img.width(), img.height()), (4, 3));
+
+        let garbage = ImageSource::Bytes { data: b"not an image".to_vec(), name: "bad.png".to_string() };
+        let error = load_image(&garbage).unwrap_err();
+        assert!(error.to_string().contains("bad.png"));
     }
 }
@@ -1,9 +1,9 @@
 use crate::api::{CompositionApi, CompositionConfig};
-use crate::cache::{apply_schema, init_database, locate_database_path};
-use crate::error::Result;
+use crate::cache::{connect_backend, locate_database_path, migrate, CacheBackend};
+use crate::error::{CacheError, CompositionError, Result};
 use crate::types::Frontmatter;
 use std::path::Path;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 /// Initialize the Composition library
 ///
@@ -32,22 +32,119 @@ pub async fn init(
     dir: Option<&Path>,
     frontmatter: Option<Frontmatter>,
 ) -> Result<CompositionApi> {
-    info!("Initializing Composition library");
-
     // Locate database path based on project scope
     let db_path = locate_database_path(dir)?;
     info!("Using database at: {}", db_path.display());
 
+    init_with_backend(dir, frontmatter, CacheBackend::RocksDb(db_path)).await
+}
+
+/// Initialize the Composition library with an explicit cache backend
+///
+/// Behaves exactly like [`init()`] apart from persistence: `CacheBackend::Memory`
+/// keeps the cache for the lifetime of the process only, and
+/// `CacheBackend::Disabled` turns every cache lookup into a miss.
+///
+/// # Example
+///
+/// ```no_run
+/// use lib::{init_with_backend, CacheBackend};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let api = init_with_backend(None, None, CacheBackend::Memory).await?;
+///     Ok(())
+/// }
+/// ```
+#[instrument]
+pub async fn init_with_backend(
+    dir: Option<&Path>,
+    frontmatter: Option<Frontmatter>,
+    backend: CacheBackend,
+) -> Result<CompositionApi> {
+    init_with_config(dir, frontmatter, CompositionConfig::new(backend)).await
+}
+
+/// Initialize the Composition library with an in-memory cache
+///
+/// Shorthand for [`init_with_backend()`] with `CacheBackend::Memory`: nothing
+/// is written to disk and the cache is discarded when the API is dropped,
+/// which suits tests and one-off CLI runs.
+///
+/// # Example
+///
+/// ```no_run
+/// use lib::init_in_memory;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let api = init_in_memory(None).await?;
+///     Ok(())
+/// }
+/// ```
+#[instrument]
+pub async fn init_in_memory(frontmatter: Option<Frontmatter>) -> Result<CompositionApi> {
+    init_with_backend(None, frontmatter, CacheBackend::Memory).await
+}
+
+/// Initialize the Composition library with a full configuration
+///
+/// When `config.project_root` is `None` it is set to `dir` (or the current
+/// directory). The cache schema is migrated to the current version before the
+/// API handle is returned.
+///
+/// If the database is locked by another process, opening it is retried for up
+/// to `config.lock_wait`. When it still can't be opened this fails with
+/// `CacheError::DatabaseLocked`, unless `config.read_only_fallback` is set: the
+/// backend is then switched to `CacheBackend::Disabled` so graph and render
+/// operations still work, just without reading or writing the cache.
+///
+/// # Example
+///
+/// ```no_run
+/// use lib::{init_with_config, CacheBackend, CompositionConfig};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut config = CompositionConfig::new(CacheBackend::Memory);
+///     config.rebuild_on_schema_mismatch = true;
+///     let api = init_with_config(None, None, config).await?;
+///     Ok(())
+/// }
+/// ```
+#[instrument]
+pub async fn init_with_config(
+    dir: Option<&Path>,
+    frontmatter: Option<Frontmatter>,
+    mut config: CompositionConfig,
+) -> Result<CompositionApi> {
+    info!("Initializing Composition library");
+
     // Determine project root (git root or current directory)
-    let project_root = dir
-        .map(|p| p.to_path_buf())
-        .or_else(|| std::env::current_dir().ok());
+    if config.project_root.is_none() {
+        config.project_root = dir
+            .map(|p| p.to_path_buf())
+            .or_else(|| std::env::current_dir().ok());
+    }
 
     // Initialize database
-    let db = init_database(&db_path).await?;
+    let db = match connect_backend(&config.backend, config.lock_wait).await {
+        Err(CompositionError::Cache(CacheError::DatabaseLocked { path, holder_hint }))
+            if config.read_only_fallback =>
+        {
+            warn!(
+                "Database at {} is locked ({}); continuing with caching disabled",
+                path.display(),
+                holder_hint
+            );
+            config.backend = CacheBackend::Disabled;
+            connect_backend(&config.backend, None).await?
+        }
+        result => result?,
+    };
 
-    // Apply schema
-    apply_schema(&db).await?;
+    // Bring the schema up to date
+    migrate(&db, config.backend.path(), config.rebuild_on_schema_mismatch).await?;
 
     // Merge frontmatter: ENV → utility defaults → passed frontmatter
     let mut merged_frontmatter = load_utility_frontmatter();
@@ -57,16 +154,22 @@ pub async fn init(
         merged_frontmatter.merge(user_frontmatter);
     }
 
-    // Create configuration
-    let config = CompositionConfig {
-        db_path,
-        project_root,
-    };
-
     // Create API instance
     let api = CompositionApi::new(db, merged_frontmatter, config).await?;
 
     info!("Composition library initialized successfully");
+    match api.cache_stats().await {
+        Ok(stats) => info!(
+            documents = stats.document_count,
+            images = stats.image_count,
+            audio = stats.audio_count,
+            llm = stats.llm_count,
+            expired = stats.expired_count,
+            size_estimate_bytes = stats.total_size_estimate_bytes,
+            "Cache statistics"
+        ),
+        Err(e) => warn!("Failed to collect cache statistics: {}", e),
+    }
     Ok(api)
 }
 
@@ -115,6 +218,106 @@ mod tests {
         assert!(fm.consolidate_model.is_some());
     }
 
+    #[tokio::test]
+    async fn test_init_with_memory_backend() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let api = init_with_backend(Some(temp_dir.path()), None, CacheBackend::Memory)
+            .await
+            .unwrap();
+
+        assert!(api.cache().is_enabled());
+        assert!(api.config().backend.path().is_none());
+        assert!(!temp_dir.path().join(".composition.db").exists());
+    }
+
+    #[tokio::test]
+    async fn test_init_in_memory_builds_graph() {
+        use crate::types::Resource;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let included = temp_dir.path().join("included.md");
+        let main = temp_dir.path().join("main.md");
+        std::fs::write(&included, "Included text").unwrap();
+        std::fs::write(&main, "# Main\n\n::file ./included.md\n").unwrap();
+
+        let api = init_in_memory(None).await.unwrap();
+        assert!(api.cache().is_enabled());
+
+        let graph = api.graph(Resource::local(main.clone())).await.unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(
+            graph.edges,
+            vec![(
+                crate::graph::compute_resource_hash(&Resource::local(main), None),
+                crate::graph::compute_resource_hash(&Resource::local(included), None),
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_init_with_disabled_backend_always_misses() {
+        use crate::cache::DocumentCacheEntry;
+
+        let api = init_with_backend(None, None, CacheBackend::Disabled).await.unwrap();
+        assert!(!api.cache().is_enabled());
+
+        api.cache()
+            .upsert_document(DocumentCacheEntry {
+                id: None,
+                resource_hash: "abc".to_string(),
+                content_hash: "def".to_string(),
+                file_path: None,
+                url: None,
+                last_validated: chrono::Utc::now(),
+                file_size: None,
+                modified_at: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(api.cache().get_document("abc").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_backend_stores_nothing() {
+        use crate::ai::MockCompletionModel;
+        use crate::audio::{AudioInput, AudioProcessingConfig, AudioSource};
+        use crate::image::ImageSource;
+        use crate::types::Resource;
+        use std::sync::Arc;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main = temp_dir.path().join("main.md");
+        std::fs::write(temp_dir.path().join("included.md"), "Included text").unwrap();
+        std::fs::write(&main, "# Main\n\n::file ./included.md\n").unwrap();
+        let image = temp_dir.path().join("photo.png");
+        image::RgbaImage::new(800, 600).save(&image).unwrap();
+        let audio = std::path::PathBuf::from("../tests/fixtures/audio/test.wav").canonicalize().unwrap();
+
+        let mut config = CompositionConfig::new(CacheBackend::Disabled);
+        config.audio_output_dir = temp_dir.path().join("audio");
+        let api = init_with_config(None, None, config)
+            .await
+            .unwrap()
+            .with_completion_model(Arc::new(MockCompletionModel::new(vec!["Summary".to_string()])));
+
+        api.graph(Resource::local(main)).await.unwrap();
+        api.optimize_image(ImageSource::Local(image)).await.unwrap();
+        api.process_audio(
+            AudioInput { source: AudioSource::Local(audio), name: None },
+            AudioProcessingConfig::default(),
+        )
+        .await
+        .unwrap();
+        api.summarize_text("Some text").await.unwrap();
+
+        for table in ["document", "depends_on", "image_cache", "audio_cache", "llm_cache"] {
+            let rows: Vec<surrealdb::sql::Thing> =
+                api.db().query(format!("SELECT VALUE id FROM {}", table)).await.unwrap().take(0).unwrap();
+            assert!(rows.is_empty(), "{} has {} rows", table, rows.len());
+        }
+    }
+
     #[test]
     fn test_merge_env_frontmatter() {
         unsafe {
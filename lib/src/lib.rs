@@ -29,6 +29,8 @@
 //!         source: ResourceSource::Local(PathBuf::from("document.md")),
 //!         requirement: ResourceRequirement::Required,
 //!         cache_duration: None,
+//!         section: None,
+//!         fallback: None,
 //!     };
 //!
 //!     let graph = api.graph(resource).await?;
@@ -72,19 +74,28 @@ pub mod types;
 pub mod parse;
 pub mod image;
 pub mod audio;
+mod http;
 
 // Placeholder modules for future phases
 pub mod render;
 pub mod ai;
 
 // Re-exports for convenience
-pub use api::{CompositionApi, CompositionConfig, HtmlOutput, ImageSource, SmartImageOutput};
+pub use cache::{CacheBackend, CacheExportReport, CacheImportReport, CacheStats};
+pub use api::{BatchProgress, BatchProgressFn, CompositionApi, CompositionConfig, HtmlOutput, ImageSource, SearchResult, SmartImageOutput};
 pub use error::{
-    AIError, AudioError, CacheError, CompositionError, ParseError, RenderError, Result,
+    AIError, AudioError, CacheError, CompositionError, FetchFailure, ParseError, ParseWarning, ParseWarningCode, RenderError,
+    Result,
+};
+pub use init::{init, init_in_memory, init_with_backend, init_with_config};
+pub use parse::ParseOptions;
+pub use render::{
+    AssetKind, AssetRef, Compression, ExcludedFile, ExclusionReason, FallbackUsed, FeedFormat, FeedOptions, LanguageVariant, NodeTransformer, RenderMetrics, RenderPass,
+    RenderWarnings, RetryPolicy, TaskFailure,
 };
-pub use init::init;
 pub use types::{
-    Breakpoint, ChartData, DarkMatterNode, DataPoint, DependencyGraph, Document,
-    Frontmatter, GraphNode, LineRange, ListExpansion, MarkdownContent, Resource,
-    ResourceHash, ResourceRequirement, ResourceSource, TableSource, WorkLayer, WorkPlan,
+    AxisScale, Breakpoint, Captions, ChartData, ChartSeries, DarkMatterNode, DataPoint, DataSeries, DependencyGraph, Document, DocumentDiff,
+    Frontmatter, FrontmatterChange, FrontmatterSchema, FrontmatterType, GraphNode, Heading, LineRange, ListExpansion, ListItem, ListSource, MarkdownContent, PieChartOptions, Resource,
+    ResourceHash, ResourceRequirement, ResourceSource, TableSource, TerminalLine, TerminalShell,
+    WorkLayer, WorkPlan,
 };
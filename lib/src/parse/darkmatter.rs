@@ -1,14 +1,25 @@
-use crate::error::ParseError;
-use crate::types::{DarkMatterNode, LineRange, WidthSpec};
-use crate::parse::resource::{parse_resource, parse_resources};
-use regex::Regex;
+use crate::error::{ParseError, Span};
+use crate::types::{
+    Breakpoint, Captions, DarkMatterNode, LineRange, MarkdownContent, Resource, TerminalLine, TerminalShell, WidthSpec,
+};
+use crate::parse::resource::{parse_resource, split_arguments, Argument};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
 use std::sync::LazyLock;
 
 // Regex patterns for DarkMatter directives
 static FILE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"^::file\s+(.+?)(?:\s+(\d+)-(\d+)?)?$").unwrap()
+    Regex::new(r"^::file\s+(.+)$").unwrap()
 });
 
+/// A `::file` line range: `10-20`, `10-` (to the end of the file) or `-20`
+/// (from the first line)
+static LINE_RANGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(\d+)-(\d+)?|-(\d+))$").unwrap()
+});
+
+// The AI directives only match the directive name; their arguments are split
+// by `DirectiveArgs::parse`
 static SUMMARIZE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^::summarize\s+(.+)$").unwrap()
 });
@@ -18,7 +29,7 @@ static CONSOLIDATE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
 });
 
 static TOPIC_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r#"^::topic\s+"([^"]+)"\s+(.+?)(?:\s+--review)?$"#).unwrap()
+    Regex::new(r"^::topic\s+(.+)$").unwrap()
 });
 
 static TABLE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
@@ -29,14 +40,123 @@ static CHART_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^::(bar-chart|line-chart|pie-chart|area-chart|bubble-chart)\s+(.+)$").unwrap()
 });
 
+static LIST_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::list\s+(.+)$").unwrap()
+});
+
+static INCLUDE_ENV_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::include-env(?:\s+(.+))?$").unwrap()
+});
+
+static INCLUDE_ENV_FILTER: LazyLock<Regex> = LazyLock::new(|| {
+    // Handles: --filter APP_
+    //          --filter "APP_"
+    Regex::new(r#"--filter\s+(?:"([^"]*)"|(\S+))"#).unwrap()
+});
+
+static INCLUDE_ENV_SHOW_VALUES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"--show-values\s+(\S+)").unwrap()
+});
+
+static REQUIRE_ENV_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::require-env\s+(.+)$").unwrap()
+});
+
 static COLUMNS_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^::columns(?:\s+(.+))?$").unwrap()
 });
 
+static ASPECT_RATIO_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::aspect-ratio(?:\s+(.+))?$").unwrap()
+});
+
+/// A `width:height` ratio such as `16:9`
+static ASPECT_RATIO_VALUE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d+):(\d+)$").unwrap()
+});
+
+/// Closes the block opened by `::aspect-ratio`
+pub(crate) const END_ASPECT_RATIO: &str = "::endaspect-ratio";
+
+/// Opens a disclosure block with its summary
+pub(crate) const SUMMARY: &str = "::summary";
+
+/// Separates a disclosure's summary from its details
+pub(crate) const DETAILS: &str = "::details";
+
+/// Separates the sections of a `::columns` block
+pub(crate) const COLUMN_BREAK: &str = "::break";
+
+/// Opens a Mermaid diagram block, whose lines are kept as they are
+pub(crate) const MERMAID: &str = "::mermaid";
+
+/// Closes a disclosure, `::columns` or `::mermaid` block
+pub(crate) const END_BLOCK: &str = "::end";
+
+static TERMINAL_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::terminal(?:\s+(.+))?$").unwrap()
+});
+
+/// Closes the block opened by `::terminal`
+pub(crate) const END_TERMINAL: &str = "::endterminal";
+
+/// Shorthand flags for common aspect ratios
+const ASPECT_RATIO_ALIASES: [(&str, (u32, u32)); 4] = [
+    ("--widescreen", (16, 9)),
+    ("--standard", (4, 3)),
+    ("--square", (1, 1)),
+    ("--ultrawide", (21, 9)),
+];
+
+/// Largest allowed ratio between the longer and shorter side
+const MAX_ASPECT_RATIO: u32 = 100;
+
 static POPOVER_LINK: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"\[([^\]]+)\]\(popover:([^)]+)\)").unwrap()
 });
 
+static DIFF_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::diff\s+(.+)$").unwrap()
+});
+
+/// Unchanged lines shown around each change of a `::diff` without `--context`
+pub(crate) const DIFF_CONTEXT_LINES: usize = 3;
+
+static FOOTNOTE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^::footnote\b(.*)$").unwrap()
+});
+
+/// A `::file` path with a region: `./utils.rs#auth`, optionally followed by a
+/// `!` or `?` requirement
+static FILE_REGION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+)#([\w-]+)([!?]?)$").unwrap()
+});
+
+/// The arguments of `::footnote`: `[id] text`
+static FOOTNOTE_ARGS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[([\w-]+)\]\s+(\S.*)$").unwrap()
+});
+
+/// A footnote reference `^[id]` or an inline footnote `^[text]`
+pub(crate) static FOOTNOTE_MARK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\^\[([^\]\n]+)\]").unwrap()
+});
+
+static FOOTNOTE_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[\w-]+$").unwrap()
+});
+
+/// Opens and closes a display math block
+pub(crate) const MATH_DELIMITER: &str = "$$";
+
+/// Inline math `$x^2$`, or an escaped `\$` that stays a dollar sign
+///
+/// Like pulldown-cmark's, the formula can't start or end with whitespace, so
+/// prices such as `$5 and $10` aren't math.
+static INLINE_MATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\\\$|\$([^\s$\\](?:[^$\n]*[^\s$\\])?)\$").unwrap()
+});
+
 static INTERPOLATION: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"\{\{(\w+)\}\}").unwrap()
 });
@@ -47,11 +167,18 @@ static AUDIO_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
     //          ::audio ./file.mp3 "Name"
     //          ::audio ./file.mp3 "Name with spaces"
     //          ::audio "./path with spaces.mp3" "Name"
-    Regex::new(r#"^::audio\s+(?:"([^"]+)"|(\S+))(?:\s+"(.+)")?$"#).unwrap()
+    //          ::audio ./file.mp3 "Name" --captions ./file.vtt --srclang fr
+    Regex::new(
+        r#"^::audio\s+(?:"([^"]+)"|(\S+))(?:\s+"(.+?)")?(?:\s+--captions\s+(?:"([^"]+)"|(\S+))(?:\s+--srclang\s+(\S+))?)?$"#,
+    )
+    .unwrap()
 });
 
+/// `srclang` of `::audio` captions without `--srclang`
+const DEFAULT_CAPTIONS_LANGUAGE: &str = "en";
+
 static YOUTUBE_DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"^::youtube\s+([^\s]+)(?:\s+(\d+(?:\.\d+)?(?:px|rem|%)))?$").unwrap()
+    Regex::new(r"^::youtube\s+([^\s]+)(?:\s+(\d+(?:\.\d+)?(?:px|rem|%|vw)|auto))?(?:\s+cc=([A-Za-z]{2,3}(?:-[A-Za-z0-9]+)*))?$").unwrap()
 });
 
 // YouTube URL patterns for video ID extraction
@@ -124,149 +251,597 @@ fn extract_youtube_id(reference: &str) -> Result<String, ParseError> {
 /// - Pixels: `512px`
 /// - Rems: `32rem`, `32.5rem`
 /// - Percentage: `80%` (validated 0-100)
+/// - Viewport width: `80vw` (validated 0-100)
+/// - `auto`, sized by the container
 ///
 /// # Errors
 ///
 /// Returns `ParseError::InvalidDirective` if the width format is invalid
-/// or percentage is out of range.
+/// or a percentage or viewport width is out of range.
 fn parse_width_spec(width_str: &str) -> Result<WidthSpec, ParseError> {
-    if let Some(px_str) = width_str.strip_suffix("px") {
-        let px = px_str.parse::<u32>().map_err(|_| {
-            ParseError::InvalidDirective {
-                line: 0,
-                directive: format!(
-                    "Invalid pixel width '{}'. Width must be a positive integer",
-                    width_str
-                ),
-            }
-        })?;
+    width_str.parse()
+}
 
-        if px == 0 {
-            return Err(ParseError::InvalidDirective {
-                line: 0,
-                directive: "Width must be positive".to_string(),
-            });
+fn invalid_directive(line: &str, line_num: usize) -> ParseError {
+    ParseError::InvalidDirective {
+        line: line_num,
+        directive: line.to_string(),
+        span: None,
+    }
+}
+
+/// Arguments of a directive, split into positional values and `--` flags
+///
+/// Values may be wrapped in double quotes to include spaces. Flags can appear
+/// anywhere in the argument list; a quoted `"--flag"` is a value.
+struct DirectiveArgs {
+    values: Vec<String>,
+    flags: Vec<String>,
+}
+
+impl DirectiveArgs {
+    /// Split `input`, rejecting unterminated quotes and flags not in `known_flags`
+    fn parse(input: &str, known_flags: &[&str], line: &str, line_num: usize) -> Result<Self, ParseError> {
+        let arguments = split_arguments(input).ok_or_else(|| invalid_directive(line, line_num))?;
+
+        let mut values = Vec::new();
+        let mut flags = Vec::new();
+        for arg in arguments {
+            if !arg.quoted && arg.value.starts_with("--") {
+                if !known_flags.contains(&arg.value.as_str()) {
+                    return Err(ParseError::InvalidDirective {
+                        line: line_num,
+                        directive: format!("Unknown flag '{}' in: {}", arg.value, line),
+                        span: None,
+                    });
+                }
+                flags.push(arg.value);
+            } else {
+                values.push(arg.value);
+            }
         }
 
-        return Ok(WidthSpec::Pixels(px));
+        Ok(Self { values, flags })
     }
 
-    if let Some(rem_str) = width_str.strip_suffix("rem") {
-        let rem = rem_str.parse::<f32>().map_err(|_| {
-            ParseError::InvalidDirective {
-                line: 0,
-                directive: format!(
-                    "Invalid rem width '{}'. Width must be a positive number",
-                    width_str
-                ),
-            }
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    /// Parse every positional value as a resource
+    fn resources(&self, line: &str, line_num: usize) -> Result<Vec<Resource>, ParseError> {
+        self.values
+            .iter()
+            .map(|value| {
+                if value.is_empty() {
+                    Err(invalid_directive(line, line_num))
+                } else {
+                    parse_resource(value)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parse the opening line of an `::aspect-ratio` block
+///
+/// Returns the `(numerator, denominator)` of the ratio, or `None` when `line`
+/// isn't an `::aspect-ratio` directive. The ratio is given either as
+/// `width:height` or as one of the [`ASPECT_RATIO_ALIASES`] flags. Zero sides
+/// and ratios beyond 100:1 (either way round) are rejected.
+pub(crate) fn parse_aspect_ratio(line: &str, line_num: usize) -> Result<Option<(u32, u32)>, ParseError> {
+    let trimmed = line.trim();
+    let Some(caps) = ASPECT_RATIO_DIRECTIVE.captures(trimmed) else {
+        return Ok(None);
+    };
+
+    let known_flags: Vec<&str> = ASPECT_RATIO_ALIASES.iter().map(|(flag, _)| *flag).collect();
+    let args = DirectiveArgs::parse(
+        caps.get(1).map_or("", |m| m.as_str()),
+        &known_flags,
+        trimmed,
+        line_num,
+    )?;
+
+    let ratio = match (args.values.as_slice(), args.flags.as_slice()) {
+        ([value], []) => {
+            let caps = ASPECT_RATIO_VALUE
+                .captures(value)
+                .ok_or_else(|| invalid_directive(trimmed, line_num))?;
+            let side = |i: usize| caps[i].parse::<u32>().map_err(|_| invalid_directive(trimmed, line_num));
+            (side(1)?, side(2)?)
+        }
+        ([], [flag]) => ASPECT_RATIO_ALIASES
+            .iter()
+            .find(|(alias, _)| alias == flag)
+            .map(|(_, ratio)| *ratio)
+            .ok_or_else(|| invalid_directive(trimmed, line_num))?,
+        _ => return Err(invalid_directive(trimmed, line_num)),
+    };
+
+    let (numerator, denominator) = ratio;
+    let (longer, shorter) = (numerator.max(denominator), numerator.min(denominator));
+    if shorter == 0 || u64::from(longer) > u64::from(shorter) * u64::from(MAX_ASPECT_RATIO) {
+        return Err(ParseError::InvalidDirective {
+            line: line_num,
+            directive: format!("Invalid aspect ratio {}:{} in: {}", numerator, denominator, trimmed),
+            span: None,
+        });
+    }
+
+    Ok(Some(ratio))
+}
+
+/// Parse the opening line of a `::columns` block
+///
+/// Returns the column count for each breakpoint, or `None` when `line` isn't a
+/// `::columns` directive. Counts are given as comma separated `name: count`
+/// pairs, e.g. `::columns md: 2, xl: 3`; without any the layout's defaults
+/// apply.
+pub(crate) fn parse_columns(line: &str, line_num: usize) -> Result<Option<HashMap<Breakpoint, u32>>, ParseError> {
+    let trimmed = line.trim();
+    let Some(caps) = COLUMNS_DIRECTIVE.captures(trimmed) else {
+        return Ok(None);
+    };
+
+    let mut breakpoints = HashMap::new();
+    for entry in caps.get(1).map_or("", |m| m.as_str()).split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let Some((name, count)) = entry.split_once(':') else {
+            return Err(invalid_directive(trimmed, line_num));
+        };
+        let breakpoint = name.trim().parse::<Breakpoint>().map_err(|e| match e {
+            ParseError::InvalidDirective { directive, .. } => ParseError::InvalidDirective {
+                line: line_num,
+                directive,
+                span: None,
+            },
+            other => other,
         })?;
+        let count = count
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&count| count > 0)
+            .ok_or_else(|| invalid_directive(trimmed, line_num))?;
+        breakpoints.insert(breakpoint, count);
+    }
 
-        if rem <= 0.0 {
-            return Err(ParseError::InvalidDirective {
-                line: 0,
-                directive: "Width must be positive".to_string(),
-            });
+    Ok(Some(breakpoints))
+}
+
+/// Parse a `::footnote[id] text` line into the footnote's id and text
+///
+/// Returns `None` when `line` isn't a `::footnote` directive.
+pub(crate) fn parse_footnote(line: &str, line_num: usize) -> Result<Option<(String, String)>, ParseError> {
+    let trimmed = line.trim();
+    let Some(caps) = FOOTNOTE_DIRECTIVE.captures(trimmed) else {
+        return Ok(None);
+    };
+    let Some(args) = FOOTNOTE_ARGS.captures(caps[1].trim()) else {
+        return Err(invalid_directive(trimmed, line_num));
+    };
+
+    Ok(Some((args[1].to_string(), args[2].trim_end().to_string())))
+}
+
+/// Whether the text of a `^[...]` mark is a footnote id, making the mark a
+/// reference rather than an inline footnote
+pub(crate) fn is_footnote_id(text: &str) -> bool {
+    FOOTNOTE_ID.is_match(text)
+}
+
+/// Parse the opening line of a `::terminal` block
+///
+/// Returns the session's shell, `bash` unless `--shell` names another, or
+/// `None` when `line` isn't a `::terminal` directive.
+pub(crate) fn parse_terminal(line: &str, line_num: usize) -> Result<Option<TerminalShell>, ParseError> {
+    let trimmed = line.trim();
+    let Some(caps) = TERMINAL_DIRECTIVE.captures(trimmed) else {
+        return Ok(None);
+    };
+
+    let arguments = split_arguments(caps.get(1).map_or("", |m| m.as_str()))
+        .ok_or_else(|| invalid_directive(trimmed, line_num))?;
+    match arguments.as_slice() {
+        [] => Ok(Some(TerminalShell::default())),
+        [flag, shell] if flag.value == "--shell" && !flag.quoted => {
+            let shell = shell.value.parse::<TerminalShell>().map_err(|e| match e {
+                ParseError::InvalidDirective { directive, .. } => ParseError::InvalidDirective {
+                    line: line_num,
+                    directive,
+                    span: None,
+                },
+                other => other,
+            })?;
+            Ok(Some(shell))
         }
+        _ => Err(invalid_directive(trimmed, line_num)),
+    }
+}
 
-        return Ok(WidthSpec::Rems(rem));
+/// Read a line of a `::terminal` session
+///
+/// Lines starting with the prompt are commands; `$` is accepted for every
+/// shell and `PS>` for PowerShell too. Lines starting with `#` are comments
+/// and anything else is output, kept as it is.
+pub(crate) fn parse_terminal_line(line: &str, shell: TerminalShell) -> TerminalLine {
+    let trimmed = line.trim_start();
+    let prompts: &[&str] = match shell {
+        TerminalShell::PowerShell => &["$", "PS>"],
+        _ => &["$"],
+    };
+    let command = prompts.iter().find_map(|prompt| {
+        let rest = trimmed.strip_prefix(prompt)?;
+        (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+    });
+
+    match (command, trimmed.strip_prefix('#')) {
+        (Some(command), _) => TerminalLine::Command(command.to_string()),
+        (None, Some(comment)) => TerminalLine::Comment(comment.trim().to_string()),
+        (None, None) => TerminalLine::Output(line.to_string()),
     }
+}
 
-    if let Some(pct_str) = width_str.strip_suffix('%') {
-        let pct = pct_str.parse::<u8>().map_err(|_| {
-            ParseError::InvalidDirective {
-                line: 0,
-                directive: format!(
-                    "Invalid percentage width '{}'. Percentage must be 0-100",
-                    width_str
-                ),
-            }
-        })?;
+/// Whether a resource reference is a URL, whose `#fragment` is its section
+fn is_url(path: &str) -> bool {
+    path.split_once("://").is_some_and(|(scheme, _)| scheme.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Split `::file` arguments into the resource and an optional line range
+///
+/// The last argument is only read as a range when it is a separate, unquoted
+/// token, so `./chapter 2-3.md` stays a path. A local path ending in `#name`
+/// selects the region called `name` instead. Unquoted paths may contain
+/// spaces; a path with quotes must be a single argument.
+fn parse_file_args(
+    args: &str,
+    line: &str,
+    line_num: usize,
+) -> Result<(Resource, Option<LineRange>), ParseError> {
+    let mut tokens = split_arguments(args).ok_or_else(|| invalid_directive(line, line_num))?;
+
+    let range_token = match tokens.last() {
+        Some(last) if tokens.len() > 1 && !last.quoted && LINE_RANGE.is_match(&last.value) => tokens.pop(),
+        _ => None,
+    };
+
+    let path = match tokens.as_slice() {
+        [single] => single.value.clone(),
+        tokens if tokens.iter().all(|token| !token.quoted) => {
+            // Keep the original spacing of an unquoted path
+            let raw = args.trim();
+            let raw = range_token
+                .as_ref()
+                .and_then(|range| raw.strip_suffix(range.value.as_str()))
+                .unwrap_or(raw);
+            raw.trim_end().to_string()
+        }
+        _ => return Err(invalid_directive(line, line_num)),
+    };
+    if path.is_empty() {
+        return Err(invalid_directive(line, line_num));
+    }
 
-        if pct > 100 {
+    // A `#name` suffix on a local path selects a region instead of lines
+    if let Some(caps) = FILE_REGION.captures(&path).filter(|_| !is_url(&path)) {
+        if range_token.is_some() {
             return Err(ParseError::InvalidDirective {
-                line: 0,
-                directive: format!(
-                    "Invalid percentage '{}'. Must be 0-100%",
-                    pct
-                ),
+                line: line_num,
+                directive: format!("'{}' can't take both a region and a line range", path),
+                span: None,
             });
         }
+        let region = LineRange::Named(caps[2].to_string());
+        return Ok((parse_resource(&format!("{}{}", &caps[1], &caps[3]))?, Some(region)));
+    }
+
+    let Some(range_token) = range_token else {
+        return Ok((parse_resource(&path)?, None));
+    };
 
-        return Ok(WidthSpec::Percentage(pct));
+    let text = range_token.value;
+    let invalid_range = || ParseError::InvalidDirective {
+        line: line_num,
+        directive: format!("Invalid line range '{}' for '{}'", text, path),
+        span: None,
+    };
+    let caps = LINE_RANGE.captures(&text).ok_or_else(invalid_range)?;
+    let number = |m: regex::Match| m.as_str().parse::<usize>().map_err(|_| invalid_range());
+
+    let (start, end) = match (caps.get(1), caps.get(2), caps.get(3)) {
+        (Some(start), end, _) => (number(start)?, end.map(number).transpose()?),
+        (None, _, Some(end)) => (1, Some(number(end)?)),
+        (None, _, None) => return Err(invalid_range()),
+    };
+    if matches!(end, Some(end) if start > end) {
+        return Err(invalid_range());
     }
 
-    Err(ParseError::InvalidDirective {
-        line: 0,
-        directive: format!(
-            "Invalid width format '{}'. Width must be pixels (512px), rems (32rem), or percentage (0-100%)",
-            width_str
-        ),
-    })
+    Ok((parse_resource(&path)?, Some(LineRange::Lines(start, end))))
 }
 
-/// Parse a DarkMatter block directive
-pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterNode>, ParseError> {
-    let trimmed = line.trim();
-
-    // Check for various directive types
-    if let Some(caps) = FILE_DIRECTIVE.captures(trimmed) {
-        let resource = parse_resource(caps.get(1).unwrap().as_str())?;
+/// Parse the arguments of `::diff` into a `Diff` node
+///
+/// Two positional arguments name the old and new files, or with `--inline`
+/// are the old and new text themselves. `--lang` and `--context` take a
+/// value.
+fn parse_diff_args(args: &str, line: &str, line_num: usize) -> Result<DarkMatterNode, ParseError> {
+    let mut arguments = split_arguments(args).ok_or_else(|| invalid_directive(line, line_num))?.into_iter();
+
+    let mut values = Vec::new();
+    let mut inline = false;
+    let mut language = None;
+    let mut context_lines = DIFF_CONTEXT_LINES;
+    while let Some(arg) = arguments.next() {
+        if arg.quoted || !arg.value.starts_with("--") {
+            values.push(arg.value);
+            continue;
+        }
 
-        let range = if let (Some(start), Some(end)) = (caps.get(2), caps.get(3)) {
-            let start_num = start.as_str().parse::<usize>()
-                .map_err(|_| ParseError::InvalidDirective {
+        let flag = arg.value;
+        let value = |next: Option<Argument>| {
+            next.map(|next| next.value).ok_or_else(|| ParseError::InvalidDirective {
+                line: line_num,
+                directive: format!("Flag '{}' needs a value in: {}", flag, line),
+                span: None,
+            })
+        };
+        match flag.as_str() {
+            "--inline" => inline = true,
+            "--lang" => language = Some(value(arguments.next())?),
+            "--context" => {
+                let lines = value(arguments.next())?;
+                context_lines = lines.parse().map_err(|_| ParseError::InvalidDirective {
                     line: line_num,
-                    directive: line.to_string(),
+                    directive: format!("Invalid --context value '{}'. Expected a number of lines", lines),
+                    span: None,
                 })?;
-            let end_num = end.as_str().parse::<usize>()
-                .map_err(|_| ParseError::InvalidDirective {
+            }
+            _ => {
+                return Err(ParseError::InvalidDirective {
                     line: line_num,
-                    directive: line.to_string(),
-                })?;
+                    directive: format!("Unknown flag '{}' in: {}", flag, line),
+                    span: None,
+                })
+            }
+        }
+    }
 
-            Some(LineRange {
-                start: start_num,
-                end: Some(end_num),
-            })
-        } else if let Some(start) = caps.get(2) {
-            let start_num = start.as_str().parse::<usize>()
-                .map_err(|_| ParseError::InvalidDirective {
+    let Ok([old, new]) = <[String; 2]>::try_from(values) else {
+        return Err(invalid_directive(line, line_num));
+    };
+    let (old, new) = if inline {
+        (Resource::memory("old", old), Resource::memory("new", new))
+    } else {
+        if old.is_empty() || new.is_empty() {
+            return Err(invalid_directive(line, line_num));
+        }
+        (parse_resource(&old)?, parse_resource(&new)?)
+    };
+
+    Ok(DarkMatterNode::Diff { old, new, language, context_lines })
+}
+
+/// Parse the arguments of `::pie-chart` into a `PieChart` node
+///
+/// The one positional argument names the data file. `--inner-radius` takes
+/// the size of a donut's hole as a fraction of the chart's radius, from 0
+/// (a pie) up to but not including 1; `--with-table` and
+/// `--show-percentages` are flags.
+fn parse_pie_chart_args(args: &str, line: &str, line_num: usize) -> Result<DarkMatterNode, ParseError> {
+    let mut arguments = split_arguments(args).ok_or_else(|| invalid_directive(line, line_num))?.into_iter();
+
+    let mut values = Vec::new();
+    let mut with_table = false;
+    let mut options = crate::types::PieChartOptions::default();
+    while let Some(arg) = arguments.next() {
+        if arg.quoted || !arg.value.starts_with("--") {
+            values.push(arg.value);
+            continue;
+        }
+
+        let flag = arg.value;
+        match flag.as_str() {
+            "--with-table" => with_table = true,
+            "--show-percentages" => options.show_percentages = true,
+            "--inner-radius" => {
+                let radius = arguments.next().map(|next| next.value).ok_or_else(|| ParseError::InvalidDirective {
                     line: line_num,
-                    directive: line.to_string(),
+                    directive: format!("Flag '{}' needs a value in: {}", flag, line),
+                    span: None,
                 })?;
+                options.inner_radius = radius
+                    .parse()
+                    .ok()
+                    .filter(|radius| (0.0..1.0).contains(radius))
+                    .ok_or_else(|| ParseError::InvalidDirective {
+                        line: line_num,
+                        directive: format!("Invalid --inner-radius value '{}'. Expected a number from 0 to below 1", radius),
+                        span: None,
+                    })?;
+            }
+            _ => {
+                return Err(ParseError::InvalidDirective {
+                    line: line_num,
+                    directive: format!("Unknown flag '{}' in: {}", flag, line),
+                    span: None,
+                })
+            }
+        }
+    }
+
+    let Ok([source]) = <[String; 1]>::try_from(values) else {
+        return Err(invalid_directive(line, line_num));
+    };
+    if source.is_empty() {
+        return Err(invalid_directive(line, line_num));
+    }
+
+    Ok(DarkMatterNode::PieChart {
+        data: crate::types::ChartData::External(parse_resource(&source)?),
+        with_table,
+        options,
+    })
+}
 
-            Some(LineRange {
-                start: start_num,
-                end: None,
+/// Parse the arguments of `::list` into a `DataList` node
+///
+/// The one positional argument names the data file. `--column` names a
+/// column and `--columns` a comma-separated list of them; `--as` takes
+/// `list` or `tasklist`. A task list needs a column for its items and one
+/// for whether they're done, so a single named column is rejected.
+fn parse_list_args(args: &str, line: &str, line_num: usize) -> Result<DarkMatterNode, ParseError> {
+    let mut arguments = split_arguments(args).ok_or_else(|| invalid_directive(line, line_num))?.into_iter();
+
+    let mut values = Vec::new();
+    let mut columns = Vec::new();
+    let mut ordered = false;
+    let mut task_list = false;
+    while let Some(arg) = arguments.next() {
+        if arg.quoted || !arg.value.starts_with("--") {
+            values.push(arg.value);
+            continue;
+        }
+
+        let flag = arg.value;
+        let value = |next: Option<Argument>| {
+            next.map(|next| next.value).ok_or_else(|| ParseError::InvalidDirective {
+                line: line_num,
+                directive: format!("Flag '{}' needs a value in: {}", flag, line),
+                span: None,
             })
-        } else {
-            None
         };
+        match flag.as_str() {
+            "--ordered" => ordered = true,
+            "--column" => columns.push(value(arguments.next())?),
+            "--columns" => {
+                columns.extend(value(arguments.next())?.split(',').map(|column| column.trim().to_string()));
+            }
+            "--as" => {
+                task_list = match value(arguments.next())?.as_str() {
+                    "list" => false,
+                    "tasklist" => true,
+                    other => {
+                        return Err(ParseError::InvalidDirective {
+                            line: line_num,
+                            directive: format!("Invalid --as value '{}'. Expected list or tasklist", other),
+                            span: None,
+                        })
+                    }
+                };
+            }
+            _ => {
+                return Err(ParseError::InvalidDirective {
+                    line: line_num,
+                    directive: format!("Unknown flag '{}' in: {}", flag, line),
+                    span: None,
+                })
+            }
+        }
+    }
+
+    let Ok([source]) = <[String; 1]>::try_from(values) else {
+        return Err(invalid_directive(line, line_num));
+    };
+    if source.is_empty() || columns.iter().any(String::is_empty) {
+        return Err(invalid_directive(line, line_num));
+    }
+    if task_list && columns.len() == 1 {
+        return Err(ParseError::InvalidDirective {
+            line: line_num,
+            directive: format!("A task list needs an item column and a done column in: {}", line),
+            span: None,
+        });
+    }
+
+    Ok(DarkMatterNode::DataList {
+        source: crate::types::ListSource::External { resource: parse_resource(&source)?, columns },
+        ordered,
+        task_list,
+    })
+}
+
+/// What a `::` line turned out to be
+pub(crate) enum Directive {
+    /// A directive producing a node
+    Node(Box<DarkMatterNode>),
+    /// A directive producing no node, such as a block marker
+    Marker,
+    /// Not a DarkMatter directive
+    Unknown,
+}
+
+/// Parse a DarkMatter block directive
+///
+/// Returns `None` for directives that produce no node and for lines that
+/// aren't directives at all. Errors carry the span of the directive within
+/// `line`; [`parse_markdown`] moves it into the coordinates of the whole
+/// document.
+///
+/// [`parse_markdown`]: crate::parse::parse_markdown
+pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterNode>, ParseError> {
+    Ok(match read_directive(line, line_num)? {
+        Directive::Node(node) => Some(*node),
+        Directive::Marker | Directive::Unknown => None,
+    })
+}
 
-        return Ok(Some(DarkMatterNode::File { resource, range }));
+/// Parse a DarkMatter block directive, telling unknown directives apart
+pub(crate) fn read_directive(line: &str, line_num: usize) -> Result<Directive, ParseError> {
+    parse_directive_line(line, line_num).map_err(|e| match e.span() {
+        Some(_) => e,
+        None => {
+            let offset = line.len() - line.trim_start().len();
+            e.with_span(Span {
+                offset,
+                length: line.trim().len(),
+                line: line_num,
+                column: line[..offset].chars().count() + 1,
+            })
+        }
+    })
+}
+
+fn parse_directive_line(line: &str, line_num: usize) -> Result<Directive, ParseError> {
+    let trimmed = line.trim();
+
+    // Check for various directive types
+    if let Some(caps) = FILE_DIRECTIVE.captures(trimmed) {
+        let (resource, range) = parse_file_args(&caps[1], line, line_num)?;
+        return Ok(Directive::Node(Box::new(DarkMatterNode::File { resource, range })));
     }
 
     if let Some(caps) = SUMMARIZE_DIRECTIVE.captures(trimmed) {
-        let resource = parse_resource(caps.get(1).unwrap().as_str())?;
-        return Ok(Some(DarkMatterNode::Summarize { resource }));
+        let args = DirectiveArgs::parse(&caps[1], &[], line, line_num)?;
+        let Ok([resource]) = <[Resource; 1]>::try_from(args.resources(line, line_num)?) else {
+            return Err(invalid_directive(line, line_num));
+        };
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Summarize { resource })));
     }
 
     if let Some(caps) = CONSOLIDATE_DIRECTIVE.captures(trimmed) {
-        let resources = parse_resources(caps.get(1).unwrap().as_str())?;
-        return Ok(Some(DarkMatterNode::Consolidate { resources }));
+        let args = DirectiveArgs::parse(&caps[1], &[], line, line_num)?;
+        let resources = args.resources(line, line_num)?;
+        if resources.is_empty() {
+            return Err(invalid_directive(line, line_num));
+        }
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Consolidate { resources })));
     }
 
     if let Some(caps) = TOPIC_DIRECTIVE.captures(trimmed) {
-        let topic = caps.get(1).unwrap().as_str().to_string();
-        let resources = parse_resources(caps.get(2).unwrap().as_str())?;
-        let review = trimmed.contains("--review");
+        let mut args = DirectiveArgs::parse(&caps[1], &["--review"], line, line_num)?;
+        if args.values.len() < 2 {
+            return Err(invalid_directive(line, line_num));
+        }
+        let topic = args.values.remove(0);
+        let review = args.has_flag("--review");
+        let resources = args.resources(line, line_num)?;
 
-        return Ok(Some(DarkMatterNode::Topic {
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Topic {
             topic,
             resources,
             review,
-        }));
+        })));
     }
 
     if let Some(caps) = TABLE_DIRECTIVE.captures(trimmed) {
@@ -288,28 +863,96 @@ pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterN
             crate::types::TableSource::Inline(Vec::new())
         };
 
-        return Ok(Some(DarkMatterNode::Table {
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Table {
             source,
             has_heading,
-        }));
+        })));
     }
 
     if let Some(caps) = CHART_DIRECTIVE.captures(trimmed) {
         let chart_type = caps.get(1).unwrap().as_str();
-        let resource = parse_resource(caps.get(2).unwrap().as_str())?;
-        let data = crate::types::ChartData::External(resource);
-
-        return Ok(Some(match chart_type {
-            "bar-chart" => DarkMatterNode::BarChart { data },
-            "line-chart" => DarkMatterNode::LineChart { data },
-            "pie-chart" => DarkMatterNode::PieChart { data },
-            "area-chart" => DarkMatterNode::AreaChart { data },
-            "bubble-chart" => DarkMatterNode::BubbleChart { data },
+        if chart_type == "pie-chart" {
+            return parse_pie_chart_args(&caps[2], line, line_num).map(|node| Directive::Node(Box::new(node)));
+        }
+        let args = DirectiveArgs::parse(&caps[2], &["--with-table"], line, line_num)?;
+        let [source] = args.values.as_slice() else {
+            return Err(invalid_directive(line, line_num));
+        };
+        let data = crate::types::ChartData::External(parse_resource(source)?);
+        let with_table = args.has_flag("--with-table");
+
+        return Ok(Directive::Node(Box::new(match chart_type {
+            "bar-chart" => DarkMatterNode::BarChart { data, with_table },
+            "line-chart" => DarkMatterNode::LineChart { data, with_table },
+            "area-chart" => DarkMatterNode::AreaChart { data, with_table },
+            "bubble-chart" => DarkMatterNode::BubbleChart { data, with_table },
             _ => return Err(ParseError::InvalidDirective {
                 line: line_num,
                 directive: line.to_string(),
+                span: None,
             }),
-        }));
+        })));
+    }
+
+    if let Some(caps) = LIST_DIRECTIVE.captures(trimmed) {
+        return parse_list_args(&caps[1], line, line_num).map(|node| Directive::Node(Box::new(node)));
+    }
+
+    if let Some(caps) = INCLUDE_ENV_DIRECTIVE.captures(trimmed) {
+        let args = caps.get(1).map(|m| m.as_str()).unwrap_or("");
+
+        let filter = INCLUDE_ENV_FILTER.captures(args).and_then(|c| {
+            c.get(1).or_else(|| c.get(2)).map(|m| m.as_str().to_string())
+        });
+
+        let show_values = match INCLUDE_ENV_SHOW_VALUES.captures(args) {
+            Some(c) => match c.get(1).unwrap().as_str() {
+                "true" => true,
+                "false" => false,
+                other => return Err(ParseError::InvalidDirective {
+                    line: line_num,
+                    directive: format!(
+                        "Invalid --show-values value '{}'. Expected true or false",
+                        other
+                    ),
+                    span: None,
+                }),
+            },
+            None => false,
+        };
+
+        let sort = args.split_whitespace().any(|arg| arg == "--sort");
+
+        return Ok(Directive::Node(Box::new(DarkMatterNode::EnvTable {
+            filter,
+            sort,
+            show_values,
+        })));
+    }
+
+    if let Some(caps) = REQUIRE_ENV_DIRECTIVE.captures(trimmed) {
+        // Fail fast: every listed variable must be present in the environment
+        let vars: Vec<String> = caps.get(1).unwrap().as_str().split_whitespace().map(str::to_string).collect();
+        for var in &vars {
+            if std::env::var_os(var).is_none() {
+                return Err(ParseError::RequiredResourceNotFound {
+                    resource: format!("environment variable {}", var),
+                });
+            }
+        }
+
+        return Ok(Directive::Node(Box::new(DarkMatterNode::RequireEnv { vars })));
+    }
+
+    if let Some(caps) = DIFF_DIRECTIVE.captures(trimmed) {
+        return parse_diff_args(&caps[1], line, line_num).map(|node| Directive::Node(Box::new(node)));
+    }
+
+    if let Some((id, text)) = parse_footnote(trimmed, line_num)? {
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Footnote {
+            id,
+            content: vec![DarkMatterNode::Markdown(MarkdownContent { raw: text, frontmatter: None })],
+        })));
     }
 
     if let Some(caps) = AUDIO_DIRECTIVE.captures(trimmed) {
@@ -320,12 +963,20 @@ pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterN
             .ok_or_else(|| ParseError::InvalidDirective {
                 line: line_num,
                 directive: line.to_string(),
+                span: None,
             })?;
 
         // Extract optional name (group 3)
         let name = caps.get(3).map(|m| m.as_str().to_string());
 
-        return Ok(Some(DarkMatterNode::Audio { source, name }));
+        // Captions file, quoted (group 4) or not (group 5), in English
+        // unless `--srclang` (group 6) says otherwise
+        let captions = caps.get(4).or_else(|| caps.get(5)).map(|path| Captions {
+            path: path.as_str().to_string(),
+            language: caps.get(6).map_or(DEFAULT_CAPTIONS_LANGUAGE, |m| m.as_str()).to_string(),
+        });
+
+        return Ok(Directive::Node(Box::new(DarkMatterNode::Audio { source, name, captions })));
     }
 
     if let Some(caps) = YOUTUBE_DIRECTIVE.captures(trimmed) {
@@ -336,6 +987,7 @@ pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterN
             return Err(ParseError::InvalidDirective {
                 line: line_num,
                 directive: "YouTube directive requires a video reference (URL or 11-character video ID)".to_string(),
+                span: None,
             });
         }
 
@@ -346,86 +998,126 @@ pub fn parse_directive(line: &str, line_num: usize) -> Result<Option<DarkMatterN
             .transpose()?
             .unwrap_or_default();
 
-        return Ok(Some(DarkMatterNode::YouTube { video_id, width }));
-    }
+        let captions = caps.get(3).map(|m| m.as_str().to_string());
 
-    // Check for summary/details directives
-    if trimmed == "::summary" {
-        // This will be handled by the parser context
-        return Ok(None);
+        return Ok(Directive::Node(Box::new(DarkMatterNode::YouTube { video_id, width, captions })));
     }
 
-    if trimmed == "::details" {
-        // This will be handled by the parser context
-        return Ok(None);
+    // Disclosure, columns, Mermaid, terminal and aspect ratio blocks are
+    // assembled by `parse_markdown`, which owns the lines between their markers
+    let block_marker =
+        [SUMMARY, DETAILS, COLUMN_BREAK, MERMAID, END_BLOCK, END_ASPECT_RATIO, END_TERMINAL].contains(&trimmed);
+    let block_opener = [&COLUMNS_DIRECTIVE, &ASPECT_RATIO_DIRECTIVE, &TERMINAL_DIRECTIVE]
+        .iter()
+        .any(|directive| directive.is_match(trimmed));
+    if block_marker || block_opener {
+        return Ok(Directive::Marker);
     }
 
-    if trimmed == "::break" {
-        // Column break - handled by parser context
-        return Ok(None);
-    }
+    // Not a recognized directive
+    Ok(Directive::Unknown)
+}
 
-    if COLUMNS_DIRECTIVE.is_match(trimmed) {
-        // Columns - will be handled by parser context
-        return Ok(None);
-    }
+/// An inline syntax pattern and the node built from each of its matches
+struct InlinePattern {
+    regex: &'static LazyLock<Regex>,
+    build: fn(&Captures) -> DarkMatterNode,
+}
 
-    // Not a recognized directive
-    Ok(None)
+/// Inline syntax recognized by [`process_inline_syntax`]
+///
+/// When matches overlap, the one that starts first wins; for matches starting
+/// at the same position, the pattern listed first wins.
+static INLINE_PATTERNS: [InlinePattern; 4] = [
+    InlinePattern {
+        regex: &POPOVER_LINK,
+        build: |caps| DarkMatterNode::Popover {
+            trigger: Box::new(DarkMatterNode::Text(caps[1].to_string())),
+            content: process_inline_syntax(&caps[2]),
+        },
+    },
+    InlinePattern {
+        regex: &FOOTNOTE_MARK,
+        build: |caps| {
+            if is_footnote_id(&caps[1]) {
+                DarkMatterNode::FootnoteRef { id: caps[1].to_string() }
+            } else {
+                DarkMatterNode::Footnote {
+                    id: String::new(),
+                    content: process_inline_syntax(&caps[1]),
+                }
+            }
+        },
+    },
+    InlinePattern {
+        regex: &INLINE_MATH,
+        build: |caps| match caps.get(1) {
+            Some(source) => DarkMatterNode::Math { source: source.as_str().to_string(), display: false },
+            None => DarkMatterNode::Text("$".to_string()),
+        },
+    },
+    InlinePattern {
+        regex: &INTERPOLATION,
+        build: |caps| DarkMatterNode::Interpolation {
+            variable: caps[1].to_string(),
+        },
+    },
+];
+
+/// A match of one of the [`INLINE_PATTERNS`]
+struct InlineMatch {
+    start: usize,
+    end: usize,
+    pattern: usize,
+    node: DarkMatterNode,
 }
 
 /// Process inline DarkMatter syntax in text
+///
+/// The text is tokenized in a single pass over the matches of every inline
+/// pattern, ordered by position, so popover links, footnotes, inline math,
+/// interpolations and the plain text between them come out interleaved as
+/// they appear. Text is only ever split at match boundaries, which always fall
+/// on character boundaries.
 pub fn process_inline_syntax(text: &str) -> Vec<DarkMatterNode> {
+    let mut matches: Vec<InlineMatch> = INLINE_PATTERNS
+        .iter()
+        .enumerate()
+        .flat_map(|(pattern, inline)| {
+            inline.regex.captures_iter(text).map(move |caps| {
+                let full_match = caps.get(0).unwrap();
+                InlineMatch {
+                    start: full_match.start(),
+                    end: full_match.end(),
+                    pattern,
+                    node: (inline.build)(&caps),
+                }
+            })
+        })
+        .collect();
+    matches.sort_by_key(|m| (m.start, m.pattern));
+
     let mut nodes = Vec::new();
     let mut current_pos = 0;
 
-    // TODO: Handle popover links in future phase
-    // For now, we'll just handle interpolations
-
-    // Find all popover links (placeholder for future implementation)
-    for caps in POPOVER_LINK.captures_iter(text) {
-        let _full_match = caps.get(0).unwrap();
-        let _trigger_text = caps.get(1).unwrap().as_str();
-        let _popover_content = caps.get(2).unwrap().as_str();
-
-        // For now, we'll create a simplified representation
-        // In a full implementation, this would create proper popover nodes
-    }
-
-    // Find all interpolations
-    for caps in INTERPOLATION.captures_iter(text) {
-        let full_match = caps.get(0).unwrap();
-        let var_name = caps.get(1).unwrap().as_str();
-
-        let match_start = full_match.start();
-
-        // Add text before interpolation
-        if match_start > current_pos {
-            let text_before = &text[current_pos..match_start];
-            if !text_before.is_empty() {
-                nodes.push(DarkMatterNode::Text(text_before.to_string()));
-            }
+    for m in matches {
+        // Skip matches inside one that has already been consumed
+        if m.start < current_pos {
+            continue;
         }
 
-        // Add interpolation node
-        nodes.push(DarkMatterNode::Interpolation {
-            variable: var_name.to_string(),
-        });
-
-        current_pos = full_match.end();
-    }
-
-    // Add remaining text
-    if current_pos < text.len() {
-        let remaining = &text[current_pos..];
-        if !remaining.is_empty() {
-            nodes.push(DarkMatterNode::Text(remaining.to_string()));
+        // Add text before the match
+        if m.start > current_pos {
+            nodes.push(DarkMatterNode::Text(text[current_pos..m.start].to_string()));
         }
+
+        nodes.push(m.node);
+        current_pos = m.end;
     }
 
-    // If no inline syntax was found, return the original text as a single node
-    if nodes.is_empty() {
-        nodes.push(DarkMatterNode::Text(text.to_string()));
+    // Add remaining text; with no inline syntax this is the whole text
+    if current_pos < text.len() || nodes.is_empty() {
+        nodes.push(DarkMatterNode::Text(text[current_pos..].to_string()));
     }
 
     nodes
@@ -434,6 +1126,8 @@ pub fn process_inline_syntax(text: &str) -> Vec<DarkMatterNode> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{ListSource, ResourceRequirement, ResourceSource};
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_parse_file_directive() {
@@ -454,14 +1148,127 @@ mod tests {
 
         match node {
             DarkMatterNode::File { resource: _, range } => {
-                let range = range.unwrap();
-                assert_eq!(range.start, 10);
-                assert_eq!(range.end, Some(20));
+                assert_eq!(range, Some(LineRange::Lines(10, Some(20))));
+            }
+            _ => panic!("Expected File node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_directive_leading_dash_range() {
+        let node = parse_directive("::file ./x.md -20", 1).unwrap().unwrap();
+
+        match node {
+            DarkMatterNode::File { resource, range } => {
+                assert!(matches!(resource.source, crate::types::ResourceSource::Local(ref p) if p.ends_with("x.md")));
+                assert_eq!(range, Some(LineRange::Lines(1, Some(20))));
+            }
+            _ => panic!("Expected File node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_directive_open_ended_range() {
+        let node = parse_directive("::file ./x.md 10-", 1).unwrap().unwrap();
+
+        match node {
+            DarkMatterNode::File { resource: _, range } => {
+                assert_eq!(range, Some(LineRange::Lines(10, None)));
             }
             _ => panic!("Expected File node"),
         }
     }
 
+    #[test]
+    fn test_parse_file_directive_rejects_reversed_range() {
+        let result = parse_directive("::file ./x.md 20-10", 7);
+        match result {
+            Err(ParseError::InvalidDirective { line: 7, directive, .. }) => {
+                assert!(directive.contains("20-10"), "{}", directive);
+                assert!(directive.contains("./x.md"), "{}", directive);
+            }
+            other => panic!("Expected InvalidDirective, got {:?}", other),
+        }
+    }
+
+    fn file_directive(line: &str) -> (PathBuf, Option<LineRange>) {
+        match parse_directive(line, 1).unwrap().unwrap() {
+            DarkMatterNode::File { resource, range } => match resource.source {
+                ResourceSource::Local(path) => (path, range),
+                other => panic!("Expected local resource, got {:?}", other),
+            },
+            _ => panic!("Expected File node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_directive_digits_in_filename() {
+        let (path, range) = file_directive("::file ./chapter 2-3.md");
+        assert_eq!(path, PathBuf::from("./chapter 2-3.md"));
+        assert!(range.is_none());
+
+        let (path, range) = file_directive("::file ./notes-2024 10.md");
+        assert_eq!(path, PathBuf::from("./notes-2024 10.md"));
+        assert!(range.is_none());
+
+        let (path, range) = file_directive("::file ./2024-01-notes.md 3-4");
+        assert_eq!(path, PathBuf::from("./2024-01-notes.md"));
+        assert_eq!(range, Some(LineRange::Lines(3, Some(4))));
+    }
+
+    #[test]
+    fn test_parse_file_directive_quoted_path() {
+        let (path, range) = file_directive(r#"::file "./chapter 2-3.md""#);
+        assert_eq!(path, PathBuf::from("./chapter 2-3.md"));
+        assert!(range.is_none());
+
+        let (path, range) = file_directive(r#"::file "./my notes.md" 5-"#);
+        assert_eq!(path, PathBuf::from("./my notes.md"));
+        assert_eq!(range, Some(LineRange::Lines(5, None)));
+
+        // A quoted range-like token is part of the path
+        assert!(parse_directive(r#"::file ./a.md "5-6""#, 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_file_directive_region() {
+        let (path, range) = file_directive("::file ./utils.rs#auth");
+        assert_eq!(path, PathBuf::from("./utils.rs"));
+        assert_eq!(range, Some(LineRange::Named("auth".to_string())));
+
+        let (path, range) = file_directive("::file ./my utils.rs#db-pool!");
+        assert_eq!(path, PathBuf::from("./my utils.rs"));
+        assert_eq!(range, Some(LineRange::Named("db-pool".to_string())));
+
+        // A '#' that isn't a region suffix is part of the path
+        let (path, range) = file_directive("::file ./notes#1.md");
+        assert_eq!(path, PathBuf::from("./notes#1.md"));
+        assert!(range.is_none());
+
+        // URL fragments stay the resource's section
+        match parse_directive("::file https://example.com/guide.md#setup", 1).unwrap().unwrap() {
+            DarkMatterNode::File { resource, range } => {
+                assert_eq!(resource.section.as_deref(), Some("setup"));
+                assert!(range.is_none());
+            }
+            other => panic!("Expected File node, got {:?}", other),
+        }
+
+        assert!(parse_directive("::file ./utils.rs#auth 1-5", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_file_directive_explicit_ranges() {
+        let (path, range) = file_directive("::file ./my notes.md 10-20");
+        assert_eq!(path, PathBuf::from("./my notes.md"));
+        assert_eq!(range, Some(LineRange::Lines(10, Some(20))));
+
+        // A bare range is a path, not a range without a file
+        let (path, range) = file_directive("::file 10-20");
+        assert_eq!(path, PathBuf::from("10-20"));
+        assert!(range.is_none());
+    }
+
     #[test]
     fn test_parse_summarize_directive() {
         let node = parse_directive("::summarize ./doc.md", 1).unwrap().unwrap();
@@ -502,14 +1309,63 @@ mod tests {
 
     #[test]
     fn test_parse_topic_directive_with_review() {
-        let node = parse_directive(r#"::topic "testing" ./a.md --review"#, 1).unwrap().unwrap();
+        for line in [
+            r#"::topic "testing" ./a.md --review"#,
+            r#"::topic "testing" --review ./a.md"#,
+            r#"::topic --review "testing" ./a.md"#,
+        ] {
+            match parse_directive(line, 1).unwrap().unwrap() {
+                DarkMatterNode::Topic { topic, resources, review } => {
+                    assert_eq!(topic, "testing", "{}", line);
+                    assert_eq!(resources.len(), 1, "{}", line);
+                    assert_eq!(resources[0].source, ResourceSource::Local(PathBuf::from("./a.md")));
+                    assert!(review, "{}", line);
+                }
+                _ => panic!("Expected Topic node"),
+            }
+        }
+    }
 
-        match node {
-            DarkMatterNode::Topic { topic: _, resources: _, review } => {
+    #[test]
+    fn test_parse_ai_directives_with_quoted_paths() {
+        let spaced = || ResourceSource::Local(PathBuf::from("./my notes.md"));
+
+        match parse_directive(r#"::topic "release plans" "./my notes.md" ./b.md --review"#, 1).unwrap().unwrap() {
+            DarkMatterNode::Topic { topic, resources, review } => {
+                assert_eq!(topic, "release plans");
+                assert_eq!(resources[0].source, spaced());
+                assert_eq!(resources.len(), 2);
                 assert!(review);
             }
             _ => panic!("Expected Topic node"),
         }
+
+        match parse_directive(r#"::consolidate ./a.md "./my notes.md""#, 1).unwrap().unwrap() {
+            DarkMatterNode::Consolidate { resources } => {
+                assert_eq!(resources[1].source, spaced());
+            }
+            _ => panic!("Expected Consolidate node"),
+        }
+
+        match parse_directive(r#"::summarize "./my notes.md"!"#, 1).unwrap().unwrap() {
+            DarkMatterNode::Summarize { resource } => {
+                assert_eq!(resource.source, spaced());
+                assert!(matches!(resource.requirement, ResourceRequirement::Required));
+            }
+            _ => panic!("Expected Summarize node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ai_directive_argument_errors() {
+        // Unknown flags are not mistaken for resources
+        assert!(parse_directive("::consolidate ./a.md --review", 1).is_err());
+        assert!(parse_directive(r#"::topic "testing" ./a.md --reveiw"#, 1).is_err());
+        // A topic needs at least one resource
+        assert!(parse_directive(r#"::topic "testing" --review"#, 1).is_err());
+        // Unquoted spaces make a second resource
+        assert!(parse_directive("::summarize ./my notes.md", 1).is_err());
+        assert!(parse_directive(r#"::summarize "./my notes.md"#, 1).is_err());
     }
 
     #[test]
@@ -543,13 +1399,112 @@ mod tests {
         let node = parse_directive("::bar-chart ./data.csv", 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::BarChart { data: _ } => {
-                // Success
+            DarkMatterNode::BarChart { data: _, with_table } => {
+                assert!(!with_table);
             }
             _ => panic!("Expected BarChart node"),
         }
     }
 
+    #[test]
+    fn test_parse_chart_directive_with_table() {
+        let node = parse_directive("::pie-chart --with-table \"./sales data.csv\"", 1).unwrap().unwrap();
+
+        match node {
+            DarkMatterNode::PieChart { data: crate::types::ChartData::External(resource), with_table, options } => {
+                assert!(with_table);
+                assert_eq!(options, crate::types::PieChartOptions::default());
+                assert!(matches!(resource.source, ResourceSource::Local(ref path) if path.ends_with("sales data.csv")));
+            }
+            other => panic!("Expected PieChart node, got {:?}", other),
+        }
+
+        assert!(parse_directive("::bar-chart ./data.csv --with-tables", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_pie_chart_options() {
+        let node = parse_directive("::pie-chart ./share.csv --inner-radius 0.6 --show-percentages", 1).unwrap().unwrap();
+
+        match node {
+            DarkMatterNode::PieChart { with_table, options, .. } => {
+                assert!(!with_table);
+                assert_eq!(options, crate::types::PieChartOptions { inner_radius: 0.6, show_percentages: true });
+            }
+            other => panic!("Expected PieChart node, got {:?}", other),
+        }
+
+        for invalid in ["--inner-radius", "--inner-radius 1", "--inner-radius half", "--donut"] {
+            assert!(parse_directive(&format!("::pie-chart ./share.csv {}", invalid), 1).is_err(), "{}", invalid);
+        }
+        assert!(parse_directive("::bar-chart ./data.csv --show-percentages", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_include_env_directive() {
+        let node = parse_directive(r#"::include-env --filter "APP_" --sort --show-values false"#, 1)
+            .unwrap()
+            .unwrap();
+
+        match node {
+            DarkMatterNode::EnvTable { filter, sort, show_values } => {
+                assert_eq!(filter, Some("APP_".to_string()));
+                assert!(sort);
+                assert!(!show_values);
+            }
+            _ => panic!("Expected EnvTable node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_include_env_directive_defaults() {
+        let node = parse_directive("::include-env", 1).unwrap().unwrap();
+
+        match node {
+            DarkMatterNode::EnvTable { filter, sort, show_values } => {
+                assert!(filter.is_none());
+                assert!(!sort);
+                assert!(!show_values);
+            }
+            _ => panic!("Expected EnvTable node"),
+        }
+    }
+
+    #[test]
+    fn test_parse_include_env_directive_invalid_show_values() {
+        let result = parse_directive("::include-env --show-values maybe", 3);
+        assert!(matches!(result, Err(ParseError::InvalidDirective { line: 3, .. })));
+    }
+
+    #[test]
+    fn test_parse_require_env_directive_present() {
+        unsafe {
+            std::env::set_var("COMPOSITION_TEST_REQUIRE_ENV_PRESENT", "1");
+        }
+
+        let result = parse_directive("::require-env COMPOSITION_TEST_REQUIRE_ENV_PRESENT", 1).unwrap();
+        assert!(matches!(
+            result,
+            Some(DarkMatterNode::RequireEnv { vars }) if vars == ["COMPOSITION_TEST_REQUIRE_ENV_PRESENT"]
+        ));
+
+        unsafe {
+            std::env::remove_var("COMPOSITION_TEST_REQUIRE_ENV_PRESENT");
+        }
+    }
+
+    #[test]
+    fn test_parse_require_env_directive_missing() {
+        let result = parse_directive("::require-env PATH COMPOSITION_TEST_REQUIRE_ENV_MISSING", 1);
+
+        match result {
+            Err(ParseError::RequiredResourceNotFound { resource }) => {
+                assert!(resource.contains("COMPOSITION_TEST_REQUIRE_ENV_MISSING"));
+            }
+            other => panic!("Expected RequiredResourceNotFound, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_process_interpolation() {
         let nodes = process_inline_syntax("Hello {{name}}, welcome!");
@@ -560,6 +1515,99 @@ mod tests {
         assert!(matches!(nodes[2], DarkMatterNode::Text(_)));
     }
 
+    fn text(node: &DarkMatterNode) -> &str {
+        match node {
+            DarkMatterNode::Text(t) => t,
+            other => panic!("Expected Text node, got {:?}", other),
+        }
+    }
+
+    fn variable(node: &DarkMatterNode) -> &str {
+        match node {
+            DarkMatterNode::Interpolation { variable } => variable,
+            other => panic!("Expected Interpolation node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_process_popover_and_interpolation() {
+        let nodes = process_inline_syntax("Hi {{name}}, see the [jungle](popover:Scary {{place}}) and {{end}}");
+
+        assert_eq!(nodes.len(), 6);
+        assert_eq!(text(&nodes[0]), "Hi ");
+        assert_eq!(variable(&nodes[1]), "name");
+        assert_eq!(text(&nodes[2]), ", see the ");
+        match &nodes[3] {
+            DarkMatterNode::Popover { trigger, content } => {
+                assert_eq!(text(trigger), "jungle");
+                assert_eq!(content.len(), 2);
+                assert_eq!(text(&content[0]), "Scary ");
+                assert_eq!(variable(&content[1]), "place");
+            }
+            other => panic!("Expected Popover node, got {:?}", other),
+        }
+        assert_eq!(text(&nodes[4]), " and ");
+        assert_eq!(variable(&nodes[5]), "end");
+    }
+
+    #[test]
+    fn test_process_popover_before_interpolation() {
+        let nodes = process_inline_syntax("[term](popover:Definition){{suffix}}");
+
+        assert_eq!(nodes.len(), 2);
+        assert!(matches!(nodes[0], DarkMatterNode::Popover { .. }));
+        assert_eq!(variable(&nodes[1]), "suffix");
+    }
+
+    #[test]
+    fn test_process_footnote_marks() {
+        let nodes = process_inline_syntax("Claim^[1] and aside^[see {{source}}].");
+
+        assert_eq!(nodes.len(), 5);
+        assert!(matches!(&nodes[1], DarkMatterNode::FootnoteRef { id } if id == "1"));
+        let DarkMatterNode::Footnote { id, content } = &nodes[3] else {
+            panic!("Expected Footnote node, got {:?}", nodes[3]);
+        };
+        assert!(id.is_empty());
+        assert_eq!(text(&content[0]), "see ");
+        assert_eq!(variable(&content[1]), "source");
+        assert_eq!(text(&nodes[4]), ".");
+    }
+
+    #[test]
+    fn test_parse_footnote_directive() {
+        let node = parse_directive("::footnote[smith-2020] Smith, *Footnotes*, 2020.", 1).unwrap().unwrap();
+
+        let DarkMatterNode::Footnote { id, content } = node else {
+            panic!("Expected Footnote node, got {:?}", node);
+        };
+        assert_eq!(id, "smith-2020");
+        assert!(matches!(content.as_slice(), [DarkMatterNode::Markdown(md)] if md.raw == "Smith, *Footnotes*, 2020."));
+
+        for invalid in ["::footnote", "::footnote[1]", "::footnote[a b] text", "::footnote text"] {
+            let result = parse_directive(invalid, 4);
+            assert!(matches!(result, Err(ParseError::InvalidDirective { line: 4, .. })), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_process_inline_syntax_multibyte_text() {
+        let nodes = process_inline_syntax("🎉 你好{{name}}世界 [火山🌋](popover:熔岩 🔥)終わり");
+
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(text(&nodes[0]), "🎉 你好");
+        assert_eq!(variable(&nodes[1]), "name");
+        assert_eq!(text(&nodes[2]), "世界 ");
+        match &nodes[3] {
+            DarkMatterNode::Popover { trigger, content } => {
+                assert_eq!(text(trigger), "火山🌋");
+                assert_eq!(text(&content[0]), "熔岩 🔥");
+            }
+            other => panic!("Expected Popover node, got {:?}", other),
+        }
+        assert_eq!(text(&nodes[4]), "終わり");
+    }
+
     #[test]
     fn test_process_plain_text() {
         let nodes = process_inline_syntax("Just plain text");
@@ -576,7 +1624,7 @@ mod tests {
         let node = parse_directive("::audio ./podcast.mp3", 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::Audio { source, name } => {
+            DarkMatterNode::Audio { source, name, .. } => {
                 assert_eq!(source, "./podcast.mp3");
                 assert!(name.is_none());
             }
@@ -589,7 +1637,7 @@ mod tests {
         let node = parse_directive(r#"::audio ./podcast.mp3 "Episode 42""#, 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::Audio { source, name } => {
+            DarkMatterNode::Audio { source, name, .. } => {
                 assert_eq!(source, "./podcast.mp3");
                 assert_eq!(name, Some("Episode 42".to_string()));
             }
@@ -602,7 +1650,7 @@ mod tests {
         let node = parse_directive(r#"::audio "./path with spaces.mp3""#, 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::Audio { source, name } => {
+            DarkMatterNode::Audio { source, name, .. } => {
                 assert_eq!(source, "./path with spaces.mp3");
                 assert!(name.is_none());
             }
@@ -615,7 +1663,7 @@ mod tests {
         let node = parse_directive(r#"::audio "./path with spaces.mp3" "My Audio""#, 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::Audio { source, name } => {
+            DarkMatterNode::Audio { source, name, .. } => {
                 assert_eq!(source, "./path with spaces.mp3");
                 assert_eq!(name, Some("My Audio".to_string()));
             }
@@ -635,7 +1683,7 @@ mod tests {
         let node = parse_directive("::youtube dQw4w9WgXcQ", 1).unwrap().unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width } => {
+            DarkMatterNode::YouTube { video_id, width, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
                 assert_eq!(width, WidthSpec::Pixels(512)); // default
             }
@@ -650,7 +1698,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
             }
             _ => panic!("Expected YouTube node"),
@@ -664,7 +1712,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
             }
             _ => panic!("Expected YouTube node"),
@@ -678,7 +1726,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
             }
             _ => panic!("Expected YouTube node"),
@@ -692,7 +1740,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
             }
             _ => panic!("Expected YouTube node"),
@@ -706,7 +1754,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(video_id, "dQw4w9WgXcQ");
             }
             _ => panic!("Expected YouTube node"),
@@ -720,7 +1768,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id: _, width } => {
+            DarkMatterNode::YouTube { video_id: _, width, .. } => {
                 assert_eq!(width, WidthSpec::Pixels(800));
             }
             _ => panic!("Expected YouTube node"),
@@ -734,7 +1782,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id: _, width } => {
+            DarkMatterNode::YouTube { video_id: _, width, .. } => {
                 assert_eq!(width, WidthSpec::Rems(32.0));
             }
             _ => panic!("Expected YouTube node"),
@@ -748,7 +1796,7 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id: _, width } => {
+            DarkMatterNode::YouTube { video_id: _, width, .. } => {
                 assert_eq!(width, WidthSpec::Rems(32.5));
             }
             _ => panic!("Expected YouTube node"),
@@ -762,13 +1810,27 @@ mod tests {
             .unwrap();
 
         match node {
-            DarkMatterNode::YouTube { video_id: _, width } => {
+            DarkMatterNode::YouTube { video_id: _, width, .. } => {
                 assert_eq!(width, WidthSpec::Percentage(80));
             }
             _ => panic!("Expected YouTube node"),
         }
     }
 
+    #[test]
+    fn test_parse_youtube_directive_with_viewport_and_auto_width() {
+        for (directive, expected) in [
+            ("::youtube dQw4w9WgXcQ 100vw", WidthSpec::Viewport(100)),
+            ("::youtube dQw4w9WgXcQ auto cc=en", WidthSpec::Auto),
+        ] {
+            match parse_directive(directive, 1).unwrap().unwrap() {
+                DarkMatterNode::YouTube { width, .. } => assert_eq!(width, expected, "{}", directive),
+                _ => panic!("Expected YouTube node"),
+            }
+        }
+        assert!(parse_directive("::youtube dQw4w9WgXcQ 120vw", 1).is_err());
+    }
+
     #[test]
     fn test_parse_youtube_directive_invalid_video_id() {
         let result = parse_directive("::youtube invalid-id", 1);
@@ -798,7 +1860,7 @@ mod tests {
         let result = parse_directive("::youtube dQw4w9WgXcQ 101%", 1);
         assert!(result.is_err());
         match result {
-            Err(ParseError::InvalidDirective { line: _, directive }) => {
+            Err(ParseError::InvalidDirective { directive, .. }) => {
                 assert!(directive.contains("Invalid percentage"));
                 assert!(directive.contains("101"));
             }
@@ -811,7 +1873,7 @@ mod tests {
         let result = parse_directive("::youtube dQw4w9WgXcQ 0px", 1);
         assert!(result.is_err());
         match result {
-            Err(ParseError::InvalidDirective { line: _, directive }) => {
+            Err(ParseError::InvalidDirective { directive, .. }) => {
                 assert!(directive.contains("Width must be positive"));
             }
             _ => panic!("Expected InvalidDirective error"),
@@ -948,6 +2010,17 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_width_spec_viewport() {
+        assert_eq!(parse_width_spec("80vw").unwrap(), WidthSpec::Viewport(80));
+        assert!(parse_width_spec("101vw").is_err());
+    }
+
+    #[test]
+    fn test_parse_width_spec_auto() {
+        assert_eq!(parse_width_spec("auto").unwrap(), WidthSpec::Auto);
+    }
+
     #[test]
     fn test_parse_width_spec_invalid_format() {
         let result = parse_width_spec("500");
@@ -1026,7 +2099,7 @@ mod tests {
         assert!(matches!(err, ParseError::InvalidDirective { .. }));
         let msg = err.to_string();
         assert!(msg.contains("Invalid width format '500em'"));
-        assert!(msg.contains("pixels (512px), rems (32rem), or percentage (0-100%)"));
+        assert!(msg.contains("pixels (512px), rems (32rem), percentage (0-100%), viewport width (0-100vw) or auto"));
     }
 
     #[test]
@@ -1037,7 +2110,7 @@ mod tests {
         assert!(matches!(err, ParseError::InvalidDirective { .. }));
         let msg = err.to_string();
         assert!(msg.contains("Invalid width format '500'"));
-        assert!(msg.contains("pixels (512px), rems (32rem), or percentage (0-100%)"));
+        assert!(msg.contains("pixels (512px), rems (32rem), percentage (0-100%), viewport width (0-100vw) or auto"));
     }
 
     #[test]
@@ -1234,6 +2307,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_aspect_ratio() {
+        assert_eq!(parse_aspect_ratio("::aspect-ratio 16:9", 1).unwrap(), Some((16, 9)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio 9:16", 1).unwrap(), Some((9, 16)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio 100:1", 1).unwrap(), Some((100, 1)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio --widescreen", 1).unwrap(), Some((16, 9)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio --standard", 1).unwrap(), Some((4, 3)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio --square", 1).unwrap(), Some((1, 1)));
+        assert_eq!(parse_aspect_ratio("::aspect-ratio --ultrawide", 1).unwrap(), Some((21, 9)));
+        assert_eq!(parse_aspect_ratio("::file ./doc.md", 1).unwrap(), None);
+        assert_eq!(parse_aspect_ratio("::aspect-ratios 16:9", 1).unwrap(), None);
+
+        // A plain `::aspect-ratio` is left to the markdown parser
+        assert!(parse_directive("::aspect-ratio 16:9", 1).unwrap().is_none());
+        assert!(parse_directive("::endaspect-ratio", 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_aspect_ratio_rejects_invalid_ratios() {
+        for line in [
+            "::aspect-ratio",
+            "::aspect-ratio 16:0",
+            "::aspect-ratio 0:9",
+            "::aspect-ratio 101:1",
+            "::aspect-ratio 1:101",
+            "::aspect-ratio 16/9",
+            "::aspect-ratio 16:9 4:3",
+            "::aspect-ratio 16:9 --square",
+            "::aspect-ratio --widescreen --square",
+            "::aspect-ratio --cinema",
+            "::aspect-ratio 99999999999:1",
+        ] {
+            assert!(
+                matches!(parse_aspect_ratio(line, 3), Err(ParseError::InvalidDirective { line: 3, .. })),
+                "{} should be rejected",
+                line
+            );
+        }
+    }
+
     // ===== Property-Based Tests =====
     // These tests use proptest to verify properties hold for generated inputs
 
@@ -1382,6 +2495,8 @@ mod tests {
             valid_pixel_width_strategy().prop_map(WidthSpec::Pixels),
             valid_rem_width_strategy().prop_map(WidthSpec::Rems),
             valid_percentage_width_strategy().prop_map(WidthSpec::Percentage),
+            valid_percentage_width_strategy().prop_map(WidthSpec::Viewport),
+            Just(WidthSpec::Auto),
         ]) {
             // Display and parse should roundtrip correctly
             let displayed = width.to_string();
@@ -1395,6 +2510,8 @@ mod tests {
                     prop_assert!((a - b).abs() < 0.001, "{} != {}", a, b);
                 }
                 (WidthSpec::Percentage(a), WidthSpec::Percentage(b)) => prop_assert_eq!(a, b),
+                (WidthSpec::Viewport(a), WidthSpec::Viewport(b)) => prop_assert_eq!(a, b),
+                (WidthSpec::Auto, WidthSpec::Auto) => {}
                 _ => prop_assert!(false, "Width variant mismatch"),
             }
         }
@@ -1406,6 +2523,8 @@ mod tests {
                 valid_pixel_width_strategy().prop_map(|px| format!("{}px", px)),
                 valid_rem_width_strategy().prop_map(|rem| format!("{}rem", rem)),
                 valid_percentage_width_strategy().prop_map(|pct| format!("{}%", pct)),
+                valid_percentage_width_strategy().prop_map(|vw| format!("{}vw", vw)),
+                Just("auto".to_string()),
             ])
         ) {
             // Construct a valid directive
@@ -1421,7 +2540,7 @@ mod tests {
             prop_assert!(node.is_some(), "Directive '{}' should return node", directive);
 
             match node.unwrap() {
-                DarkMatterNode::YouTube { video_id, width: _ } => {
+                DarkMatterNode::YouTube { video_id, width: _, .. } => {
                     prop_assert_eq!(video_id, id, "Video ID mismatch in directive '{}'", directive);
                 }
                 _ => prop_assert!(false, "Should return YouTube node for '{}'", directive),
@@ -1445,4 +2564,100 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_diff_directive() {
+        let node = parse_directive("::diff ./old.rs ./new.rs --lang rust --context 5", 1).unwrap();
+
+        match node {
+            Some(DarkMatterNode::Diff { old, new, language, context_lines }) => {
+                assert!(matches!(old.source, ResourceSource::Local(path) if path == Path::new("./old.rs")));
+                assert!(matches!(new.source, ResourceSource::Local(path) if path == Path::new("./new.rs")));
+                assert_eq!(language.as_deref(), Some("rust"));
+                assert_eq!(context_lines, 5);
+            }
+            other => panic!("Expected Diff node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_inline_diff_directive() {
+        let node = parse_directive(r#"::diff --inline "let x = 1;" "let x = 2;""#, 1).unwrap();
+
+        match node {
+            Some(DarkMatterNode::Diff { old, new, language: None, context_lines: 3 }) => {
+                assert!(matches!(old.source, ResourceSource::Memory { content, .. } if &*content == "let x = 1;"));
+                assert!(matches!(new.source, ResourceSource::Memory { content, .. } if &*content == "let x = 2;"));
+            }
+            other => panic!("Expected inline Diff node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_diff_directive_rejects_invalid_arguments() {
+        for line in [
+            "::diff ./old.rs",
+            "::diff ./a.rs ./b.rs ./c.rs",
+            "::diff ./old.rs ./new.rs --lang",
+            "::diff ./old.rs ./new.rs --context many",
+            "::diff ./old.rs ./new.rs --color",
+            r#"::diff --inline "unterminated"#,
+        ] {
+            assert!(
+                matches!(parse_directive(line, 4), Err(ParseError::InvalidDirective { line: 4, .. })),
+                "{} should be rejected",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_list_directive() {
+        let node = parse_directive("::list ./items.csv --column name --ordered", 1).unwrap();
+
+        match node {
+            Some(DarkMatterNode::DataList {
+                source: ListSource::External { resource, columns },
+                ordered: true,
+                task_list: false,
+            }) => {
+                assert!(matches!(resource.source, ResourceSource::Local(path) if path == Path::new("./items.csv")));
+                assert_eq!(columns, ["name"]);
+            }
+            other => panic!("Expected ordered DataList node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_task_list_directive() {
+        let node = parse_directive(r#"::list ./tasks.csv --columns "title, done" --as tasklist"#, 1).unwrap();
+
+        match node {
+            Some(DarkMatterNode::DataList {
+                source: ListSource::External { columns, .. },
+                ordered: false,
+                task_list: true,
+            }) => assert_eq!(columns, ["title", "done"]),
+            other => panic!("Expected task list DataList node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_directive_rejects_invalid_arguments() {
+        for line in [
+            "::list --ordered",
+            "::list ./a.csv ./b.csv",
+            "::list ./items.csv --column",
+            "::list ./items.csv --as table",
+            "::list ./tasks.csv --column title --as tasklist",
+            "::list ./items.csv --columns \"a,,b\"",
+            "::list ./items.csv --reverse",
+        ] {
+            assert!(
+                matches!(parse_directive(line, 4), Err(ParseError::InvalidDirective { line: 4, .. })),
+                "{} should be rejected",
+                line
+            );
+        }
+    }
 }
@@ -1,56 +1,115 @@
-use crate::error::ParseError;
-use crate::types::Frontmatter;
-use yaml_rust2::{Yaml, YamlLoader};
+use crate::error::{ParseError, Span};
+use crate::parse::resource::parse_resource;
+use crate::types::{Frontmatter, ListExpansion, Resource};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+use toml_edit::{DocumentMut, Item, Value as TomlValue};
+use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
+
+/// The fence a frontmatter block is written between, which picks its format
+#[derive(Debug, Clone, Copy)]
+enum Fence {
+    /// `---`, YAML
+    Yaml,
+    /// `+++`, TOML
+    Toml,
+    /// `;;;`, JSON
+    Json,
+}
+
+impl Fence {
+    /// The fence `text` opens with, if any
+    fn opening(text: &[u8]) -> Option<Self> {
+        if text.starts_with(b"---") {
+            Some(Self::Yaml)
+        } else if text.starts_with(b"+++") {
+            Some(Self::Toml)
+        } else if text.starts_with(b";;;") {
+            Some(Self::Json)
+        } else {
+            None
+        }
+    }
+
+    fn delimiter(self) -> &'static str {
+        match self {
+            Self::Yaml => "---",
+            Self::Toml => "+++",
+            Self::Json => ";;;",
+        }
+    }
+
+    fn parse(self, text: &str) -> Result<Frontmatter, ParseError> {
+        match self {
+            Self::Yaml => parse_yaml(text),
+            Self::Toml => parse_toml(text),
+            Self::Json => parse_json(text),
+        }
+    }
+}
 
-/// Extract YAML frontmatter from markdown content
+/// Extract frontmatter from markdown content
 ///
 /// Returns (frontmatter, body) tuple where frontmatter is parsed
-/// and body is the content after frontmatter delimiter
+/// and body is the content after frontmatter delimiter. Frontmatter between
+/// `---` lines is YAML, between `+++` lines TOML and between `;;;` lines a
+/// JSON object. A document is only read as having frontmatter when it opens
+/// with one of these fences, so a body that starts with `{` is left alone. A
+/// leading UTF-8 byte order mark is dropped. A YAML error's span is in the
+/// coordinates of `content`.
 pub fn extract_frontmatter(content: &str) -> Result<(Frontmatter, &str), ParseError> {
+    let source = content;
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+
     // Check for frontmatter delimiter
-    if !content.starts_with("---") {
+    let Some(fence) = Fence::opening(content.as_bytes()) else {
         return Ok((Frontmatter::default(), content));
-    }
+    };
+    let delimiter = fence.delimiter();
 
     // Find the closing delimiter
     let after_first_delimiter = &content[3..];
 
-    // Find end of line after first ---
-    let first_newline = after_first_delimiter
-        .find('\n')
-        .ok_or_else(|| ParseError::InvalidFrontmatter("No newline after opening ---".into()))?;
-
-    let yaml_start = 3 + first_newline + 1;
-    let remaining = &content[yaml_start..];
-
-    // Check if closing --- is at the very start (empty frontmatter)
-    if let Some(body) = remaining.strip_prefix("---\n") {
+    // Find end of line after the opening delimiter
+    let first_newline = after_first_delimiter.find('\n').ok_or_else(|| {
+        ParseError::InvalidFrontmatter(format!("No newline after opening {}", delimiter))
+    })?;
+
+    let fenced_start = 3 + first_newline + 1;
+    let remaining = &content[fenced_start..];
+    let parse = |fenced: &str| {
+        fence
+            .parse(fenced)
+            .map_err(|e| e.relocate(source, source.len() - content.len() + fenced_start))
+    };
+
+    // Check if the closing delimiter is at the very start (empty frontmatter)
+    if let Some(body) = remaining.strip_prefix(delimiter).and_then(|rest| rest.strip_prefix('\n')) {
         return Ok((Frontmatter::default(), body));
     }
 
-    if remaining.starts_with("---") && remaining.len() == 3 {
+    if remaining == delimiter {
         // Empty frontmatter at end of file
         return Ok((Frontmatter::default(), ""));
     }
 
-    // Find closing ---
-    if let Some(end_pos) = remaining.find("\n---\n") {
-        let yaml_content = &remaining[..end_pos];
-        let body = &remaining[end_pos + 5..]; // Skip \n---\n
-
-        // Parse YAML
-        let frontmatter = parse_yaml(yaml_content)?;
-
-        Ok((frontmatter, body))
-    } else if let Some(end_pos) = remaining.find("\n---") {
-        // Check if --- is at end of file
-        let potential_body = &remaining[end_pos + 4..];
+    // Find the closing delimiter
+    let closing_line = format!("\n{}\n", delimiter);
+    let closing = format!("\n{}", delimiter);
+    if let Some(end_pos) = remaining.find(&closing_line) {
+        let fenced = &remaining[..end_pos];
+        let body = &remaining[end_pos + closing_line.len()..];
+
+        Ok((parse(fenced)?, body))
+    } else if let Some(end_pos) = remaining.find(&closing) {
+        // Check if the delimiter is at end of file
+        let potential_body = &remaining[end_pos + closing.len()..];
         if potential_body.trim().is_empty() || potential_body.starts_with('\n') {
-            let yaml_content = &remaining[..end_pos];
+            let fenced = &remaining[..end_pos];
             let body = potential_body.trim_start_matches('\n');
 
-            let frontmatter = parse_yaml(yaml_content)?;
-            Ok((frontmatter, body))
+            Ok((parse(fenced)?, body))
         } else {
             // Not a valid closing delimiter
             Ok((Frontmatter::default(), content))
@@ -61,10 +120,74 @@ pub fn extract_frontmatter(content: &str) -> Result<(Frontmatter, &str), ParseEr
     }
 }
 
+/// Byte order mark some editors put at the start of UTF-8 files
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Extract frontmatter from the start of a stream
+///
+/// Reads only up to the closing `---` (or `+++`, or `;;;`) line and returns the parsed
+/// frontmatter along with a reader positioned at the start of the body, so
+/// the body is never buffered here. Follows [`extract_frontmatter`]: without
+/// an opening delimiter, or when no closing line is found, the frontmatter is
+/// empty and the returned reader yields the whole stream. A YAML error's span
+/// is in the coordinates of the stream after any byte order mark.
+pub async fn extract_frontmatter_async<R>(
+    mut reader: R,
+) -> Result<(Frontmatter, impl AsyncRead + Unpin), ParseError>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut consumed = Vec::new();
+    let read_error = |e: std::io::Error| ParseError::ReadFailed(e.to_string());
+
+    reader.read_until(b'\n', &mut consumed).await.map_err(read_error)?;
+    if consumed.starts_with(UTF8_BOM.as_bytes()) {
+        consumed.drain(..UTF8_BOM.len());
+    }
+    let Some(fence) = Fence::opening(&consumed) else {
+        return Ok((Frontmatter::default(), Cursor::new(consumed).chain(reader)));
+    };
+    if !consumed.ends_with(b"\n") {
+        return Err(ParseError::InvalidFrontmatter(format!(
+            "No newline after opening {}",
+            fence.delimiter()
+        )));
+    }
+
+    let fenced_start = consumed.len();
+    loop {
+        let line_start = consumed.len();
+        let read = reader.read_until(b'\n', &mut consumed).await.map_err(read_error)?;
+        if read == 0 {
+            // No closing delimiter: treat the whole stream as body
+            return Ok((Frontmatter::default(), Cursor::new(consumed).chain(reader)));
+        }
+
+        let line = &consumed[line_start..];
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        if line.strip_suffix(b"\r").unwrap_or(line) == fence.delimiter().as_bytes() {
+            let fenced = std::str::from_utf8(&consumed[fenced_start..line_start])
+                .map_err(|e| ParseError::InvalidFrontmatter(e.to_string()))?;
+            let frontmatter = fence.parse(fenced).map_err(|e| match std::str::from_utf8(&consumed[..line_start]) {
+                Ok(source) => e.relocate(source, fenced_start),
+                Err(_) => e,
+            })?;
+            return Ok((frontmatter, Cursor::new(Vec::new()).chain(reader)));
+        }
+    }
+}
+
 /// Parse YAML string into Frontmatter struct
 fn parse_yaml(yaml_str: &str) -> Result<Frontmatter, ParseError> {
-    let docs = YamlLoader::load_from_str(yaml_str)
-        .map_err(|e| ParseError::YamlParse(e.to_string()))?;
+    let docs = YamlLoader::load_from_str(yaml_str).map_err(|e| {
+        // The marker counts characters, spans count bytes
+        let marker = e.marker();
+        let offset = yaml_str.char_indices().nth(marker.index()).map_or(yaml_str.len(), |(offset, _)| offset);
+        ParseError::YamlParse {
+            message: e.to_string(),
+            span: Some(Span::locate(yaml_str, offset, 0)),
+        }
+    })?;
 
     if docs.is_empty() {
         return Ok(Frontmatter::default());
@@ -73,87 +196,222 @@ fn parse_yaml(yaml_str: &str) -> Result<Frontmatter, ParseError> {
     let doc = &docs[0];
 
     if let Yaml::Hash(hash) = doc {
-        let mut frontmatter = Frontmatter::default();
-
-        for (key, value) in hash {
-            if let Yaml::String(key_str) = key {
-                match key_str.as_str() {
-                    "list_expansion" => {
-                        if let Some(val_str) = value.as_str() {
-                            frontmatter.list_expansion = match val_str {
-                                "expanded" => Some(crate::types::ListExpansion::Expanded),
-                                "collapsed" => Some(crate::types::ListExpansion::Collapsed),
-                                "none" => Some(crate::types::ListExpansion::None),
-                                _ => None,
-                            };
-                        }
-                    }
-                    "replace" => {
-                        if let Yaml::Hash(replace_hash) = value {
-                            let mut replace_map = std::collections::HashMap::new();
-                            for (k, v) in replace_hash {
-                                if let (Yaml::String(k_str), Yaml::String(v_str)) = (k, v) {
-                                    replace_map.insert(k_str.clone(), v_str.clone());
-                                }
-                            }
-                            if !replace_map.is_empty() {
-                                frontmatter.replace = Some(replace_map);
-                            }
-                        }
-                    }
-                    "summarize_model" => {
-                        if let Some(val_str) = value.as_str() {
-                            frontmatter.summarize_model = Some(val_str.to_string());
-                        }
-                    }
-                    "consolidate_model" => {
-                        if let Some(val_str) = value.as_str() {
-                            frontmatter.consolidate_model = Some(val_str.to_string());
-                        }
+        // Values that can't be represented as JSON are skipped
+        let values = hash.iter().filter_map(|(key, value)| match key {
+            Yaml::String(key) => Some((key.clone(), yaml_to_json(value).ok()?)),
+            _ => None,
+        });
+
+        Ok(frontmatter_from_values(values))
+    } else {
+        Ok(Frontmatter::default())
+    }
+}
+
+/// Parse TOML string into Frontmatter struct
+///
+/// Dates and times become strings in RFC 3339 form.
+fn parse_toml(toml_str: &str) -> Result<Frontmatter, ParseError> {
+    let document = toml_str
+        .parse::<DocumentMut>()
+        .map_err(|e| ParseError::InvalidFrontmatter(format!("Invalid TOML frontmatter: {}", e)))?;
+
+    let values = document
+        .iter()
+        .filter_map(|(key, item)| Some((key.to_string(), toml_item_to_json(item)?)));
+
+    Ok(frontmatter_from_values(values))
+}
+
+/// Parse a JSON object into Frontmatter struct
+///
+/// Whitespace alone is empty frontmatter; anything other than an object is
+/// an error.
+fn parse_json(json_str: &str) -> Result<Frontmatter, ParseError> {
+    if json_str.trim().is_empty() {
+        return Ok(Frontmatter::default());
+    }
+
+    match serde_json::from_str(json_str) {
+        Ok(serde_json::Value::Object(values)) => Ok(frontmatter_from_values(values)),
+        Ok(_) => Err(ParseError::InvalidFrontmatter(
+            "JSON frontmatter must be an object".to_string(),
+        )),
+        Err(e) => Err(ParseError::InvalidFrontmatter(format!("Invalid JSON frontmatter: {}", e))),
+    }
+}
+
+/// The documents a frontmatter's `imports` map names, by namespace
+///
+/// `imports: { versions: ./versions.md }` makes the frontmatter of
+/// `versions.md` available as `{{versions.NAME}}`. Namespaces are names of
+/// letters, digits and `_` that don't start with a digit, and `env` is taken
+/// by environment variables. Imports are returned in namespace order.
+pub(crate) fn parse_imports(frontmatter: &Frontmatter) -> Result<Vec<(String, Resource)>, ParseError> {
+    let Some(imports) = frontmatter.get_typed::<BTreeMap<String, String>>("imports") else {
+        return Ok(Vec::new());
+    };
+    imports?
+        .into_iter()
+        .map(|(namespace, path)| {
+            let valid = namespace.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && namespace != "env";
+            if !valid {
+                return Err(ParseError::InvalidFrontmatter(format!(
+                    "import namespace '{}' must be a name of letters, digits and '_', other than 'env'",
+                    namespace
+                )));
+            }
+            Ok((namespace, parse_resource(&path)?))
+        })
+        .collect()
+}
+
+/// Build frontmatter from its top-level values, whatever format they were
+/// written in
+///
+/// Reserved properties with a value of the wrong shape are ignored.
+fn frontmatter_from_values(values: impl IntoIterator<Item = (String, serde_json::Value)>) -> Frontmatter {
+    let mut frontmatter = Frontmatter::default();
+
+    for (key, value) in values {
+        match key.as_str() {
+            "list_expansion" => {
+                if let Some(val_str) = value.as_str() {
+                    frontmatter.list_expansion = match val_str {
+                        "expanded" => Some(crate::types::ListExpansion::Expanded),
+                        "collapsed" => Some(crate::types::ListExpansion::Collapsed),
+                        "none" => Some(crate::types::ListExpansion::None),
+                        _ => None,
+                    };
+                }
+            }
+            "replace" => {
+                if let Some(replace_object) = value.as_object() {
+                    let replace_map: std::collections::HashMap<String, String> = replace_object
+                        .iter()
+                        .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                        .collect();
+                    if !replace_map.is_empty() {
+                        frontmatter.replace = Some(replace_map);
                     }
-                    "breakpoints" => {
-                        if let Yaml::Hash(bp_hash) = value {
-                            let mut breakpoints = crate::types::Breakpoints {
-                                xs: None,
-                                sm: None,
-                                md: None,
-                                lg: None,
-                                xl: None,
-                                xxl: None,
-                            };
-
-                            for (k, v) in bp_hash {
-                                if let Yaml::String(k_str) = k {
-                                    if let Some(v_int) = v.as_i64() {
-                                        let v_u32 = v_int as u32;
-                                        match k_str.as_str() {
-                                            "xs" => breakpoints.xs = Some(v_u32),
-                                            "sm" => breakpoints.sm = Some(v_u32),
-                                            "md" => breakpoints.md = Some(v_u32),
-                                            "lg" => breakpoints.lg = Some(v_u32),
-                                            "xl" => breakpoints.xl = Some(v_u32),
-                                            "xxl" => breakpoints.xxl = Some(v_u32),
-                                            _ => {}
-                                        }
-                                    }
-                                }
+                }
+            }
+            "summarize_model" => {
+                if let Some(val_str) = value.as_str() {
+                    frontmatter.summarize_model = Some(val_str.to_string());
+                }
+            }
+            "consolidate_model" => {
+                if let Some(val_str) = value.as_str() {
+                    frontmatter.consolidate_model = Some(val_str.to_string());
+                }
+            }
+            "breakpoints" => {
+                if let Some(bp_object) = value.as_object() {
+                    let mut breakpoints = crate::types::Breakpoints {
+                        xs: None,
+                        sm: None,
+                        md: None,
+                        lg: None,
+                        xl: None,
+                        xxl: None,
+                    };
+
+                    for (k, v) in bp_object {
+                        if let Some(v_int) = v.as_i64() {
+                            let v_u32 = v_int as u32;
+                            match k.as_str() {
+                                "xs" => breakpoints.xs = Some(v_u32),
+                                "sm" => breakpoints.sm = Some(v_u32),
+                                "md" => breakpoints.md = Some(v_u32),
+                                "lg" => breakpoints.lg = Some(v_u32),
+                                "xl" => breakpoints.xl = Some(v_u32),
+                                "xxl" => breakpoints.xxl = Some(v_u32),
+                                _ => {}
                             }
-                            frontmatter.breakpoints = Some(breakpoints);
-                        }
-                    }
-                    _ => {
-                        // Custom field - convert to serde_json::Value
-                        if let Ok(json_value) = yaml_to_json(value) {
-                            frontmatter.custom.insert(key_str.clone(), json_value);
                         }
                     }
+                    frontmatter.breakpoints = Some(breakpoints);
                 }
             }
+            _ => {
+                // Custom field
+                frontmatter.custom.insert(key, value);
+            }
         }
+    }
 
-        Ok(frontmatter)
-    } else {
-        Ok(Frontmatter::default())
+    frontmatter
+}
+
+/// Write frontmatter as a YAML document, starting with its `---` line, that
+/// reads back as the same frontmatter
+///
+/// Keys are written in order, and reserved properties in the form
+/// [`frontmatter_from_values`] reads them.
+pub(crate) fn frontmatter_to_yaml(frontmatter: &Frontmatter) -> String {
+    let mut values: BTreeMap<String, serde_json::Value> =
+        frontmatter.custom.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+    if let Some(expansion) = frontmatter.list_expansion {
+        let name = match expansion {
+            ListExpansion::Expanded => "expanded",
+            ListExpansion::Collapsed => "collapsed",
+            ListExpansion::None => "none",
+        };
+        values.insert("list_expansion".to_string(), name.into());
+    }
+    if let Some(replace) = &frontmatter.replace {
+        values.insert("replace".to_string(), serde_json::json!(replace));
+    }
+    if let Some(model) = &frontmatter.summarize_model {
+        values.insert("summarize_model".to_string(), model.as_str().into());
+    }
+    if let Some(model) = &frontmatter.consolidate_model {
+        values.insert("consolidate_model".to_string(), model.as_str().into());
+    }
+    if let Some(breakpoints) = &frontmatter.breakpoints {
+        let widths = [
+            ("xs", breakpoints.xs),
+            ("sm", breakpoints.sm),
+            ("md", breakpoints.md),
+            ("lg", breakpoints.lg),
+            ("xl", breakpoints.xl),
+            ("xxl", breakpoints.xxl),
+        ];
+        let set: serde_json::Map<String, serde_json::Value> = widths
+            .into_iter()
+            .filter_map(|(name, width)| Some((name.to_string(), width?.into())))
+            .collect();
+        values.insert("breakpoints".to_string(), set.into());
+    }
+
+    let hash = values.into_iter().map(|(key, value)| (Yaml::String(key), json_to_yaml(&value))).collect();
+    let mut yaml = String::new();
+    YamlEmitter::new(&mut yaml)
+        .dump(&Yaml::Hash(hash))
+        .expect("writing YAML to a String can't fail");
+    yaml
+}
+
+/// Convert a serde_json::Value to YAML
+///
+/// Numbers that aren't integers are written as YAML reals, which
+/// [`yaml_to_json`] reads back as strings.
+fn json_to_yaml(value: &serde_json::Value) -> Yaml {
+    match value {
+        serde_json::Value::Null => Yaml::Null,
+        serde_json::Value::Bool(b) => Yaml::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        serde_json::Value::String(s) => Yaml::String(s.clone()),
+        serde_json::Value::Array(items) => Yaml::Array(items.iter().map(json_to_yaml).collect()),
+        serde_json::Value::Object(map) => Yaml::Hash(
+            map.iter().map(|(key, value)| (Yaml::String(key.clone()), json_to_yaml(value))).collect(),
+        ),
     }
 }
 
@@ -177,7 +435,60 @@ fn yaml_to_json(yaml: &Yaml) -> Result<serde_json::Value, ParseError> {
             Ok(serde_json::Value::Object(map))
         }
         Yaml::Null => Ok(serde_json::Value::Null),
-        _ => Err(ParseError::YamlParse("Unsupported YAML type".into())),
+        _ => Err(ParseError::YamlParse {
+            message: "Unsupported YAML type".into(),
+            span: None,
+        }),
+    }
+}
+
+/// Convert a TOML item to serde_json::Value
+///
+/// Returns `None` for an empty item.
+fn toml_item_to_json(item: &Item) -> Option<serde_json::Value> {
+    match item {
+        Item::None => None,
+        Item::Value(value) => Some(toml_to_json(value)),
+        Item::Table(table) => Some(serde_json::Value::Object(
+            table
+                .iter()
+                .filter_map(|(key, item)| Some((key.to_string(), toml_item_to_json(item)?)))
+                .collect(),
+        )),
+        Item::ArrayOfTables(tables) => Some(serde_json::Value::Array(
+            tables
+                .iter()
+                .map(|table| {
+                    serde_json::Value::Object(
+                        table
+                            .iter()
+                            .filter_map(|(key, item)| Some((key.to_string(), toml_item_to_json(item)?)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Convert a TOML value to serde_json::Value
+fn toml_to_json(value: &TomlValue) -> serde_json::Value {
+    match value {
+        TomlValue::String(s) => serde_json::Value::String(s.value().clone()),
+        TomlValue::Integer(i) => serde_json::Value::Number((*i.value()).into()),
+        // JSON has no NaN or infinities
+        TomlValue::Float(f) => serde_json::Number::from_f64(*f.value())
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        TomlValue::Boolean(b) => serde_json::Value::Bool(*b.value()),
+        TomlValue::Datetime(dt) => serde_json::Value::String(dt.value().to_string()),
+        TomlValue::Array(arr) => serde_json::Value::Array(arr.iter().map(toml_to_json).collect()),
+        TomlValue::InlineTable(table) => serde_json::Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_to_json(value)))
+                .collect(),
+        ),
     }
 }
 
@@ -258,4 +569,134 @@ Content"#;
         assert_eq!(fm.summarize_model, Some("gpt-4".to_string()));
         assert_eq!(fm.consolidate_model, Some("claude-3".to_string()));
     }
+
+    async fn extract_async(content: &str) -> (Frontmatter, String) {
+        let (frontmatter, mut body) = extract_frontmatter_async(content.as_bytes()).await.unwrap();
+        let mut rest = String::new();
+        body.read_to_string(&mut rest).await.unwrap();
+        (frontmatter, rest)
+    }
+
+    #[tokio::test]
+    async fn test_async_matches_sync_extraction() {
+        let cases = [
+            "# No frontmatter\n\nBody",
+            "---\n---\nBody",
+            "---\ntitle: Streamed\nlist_expansion: expanded\n---\n# Heading\n\nBody\n",
+            "---\ntitle: Unclosed\n\nBody",
+            ";;;\n{\"title\": \"Streamed\"}\n;;;\nBody\n",
+            "{\"title\": \"Not frontmatter\"}\nBody",
+        ];
+
+        for content in cases {
+            let (expected_fm, expected_body) = extract_frontmatter(content).unwrap();
+            let (frontmatter, body) = extract_async(content).await;
+
+            assert_eq!(body, expected_body, "body of {:?}", content);
+            assert_eq!(frontmatter.custom, expected_fm.custom, "frontmatter of {:?}", content);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_byte_order_mark_is_dropped() {
+        let content = include_str!("../../tests/fixtures/bom_frontmatter.md");
+        assert!(content.starts_with('\u{feff}'));
+
+        let (frontmatter, body) = extract_frontmatter(content).unwrap();
+        assert_eq!(frontmatter.get_string("title"), Some("Saved With BOM"));
+        assert!(body.starts_with("# {{title}}"));
+
+        let (frontmatter, streamed_body) = extract_async(content).await;
+        assert_eq!(frontmatter.get_string("title"), Some("Saved With BOM"));
+        assert_eq!(streamed_body, body);
+    }
+
+    #[tokio::test]
+    async fn test_async_stops_at_closing_delimiter() {
+        let (frontmatter, body) = extract_async("---\r\ntitle: CRLF\r\n---\r\nBody\r\n---\r\nmore").await;
+
+        assert_eq!(frontmatter.get_string("title"), Some("CRLF"));
+        assert_eq!(body, "Body\r\n---\r\nmore");
+    }
+
+    #[test]
+    fn test_toml_frontmatter_matches_yaml() {
+        let yaml = "---\ntitle: Release Notes\nversion: 3\ntags:\n  - rust\n  - docs\nsummarize_model: gpt-4\nreplace:\n  foo: bar\n---\n# Notes\n";
+        let toml = "+++\ntitle = \"Release Notes\"\nversion = 3\ntags = [\"rust\", \"docs\"]\nsummarize_model = \"gpt-4\"\n\n[replace]\nfoo = \"bar\"\n+++\n# Notes\n";
+
+        let (yaml_fm, yaml_body) = extract_frontmatter(yaml).unwrap();
+        let (toml_fm, toml_body) = extract_frontmatter(toml).unwrap();
+
+        assert_eq!(toml_body, yaml_body);
+        assert_eq!(toml_fm.custom, yaml_fm.custom);
+        assert_eq!(toml_fm.get_string("title"), Some("Release Notes"));
+        assert_eq!(toml_fm.get_i64("version"), Some(3));
+        assert_eq!(toml_fm.get_string_array("tags"), Some(vec!["rust".to_string(), "docs".to_string()]));
+        assert_eq!(toml_fm.summarize_model, yaml_fm.summarize_model);
+        assert_eq!(toml_fm.replace, yaml_fm.replace);
+    }
+
+    #[tokio::test]
+    async fn test_async_toml_frontmatter() {
+        let content = "+++\r\ntitle = \"Streamed\"\r\n+++\r\nBody\r\n---\r\nmore";
+        let (frontmatter, body) = extract_async(content).await;
+
+        assert_eq!(frontmatter.get_string("title"), Some("Streamed"));
+        assert_eq!(body, "Body\r\n---\r\nmore");
+    }
+
+    #[test]
+    fn test_invalid_toml_frontmatter() {
+        match extract_frontmatter("+++\ntitle = \n+++\nBody") {
+            Err(ParseError::InvalidFrontmatter(message)) => assert!(message.contains("TOML"), "{}", message),
+            other => panic!("Expected InvalidFrontmatter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_frontmatter_matches_yaml() {
+        let yaml = "---\ntitle: Generated\ncount: 2\ntags:\n  - a\n  - b\nlist_expansion: collapsed\n---\nBody\n";
+        let json = ";;;\n{\n  \"title\": \"Generated\",\n  \"count\": 2,\n  \"tags\": [\"a\", \"b\"],\n  \"list_expansion\": \"collapsed\"\n}\n;;;\nBody\n";
+
+        let (yaml_fm, yaml_body) = extract_frontmatter(yaml).unwrap();
+        let (json_fm, json_body) = extract_frontmatter(json).unwrap();
+
+        assert_eq!(json_body, yaml_body);
+        assert_eq!(json_fm.custom, yaml_fm.custom);
+        assert_eq!(json_fm.get_string("title"), Some("Generated"));
+        assert!(matches!(json_fm.list_expansion, Some(crate::types::ListExpansion::Collapsed)));
+    }
+
+    #[test]
+    fn test_body_starting_with_brace_has_no_frontmatter() {
+        let content = "{\"title\": \"not frontmatter\"}\n\nSome text";
+        let (frontmatter, body) = extract_frontmatter(content).unwrap();
+
+        assert!(frontmatter.custom.is_empty());
+        assert_eq!(body, content);
+    }
+
+    #[test]
+    fn test_invalid_json_frontmatter() {
+        for content in [";;;\n{\"title\": }\n;;;\nBody", ";;;\n[1, 2]\n;;;\nBody"] {
+            match extract_frontmatter(content) {
+                Err(ParseError::InvalidFrontmatter(message)) => assert!(message.contains("JSON"), "{}", message),
+                other => panic!("Expected InvalidFrontmatter, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_yaml_error_span_points_into_source() {
+        let content = "\u{feff}---\ntitle: Guide\ntags: [rust\n---\nBody";
+
+        match extract_frontmatter(content) {
+            Err(ParseError::YamlParse { span: Some(span), .. }) => {
+                assert!(span.line >= 3, "{:?}", span);
+                assert!(span.offset > content.find("tags").unwrap(), "{:?}", span);
+                assert_eq!(span, Span::locate(content, span.offset, 0));
+            }
+            other => panic!("Expected YamlParse with a span, got {:?}", other),
+        }
+    }
 }
@@ -1,32 +1,160 @@
-use crate::error::ParseError;
-use crate::types::{DarkMatterNode, MarkdownContent};
-use crate::parse::darkmatter::{parse_directive, process_inline_syntax};
+use crate::error::{ParseError, ParseWarning, ParseWarningCode, Span};
+use crate::types::{Breakpoint, DarkMatterNode, MarkdownContent, TerminalShell};
+use crate::parse::darkmatter::{
+    parse_aspect_ratio, parse_columns, parse_directive, parse_footnote, read_directive, Directive, parse_terminal, parse_terminal_line,
+    process_inline_syntax, COLUMN_BREAK, DETAILS, END_ASPECT_RATIO, END_BLOCK, END_TERMINAL, MATH_DELIMITER, MERMAID,
+    SUMMARY,
+};
 use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::collections::HashMap;
 
 /// Parse markdown content with GFM extensions
 pub fn parse_markdown(content: &str) -> Result<Vec<DarkMatterNode>, ParseError> {
+    parse_markdown_with_warnings(content).map(|(nodes, _)| nodes)
+}
+
+/// Parse markdown content, also returning the problems that didn't stop it
+/// from parsing
+///
+/// Warning spans are in the coordinates of `content`.
+pub(crate) fn parse_markdown_with_warnings(
+    content: &str,
+) -> Result<(Vec<DarkMatterNode>, Vec<ParseWarning>), ParseError> {
+    let lines: Vec<(usize, &str)> = (1..).zip(content.lines()).collect();
+    let mut warnings = Vec::new();
+    let nodes = parse_lines(&lines, &mut warnings)
+        .and_then(|nodes| check_footnote_ids(&lines).map(|_| nodes))
+        .map_err(|e| locate_error(e, content))?;
+
+    for warning in &mut warnings {
+        if let Some(span) = warning.span {
+            warning.span = locate_on_line(content, span.line, Some(&span));
+        }
+    }
+    Ok((nodes, warnings))
+}
+
+/// Give an error raised on a line the span of that line's text in `content`
+///
+/// An error that already has a span, relative to the trimmed line it was
+/// raised for, keeps it and is moved into the coordinates of `content`.
+fn locate_error(error: ParseError, content: &str) -> ParseError {
+    let Some(line_num) = error.span().map(|span| span.line).or(error.line()) else {
+        return error;
+    };
+    match locate_on_line(content, line_num, error.span()) {
+        Some(span) => error.with_span(span),
+        None => error,
+    }
+}
+
+/// The span in `content` of `span`, given relative to the trimmed text of
+/// line `line_num`, or of that whole trimmed line
+fn locate_on_line(content: &str, line_num: usize, span: Option<&Span>) -> Option<Span> {
+    if line_num == 0 {
+        return None;
+    }
+
+    let mut offset = 0;
+    for line in content.split_inclusive('\n').take(line_num - 1) {
+        offset += line.len();
+    }
+    let line = content[offset..].lines().next()?;
+    let start = offset + line.len() - line.trim_start().len();
+
+    Some(match span {
+        Some(span) => Span::locate(content, start + span.offset, span.length),
+        None => Span::locate(content, start, line.trim().len()),
+    })
+}
+
+/// Reject a `::footnote` reusing the id of an earlier one
+///
+/// Footnotes are listed per document, so ids must be unique across the whole
+/// document, including footnotes inside blocks.
+fn check_footnote_ids(lines: &[(usize, &str)]) -> Result<(), ParseError> {
+    let mut code_blocks = CodeBlocks::new();
+    let mut ids = HashMap::new();
+    for &(line_num, line) in lines {
+        if code_blocks.is_code(line) {
+            continue;
+        }
+        let Some((id, _)) = parse_footnote(line, line_num)? else {
+            continue;
+        };
+        if let Some(first) = ids.insert(id.clone(), line_num) {
+            return Err(ParseError::InvalidDirective {
+                line: line_num,
+                directive: format!("Footnote [{}] is already defined on line {}", id, first),
+                span: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Parse numbered lines, assembling block directives from their contents
+///
+/// Lines inside code blocks are always markdown, so documentation can show
+/// directives. Outside of one, `\::` keeps a line from being a directive and
+/// renders as a literal `::`, and `\$$` keeps one from opening display math.
+/// Lines starting with `::` that aren't directives are dropped with a warning
+/// whose span is relative to the trimmed line.
+fn parse_lines(lines: &[(usize, &str)], warnings: &mut Vec<ParseWarning>) -> Result<Vec<DarkMatterNode>, ParseError> {
     // Split content into lines and process directives separately
     let mut nodes = Vec::new();
     let mut markdown_buffer = String::new();
-    let mut line_num = 1;
-
-    for line in content.lines() {
+    let mut code_blocks = CodeBlocks::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (line_num, line) = lines[index];
         let trimmed = line.trim();
+        let in_code = code_blocks.is_code(line);
+
+        // Display math owns every line up to its closing `$$`
+        if !in_code {
+            if let Some((source, length)) = display_math(&lines[index..])? {
+                flush_markdown(&mut nodes, &mut markdown_buffer);
+                nodes.push(DarkMatterNode::Math { source, display: true });
+                index += length;
+                continue;
+            }
+        }
+        index += 1;
 
         // Check if this is a DarkMatter directive
-        if trimmed.starts_with("::") {
+        if !in_code && trimmed.starts_with("::") {
             // Flush any accumulated markdown first
-            if !markdown_buffer.is_empty() {
-                nodes.push(DarkMatterNode::Markdown(MarkdownContent {
-                    raw: markdown_buffer.clone(),
-                    frontmatter: None,
-                }));
-                markdown_buffer.clear();
+            flush_markdown(&mut nodes, &mut markdown_buffer);
+
+            // Block directives own every line up to their closing directive
+            if let Some(block) = Block::open(trimmed, line_num)? {
+                let body = &lines[index..];
+                let (separators, end) = find_block_end(body, &block, trimmed, line_num)?;
+                nodes.push(block.build(body, &separators, end, warnings)?);
+                index += end + 1;
+                continue;
+            }
+            if let Some(opener) = Block::opener_of(trimmed) {
+                return Err(ParseError::InvalidDirective {
+                    line: line_num,
+                    directive: format!("{} without a matching {}", trimmed, opener),
+                    span: None,
+                });
             }
 
             // Parse the directive
-            if let Some(node) = parse_directive(trimmed, line_num)? {
-                nodes.push(node);
+            match read_directive(trimmed, line_num)? {
+                Directive::Node(node) => nodes.push(*node),
+                Directive::Marker => {}
+                Directive::Unknown => warnings.push(ParseWarning {
+                    span: Some(Span { offset: 0, length: trimmed.len(), line: line_num, column: 1 }),
+                    message: format!(
+                        "Unknown directive {}",
+                        trimmed.split_whitespace().next().unwrap_or(trimmed)
+                    ),
+                    code: ParseWarningCode::UnknownDirective,
+                }),
             }
         } else {
             // Accumulate markdown content
@@ -35,19 +163,340 @@ pub fn parse_markdown(content: &str) -> Result<Vec<DarkMatterNode>, ParseError>
             }
             markdown_buffer.push_str(line);
         }
-
-        line_num += 1;
     }
 
     // Flush any remaining markdown
-    if !markdown_buffer.is_empty() {
+    flush_markdown(&mut nodes, &mut markdown_buffer);
+
+    Ok(nodes)
+}
+
+/// Move the markdown accumulated so far into a node
+fn flush_markdown(nodes: &mut Vec<DarkMatterNode>, buffer: &mut String) {
+    if !buffer.is_empty() {
         nodes.push(DarkMatterNode::Markdown(MarkdownContent {
-            raw: markdown_buffer,
+            raw: std::mem::take(buffer),
             frontmatter: None,
         }));
     }
+}
 
-    Ok(nodes)
+/// Read a display math block opening at the first of `lines`
+///
+/// Returns the formula and the number of lines the block spans, or `None`
+/// when the line doesn't start with `$$`. The delimiters may share lines with
+/// the formula, so `$$ e^{i\pi} = -1 $$` is a block of its own.
+fn display_math(lines: &[(usize, &str)]) -> Result<Option<(String, usize)>, ParseError> {
+    let (line_num, first) = lines[0];
+    let Some(rest) = first.trim().strip_prefix(MATH_DELIMITER) else {
+        return Ok(None);
+    };
+    if let Some(formula) = rest.strip_suffix(MATH_DELIMITER) {
+        return Ok(Some((formula.trim().to_string(), 1)));
+    }
+
+    let mut formula = vec![rest];
+    for (offset, &(_, line)) in lines.iter().enumerate().skip(1) {
+        if let Some(last) = line.trim_end().strip_suffix(MATH_DELIMITER) {
+            formula.push(last);
+            return Ok(Some((formula.join("\n").trim().to_string(), offset + 1)));
+        }
+        formula.push(line);
+    }
+
+    Err(ParseError::InvalidDirective {
+        line: line_num,
+        directive: format!("Missing closing {} for display math", MATH_DELIMITER),
+        span: None,
+    })
+}
+
+/// A block directive: an opening line, the lines it owns and a closing line
+///
+/// Disclosures (`::summary` ... `::details` ... `::end`), columns
+/// (`::columns` ... `::break` ... `::end`) and Mermaid diagrams (`::mermaid`
+/// ... `::end`) close with `::end`, aspect ratio containers with
+/// `::endaspect-ratio` and terminal sessions with `::endterminal`. Blocks
+/// nest: a closing line belongs to the innermost open block. The lines of
+/// Mermaid and terminal blocks are raw text, so nothing but their closing
+/// line is a directive inside them.
+enum Block {
+    AspectRatio { numerator: u32, denominator: u32 },
+    Disclosure,
+    Columns { breakpoints: HashMap<Breakpoint, u32> },
+    Mermaid,
+    Terminal { shell: TerminalShell },
+}
+
+impl Block {
+    /// Parse the line opening a block, or `None` for any other line
+    fn open(trimmed: &str, line_num: usize) -> Result<Option<Self>, ParseError> {
+        if trimmed == SUMMARY {
+            return Ok(Some(Block::Disclosure));
+        }
+        if trimmed == MERMAID {
+            return Ok(Some(Block::Mermaid));
+        }
+        if let Some(shell) = parse_terminal(trimmed, line_num)? {
+            return Ok(Some(Block::Terminal { shell }));
+        }
+        if let Some(breakpoints) = parse_columns(trimmed, line_num)? {
+            return Ok(Some(Block::Columns { breakpoints }));
+        }
+        Ok(parse_aspect_ratio(trimmed, line_num)?
+            .map(|(numerator, denominator)| Block::AspectRatio { numerator, denominator }))
+    }
+
+    /// The opening directive of the blocks a closing or separating line
+    /// belongs in, or `None` when it is neither
+    fn opener_of(trimmed: &str) -> Option<&'static str> {
+        match trimmed {
+            END_ASPECT_RATIO => Some("::aspect-ratio"),
+            END_TERMINAL => Some("::terminal"),
+            END_BLOCK => Some("::summary, ::columns or ::mermaid"),
+            DETAILS => Some(SUMMARY),
+            COLUMN_BREAK => Some("::columns"),
+            _ => None,
+        }
+    }
+
+    fn end(&self) -> &'static str {
+        match self {
+            Block::AspectRatio { .. } => END_ASPECT_RATIO,
+            Block::Terminal { .. } => END_TERMINAL,
+            Block::Disclosure | Block::Columns { .. } | Block::Mermaid => END_BLOCK,
+        }
+    }
+
+    /// Whether the block's lines are kept as text rather than parsed
+    fn is_raw(&self) -> bool {
+        matches!(self, Block::Mermaid | Block::Terminal { .. })
+    }
+
+    /// The line splitting the block's content into parts, if it has one
+    fn separator(&self) -> Option<&'static str> {
+        match self {
+            Block::AspectRatio { .. } | Block::Mermaid | Block::Terminal { .. } => None,
+            Block::Disclosure => Some(DETAILS),
+            Block::Columns { .. } => Some(COLUMN_BREAK),
+        }
+    }
+
+    /// Build the block's node from the lines after its opening line
+    ///
+    /// `separators` and `end` are indices into `body`, as returned by
+    /// [`find_block_end`].
+    fn build(
+        self,
+        body: &[(usize, &str)],
+        separators: &[usize],
+        end: usize,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<DarkMatterNode, ParseError> {
+        let raw_lines = body[..end].iter().map(|&(_, line)| line);
+        match self {
+            Block::Mermaid => {
+                let source = raw_lines.collect::<Vec<_>>().join("\n");
+                return Ok(DarkMatterNode::Mermaid { source });
+            }
+            Block::Terminal { shell } => {
+                let lines = raw_lines.map(|line| parse_terminal_line(line, shell)).collect();
+                return Ok(DarkMatterNode::Terminal { lines, shell });
+            }
+            _ => {}
+        }
+
+        let bounds: Vec<usize> = separators.iter().copied().chain([end]).collect();
+        let mut parts = Vec::with_capacity(bounds.len());
+        let mut start = 0;
+        for bound in bounds {
+            parts.push(parse_lines(&body[start..bound], warnings)?);
+            start = bound + 1;
+        }
+
+        Ok(match self {
+            Block::AspectRatio { numerator, denominator } => DarkMatterNode::AspectRatio {
+                numerator,
+                denominator,
+                content: parts.remove(0),
+            },
+            Block::Disclosure => {
+                let Ok([summary, details]) = <[Vec<DarkMatterNode>; 2]>::try_from(parts) else {
+                    let line = match separators.get(1) {
+                        Some(&second) => body[second].0,
+                        None => body[end].0,
+                    };
+                    return Err(ParseError::InvalidDirective {
+                        line,
+                        directive: format!("A {} block needs exactly one {}", SUMMARY, DETAILS),
+                        span: None,
+                    });
+                };
+                DarkMatterNode::Disclosure { summary, details }
+            }
+            Block::Columns { breakpoints } => DarkMatterNode::Columns {
+                breakpoints,
+                sections: parts,
+            },
+            Block::Mermaid | Block::Terminal { .. } => unreachable!("raw blocks are built above"),
+        })
+    }
+}
+
+/// Find the line closing a block, and its separators, skipping nested blocks
+///
+/// `lines` starts just after the opening directive; the returned indices are
+/// relative to it. Lines in code blocks are never directives.
+fn find_block_end(
+    lines: &[(usize, &str)],
+    block: &Block,
+    opener: &str,
+    opener_line: usize,
+) -> Result<(Vec<usize>, usize), ParseError> {
+    let mut code_blocks = CodeBlocks::new();
+    // Closing lines of the nested blocks that are still open, and whether
+    // their lines are raw
+    let mut open: Vec<(&'static str, bool)> = Vec::new();
+    let mut separators = Vec::new();
+
+    for (offset, &(line_num, line)) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if open.last().map_or(block.is_raw(), |&(_, raw)| raw) {
+            let expected = open.last().map_or(block.end(), |&(end, _)| end);
+            if trimmed == expected && open.pop().is_none() {
+                return Ok((separators, offset));
+            }
+            continue;
+        }
+        if code_blocks.is_code(line) || !trimmed.starts_with("::") {
+            continue;
+        }
+
+        if let Some(nested) = Block::open(trimmed, line_num)? {
+            open.push((nested.end(), nested.is_raw()));
+        } else if [END_BLOCK, END_ASPECT_RATIO, END_TERMINAL].contains(&trimmed) {
+            let expected = open.last().map_or(block.end(), |&(end, _)| end);
+            if trimmed != expected {
+                return Err(ParseError::InvalidDirective {
+                    line: line_num,
+                    directive: format!("Expected {} but found {}", expected, trimmed),
+                    span: None,
+                });
+            }
+            if open.pop().is_none() {
+                return Ok((separators, offset));
+            }
+        } else if open.is_empty() && Some(trimmed) == block.separator() {
+            separators.push(offset);
+        }
+    }
+
+    Err(ParseError::InvalidDirective {
+        line: opener_line,
+        directive: format!("Missing {} for: {}", block.end(), opener),
+        span: None,
+    })
+}
+
+/// Tracks which lines belong to code blocks, where directives aren't recognized
+///
+/// Follows CommonMark as far as directive lines need: a fenced block opens
+/// with three or more backticks or tildes and closes with at least as many of
+/// the same character, and an indented block is lines indented four or more
+/// columns that don't continue a paragraph or a list item.
+struct CodeBlocks {
+    /// Character and length of the open fence
+    fence: Option<(char, usize)>,
+    /// Inside an indented code block
+    indented: bool,
+    /// The previous line was blank, or there was none
+    after_blank: bool,
+    /// Inside a list, where indented lines continue the item
+    in_list: bool,
+}
+
+impl CodeBlocks {
+    fn new() -> Self {
+        Self {
+            fence: None,
+            indented: false,
+            after_blank: true,
+            in_list: false,
+        }
+    }
+
+    /// Take the next line, returning whether it is part of a code block
+    ///
+    /// Fence lines count as part of their block.
+    fn is_code(&mut self, line: &str) -> bool {
+        let indent = indent_width(line);
+        let trimmed = line.trim_start();
+        let blank = trimmed.is_empty();
+        let after_blank = std::mem::replace(&mut self.after_blank, blank);
+
+        if let Some((marker, length)) = self.fence {
+            let run = trimmed.chars().take_while(|&c| c == marker).count();
+            if indent <= 3 && run >= length && trimmed[run..].trim().is_empty() {
+                self.fence = None;
+            }
+            return true;
+        }
+
+        if self.indented {
+            if blank || indent >= 4 {
+                return true;
+            }
+            self.indented = false;
+        } else if indent >= 4 {
+            if after_blank && !self.in_list && !blank {
+                self.indented = true;
+                return true;
+            }
+            return false;
+        }
+
+        if blank {
+            return false;
+        }
+        if let Some(marker) = trimmed.chars().next().filter(|&c| c == '`' || c == '~') {
+            let run = trimmed.chars().take_while(|&c| c == marker).count();
+            // A backtick fence's info string can't contain backticks
+            if run >= 3 && !(marker == '`' && trimmed[run..].contains('`')) {
+                self.fence = Some((marker, run));
+                return true;
+            }
+        }
+
+        // A line after a blank one that doesn't start an item ends the list
+        self.in_list = is_list_item(trimmed) || (self.in_list && !after_blank);
+        false
+    }
+}
+
+/// Width of a line's indentation, with tabs advancing to the next multiple of 4
+fn indent_width(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Whether a line (without its indentation) starts a list item
+fn is_list_item(trimmed: &str) -> bool {
+    let bullet = trimmed
+        .strip_prefix(['-', '*', '+'])
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']));
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let ordered = (1..=9).contains(&digits)
+        && trimmed[digits..]
+            .strip_prefix(['.', ')'])
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']));
+    bullet || ordered
 }
 
 /// Parse markdown content with GFM extensions (old detailed parser - keeping for reference)
@@ -133,38 +582,34 @@ fn parse_markdown_detailed(content: &str) -> Result<Vec<DarkMatterNode>, ParseEr
                 }
             }
 
-            Event::End(TagEnd::CodeBlock) => {
-                if !current_text.is_empty() {
-                    // If it was a table directive, parse it
-                    if current_text.starts_with("::table") {
-                        let first_line = current_text.lines().next().unwrap_or("");
-                        if let Some(node) = parse_directive(first_line, line_num)? {
-                            nodes.push(node);
-                        }
-                    } else {
-                        // Regular code block - store as markdown
-                        current_text.push_str("\n```");
-                        nodes.push(DarkMatterNode::Markdown(MarkdownContent {
-                            raw: current_text.clone(),
-                            frontmatter: None,
-                        }));
+            Event::End(TagEnd::CodeBlock) if !current_text.is_empty() => {
+                // If it was a table directive, parse it
+                if current_text.starts_with("::table") {
+                    let first_line = current_text.lines().next().unwrap_or("");
+                    if let Some(node) = parse_directive(first_line, line_num)? {
+                        nodes.push(node);
                     }
-
-                    current_text.clear();
+                } else {
+                    // Regular code block - store as markdown
+                    current_text.push_str("\n```");
+                    nodes.push(DarkMatterNode::Markdown(MarkdownContent {
+                        raw: current_text.clone(),
+                        frontmatter: None,
+                    }));
                 }
+
+                current_text.clear();
             }
 
             Event::Start(Tag::Heading { .. }) => {
                 current_text.clear();
             }
 
-            Event::End(TagEnd::Heading(_)) => {
-                if !current_text.is_empty() {
-                    // Process inline syntax in heading text (for interpolations, etc.)
-                    let inline_nodes = process_inline_syntax(&current_text);
-                    nodes.extend(inline_nodes);
-                    current_text.clear();
-                }
+            Event::End(TagEnd::Heading(_)) if !current_text.is_empty() => {
+                // Process inline syntax in heading text (for interpolations, etc.)
+                let inline_nodes = process_inline_syntax(&current_text);
+                nodes.extend(inline_nodes);
+                current_text.clear();
             }
 
             Event::SoftBreak => {
@@ -214,6 +659,7 @@ fn parse_markdown_detailed(content: &str) -> Result<Vec<DarkMatterNode>, ParseEr
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::TerminalLine;
 
     #[test]
     fn test_parse_simple_markdown() {
@@ -264,4 +710,360 @@ mod tests {
 
         assert!(!nodes.is_empty());
     }
+
+    #[test]
+    fn test_parse_aspect_ratio_block() {
+        let content = "# Video\n\n::aspect-ratio 16:9\n<iframe src=\"https://example.com\"></iframe>\n::endaspect-ratio\n\nAfter.";
+        let nodes = parse_markdown(content).unwrap();
+
+        assert_eq!(nodes.len(), 3);
+        match &nodes[1] {
+            DarkMatterNode::AspectRatio { numerator, denominator, content } => {
+                assert_eq!((*numerator, *denominator), (16, 9));
+                assert_eq!(content.len(), 1);
+                assert!(matches!(&content[0], DarkMatterNode::Markdown(md) if md.raw.contains("<iframe")));
+            }
+            other => panic!("Expected aspect ratio, got {:?}", other),
+        }
+        assert!(matches!(&nodes[2], DarkMatterNode::Markdown(md) if md.raw.contains("After.")));
+    }
+
+    #[test]
+    fn test_parse_nested_aspect_ratio_blocks() {
+        let content = "::aspect-ratio --widescreen\n::aspect-ratio --square\ninner\n::endaspect-ratio\n::file ./x.md\n::endaspect-ratio";
+        let nodes = parse_markdown(content).unwrap();
+
+        assert_eq!(nodes.len(), 1);
+        let DarkMatterNode::AspectRatio { content, .. } = &nodes[0] else {
+            panic!("Expected aspect ratio, got {:?}", nodes[0]);
+        };
+        assert!(matches!(
+            content.as_slice(),
+            [DarkMatterNode::AspectRatio { numerator: 1, denominator: 1, .. }, DarkMatterNode::File { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_parse_unbalanced_aspect_ratio_blocks() {
+        let missing_end = parse_markdown("text\n::aspect-ratio 4:3\ncontent");
+        assert!(matches!(missing_end, Err(ParseError::InvalidDirective { line: 2, .. })));
+
+        let stray_end = parse_markdown("text\n::endaspect-ratio");
+        assert!(matches!(stray_end, Err(ParseError::InvalidDirective { line: 2, .. })));
+
+        let bad_ratio = parse_markdown("::aspect-ratio 1:0\n::endaspect-ratio");
+        assert!(matches!(bad_ratio, Err(ParseError::InvalidDirective { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_parse_disclosure_block() {
+        let content = "::summary\nLicense\n::details\nMIT, see below.\n::end\n\nAfter.";
+        let nodes = parse_markdown(content).unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        let DarkMatterNode::Disclosure { summary, details } = &nodes[0] else {
+            panic!("Expected disclosure, got {:?}", nodes[0]);
+        };
+        assert!(matches!(summary.as_slice(), [DarkMatterNode::Markdown(md)] if md.raw.contains("License")));
+        assert!(matches!(details.as_slice(), [DarkMatterNode::Markdown(md)] if md.raw.contains("MIT")));
+        assert!(matches!(&nodes[1], DarkMatterNode::Markdown(md) if md.raw.contains("After.")));
+    }
+
+    #[test]
+    fn test_parse_columns_block() {
+        let content = "::columns md: 2, xl: 3\nOne\n::break\nTwo\n::break\nThree\n::end";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::Columns { breakpoints, sections }] = nodes.as_slice() else {
+            panic!("Expected columns, got {:?}", nodes);
+        };
+        assert_eq!(breakpoints.get(&Breakpoint::Md), Some(&2));
+        assert_eq!(breakpoints.get(&Breakpoint::Xl), Some(&3));
+        assert_eq!(sections.len(), 3);
+        assert!(matches!(sections[2].as_slice(), [DarkMatterNode::Markdown(md)] if md.raw.contains("Three")));
+    }
+
+    #[test]
+    fn test_parse_disclosure_inside_columns() {
+        let content = "::columns\n::summary\nMore\n::details\nHidden\n::end\n::break\nRight\n::end";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::Columns { sections, .. }] = nodes.as_slice() else {
+            panic!("Expected columns, got {:?}", nodes);
+        };
+        assert_eq!(sections.len(), 2);
+        assert!(matches!(sections[0].as_slice(), [DarkMatterNode::Disclosure { .. }]));
+        assert!(matches!(sections[1].as_slice(), [DarkMatterNode::Markdown(md)] if md.raw.contains("Right")));
+    }
+
+    #[test]
+    fn test_parse_columns_inside_disclosure() {
+        let content = "::summary\nMore\n::details\n::columns\nA\n::break\nB\n::end\n::aspect-ratio 16:9\nvideo\n::endaspect-ratio\n::end";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::Disclosure { details, .. }] = nodes.as_slice() else {
+            panic!("Expected disclosure, got {:?}", nodes);
+        };
+        let [DarkMatterNode::Columns { sections, .. }, DarkMatterNode::AspectRatio { .. }] = details.as_slice() else {
+            panic!("Expected columns and an aspect ratio, got {:?}", details);
+        };
+        assert_eq!(sections.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_mermaid_block() {
+        let content = "::columns\n::mermaid\ngraph TD\n  A::B --> C\n::break\n```\n::end\n::break\nRight\n::end";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::Columns { sections, .. }] = nodes.as_slice() else {
+            panic!("Expected columns, got {:?}", nodes);
+        };
+        assert_eq!(sections.len(), 2);
+        assert!(matches!(
+            sections[0].as_slice(),
+            [DarkMatterNode::Mermaid { source }] if source == "graph TD\n  A::B --> C\n::break\n```"
+        ));
+
+        let unterminated = parse_markdown("text\n::mermaid\ngraph TD");
+        assert!(matches!(unterminated, Err(ParseError::InvalidDirective { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_parse_unterminated_blocks() {
+        let columns = parse_markdown("text\n::columns\nA\n::break\nB");
+        assert!(matches!(columns, Err(ParseError::InvalidDirective { line: 2, .. })));
+
+        let disclosure = parse_markdown("::summary\nMore\n::details\nruns on to the end");
+        assert!(matches!(disclosure, Err(ParseError::InvalidDirective { line: 1, .. })));
+
+        // The inner block's `::end` doesn't close the outer one
+        let nested = parse_markdown("::columns\n::summary\nA\n::details\nB\n::end\n::break\nC");
+        assert!(matches!(nested, Err(ParseError::InvalidDirective { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_parse_misplaced_block_directives() {
+        let mismatched = parse_markdown("::aspect-ratio 1:1\n::summary\nA\n::details\nB\n::endaspect-ratio\n::end");
+        assert!(matches!(mismatched, Err(ParseError::InvalidDirective { line: 6, .. })));
+
+        for stray in ["::end", "::break", "::details"] {
+            let result = parse_markdown(&format!("text\n{}", stray));
+            assert!(matches!(result, Err(ParseError::InvalidDirective { line: 2, .. })), "{}", stray);
+        }
+
+        let no_details = parse_markdown("::summary\nA\n::end");
+        assert!(matches!(no_details, Err(ParseError::InvalidDirective { line: 3, .. })));
+
+        let two_details = parse_markdown("::summary\nA\n::details\nB\n::details\nC\n::end");
+        assert!(matches!(two_details, Err(ParseError::InvalidDirective { line: 5, .. })));
+
+        let bad_columns = parse_markdown("::columns md: 0\n::end");
+        assert!(matches!(bad_columns, Err(ParseError::InvalidDirective { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_parse_duplicate_footnote_ids() {
+        let unique = parse_markdown("Text^[a]\n\n::footnote[a] First.\n::footnote[b] Second.");
+        assert!(unique.is_ok());
+
+        let nested = "::footnote[a] First.\n::summary\nMore\n::details\n::footnote[a] Again.\n::end";
+        assert!(matches!(parse_markdown(nested), Err(ParseError::InvalidDirective { line: 5, .. })));
+
+        // Definitions shown in code blocks don't count
+        assert!(parse_markdown("::footnote[a] First.\n\n```\n::footnote[a] Example.\n```").is_ok());
+    }
+
+    /// One line of every directive
+    const ALL_DIRECTIVES: &[&str] = &[
+        "::file ./chapter.md",
+        "::summarize ./doc.md",
+        "::consolidate ./a.md ./b.md",
+        "::topic \"testing\" ./a.md ./b.md",
+        "::table ./data.csv --with-heading-row",
+        "::bar-chart ./data.csv",
+        "::line-chart ./data.csv",
+        "::pie-chart ./data.csv",
+        "::area-chart ./data.csv",
+        "::bubble-chart ./data.csv",
+        "::include-env --filter APP_",
+        "::require-env COMPOSITION_TEST_CODE_BLOCK_MISSING",
+        "::audio ./podcast.mp3",
+        "::youtube dQw4w9WgXcQ",
+        "::footnote[1] A footnote.",
+        "::columns",
+        "::break",
+        "::summary",
+        "::details",
+        "::aspect-ratio 16:9",
+        "::endaspect-ratio",
+        "::mermaid",
+        "::end",
+    ];
+
+    #[test]
+    fn test_directives_in_code_blocks_are_markdown() {
+        let directives = ALL_DIRECTIVES.join("\n");
+        let indented = ALL_DIRECTIVES.iter().map(|d| format!("    {}", d)).collect::<Vec<_>>().join("\n");
+        let content = format!(
+            "# Directives\n\n```markdown\n{directives}\n```\n\n~~~\n{directives}\n~~~\n\n\
+             ````\n```\n{directives}\n```\n````\n\n{indented}\n\nDone."
+        );
+
+        let nodes = parse_markdown(&content).unwrap();
+        assert!(nodes.iter().all(|n| matches!(n, DarkMatterNode::Markdown(_))), "{:?}", nodes);
+
+        let resource = crate::types::Resource::local("doc.md".into());
+        let (doc, _) = crate::parse::parse_document(&content, resource, Default::default()).unwrap();
+        assert!(doc.dependencies.is_empty(), "{:?}", doc.dependencies);
+    }
+
+    #[test]
+    fn test_directives_after_code_blocks_are_parsed() {
+        let content = "```\n::file ./in-fence.md\n```\n::file ./after.md\n\n    ::file ./indented.md\n::file ./after-indented.md";
+        let nodes = parse_markdown(content).unwrap();
+
+        let files: Vec<_> = nodes
+            .iter()
+            .filter_map(|n| match n {
+                DarkMatterNode::File { resource, .. } => Some(format!("{:?}", resource.source)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(files.len(), 2, "{:?}", nodes);
+        assert!(files[0].contains("after.md"));
+        assert!(files[1].contains("after-indented.md"));
+    }
+
+    #[test]
+    fn test_indented_lines_that_are_not_code() {
+        // Paragraph continuations and list items aren't code blocks
+        let content = "Some text\n    ::file ./continuation.md\n\n- item\n\n    ::file ./in-item.md";
+        let nodes = parse_markdown(content).unwrap();
+
+        assert_eq!(nodes.iter().filter(|n| matches!(n, DarkMatterNode::File { .. })).count(), 2);
+    }
+
+    #[test]
+    fn test_escaped_directive_is_markdown() {
+        let nodes = parse_markdown("\\::file ./shown.md").unwrap();
+
+        assert!(matches!(nodes.as_slice(), [DarkMatterNode::Markdown(md)] if md.raw == "\\::file ./shown.md"));
+    }
+
+    #[test]
+    fn test_aspect_ratio_end_inside_code_block() {
+        let content = "::aspect-ratio 4:3\n```\n::endaspect-ratio\n```\n::endaspect-ratio";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::AspectRatio { content, .. }] = nodes.as_slice() else {
+            panic!("Expected aspect ratio, got {:?}", nodes);
+        };
+        assert!(matches!(content.as_slice(), [DarkMatterNode::Markdown(md)] if md.raw.contains("::endaspect-ratio")));
+    }
+
+    #[test]
+    fn test_parse_display_math() {
+        let nodes = parse_markdown("Before\n$$\n\\int_0^1 x\\,dx\n\n= \\frac{1}{2}\n$$\n$$ e^{i\\pi} = -1 $$\nAfter").unwrap();
+
+        match nodes.as_slice() {
+            [DarkMatterNode::Markdown(before), DarkMatterNode::Math { source: first, display: true }, DarkMatterNode::Math { source: second, display: true }, DarkMatterNode::Markdown(after)] =>
+            {
+                assert_eq!(before.raw, "Before");
+                assert_eq!(first, "\\int_0^1 x\\,dx\n\n= \\frac{1}{2}");
+                assert_eq!(second, "e^{i\\pi} = -1");
+                assert_eq!(after.raw, "After");
+            }
+            other => panic!("Expected display math between markdown, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_inline_math() {
+        let nodes = process_inline_syntax("Euler: $e^{i\\pi} + 1 = 0$, done");
+
+        assert!(matches!(
+            nodes.as_slice(),
+            [DarkMatterNode::Text(before), DarkMatterNode::Math { source, display: false }, DarkMatterNode::Text(after)]
+                if before == "Euler: " && source == "e^{i\\pi} + 1 = 0" && after == ", done"
+        ));
+    }
+
+    #[test]
+    fn test_escaped_dollar_is_not_math() {
+        let text = process_inline_syntax("Costs \\$5, not \\$10$");
+        let markdown = parse_markdown("\\$$ x $$\n```\n$$\n```").unwrap();
+
+        let plain: String = text
+            .iter()
+            .map(|node| match node {
+                DarkMatterNode::Text(text) => text.as_str(),
+                other => panic!("Expected text, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(plain, "Costs $5, not $10$");
+        assert!(matches!(markdown.as_slice(), [DarkMatterNode::Markdown(_)]), "{:?}", markdown);
+    }
+
+    #[test]
+    fn test_parse_unterminated_display_math() {
+        let result = parse_markdown("Intro\n\n$$\nx^2");
+
+        assert!(matches!(result, Err(ParseError::InvalidDirective { line: 3, .. })));
+    }
+
+    #[test]
+    fn test_parse_terminal_block() {
+        let content = "::terminal --shell zsh\n# install\n$ cargo install lib\n  Compiling lib v0.1.0\n\n::file ./not-a-directive.md\n::endterminal\nAfter";
+        let nodes = parse_markdown(content).unwrap();
+
+        let [DarkMatterNode::Terminal { lines, shell }, DarkMatterNode::Markdown(after)] = nodes.as_slice() else {
+            panic!("Expected terminal then markdown, got {:?}", nodes);
+        };
+        assert_eq!(*shell, TerminalShell::Zsh);
+        assert_eq!(
+            lines,
+            &vec![
+                TerminalLine::Comment("install".to_string()),
+                TerminalLine::Command("cargo install lib".to_string()),
+                TerminalLine::Output("  Compiling lib v0.1.0".to_string()),
+                TerminalLine::Output(String::new()),
+                TerminalLine::Output("::file ./not-a-directive.md".to_string()),
+            ]
+        );
+        assert_eq!(after.raw, "After");
+    }
+
+    #[test]
+    fn test_parse_terminal_shells() {
+        let nodes = parse_markdown("::terminal\n$ ls\n::endterminal\n::terminal --shell pwsh\nPS> ls\n$5 spent\n::endterminal").unwrap();
+
+        assert!(matches!(&nodes[0], DarkMatterNode::Terminal { shell: TerminalShell::Bash, .. }));
+        let DarkMatterNode::Terminal { lines, shell: TerminalShell::PowerShell } = &nodes[1] else {
+            panic!("Expected a PowerShell terminal, got {:?}", nodes[1]);
+        };
+        assert_eq!(lines[0], TerminalLine::Command("ls".to_string()));
+        assert_eq!(lines[1], TerminalLine::Output("$5 spent".to_string()));
+
+        for line in ["::terminal --shell cmd", "::terminal zsh", "::terminal --shell"] {
+            let result = parse_markdown(&format!("{}\n$ ls\n::endterminal", line));
+            assert!(matches!(result, Err(ParseError::InvalidDirective { line: 1, .. })), "{}: {:?}", line, result);
+        }
+        let unterminated = parse_markdown("::terminal\n$ ls\n::end");
+        assert!(matches!(unterminated, Err(ParseError::InvalidDirective { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_parse_error_spans_directive() {
+        let content = "Intro\n\n  ::youtube dQw4w9WgXcQ 101%\nOutro";
+        let err = parse_markdown(content).unwrap_err();
+
+        let span = err.span().copied().expect("directive errors have a span");
+        assert_eq!(&content[span.offset..span.offset + span.length], "::youtube dQw4w9WgXcQ 101%");
+        assert_eq!((span.line, span.column), (3, 3));
+
+        let content = "Text\n::columns\nLeft";
+        let span = parse_markdown(content).unwrap_err().span().copied().unwrap();
+        assert_eq!(&content[span.offset..span.offset + span.length], "::columns");
+        assert_eq!((span.line, span.column), (2, 1));
+    }
 }
@@ -2,15 +2,48 @@ mod frontmatter;
 mod resource;
 pub mod darkmatter;
 mod markdown;
+pub mod regions;
+pub mod stream;
 
-pub use frontmatter::extract_frontmatter;
+pub use frontmatter::{extract_frontmatter, extract_frontmatter_async};
+pub(crate) use frontmatter::{frontmatter_to_yaml, parse_imports};
 pub use resource::{parse_resource, parse_resources};
+pub(crate) use resource::validate_git_path;
 pub use darkmatter::{parse_directive, process_inline_syntax};
 pub use markdown::parse_markdown;
+use markdown::parse_markdown_with_warnings;
+pub use stream::parse_document_async;
 
-use crate::error::ParseError;
-use crate::types::{Document, Resource, DarkMatterNode};
+use crate::error::{ParseError, ParseWarning, Span};
+use crate::types::{Document, Frontmatter, FrontmatterSchema, Resource, DarkMatterNode};
 use chrono::Utc;
+use tracing::warn;
+
+/// Options for [`parse_document`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail on the first warning, as [`ParseError::Strict`], instead of
+    /// returning the warnings with the document
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    /// Options that turn every warning into an error
+    pub fn strict() -> Self {
+        ParseOptions { strict: true }
+    }
+
+    /// Log each warning and, in strict mode, fail on the first one
+    pub(crate) fn check(&self, source: &Resource, warnings: &[ParseWarning]) -> Result<(), ParseError> {
+        for warning in warnings {
+            warn!("{}: {}", source.label(), warning);
+        }
+        match warnings.first() {
+            Some(warning) if self.strict => Err(ParseError::Strict(warning.clone())),
+            _ => Ok(()),
+        }
+    }
+}
 
 /// Parse a DarkMatter document from source content
 ///
@@ -18,24 +51,103 @@ use chrono::Utc;
 /// 1. Extracts frontmatter
 /// 2. Parses markdown with DarkMatter DSL extensions
 /// 3. Collects resource dependencies
-/// 4. Returns a complete Document
-pub fn parse_document(content: &str, source: Resource) -> Result<Document, ParseError> {
+/// 4. Returns a complete Document along with the problems that didn't stop
+///    it from parsing, such as unknown directives
+///
+/// Each warning is logged. With [`ParseOptions::strict`] the first one fails
+/// the parse instead. Error and warning spans are in the coordinates of
+/// `content`, frontmatter included.
+pub fn parse_document(
+    content: &str,
+    source: Resource,
+    opts: ParseOptions,
+) -> Result<(Document, Vec<ParseWarning>), ParseError> {
+    let (document, warnings) = parse_document_unchecked(content, source)?;
+    opts.check(&document.resource, &warnings)?;
+    Ok((document, warnings))
+}
+
+/// Parse a document without logging its warnings or failing on them
+///
+/// For passes over documents that are parsed again to be rendered, such as
+/// building the dependency graph, so each warning is reported once.
+pub(crate) fn parse_document_unchecked(
+    content: &str,
+    source: Resource,
+) -> Result<(Document, Vec<ParseWarning>), ParseError> {
     // 1. Extract frontmatter
     let (frontmatter, body) = extract_frontmatter(content)?;
 
+    // The body is the tail of `content`
+    let base = content.len() - body.len();
+    let (document, mut warnings) =
+        build_document(frontmatter, body, source).map_err(|e| e.relocate(content, base))?;
+    for warning in &mut warnings {
+        if let Some(span) = warning.span {
+            warning.span = Some(Span::locate(content, base + span.offset, span.length));
+        }
+    }
+    Ok((document, warnings))
+}
+
+/// Parse a document and check its frontmatter against a schema
+///
+/// Like [`parse_document`], then [`validate_document`] when a schema is given.
+pub fn parse_document_with_schema(
+    content: &str,
+    source: Resource,
+    schema: Option<&FrontmatterSchema>,
+    opts: ParseOptions,
+) -> Result<(Document, Vec<ParseWarning>), ParseError> {
+    let (document, warnings) = parse_document(content, source, opts)?;
+    if let Some(schema) = schema {
+        validate_document(&document, schema)?;
+    }
+    Ok((document, warnings))
+}
+
+/// Check a parsed document's frontmatter against a schema
+///
+/// Documents without frontmatter, such as fragments written to be
+/// transcluded, aren't checked. The error names the document.
+pub fn validate_document(document: &Document, schema: &FrontmatterSchema) -> Result<(), ParseError> {
+    if document.frontmatter.is_empty() {
+        return Ok(());
+    }
+
+    document.frontmatter.validate(schema).map_err(|e| match e {
+        ParseError::InvalidFrontmatter(message) => {
+            ParseError::InvalidFrontmatter(format!("{}: {}", document.resource.label(), message))
+        }
+        other => other,
+    })
+}
+
+/// Parse a document body and assemble the `Document`
+///
+/// Warning spans are in the coordinates of `body`.
+fn build_document(
+    frontmatter: Frontmatter,
+    body: &str,
+    source: Resource,
+) -> Result<(Document, Vec<ParseWarning>), ParseError> {
     // 2. Parse markdown and DarkMatter
-    let nodes = parse_markdown(body)?;
+    let (nodes, warnings) = parse_markdown_with_warnings(body)?;
 
-    // 3. Collect dependencies from nodes
-    let dependencies = collect_dependencies(&nodes);
+    // 3. Collect dependencies from nodes and frontmatter imports; only real
+    // resources count, never in-memory ones
+    let mut dependencies = collect_dependencies(&nodes);
+    dependencies.extend(parse_imports(&frontmatter)?.into_iter().map(|(_, resource)| resource));
+    dependencies.retain(|dep| !dep.is_memory());
 
-    Ok(Document {
+    let document = Document {
         resource: source,
         frontmatter,
         content: nodes,
         dependencies,
         parsed_at: Utc::now(),
-    })
+    };
+    Ok((document, warnings))
 }
 
 /// Collect all resource dependencies from parsed nodes
@@ -62,11 +174,17 @@ fn collect_dependencies(nodes: &[DarkMatterNode]) -> Vec<Resource> {
             DarkMatterNode::Table { .. } => {
                 // Inline table, no external dependencies
             }
-            DarkMatterNode::BarChart { data } |
-            DarkMatterNode::LineChart { data } |
-            DarkMatterNode::PieChart { data } |
-            DarkMatterNode::AreaChart { data } |
-            DarkMatterNode::BubbleChart { data } => {
+            DarkMatterNode::DataList { source: crate::types::ListSource::External { resource, .. }, .. } => {
+                deps.push(resource.clone());
+            }
+            DarkMatterNode::Audio { captions: Some(captions), .. } => {
+                deps.push(Resource::local(captions.path.clone().into()));
+            }
+            DarkMatterNode::BarChart { data, .. } |
+            DarkMatterNode::LineChart { data, .. } |
+            DarkMatterNode::PieChart { data, .. } |
+            DarkMatterNode::AreaChart { data, .. } |
+            DarkMatterNode::BubbleChart { data, .. } => {
                 if let crate::types::ChartData::External(resource) = data {
                     deps.push(resource.clone());
                 }
@@ -86,6 +204,13 @@ fn collect_dependencies(nodes: &[DarkMatterNode]) -> Vec<Resource> {
                 deps.extend(collect_dependencies(summary));
                 deps.extend(collect_dependencies(details));
             }
+            DarkMatterNode::Diff { old, new, .. } => {
+                // Inline diffs compare strings, not files
+                deps.extend([old, new].into_iter().filter(|side| !side.is_memory()).cloned());
+            }
+            DarkMatterNode::AspectRatio { content, .. } | DarkMatterNode::Footnote { content, .. } => {
+                deps.extend(collect_dependencies(content));
+            }
             _ => {
                 // Other node types don't have dependencies
             }
@@ -98,6 +223,7 @@ fn collect_dependencies(nodes: &[DarkMatterNode]) -> Vec<Resource> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ParseWarningCode;
     use std::path::PathBuf;
 
     #[test]
@@ -105,7 +231,7 @@ mod tests {
         let content = "# Hello World\n\nThis is a test.";
         let resource = Resource::local(PathBuf::from("test.md"));
 
-        let doc = parse_document(content, resource).unwrap();
+        let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
         assert!(!doc.content.is_empty());
         assert!(doc.dependencies.is_empty());
@@ -122,7 +248,7 @@ author: John Doe
 Content here"#;
 
         let resource = Resource::local(PathBuf::from("test.md"));
-        let doc = parse_document(content, resource).unwrap();
+        let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
         assert_eq!(doc.frontmatter.get_string("title"), Some("Test Document"));
         assert_eq!(doc.frontmatter.get_string("author"), Some("John Doe"));
@@ -133,11 +259,35 @@ Content here"#;
         let content = "# Document\n\n::file ./other.md\n\n::summarize ./data.md";
         let resource = Resource::local(PathBuf::from("test.md"));
 
-        let doc = parse_document(content, resource).unwrap();
+        let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
         assert_eq!(doc.dependencies.len(), 2);
     }
 
+    #[test]
+    fn test_imports_are_dependencies() {
+        let content = "---\nimports:\n  versions: ./versions.md\n---\n# Changelog\n\n::file ./intro.md";
+        let resource = Resource::local(PathBuf::from("changelog.md"));
+
+        let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
+
+        let labels: Vec<_> = doc.dependencies.iter().map(|dep| dep.label()).collect();
+        assert_eq!(labels.len(), 2);
+        assert!(labels.iter().any(|label| label.ends_with("versions.md")), "{:?}", labels);
+    }
+
+    #[test]
+    fn test_invalid_import_namespace() {
+        for imports in ["{ env: ./vars.md }", "{ my-vars: ./vars.md }", "./vars.md"] {
+            let content = format!("---\nimports: {}\n---\nBody", imports);
+
+            let error = parse_document(&content, Resource::local(PathBuf::from("a.md")), ParseOptions::default())
+                .unwrap_err();
+
+            assert!(matches!(error, ParseError::InvalidFrontmatter(_)), "{}: {:?}", imports, error);
+        }
+    }
+
     #[test]
     fn test_collect_dependencies() {
         let nodes = vec![
@@ -153,5 +303,143 @@ Content here"#;
         let deps = collect_dependencies(&nodes);
         assert_eq!(deps.len(), 2);
     }
-}
 
+    #[test]
+    fn test_collect_diff_dependencies() {
+        let diff = |old, new| DarkMatterNode::Diff { old, new, language: None, context_lines: 3 };
+        let nodes = vec![
+            diff(Resource::local(PathBuf::from("old.rs")), Resource::local(PathBuf::from("new.rs"))),
+            diff(Resource::memory("old", "a"), Resource::memory("new", "b")),
+        ];
+
+        let deps = collect_dependencies(&nodes);
+        assert_eq!(deps.len(), 2);
+    }
+
+    #[test]
+    fn test_collect_data_list_dependencies() {
+        use crate::types::ListSource;
+
+        let nodes = vec![
+            DarkMatterNode::DataList {
+                source: ListSource::External { resource: Resource::local(PathBuf::from("items.csv")), columns: Vec::new() },
+                ordered: false,
+                task_list: false,
+            },
+            DarkMatterNode::DataList { source: ListSource::Inline(Vec::new()), ordered: false, task_list: false },
+        ];
+
+        let deps = collect_dependencies(&nodes);
+        assert_eq!(deps.len(), 1);
+    }
+
+    fn article_schema() -> FrontmatterSchema {
+        use crate::types::FrontmatterType;
+
+        FrontmatterSchema::new()
+            .require("title", FrontmatterType::String)
+            .require("tags", FrontmatterType::Array)
+    }
+
+    #[test]
+    fn test_parse_document_with_schema_accepts_valid_frontmatter() {
+        let content = "---\ntitle: Guide\ntags: [rust]\n---\n# Guide";
+
+        let (doc, _) = parse_document_with_schema(
+            content,
+            Resource::local(PathBuf::from("guide.md")),
+            Some(&article_schema()),
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(doc.frontmatter.get_string("title"), Some("Guide"));
+
+        // Fragments without frontmatter aren't checked
+        let fragment = parse_document_with_schema(
+            "Snippet",
+            Resource::local(PathBuf::from("part.md")),
+            Some(&article_schema()),
+            ParseOptions::default(),
+        );
+        assert!(fragment.is_ok());
+    }
+
+    #[test]
+    fn test_parse_document_with_schema_reports_missing_key() {
+        let content = "---\ntitel: Guide\ntags: [rust]\n---\n# Guide";
+
+        let resource = Resource::local(PathBuf::from("guide.md"));
+        match parse_document_with_schema(content, resource, Some(&article_schema()), ParseOptions::default()) {
+            Err(ParseError::InvalidFrontmatter(message)) => {
+                assert_eq!(message, "guide.md: missing required key 'title'")
+            }
+            other => panic!("Expected InvalidFrontmatter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_document_with_schema_reports_type_mismatch() {
+        let content = "---\ntitle: Guide\ntags: rust\n---\n# Guide";
+
+        let resource = Resource::local(PathBuf::from("guide.md"));
+        match parse_document_with_schema(content, resource, Some(&article_schema()), ParseOptions::default()) {
+            Err(ParseError::InvalidFrontmatter(message)) => {
+                assert_eq!(message, "guide.md: key 'tags' should be array but is string")
+            }
+            other => panic!("Expected InvalidFrontmatter, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_document_error_span_counts_frontmatter() {
+        let content = "---\ntitle: Guide\n---\n# Guide\n\n::youtube dQw4w9WgXcQ 101%\n";
+
+        let err = parse_document(content, Resource::local(PathBuf::from("guide.md")), ParseOptions::default()).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDirective { .. }), "{:?}", err);
+
+        let span = err.span().copied().unwrap();
+        assert_eq!(&content[span.offset..span.offset + span.length], "::youtube dQw4w9WgXcQ 101%");
+        assert_eq!((span.line, span.column), (6, 1));
+    }
+
+    #[test]
+    fn test_parse_document_warns_about_unknown_directives() {
+        let content = "---\ntitle: Guide\n---\n# Guide\n\n  ::fle ./other.md\n\n```\n::nope\n```\n";
+
+        let (doc, warnings) =
+            parse_document(content, Resource::local(PathBuf::from("guide.md")), ParseOptions::default()).unwrap();
+        assert!(doc.dependencies.is_empty());
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+
+        let warning = &warnings[0];
+        assert_eq!(warning.code, ParseWarningCode::UnknownDirective);
+        assert_eq!(warning.message, "Unknown directive ::fle");
+        let span = warning.span.unwrap();
+        assert_eq!(&content[span.offset..span.offset + span.length], "::fle ./other.md");
+        assert_eq!((span.line, span.column), (6, 3));
+    }
+
+    #[test]
+    fn test_parse_document_warns_inside_blocks() {
+        let content = "::summary\nMore\n::details\n::unknown\n::end\n";
+
+        let (_, warnings) =
+            parse_document(content, Resource::local(PathBuf::from("doc.md")), ParseOptions::default()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span.map(|span| span.line), Some(4));
+    }
+
+    #[test]
+    fn test_strict_parse_fails_on_warning() {
+        let content = "# Guide\n\n::fle ./other.md\n";
+
+        let err = parse_document(content, Resource::local(PathBuf::from("guide.md")), ParseOptions::strict())
+            .unwrap_err();
+        match &err {
+            ParseError::Strict(warning) => assert_eq!(warning.code, ParseWarningCode::UnknownDirective),
+            other => panic!("Expected Strict, got {:?}", other),
+        }
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.to_string(), "Unknown directive ::fle at line 3, column 1 (strict mode)");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::RenderError;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// How a language writes a comment around a region marker
+struct CommentStyle {
+    open: &'static str,
+    close: &'static str,
+}
+
+const SLASHES: CommentStyle = CommentStyle { open: "//", close: "" };
+const HASH: CommentStyle = CommentStyle { open: "#", close: "" };
+const DASHES: CommentStyle = CommentStyle { open: "--", close: "" };
+const BLOCK: CommentStyle = CommentStyle { open: "/*", close: "*/" };
+const HTML: CommentStyle = CommentStyle { open: "<!--", close: "-->" };
+
+/// Every comment style, for files whose language isn't known
+const ALL_STYLES: &[CommentStyle] = &[SLASHES, HASH, DASHES, BLOCK, HTML];
+
+/// A `region:` or `endregion:` marker, once the comment syntax is removed
+static MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(region|endregion):\s*([\w-]+)$").unwrap()
+});
+
+/// The comment styles region markers are written in for a file extension
+///
+/// Unknown extensions accept markers in any style.
+fn comment_styles(language: &str) -> &'static [CommentStyle] {
+    match language.to_ascii_lowercase().as_str() {
+        "rs" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "go" | "java" | "kt" | "kts" | "scala" | "swift"
+        | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "dart" | "php" | "scss" | "less" => &[SLASHES],
+        "py" | "rb" | "sh" | "bash" | "zsh" | "fish" | "ps1" | "pl" | "r" | "jl" | "ex" | "exs" | "yaml"
+        | "yml" | "toml" | "nim" => &[HASH],
+        "sql" | "lua" | "hs" | "elm" => &[DASHES],
+        "css" => &[BLOCK],
+        "html" | "htm" | "xml" | "svg" | "vue" | "md" | "markdown" => &[HTML],
+        _ => ALL_STYLES,
+    }
+}
+
+/// Read a line as a region marker, returning whether it opens a region and
+/// the region's name
+fn parse_marker<'a>(line: &'a str, styles: &[CommentStyle]) -> Option<(bool, &'a str)> {
+    let trimmed = line.trim();
+    styles.iter().find_map(|style| {
+        let inner = trimmed.strip_prefix(style.open)?.strip_suffix(style.close)?.trim();
+        let caps = MARKER.captures(inner)?;
+        Some((&caps[1] == "region", caps.get(2)?.as_str()))
+    })
+}
+
+/// Extract the lines of the region called `name`
+///
+/// A region starts after a `region: name` comment and ends before the
+/// matching `endregion: name`, written in the comment syntax of `language`
+/// (a file extension such as `rs`, `py` or `html`): `// region: auth`,
+/// `# region: auth`, `<!-- region: auth -->` and so on. Regions may nest or
+/// overlap; the markers of other regions are part of the extracted text.
+pub fn extract_region<'a>(content: &'a str, name: &str, language: &str) -> Result<&'a str, RenderError> {
+    let styles = comment_styles(language);
+    let mut start = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        match (parse_marker(line, styles), start) {
+            (Some((true, region)), None) if region == name => start = Some(offset),
+            (Some((false, region)), Some(start)) if region == name => return Ok(&content[start..line_start]),
+            _ => {}
+        }
+    }
+
+    if start.is_some() {
+        return Err(RenderError::InvalidLineRange(format!(
+            "Region '{}' has no matching endregion: {}",
+            name, name
+        )));
+    }
+
+    let available = region_names(content, styles);
+    Err(RenderError::InvalidLineRange(if available.is_empty() {
+        format!("Region '{}' not found; the file has no regions", name)
+    } else {
+        format!("Region '{}' not found; available regions: {}", name, available.join(", "))
+    }))
+}
+
+/// The names of the regions opened in `content`, in order
+fn region_names<'a>(content: &'a str, styles: &[CommentStyle]) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    for line in content.lines() {
+        if let Some((true, name)) = parse_marker(line, styles) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = "use std::fmt;\n\n// region: auth\nfn login() {}\n  // region: inner\nfn check() {}\n  // endregion: inner\n// endregion: auth\n\n// region: db\nfn query() {}\n// endregion: db\n";
+
+    #[test]
+    fn test_extract_region() {
+        assert_eq!(extract_region(RUST, "db", "rs").unwrap(), "fn query() {}\n");
+        assert_eq!(extract_region(RUST, "inner", "rs").unwrap(), "fn check() {}\n");
+        assert_eq!(
+            extract_region(RUST, "auth", "rs").unwrap(),
+            "fn login() {}\n  // region: inner\nfn check() {}\n  // endregion: inner\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_region_comment_styles() {
+        let python = "# region: setup\nimport os\n# endregion: setup";
+        let html = "<!-- region: nav -->\n<nav></nav>\n<!--endregion: nav-->";
+
+        assert_eq!(extract_region(python, "setup", "py").unwrap(), "import os\n");
+        assert_eq!(extract_region(html, "nav", "html").unwrap(), "<nav></nav>\n");
+        assert_eq!(extract_region(python, "setup", "unknown").unwrap(), "import os\n");
+        // Markers in another language's syntax aren't recognized
+        assert!(extract_region(python, "setup", "rs").is_err());
+    }
+
+    #[test]
+    fn test_extract_region_lists_available_regions() {
+        match extract_region(RUST, "missing", "rs") {
+            Err(RenderError::InvalidLineRange(message)) => {
+                assert!(message.contains("'missing'"), "{}", message);
+                assert!(message.contains("auth, inner, db"), "{}", message);
+            }
+            other => panic!("Expected InvalidLineRange, got {:?}", other),
+        }
+
+        let unterminated = extract_region("// region: open\nfn f() {}", "open", "rs");
+        assert!(matches!(unterminated, Err(RenderError::InvalidLineRange(message)) if message.contains("endregion")));
+    }
+}
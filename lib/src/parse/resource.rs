@@ -1,16 +1,53 @@
 use crate::error::ParseError;
+use crate::graph::utils::normalize_separators;
 use crate::types::{Resource, ResourceRequirement};
-use std::path::PathBuf;
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
 use url::Url;
 
+/// `git://<host>/<repo>@<ref>:<path>`
+static GIT_RESOURCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^git://([^@]+)@([^:@]+):(.+)$").unwrap()
+});
+
 /// Parse a resource reference string into a Resource struct
 ///
 /// Handles:
 /// - Local file paths (relative/absolute)
-/// - URLs (http/https)
+/// - URLs (http/https); a `#fragment` becomes the resource's `section`
+/// - `file://` URLs, which are mapped onto local paths
+/// - Git references (`git://github.com/org/repo.git@main:path/to/file.md`)
 /// - Required (!) and optional (?) suffixes
 /// - Cache duration overrides
+/// - Fallback chains (`https://example.com/spec.md || ./vendor/spec.md`),
+///   tried in order; a suffix after the last candidate applies to the whole
+///   chain
 pub fn parse_resource(input: &str) -> Result<Resource, ParseError> {
+    if !input.contains("||") {
+        return parse_candidate(input);
+    }
+
+    let mut candidates = input
+        .split("||")
+        .map(|candidate| match candidate.trim() {
+            "" => Err(ParseError::InvalidResource(format!("Empty fallback in '{}'", input.trim()))),
+            candidate => parse_candidate(candidate),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let requirement = candidates.last().map(|last| last.requirement).unwrap_or_default();
+    let mut chain = candidates.pop().map(|last| last.with_requirement(requirement));
+    while let Some(candidate) = candidates.pop() {
+        let mut candidate = candidate.with_requirement(requirement);
+        candidate.fallback = chain.map(Box::new);
+        chain = Some(candidate);
+    }
+    chain.ok_or_else(|| ParseError::InvalidResource(input.to_string()))
+}
+
+/// Parse one candidate of a resource reference
+fn parse_candidate(input: &str) -> Result<Resource, ParseError> {
     let trimmed = input.trim();
 
     // Check for requirement suffix
@@ -22,32 +59,165 @@ pub fn parse_resource(input: &str) -> Result<Resource, ParseError> {
         (trimmed, ResourceRequirement::Default)
     };
 
+    if path_str.starts_with("git://") {
+        return parse_git_resource(path_str).map(|r| r.with_requirement(requirement));
+    }
+
+    // URL schemes are case-insensitive
+    let has_scheme = |scheme: &str| {
+        path_str
+            .get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    };
+
+    if has_scheme("file://") {
+        return parse_file_url(path_str).map(|r| r.with_requirement(requirement));
+    }
+
     // Try to parse as URL first
-    if path_str.starts_with("http://") || path_str.starts_with("https://") {
-        let url = Url::parse(path_str)?;
+    if has_scheme("http://") || has_scheme("https://") {
+        let mut url = Url::parse(path_str)?;
+        let section = take_fragment(&mut url);
         Ok(Resource::remote(url)
-            .with_requirement(requirement))
+            .with_requirement(requirement)
+            .with_section(section))
     } else {
-        // Treat as local path
-        let path = PathBuf::from(path_str);
+        // Treat as local path, written with either separator
+        let path = PathBuf::from(normalize_separators(path_str));
         Ok(Resource::local(path)
             .with_requirement(requirement))
     }
 }
 
+/// Remove the fragment from `url`, returning it unless it was empty
+///
+/// Fragments are never sent to the server, so they only select a section of
+/// the fetched document.
+fn take_fragment(url: &mut Url) -> Option<String> {
+    let section = url.fragment().filter(|f| !f.is_empty()).map(str::to_string);
+    url.set_fragment(None);
+    section
+}
+
+/// Parse a `file://` URL into a local resource
+///
+/// Only absolute paths on the local machine are accepted. Query strings are
+/// ignored; a fragment becomes the resource's `section`.
+fn parse_file_url(input: &str) -> Result<Resource, ParseError> {
+    let mut url = Url::parse(input)?;
+    let section = take_fragment(&mut url);
+    let path = url.to_file_path().map_err(|_| {
+        ParseError::InvalidResource(format!(
+            "Invalid file URL '{}': expected file:///<absolute path>",
+            input
+        ))
+    })?;
+
+    Ok(Resource::local(path).with_section(section))
+}
+
+/// Parse a `git://` reference into a `ResourceSource::Git` resource
+///
+/// The repository is cloned over HTTPS, so `git://github.com/org/repo.git`
+/// becomes the clone URL `https://github.com/org/repo.git`.
+fn parse_git_resource(input: &str) -> Result<Resource, ParseError> {
+    let caps = GIT_RESOURCE.captures(input).ok_or_else(|| {
+        ParseError::InvalidResource(format!(
+            "Invalid git reference '{}'. Expected git://<host>/<repo>@<ref>:<path>",
+            input
+        ))
+    })?;
+
+    let repo_url = format!("https://{}", &caps[1]);
+    let ref_ = caps[2].to_string();
+    let path = caps[3].to_string();
+
+    validate_git_path(&path)?;
+
+    Ok(Resource::git(repo_url, ref_, path))
+}
+
+/// Ensure a path inside a git repository cannot escape the checkout
+pub(crate) fn validate_git_path(path: &str) -> Result<(), ParseError> {
+    let escapes = Path::new(path).components().any(|c| {
+        matches!(c, Component::ParentDir | Component::RootDir | Component::Prefix(_))
+    });
+
+    if escapes || path.is_empty() {
+        return Err(ParseError::InvalidResource(format!(
+            "Invalid path '{}' in git reference: must be relative and may not contain '..'",
+            path
+        )));
+    }
+
+    Ok(())
+}
+
 /// Parse multiple resources from a space-separated string
+///
+/// Paths containing spaces can be wrapped in double quotes.
 pub fn parse_resources(input: &str) -> Result<Vec<Resource>, ParseError> {
-    input
-        .split_whitespace()
-        .map(parse_resource)
+    split_arguments(input)
+        .ok_or_else(|| ParseError::InvalidResource(format!("Unterminated quote in '{}'", input)))?
+        .iter()
+        .map(|arg| parse_resource(&arg.value))
         .collect()
 }
 
+/// A single argument of a directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Argument {
+    pub value: String,
+    /// Whether any part of the argument was inside double quotes
+    pub quoted: bool,
+}
+
+/// Split directive arguments on whitespace, shell style
+///
+/// Text inside double quotes is kept together and the quotes are removed, so
+/// `"./my notes.md"` is a single argument. Returns `None` when a quote is not
+/// closed.
+pub(crate) fn split_arguments(input: &str) -> Option<Vec<Argument>> {
+    let mut arguments = Vec::new();
+    let mut current: Option<Argument> = None;
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current
+                    .get_or_insert_with(|| Argument { value: String::new(), quoted: true })
+                    .quoted = true;
+            }
+            c if c.is_whitespace() && !in_quotes => arguments.extend(current.take()),
+            c => current
+                .get_or_insert_with(|| Argument { value: String::new(), quoted: false })
+                .value
+                .push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    arguments.extend(current);
+    Some(arguments)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::ResourceSource;
 
+    #[test]
+    fn test_parse_local_path_with_backslashes() {
+        let resource = parse_resource(r".\docs\intro.md!").unwrap();
+
+        assert!(matches!(resource.source, ResourceSource::Local(path) if path == Path::new("./docs/intro.md")));
+        assert!(matches!(resource.requirement, ResourceRequirement::Required));
+    }
+
     #[test]
     fn test_parse_local_path() {
         let resource = parse_resource("./path/to/file.md").unwrap();
@@ -98,6 +268,76 @@ mod tests {
         assert!(matches!(resource.requirement, ResourceRequirement::Required));
     }
 
+    #[test]
+    fn test_parse_url_fragment_becomes_section() {
+        let resource = parse_resource("https://example.com/doc.md?lang=en#setup!").unwrap();
+
+        match &resource.source {
+            // The fragment is dropped from the fetch URL; the query is kept
+            ResourceSource::Remote(url) => assert_eq!(url.as_str(), "https://example.com/doc.md?lang=en"),
+            _ => panic!("Expected remote resource"),
+        }
+        assert_eq!(resource.section.as_deref(), Some("setup"));
+        assert!(matches!(resource.requirement, ResourceRequirement::Required));
+
+        // An empty fragment selects nothing
+        let resource = parse_resource("https://example.com/doc.md#").unwrap();
+        assert_eq!(resource.section, None);
+        assert!(matches!(&resource.source, ResourceSource::Remote(url) if url.fragment().is_none()));
+    }
+
+    #[test]
+    fn test_parse_file_url() {
+        let resource = parse_resource("file:///abs/path/doc.md").unwrap();
+        assert_eq!(resource.source, ResourceSource::Local(PathBuf::from("/abs/path/doc.md")));
+        assert!(resource.cache_duration.is_none());
+
+        // Percent-encoding is decoded and the fragment becomes the section
+        let resource = parse_resource("file:///abs/my%20notes.md#intro?").unwrap();
+        assert_eq!(resource.source, ResourceSource::Local(PathBuf::from("/abs/my notes.md")));
+        assert_eq!(resource.section.as_deref(), Some("intro"));
+        assert!(matches!(resource.requirement, ResourceRequirement::Optional));
+
+        // Only local, absolute paths are allowed
+        assert!(matches!(
+            parse_resource("file://remote-host/doc.md"),
+            Err(ParseError::InvalidResource(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_git_resource() {
+        let resource = parse_resource("git://github.com/org/shared-docs.git@main:snippets/warning.md!").unwrap();
+
+        match resource.source {
+            ResourceSource::Git { repo_url, ref_, path } => {
+                assert_eq!(repo_url, "https://github.com/org/shared-docs.git");
+                assert_eq!(ref_, "main");
+                assert_eq!(path, "snippets/warning.md");
+            }
+            _ => panic!("Expected git resource"),
+        }
+        assert!(matches!(resource.requirement, ResourceRequirement::Required));
+    }
+
+    #[test]
+    fn test_parse_git_resource_rejects_traversal() {
+        let result = parse_resource("git://github.com/org/docs.git@main:../../etc/passwd");
+        assert!(matches!(result, Err(ParseError::InvalidResource(_))));
+
+        let result = parse_resource("git://github.com/org/docs.git@main:snippets/../../secret.md");
+        assert!(matches!(result, Err(ParseError::InvalidResource(_))));
+
+        let result = parse_resource("git://github.com/org/docs.git@main:/etc/passwd");
+        assert!(matches!(result, Err(ParseError::InvalidResource(_))));
+    }
+
+    #[test]
+    fn test_parse_git_resource_missing_ref() {
+        let result = parse_resource("git://github.com/org/docs.git:README.md");
+        assert!(matches!(result, Err(ParseError::InvalidResource(_))));
+    }
+
     #[test]
     fn test_parse_multiple_resources() {
         let resources = parse_resources("./a.md ./b.md https://example.com/c.md").unwrap();
@@ -107,4 +347,33 @@ mod tests {
         assert!(matches!(resources[1].source, ResourceSource::Local(_)));
         assert!(matches!(resources[2].source, ResourceSource::Remote(_)));
     }
+
+    #[test]
+    fn test_parse_resources_with_quoted_path() {
+        let resources = parse_resources(r#"./a.md "./my notes.md"! ./b.md"#).unwrap();
+
+        assert_eq!(resources.len(), 3);
+        assert_eq!(resources[1].source, ResourceSource::Local(PathBuf::from("./my notes.md")));
+        assert!(matches!(resources[1].requirement, ResourceRequirement::Required));
+    }
+
+    #[test]
+    fn test_split_arguments_unterminated_quote() {
+        assert!(split_arguments(r#""./open.md"#).is_none());
+        assert!(parse_resources(r#"./a.md "./open.md"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_fallback_chain() {
+        let resource = parse_resource("https://example.com/spec.md || ./vendor/spec.md || ./old/spec.md!").unwrap();
+
+        let candidates: Vec<_> = resource.candidates().map(Resource::label).collect();
+        assert_eq!(candidates, ["https://example.com/spec.md", "./vendor/spec.md", "./old/spec.md"]);
+        assert!(resource
+            .candidates()
+            .all(|candidate| matches!(candidate.requirement, ResourceRequirement::Required)));
+
+        assert!(parse_resource("./spec.md").unwrap().fallback.is_none());
+        assert!(matches!(parse_resource("./spec.md || "), Err(ParseError::InvalidResource(_))));
+    }
 }
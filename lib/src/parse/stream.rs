@@ -0,0 +1,72 @@
+//! Parsing documents from streams
+//!
+//! Lets documents be parsed from stdin or a network stream without first
+//! reading them into a string. The frontmatter is read up to its closing
+//! delimiter; the markdown body is then buffered, since `pulldown-cmark`
+//! needs the whole body at once.
+
+use crate::error::{ParseError, ParseWarning};
+use crate::types::{Document, Resource};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+use tracing::{debug, instrument};
+
+use super::{extract_frontmatter_async, ParseOptions};
+
+/// Parse a DarkMatter document from a stream
+///
+/// Produces the same `Document` and warnings as
+/// [`parse_document`](super::parse_document) would for the stream's full
+/// content, except that warning spans are in the coordinates of the body
+/// after the frontmatter. `source` identifies where the content came from.
+#[instrument(skip(reader), fields(source = ?source.source))]
+pub async fn parse_document_async(
+    reader: impl AsyncRead + Unpin,
+    source: Resource,
+    opts: ParseOptions,
+) -> Result<(Document, Vec<ParseWarning>), ParseError> {
+    // 1. Extract frontmatter
+    let (frontmatter, mut body_reader) = extract_frontmatter_async(BufReader::new(reader)).await?;
+
+    // 2. Buffer the body for the markdown parser
+    let mut body = String::new();
+    body_reader
+        .read_to_string(&mut body)
+        .await
+        .map_err(|e| ParseError::ReadFailed(e.to_string()))?;
+    debug!("Read {} byte body from stream", body.len());
+
+    let (document, warnings) = super::build_document(frontmatter, &body, source)?;
+    opts.check(&document.resource, &warnings)?;
+    Ok((document, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_document;
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_stream_matches_parse_document() {
+        let content = "---\ntitle: Streamed\n---\n# {{title}}\n\n::file ./other.md\n\nSome text.\n";
+        let source = Resource::local(PathBuf::from("doc.md"));
+
+        let (streamed, _) = parse_document_async(content.as_bytes(), source.clone(), ParseOptions::default())
+            .await
+            .unwrap();
+        let (parsed, _) = parse_document(content, source, ParseOptions::default()).unwrap();
+
+        assert_eq!(streamed.frontmatter.get_string("title"), Some("Streamed"));
+        assert_eq!(format!("{:?}", streamed.content), format!("{:?}", parsed.content));
+        assert_eq!(streamed.dependencies.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_rejects_invalid_utf8() {
+        let content: &[u8] = b"---\ntitle: x\n---\nbad \xff body";
+        let result =
+            parse_document_async(content, Resource::local(PathBuf::from("doc.md")), ParseOptions::default()).await;
+
+        assert!(matches!(result, Err(ParseError::ReadFailed(_))));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::types::DarkMatterNode;
+use crate::error::RenderError;
+
+use super::html::render_fragment;
+
+/// Render an aspect ratio container to HTML
+///
+/// The ratio differs per block, so the CSS is written inline rather than into
+/// a shared stylesheet. The container reserves its height with the
+/// padding-bottom intrinsic ratio technique, which works in every browser,
+/// and the content is stretched over it.
+pub fn render_aspect_ratio(
+    numerator: u32,
+    denominator: u32,
+    content: &[DarkMatterNode],
+) -> Result<String, RenderError> {
+    if numerator == 0 || denominator == 0 {
+        return Err(RenderError::HtmlGenerationFailed(format!(
+            "Invalid aspect ratio {}:{}",
+            numerator, denominator
+        )));
+    }
+
+    let content_html = render_fragment(content)?;
+
+    let html = format!(
+        r#"<div class="composition-aspect-ratio" data-ratio="{numerator}:{denominator}" style="position: relative; width: 100%; height: 0; padding-bottom: calc({denominator} / {numerator} * 100%);">
+  <div class="composition-aspect-ratio-content" style="position: absolute; top: 0; left: 0; width: 100%; height: 100%; overflow: hidden;">
+    {content_html}
+  </div>
+</div>"#
+    );
+
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarkdownContent;
+
+    #[test]
+    fn test_render_aspect_ratio() {
+        let content = vec![DarkMatterNode::Markdown(MarkdownContent {
+            raw: r#"<iframe src="https://example.com/embed"></iframe>"#.to_string(),
+            frontmatter: None,
+        })];
+
+        let html = render_aspect_ratio(16, 9, &content).unwrap();
+
+        assert!(html.contains("composition-aspect-ratio"));
+        assert!(html.contains(r#"data-ratio="16:9""#));
+        assert!(html.contains("padding-bottom: calc(9 / 16 * 100%)"));
+        assert!(html.contains(r#"<iframe src="https://example.com/embed"></iframe>"#));
+    }
+
+    #[test]
+    fn test_render_aspect_ratio_renders_nested_containers() {
+        let inner = DarkMatterNode::AspectRatio {
+            numerator: 1,
+            denominator: 1,
+            content: vec![DarkMatterNode::Text("square".to_string())],
+        };
+
+        let html = render_aspect_ratio(4, 3, &[inner]).unwrap();
+
+        assert!(html.contains("padding-bottom: calc(3 / 4 * 100%)"));
+        assert!(html.contains("padding-bottom: calc(1 / 1 * 100%)"));
+        assert!(html.contains("square"));
+    }
+
+    #[test]
+    fn test_render_aspect_ratio_rejects_zero_side() {
+        assert!(render_aspect_ratio(16, 0, &[]).is_err());
+        assert!(render_aspect_ratio(0, 9, &[]).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Stylesheets and scripts the crate's components need, once per document
+//!
+//! The components a document uses are collected from its whole node tree,
+//! containers included, before anything is rendered. Each asset they need
+//! is then written once, at the start of the document, however many
+//! components use it and however deeply they are nested.
+
+use crate::audio::audio_chapters_js;
+use crate::types::DarkMatterNode;
+use pulldown_cmark::{Event, Options, Parser};
+use std::collections::BTreeSet;
+
+use super::charts::chart_table_css;
+use super::diff::diff_css;
+use super::math::{math_js, math_stylesheet};
+use super::mermaid::mermaid_js;
+use super::table::env_table_css;
+use super::terminal::{clipboard_js, terminal_css};
+use super::theme::component_theme_css;
+use super::youtube::{youtube_css, youtube_js};
+
+/// Markup of an audio player's chapter list (see
+/// [`generate_audio_html`](crate::audio::generate_audio_html))
+const AUDIO_CHAPTERS_MARKER: &str = r#"<ol class="audio-chapters">"#;
+
+/// Markup of an environment table (see
+/// [`render_env_table`](super::table::render_env_table))
+const ENV_TABLE_MARKER: &str = r#"<table class="composition-env-table">"#;
+
+/// A stylesheet or script shared by every component of one kind
+///
+/// Assets are written in the order they are declared here, so the theme
+/// comes before the component styles reading its variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum ComponentAsset {
+    /// The CSS variables component styles read their colors from
+    Theme,
+    YouTube,
+    EnvTable,
+    Mermaid,
+    Diff,
+    Terminal,
+    ChartTable,
+    Math,
+    AudioChapters,
+}
+
+impl ComponentAsset {
+    /// The `<style>`, `<link>` and `<script>` tags of the asset
+    fn html(self) -> String {
+        match self {
+            ComponentAsset::Theme => format!("<style id=\"dm-theme\">{}</style>", component_theme_css()),
+            ComponentAsset::YouTube => format!(
+                "<style id=\"dm-youtube\">{}</style>\n<script id=\"dm-youtube\">{}</script>",
+                youtube_css(),
+                youtube_js()
+            ),
+            ComponentAsset::EnvTable => format!("<style id=\"dm-env-table\">{}</style>", env_table_css()),
+            ComponentAsset::Mermaid => format!("<script type=\"module\" id=\"dm-mermaid\">{}</script>", mermaid_js()),
+            ComponentAsset::Diff => format!("<style id=\"dm-diff\">{}</style>", diff_css()),
+            ComponentAsset::Terminal => format!(
+                "<style id=\"dm-terminal\">{}</style>\n<script id=\"dm-clipboard\">{}</script>",
+                terminal_css(),
+                clipboard_js()
+            ),
+            ComponentAsset::ChartTable => format!("<style id=\"dm-chart-table\">{}</style>", chart_table_css()),
+            ComponentAsset::Math => format!(
+                "<link rel=\"stylesheet\" id=\"dm-katex\" href=\"{}\">\n<script type=\"module\" id=\"dm-katex\">{}</script>",
+                math_stylesheet(),
+                math_js()
+            ),
+            ComponentAsset::AudioChapters => format!("<script id=\"dm-audio-chapters\">{}</script>", audio_chapters_js()),
+        }
+    }
+
+    /// The assets a single node needs, not counting its children
+    fn of(node: &DarkMatterNode) -> &'static [ComponentAsset] {
+        match node {
+            DarkMatterNode::YouTube { .. } => &[ComponentAsset::Theme, ComponentAsset::YouTube],
+            DarkMatterNode::EnvTable { .. } => &[ComponentAsset::Theme, ComponentAsset::EnvTable],
+            DarkMatterNode::Mermaid { .. } => &[ComponentAsset::Mermaid],
+            DarkMatterNode::Diff { .. } => &[ComponentAsset::Diff],
+            DarkMatterNode::Terminal { .. } => &[ComponentAsset::Terminal],
+            DarkMatterNode::Math { .. } => &[ComponentAsset::Math],
+            DarkMatterNode::BarChart { with_table: true, .. }
+            | DarkMatterNode::LineChart { with_table: true, .. }
+            | DarkMatterNode::PieChart { with_table: true, .. }
+            | DarkMatterNode::AreaChart { with_table: true, .. }
+            | DarkMatterNode::BubbleChart { with_table: true, .. } => &[ComponentAsset::ChartTable],
+            _ => &[],
+        }
+    }
+
+    /// The assets of the components in a Markdown node's HTML
+    ///
+    /// Audio players are rendered into Markdown nodes by the audio pass, and
+    /// env tables by interpolation.
+    fn in_markdown(markdown: &str) -> impl Iterator<Item = ComponentAsset> {
+        let env_table = markdown.contains(ENV_TABLE_MARKER);
+        [
+            (ComponentAsset::Math, has_math(markdown)),
+            (ComponentAsset::AudioChapters, markdown.contains(AUDIO_CHAPTERS_MARKER)),
+            (ComponentAsset::Theme, env_table),
+            (ComponentAsset::EnvTable, env_table),
+        ]
+        .into_iter()
+        .filter_map(|(asset, used)| used.then_some(asset))
+    }
+}
+
+/// The assets a document needs, each listed once
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AssetRegistry {
+    assets: BTreeSet<ComponentAsset>,
+}
+
+impl AssetRegistry {
+    /// Collect the assets of every node in the tree
+    pub(crate) fn collect(nodes: &[DarkMatterNode]) -> Self {
+        let mut registry = Self::default();
+        registry.visit(nodes);
+        registry
+    }
+
+    fn visit(&mut self, nodes: &[DarkMatterNode]) {
+        for node in nodes {
+            self.assets.extend(ComponentAsset::of(node));
+            if let DarkMatterNode::Markdown(content) = node {
+                self.assets.extend(ComponentAsset::in_markdown(&content.raw));
+            }
+            for children in node.children() {
+                self.visit(children);
+            }
+        }
+    }
+
+    /// The tags of every asset, one per line, each line ending in a newline;
+    /// empty when no asset is needed
+    pub(crate) fn to_html(&self) -> String {
+        self.assets.iter().map(|asset| asset.html() + "\n").collect()
+    }
+}
+
+/// Whether Markdown holds a `$...$` or `$$...$$` formula, which renders
+/// with the same markup as a math node
+fn has_math(markdown: &str) -> bool {
+    markdown.contains('$')
+        && Parser::new_ext(markdown, Options::ENABLE_MATH)
+            .any(|event| matches!(event, Event::InlineMath(_) | Event::DisplayMath(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_markdown;
+
+    #[test]
+    fn test_assets_are_collected_from_nested_components() {
+        let nodes = parse_markdown(
+            "::columns lg: 2\n::mermaid\ngraph TD\n::end\n::break\n::summary\nIn $x$\n::details\n::mermaid\ngraph LR\n::end\n::end\n::end",
+        )
+        .unwrap();
+
+        let registry = AssetRegistry::collect(&nodes);
+
+        assert_eq!(registry.assets.into_iter().collect::<Vec<_>>(), vec![ComponentAsset::Mermaid, ComponentAsset::Math]);
+    }
+
+    #[test]
+    fn test_audio_chapters_need_their_script() {
+        let player = crate::types::MarkdownContent {
+            raw: "<div class=\"audio-player\">\n  <ol class=\"audio-chapters\">\n  </ol>\n</div>".to_string(),
+            frontmatter: None,
+        };
+
+        let registry = AssetRegistry::collect(&[DarkMatterNode::Markdown(player)]);
+
+        assert_eq!(registry.assets.into_iter().collect::<Vec<_>>(), vec![ComponentAsset::AudioChapters]);
+        assert!(ComponentAsset::AudioChapters.html().contains("data-start"));
+    }
+
+    #[test]
+    fn test_env_tables_need_their_stylesheet() {
+        let rendered = crate::types::MarkdownContent {
+            raw: super::super::table::render_env_table(Some("COMPOSITION_TEST_NO_SUCH_PREFIX_"), false, false, &[], &[])
+                .unwrap(),
+            frontmatter: None,
+        };
+        let unresolved = DarkMatterNode::EnvTable { filter: None, sort: false, show_values: false };
+        let expected = vec![ComponentAsset::Theme, ComponentAsset::EnvTable];
+
+        let registry = AssetRegistry::collect(&[DarkMatterNode::Markdown(rendered)]);
+        assert_eq!(registry.assets.into_iter().collect::<Vec<_>>(), expected);
+
+        let registry = AssetRegistry::collect(&[unresolved]);
+        assert_eq!(registry.assets.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_dollar_signs_outside_formulas_need_no_math() {
+        assert!(!has_math("It costs $5.\n\n```\n$x$\n```"));
+        assert!(has_math("Euler: $e^{i\\pi} = -1$"));
+    }
+}
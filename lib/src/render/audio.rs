@@ -1,43 +1,67 @@
-use crate::audio::{process_audio, generate_audio_html, AudioHtmlOptions, AudioInput, AudioSource, AudioProcessingConfig, AudioCache};
-use crate::error::RenderError;
-use crate::types::DarkMatterNode;
+use crate::audio::{copy_captions, process_audio, generate_audio_html, AudioHtmlOptions, AudioInput, AudioSource, AudioProcessingConfig, AudioCache};
+use crate::cache::CacheOperations;
+use crate::error::{ParseWarning, RenderError};
+use crate::graph::utils::normalize_separators;
+use crate::types::{Captions, DarkMatterNode, MarkdownContent};
 use std::path::{Path, PathBuf};
-use surrealdb::{Surreal, engine::local::Db};
 use tracing::instrument;
 
 /// Process audio directives in a list of nodes
 ///
 /// This function finds Audio nodes and processes them into HTML,
-/// returning a new list with Audio nodes replaced by Text nodes containing HTML.
-#[instrument(skip(nodes, db))]
+/// returning a new list with Audio nodes replaced by Markdown nodes holding
+/// the player's HTML, which markdown rendering passes through unchanged.
+/// Copied files are named by their hash relative to `project_root`.
+#[instrument(skip(nodes, cache))]
 pub async fn process_audio_nodes(
     nodes: &[DarkMatterNode],
     output_dir: &Path,
-    db: &Surreal<Db>,
+    cache: &CacheOperations,
     inline_mode: bool,
     base_path: Option<&PathBuf>,
+    project_root: Option<&Path>,
 ) -> Result<Vec<DarkMatterNode>, RenderError> {
-    let mut result = Vec::new();
     let config = AudioProcessingConfig::default();
-    let audio_cache = AudioCache::new(db.clone());
+    let (nodes, _warnings) =
+        process_audio_nodes_with_warnings(nodes, output_dir, cache, inline_mode, base_path, &config, project_root)
+            .await?;
+    Ok(nodes)
+}
+
+/// Process audio directives in a list of nodes with the given limits,
+/// returning the problems that didn't stop them from rendering
+///
+/// Like [`process_audio_nodes`], but the warnings of every audio file, such
+/// as inline audio over `config.max_inline_size`, are returned in the order
+/// the files appear instead of only being logged.
+#[instrument(skip(nodes, cache, config))]
+pub async fn process_audio_nodes_with_warnings(
+    nodes: &[DarkMatterNode],
+    output_dir: &Path,
+    cache: &CacheOperations,
+    inline_mode: bool,
+    base_path: Option<&PathBuf>,
+    config: &AudioProcessingConfig,
+    project_root: Option<&Path>,
+) -> Result<(Vec<DarkMatterNode>, Vec<ParseWarning>), RenderError> {
+    let mut result = Vec::new();
+    let mut warnings = Vec::new();
+    let audio_cache = AudioCache::from_operations(cache);
+    let html_options = AudioHtmlOptions { inline: inline_mode, ..AudioHtmlOptions::default() };
 
     for node in nodes {
         match node {
-            DarkMatterNode::Audio { source, name } => {
-                // Resolve relative paths
-                let resolved_path = if Path::new(source).is_relative() {
-                    if let Some(base) = base_path {
-                        base.parent()
-                            .ok_or_else(|| RenderError::InvalidPath(base.display().to_string()))?
-                            .join(source)
-                    } else {
-                        std::env::current_dir()
-                            .map_err(|e| RenderError::IoError(e.to_string()))?
-                            .join(source)
-                    }
-                } else {
-                    PathBuf::from(source)
-                };
+            DarkMatterNode::Audio { source, name, captions } => {
+                let resolved_path = resolve_path(source, base_path)?;
+                let captions = captions
+                    .as_ref()
+                    .map(|captions| {
+                        Ok::<_, RenderError>(Captions {
+                            path: resolve_path(&captions.path, base_path)?.display().to_string(),
+                            language: captions.language.clone(),
+                        })
+                    })
+                    .transpose()?;
 
                 // Create AudioInput
                 let input = AudioInput {
@@ -45,22 +69,31 @@ pub async fn process_audio_nodes(
                     name: name.clone(),
                 };
 
-                // Process audio
-                match process_audio(input, output_dir, &audio_cache, inline_mode, &config).await {
-                    Ok(output) => {
+                // Process audio, then copy its captions beside it
+                let processed = process_audio(input, output_dir, &audio_cache, inline_mode, config, project_root)
+                    .await
+                    .and_then(|mut output| {
+                        output.captions = captions
+                            .map(|captions| copy_captions(&captions, output_dir, inline_mode, project_root))
+                            .transpose()?;
+                        Ok(output)
+                    });
+                match processed {
+                    Ok(mut output) => {
+                        warnings.append(&mut output.warnings);
                         // Generate HTML
-                        let html = generate_audio_html(&output, &AudioHtmlOptions::default());
-                        result.push(DarkMatterNode::Text(html));
+                        let html = generate_audio_html(&output, &html_options);
+                        result.push(raw_html(html));
                     }
                     Err(e) => {
                         // Emit error HTML instead of failing the entire render
                         let error_html = format!(
-                            r#"<div class="audio-error" style="border: 2px solid #ef4444; background: #fee2e2; color: #991b1b; padding: 1rem; border-radius: 0.5rem; margin: 1rem 0;">
+                            r#"<div class="audio-error" style="border: 2px solid var(--dm-danger-border, #ef4444); background: var(--dm-danger-bg, #fee2e2); color: var(--dm-danger-fg, #991b1b); padding: 1rem; border-radius: 0.5rem; margin: 1rem 0;">
                                 <strong>Audio Error:</strong> {}
                             </div>"#,
                             html_escape(&e.to_string())
                         );
-                        result.push(DarkMatterNode::Text(error_html));
+                        result.push(raw_html(error_html));
                     }
                 }
             }
@@ -70,7 +103,29 @@ pub async fn process_audio_nodes(
         }
     }
 
-    Ok(result)
+    Ok((result, warnings))
+}
+
+/// A node rendering as the given HTML; a text node would be escaped
+fn raw_html(html: String) -> DarkMatterNode {
+    DarkMatterNode::Markdown(MarkdownContent { raw: html, frontmatter: None })
+}
+
+/// Resolve a path from a directive against the document's directory
+fn resolve_path(path: &str, base_path: Option<&PathBuf>) -> Result<PathBuf, RenderError> {
+    let path = normalize_separators(path);
+    if !Path::new(&path).is_relative() {
+        return Ok(PathBuf::from(path));
+    }
+
+    let base = match base_path {
+        Some(base) => base
+            .parent()
+            .ok_or_else(|| RenderError::InvalidPath(base.display().to_string()))?
+            .to_path_buf(),
+        None => std::env::current_dir().map_err(|e| RenderError::IoError(e.to_string()))?,
+    };
+    Ok(base.join(path))
 }
 
 /// HTML escape function
@@ -91,4 +146,30 @@ mod tests {
         assert_eq!(html_escape("a & b"), "a &amp; b");
         assert_eq!(html_escape(r#"x="y""#), "x=&quot;y&quot;");
     }
+
+    #[tokio::test]
+    async fn test_oversized_inline_audio_warns_once() {
+        let cache = CacheOperations::new(crate::cache::init_memory_database().await.unwrap());
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let fixture = PathBuf::from("../tests/fixtures/audio/test.wav").canonicalize().unwrap();
+        let nodes = vec![
+            DarkMatterNode::Text("Listen:".to_string()),
+            DarkMatterNode::Audio { source: fixture.display().to_string(), name: None, captions: None },
+        ];
+        let config = AudioProcessingConfig { max_inline_size: 1, ..Default::default() };
+
+        let (nodes, warnings) =
+            process_audio_nodes_with_warnings(&nodes, output_dir.path(), &cache, true, None, &config, None)
+                .await
+                .unwrap();
+
+        assert!(
+            matches!(&nodes[1], DarkMatterNode::Markdown(html) if html.raw.contains("data:audio/wav;base64,")),
+            "{:?}",
+            nodes[1]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, crate::error::ParseWarningCode::OversizedInlineAudio);
+        assert!(warnings[0].message.contains("test.wav"), "{}", warnings[0].message);
+    }
 }
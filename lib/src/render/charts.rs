@@ -1,110 +1,169 @@
-use crate::types::{ChartData, DataPoint};
+//! SVG chart rendering
+//!
+//! Every chart is an `<svg role="img">` labelled by a generated `<title>`
+//! and described by a `<desc>` summarizing its data, for screen readers.
+//! Charts with `--with-table` are followed by a table of their data, hidden
+//! visually by a stylesheet the orchestration layer adds once per document;
+//! where styles are stripped, as in many email and RSS readers, the table is
+//! shown instead.
+//!
+//! Bar and line charts draw labeled axes, and a legend naming the series
+//! when there are several or a CSV header named them. Pie, area and bubble
+//! charts show the first series.
+//!
+//! Each bar, point, slice and bubble holds a `<title>` with its label and
+//! value, which browsers show as a tooltip on hover; no script is needed.
+//!
+//! Pie charts become donuts with a [`PieChartOptions::inner_radius`], and
+//! can label each slice with its share of the total.
+
+use crate::types::{AxisScale, ChartData, ChartSeries, DataPoint, DataSeries, PieChartOptions, TableSource};
 use crate::error::RenderError;
+use super::table::render_table;
+use xxhash_rust::xxh3::xxh3_64;
+
+const CHART_TABLE_CSS: &str = r#"
+.dm-chart-table {
+  position: absolute;
+  width: 1px;
+  height: 1px;
+  padding: 0;
+  margin: -1px;
+  overflow: hidden;
+  clip: rect(0, 0, 0, 0);
+  white-space: nowrap;
+  border: 0;
+}
+"#;
+
+/// Colors given to series in order
+const SERIES_COLORS: [&str; 6] = ["#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899"];
+
+/// Largest hole a donut chart leaves, as a fraction of its radius
+const MAX_INNER_RADIUS: f64 = 0.95;
+
+/// Number of intervals the y axis is divided into
+const Y_TICKS: usize = 4;
 
 /// Render a bar chart to SVG
+///
+/// Each x label gets a group of bars, one per series.
 pub fn render_bar_chart(data: &ChartData, width: u32, height: u32) -> Result<String, RenderError> {
-    let points = extract_data_points(data)?;
+    let data = chart_series(data)?;
 
-    if points.is_empty() {
+    if is_empty(&data) {
         return Ok(String::from("<svg></svg>"));
     }
 
-    let max_value = points.iter()
-        .map(|p| p.value)
-        .fold(f64::NEG_INFINITY, f64::max);
-
-    let bar_width = (width as f64 * 0.8) / points.len() as f64;
-    let margin = width as f64 * 0.1;
-    let chart_height = height as f64 * 0.8;
-    let margin_top = height as f64 * 0.1;
+    let plot = Plot::new(width, height, has_legend(&data));
+    let max_value = axis_max(&data);
+    let group_width = plot.width / data.x_labels.len() as f64;
+    let bar_width = group_width * 0.8 / data.series.len() as f64;
 
-    let mut svg = format!(
-        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="composition-bar-chart">"#,
-        width, height
-    );
+    let mut svg = open_svg("Bar chart", "composition-bar-chart", &data, width, height);
+    svg.push_str(&axes(&data, &plot, max_value, height));
 
     // Draw bars
-    for (i, point) in points.iter().enumerate() {
-        let bar_height = (point.value / max_value) * chart_height;
-        let x = margin + (i as f64 * bar_width);
-        let y = margin_top + (chart_height - bar_height);
-
-        svg.push_str(&format!(
-            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#3b82f6" class="bar"/>"##,
-            x, y, bar_width * 0.8, bar_height
-        ));
+    for (i, label) in data.x_labels.iter().enumerate() {
+        let group_x = plot.left + i as f64 * group_width;
+        for (s, series) in data.series.iter().enumerate() {
+            let bar_height = (series.values[i].max(0.0) / max_value) * plot.height;
+            svg.push_str(&format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" class="bar">{}</rect>"#,
+                group_x + group_width * 0.1 + s as f64 * bar_width,
+                plot.bottom() - bar_height,
+                bar_width,
+                bar_height,
+                series_color(s),
+                tooltip(&data, series, label, series.values[i])
+            ));
+        }
 
         // Add label
-        svg.push_str(&format!(
-            r#"<text x="{}" y="{}" text-anchor="middle" font-size="12" class="label">{}</text>"#,
-            x + (bar_width * 0.4),
-            height - 5,
-            point.label
-        ));
+        svg.push_str(&x_label(group_x + group_width / 2.0, &plot, label));
     }
 
+    svg.push_str(&legend(&data, &plot));
     svg.push_str("</svg>");
     Ok(svg)
 }
 
 /// Render a line chart to SVG
+///
+/// Each series is a line. Numeric x labels are placed in proportion to
+/// their value, others evenly.
 pub fn render_line_chart(data: &ChartData, width: u32, height: u32) -> Result<String, RenderError> {
-    let points = extract_data_points(data)?;
+    let data = chart_series(data)?;
 
-    if points.is_empty() {
+    if is_empty(&data) {
         return Ok(String::from("<svg></svg>"));
     }
 
-    let max_value = points.iter()
-        .map(|p| p.value)
-        .fold(f64::NEG_INFINITY, f64::max);
-
-    let margin = 40.0;
-    let chart_width = width as f64 - (2.0 * margin);
-    let chart_height = height as f64 - (2.0 * margin);
+    let plot = Plot::new(width, height, has_legend(&data));
+    let max_value = axis_max(&data);
+    let xs = x_positions(&data, &plot);
 
-    let mut svg = format!(
-        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="composition-line-chart">"#,
-        width, height
-    );
-
-    // Build path data
-    let mut path_data = String::from("M");
-    for (i, point) in points.iter().enumerate() {
-        let x = margin + (i as f64 * chart_width / (points.len() - 1).max(1) as f64);
-        let y = margin + (chart_height - (point.value / max_value) * chart_height);
-
-        if i > 0 {
-            path_data.push_str(&format!(" L{},{}", x, y));
-        } else {
-            path_data.push_str(&format!("{},{}", x, y));
-        }
+    let mut svg = open_svg("Line chart", "composition-line-chart", &data, width, height);
+    svg.push_str(&axes(&data, &plot, max_value, height));
+    for (x, label) in xs.iter().zip(&data.x_labels) {
+        svg.push_str(&x_label(*x, &plot, label));
     }
 
-    // Draw line
-    svg.push_str(&format!(
-        r##"<path d="{}" fill="none" stroke="#3b82f6" stroke-width="2" class="line"/>"##,
-        path_data
-    ));
-
-    // Draw points
-    for (i, point) in points.iter().enumerate() {
-        let x = margin + (i as f64 * chart_width / (points.len() - 1).max(1) as f64);
-        let y = margin + (chart_height - (point.value / max_value) * chart_height);
+    for (s, series) in data.series.iter().enumerate() {
+        let color = series_color(s);
+        let points: Vec<(f64, f64)> = xs
+            .iter()
+            .zip(&series.values)
+            .map(|(x, value)| (*x, plot.bottom() - (value / max_value) * plot.height))
+            .collect();
+
+        // Build path data
+        let mut path_data = String::from("M");
+        for (i, (x, y)) in points.iter().enumerate() {
+            if i > 0 {
+                path_data.push_str(&format!(" L{},{}", x, y));
+            } else {
+                path_data.push_str(&format!("{},{}", x, y));
+            }
+        }
 
+        // Draw line
         svg.push_str(&format!(
-            r##"<circle cx="{}" cy="{}" r="4" fill="#3b82f6" class="point"/>"##,
-            x, y
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="2" class="line"/>"#,
+            path_data, color
         ));
+
+        // Draw points
+        for (((x, y), label), value) in points.into_iter().zip(&data.x_labels).zip(&series.values) {
+            svg.push_str(&format!(
+                r#"<circle cx="{}" cy="{}" r="4" fill="{}" class="point">{}</circle>"#,
+                x, y, color, tooltip(&data, series, label, *value)
+            ));
+        }
     }
 
+    svg.push_str(&legend(&data, &plot));
     svg.push_str("</svg>");
     Ok(svg)
 }
 
 /// Render a pie chart to SVG
 pub fn render_pie_chart(data: &ChartData, width: u32, height: u32) -> Result<String, RenderError> {
-    let points = extract_data_points(data)?;
+    render_pie_chart_with_options(data, width, height, &PieChartOptions::default())
+}
+
+/// Render a pie or donut chart to SVG
+///
+/// A donut's slices are rings around a hole `inner_radius` times the
+/// chart's radius, so whatever is behind the chart shows through.
+pub fn render_pie_chart_with_options(
+    data: &ChartData,
+    width: u32,
+    height: u32,
+    options: &PieChartOptions,
+) -> Result<String, RenderError> {
+    let data = first_series(chart_series(data)?);
+    let points = data_points(&data);
 
     if points.is_empty() {
         return Ok(String::from("<svg></svg>"));
@@ -114,50 +173,73 @@ pub fn render_pie_chart(data: &ChartData, width: u32, height: u32) -> Result<Str
     let center_x = width as f64 / 2.0;
     let center_y = height as f64 / 2.0;
     let radius = (width.min(height) as f64 / 2.0) * 0.8;
+    let inner_radius = radius * options.inner_radius.clamp(0.0, MAX_INNER_RADIUS);
+    let at = |r: f64, angle: f64| (center_x + r * angle.to_radians().cos(), center_y + r * angle.to_radians().sin());
 
-    let mut svg = format!(
-        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="composition-pie-chart">"#,
-        width, height
-    );
+    let (title, class) = match inner_radius > 0.0 {
+        true => ("Donut chart", "composition-pie-chart composition-donut-chart"),
+        false => ("Pie chart", "composition-pie-chart"),
+    };
+    let mut svg = open_svg(title, class, &data, width, height);
 
-    let colors = ["#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899"];
     let mut current_angle = -90.0; // Start at top
 
     for (i, point) in points.iter().enumerate() {
         let slice_angle = (point.value / total) * 360.0;
         let end_angle = current_angle + slice_angle;
 
-        let start_rad = current_angle.to_radians();
-        let end_rad = end_angle.to_radians();
-
-        let x1 = center_x + radius * start_rad.cos();
-        let y1 = center_y + radius * start_rad.sin();
-        let x2 = center_x + radius * end_rad.cos();
-        let y2 = center_y + radius * end_rad.sin();
+        let (x1, y1) = at(radius, current_angle);
+        let (x2, y2) = at(radius, end_angle);
 
         let large_arc = if slice_angle > 180.0 { 1 } else { 0 };
 
+        // A donut slice comes back along the hole's edge instead of to the center
+        let inner_edge = if inner_radius > 0.0 {
+            let (x3, y3) = at(inner_radius, end_angle);
+            let (x4, y4) = at(inner_radius, current_angle);
+            format!("L{},{} A{},{} 0 {},0 {},{}", x3, y3, inner_radius, inner_radius, large_arc, x4, y4)
+        } else {
+            format!("L{},{}", center_x, center_y)
+        };
+
         svg.push_str(&format!(
-            r#"<path d="M{},{} L{},{} A{},{} 0 {},{} {},{} Z" fill="{}" class="slice"/>"#,
-            center_x, center_y,
+            r#"<path d="M{},{} A{},{} 0 {},{} {},{} {} Z" fill="{}" class="slice">{}</path>"#,
             x1, y1,
             radius, radius,
             large_arc,
             1,
             x2, y2,
-            colors[i % colors.len()]
+            inner_edge,
+            series_color(i),
+            point_tooltip(point)
         ));
 
         current_angle = end_angle;
     }
 
+    if options.show_percentages {
+        // Labels sit halfway across the slice, or across the ring of a donut
+        let label_radius = (radius + inner_radius) / 2.0;
+        let mut current_angle = -90.0;
+        for (point, percent) in points.iter().zip(rounded_percentages(&points)) {
+            let slice_angle = (point.value / total) * 360.0;
+            let (x, y) = at(label_radius, current_angle + slice_angle / 2.0);
+            svg.push_str(&format!(
+                r##"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="middle" font-size="12" fill="#ffffff" class="percentage">{}%</text>"##,
+                x, y, percent
+            ));
+            current_angle += slice_angle;
+        }
+    }
+
     svg.push_str("</svg>");
     Ok(svg)
 }
 
 /// Render an area chart to SVG
 pub fn render_area_chart(data: &ChartData, width: u32, height: u32) -> Result<String, RenderError> {
-    let points = extract_data_points(data)?;
+    let data = first_series(chart_series(data)?);
+    let points = data_points(&data);
 
     if points.is_empty() {
         return Ok(String::from("<svg></svg>"));
@@ -171,10 +253,7 @@ pub fn render_area_chart(data: &ChartData, width: u32, height: u32) -> Result<St
     let chart_width = width as f64 - (2.0 * margin);
     let chart_height = height as f64 - (2.0 * margin);
 
-    let mut svg = format!(
-        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="composition-area-chart">"#,
-        width, height
-    );
+    let mut svg = open_svg("Area chart", "composition-area-chart", &data, width, height);
 
     // Build path data for area
     let mut path_data = String::from("M");
@@ -206,7 +285,8 @@ pub fn render_area_chart(data: &ChartData, width: u32, height: u32) -> Result<St
 
 /// Render a bubble chart to SVG
 pub fn render_bubble_chart(data: &ChartData, width: u32, height: u32) -> Result<String, RenderError> {
-    let points = extract_data_points(data)?;
+    let data = first_series(chart_series(data)?);
+    let points = data_points(&data);
 
     if points.is_empty() {
         return Ok(String::from("<svg></svg>"));
@@ -220,12 +300,8 @@ pub fn render_bubble_chart(data: &ChartData, width: u32, height: u32) -> Result<
     let chart_width = width as f64 - (2.0 * margin);
     let chart_height = height as f64 - (2.0 * margin);
 
-    let mut svg = format!(
-        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="composition-bubble-chart">"#,
-        width, height
-    );
+    let mut svg = open_svg("Bubble chart", "composition-bubble-chart", &data, width, height);
 
-    let colors = ["#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899"];
 
     // Draw bubbles
     for (i, point) in points.iter().enumerate() {
@@ -234,8 +310,8 @@ pub fn render_bubble_chart(data: &ChartData, width: u32, height: u32) -> Result<
         let radius = (point.value / max_value) * 30.0 + 10.0;
 
         svg.push_str(&format!(
-            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" fill-opacity="0.6" stroke="{}" stroke-width="2" class="bubble"/>"#,
-            x, y, radius, colors[i % colors.len()], colors[i % colors.len()]
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" fill-opacity="0.6" stroke="{}" stroke-width="2" class="bubble">{}</circle>"#,
+            x, y, radius, series_color(i), series_color(i), point_tooltip(point)
         ));
     }
 
@@ -243,18 +319,306 @@ pub fn render_bubble_chart(data: &ChartData, width: u32, height: u32) -> Result<
     Ok(svg)
 }
 
-/// Extract data points from ChartData
-fn extract_data_points(data: &ChartData) -> Result<Vec<DataPoint>, RenderError> {
+/// Render the data of a chart as a visually hidden table
+///
+/// One row per x label, under a heading naming the labels' column (`Label`
+/// unless a CSV header named it) and each series (`Value` for inline data).
+pub fn render_chart_table(data: &ChartData) -> Result<String, RenderError> {
+    let data = chart_series(data)?;
+    let mut heading = vec![data.x_title.clone().unwrap_or_else(|| "Label".to_string())];
+    heading.extend(data.series.iter().map(|series| series.name.clone()));
+
+    let mut rows = vec![heading];
+    rows.extend(data.x_labels.iter().enumerate().map(|(i, label)| {
+        let mut row = vec![label.clone()];
+        row.extend(data.series.iter().map(|series| series.values[i].to_string()));
+        row
+    }));
+
+    Ok(format!(r#"<div class="dm-chart-table">{}</div>"#, render_table(&TableSource::Inline(rows), true)?))
+}
+
+/// Returns the CSS hiding chart data tables (called by orchestration layer)
+pub fn chart_table_css() -> &'static str {
+    CHART_TABLE_CSS
+}
+
+/// The area of a chart inside its axes
+struct Plot {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Plot {
+    /// Leaves room for tick labels on the left, x labels and the x-axis
+    /// title below and, when there is one, the legend above
+    fn new(width: u32, height: u32, legend: bool) -> Self {
+        let (left, right, bottom) = (50.0, 20.0, 45.0);
+        let top = if legend { 40.0 } else { 20.0 };
+        Plot {
+            left,
+            top,
+            width: (width as f64 - left - right).max(1.0),
+            height: (height as f64 - top - bottom).max(1.0),
+        }
+    }
+
+    fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+}
+
+/// The x and y axes, with ticks and values on the y axis and the x-axis title
+fn axes(data: &ChartSeries, plot: &Plot, max_value: f64, height: u32) -> String {
+    let mut svg = format!(
+        r##"<g class="axes"><line x1="{left}" y1="{bottom}" x2="{right}" y2="{bottom}" stroke="#6b7280" class="axis x-axis"/><line x1="{left}" y1="{top}" x2="{left}" y2="{bottom}" stroke="#6b7280" class="axis y-axis"/>"##,
+        left = plot.left,
+        right = plot.left + plot.width,
+        top = plot.top,
+        bottom = plot.bottom(),
+    );
+
+    for tick in 0..=Y_TICKS {
+        let y = plot.bottom() - plot.height * tick as f64 / Y_TICKS as f64;
+        svg.push_str(&format!(
+            r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#6b7280" class="tick"/><text x="{}" y="{}" text-anchor="end" font-size="11" class="tick-label">{}</text>"##,
+            plot.left - 4.0,
+            y,
+            plot.left,
+            y,
+            plot.left - 6.0,
+            y + 4.0,
+            format_value(max_value * tick as f64 / Y_TICKS as f64)
+        ));
+    }
+
+    if let Some(title) = &data.x_title {
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="12" class="axis-title">{}</text>"#,
+            plot.left + plot.width / 2.0,
+            height - 5,
+            escape_html(title)
+        ));
+    }
+
+    svg.push_str("</g>");
+    svg
+}
+
+/// An x label below the x axis
+fn x_label(x: f64, plot: &Plot, label: &str) -> String {
+    format!(
+        r#"<text x="{}" y="{}" text-anchor="middle" font-size="12" class="label">{}</text>"#,
+        x,
+        plot.bottom() + 18.0,
+        escape_html(label)
+    )
+}
+
+/// A swatch and the name of each series above the plot, when the chart has
+/// a legend
+fn legend(data: &ChartSeries, plot: &Plot) -> String {
+    if !has_legend(data) {
+        return String::new();
+    }
+
+    let mut svg = String::from(r#"<g class="legend">"#);
+    for (s, series) in data.series.iter().enumerate() {
+        let x = plot.left + s as f64 * 120.0;
+        svg.push_str(&format!(
+            r#"<rect x="{}" y="12" width="12" height="12" fill="{}" class="legend-swatch"/><text x="{}" y="22" font-size="12" class="legend-label">{}</text>"#,
+            x,
+            series_color(s),
+            x + 16.0,
+            escape_html(&series.name)
+        ));
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+/// Series get a legend when there are several, or when a CSV header named
+/// them
+fn has_legend(data: &ChartSeries) -> bool {
+    data.series.len() > 1 || data.x_title.is_some()
+}
+
+/// Where each x label sits along the x axis
+fn x_positions(data: &ChartSeries, plot: &Plot) -> Vec<f64> {
+    let evenly = |i: usize| plot.left + i as f64 * plot.width / (data.x_labels.len() - 1).max(1) as f64;
+
+    let numbers: Vec<f64> = data.x_labels.iter().filter_map(|label| label.trim().parse().ok()).collect();
+    let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if data.x_axis != AxisScale::Numeric || numbers.len() != data.x_labels.len() || max <= min {
+        return (0..data.x_labels.len()).map(evenly).collect();
+    }
+
+    numbers.iter().map(|x| plot.left + (x - min) / (max - min) * plot.width).collect()
+}
+
+/// The value at the top of the y axis: the largest value, or 1 when no
+/// value is positive
+fn axis_max(data: &ChartSeries) -> f64 {
+    let max = data.series.iter().flat_map(|series| series.values.iter().copied()).fold(0.0, f64::max);
+    if max > 0.0 { max } else { 1.0 }
+}
+
+fn series_color(index: usize) -> &'static str {
+    SERIES_COLORS[index % SERIES_COLORS.len()]
+}
+
+/// A value as shown on an axis, with at most two decimals
+fn format_value(value: f64) -> String {
+    ((value * 100.0).round() / 100.0).to_string()
+}
+
+/// The `<title>` browsers show as the tooltip of one value, e.g. `A: 10`,
+/// naming the series too (`Costs, A: 10`) when the chart has several
+fn tooltip(data: &ChartSeries, series: &DataSeries, label: &str, value: f64) -> String {
+    let text = match data.series.len() > 1 {
+        true => format!("{}, {}: {}", series.name, label, format_value(value)),
+        false => format!("{}: {}", label, format_value(value)),
+    };
+    format!("<title>{}</title>", escape_html(&text))
+}
+
+/// The tooltip of a data point of a single-series chart
+fn point_tooltip(point: &DataPoint) -> String {
+    format!("<title>{}</title>", escape_html(&format!("{}: {}", point.label, format_value(point.value))))
+}
+
+/// Each point's share of the total in whole percents, adding up to 100
+///
+/// Shares are rounded down, and the percents left over go to the points
+/// that lost the most in rounding (largest remainder), earlier points first
+/// on a tie, so three equal slices read 34%, 33%, 33% rather than 99% in all.
+fn rounded_percentages(points: &[DataPoint]) -> Vec<u32> {
+    let total: f64 = points.iter().map(|p| p.value.max(0.0)).sum();
+    if total <= 0.0 {
+        return vec![0; points.len()];
+    }
+
+    let shares: Vec<f64> = points.iter().map(|p| p.value.max(0.0) / total * 100.0).collect();
+    let mut percents: Vec<u32> = shares.iter().map(|share| share.floor() as u32).collect();
+    let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+    by_remainder.sort_by(|&a, &b| (shares[b] - shares[b].floor()).total_cmp(&(shares[a] - shares[a].floor())));
+
+    let left_over = 100u32.saturating_sub(percents.iter().sum());
+    for &index in by_remainder.iter().take(left_over as usize) {
+        percents[index] += 1;
+    }
+    percents
+}
+
+/// The opening `<svg>` tag of a chart, with its `<title>` and `<desc>`
+///
+/// Their ids are derived from the chart's data, so rendering is repeatable.
+fn open_svg(title: &str, class: &str, data: &ChartSeries, width: u32, height: u32) -> String {
+    let mut key = format!("{}\n", class);
+    for series in &data.series {
+        key.push_str(&format!("{}\n", series.name));
+        for (label, value) in data.x_labels.iter().zip(&series.values) {
+            key.push_str(&format!("{}\t{}\n", label, value));
+        }
+    }
+    let id = format!("dm-chart-{:016x}", xxh3_64(key.as_bytes()));
+
+    format!(
+        r#"<svg viewBox="0 0 {} {}" xmlns="http://www.w3.org/2000/svg" class="{}" role="img" aria-labelledby="{}-title" aria-describedby="{}-desc"><title id="{}-title">{}</title><desc id="{}-desc">{}</desc>"#,
+        width,
+        height,
+        class,
+        id,
+        id,
+        id,
+        escape_html(title),
+        id,
+        escape_html(&describe(data))
+    )
+}
+
+/// Summarize the data of a chart, e.g. "1 series of 3 values, ranging from
+/// 10 (A) to 20 (B)." Several series name the series of the extremes.
+fn describe(data: &ChartSeries) -> String {
+    let count = if data.x_labels.len() == 1 { "1 value".to_string() } else { format!("{} values", data.x_labels.len()) };
+    let several = data.series.len() > 1;
+    let values = data.series.iter().flat_map(|series| {
+        data.x_labels.iter().zip(&series.values).map(move |(label, value)| (series, label, *value))
+    });
+    let min = values.clone().min_by(|a, b| a.2.total_cmp(&b.2));
+    let max = values.max_by(|a, b| a.2.total_cmp(&b.2));
+    let at = |(series, label, _): (&DataSeries, &String, f64)| {
+        if several { format!("{}, {}", series.name, label) } else { label.clone() }
+    };
+
+    let series = if several { format!("{} series of {} each", data.series.len(), count) } else { format!("1 series of {}", count) };
+    match (min, max) {
+        (Some(min), Some(max)) => format!("{}, ranging from {} ({}) to {} ({}).", series, min.2, at(min), max.2, at(max)),
+        _ => format!("{}.", series),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// The series of a chart's data; inline data points are one series called
+/// `Value`
+fn chart_series(data: &ChartData) -> Result<ChartSeries, RenderError> {
     match data {
-        ChartData::Inline(points) => Ok(points.clone()),
+        ChartData::Inline(points) => Ok(ChartSeries {
+            x_title: None,
+            x_labels: points.iter().map(|point| point.label.clone()).collect(),
+            x_axis: AxisScale::Categorical,
+            series: vec![DataSeries {
+                name: "Value".to_string(),
+                values: points.iter().map(|point| point.value).collect(),
+            }],
+        }),
+        ChartData::Series(series) => Ok(series.clone()),
         ChartData::External(_resource) => {
-            // TODO: In a full implementation, this would read and parse the external resource
-            // For now, return an error
-            Err(RenderError::ChartError("External chart data not yet supported".to_string()))
+            // External data is loaded into series by the charts render pass
+            Err(RenderError::ChartError(
+                "External chart data must be resolved by the charts render pass".to_string(),
+            ))
         }
     }
 }
 
+/// Whether there is nothing to plot
+fn is_empty(data: &ChartSeries) -> bool {
+    data.x_labels.is_empty() || data.series.is_empty()
+}
+
+/// Only the first series, for charts that show a single one
+fn first_series(mut data: ChartSeries) -> ChartSeries {
+    data.series.truncate(1);
+    data
+}
+
+/// The first series as data points
+fn data_points(data: &ChartSeries) -> Vec<DataPoint> {
+    let Some(series) = data.series.first() else {
+        return Vec::new();
+    };
+    data.x_labels
+        .iter()
+        .zip(&series.values)
+        .map(|(label, value)| DataPoint {
+            label: label.clone(),
+            value: *value,
+            metadata: None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +675,41 @@ mod tests {
         assert!(result.contains("<path"));
     }
 
+    #[test]
+    fn test_donut_chart_with_percentages() {
+        let data = ChartData::Inline(
+            ["A", "B", "C"]
+                .into_iter()
+                .map(|label| DataPoint { label: label.to_string(), value: 1.0, metadata: None })
+                .collect(),
+        );
+        let options = PieChartOptions { inner_radius: 0.5, show_percentages: true };
+
+        let donut = render_pie_chart_with_options(&data, 400, 400, &options).unwrap();
+
+        // Each slice comes back along a hole of half the chart's 160px radius
+        assert!(donut.contains("composition-donut-chart"));
+        assert_eq!(donut.matches(" A80,80 0 0,0 ").count(), 3, "{}", donut);
+        let labels: Vec<&str> = donut.split(r#"class="percentage">"#).skip(1).map(|rest| &rest[..rest.find('<').unwrap()]).collect();
+        assert_eq!(labels, vec!["34%", "33%", "33%"]);
+
+        let pie = render_pie_chart(&data, 400, 400).unwrap();
+        assert!(!pie.contains("composition-donut-chart") && !pie.contains("A80,80"));
+        assert!(!pie.contains("percentage"));
+    }
+
+    #[test]
+    fn test_rounded_percentages_add_up_to_100() {
+        let points = |values: &[f64]| -> Vec<DataPoint> {
+            values.iter().map(|&value| DataPoint { label: String::new(), value, metadata: None }).collect()
+        };
+
+        assert_eq!(rounded_percentages(&points(&[1.0, 1.0, 1.0])), vec![34, 33, 33]);
+        assert_eq!(rounded_percentages(&points(&[2.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0])), vec![25, 13, 13, 13, 12, 12, 12]);
+        assert_eq!(rounded_percentages(&points(&[0.5, 99.5])), vec![1, 99]);
+        assert_eq!(rounded_percentages(&points(&[0.0, 0.0])), vec![0, 0]);
+    }
+
     #[test]
     fn test_render_area_chart() {
         let data = ChartData::Inline(sample_data());
@@ -338,4 +737,112 @@ mod tests {
 
         assert!(result.contains("<svg></svg>"));
     }
+
+    #[test]
+    fn test_charts_are_labelled_for_screen_readers() {
+        let data = ChartData::Inline(sample_data());
+        let result = render_line_chart(&data, 800, 400).unwrap();
+
+        let id = result.split("aria-labelledby=\"").nth(1).unwrap().split("-title\"").next().unwrap();
+        assert!(result.contains(r#"role="img""#));
+        assert!(result.contains(&format!(r#"<title id="{}-title">Line chart</title>"#, id)));
+        assert!(result.contains(&format!(
+            r#"<desc id="{}-desc">1 series of 3 values, ranging from 10 (A) to 20 (B).</desc>"#,
+            id
+        )));
+        // The same data gets the same ids; other data gets others
+        assert_eq!(render_line_chart(&data, 800, 400).unwrap(), result);
+        assert!(!render_bar_chart(&data, 800, 400).unwrap().contains(id));
+    }
+
+    #[test]
+    fn test_each_value_has_a_tooltip() {
+        let mut points = sample_data();
+        points[0].label = "<A>".to_string();
+        let data = ChartData::Inline(points);
+
+        let bar = render_bar_chart(&data, 800, 400).unwrap();
+        let bars: Vec<&str> = bar.split(r#"class="bar">"#).skip(1).collect();
+        assert_eq!(bars.len(), 3);
+        for (bar, expected) in bars.iter().zip(["&lt;A&gt;: 10", "B: 20", "C: 15"]) {
+            assert!(bar.starts_with(&format!("<title>{}</title></rect>", expected)));
+        }
+
+        assert_eq!(render_line_chart(&data, 800, 400).unwrap().matches("</title></circle>").count(), 3);
+        assert!(render_pie_chart(&data, 400, 400).unwrap().contains(r#"class="slice"><title>B: 20</title></path>"#));
+        assert!(render_bubble_chart(&data, 800, 400).unwrap().contains(r#"class="bubble"><title>C: 15</title></circle>"#));
+    }
+
+    #[test]
+    fn test_render_chart_table() {
+        let mut points = sample_data();
+        points[0].label = "<A>".to_string();
+        let result = render_chart_table(&ChartData::Inline(points)).unwrap();
+
+        assert!(result.starts_with(r#"<div class="dm-chart-table"><table>"#));
+        assert!(result.contains("<th>Label</th>"));
+        assert!(result.contains("<td>&lt;A&gt;</td>"));
+        assert!(result.contains("<td>20</td>"));
+    }
+
+    fn two_series(x_labels: &[&str], x_axis: AxisScale) -> ChartData {
+        ChartData::Series(ChartSeries {
+            x_title: Some("Year".to_string()),
+            x_labels: x_labels.iter().map(|label| label.to_string()).collect(),
+            x_axis,
+            series: vec![
+                DataSeries { name: "Revenue".to_string(), values: vec![10.0, 12.0, 20.0] },
+                DataSeries { name: "Costs".to_string(), values: vec![4.0, 5.0, 6.0] },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_charts_draw_labeled_axes() {
+        let result = render_bar_chart(&ChartData::Inline(sample_data()), 800, 400).unwrap();
+
+        assert!(result.contains(r#"class="axis x-axis""#));
+        assert!(result.contains(r#"class="axis y-axis""#));
+        assert!(result.contains(r#"class="tick-label">0</text>"#));
+        assert!(result.contains(r#"class="tick-label">20</text>"#));
+        assert!(result.contains(r#"class="tick-label">10</text>"#));
+        assert!(result.contains(r#"class="label">B</text>"#));
+        // A single inline series needs no legend
+        assert!(!result.contains("legend"));
+    }
+
+    #[test]
+    fn test_multi_series_charts() {
+        let data = two_series(&["2022", "2023", "2024"], AxisScale::Categorical);
+
+        let bar = render_bar_chart(&data, 800, 400).unwrap();
+        assert_eq!(bar.matches(r#"class="bar""#).count(), 6);
+        assert!(bar.contains(r#"class="bar"><title>Costs, 2023: 5</title></rect>"#));
+        assert!(bar.contains(r##"fill="#ef4444" class="legend-swatch"/><text x="186" y="22" font-size="12" class="legend-label">Costs</text>"##));
+
+        let line = render_line_chart(&data, 800, 400).unwrap();
+        assert_eq!(line.matches(r#"class="line""#).count(), 2);
+        assert!(line.contains("2 series of 3 values each, ranging from 4 (Costs, 2022) to 20 (Revenue, 2024)."));
+    }
+
+    #[test]
+    fn test_numeric_x_axis_is_proportional() {
+        let numeric = render_line_chart(&two_series(&["0", "1", "10"], AxisScale::Numeric), 800, 400).unwrap();
+        let categorical = render_line_chart(&two_series(&["0", "1", "10"], AxisScale::Categorical), 800, 400).unwrap();
+
+        // The plot is 730 wide from x = 50; 1 is a tenth of the way along a
+        // numeric axis but halfway along a categorical one
+        assert!(numeric.contains(r#"<text x="123" y="373" text-anchor="middle" font-size="12" class="label">1</text>"#));
+        assert!(categorical.contains(r#"<text x="415" y="373" text-anchor="middle" font-size="12" class="label">1</text>"#));
+    }
+
+    #[test]
+    fn test_render_series_chart_table() {
+        let result = render_chart_table(&two_series(&["2022", "2023", "2024"], AxisScale::Numeric)).unwrap();
+
+        assert!(result.contains("<th>Year</th>"));
+        assert!(result.contains("<th>Costs</th>"));
+        assert!(result.contains("<td>2024</td>"));
+        assert!(result.contains("<td>6</td>"));
+    }
 }
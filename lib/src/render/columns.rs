@@ -2,6 +2,8 @@ use crate::types::{DarkMatterNode, Breakpoint};
 use crate::error::RenderError;
 use std::collections::HashMap;
 
+use super::html::render_fragment;
+
 /// Render a multi-column layout with responsive breakpoints
 pub fn render_columns(
     breakpoints: &HashMap<Breakpoint, u32>,
@@ -18,7 +20,7 @@ pub fn render_columns(
     for section in sections {
         html.push_str(r#"<div class="composition-column">"#);
 
-        let section_html = render_nodes_to_html(section)?;
+        let section_html = render_fragment(section)?;
         html.push_str(&section_html);
 
         html.push_str(r#"</div>"#);
@@ -42,8 +44,7 @@ fn generate_column_class(breakpoints: &HashMap<Breakpoint, u32>) -> String {
     breakpoint_list.sort_by_key(|(bp, _)| breakpoint_order(bp));
 
     for (bp, cols) in breakpoint_list {
-        let bp_name = breakpoint_name(bp);
-        classes.push(format!("{}-{}", bp_name, cols));
+        classes.push(format!("{}-{}", bp, cols));
     }
 
     format!("composition-columns-{}", classes.join("-"))
@@ -90,7 +91,7 @@ pub fn generate_columns_styles(breakpoints: &HashMap<Breakpoint, u32>) -> String
     breakpoint_list.sort_by_key(|(bp, _)| breakpoint_order(bp));
 
     for (i, (bp, cols)) in breakpoint_list.iter().enumerate() {
-        let bp_px = breakpoint_pixels(bp);
+        let bp_px = bp.pixels();
 
         if i == 0 && **bp == Breakpoint::Micro {
             // Base styles (no media query for micro - mobile-first)
@@ -122,72 +123,11 @@ pub fn generate_columns_styles(breakpoints: &HashMap<Breakpoint, u32>) -> String
 
 // Helper functions
 
-fn render_nodes_to_html(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
-    let mut html = String::new();
-
-    for node in nodes {
-        match node {
-            DarkMatterNode::Text(text) => html.push_str(&escape_html(text)),
-            DarkMatterNode::Markdown(content) => {
-                // For now, just escape the raw content
-                // In a full implementation, this would parse markdown to HTML
-                html.push_str(&escape_html(&content.raw));
-            }
-            DarkMatterNode::Interpolation { variable } => {
-                // Placeholder - would need frontmatter context
-                html.push_str(&format!("{{{{{}}}}}", variable));
-            }
-            _ => {
-                html.push_str(&format!("[Unsupported node type: {:?}]", node));
-            }
-        }
-    }
-
-    Ok(html)
-}
-
-fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
-}
-
-fn breakpoint_name(bp: &Breakpoint) -> &'static str {
-    match bp {
-        Breakpoint::Micro => "micro",
-        Breakpoint::Xs => "xs",
-        Breakpoint::Sm => "sm",
-        Breakpoint::Md => "md",
-        Breakpoint::Lg => "lg",
-        Breakpoint::Xl => "xl",
-        Breakpoint::Xxl => "xxl",
-    }
-}
-
-fn breakpoint_pixels(bp: &Breakpoint) -> u32 {
-    match bp {
-        Breakpoint::Micro => 320,
-        Breakpoint::Xs => 640,
-        Breakpoint::Sm => 640,
-        Breakpoint::Md => 768,
-        Breakpoint::Lg => 1024,
-        Breakpoint::Xl => 1280,
-        Breakpoint::Xxl => 1536,
-    }
-}
-
-fn breakpoint_order(bp: &Breakpoint) -> u8 {
-    match bp {
-        Breakpoint::Micro => 0,
-        Breakpoint::Xs => 1,
-        Breakpoint::Sm => 2,
-        Breakpoint::Md => 3,
-        Breakpoint::Lg => 4,
-        Breakpoint::Xl => 5,
-        Breakpoint::Xxl => 6,
-    }
+/// Sort key putting breakpoints in size order; Tailwind breakpoints of the
+/// same width (`xs` and `sm`) keep their usual order, ahead of custom ones
+fn breakpoint_order(bp: &Breakpoint) -> (u32, usize) {
+    let position = Breakpoint::ALL.iter().position(|known| known == bp).unwrap_or(Breakpoint::ALL.len());
+    (bp.pixels(), position)
 }
 
 #[cfg(test)]
@@ -278,13 +218,25 @@ mod tests {
 
     #[test]
     fn test_breakpoint_pixels() {
-        assert_eq!(breakpoint_pixels(&Breakpoint::Micro), 320);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Xs), 640);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Sm), 640);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Md), 768);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Lg), 1024);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Xl), 1280);
-        assert_eq!(breakpoint_pixels(&Breakpoint::Xxl), 1536);
+        assert_eq!(Breakpoint::Micro.pixels(), 320);
+        assert_eq!(Breakpoint::Xs.pixels(), 640);
+        assert_eq!(Breakpoint::Sm.pixels(), 640);
+        assert_eq!(Breakpoint::Md.pixels(), 768);
+        assert_eq!(Breakpoint::Lg.pixels(), 1024);
+        assert_eq!(Breakpoint::Xl.pixels(), 1280);
+        assert_eq!(Breakpoint::Xxl.pixels(), 1536);
+        assert_eq!(Breakpoint::Custom("tablet".into(), 900).pixels(), 900);
+    }
+
+    #[test]
+    fn test_custom_breakpoints_sort_by_width() {
+        let mut breakpoints = HashMap::new();
+        breakpoints.insert(Breakpoint::Lg, 3);
+        breakpoints.insert(Breakpoint::Custom("tablet".into(), 900), 2);
+        breakpoints.insert(Breakpoint::Sm, 1);
+
+        assert_eq!(generate_column_class(&breakpoints), "composition-columns-sm-1-tablet-2-lg-3");
+        assert!(generate_columns_styles(&breakpoints).contains("@media (min-width: 900px)"));
     }
 
     #[test]
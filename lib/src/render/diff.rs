@@ -0,0 +1,174 @@
+//! Unified diff rendering
+//!
+//! A diff is rendered as a `<pre class="dm-diff">` in git's unified format:
+//! file headers, `@@` hunk headers and one line per change, each in a span
+//! whose class picks its background. The stylesheet is added once per
+//! document by the orchestration layer.
+
+use crate::error::RenderError;
+use crate::types::{Resource, ResourceSource};
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+
+const DIFF_CSS: &str = r#"
+.dm-diff {
+  font-family: ui-monospace, SFMono-Regular, Menlo, monospace;
+  overflow-x: auto;
+}
+.dm-diff span {
+  display: inline-block;
+  min-width: 100%;
+}
+.dm-diff .diff-file {
+  font-weight: 600;
+}
+.dm-diff .diff-hunk {
+  background-color: #eff6ff;
+  color: #1e40af;
+}
+.dm-diff .diff-add {
+  background-color: #dcfce7;
+  color: #166534;
+}
+.dm-diff .diff-del {
+  background-color: #fee2e2;
+  color: #991b1b;
+}
+"#;
+
+/// Render a unified diff from `old` to `new`
+///
+/// Both resources must be held in memory or be local files; the transclusion
+/// pass loads remote ones. `language` is added as a `language-*` class on the
+/// `<code>` element for client-side highlighting.
+pub fn render_diff(
+    old: &Resource,
+    new: &Resource,
+    language: Option<&str>,
+    context_lines: usize,
+) -> Result<String, RenderError> {
+    let old_text = read_text(old)?;
+    let new_text = read_text(new)?;
+
+    let code_class = language
+        .map(|language| format!(r#" class="language-{}""#, escape_html(language)))
+        .unwrap_or_default();
+    let mut html = format!(r#"<pre class="dm-diff"><code{}>"#, code_class);
+    for (class, line) in unified_lines(&old.label(), &old_text, &new.label(), &new_text, context_lines) {
+        match class {
+            Some(class) => html.push_str(&format!(r#"<span class="{}">{}</span>"#, class, escape_html(&line))),
+            None => html.push_str(&escape_html(&line)),
+        }
+        html.push('\n');
+    }
+    html.push_str("</code></pre>");
+
+    Ok(html)
+}
+
+/// Returns the CSS for diffs (called by orchestration layer)
+pub fn diff_css() -> &'static str {
+    DIFF_CSS
+}
+
+/// The lines of the unified diff, each with the class that styles it
+///
+/// Unchanged lines have no class. Identical texts have no lines at all.
+fn unified_lines(
+    old_label: &str,
+    old: &str,
+    new_label: &str,
+    new: &str,
+    context_lines: usize,
+) -> Vec<(Option<&'static str>, String)> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(context_lines);
+
+    let mut lines = Vec::new();
+    for hunk in unified.iter_hunks() {
+        if lines.is_empty() {
+            lines.push((Some("diff-file"), format!("--- {}", old_label)));
+            lines.push((Some("diff-file"), format!("+++ {}", new_label)));
+        }
+        lines.push((Some("diff-hunk"), hunk.header().to_string()));
+
+        for change in hunk.iter_changes() {
+            let (class, sign) = match change.tag() {
+                ChangeTag::Delete => (Some("diff-del"), '-'),
+                ChangeTag::Insert => (Some("diff-add"), '+'),
+                ChangeTag::Equal => (None, ' '),
+            };
+            let text = change.value().trim_end_matches(['\n', '\r']);
+            lines.push((class, format!("{}{}", sign, text)));
+        }
+    }
+
+    lines
+}
+
+/// Read one side of a diff
+fn read_text(resource: &Resource) -> Result<String, RenderError> {
+    match &resource.source {
+        ResourceSource::Memory { content, .. } => Ok(content.to_string()),
+        ResourceSource::Local(path) => fs::read_to_string(path)
+            .map_err(|e| RenderError::ResourceNotFound(path.display().to_string(), e.to_string())),
+        ResourceSource::Remote(_) | ResourceSource::Git { .. } => Err(RenderError::HtmlGenerationFailed(format!(
+            "Diff source {} must be loaded before HTML generation",
+            resource.label()
+        ))),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+    const NEW: &str = "fn main() {\n    let x = 2;\n    println!(\"{}\", x);\n}\n";
+
+    #[test]
+    fn test_render_diff_classes_lines() {
+        let html = render_diff(&Resource::memory("old.rs", OLD), &Resource::memory("new.rs", NEW), Some("rust"), 3)
+            .unwrap();
+
+        assert!(html.starts_with(r#"<pre class="dm-diff"><code class="language-rust">"#));
+        assert!(html.contains(r#"<span class="diff-file">--- old.rs</span>"#));
+        assert!(html.contains(r#"<span class="diff-file">+++ new.rs</span>"#));
+        assert!(html.contains(r#"<span class="diff-hunk">@@ -1,4 +1,4 @@</span>"#));
+        assert!(html.contains(r#"<span class="diff-del">-    let x = 1;</span>"#));
+        assert!(html.contains(r#"<span class="diff-add">+    let x = 2;</span>"#));
+        assert!(html.contains("\n     println!(&quot;{}&quot;, x);\n"));
+    }
+
+    #[test]
+    fn test_render_diff_context_lines() {
+        let html = render_diff(&Resource::memory("old", OLD), &Resource::memory("new", NEW), None, 0).unwrap();
+
+        assert!(html.contains(r#"<span class="diff-hunk">@@ -2 +2 @@</span>"#));
+        assert!(!html.contains("fn main()"));
+    }
+
+    #[test]
+    fn test_render_diff_of_identical_text_is_empty() {
+        let html = render_diff(&Resource::memory("old", OLD), &Resource::memory("new", OLD), None, 3).unwrap();
+
+        assert_eq!(html, r#"<pre class="dm-diff"><code></code></pre>"#);
+    }
+
+    #[test]
+    fn test_render_diff_inline_strings() {
+        let html = render_diff(&Resource::memory("old", "a <b>"), &Resource::memory("new", "a <i>"), None, 3).unwrap();
+
+        assert!(html.contains(r#"<span class="diff-del">-a &lt;b&gt;</span>"#));
+        assert!(html.contains(r#"<span class="diff-add">+a &lt;i&gt;</span>"#));
+    }
+}
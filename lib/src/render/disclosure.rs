@@ -1,10 +1,12 @@
 use crate::types::DarkMatterNode;
 use crate::error::RenderError;
 
+use super::html::render_fragment;
+
 /// Render a disclosure block (details/summary) to HTML
 pub fn render_disclosure(summary: &[DarkMatterNode], details: &[DarkMatterNode]) -> Result<String, RenderError> {
-    let summary_html = render_nodes_to_html(summary)?;
-    let details_html = render_nodes_to_html(details)?;
+    let summary_html = render_fragment(summary)?;
+    let details_html = render_fragment(details)?;
 
     let html = format!(
         r#"<details class="composition-disclosure">
@@ -24,8 +26,8 @@ pub fn render_disclosure(summary: &[DarkMatterNode], details: &[DarkMatterNode])
 
 /// Render disclosure with custom open state
 pub fn render_disclosure_open(summary: &[DarkMatterNode], details: &[DarkMatterNode], open: bool) -> Result<String, RenderError> {
-    let summary_html = render_nodes_to_html(summary)?;
-    let details_html = render_nodes_to_html(details)?;
+    let summary_html = render_fragment(summary)?;
+    let details_html = render_fragment(details)?;
 
     let open_attr = if open { " open" } else { "" };
 
@@ -100,41 +102,6 @@ pub fn generate_disclosure_styles() -> String {
 "#.to_string()
 }
 
-// Helper functions
-
-fn render_nodes_to_html(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
-    let mut html = String::new();
-
-    for node in nodes {
-        match node {
-            DarkMatterNode::Text(text) => html.push_str(&escape_html(text)),
-            DarkMatterNode::Markdown(content) => {
-                // For now, just escape the raw content
-                // In a full implementation, this would parse markdown to HTML
-                html.push_str(&escape_html(&content.raw));
-            }
-            DarkMatterNode::Interpolation { variable } => {
-                // Placeholder - would need frontmatter context
-                html.push_str(&format!("{{{{{}}}}}", variable));
-            }
-            _ => {
-                // For other node types, attempt to render as text
-                html.push_str(&format!("[Unsupported node type: {:?}]", node));
-            }
-        }
-    }
-
-    Ok(html)
-}
-
-fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
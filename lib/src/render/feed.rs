@@ -0,0 +1,351 @@
+//! Atom and RSS feeds of rendered documents
+//!
+//! [`CompositionApi::to_feed`](crate::CompositionApi::to_feed) renders the
+//! documents and turns each into a [`FeedEntry`]; this module only writes
+//! the XML.
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use url::Url;
+
+/// An attribute holding a URL
+static URL_ATTR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(\s(?:src|href|poster|srcset)=")([^"]*)""#).unwrap()
+});
+
+/// The XML format of a feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FeedFormat {
+    /// Atom (RFC 4287)
+    #[default]
+    Atom,
+    /// RSS 2.0
+    Rss,
+}
+
+/// Feed-level settings for [`CompositionApi::to_feed`](crate::CompositionApi::to_feed)
+#[derive(Debug, Clone)]
+pub struct FeedOptions {
+    pub title: String,
+    /// Where the rendered pages are published; entry links and relative
+    /// asset URLs are resolved against it
+    pub base_url: Url,
+    /// Atom `subtitle` and RSS `description`; RSS falls back to the title
+    pub subtitle: Option<String>,
+    /// Atom requires an author for the feed or for every entry, so when
+    /// this is `None` and an entry has no `author` property the title is
+    /// used instead
+    pub author: Option<String>,
+    /// The feed's permanent Atom id, `base_url` when `None`
+    pub id: Option<String>,
+    pub format: FeedFormat,
+    /// Fail on a document without a valid `date` property instead of leaving
+    /// it out with a warning
+    pub strict: bool,
+}
+
+impl FeedOptions {
+    pub fn new(title: impl Into<String>, base_url: Url) -> Self {
+        Self {
+            title: title.into(),
+            base_url,
+            subtitle: None,
+            author: None,
+            id: None,
+            format: FeedFormat::default(),
+            strict: false,
+        }
+    }
+
+    /// `base_url` as a directory, so that joining a page's path keeps its
+    /// last segment (`https://example.com/blog` becomes `.../blog/`)
+    pub(crate) fn base(&self) -> Url {
+        let mut base = self.base_url.clone();
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        base
+    }
+}
+
+/// A rendered document as it appears in a feed
+#[derive(Debug, Clone)]
+pub(crate) struct FeedEntry {
+    pub title: String,
+    /// The published page
+    pub link: Url,
+    pub date: DateTime<Utc>,
+    pub author: Option<String>,
+    /// Plain-text summary; left out when empty
+    pub summary: String,
+    /// The page's HTML, with absolute URLs
+    pub content: String,
+}
+
+/// A `date` frontmatter value as a timestamp: RFC 3339, or a plain
+/// `YYYY-MM-DD` date taken as midnight UTC
+pub(crate) fn feed_date(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    let value = value.as_str()?.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| date.and_utc())
+        })
+}
+
+/// `html` with every relative `src`, `href`, `poster` and `srcset` URL
+/// resolved against `page`, since feed readers show entries away from it
+pub(crate) fn absolutize_urls(html: &str, page: &Url) -> String {
+    let absolute = |url: &str| -> String {
+        let unescaped = url.replace("&amp;", "&");
+        match Url::parse(&unescaped) {
+            Ok(_) => url.to_string(),
+            Err(_) => page
+                .join(&unescaped)
+                .map(|url| url.as_str().replace('&', "&amp;"))
+                .unwrap_or_else(|_| url.to_string()),
+        }
+    };
+
+    URL_ATTR
+        .replace_all(html, |caps: &Captures| {
+            let value = if caps[1].ends_with("srcset=\"") {
+                caps[2]
+                    .split(',')
+                    .map(|candidate| {
+                        let candidate = candidate.trim();
+                        match candidate.split_once(char::is_whitespace) {
+                            Some((url, descriptor)) => format!("{} {}", absolute(url), descriptor.trim()),
+                            None => absolute(candidate),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            } else {
+                absolute(&caps[2])
+            };
+            format!("{}{}\"", &caps[1], value)
+        })
+        .into_owned()
+}
+
+/// An Atom feed of `entries`, in the order given
+pub(crate) fn atom_feed(entries: &[FeedEntry], options: &FeedOptions) -> String {
+    let base = options.base();
+    let updated = entries.iter().map(|entry| entry.date).max().unwrap_or_else(Utc::now);
+    let feed_author = options.author.clone().or_else(|| {
+        entries
+            .iter()
+            .any(|entry| entry.author.is_none())
+            .then(|| options.title.clone())
+    });
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <id>{}</id>\n", escape_xml(options.id.as_deref().unwrap_or(base.as_str()))));
+    xml.push_str(&format!("  <title>{}</title>\n", escape_xml(&options.title)));
+    if let Some(subtitle) = &options.subtitle {
+        xml.push_str(&format!("  <subtitle>{}</subtitle>\n", escape_xml(subtitle)));
+    }
+    xml.push_str(&format!("  <updated>{}</updated>\n", atom_date(updated)));
+    xml.push_str(&format!("  <link rel=\"alternate\" href=\"{}\"/>\n", escape_xml(base.as_str())));
+    if let Some(author) = &feed_author {
+        xml.push_str(&format!("  <author><name>{}</name></author>\n", escape_xml(author)));
+    }
+
+    for entry in entries {
+        let link = escape_xml(entry.link.as_str());
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>{}</id>\n", link));
+        xml.push_str(&format!("    <title>{}</title>\n", escape_xml(&entry.title)));
+        xml.push_str(&format!("    <published>{}</published>\n", atom_date(entry.date)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", atom_date(entry.date)));
+        xml.push_str(&format!("    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n", link));
+        if let Some(author) = &entry.author {
+            xml.push_str(&format!("    <author><name>{}</name></author>\n", escape_xml(author)));
+        }
+        if !entry.summary.is_empty() {
+            xml.push_str(&format!("    <summary>{}</summary>\n", escape_xml(&entry.summary)));
+        }
+        xml.push_str(&format!("    <content type=\"html\">{}</content>\n", escape_xml(&entry.content)));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// An RSS 2.0 feed of `entries`, in the order given
+///
+/// RSS only allows e-mail addresses in `author`, so authors are written as
+/// Dublin Core `dc:creator` elements.
+pub(crate) fn rss_feed(entries: &[FeedEntry], options: &FeedOptions) -> String {
+    let base = options.base();
+    let updated = entries.iter().map(|entry| entry.date).max().unwrap_or_else(Utc::now);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <rss version=\"2.0\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<channel>\n",
+    );
+    xml.push_str(&format!("  <title>{}</title>\n", escape_xml(&options.title)));
+    xml.push_str(&format!("  <link>{}</link>\n", escape_xml(base.as_str())));
+    xml.push_str(&format!(
+        "  <description>{}</description>\n",
+        escape_xml(options.subtitle.as_deref().unwrap_or(&options.title))
+    ));
+    xml.push_str(&format!("  <lastBuildDate>{}</lastBuildDate>\n", updated.to_rfc2822()));
+    if let Some(author) = &options.author {
+        xml.push_str(&format!("  <dc:creator>{}</dc:creator>\n", escape_xml(author)));
+    }
+
+    for entry in entries {
+        let link = escape_xml(entry.link.as_str());
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape_xml(&entry.title)));
+        xml.push_str(&format!("    <link>{}</link>\n", link));
+        xml.push_str(&format!("    <guid isPermaLink=\"true\">{}</guid>\n", link));
+        xml.push_str(&format!("    <pubDate>{}</pubDate>\n", entry.date.to_rfc2822()));
+        if let Some(author) = &entry.author {
+            xml.push_str(&format!("    <dc:creator>{}</dc:creator>\n", escape_xml(author)));
+        }
+        xml.push_str(&format!("    <description>{}</description>\n", escape_xml(&entry.content)));
+        xml.push_str("  </item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn atom_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, date: &str, author: Option<&str>) -> FeedEntry {
+        FeedEntry {
+            title: title.to_string(),
+            link: Url::parse(&format!("https://example.com/blog/{}.html", title.to_lowercase())).unwrap(),
+            date: feed_date(&serde_json::json!(date)).unwrap(),
+            author: author.map(str::to_string),
+            summary: format!("About {}", title),
+            content: format!("<p>{} &amp; more</p>", title),
+        }
+    }
+
+    fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> roxmltree::Node<'a, 'input> {
+        node.children()
+            .find(|n| n.tag_name().name() == name)
+            .unwrap_or_else(|| panic!("no <{}> in <{}>", name, node.tag_name().name()))
+    }
+
+    #[test]
+    fn test_feed_date() {
+        let date = |value: serde_json::Value| feed_date(&value).map(|d| d.to_rfc3339());
+
+        assert_eq!(date(serde_json::json!("2024-03-01")).as_deref(), Some("2024-03-01T00:00:00+00:00"));
+        assert_eq!(date(serde_json::json!("2024-03-01T10:30:00+02:00")).as_deref(), Some("2024-03-01T08:30:00+00:00"));
+        assert_eq!(date(serde_json::json!("March 1st")), None);
+        assert_eq!(date(serde_json::json!(20240301)), None);
+    }
+
+    #[test]
+    fn test_absolutize_urls() {
+        let page = Url::parse("https://example.com/blog/posts/a.html").unwrap();
+        let html = concat!(
+            r#"<img src="images/cover.png" srcset="img/a-320w.webp 320w, img/a-640w.webp 640w">"#,
+            r#"<a href="../index.html">Home</a><a href="https://other.example/x?a=1&amp;b=2">x</a>"#,
+            r#"<a href="/about.html?a=1&amp;b=2">About</a><img src="data:image/png;base64,AAAA">"#,
+        );
+
+        assert_eq!(
+            absolutize_urls(html, &page),
+            concat!(
+                r#"<img src="https://example.com/blog/posts/images/cover.png" "#,
+                r#"srcset="https://example.com/blog/posts/img/a-320w.webp 320w, https://example.com/blog/posts/img/a-640w.webp 640w">"#,
+                r#"<a href="https://example.com/blog/index.html">Home</a><a href="https://other.example/x?a=1&amp;b=2">x</a>"#,
+                r#"<a href="https://example.com/about.html?a=1&amp;b=2">About</a><img src="data:image/png;base64,AAAA">"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_atom_feed_structure() {
+        let mut options = FeedOptions::new("Notes & Thoughts", Url::parse("https://example.com/blog").unwrap());
+        options.subtitle = Some("Occasional writing".to_string());
+        let entries = vec![entry("Second", "2024-02-01", Some("Sam")), entry("First", "2024-01-01", None)];
+
+        let xml = atom_feed(&entries, &options);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let feed = doc.root_element();
+
+        assert_eq!(feed.tag_name().namespace(), Some("http://www.w3.org/2005/Atom"));
+        assert_eq!(feed.tag_name().name(), "feed");
+        assert_eq!(child(feed, "id").text(), Some("https://example.com/blog/"));
+        assert_eq!(child(feed, "title").text(), Some("Notes & Thoughts"));
+        assert_eq!(child(feed, "updated").text(), Some("2024-02-01T00:00:00Z"));
+        // The second entry has no author, so the feed needs one
+        assert_eq!(child(child(feed, "author"), "name").text(), Some("Notes & Thoughts"));
+
+        let entries: Vec<_> = feed.children().filter(|n| n.tag_name().name() == "entry").collect();
+        assert_eq!(entries.len(), 2);
+        for name in ["id", "title", "updated", "link", "content"] {
+            assert!(entries.iter().all(|e| e.children().any(|n| n.tag_name().name() == name)), "{}", name);
+        }
+        assert_eq!(child(entries[0], "title").text(), Some("Second"));
+        assert_eq!(child(entries[0], "link").attribute("href"), Some("https://example.com/blog/second.html"));
+        assert_eq!(child(child(entries[0], "author"), "name").text(), Some("Sam"));
+        let content = child(entries[0], "content");
+        assert_eq!(content.attribute("type"), Some("html"));
+        assert_eq!(content.text(), Some("<p>Second &amp; more</p>"));
+    }
+
+    #[test]
+    fn test_atom_feed_author_and_id_from_options() {
+        let mut options = FeedOptions::new("Blog", Url::parse("https://example.com/").unwrap());
+        options.author = Some("Alex".to_string());
+        options.id = Some("urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6".to_string());
+
+        let xml = atom_feed(&[entry("Only", "2024-01-01", None)], &options);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let feed = doc.root_element();
+
+        assert_eq!(child(feed, "id").text(), options.id.as_deref());
+        assert_eq!(child(child(feed, "author"), "name").text(), Some("Alex"));
+    }
+
+    #[test]
+    fn test_rss_feed_structure() {
+        let mut options = FeedOptions::new("Blog", Url::parse("https://example.com/").unwrap());
+        options.format = FeedFormat::Rss;
+
+        let xml = rss_feed(&[entry("Second", "2024-02-01", Some("Sam"))], &options);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let rss = doc.root_element();
+
+        assert_eq!(rss.tag_name().name(), "rss");
+        assert_eq!(rss.attribute("version"), Some("2.0"));
+        let channel = child(rss, "channel");
+        assert_eq!(child(channel, "description").text(), Some("Blog"));
+        let item = child(channel, "item");
+        assert_eq!(child(item, "link").text(), Some("https://example.com/blog/second.html"));
+        assert_eq!(child(item, "pubDate").text(), Some("Thu, 1 Feb 2024 00:00:00 +0000"));
+        assert_eq!(child(item, "creator").tag_name().namespace(), Some("http://purl.org/dc/elements/1.1/"));
+        assert_eq!(child(item, "description").text(), Some("<p>Second &amp; more</p>"));
+    }
+}
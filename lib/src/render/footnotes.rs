@@ -0,0 +1,327 @@
+use crate::error::RenderError;
+use crate::parse::darkmatter::{is_footnote_id, FOOTNOTE_MARK};
+use crate::types::{DarkMatterNode, MarkdownContent};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use super::html::render_html;
+use super::interpolation::protected_ranges;
+
+/// The anchor a footnote reference links back to
+static REF_ANCHOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"id="fnref-([\w-]+)""#).unwrap()
+});
+
+/// Render a reference to footnote `id`
+pub fn render_footnote_ref(id: &str) -> String {
+    format!(r##"<sup><a href="#fn-{id}" id="fnref-{id}">[{id}]</a></sup>"##)
+}
+
+/// The footnotes of a document, collected before it is rendered
+///
+/// Collection numbers the inline footnotes and leaves a reference in their
+/// place, both for `Footnote` nodes and for `^[text]` in markdown, so the
+/// nodes can then be rendered one at a time without any state.
+#[derive(Default)]
+pub(crate) struct Footnotes {
+    /// Footnote content by id
+    definitions: HashMap<String, Vec<DarkMatterNode>>,
+    /// Ids in the order they were defined
+    defined: Vec<String>,
+    /// Ids in the order they were first referenced
+    referenced: Vec<String>,
+    /// Ids of the `::footnote` definitions, which inline footnotes can't take
+    named: HashSet<String>,
+    /// Last number given to an inline footnote
+    last_number: usize,
+}
+
+impl Footnotes {
+    /// Collect the footnotes of `nodes`, returning the nodes to render
+    ///
+    /// A reference is only a reference when some `::footnote` has its id;
+    /// otherwise, like `^[sic]`, it is an inline footnote holding its text.
+    /// Fails when an id is defined twice.
+    pub(crate) fn collect(nodes: &[DarkMatterNode]) -> Result<(Vec<DarkMatterNode>, Self), RenderError> {
+        let mut footnotes = Footnotes::default();
+        named_ids(nodes, &mut footnotes.named);
+
+        let mut nodes = nodes.to_vec();
+        footnotes.collect_nodes(&mut nodes)?;
+
+        Ok((nodes, footnotes))
+    }
+
+    fn collect_nodes(&mut self, nodes: &mut [DarkMatterNode]) -> Result<(), RenderError> {
+        for node in nodes {
+            match node {
+                DarkMatterNode::Markdown(content) => content.raw = self.collect_markdown(&content.raw)?,
+                DarkMatterNode::FootnoteRef { id } if self.named.contains(id.as_str()) => self.reference(id),
+                DarkMatterNode::FootnoteRef { id } => {
+                    let text = vec![DarkMatterNode::Text(std::mem::take(id))];
+                    let id = self.inline(text)?;
+                    *node = DarkMatterNode::FootnoteRef { id };
+                }
+                DarkMatterNode::Footnote { id, content } => {
+                    let mut content = std::mem::take(content);
+                    self.collect_nodes(&mut content)?;
+                    if id.is_empty() {
+                        let id = self.inline(content)?;
+                        *node = DarkMatterNode::FootnoteRef { id };
+                    } else {
+                        self.define(id.clone(), content)?;
+                    }
+                }
+                DarkMatterNode::Popover { trigger, content } => {
+                    self.collect_nodes(std::slice::from_mut(trigger.as_mut()))?;
+                    self.collect_nodes(content)?;
+                }
+                DarkMatterNode::Columns { sections, .. } => {
+                    for section in sections {
+                        self.collect_nodes(section)?;
+                    }
+                }
+                DarkMatterNode::Disclosure { summary, details } => {
+                    self.collect_nodes(summary)?;
+                    self.collect_nodes(details)?;
+                }
+                DarkMatterNode::AspectRatio { content, .. } => self.collect_nodes(content)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the `^[...]` marks in markdown with references
+    ///
+    /// Marks in code are left alone.
+    fn collect_markdown(&mut self, raw: &str) -> Result<String, RenderError> {
+        let protected = protected_ranges(raw, true);
+        let mut result = String::with_capacity(raw.len());
+        let mut position = 0;
+
+        for mark in FOOTNOTE_MARK.captures_iter(raw) {
+            let whole = mark.get(0).unwrap();
+            if protected.iter().any(|range| range.start < whole.end() && whole.start() < range.end) {
+                continue;
+            }
+
+            let id = if is_footnote_id(&mark[1]) && self.named.contains(&mark[1]) {
+                self.reference(&mark[1]);
+                mark[1].to_string()
+            } else {
+                let content = MarkdownContent { raw: mark[1].to_string(), frontmatter: None };
+                self.inline(vec![DarkMatterNode::Markdown(content)])?
+            };
+
+            result.push_str(&raw[position..whole.start()]);
+            result.push_str(&render_footnote_ref(&id));
+            position = whole.end();
+        }
+        result.push_str(&raw[position..]);
+
+        Ok(result)
+    }
+
+    /// Define and reference an inline footnote, returning its number
+    fn inline(&mut self, content: Vec<DarkMatterNode>) -> Result<String, RenderError> {
+        let id = self.next_number();
+        self.define(id.clone(), content)?;
+        self.reference(&id);
+        Ok(id)
+    }
+
+    fn define(&mut self, id: String, content: Vec<DarkMatterNode>) -> Result<(), RenderError> {
+        if self.definitions.contains_key(&id) {
+            return Err(RenderError::FootnoteError(format!("Footnote [{}] is defined more than once", id)));
+        }
+        self.defined.push(id.clone());
+        self.definitions.insert(id, content);
+        Ok(())
+    }
+
+    fn reference(&mut self, id: &str) {
+        if !self.referenced.iter().any(|referenced| referenced == id) {
+            self.referenced.push(id.to_string());
+        }
+    }
+
+    /// The id of the next inline footnote: the lowest number above the last
+    /// one that no `::footnote` uses
+    fn next_number(&mut self) -> String {
+        loop {
+            self.last_number += 1;
+            let id = self.last_number.to_string();
+            if !self.named.contains(&id) {
+                return id;
+            }
+        }
+    }
+
+    /// Finish the document's HTML with the footnotes section
+    ///
+    /// Footnotes are listed in the order they are first referenced, followed
+    /// by any that are never referenced. Only the first reference to a
+    /// footnote keeps the `fnref-` id its back-link points to; later ones are
+    /// numbered so ids stay unique.
+    pub(crate) fn finish(&self, html: String, sanitize: bool) -> Result<String, RenderError> {
+        if self.definitions.is_empty() {
+            return Ok(html);
+        }
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut html = REF_ANCHOR
+            .replace_all(&html, |caps: &Captures| {
+                let count = seen.entry(caps[1].to_string()).or_default();
+                *count += 1;
+                match *count {
+                    1 => caps[0].to_string(),
+                    n => format!(r#"id="fnref-{}-{}""#, &caps[1], n),
+                }
+            })
+            .into_owned();
+
+        html.push_str("\n<footer class=\"footnotes\">\n<ol>\n");
+        let unreferenced = self.defined.iter().filter(|id| !self.referenced.contains(id));
+        for id in self.referenced.iter().chain(unreferenced) {
+            let content = render_html(&self.definitions[id], sanitize)?;
+            let content = content.trim_end();
+            let back_link = if self.referenced.contains(id) {
+                format!(r##" <a href="#fnref-{id}" class="footnote-backref">↩</a>"##)
+            } else {
+                String::new()
+            };
+
+            // Keep the back-link on the footnote's last line
+            let item = match content.strip_suffix("</p>") {
+                Some(body) => format!("{body}{back_link}</p>"),
+                None => format!("{content}{back_link}"),
+            };
+            html.push_str(&format!("<li id=\"fn-{id}\">{item}</li>\n"));
+        }
+        html.push_str("</ol>\n</footer>");
+
+        Ok(html)
+    }
+}
+
+/// Collect the ids of the `::footnote` definitions in a node tree
+fn named_ids(nodes: &[DarkMatterNode], ids: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            DarkMatterNode::Footnote { id, content } => {
+                if !id.is_empty() {
+                    ids.insert(id.clone());
+                }
+                named_ids(content, ids);
+            }
+            DarkMatterNode::Popover { trigger, content } => {
+                named_ids(std::slice::from_ref(trigger.as_ref()), ids);
+                named_ids(content, ids);
+            }
+            DarkMatterNode::Columns { sections, .. } => {
+                for section in sections {
+                    named_ids(section, ids);
+                }
+            }
+            DarkMatterNode::Disclosure { summary, details } => {
+                named_ids(summary, ids);
+                named_ids(details, ids);
+            }
+            DarkMatterNode::AspectRatio { content, .. } => named_ids(content, ids),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(raw: &str) -> DarkMatterNode {
+        DarkMatterNode::Markdown(MarkdownContent { raw: raw.to_string(), frontmatter: None })
+    }
+
+    fn footnote(id: &str, text: &str) -> DarkMatterNode {
+        DarkMatterNode::Footnote { id: id.to_string(), content: vec![markdown(text)] }
+    }
+
+    #[test]
+    fn test_collect_replaces_markdown_marks() {
+        let nodes = vec![markdown("Claim^[1] and aside^[an inline note]."), footnote("1", "Source.")];
+
+        let (nodes, footnotes) = Footnotes::collect(&nodes).unwrap();
+
+        let DarkMatterNode::Markdown(content) = &nodes[0] else {
+            panic!("expected markdown, got {:?}", nodes[0]);
+        };
+        assert!(content.raw.contains(&render_footnote_ref("1")));
+        assert!(content.raw.contains(&render_footnote_ref("2")));
+        assert_eq!(footnotes.referenced, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_inline_footnotes_skip_named_ids() {
+        let nodes = vec![
+            DarkMatterNode::Footnote { id: String::new(), content: vec![DarkMatterNode::Text("inline".into())] },
+            footnote("1", "named"),
+        ];
+
+        let (nodes, footnotes) = Footnotes::collect(&nodes).unwrap();
+
+        assert!(matches!(&nodes[0], DarkMatterNode::FootnoteRef { id } if id == "2"));
+        assert_eq!(footnotes.defined, vec!["2", "1"]);
+    }
+
+    #[test]
+    fn test_collect_ignores_marks_in_code() {
+        let nodes = vec![markdown("`^[1]`\n\n```\n^[an inline note]\n```")];
+
+        let (nodes, footnotes) = Footnotes::collect(&nodes).unwrap();
+
+        assert!(matches!(&nodes[0], DarkMatterNode::Markdown(content) if !content.raw.contains("<sup>")));
+        assert!(footnotes.definitions.is_empty());
+    }
+
+    #[test]
+    fn test_collect_rejects_duplicate_footnotes() {
+        let duplicate = Footnotes::collect(&[footnote("a", "one"), footnote("a", "two")]);
+
+        assert!(matches!(duplicate, Err(RenderError::FootnoteError(_))));
+    }
+
+    #[test]
+    fn test_undefined_ids_are_inline_footnotes() {
+        let nodes = vec![
+            markdown("Their spelling^[sic] and claim^[a]."),
+            DarkMatterNode::FootnoteRef { id: "sic".to_string() },
+            footnote("a", "Source."),
+        ];
+
+        let (nodes, footnotes) = Footnotes::collect(&nodes).unwrap();
+
+        let DarkMatterNode::Markdown(content) = &nodes[0] else {
+            panic!("expected markdown, got {:?}", nodes[0]);
+        };
+        assert!(content.raw.contains(&render_footnote_ref("1")));
+        assert!(content.raw.contains(&render_footnote_ref("a")));
+        assert!(matches!(&nodes[1], DarkMatterNode::FootnoteRef { id } if id == "2"));
+        assert_eq!(footnotes.referenced, vec!["1", "a", "2"]);
+        assert!(matches!(&footnotes.definitions["1"][0], DarkMatterNode::Markdown(note) if note.raw == "sic"));
+        assert!(matches!(&footnotes.definitions["2"][0], DarkMatterNode::Text(note) if note == "sic"));
+    }
+
+    #[test]
+    fn test_finish_numbers_repeated_references() {
+        let nodes = vec![markdown("One^[1], two^[1]."), footnote("1", "Note.")];
+        let (_, footnotes) = Footnotes::collect(&nodes).unwrap();
+        let refs = format!("{} {}", render_footnote_ref("1"), render_footnote_ref("1"));
+
+        let html = footnotes.finish(refs, false).unwrap();
+
+        assert_eq!(html.matches(r#"id="fnref-1""#).count(), 1);
+        assert!(html.contains(r#"id="fnref-1-2""#));
+        assert!(html.contains(r##"<li id="fn-1"><p>Note. <a href="#fnref-1" class="footnote-backref">↩</a></p></li>"##));
+    }
+}
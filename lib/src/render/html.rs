@@ -1,41 +1,74 @@
 use crate::error::RenderError;
-use crate::types::{DarkMatterNode, MarkdownContent};
+use crate::types::{ChartData, DarkMatterNode, ListSource, MarkdownContent};
 use pulldown_cmark::{html, Options, Parser};
 use tracing::instrument;
 
-use super::table::render_table;
-use super::charts::{render_bar_chart, render_line_chart, render_pie_chart, render_area_chart, render_bubble_chart};
+use super::table::{render_table, render_env_table};
+use super::list::render_data_list;
+use super::assets::AssetRegistry;
+use super::charts::{
+    render_area_chart, render_bar_chart, render_bubble_chart, render_chart_table, render_line_chart,
+    render_pie_chart_with_options,
+};
 use super::popover::render_popover as render_popover_component;
 use super::disclosure::render_disclosure as render_disclosure_component;
 use super::columns::render_columns as render_columns_component;
-use super::youtube::render_youtube_embed;
+use super::aspect_ratio::render_aspect_ratio;
+use super::youtube::{render_youtube_embed, render_youtube_embed_with_captions};
+use super::mermaid::render_mermaid;
+use super::diff::render_diff;
+use super::math::render_math;
+use super::terminal::render_terminal;
+use super::sanitize::sanitize_html;
+use super::footnotes::{render_footnote_ref, Footnotes};
 
 /// Convert DarkMatter nodes to HTML
 ///
-/// This function processes all node types and generates self-contained HTML output
+/// This function processes all node types and generates self-contained HTML
+/// output. Footnotes are collected from the whole tree and listed in a
+/// `<footer class="footnotes">` at the end.
 #[instrument(skip(nodes))]
 pub fn to_html(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
-    let mut html = String::new();
-    let mut youtube_assets_included = false;
+    render_document(nodes, false)
+}
+
+/// Convert DarkMatter nodes to HTML with unsafe markup removed
+///
+/// The HTML of every node goes through [`sanitize_html`], so scripts from
+/// transcluded or remote content are stripped. Only the stylesheets and
+/// scripts the crate adds for its own components are left as they are.
+#[instrument(skip(nodes))]
+pub fn to_sanitized_html(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
+    render_document(nodes, true)
+}
+
+/// Render a whole document: the assets its components need, its nodes and
+/// its footnotes
+fn render_document(nodes: &[DarkMatterNode], sanitize: bool) -> Result<String, RenderError> {
+    let assets = AssetRegistry::collect(nodes);
+    let (nodes, footnotes) = Footnotes::collect(nodes)?;
+    let html = render_html(&nodes, sanitize)?;
+    Ok(assets.to_html() + &footnotes.finish(html, sanitize)?)
+}
+
+/// Render the nodes inside a component
+///
+/// Unlike [`to_html`] this doesn't collect footnotes: the document they are
+/// part of already has.
+pub(super) fn render_fragment(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
+    render_html(nodes, false)
+}
 
+pub(super) fn render_html(nodes: &[DarkMatterNode], sanitize: bool) -> Result<String, RenderError> {
+    let mut html = String::new();
     for node in nodes {
         let node_html = render_node(node)?;
-        html.push_str(&node_html);
-
-        // Include YouTube assets on first occurrence
-        if matches!(node, DarkMatterNode::YouTube { .. }) && !youtube_assets_included {
-            html.push_str(&format!(
-                "\n<style id=\"dm-youtube\">{}</style>",
-                super::youtube::youtube_css()
-            ));
-            html.push_str(&format!(
-                "\n<script id=\"dm-youtube\">{}</script>",
-                super::youtube::youtube_js()
-            ));
-            youtube_assets_included = true;
+        if sanitize {
+            html.push_str(&sanitize_html(&node_html));
+        } else {
+            html.push_str(&node_html);
         }
     }
-
     Ok(html)
 }
 
@@ -45,9 +78,32 @@ fn render_node(node: &DarkMatterNode) -> Result<String, RenderError> {
         DarkMatterNode::Markdown(content) => render_markdown(content),
         DarkMatterNode::Text(text) => Ok(escape_html(text)),
         DarkMatterNode::Table { source, has_heading } => render_table(source, *has_heading),
+        // Tables not resolved by interpolation have no allowlist, so every
+        // value is redacted
+        DarkMatterNode::EnvTable { filter, sort, show_values } => {
+            render_env_table(filter.as_deref(), *sort, *show_values, &[], &[])
+        }
+        DarkMatterNode::RequireEnv { .. } => Ok(String::new()),
+        DarkMatterNode::DataList { source: ListSource::Inline(items), ordered, task_list } => {
+            Ok(render_data_list(items, *ordered, *task_list))
+        }
+        // Data files are read before HTML generation
+        DarkMatterNode::DataList { source: ListSource::External { .. }, .. } => {
+            Err(RenderError::HtmlGenerationFailed(
+                "Data lists must be resolved before HTML generation".to_string()
+            ))
+        }
         DarkMatterNode::Popover { trigger, content } => render_popover(trigger, content),
         DarkMatterNode::Disclosure { summary, details } => render_disclosure(summary, details),
         DarkMatterNode::Columns { breakpoints, sections } => render_columns(breakpoints, sections),
+        DarkMatterNode::AspectRatio { numerator, denominator, content } => {
+            render_aspect_ratio(*numerator, *denominator, content)
+        }
+
+        // Footnotes are listed at the end of the document; inline ones were
+        // replaced by references when they were collected
+        DarkMatterNode::Footnote { .. } => Ok(String::new()),
+        DarkMatterNode::FootnoteRef { id } => Ok(render_footnote_ref(id)),
 
         // AI operations would be resolved before HTML generation
         DarkMatterNode::Summarize { .. } |
@@ -73,25 +129,33 @@ fn render_node(node: &DarkMatterNode) -> Result<String, RenderError> {
         }
 
         // YouTube rendering
-        DarkMatterNode::YouTube { video_id, width } => {
-            Ok(render_youtube_embed(video_id, width))
+        DarkMatterNode::YouTube { video_id, width, captions } => Ok(match captions {
+            Some(language) => render_youtube_embed_with_captions(video_id, width, language),
+            None => render_youtube_embed(video_id, width),
+        }),
+
+        DarkMatterNode::Mermaid { source } => Ok(render_mermaid(source)),
+        DarkMatterNode::Terminal { lines, shell } => Ok(render_terminal(lines, *shell)),
+        DarkMatterNode::Math { source, display } => Ok(render_math(source, *display)),
+        DarkMatterNode::Diff { old, new, language, context_lines } => {
+            render_diff(old, new, language.as_deref(), *context_lines)
         }
 
         // Charts
-        DarkMatterNode::BarChart { data } => {
-            render_bar_chart(data, 800, 400)
+        DarkMatterNode::BarChart { data, with_table } => {
+            with_chart_table(render_bar_chart(data, 800, 400)?, data, *with_table)
         }
-        DarkMatterNode::LineChart { data } => {
-            render_line_chart(data, 800, 400)
+        DarkMatterNode::LineChart { data, with_table } => {
+            with_chart_table(render_line_chart(data, 800, 400)?, data, *with_table)
         }
-        DarkMatterNode::PieChart { data } => {
-            render_pie_chart(data, 400, 400)
+        DarkMatterNode::PieChart { data, with_table, options } => {
+            with_chart_table(render_pie_chart_with_options(data, 400, 400, options)?, data, *with_table)
         }
-        DarkMatterNode::AreaChart { data } => {
-            render_area_chart(data, 800, 400)
+        DarkMatterNode::AreaChart { data, with_table } => {
+            with_chart_table(render_area_chart(data, 800, 400)?, data, *with_table)
         }
-        DarkMatterNode::BubbleChart { data } => {
-            render_bubble_chart(data, 800, 400)
+        DarkMatterNode::BubbleChart { data, with_table } => {
+            with_chart_table(render_bubble_chart(data, 800, 400)?, data, *with_table)
         }
 
         // Interpolation should be processed before HTML generation
@@ -101,6 +165,15 @@ fn render_node(node: &DarkMatterNode) -> Result<String, RenderError> {
     }
 }
 
+/// Follow a chart's SVG with the table of its data, if it has one
+fn with_chart_table(svg: String, data: &ChartData, with_table: bool) -> Result<String, RenderError> {
+    if with_table {
+        Ok(svg + &render_chart_table(data)?)
+    } else {
+        Ok(svg)
+    }
+}
+
 /// Render markdown content to HTML using pulldown-cmark
 fn render_markdown(content: &MarkdownContent) -> Result<String, RenderError> {
     let mut options = Options::empty();
@@ -109,6 +182,8 @@ fn render_markdown(content: &MarkdownContent) -> Result<String, RenderError> {
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TASKLISTS);
     options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    // Renders `$...$` with the same markup as `render_math`
+    options.insert(Options::ENABLE_MATH);
 
     let parser = Parser::new_ext(&content.raw, options);
     let mut html_output = String::new();
@@ -230,6 +305,7 @@ mod tests {
             DarkMatterNode::YouTube {
                 video_id: "dQw4w9WgXcQ".to_string(),
                 width: WidthSpec::Pixels(512),
+                captions: None,
             },
         ];
 
@@ -258,14 +334,17 @@ mod tests {
             DarkMatterNode::YouTube {
                 video_id: "dQw4w9WgXcQ".to_string(),
                 width: WidthSpec::Pixels(512),
+                captions: None,
             },
             DarkMatterNode::YouTube {
                 video_id: "jNQXAC9IVRw".to_string(),
                 width: WidthSpec::Pixels(800),
+                captions: None,
             },
             DarkMatterNode::YouTube {
                 video_id: "9bZkp7q19f0".to_string(),
                 width: WidthSpec::Rems(32.0),
+                captions: None,
             },
         ];
 
@@ -287,6 +366,10 @@ mod tests {
         // Verify JS is included exactly once
         let js_count = html.matches(r#"<script id="dm-youtube">"#).count();
         assert_eq!(js_count, 1, "JS should be included exactly once despite multiple embeds");
+
+        // The theme the CSS reads is included once, ahead of it
+        assert_eq!(html.matches(r#"<style id="dm-theme">"#).count(), 1);
+        assert!(html.find(r#"<style id="dm-theme">"#) < html.find(r#"<style id="dm-youtube">"#));
     }
 
     #[test]
@@ -298,11 +381,13 @@ mod tests {
             DarkMatterNode::YouTube {
                 video_id: "dQw4w9WgXcQ".to_string(),
                 width: WidthSpec::Pixels(512),
+                captions: None,
             },
             DarkMatterNode::Text("Middle text".to_string()),
             DarkMatterNode::YouTube {
                 video_id: "jNQXAC9IVRw".to_string(),
                 width: WidthSpec::Pixels(800),
+                captions: None,
             },
             DarkMatterNode::Text("Conclusion text".to_string()),
         ];
@@ -346,19 +431,20 @@ mod tests {
             DarkMatterNode::YouTube {
                 video_id: "dQw4w9WgXcQ".to_string(),
                 width: WidthSpec::Pixels(512),
+                captions: None,
             },
         ];
 
         let html = to_html(&nodes).unwrap();
 
         // Find positions of embed, CSS, and JS
-        let embed_pos = html.find("dm-youtube-container").unwrap();
+        let embed_pos = html.find(r#"<div class="dm-youtube-container""#).unwrap();
         let css_pos = html.find(r#"<style id="dm-youtube">"#).unwrap();
         let js_pos = html.find(r#"<script id="dm-youtube">"#).unwrap();
 
-        // Assets should come after the first embed
-        assert!(css_pos > embed_pos, "CSS should come after the first embed");
-        assert!(js_pos > embed_pos, "JS should come after the first embed");
+        // Assets start the document
+        assert!(css_pos < embed_pos, "CSS should come before the embed");
+        assert!(js_pos < embed_pos, "JS should come before the embed");
 
         // CSS should come before JS
         assert!(css_pos < js_pos, "CSS should come before JS");
@@ -372,14 +458,17 @@ mod tests {
             DarkMatterNode::YouTube {
                 video_id: "video1".to_string(),
                 width: WidthSpec::Pixels(512),
+                captions: None,
             },
             DarkMatterNode::YouTube {
                 video_id: "video2".to_string(),
                 width: WidthSpec::Rems(32.0),
+                captions: None,
             },
             DarkMatterNode::YouTube {
                 video_id: "video3".to_string(),
                 width: WidthSpec::Percentage(80),
+                captions: None,
             },
         ];
 
@@ -396,4 +485,203 @@ mod tests {
         let js_count = html.matches(r#"<script id="dm-youtube">"#).count();
         assert_eq!(js_count, 1);
     }
+
+    #[test]
+    fn test_unresolved_env_table_redacts_values() {
+        unsafe {
+            std::env::set_var("COMPOSITION_TEST_HTML_ENV_SECRET", "hunter2");
+        }
+        let nodes = vec![DarkMatterNode::EnvTable {
+            filter: Some("COMPOSITION_TEST_HTML_ENV_".to_string()),
+            sort: true,
+            show_values: true,
+        }];
+
+        let html = to_html(&nodes).unwrap();
+
+        assert!(html.contains("<td>COMPOSITION_TEST_HTML_ENV_SECRET</td>"), "{}", html);
+        assert!(html.contains("<td>[SET]</td>"), "{}", html);
+        assert!(!html.contains("hunter2"), "{}", html);
+        unsafe {
+            std::env::remove_var("COMPOSITION_TEST_HTML_ENV_SECRET");
+        }
+    }
+
+    #[test]
+    fn test_footnotes_listed_at_end_of_document() {
+        let nodes = crate::parse::parse_markdown(
+            "# Paper\n\nA claim^[1] and an aside^[Only *inline*.].\n\n::footnote[1] Smith, 2020.\n\nThe end.",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert!(html.contains(r##"<sup><a href="#fn-1" id="fnref-1">[1]</a></sup>"##), "{}", html);
+        assert!(html.contains(r##"<sup><a href="#fn-2" id="fnref-2">[2]</a></sup>"##), "{}", html);
+        let footer = html.find(r#"<footer class="footnotes">"#).unwrap();
+        assert!(footer > html.find("The end.").unwrap());
+        assert!(html.contains(r##"<li id="fn-1"><p>Smith, 2020. <a href="#fnref-1" class="footnote-backref">↩</a></p></li>"##));
+        assert!(html.contains("<em>inline</em>"));
+        assert!(html.trim_end().ends_with("</footer>"));
+    }
+
+    #[test]
+    fn test_footnotes_in_components_share_one_footer() {
+        let nodes = crate::parse::parse_markdown(
+            "::summary\nSources\n::details\nSee^[a].\n\n::footnote[a] In the details.\n::end\n\nAlso^[a].",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert_eq!(html.matches("<footer").count(), 1);
+        assert_eq!(html.matches(r#"<li id="fn-a">"#).count(), 1);
+        assert!(html.contains(r#"id="fnref-a-2""#));
+    }
+
+    #[test]
+    fn test_sanitized_html_keeps_footnotes() {
+        let nodes = vec![
+            DarkMatterNode::FootnoteRef { id: "1".to_string() },
+            DarkMatterNode::Footnote {
+                id: "1".to_string(),
+                content: vec![DarkMatterNode::Text("<script>x</script>Note".to_string())],
+            },
+        ];
+
+        let html = to_sanitized_html(&nodes).unwrap();
+
+        assert!(html.contains(r#"id="fnref-1""#));
+        assert!(html.contains(r#"<li id="fn-1">"#));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_footnote_without_definition_is_inline() {
+        let nodes = vec![DarkMatterNode::FootnoteRef { id: "sic".to_string() }];
+
+        let html = to_html(&nodes).unwrap();
+
+        assert!(html.contains(r#"id="fnref-1""#), "{}", html);
+        assert!(html.contains(r#"<li id="fn-1">"#) && html.contains("sic"), "{}", html);
+    }
+
+    #[test]
+    fn test_mermaid_script_included_once() {
+        let nodes = crate::parse::parse_markdown(
+            "::mermaid\ngraph TD\n  A-->B\n::end\n\nBetween.\n\n::mermaid\nsequenceDiagram\n  A->>B: Hi\n::end",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert_eq!(html.matches(r#"<pre class="mermaid">"#).count(), 2);
+        assert!(html.contains("A--&gt;B"));
+        assert_eq!(html.matches(r#"<script type="module" id="dm-mermaid">"#).count(), 1);
+        assert!(html.starts_with(r#"<script type="module" id="dm-mermaid">"#));
+    }
+
+    #[test]
+    fn test_nested_components_share_assets() {
+        let nodes = crate::parse::parse_markdown(
+            "::columns lg: 2
+::mermaid
+graph TD
+::end
+::break
+::summary
+Diagram
+::details
+::mermaid
+graph LR
+::end
+::end
+::end
+
+::mermaid
+sequenceDiagram
+::end",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert_eq!(html.matches(r#"<pre class="mermaid">"#).count(), 3);
+        assert_eq!(html.matches(r#"id="dm-mermaid""#).count(), 1, "{}", html);
+    }
+
+    #[test]
+    fn test_no_mermaid_no_script() {
+        let html = to_html(&[DarkMatterNode::Text("No diagrams".to_string())]).unwrap();
+
+        assert!(!html.contains("dm-mermaid"));
+    }
+
+    #[test]
+    fn test_diff_styles_included_once() {
+        let nodes = crate::parse::parse_markdown(
+            "::diff --inline \"a = 1\" \"a = 2\" --lang python\n\nBetween.\n\n::diff --inline \"b\" \"c\"",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert_eq!(html.matches(r#"<pre class="dm-diff">"#).count(), 2);
+        assert!(html.contains(r#"<code class="language-python">"#));
+        assert!(html.contains(r#"<span class="diff-add">+a = 2</span>"#));
+        assert_eq!(html.matches(r#"<style id="dm-diff">"#).count(), 1);
+    }
+
+    #[test]
+    fn test_math_markup_and_assets_included_once() {
+        let nodes = crate::parse::parse_markdown("Area: $\\pi r^2$, not \\$5.\n\n$$\nx < y\n$$").unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert!(html.contains(r#"<span class="math math-inline">\pi r^2</span>"#), "{}", html);
+        assert!(html.contains(r#"<span class="math math-display">x &lt; y</span>"#), "{}", html);
+        assert!(html.contains("not $5."), "{}", html);
+        assert_eq!(html.matches(r#"<link rel="stylesheet" id="dm-katex""#).count(), 1);
+        assert_eq!(html.matches(r#"<script type="module" id="dm-katex">"#).count(), 1);
+    }
+
+    #[test]
+    fn test_no_math_no_katex() {
+        let nodes = crate::parse::parse_markdown("Prices: $5 and $10.").unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert!(!html.contains("dm-katex"), "{}", html);
+        assert!(html.contains("$5 and $10"), "{}", html);
+    }
+
+    #[test]
+    fn test_terminal_assets_included_once() {
+        let nodes = crate::parse::parse_markdown(
+            "::terminal --shell powershell\nPS> Get-Date\nMonday\n::endterminal\n\n::terminal\n$ echo hi\nhi\n::endterminal",
+        )
+        .unwrap();
+
+        let html = to_html(&nodes).unwrap();
+
+        assert_eq!(html.matches(r#"<div class="dm-terminal""#).count(), 2);
+        assert!(html.contains(r#"<span class="dm-terminal-prompt">PS&gt;</span><code>Get-Date</code>"#));
+        assert_eq!(html.matches(r#"<style id="dm-terminal">"#).count(), 1);
+        assert_eq!(html.matches(r#"<script id="dm-clipboard">"#).count(), 1);
+    }
+
+    #[test]
+    fn test_chart_tables_follow_charts_with_styles_once() {
+        let points = vec![crate::types::DataPoint { label: "A".to_string(), value: 1.0, metadata: None }];
+        let chart = |with_table| DarkMatterNode::BarChart { data: ChartData::Inline(points.clone()), with_table };
+
+        let html = to_html(&[chart(true), chart(false), chart(true)]).unwrap();
+
+        assert_eq!(html.matches(r#"<div class="dm-chart-table">"#).count(), 2);
+        assert!(html.contains(r#"</svg><div class="dm-chart-table"><table>"#));
+        assert_eq!(html.matches(r#"<style id="dm-chart-table">"#).count(), 1);
+
+        let html = to_html(&[chart(false)]).unwrap();
+        assert!(!html.contains("dm-chart-table"));
+    }
 }
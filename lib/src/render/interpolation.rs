@@ -1,102 +1,318 @@
-use crate::error::RenderError;
-use crate::types::{DarkMatterNode, Frontmatter};
-use chrono::{Datelike, Local, Utc, Weekday};
-use regex::Regex;
+use super::table::render_env_table;
+use crate::error::{ParseWarning, ParseWarningCode, RenderError};
+use crate::types::{DarkMatterNode, Frontmatter, MarkdownContent};
+use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
+use chrono_tz::Tz;
+use regex::{Captures, Regex};
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::LazyLock;
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 /// Regex pattern for matching {{variable}} interpolation syntax
+///
+/// The first alternative matches the `\{{` escape, which is kept as a literal
+/// `{{` instead of starting a variable. Environment variables are written
+/// `{{env.NAME}}`, and values nested in objects and arrays by a dotted path,
+/// e.g. `{{versions.latest}}` or `{{authors.0}}`.
 static INTERPOLATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"\{\{([a-zA-Z_][a-zA-Z0-9_]*)\}\}").expect("Invalid regex pattern")
+    Regex::new(r"\\\{\{|\{\{([a-zA-Z_][a-zA-Z0-9_]*(?:\.[a-zA-Z0-9_]+)*)\}\}").expect("Invalid regex pattern")
 });
 
+/// Prefix of the variables read from the process environment
+const ENV_PREFIX: &str = "env.";
+
+/// Which environment variables interpolation may read, and how their values
+/// are written
+///
+/// Only variables named in `allowed` are read; any other `{{env.NAME}}` is
+/// treated as undefined, so a document can't expose the environment by
+/// naming a variable.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EnvAccess<'a> {
+    pub allowed: &'a [String],
+    /// Escape values for HTML, for Markdown that is rendered to HTML
+    pub escape_html: bool,
+    /// Variables the document names in `::require-env`, listed in each of
+    /// its env tables
+    pub required: &'a [String],
+}
+
+impl EnvAccess<'_> {
+    /// The value of the allowed variable `name`, or `Err` when it isn't allowed
+    fn lookup(&self, name: &str) -> Result<Option<String>, ()> {
+        if !self.allowed.iter().any(|allowed| allowed == name) {
+            return Err(());
+        }
+        Ok(std::env::var(name).ok().map(|value| if self.escape_html { escape_html(&value) } else { value }))
+    }
+}
+
+/// Variables interpolation couldn't substitute, each listed once in the order
+/// it first appears
+#[derive(Debug, Default)]
+struct Unresolved {
+    /// Variables without a value, including allowed but unset environment
+    /// variables
+    undefined: Vec<String>,
+    /// Environment variables that aren't in the allowlist
+    blocked: Vec<String>,
+}
+
+/// Link destinations (`](url)`) and autolinks (`<https://...>`), which text
+/// replacement leaves alone
+static LINK_URL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\]\([^)\s]*|<[a-zA-Z][a-zA-Z0-9+.-]*:[^>\s]*>").expect("Invalid regex pattern")
+});
+
+/// Month, weekday and season names of a built-in locale
+struct LocaleNames {
+    months: [&'static str; 12],
+    months_abbr: [&'static str; 12],
+    /// Monday first
+    days: [&'static str; 7],
+    days_abbr: [&'static str; 7],
+    /// Spring, summer, fall and winter
+    seasons: [&'static str; 4],
+}
+
+const ENGLISH: LocaleNames = LocaleNames {
+    months: [
+        "January", "February", "March", "April", "May", "June", "July", "August", "September", "October",
+        "November", "December",
+    ],
+    months_abbr: ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+    days: ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+    days_abbr: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    seasons: ["Spring", "Summer", "Fall", "Winter"],
+};
+
+const SPANISH: LocaleNames = LocaleNames {
+    months: [
+        "enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre",
+        "noviembre", "diciembre",
+    ],
+    months_abbr: ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"],
+    days: ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"],
+    days_abbr: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+    seasons: ["primavera", "verano", "otoño", "invierno"],
+};
+
+const FRENCH: LocaleNames = LocaleNames {
+    months: [
+        "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre",
+        "novembre", "décembre",
+    ],
+    months_abbr: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc.",
+    ],
+    days: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+    days_abbr: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+    seasons: ["printemps", "été", "automne", "hiver"],
+};
+
+const GERMAN: LocaleNames = LocaleNames {
+    months: [
+        "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober",
+        "November", "Dezember",
+    ],
+    months_abbr: ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+    days: ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+    days_abbr: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    seasons: ["Frühling", "Sommer", "Herbst", "Winter"],
+};
+
+const PORTUGUESE: LocaleNames = LocaleNames {
+    months: [
+        "janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro",
+        "novembro", "dezembro",
+    ],
+    months_abbr: ["jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez"],
+    days: ["segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado", "domingo"],
+    days_abbr: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+    seasons: ["primavera", "verão", "outono", "inverno"],
+};
+
+/// The built-in names for a locale such as `es` or `es-MX`
+///
+/// Only the language subtag is considered.
+fn locale_names(locale: &str) -> Option<&'static LocaleNames> {
+    let language = locale.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    match language.as_str() {
+        "en" => Some(&ENGLISH),
+        "es" => Some(&SPANISH),
+        "fr" => Some(&FRENCH),
+        "de" => Some(&GERMAN),
+        "pt" => Some(&PORTUGUESE),
+        _ => None,
+    }
+}
+
+/// Names given in the `date_names` frontmatter property, replacing those of
+/// the locale
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DateNameOverrides {
+    months: Option<Vec<String>>,
+    months_abbr: Option<Vec<String>>,
+    days: Option<Vec<String>>,
+    days_abbr: Option<Vec<String>>,
+    seasons: Option<Vec<String>>,
+}
+
+/// Month, weekday and season names used by the date variables
+#[derive(Debug, Clone)]
+struct DateNames {
+    months: Vec<String>,
+    months_abbr: Vec<String>,
+    days: Vec<String>,
+    days_abbr: Vec<String>,
+    seasons: Vec<String>,
+}
+
+impl From<&LocaleNames> for DateNames {
+    fn from(names: &LocaleNames) -> Self {
+        let owned = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            months: owned(&names.months),
+            months_abbr: owned(&names.months_abbr),
+            days: owned(&names.days),
+            days_abbr: owned(&names.days_abbr),
+            seasons: owned(&names.seasons),
+        }
+    }
+}
+
+/// How the date variables are computed
+///
+/// Read from the `timezone` (an IANA name such as `America/Mexico_City`),
+/// `hemisphere` (`northern` or `southern`), `locale` and `date_names`
+/// frontmatter properties. Without them dates are in the system's local
+/// time zone, with Northern Hemisphere seasons and English names.
+#[derive(Debug, Clone)]
+struct DateSettings {
+    timezone: Option<Tz>,
+    southern: bool,
+    names: DateNames,
+}
+
+impl Default for DateSettings {
+    fn default() -> Self {
+        Self {
+            timezone: None,
+            southern: false,
+            names: DateNames::from(&ENGLISH),
+        }
+    }
+}
+
+impl DateSettings {
+    fn from_frontmatter(frontmatter: &Frontmatter) -> Result<Self, RenderError> {
+        let mut settings = Self::default();
+
+        if let Some(name) = frontmatter.get_string("timezone") {
+            let timezone = name
+                .parse::<Tz>()
+                .map_err(|_| RenderError::InvalidDateSettings(format!("unknown timezone '{}'", name)))?;
+            settings.timezone = Some(timezone);
+        }
+
+        if let Some(hemisphere) = frontmatter.get_string("hemisphere") {
+            settings.southern = match hemisphere.to_ascii_lowercase().as_str() {
+                "northern" | "north" => false,
+                "southern" | "south" => true,
+                _ => {
+                    return Err(RenderError::InvalidDateSettings(format!(
+                        "hemisphere should be 'northern' or 'southern', not '{}'",
+                        hemisphere
+                    )))
+                }
+            };
+        }
+
+        if let Some(locale) = frontmatter.get_string("locale") {
+            let names = locale_names(locale).ok_or_else(|| {
+                RenderError::InvalidDateSettings(format!(
+                    "no built-in names for locale '{}'; use one of en, es, fr, de, pt or set date_names",
+                    locale
+                ))
+            })?;
+            settings.names = DateNames::from(names);
+        }
+
+        if let Some(overrides) = frontmatter.get_typed::<DateNameOverrides>("date_names") {
+            let overrides = overrides.map_err(|e| RenderError::InvalidDateSettings(e.to_string()))?;
+            let names = &mut settings.names;
+            for (field, replacement, target, count) in [
+                ("months", overrides.months, &mut names.months, 12),
+                ("months_abbr", overrides.months_abbr, &mut names.months_abbr, 12),
+                ("days", overrides.days, &mut names.days, 7),
+                ("days_abbr", overrides.days_abbr, &mut names.days_abbr, 7),
+                ("seasons", overrides.seasons, &mut names.seasons, 4),
+            ] {
+                let Some(replacement) = replacement else { continue };
+                if replacement.len() != count {
+                    return Err(RenderError::InvalidDateSettings(format!(
+                        "date_names.{} should have {} names, not {}",
+                        field,
+                        count,
+                        replacement.len()
+                    )));
+                }
+                *target = replacement;
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
 /// Generate utility variables that are always available
 ///
 /// Returns a HashMap of utility variable names to their JSON values.
 /// These variables provide date/time information and can be overridden
 /// by custom frontmatter variables.
-fn generate_utility_variables() -> HashMap<String, serde_json::Value> {
+fn generate_utility_variables(settings: &DateSettings) -> HashMap<String, serde_json::Value> {
+    utility_variables_at(Utc::now(), settings)
+}
+
+/// The utility variables for the instant `now`
+fn utility_variables_at(now_utc: DateTime<Utc>, settings: &DateSettings) -> HashMap<String, serde_json::Value> {
     use serde_json::json;
 
-    let now_local = Local::now();
-    let now_utc = Utc::now();
+    let now_local: DateTime<FixedOffset> = match &settings.timezone {
+        Some(timezone) => now_utc.with_timezone(timezone).fixed_offset(),
+        None => now_utc.with_timezone(&Local).fixed_offset(),
+    };
     let today = now_local.date_naive();
     let yesterday = today - chrono::Days::new(1);
     let tomorrow = today + chrono::Days::new(1);
+    let names = &settings.names;
 
-    // Calculate season (Northern Hemisphere)
+    // Calculate season, Northern Hemisphere unless configured otherwise
     let month = now_local.month();
-    let season = match month {
-        3..=5 => "Spring",
-        6..=8 => "Summer",
-        9..=11 => "Fall",
-        12 | 1 | 2 => "Winter",
-        _ => "Unknown",
+    let northern_season = match month {
+        3..=5 => 0,
+        6..=8 => 1,
+        9..=11 => 2,
+        _ => 3,
     };
+    let season = if settings.southern { (northern_season + 2) % 4 } else { northern_season };
 
     // Get day of week
-    let weekday = now_local.weekday();
-    let day_of_week = match weekday {
-        Weekday::Mon => "Monday",
-        Weekday::Tue => "Tuesday",
-        Weekday::Wed => "Wednesday",
-        Weekday::Thu => "Thursday",
-        Weekday::Fri => "Friday",
-        Weekday::Sat => "Saturday",
-        Weekday::Sun => "Sunday",
-    };
-
-    let day_of_week_abbr = match weekday {
-        Weekday::Mon => "Mon",
-        Weekday::Tue => "Tue",
-        Weekday::Wed => "Wed",
-        Weekday::Thu => "Thu",
-        Weekday::Fri => "Fri",
-        Weekday::Sat => "Sat",
-        Weekday::Sun => "Sun",
-    };
-
-    // Get month names
-    let month_name = match month {
-        1 => "January",
-        2 => "February",
-        3 => "March",
-        4 => "April",
-        5 => "May",
-        6 => "June",
-        7 => "July",
-        8 => "August",
-        9 => "September",
-        10 => "October",
-        11 => "November",
-        12 => "December",
-        _ => "Unknown",
-    };
-
-    let month_abbr = match month {
-        1 => "Jan",
-        2 => "Feb",
-        3 => "Mar",
-        4 => "Apr",
-        5 => "May",
-        6 => "Jun",
-        7 => "Jul",
-        8 => "Aug",
-        9 => "Sep",
-        10 => "Oct",
-        11 => "Nov",
-        12 => "Dec",
-        _ => "Unk",
-    };
+    let weekday = now_local.weekday().num_days_from_monday() as usize;
 
     // Check if today is last day of month
     let next_day = today + chrono::Days::new(1);
     let is_last_day = next_day.month() != today.month();
 
-    // Get timezone - use offset format since IANA names aren't available
-    let tz_offset = now_local.offset();
-    let timezone = format!("{}", tz_offset);
+    // Get timezone - the configured IANA name, or else the offset since the
+    // system's IANA name isn't available
+    let timezone = match &settings.timezone {
+        Some(timezone) => timezone.name().to_string(),
+        None => format!("{}", now_local.offset()),
+    };
 
     let mut vars = HashMap::new();
 
@@ -105,17 +321,17 @@ fn generate_utility_variables() -> HashMap<String, serde_json::Value> {
     vars.insert("yesterday".to_string(), json!(yesterday.format("%Y-%m-%d").to_string()));
     vars.insert("tomorrow".to_string(), json!(tomorrow.format("%Y-%m-%d").to_string()));
     vars.insert("year".to_string(), json!(now_local.year().to_string()));
-    vars.insert("month".to_string(), json!(month_name));
-    vars.insert("month_abbr".to_string(), json!(month_abbr));
+    vars.insert("month".to_string(), json!(names.months[month as usize - 1]));
+    vars.insert("month_abbr".to_string(), json!(names.months_abbr[month as usize - 1]));
     vars.insert("month_numeric".to_string(), json!(format!("{:02}", month)));
     vars.insert("day".to_string(), json!(format!("{:02}", now_local.day())));
 
     // Day of week
-    vars.insert("day_of_week".to_string(), json!(day_of_week));
-    vars.insert("day_of_week_abbr".to_string(), json!(day_of_week_abbr));
+    vars.insert("day_of_week".to_string(), json!(names.days[weekday]));
+    vars.insert("day_of_week_abbr".to_string(), json!(names.days_abbr[weekday]));
 
     // Season
-    vars.insert("season".to_string(), json!(season));
+    vars.insert("season".to_string(), json!(names.seasons[season]));
 
     // Week number (ISO week)
     vars.insert("week_number".to_string(), json!(now_local.iso_week().week().to_string()));
@@ -139,17 +355,46 @@ fn generate_utility_variables() -> HashMap<String, serde_json::Value> {
 /// Process frontmatter interpolation in content
 ///
 /// This function:
-/// 1. Generates utility variables (dates, times, etc.)
+/// 1. Generates utility variables (dates, times, etc.) in the time zone,
+///    hemisphere and locale set by the frontmatter
 /// 2. Merges with custom frontmatter (custom overrides utilities)
-/// 3. Replaces {{variable}} patterns with values
-/// 4. Applies text replacements defined in frontmatter.replace
+/// 3. Replaces {{variable}} patterns with values in a single left-to-right
+///    pass, so substituted values are never scanned again; `\{{` produces a
+///    literal `{{`. Code blocks and code spans are left as written
+/// 4. Applies text replacements defined in frontmatter.replace, outside of
+///    code blocks, code spans and link URLs
 /// 5. Returns the processed content
+///
+/// No environment variables are read; see [`process_interpolation_with_env`].
 #[instrument(skip(frontmatter))]
 pub fn process_interpolation(content: &str, frontmatter: &Frontmatter) -> Result<String, RenderError> {
-    let mut result = content.to_string();
+    interpolate(content, frontmatter, EnvAccess::default(), &mut Unresolved::default())
+}
 
+/// Process interpolation in content, also substituting `{{env.NAME}}` for
+/// each variable in `allowed_env_vars`
+///
+/// Values are written as they are; the render passes escape them for HTML.
+/// Other environment variables are left as written, like undefined ones.
+pub fn process_interpolation_with_env(
+    content: &str,
+    frontmatter: &Frontmatter,
+    allowed_env_vars: &[String],
+) -> Result<String, RenderError> {
+    let env = EnvAccess { allowed: allowed_env_vars, escape_html: false, required: &[] };
+    interpolate(content, frontmatter, env, &mut Unresolved::default())
+}
+
+/// Interpolate `content`, adding the variables it couldn't substitute to
+/// `unresolved`
+fn interpolate(
+    content: &str,
+    frontmatter: &Frontmatter,
+    env: EnvAccess<'_>,
+    unresolved: &mut Unresolved,
+) -> Result<String, RenderError> {
     // Generate utility variables
-    let utilities = generate_utility_variables();
+    let utilities = generate_utility_variables(&DateSettings::from_frontmatter(frontmatter)?);
 
     // Merge: custom frontmatter overrides utilities
     let all_vars: HashMap<String, serde_json::Value> = utilities
@@ -158,63 +403,358 @@ pub fn process_interpolation(content: &str, frontmatter: &Frontmatter) -> Result
         .collect();
 
     // Process {{variable}} patterns
-    for cap in INTERPOLATION_REGEX.captures_iter(content) {
-        let var_name = &cap[1];
-        if let Some(value) = all_vars.get(var_name) {
-            // Convert JSON value to string
-            let replacement = match value {
-                serde_json::Value::String(s) => s.clone(),
-                serde_json::Value::Number(n) => n.to_string(),
-                serde_json::Value::Bool(b) => b.to_string(),
-                serde_json::Value::Null => String::new(),
-                _ => {
-                    // For complex values (arrays, objects), use JSON representation
-                    serde_json::to_string(value)
-                        .map_err(|_e| RenderError::InterpolationFailed {
-                            variable: var_name.to_string(),
-                        })?
+    let mut failure = None;
+    let mut substitute = |text: &str| {
+        INTERPOLATION_REGEX
+            .replace_all(text, |cap: &Captures| {
+                let Some(var_name) = cap.get(1).map(|m| m.as_str()) else {
+                    // Escaped `\{{`
+                    return "{{".to_string();
+                };
+                if let Some(env_name) = var_name.strip_prefix(ENV_PREFIX) {
+                    let missing = match env.lookup(env_name) {
+                        Ok(Some(value)) => return value,
+                        Ok(None) => &mut unresolved.undefined,
+                        Err(()) => &mut unresolved.blocked,
+                    };
+                    if !missing.iter().any(|name| name == var_name) {
+                        missing.push(var_name.to_string());
+                    }
+                    return cap[0].to_string();
                 }
-            };
-            result = result.replace(&cap[0], &replacement);
-        }
-        // If variable not found, leave it as-is (or could error based on strictness setting)
+                match lookup_path(&all_vars, var_name).map(|value| value_to_string(var_name, value)) {
+                    Some(Ok(replacement)) => replacement,
+                    Some(Err(e)) => {
+                        failure.get_or_insert(e);
+                        cap[0].to_string()
+                    }
+                    // If variable not found, leave it as-is
+                    None => {
+                        if !unresolved.undefined.iter().any(|name| name == var_name) {
+                            unresolved.undefined.push(var_name.to_string());
+                        }
+                        cap[0].to_string()
+                    }
+                }
+            })
+            .into_owned()
+    };
+
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for code in protected_ranges(content, false) {
+        result.push_str(&substitute(&content[position..code.start]));
+        result.push_str(&content[code.clone()]);
+        position = code.end;
+    }
+    result.push_str(&substitute(&content[position..]));
+    if let Some(e) = failure {
+        return Err(e);
     }
 
     // Process text replacements from frontmatter
-    if let Some(replacements) = &frontmatter.replace {
-        for (from, to) in replacements {
-            result = result.replace(from, to);
+    match &frontmatter.replace {
+        Some(replacements) if !replacements.is_empty() => Ok(apply_replacements(&result, replacements)),
+        _ => Ok(result),
+    }
+}
+
+/// The value at a dotted `path`: a variable, then object keys and array
+/// indices inside it
+fn lookup_path<'a>(vars: &'a HashMap<String, serde_json::Value>, path: &str) -> Option<&'a serde_json::Value> {
+    let mut segments = path.split('.');
+    let root = vars.get(segments.next()?)?;
+    segments.try_fold(root, |value, segment| match value {
+        serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => value.get(segment),
+    })
+}
+
+/// The custom frontmatter values of a document, with the `{{variable}}`s in
+/// their strings interpolated from the same frontmatter
+///
+/// This is how an imported document's values are resolved: one level only,
+/// so its own imports and environment variables are left as written, as is
+/// anything undefined. Text replacements aren't applied.
+pub(crate) fn interpolate_frontmatter_values(
+    frontmatter: &Frontmatter,
+) -> Result<serde_json::Map<String, serde_json::Value>, RenderError> {
+    fn resolve(value: &serde_json::Value, frontmatter: &Frontmatter) -> Result<serde_json::Value, RenderError> {
+        Ok(match value {
+            serde_json::Value::String(text) => serde_json::Value::String(interpolate(
+                text,
+                frontmatter,
+                EnvAccess::default(),
+                &mut Unresolved::default(),
+            )?),
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|item| resolve(item, frontmatter)).collect::<Result<_, _>>()?)
+            }
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(key, item)| Ok((key.clone(), resolve(item, frontmatter)?)))
+                    .collect::<Result<_, RenderError>>()?,
+            ),
+            other => other.clone(),
+        })
+    }
+    let own = Frontmatter { replace: None, ..frontmatter.clone() };
+    own.custom
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), resolve(value, &own)?)))
+        .collect()
+}
+
+/// Convert a frontmatter value to the text it interpolates to
+fn value_to_string(var_name: &str, value: &serde_json::Value) -> Result<String, RenderError> {
+    match value {
+        serde_json::Value::String(s) => Ok(s.clone()),
+        serde_json::Value::Number(n) => Ok(n.to_string()),
+        serde_json::Value::Bool(b) => Ok(b.to_string()),
+        serde_json::Value::Null => Ok(String::new()),
+        _ => {
+            // For complex values (arrays, objects), use JSON representation
+            serde_json::to_string(value).map_err(|_e| RenderError::InterpolationFailed {
+                variable: var_name.to_string(),
+            })
         }
     }
+}
 
-    Ok(result)
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Apply frontmatter `replace` rules outside of code and link URLs
+///
+/// All rules are applied in one pass, longest key first, so the output of one
+/// rule is never rewritten by another.
+fn apply_replacements(text: &str, replacements: &HashMap<String, String>) -> String {
+    let mut keys: Vec<&String> = replacements.keys().filter(|key| !key.is_empty()).collect();
+    if keys.is_empty() {
+        return text.to_string();
+    }
+    keys.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+    let pattern = keys.iter().map(|key| regex::escape(key)).collect::<Vec<_>>().join("|");
+    let Ok(rules) = Regex::new(&pattern) else {
+        warn!("Skipping text replacement: too many or too large replace rules");
+        return text.to_string();
+    };
+
+    let mut result = String::with_capacity(text.len());
+    let mut position = 0;
+    for protected in protected_ranges(text, true) {
+        result.push_str(&rules.replace_all(&text[position..protected.start], |cap: &Captures| {
+            replacements[&cap[0]].clone()
+        }));
+        result.push_str(&text[protected.clone()]);
+        position = protected.end;
+    }
+    result.push_str(&rules.replace_all(&text[position..], |cap: &Captures| replacements[&cap[0]].clone()));
+
+    result
+}
+
+/// Byte ranges of fenced code blocks, inline code spans and, with `links`, link
+/// URLs, in order
+pub(super) fn protected_ranges(text: &str, links: bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut prose_start = 0;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let run = marker.map_or(0, |c| trimmed.chars().take_while(|&x| x == c).count());
+
+        match fence {
+            Some((char, length, start)) => {
+                let closes = marker == Some(char)
+                    && run >= length
+                    && indent <= 3
+                    && trimmed[run..].trim().is_empty();
+                if closes {
+                    ranges.push(start..offset);
+                    fence = None;
+                    prose_start = offset;
+                }
+            }
+            None if indent <= 3 && run >= 3 => {
+                inline_ranges(text, prose_start..line_start, links, &mut ranges);
+                fence = Some((marker.unwrap_or('`'), run, line_start));
+            }
+            None => {}
+        }
+    }
+
+    match fence {
+        // An unclosed fence runs to the end of the document
+        Some((_, _, start)) => ranges.push(start..text.len()),
+        None => inline_ranges(text, prose_start..text.len(), links, &mut ranges),
+    }
+
+    ranges
+}
+
+/// Add the code spans and, with `links`, link URLs within `prose` to `ranges`
+fn inline_ranges(text: &str, prose: Range<usize>, links: bool, ranges: &mut Vec<Range<usize>>) {
+    let segment = &text[prose.clone()];
+    let bytes = segment.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+        let mut j = i + run;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let other = bytes[j..].iter().take_while(|&&b| b == b'`').count();
+                if other == run {
+                    close = Some(j + other);
+                    break;
+                }
+                j += other;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                spans.push(prose.start + i..prose.start + end);
+                i = end;
+            }
+            // Unmatched backticks are literal text
+            None => i += run,
+        }
+    }
+
+    if links {
+        let mut urls: Vec<Range<usize>> = LINK_URL_REGEX
+            .find_iter(segment)
+            .map(|m| prose.start + m.start()..prose.start + m.end())
+            .filter(|link| !spans.iter().any(|span| span.start < link.end && link.start < span.end))
+            .collect();
+        spans.append(&mut urls);
+    }
+
+    spans.sort_by_key(|range| range.start);
+    ranges.extend(spans);
+}
+
+/// Every variable named in a `::require-env` of the node tree, once each
+fn required_env_vars(nodes: &[DarkMatterNode]) -> Vec<String> {
+    fn collect(nodes: &[DarkMatterNode], required: &mut Vec<String>) {
+        for node in nodes {
+            if let DarkMatterNode::RequireEnv { vars } = node {
+                for var in vars {
+                    if !required.contains(var) {
+                        required.push(var.clone());
+                    }
+                }
+            }
+            for children in node.children() {
+                collect(children, required);
+            }
+        }
+    }
+
+    let mut required = Vec::new();
+    collect(nodes, &mut required);
+    required
 }
 
 /// Recursively process interpolation in all text nodes
 pub fn process_nodes_interpolation(
     nodes: &[DarkMatterNode],
     frontmatter: &Frontmatter,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    interpolate_nodes(nodes, frontmatter, EnvAccess::default(), &mut Unresolved::default())
+}
+
+/// Process interpolation in all text nodes, with a warning for each
+/// variable that has no value and each environment variable that isn't in
+/// `allowed_env_vars`
+///
+/// Env tables are rendered here, into Markdown nodes holding their HTML,
+/// showing only the values of `allowed_env_vars` (see [`render_env_table`]).
+///
+/// Environment variable values substituted into Markdown are escaped for
+/// HTML, since Markdown may hold raw HTML; text nodes are escaped when they
+/// are rendered, so their values are substituted as they are.
+///
+/// Each variable is reported once, in the order it first appears. The
+/// warnings have no span, since transclusion has already merged content
+/// from other files into the nodes.
+pub(crate) fn process_nodes_interpolation_with_warnings(
+    nodes: &[DarkMatterNode],
+    frontmatter: &Frontmatter,
+    allowed_env_vars: &[String],
+) -> Result<(Vec<DarkMatterNode>, Vec<ParseWarning>), RenderError> {
+    let mut unresolved = Unresolved::default();
+    let required = required_env_vars(nodes);
+    let env = EnvAccess { allowed: allowed_env_vars, escape_html: true, required: &required };
+    let nodes = interpolate_nodes(nodes, frontmatter, env, &mut unresolved)?;
+    let undefined = unresolved.undefined.into_iter().map(|name| ParseWarning {
+        span: None,
+        message: format!("Undefined variable {{{{{}}}}}", name),
+        code: ParseWarningCode::UndefinedVariable,
+    });
+    let blocked = unresolved.blocked.into_iter().map(|name| ParseWarning {
+        span: None,
+        message: format!(
+            "Environment variable {} isn't in allowed_env_vars, so {{{{{}}}}} is left as written",
+            &name[ENV_PREFIX.len()..],
+            name
+        ),
+        code: ParseWarningCode::BlockedEnvVar,
+    });
+    Ok((nodes, undefined.chain(blocked).collect()))
+}
+
+/// Interpolate nodes, collecting the variables that couldn't be substituted
+fn interpolate_nodes(
+    nodes: &[DarkMatterNode],
+    frontmatter: &Frontmatter,
+    env: EnvAccess<'_>,
+    unresolved: &mut Unresolved,
 ) -> Result<Vec<DarkMatterNode>, RenderError> {
     let mut result = Vec::new();
 
     for node in nodes {
         let processed = match node {
+            // Text is escaped when it is rendered, so its values are written as they are
             DarkMatterNode::Text(text) => {
-                DarkMatterNode::Text(process_interpolation(text, frontmatter)?)
+                let env = EnvAccess { escape_html: false, ..env };
+                DarkMatterNode::Text(interpolate(text, frontmatter, env, unresolved)?)
             }
             DarkMatterNode::Markdown(content) => {
                 let mut new_content = content.clone();
-                new_content.raw = process_interpolation(&content.raw, frontmatter)?;
+                new_content.raw = interpolate(&content.raw, frontmatter, env, unresolved)?;
                 DarkMatterNode::Markdown(new_content)
             }
             DarkMatterNode::Popover { trigger, content } => {
                 let processed_trigger = Box::new(
-                    process_nodes_interpolation(&[*trigger.clone()], frontmatter)?
+                    interpolate_nodes(&[*trigger.clone()], frontmatter, env, unresolved)?
                         .into_iter()
                         .next()
                         .unwrap_or(DarkMatterNode::Text(String::new())),
                 );
-                let processed_content = process_nodes_interpolation(content, frontmatter)?;
+                let processed_content = interpolate_nodes(content, frontmatter, env, unresolved)?;
                 DarkMatterNode::Popover {
                     trigger: processed_trigger,
                     content: processed_content,
@@ -223,7 +763,7 @@ pub fn process_nodes_interpolation(
             DarkMatterNode::Columns { breakpoints, sections } => {
                 let processed_sections = sections
                     .iter()
-                    .map(|section| process_nodes_interpolation(section, frontmatter))
+                    .map(|section| interpolate_nodes(section, frontmatter, env, unresolved))
                     .collect::<Result<Vec<_>, _>>()?;
                 DarkMatterNode::Columns {
                     breakpoints: breakpoints.clone(),
@@ -231,13 +771,28 @@ pub fn process_nodes_interpolation(
                 }
             }
             DarkMatterNode::Disclosure { summary, details } => {
-                let processed_summary = process_nodes_interpolation(summary, frontmatter)?;
-                let processed_details = process_nodes_interpolation(details, frontmatter)?;
+                let processed_summary = interpolate_nodes(summary, frontmatter, env, unresolved)?;
+                let processed_details = interpolate_nodes(details, frontmatter, env, unresolved)?;
                 DarkMatterNode::Disclosure {
                     summary: processed_summary,
                     details: processed_details,
                 }
             }
+            DarkMatterNode::AspectRatio { numerator, denominator, content } => {
+                DarkMatterNode::AspectRatio {
+                    numerator: *numerator,
+                    denominator: *denominator,
+                    content: interpolate_nodes(content, frontmatter, env, unresolved)?,
+                }
+            }
+            DarkMatterNode::Footnote { id, content } => DarkMatterNode::Footnote {
+                id: id.clone(),
+                content: interpolate_nodes(content, frontmatter, env, unresolved)?,
+            },
+            DarkMatterNode::EnvTable { filter, sort, show_values } => DarkMatterNode::Markdown(MarkdownContent {
+                raw: render_env_table(filter.as_deref(), *sort, *show_values, env.allowed, env.required)?,
+                frontmatter: None,
+            }),
             // Other node types pass through unchanged
             other => other.clone(),
         };
@@ -345,6 +900,89 @@ mod tests {
         assert_eq!(result, "Value: ");
     }
 
+    #[test]
+    fn test_interpolated_values_are_not_expanded_again() {
+        let mut fm = Frontmatter::default();
+        fm.custom.insert("greeting".to_string(), "Hi {{name}} $1 ${name}".into());
+        fm.custom.insert("name".to_string(), "Alice".into());
+
+        let result = process_interpolation("{{greeting}}, {{name}}", &fm).unwrap();
+        assert_eq!(result, "Hi {{name}} $1 ${name}, Alice");
+    }
+
+    #[test]
+    fn test_interpolation_escape() {
+        let mut fm = Frontmatter::default();
+        fm.custom.insert("title".to_string(), "My Title".into());
+
+        let result = process_interpolation(r"Use \{{title}} to show {{title}}", &fm).unwrap();
+        assert_eq!(result, "Use {{title}} to show My Title");
+    }
+
+    #[test]
+    fn test_interpolation_skips_code() {
+        let mut fm = Frontmatter::default();
+        fm.custom.insert("title".to_string(), "My Title".into());
+
+        let content = "{{title}} `{{title}}` [link](https://example.com/{{title}})\n\
+                       ```\n{{title}}\n```\n\
+                       after {{title}}";
+        let result = process_interpolation(content, &fm).unwrap();
+        assert_eq!(
+            result,
+            "My Title `{{title}}` [link](https://example.com/My Title)\n\
+             ```\n{{title}}\n```\n\
+             after My Title"
+        );
+    }
+
+    #[test]
+    fn test_replacements_are_not_chained() {
+        let fm = Frontmatter {
+            replace: Some(HashMap::from([
+                ("cat".to_string(), "dog".to_string()),
+                ("dog".to_string(), "wolf".to_string()),
+                ("category".to_string(), "group".to_string()),
+            ])),
+            ..Default::default()
+        };
+
+        let result = process_interpolation("cat dog category", &fm).unwrap();
+        assert_eq!(result, "dog wolf group");
+    }
+
+    #[test]
+    fn test_replacements_skip_code_and_urls() {
+        let fm = Frontmatter {
+            replace: Some(HashMap::from([("foo".to_string(), "bar".to_string())])),
+            ..Default::default()
+        };
+
+        let content = "foo `foo` ``a`foo`` [foo](https://foo.example/foo) <https://foo.example>\n\
+                       ```rust\nlet foo = 1;\n```\n\
+                       ~~~~\nfoo\n~~~~\n\
+                       after foo";
+        let result = process_interpolation(content, &fm).unwrap();
+        assert_eq!(
+            result,
+            "bar `foo` ``a`foo`` [bar](https://foo.example/foo) <https://foo.example>\n\
+             ```rust\nlet foo = 1;\n```\n\
+             ~~~~\nfoo\n~~~~\n\
+             after bar"
+        );
+    }
+
+    #[test]
+    fn test_replacements_in_unclosed_fence() {
+        let fm = Frontmatter {
+            replace: Some(HashMap::from([("foo".to_string(), "bar".to_string())])),
+            ..Default::default()
+        };
+
+        let result = process_interpolation("foo\n```\nfoo", &fm).unwrap();
+        assert_eq!(result, "bar\n```\nfoo");
+    }
+
     // Utility variable tests
 
     #[test]
@@ -536,10 +1174,258 @@ mod tests {
     #[test]
     fn test_season_calculation() {
         // Directly test the utility generation logic for season
-        let utilities = generate_utility_variables();
+        let utilities = generate_utility_variables(&DateSettings::default());
         let season = utilities.get("season").unwrap().as_str().unwrap();
 
         // Should be one of the four seasons
         assert!(["Spring", "Summer", "Fall", "Winter"].contains(&season));
     }
+
+    /// 2024-03-10T03:30:00Z, still Saturday the 9th in Mexico City
+    fn fixed_instant() -> DateTime<Utc> {
+        "2024-03-10T03:30:00Z".parse().unwrap()
+    }
+
+    fn date_frontmatter(properties: serde_json::Value) -> Frontmatter {
+        serde_json::from_value(properties).unwrap()
+    }
+
+    #[test]
+    fn test_date_variables_in_timezone() {
+        let fm = date_frontmatter(serde_json::json!({ "timezone": "America/Mexico_City" }));
+        let vars = utility_variables_at(fixed_instant(), &DateSettings::from_frontmatter(&fm).unwrap());
+
+        assert_eq!(vars["today"], "2024-03-09");
+        assert_eq!(vars["day_of_week"], "Saturday");
+        assert_eq!(vars["timezone"], "America/Mexico_City");
+        assert_eq!(vars["now_local"], "2024-03-09T21:30:00-06:00");
+        assert_eq!(vars["now"], "2024-03-10T03:30:00Z");
+
+        let fm = date_frontmatter(serde_json::json!({ "timezone": "Asia/Tokyo" }));
+        let vars = utility_variables_at(fixed_instant(), &DateSettings::from_frontmatter(&fm).unwrap());
+
+        assert_eq!(vars["today"], "2024-03-10");
+        assert_eq!(vars["day_of_week"], "Sunday");
+        assert_eq!(vars["day_of_week_abbr"], "Sun");
+    }
+
+    #[test]
+    fn test_date_variables_locale_and_hemisphere() {
+        let fm = date_frontmatter(serde_json::json!({
+            "timezone": "America/Mexico_City",
+            "locale": "es-MX",
+            "hemisphere": "southern"
+        }));
+        let vars = utility_variables_at(fixed_instant(), &DateSettings::from_frontmatter(&fm).unwrap());
+
+        assert_eq!(vars["month"], "marzo");
+        assert_eq!(vars["month_abbr"], "mar");
+        assert_eq!(vars["day_of_week"], "sábado");
+        assert_eq!(vars["season"], "otoño");
+
+        // Defaults: Northern Hemisphere, English
+        let vars = utility_variables_at(fixed_instant(), &DateSettings::default());
+        assert_eq!(vars["month"], "March");
+        assert_eq!(vars["season"], "Spring");
+    }
+
+    #[test]
+    fn test_date_names_override_locale() {
+        let fm = date_frontmatter(serde_json::json!({
+            "timezone": "UTC",
+            "locale": "de",
+            "date_names": { "days_abbr": ["L", "M", "X", "J", "V", "S", "D"] }
+        }));
+        let vars = utility_variables_at(fixed_instant(), &DateSettings::from_frontmatter(&fm).unwrap());
+
+        assert_eq!(vars["day_of_week"], "Sonntag");
+        assert_eq!(vars["day_of_week_abbr"], "D");
+    }
+
+    #[test]
+    fn test_invalid_date_settings() {
+        for properties in [
+            serde_json::json!({ "timezone": "Mars/Olympus_Mons" }),
+            serde_json::json!({ "hemisphere": "eastern" }),
+            serde_json::json!({ "locale": "tlh" }),
+            serde_json::json!({ "date_names": { "seasons": ["wet", "dry"] } }),
+        ] {
+            let fm = date_frontmatter(properties.clone());
+            assert!(
+                matches!(process_interpolation("{{today}}", &fm), Err(RenderError::InvalidDateSettings(_))),
+                "{}",
+                properties
+            );
+        }
+    }
+
+    #[test]
+    fn test_undefined_variables_are_reported_once() {
+        let mut fm = Frontmatter::default();
+        fm.custom.insert("title".to_string(), serde_json::json!("Guide"));
+        let nodes = vec![
+            DarkMatterNode::Text("{{title}} by {{author}}".to_string()),
+            DarkMatterNode::Markdown(crate::types::MarkdownContent {
+                raw: "{{author}} and {{editor}}, not `{{code}}`".to_string(),
+                frontmatter: None,
+            }),
+        ];
+
+        let (nodes, warnings) = process_nodes_interpolation_with_warnings(&nodes, &fm, &[]).unwrap();
+
+        assert!(matches!(&nodes[0], DarkMatterNode::Text(text) if text == "Guide by {{author}}"));
+        let messages: Vec<_> = warnings.iter().map(|warning| warning.message.as_str()).collect();
+        assert_eq!(messages, ["Undefined variable {{author}}", "Undefined variable {{editor}}"]);
+        assert!(warnings.iter().all(|warning| warning.code == ParseWarningCode::UndefinedVariable));
+    }
+
+    /// Serializes the tests that change the environment, since it mustn't be
+    /// changed while another thread reads it
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// Run `f` with the environment variable `name` set to `value`, or unset,
+    /// removing it again afterwards
+    fn with_env_var<T>(name: &str, value: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        let result = f();
+        unsafe {
+            std::env::remove_var(name);
+        }
+        result
+    }
+
+    #[test]
+    fn test_allowed_env_var_is_interpolated() {
+        let value = "https://api.example.com/?a=1&b=<2>";
+        with_env_var("COMPOSITION_TEST_API_BASE_URL", Some(value), || {
+            let allowed = ["COMPOSITION_TEST_API_BASE_URL".to_string()];
+            let content = "Base: {{env.COMPOSITION_TEST_API_BASE_URL}}";
+
+            let text = process_interpolation_with_env(content, &Frontmatter::default(), &allowed).unwrap();
+            assert_eq!(text, format!("Base: {}", value));
+
+            // Markdown may hold raw HTML, so the value is escaped there; text
+            // is escaped when it is rendered, so it is substituted as it is
+            let nodes = vec![
+                DarkMatterNode::Text(content.to_string()),
+                DarkMatterNode::Markdown(crate::types::MarkdownContent { raw: content.to_string(), frontmatter: None }),
+            ];
+            let (nodes, warnings) =
+                process_nodes_interpolation_with_warnings(&nodes, &Frontmatter::default(), &allowed).unwrap();
+            assert!(matches!(&nodes[0], DarkMatterNode::Text(text) if *text == format!("Base: {}", value)), "{:?}", nodes);
+            assert!(
+                matches!(&nodes[1], DarkMatterNode::Markdown(markdown)
+                    if markdown.raw == "Base: https://api.example.com/?a=1&amp;b=&lt;2&gt;"),
+                "{:?}",
+                nodes
+            );
+            assert!(warnings.is_empty());
+
+            // Either way the value is escaped exactly once in the HTML
+            let html = crate::render::to_html(&nodes).unwrap();
+            assert_eq!(html.matches("?a=1&amp;b=&lt;2&gt;").count(), 2, "{}", html);
+        });
+    }
+
+    #[test]
+    fn test_env_var_outside_allowlist_is_blocked() {
+        with_env_var("COMPOSITION_TEST_SECRET", Some("hunter2"), || {
+            let nodes = vec![DarkMatterNode::Text("Key: {{env.COMPOSITION_TEST_SECRET}}".to_string())];
+
+            let (nodes, warnings) =
+                process_nodes_interpolation_with_warnings(&nodes, &Frontmatter::default(), &["OTHER".to_string()]).unwrap();
+
+            assert!(matches!(&nodes[0], DarkMatterNode::Text(text) if text == "Key: {{env.COMPOSITION_TEST_SECRET}}"));
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].code, ParseWarningCode::BlockedEnvVar);
+            assert!(warnings[0].message.contains("COMPOSITION_TEST_SECRET isn't in allowed_env_vars"));
+            // Without an allowlist nothing is read
+            let text = process_interpolation("{{env.COMPOSITION_TEST_SECRET}}", &Frontmatter::default()).unwrap();
+            assert_eq!(text, "{{env.COMPOSITION_TEST_SECRET}}");
+        });
+    }
+
+    #[test]
+    fn test_env_table_shows_only_allowed_values() {
+        with_env_var("COMPOSITION_TEST_TABLE_SECRET", Some("hunter2"), || {
+            let nodes = vec![
+                DarkMatterNode::EnvTable {
+                    filter: Some("COMPOSITION_TEST_TABLE_".to_string()),
+                    sort: true,
+                    show_values: true,
+                },
+                DarkMatterNode::RequireEnv { vars: vec!["COMPOSITION_TEST_TABLE_UNSET".to_string()] },
+            ];
+            let render = |allowed: &[String]| {
+                let (nodes, _) = process_nodes_interpolation_with_warnings(&nodes, &Frontmatter::default(), allowed).unwrap();
+                crate::render::to_html(&nodes).unwrap()
+            };
+
+            let html = render(&["OTHER".to_string()]);
+            assert!(!html.contains("hunter2"), "{}", html);
+            assert!(html.contains("<td>COMPOSITION_TEST_TABLE_SECRET</td>\n      <td>[SET]</td>"), "{}", html);
+            assert!(html.contains("<td>COMPOSITION_TEST_TABLE_UNSET</td>\n      <td>[NOT SET]</td>\n      <td>unset</td>"), "{}", html);
+            assert!(html.contains("<tr class=\"composition-env-unset\">\n      <td>COMPOSITION_TEST_TABLE_UNSET</td>"), "{}", html);
+            assert_eq!(html.matches(r#"<style id="dm-env-table">"#).count(), 1, "{}", html);
+
+            let html = render(&["COMPOSITION_TEST_TABLE_SECRET".to_string()]);
+            assert!(html.contains("<td>hunter2</td>"), "{}", html);
+        });
+    }
+
+    #[test]
+    fn test_unset_allowed_env_var_is_undefined() {
+        with_env_var("COMPOSITION_TEST_UNSET", None, || {
+            let nodes = vec![DarkMatterNode::Text("{{env.COMPOSITION_TEST_UNSET}}".to_string())];
+
+            let (nodes, warnings) = process_nodes_interpolation_with_warnings(
+                &nodes,
+                &Frontmatter::default(),
+                &["COMPOSITION_TEST_UNSET".to_string()],
+            )
+            .unwrap();
+
+            assert!(matches!(&nodes[0], DarkMatterNode::Text(text) if text == "{{env.COMPOSITION_TEST_UNSET}}"));
+            let messages: Vec<_> = warnings.iter().map(|warning| warning.message.as_str()).collect();
+            assert_eq!(messages, ["Undefined variable {{env.COMPOSITION_TEST_UNSET}}"]);
+            assert_eq!(warnings[0].code, ParseWarningCode::UndefinedVariable);
+        });
+    }
+
+    #[test]
+    fn test_interpolation_dotted_paths() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.custom.insert(
+            "versions".to_string(),
+            serde_json::json!({"latest": "2.1", "history": ["2.0", "1.9"], "nested": {"lts": true}}),
+        );
+
+        let result = process_interpolation(
+            "{{versions.latest}}, {{versions.history.1}}, {{versions.nested.lts}}, {{versions.missing}}, {{versions.history.x}}",
+            &frontmatter,
+        )
+        .unwrap();
+
+        assert_eq!(result, "2.1, 1.9, true, {{versions.missing}}, {{versions.history.x}}");
+    }
+
+    #[test]
+    fn test_interpolate_frontmatter_values_one_level() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.custom.insert("latest".to_string(), serde_json::json!("2.1"));
+        frontmatter.custom.insert("labels".to_string(), serde_json::json!(["v{{latest}}", 3]));
+        frontmatter.custom.insert("upstream".to_string(), serde_json::json!("{{deps.core}} {{env.HOME}}"));
+        frontmatter.replace = Some(HashMap::from([("v".to_string(), "version ".to_string())]));
+
+        let values = interpolate_frontmatter_values(&frontmatter).unwrap();
+
+        assert_eq!(values["labels"], serde_json::json!(["v2.1", 3]));
+        assert_eq!(values["upstream"], serde_json::json!("{{deps.core}} {{env.HOME}}"));
+    }
 }
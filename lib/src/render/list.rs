@@ -0,0 +1,271 @@
+//! Data list rendering
+//!
+//! `::list` reads a CSV file, whose first row names its columns, or a JSON
+//! array, of objects or of plain values, and lists the chosen columns of
+//! each row. Task lists take their last listed column as whether the item is
+//! done; the other columns make up the item's text, joined with ", ".
+
+use crate::error::RenderError;
+use crate::types::ListItem;
+
+/// Values a task list's done column counts as checked, compared without case
+const CHECKED_VALUES: &[&str] = &["true", "yes", "y", "x", "1", "done"];
+
+/// A data file's rows and the names of their columns
+///
+/// JSON arrays of plain values have a single, unnamed column.
+struct DataRows {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// Read the list items from a data file's `content`
+///
+/// `json` selects JSON over CSV. `columns` are looked up by name; without
+/// them the first column is listed, and a task list checks its items by the
+/// second. A data file with no rows gives no items, whatever the columns.
+pub(super) fn read_list_items(
+    content: &str,
+    json: bool,
+    columns: &[String],
+    task_list: bool,
+    label: &str,
+) -> Result<Vec<ListItem>, RenderError> {
+    let data = if json {
+        read_json_rows(content, columns, label)?
+    } else {
+        read_csv_rows(content)?
+    };
+    if data.rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let indices = if columns.is_empty() {
+        (0..data.rows[0].len().min(if task_list { 2 } else { 1 })).collect::<Vec<_>>()
+    } else {
+        columns
+            .iter()
+            .map(|column| {
+                data.headers.iter().position(|header| header == column).ok_or_else(|| {
+                    RenderError::ListError(format!(
+                        "Column '{}' not found in {}; its columns are {}",
+                        column,
+                        label,
+                        data.headers.join(", ")
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?
+    };
+    if task_list && indices.len() < 2 {
+        return Err(RenderError::ListError(format!(
+            "A task list needs an item column and a done column, but {} has one column",
+            label
+        )));
+    }
+
+    let cell = |row: &[String], index: usize| row.get(index).map_or("", |value| value.trim()).to_string();
+    Ok(data
+        .rows
+        .iter()
+        .map(|row| {
+            let (text_columns, checked) = match indices.split_last() {
+                Some((done, text_columns)) if task_list => {
+                    let done = cell(row, *done);
+                    (text_columns, CHECKED_VALUES.iter().any(|value| done.eq_ignore_ascii_case(value)))
+                }
+                _ => (indices.as_slice(), false),
+            };
+            let text = text_columns.iter().map(|index| cell(row, *index)).collect::<Vec<_>>().join(", ");
+            ListItem { text, checked }
+        })
+        .collect())
+}
+
+/// Read a CSV file whose first row names its columns
+fn read_csv_rows(content: &str) -> Result<DataRows, RenderError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let mut records = reader.records();
+    let headers = match records.next() {
+        Some(record) => record.map_err(|e| RenderError::CsvError(e.to_string()))?.iter().map(str::to_string).collect(),
+        None => Vec::new(),
+    };
+    let rows = records
+        .map(|record| {
+            record
+                .map(|record| record.iter().map(str::to_string).collect())
+                .map_err(|e| RenderError::CsvError(e.to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(DataRows { headers, rows })
+}
+
+/// Read a JSON array of objects or of plain values
+///
+/// JSON objects don't keep their keys in order, so lists of objects must
+/// name their columns; the rows hold just those columns.
+fn read_json_rows(content: &str, columns: &[String], label: &str) -> Result<DataRows, RenderError> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(content)
+        .map_err(|e| RenderError::ListError(format!("{} isn't a JSON array: {}", label, e)))?;
+
+    if !items.iter().any(serde_json::Value::is_object) {
+        let rows = items.iter().map(|item| vec![json_text(item)]).collect();
+        return Ok(DataRows { headers: Vec::new(), rows });
+    }
+    if columns.is_empty() {
+        return Err(RenderError::ListError(format!(
+            "{} holds JSON objects; name the columns to list with --column or --columns",
+            label
+        )));
+    }
+
+    let rows = items
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| item.get(column).map(json_text).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    Ok(DataRows { headers: columns.to_vec(), rows })
+}
+
+/// A JSON value as list text; strings lose their quotes and null is empty
+fn json_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Render a data list's items as an HTML list
+///
+/// Task items carry GitHub's task list classes and a disabled checkbox, as
+/// GFM task lists in markdown do.
+pub fn render_data_list(items: &[ListItem], ordered: bool, task_list: bool) -> String {
+    let tag = if ordered { "ol" } else { "ul" };
+    let class = if task_list { "data-list contains-task-list" } else { "data-list" };
+
+    let mut html = format!("<{} class=\"{}\">\n", tag, class);
+    for item in items {
+        if task_list {
+            html.push_str(&format!(
+                "  <li class=\"task-list-item\"><input type=\"checkbox\" disabled{}> {}</li>\n",
+                if item.checked { " checked" } else { "" },
+                escape_html(&item.text)
+            ));
+        } else {
+            html.push_str(&format!("  <li>{}</li>\n", escape_html(&item.text)));
+        }
+    }
+    html.push_str(&format!("</{}>\n", tag));
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_read_csv_column() {
+        let csv = "id,name\n1,Alpha\n2,\"Beta, Gamma\"\n";
+
+        let items = read_list_items(csv, false, &columns(&["name"]), false, "items.csv").unwrap();
+
+        let texts: Vec<_> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, ["Alpha", "Beta, Gamma"]);
+        assert!(items.iter().all(|item| !item.checked));
+    }
+
+    #[test]
+    fn test_read_csv_task_list() {
+        let csv = "title,owner,done\nWrite docs,Ana,yes\nShip,Bo,false\nTest,Cy,TRUE\n";
+
+        let items = read_list_items(csv, false, &columns(&["title", "owner", "done"]), true, "tasks.csv").unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ListItem { text: "Write docs, Ana".to_string(), checked: true },
+                ListItem { text: "Ship, Bo".to_string(), checked: false },
+                ListItem { text: "Test, Cy".to_string(), checked: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_json_items() {
+        let values = read_list_items(r#"["a", 2, null]"#, true, &[], false, "values.json").unwrap();
+        let texts: Vec<_> = values.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, ["a", "2", ""]);
+
+        let tasks = r#"[{"title": "Plan", "done": true}, {"title": "Build"}]"#;
+        let items = read_list_items(tasks, true, &columns(&["title", "done"]), true, "tasks.json").unwrap();
+        assert_eq!(
+            items,
+            [
+                ListItem { text: "Plan".to_string(), checked: true },
+                ListItem { text: "Build".to_string(), checked: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_list_items_errors() {
+        let missing = read_list_items("name\nA\n", false, &columns(&["title"]), false, "items.csv").unwrap_err();
+        assert!(missing.to_string().contains("Column 'title' not found in items.csv; its columns are name"));
+
+        let one_column = read_list_items("title\nA\n", false, &[], true, "tasks.csv").unwrap_err();
+        assert!(matches!(one_column, RenderError::ListError(_)));
+
+        let unnamed = read_list_items(r#"[{"title": "A"}]"#, true, &[], false, "tasks.json").unwrap_err();
+        assert!(unnamed.to_string().contains("name the columns"));
+    }
+
+    #[test]
+    fn test_empty_data_has_no_items() {
+        for (content, json) in [("", false), ("name\n", false), ("[]", true)] {
+            let items = read_list_items(content, json, &columns(&["name"]), false, "items").unwrap();
+            assert!(items.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_render_data_list() {
+        let items = [
+            ListItem { text: "<b>".to_string(), checked: true },
+            ListItem { text: "Ship".to_string(), checked: false },
+        ];
+
+        assert_eq!(
+            render_data_list(&items, true, false),
+            "<ol class=\"data-list\">\n  <li>&lt;b&gt;</li>\n  <li>Ship</li>\n</ol>\n"
+        );
+        assert_eq!(
+            render_data_list(&items, false, true),
+            "<ul class=\"data-list contains-task-list\">\n  \
+             <li class=\"task-list-item\"><input type=\"checkbox\" disabled checked> &lt;b&gt;</li>\n  \
+             <li class=\"task-list-item\"><input type=\"checkbox\" disabled> Ship</li>\n</ul>\n"
+        );
+        assert_eq!(render_data_list(&[], false, false), "<ul class=\"data-list\">\n</ul>\n");
+    }
+}
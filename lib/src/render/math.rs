@@ -0,0 +1,56 @@
+//! Math rendering
+//!
+//! Formulas are typeset in the browser by KaTeX: each one becomes a
+//! `<span class="math">` holding its TeX source, the same markup pulldown-cmark
+//! gives `$...$` in markdown, and KaTeX's stylesheet and module script, added
+//! once per document by the orchestration layer, render every such span on
+//! load.
+
+/// KaTeX release loaded by [`math_js`] and [`math_stylesheet`]
+const KATEX_DIST: &str = "https://cdn.jsdelivr.net/npm/katex@0.16/dist";
+
+/// Render a TeX formula for client-side typesetting
+///
+/// Display math is set on its own line; inline math flows with the text.
+pub fn render_math(source: &str, display: bool) -> String {
+    let mode = if display { "math-display" } else { "math-inline" };
+    format!(r#"<span class="math {}">{}</span>"#, mode, escape_html(source))
+}
+
+/// Returns the URL of KaTeX's stylesheet (called by orchestration layer)
+pub fn math_stylesheet() -> String {
+    format!("{}/katex.min.css", KATEX_DIST)
+}
+
+/// Returns the module script that typesets the formulas (called by orchestration layer)
+pub fn math_js() -> String {
+    format!(
+        "import katex from \"{}/katex.mjs\";\n\
+         for (const el of document.querySelectorAll(\"span.math\")) {{\n  \
+         katex.render(el.textContent, el, {{ displayMode: el.classList.contains(\"math-display\"), throwOnError: false }});\n\
+         }}",
+        KATEX_DIST
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_math_modes() {
+        assert_eq!(render_math("a < b", false), r#"<span class="math math-inline">a &lt; b</span>"#);
+        assert_eq!(
+            render_math(r"\sum_{i=1}^n i", true),
+            r#"<span class="math math-display">\sum_{i=1}^n i</span>"#
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Mermaid diagram rendering
+//!
+//! Diagrams are drawn in the browser: each one becomes a `<pre class="mermaid">`
+//! holding its source, and Mermaid's module script, added once per document by
+//! the orchestration layer, renders them on load.
+
+/// Mermaid release loaded by [`mermaid_js`]
+const MERMAID_MODULE: &str = "https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.esm.min.mjs";
+
+/// Render a Mermaid diagram's source for client-side rendering
+pub fn render_mermaid(source: &str) -> String {
+    format!(r#"<pre class="mermaid">{}</pre>"#, escape_html(source))
+}
+
+/// Returns the module script that renders the diagrams (called by orchestration layer)
+pub fn mermaid_js() -> String {
+    format!(
+        "import mermaid from \"{}\";\nmermaid.initialize({{ startOnLoad: true }});",
+        MERMAID_MODULE
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_mermaid_escapes_source() {
+        let html = render_mermaid("graph TD\n  A-->B[\"<b>\"]");
+
+        assert_eq!(html, "<pre class=\"mermaid\">graph TD\n  A--&gt;B[&quot;&lt;b&gt;&quot;]</pre>");
+    }
+}
@@ -1,23 +1,63 @@
+mod assets;
 mod transclusion;
 mod interpolation;
 mod table;
+mod list;
 mod html;
 mod orchestrator;
+mod passes;
+mod output;
 mod charts;
 mod popover;
 mod disclosure;
 mod columns;
+mod aspect_ratio;
+mod footnotes;
+mod mermaid;
+mod diff;
+mod math;
+mod terminal;
+mod transform;
+mod sanitize;
 mod audio;
+mod theme;
+mod section;
+mod feed;
+mod source;
+mod structured_data;
 pub mod youtube;
 
 pub use transclusion::resolve_transclusion;
-pub use interpolation::{process_interpolation, process_nodes_interpolation};
-pub use table::render_table;
-pub use html::to_html;
-pub use orchestrator::execute_workplan;
-pub use charts::{render_bar_chart, render_line_chart, render_pie_chart, render_area_chart, render_bubble_chart};
+pub use interpolation::{process_interpolation, process_interpolation_with_env, process_nodes_interpolation};
+pub use table::{render_table, render_env_table};
+pub use list::render_data_list;
+pub use html::{to_html, to_sanitized_html};
+pub use sanitize::sanitize_html;
+pub use orchestrator::{
+    execute_workplan, execute_workplan_with_metrics, rendered_cache_key, RenderSettings, RetryPolicy,
+};
+pub(crate) use orchestrator::render_parsed_document;
+pub(crate) use section::{extract_section, markdown_headings};
+pub(crate) use source::write_markdown;
+pub(crate) use structured_data::{structured_data, PageImage};
+pub use passes::{
+    ExcludedFile, ExclusionReason, FallbackUsed, LanguageVariant, PassReport, RenderMetrics, RenderPass, RenderWarnings, TaskFailure,
+};
+pub use transform::NodeTransformer;
+pub use output::{AssetKind, AssetRef, Compression, OutputPaths};
+pub(crate) use output::{excerpt, linked_assets, prose_words};
+pub use feed::{FeedFormat, FeedOptions};
+pub(crate) use feed::{absolutize_urls, atom_feed, feed_date, rss_feed, FeedEntry};
+pub use charts::{render_bar_chart, render_line_chart, render_pie_chart, render_pie_chart_with_options, render_area_chart, render_bubble_chart, render_chart_table};
 pub use popover::{render_popover, render_inline_popover, generate_popover_styles, generate_popover_script};
 pub use disclosure::{render_disclosure, render_disclosure_open, generate_disclosure_styles};
 pub use columns::{render_columns, generate_columns_styles};
-pub use audio::process_audio_nodes;
-pub use youtube::{render_youtube_embed, youtube_css, youtube_js};
+pub use aspect_ratio::render_aspect_ratio;
+pub use footnotes::render_footnote_ref;
+pub use mermaid::{render_mermaid, mermaid_js};
+pub use diff::{render_diff, diff_css};
+pub use math::{render_math, math_js, math_stylesheet};
+pub use terminal::{render_terminal, terminal_css, clipboard_js};
+pub use audio::{process_audio_nodes, process_audio_nodes_with_warnings};
+pub use youtube::{render_youtube_embed, render_youtube_embed_with_captions, youtube_css, youtube_js};
+pub use theme::{component_theme_css, THEME_VARIABLES};
@@ -1,28 +1,176 @@
-use crate::cache::CacheOperations;
-use crate::error::RenderError;
-use crate::parse::parse_document;
-use crate::types::{Document, Frontmatter, Resource, WorkPlan};
-use std::sync::Arc;
-use tracing::{info, instrument, span, Level};
+//! Rendering the documents of a work plan
+//!
+//! Layers are rendered one after another. The documents of a parallel layer
+//! are each rendered in a tokio task; with a `max_parallelism`, the tasks
+//! wait for a [`Semaphore`] permit before rendering. Documents are dispatched
+//! in priority order, taken from a [`BinaryHeap`], and since waiters get
+//! permits in the order they asked, the limit keeps that order.
+
+use crate::ai::AsyncAIQueue;
+use crate::audio::AudioBatchOptions;
+use crate::cache::{CacheOperations, DocumentCacheEntry, RenderedDocumentEntry};
+use crate::error::{ParseError, ParseWarning, RenderError};
+use crate::graph::utils::{compute_json_hash, decode_text};
+use crate::graph::{
+    compute_content_hash, compute_resource_hash, document_entry, git_location, load_resource_candidate, FileStat,
+};
+use crate::parse::{parse_document, ParseOptions};
+use crate::types::{Document, Frontmatter, Resource, ResourceHash, WorkLayer, WorkPlan};
+use futures::FutureExt;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::{info, info_span, instrument, span, warn, Instrument, Level};
+
+use super::passes::{
+    run_pass, FallbackUsed, LanguageVariant, PassContext, PassReport, RenderMetrics, RenderPass, TaskFailure,
+};
+use super::transform::{apply_transformers, NodeTransformer};
+
+/// How often a document's render is retried after a transient failure
+///
+/// Only errors [`RenderError::is_transient`] accepts are retried: failed
+/// remote fetches, rate limits and timeouts. The wait before each retry
+/// doubles, starting at `backoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per document, the first one included; 0 counts as 1
+    pub attempts: u32,
+    /// Wait before the first retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 3, backoff: Duration::from_millis(250) }
+    }
+}
 
-use super::interpolation::process_nodes_interpolation;
-use super::transclusion::resolve_transclusion;
+impl RetryPolicy {
+    /// Render each document once
+    pub fn none() -> Self {
+        Self { attempts: 1, backoff: Duration::ZERO }
+    }
+
+    /// The wait after failed attempt number `attempt`, counting from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// How the documents of a work plan are rendered, besides the passes,
+/// AI queue and transformers they go through
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// Documents of a layer rendered at once (0 counts as 1), as the permits
+    /// of a semaphore their tasks wait on; unlimited when `None`
+    pub max_parallelism: Option<usize>,
+    pub parse_options: ParseOptions,
+    /// Environment variables interpolation may read as `{{env.NAME}}`
+    pub allowed_env_vars: Vec<String>,
+    /// Largest remote response a document, transclusion, import or chart may load
+    pub max_remote_bytes: u64,
+    /// Language the documents are rendered in
+    pub language: Option<String>,
+    pub retry: RetryPolicy,
+    /// Return the documents that rendered when others fail, rather than
+    /// failing the whole work plan
+    pub partial_results: bool,
+    /// Where the [`Audio`](RenderPass::Audio) pass copies `::audio` files,
+    /// whether it inlines them instead, and their size limits
+    pub audio: AudioBatchOptions,
+    /// Root that local documents and audio are hashed relative to (see
+    /// [`compute_resource_hash`]); the graph the work plan came from should
+    /// have been built with the same root
+    pub project_root: Option<PathBuf>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            max_parallelism: None,
+            parse_options: ParseOptions::default(),
+            allowed_env_vars: Vec::new(),
+            max_remote_bytes: crate::http::DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            retry: RetryPolicy::default(),
+            partial_results: false,
+            audio: AudioBatchOptions::default(),
+            project_root: None,
+        }
+    }
+}
 
 /// Orchestrate the rendering of documents according to a work plan
 ///
 /// This function:
 /// 1. Processes work plan layers in order
-/// 2. Parallelizes independent resources within each layer using rayon
-/// 3. Resolves transclusions recursively
-/// 4. Applies frontmatter interpolation
-/// 5. Reports progress via tracing
-#[instrument(skip(plan, frontmatter, cache))]
+/// 2. Renders the resources of a parallel layer in tokio tasks, spawned in
+///    priority order; with a `max_parallelism`, the tasks share a semaphore
+///    and at most that many of a layer's documents render at once, highest
+///    priority first. Without one, every document of a parallel layer starts
+///    at once and priority has no effect
+/// 3. Runs each document through the given render passes, in order, sending AI
+///    operations to `ai_queue` when one is given and letting interpolation read
+///    only the `allowed_env_vars` from the environment, and then through
+///    `transformers`; remote documents and transclusions whose response is
+///    over `max_remote_bytes` or isn't text fail to load. With a `language`,
+///    documents are rendered in it: `{{language}}` is set to it and `::file`
+///    transclusions load their translations where they exist
+/// 4. Reports progress via tracing, including every warning; with
+///    `parse_options.strict` the first warning fails the render instead
+/// 5. Retries documents that fail transiently, as the `retry` policy allows,
+///    and turns a panic while rendering a document into that document's failure
+/// 6. Records every rendered document and its content hash in the cache, in one batch;
+///    output rendered in a language or with other frontmatter is stored apart
+///    (see [`rendered_cache_key`])
+///
+/// A failed document doesn't stop the others: every document in the plan
+/// is rendered, and the failures are then reported together as
+/// [`RenderError::TasksFailed`], or as the document's own error when the plan
+/// has only one. With `partial_results` the documents that
+/// rendered are returned instead, and the failures are only logged.
+#[instrument(skip(plan, frontmatter, cache, ai_queue, transformers))]
 pub async fn execute_workplan(
     plan: &WorkPlan,
     frontmatter: &Frontmatter,
     cache: &Arc<CacheOperations>,
+    passes: &[RenderPass],
+    ai_queue: Option<&Arc<AsyncAIQueue>>,
+    transformers: &[Arc<dyn NodeTransformer>],
+    settings: &RenderSettings,
 ) -> Result<Vec<Document>, RenderError> {
+    let (documents, _metrics) =
+        execute_workplan_with_metrics(plan, frontmatter, cache, passes, ai_queue, transformers, settings).await?;
+    Ok(documents)
+}
+
+/// Execute a work plan and report how long each render pass took
+///
+/// Documents are scheduled as in [`execute_workplan`]: tokio tasks bounded
+/// by a semaphore, dispatched by priority. Pass timings are summed over every rendered document, and the warnings
+/// of each document are collected in [`RenderMetrics::parse_warnings`].
+/// With `partial_results`, the documents that failed are listed in
+/// [`RenderMetrics::failures`].
+#[instrument(skip(plan, frontmatter, cache, ai_queue, transformers))]
+pub async fn execute_workplan_with_metrics(
+    plan: &WorkPlan,
+    frontmatter: &Frontmatter,
+    cache: &Arc<CacheOperations>,
+    passes: &[RenderPass],
+    ai_queue: Option<&Arc<AsyncAIQueue>>,
+    transformers: &[Arc<dyn NodeTransformer>],
+    settings: &RenderSettings,
+) -> Result<(Vec<Document>, RenderMetrics), RenderError> {
+    let started = Instant::now();
     let mut results = Vec::new();
+    let mut failures = Vec::new();
+    let mut metrics = RenderMetrics::default();
+    let mut cache_entries = CacheEntries::default();
     let total_layers = plan.layers.len();
 
     info!(
@@ -43,128 +191,404 @@ pub async fn execute_workplan(
         );
 
         if layer.parallelizable && layer.resources.len() > 1 {
-            // Use tokio for parallel processing with join_all
+            // Use tokio for parallel processing with join_all. Waiters get
+            // permits in the order they were spawned, so the limit keeps the
+            // dispatch order.
             let mut tasks = Vec::new();
+            let limit = settings.max_parallelism.map(|max| Arc::new(Semaphore::new(max.max(1))));
 
-            for resource in &layer.resources {
+            for resource in dispatch_order(plan, layer) {
                 let fm = frontmatter.clone();
                 let cache_ref = Arc::clone(cache);
                 let resource = resource.clone();
+                let passes = passes.to_vec();
+                let queue = ai_queue.cloned();
+                let transformers = transformers.to_vec();
+                let limit = limit.clone();
+                let settings = settings.clone();
 
+                let task_resource = resource.clone();
                 let task = tokio::spawn(async move {
-                    render_document(&resource, &fm, &cache_ref).await
+                    let _permit = match limit {
+                        Some(limit) => Some(limit.acquire_owned().await.map_err(|e| TaskFailure {
+                            resource: resource.clone(),
+                            error: RenderError::WorkPlanFailed(format!("Parallelism limit closed: {}", e)),
+                            attempts: 0,
+                        })?),
+                        None => None,
+                    };
+                    render_task(&resource, &fm, &cache_ref, &passes, queue.as_deref(), &transformers, &settings).await
                 });
 
-                tasks.push(task);
+                tasks.push((task_resource, task));
             }
 
-            // Wait for all tasks to complete
-            let layer_results = futures::future::join_all(tasks).await;
-
-            // Collect results and handle errors
-            for result in layer_results {
-                let doc = result
-                    .map_err(|e| RenderError::HtmlGenerationFailed(format!("Task join error: {}", e)))??;
-                results.push(doc);
+            // Wait for every task, whether or not the others failed
+            let (resources, handles): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+            let layer_results = futures::future::join_all(handles).await;
+
+            for (resource, result) in resources.into_iter().zip(layer_results) {
+                match result {
+                    Ok(Ok(rendered)) => record(rendered, settings, frontmatter, &mut results, &mut metrics, &mut cache_entries),
+                    Ok(Err(failure)) => failures.push(failure),
+                    Err(e) => failures.push(TaskFailure {
+                        resource,
+                        error: RenderError::WorkPlanFailed(format!("Task join error: {}", e)),
+                        attempts: 1,
+                    }),
+                }
             }
         } else {
             // Process sequentially
-            for resource in &layer.resources {
-                let doc = render_document(resource, frontmatter, cache).await?;
-                results.push(doc);
+            for resource in dispatch_order(plan, layer) {
+                let rendered =
+                    render_task(resource, frontmatter, cache, passes, ai_queue.map(Arc::as_ref), transformers, settings)
+                        .await;
+                match rendered {
+                    Ok(rendered) => record(rendered, settings, frontmatter, &mut results, &mut metrics, &mut cache_entries),
+                    Err(failure) => failures.push(failure),
+                }
             }
         }
 
         info!("Completed layer {}/{}", layer_idx + 1, total_layers);
     }
 
+    // The documents are already rendered, so a failed cache write shouldn't lose them
+    if let Err(e) = cache.upsert_documents(cache_entries.documents).await {
+        warn!("Failed to record rendered documents in the cache: {}", e);
+    }
+    if let Err(e) = cache.upsert_rendered_documents(cache_entries.rendered).await {
+        warn!("Failed to store rendered output in the cache: {}", e);
+    }
+
+    metrics.total = started.elapsed();
     info!("Work plan execution complete. Rendered {} documents", results.len());
-    Ok(results)
+    for failure in &failures {
+        warn!("{}", failure);
+    }
+    if !failures.is_empty() && !settings.partial_results {
+        // With a single document there is nothing to collect
+        if plan.total_tasks == 1 {
+            if let Some(failure) = failures.pop() {
+                return Err(failure.error);
+            }
+        }
+        return Err(RenderError::TasksFailed(failures));
+    }
+    metrics.failures = failures;
+    Ok((results, metrics))
+}
+
+/// Render a document, retrying transient failures as the settings' `retry`
+/// policy allows
+///
+/// A panic while rendering is caught and reported as the document's
+/// failure, without a retry, so it can't take the other documents down.
+async fn render_task(
+    resource: &Resource,
+    frontmatter: &Frontmatter,
+    cache: &CacheOperations,
+    passes: &[RenderPass],
+    ai_queue: Option<&AsyncAIQueue>,
+    transformers: &[Arc<dyn NodeTransformer>],
+    settings: &RenderSettings,
+) -> Result<RenderedDocument, TaskFailure> {
+    let retry = settings.retry;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let render = render_document(resource, frontmatter, cache, passes, ai_queue, transformers, settings);
+        let rendered = AssertUnwindSafe(render)
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|panic| Err(RenderError::TaskPanicked(panic_message(panic.as_ref()))));
+
+        match rendered {
+            Ok(rendered) => return Ok(rendered),
+            Err(e) if e.is_transient() && attempts < retry.attempts => {
+                let delay = retry.delay(attempts);
+                warn!("Retrying {} in {:?} after a transient failure: {}", resource.label(), delay, e);
+                tokio::time::sleep(delay).await;
+            }
+            Err(error) => return Err(TaskFailure { resource: resource.clone(), error, attempts }),
+        }
+    }
+}
+
+/// The message a panic was raised with
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// A layer's resources ordered by priority, highest first
+///
+/// Resources with the same priority keep their order within the layer. The
+/// order only matters where resources wait for one another: in sequential
+/// layers and behind a `max_parallelism` limit.
+fn dispatch_order<'a>(plan: &WorkPlan, layer: &'a WorkLayer) -> Vec<&'a Resource> {
+    let mut queue: BinaryHeap<(u8, Reverse<usize>)> = layer
+        .resources
+        .iter()
+        .enumerate()
+        .map(|(idx, resource)| (plan.priority(&plan.resource_hash(resource)), Reverse(idx)))
+        .collect();
+
+    let mut ordered = Vec::with_capacity(queue.len());
+    while let Some((_, Reverse(idx))) = queue.pop() {
+        ordered.push(&layer.resources[idx]);
+    }
+    ordered
+}
+
+/// Output of rendering a single document
+struct RenderedDocument {
+    document: Document,
+    /// Hash of the document's source content
+    content_hash: String,
+    /// Stat of the source file taken before it was read, for local documents
+    file_stat: Option<FileStat>,
+    /// Time spent in each render pass
+    timings: Vec<(RenderPass, Duration)>,
+    /// Unknown directives, undefined variables, lossily decoded files and
+    /// the other problems that didn't stop the document from rendering
+    parse_warnings: Vec<ParseWarning>,
+    /// Fallback candidates loaded in place of the document or its transclusions
+    fallbacks: Vec<FallbackUsed>,
+    /// The variants the document's transclusions were loaded from
+    language_variants: Vec<LanguageVariant>,
 }
 
 /// Render a single document
 ///
 /// This function:
 /// 1. Loads and parses the document
-/// 2. Resolves all transclusions recursively
-/// 3. Applies frontmatter interpolation
-/// 4. Returns the fully resolved document
-#[instrument(skip(frontmatter, cache))]
+/// 2. Merges the document's frontmatter over the shared frontmatter
+/// 3. Runs the content through each render pass in order, then the transformers
+/// 4. Returns the processed document along with the hash of its source content,
+///    the time spent in each pass and the warnings from parsing and rendering
+#[instrument(skip(frontmatter, cache, ai_queue, transformers, settings))]
 async fn render_document(
     resource: &Resource,
     frontmatter: &Frontmatter,
     cache: &CacheOperations,
-) -> Result<Document, RenderError> {
+    passes: &[RenderPass],
+    ai_queue: Option<&AsyncAIQueue>,
+    transformers: &[Arc<dyn NodeTransformer>],
+    settings: &RenderSettings,
+) -> Result<RenderedDocument, RenderError> {
     info!("Rendering document: {:?}", resource.source);
 
-    // 1. Load and parse the document
-    let content = load_resource_content(resource, cache).await?;
-    let mut doc = parse_document(&content, resource.clone())
-        .map_err(|e| RenderError::ParseError(e.to_string()))?;
+    // 1. Load and parse the document, from the first fallback candidate that
+    //    loads; its relative transclusions resolve against that candidate
+    let (used, (content, warning)) = load_resource_candidate(resource, |candidate| {
+        load_resource_content(candidate, cache, settings.max_remote_bytes)
+    })
+    .await?;
+    // Only the primary's stat can vouch for the cached content later
+    let file_stat = FileStat::of_resource(resource).filter(|_| std::ptr::eq(used, resource));
+    let content_hash = compute_content_hash(&content);
+    // The decoding warning was logged when the file was read
+    if let Some(warning) = warning.as_ref().filter(|_| settings.parse_options.strict) {
+        return Err(ParseError::Strict(warning.clone()).into());
+    }
+    let (doc, mut parse_warnings) = parse_document(&content, used.clone(), settings.parse_options)?;
+    parse_warnings.extend(warning);
+
+    let (mut document, timings, report) =
+        render_parsed_document(doc, frontmatter, cache, passes, ai_queue, transformers, settings).await?;
+    parse_warnings.extend(report.warnings);
+    let mut fallbacks = report.fallbacks;
+    if !std::ptr::eq(used, resource) {
+        fallbacks.insert(0, FallbackUsed { resource: resource.clone(), used: used.clone() });
+    }
+    // The document is cached under its full fallback chain
+    document.resource = resource.clone();
+
+    Ok(RenderedDocument {
+        document,
+        content_hash,
+        file_stat,
+        timings,
+        parse_warnings,
+        fallbacks,
+        language_variants: report.language_variants,
+    })
+}
+
+/// Run an already parsed document through the render passes
+///
+/// Merges the document's frontmatter over the shared frontmatter, and both
+/// over a `language` property holding `language` when one is given, then runs
+/// the content through each render pass in order and then through the
+/// transformers. Returns the processed document, the time spent in each pass
+/// and what the passes reported: the fallback candidates they used and the
+/// warnings they raised, which are logged and, with `parse_options.strict`,
+/// fail the render instead. The settings' `max_parallelism`, `retry` and
+/// `partial_results` are for work plans and aren't used.
+pub(crate) async fn render_parsed_document(
+    mut doc: Document,
+    frontmatter: &Frontmatter,
+    cache: &CacheOperations,
+    passes: &[RenderPass],
+    ai_queue: Option<&AsyncAIQueue>,
+    transformers: &[Arc<dyn NodeTransformer>],
+    settings: &RenderSettings,
+) -> Result<(Document, Vec<(RenderPass, Duration)>, PassReport), RenderError> {
+    let resource = &doc.resource;
+    let language = settings.language.as_deref();
 
     // 2. Merge frontmatter
-    let mut merged_frontmatter = frontmatter.clone();
+    let mut merged_frontmatter = Frontmatter::new();
+    if let Some(language) = language {
+        merged_frontmatter.custom.insert("language".to_string(), language.into());
+    }
+    merged_frontmatter.merge(frontmatter.clone());
     merged_frontmatter.merge(doc.frontmatter.clone());
 
-    // 3. Resolve transclusions recursively
-    let mut resolved_nodes = Vec::new();
-    for node in &doc.content {
-        let resolved = resolve_transclusion(
-            node,
-            &merged_frontmatter,
-            cache,
-            extract_base_path(resource),
-        )
-        .await?;
-        resolved_nodes.extend(resolved);
+    // 3. Run the render passes
+    let report = Mutex::new(PassReport::default());
+    let ctx = PassContext {
+        frontmatter: &merged_frontmatter,
+        cache,
+        base_path: extract_base_path(resource),
+        ai_queue,
+        document_hash: compute_resource_hash(resource, settings.project_root.as_deref()),
+        allowed_env_vars: &settings.allowed_env_vars,
+        max_remote_bytes: settings.max_remote_bytes,
+        language,
+        audio: &settings.audio,
+        project_root: settings.project_root.as_deref(),
+        report: &report,
+    };
+    let mut nodes = std::mem::take(&mut doc.content);
+    let mut timings = Vec::with_capacity(passes.len());
+
+    for &pass in passes {
+        let started = Instant::now();
+        nodes = run_pass(pass, nodes, &ctx)
+            .instrument(info_span!("render_pass", pass = pass.name()))
+            .await?;
+        timings.push((pass, started.elapsed()));
     }
 
-    // 4. Apply frontmatter interpolation
-    let interpolated_nodes = process_nodes_interpolation(&resolved_nodes, &merged_frontmatter)
-        .map_err(|e| RenderError::HtmlGenerationFailed(e.to_string()))?;
+    // 4. Apply the caller's transformers
+    let nodes = apply_transformers(nodes, transformers);
+
+    // 5. Report the warnings
+    let report = report.into_inner().unwrap_or_else(|e| e.into_inner());
+    settings.parse_options.check(resource, &report.warnings)?;
 
-    // 5. Update document with processed content
-    doc.content = interpolated_nodes;
+    // 6. Update document with processed content
+    doc.content = nodes;
     doc.frontmatter = merged_frontmatter;
 
-    Ok(doc)
+    Ok((doc, timings, report))
+}
+
+/// Cache writes collected while executing a work plan
+#[derive(Default)]
+struct CacheEntries {
+    documents: Vec<DocumentCacheEntry>,
+    rendered: Vec<RenderedDocumentEntry>,
+}
+
+/// Key of a document's rendered output in the cache (see
+/// [`CacheOperations::get_rendered_documents`])
+///
+/// Output rendered in a language, or with other frontmatter interpolated
+/// into it, is stored apart from the output of the same file rendered
+/// otherwise. `frontmatter` is everything the document was rendered with:
+/// the API's frontmatter merged with any state.
+pub fn rendered_cache_key(hash: ResourceHash, language: Option<&str>, frontmatter: &Frontmatter) -> String {
+    let mut key = hash.to_string();
+    if let Some(language) = language {
+        key = format!("{}@{}", key, language);
+    }
+    if !frontmatter.is_empty() {
+        // Every frontmatter property is a JSON value or a plain string map
+        let state = compute_json_hash(frontmatter).expect("frontmatter is representable as JSON");
+        key = format!("{}#{:016x}", key, state);
+    }
+    key
+}
+
+/// Collect a rendered document, its pass timings and its cache entries
+fn record(
+    rendered: RenderedDocument,
+    settings: &RenderSettings,
+    frontmatter: &Frontmatter,
+    results: &mut Vec<Document>,
+    metrics: &mut RenderMetrics,
+    cache_entries: &mut CacheEntries,
+) {
+    let resource = &rendered.document.resource;
+    let hash = compute_resource_hash(resource, settings.project_root.as_deref());
+    let language = settings.language.as_deref();
+    metrics.record_document(&rendered.timings);
+    metrics.parse_warnings.record(resource, rendered.parse_warnings);
+    metrics.fallbacks.extend(rendered.fallbacks);
+    metrics.language_variants.extend(rendered.language_variants);
+    if resource.is_memory() {
+        // Nothing to check freshness against later, so never cached
+        results.push(rendered.document);
+        return;
+    }
+    cache_entries
+        .documents
+        .push(document_entry(
+            hash,
+            resource,
+            rendered.content_hash.clone(),
+            rendered.file_stat,
+        ));
+    cache_entries.rendered.push(RenderedDocumentEntry {
+        resource_hash: rendered_cache_key(hash, language, frontmatter),
+        content_hash: rendered.content_hash,
+        document: rendered.document.clone(),
+        rendered_at: chrono::Utc::now(),
+    });
+    results.push(rendered.document);
 }
 
 /// Load resource content (similar to transclusion but without parsing)
+///
+/// A remote response over `max_remote_bytes` or that isn't text is refused.
+/// Also returns a warning when a local file that isn't required had to be
+/// decoded lossily (see [`decode_text`]).
 async fn load_resource_content(
     resource: &Resource,
     _cache: &CacheOperations,
-) -> Result<String, RenderError> {
+    max_remote_bytes: u64,
+) -> Result<(String, Option<ParseWarning>), RenderError> {
     use crate::types::ResourceSource;
     use std::fs;
 
-    match &resource.source {
+    let content = match &resource.source {
         ResourceSource::Local(path) => {
-            fs::read_to_string(path)
+            let bytes = fs::read(path)
                 .map_err(|e| RenderError::ResourceNotFound(
                     path.display().to_string(),
                     e.to_string()
-                ))
+                ))?;
+            return Ok(decode_text(path, bytes, resource.requirement)?);
         }
-        ResourceSource::Remote(url) => {
-            let url_str = url.to_string();
-            let response = reqwest::get(url.clone())
+        ResourceSource::Remote(url) => crate::http::fetch_text(url, max_remote_bytes).await,
+        ResourceSource::Git { repo_url, ref_, path } => {
+            crate::graph::git::read_git_file(repo_url, ref_, path)
                 .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str.clone(), e.to_string()))?;
-
-            if !response.status().is_success() {
-                return Err(RenderError::RemoteFetchError(
-                    url_str,
-                    format!("HTTP {}", response.status()),
-                ));
-            }
-
-            response
-                .text()
-                .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str, e.to_string()))
+                .map_err(|e| RenderError::ResourceNotFound(
+                    git_location(repo_url, ref_, path),
+                    e.to_string()
+                ))
         }
-    }
+        ResourceSource::Memory { content, .. } => Ok(content.to_string()),
+    }?;
+    Ok((content, None))
 }
 
 /// Extract base path from resource for relative path resolution
@@ -172,14 +596,15 @@ fn extract_base_path(resource: &Resource) -> Option<&std::path::PathBuf> {
     use crate::types::ResourceSource;
     match &resource.source {
         ResourceSource::Local(path) => Some(path),
-        ResourceSource::Remote(_) => None,
+        ResourceSource::Remote(_) | ResourceSource::Git { .. } | ResourceSource::Memory { .. } => None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::ResourceSource;
+    use crate::error::{AIError, FetchFailure};
+    use crate::types::{DarkMatterNode, MarkdownContent, ResourceSource};
     use std::path::PathBuf;
 
     #[test]
@@ -188,6 +613,8 @@ mod tests {
             source: ResourceSource::Local(PathBuf::from("/tmp/test.md")),
             requirement: Default::default(),
             cache_duration: None,
+            section: None,
+            fallback: None,
         };
 
         let base = extract_base_path(&resource);
@@ -203,12 +630,322 @@ mod tests {
             source: ResourceSource::Remote(Url::parse("https://example.com/test.md").unwrap()),
             requirement: Default::default(),
             cache_duration: None,
+            section: None,
+            fallback: None,
         };
 
         let base = extract_base_path(&resource);
         assert!(base.is_none());
     }
 
+    #[test]
+    fn test_dispatch_order_follows_priority() {
+        let resources: Vec<Resource> = ["a.md", "b.md", "index.md", "c.md"]
+            .iter()
+            .map(|name| Resource::local(PathBuf::from(name)))
+            .collect();
+        let mut plan = WorkPlan::new();
+        plan.with_priority(&compute_resource_hash(&resources[2], None), 200)
+            .with_priority(&compute_resource_hash(&resources[3], None), 50);
+        let layer = WorkLayer {
+            resources: resources.clone(),
+            parallelizable: true,
+        };
+
+        let order: Vec<&ResourceSource> = dispatch_order(&plan, &layer)
+            .into_iter()
+            .map(|resource| &resource.source)
+            .collect();
+        let expected: Vec<&ResourceSource> = [2, 3, 0, 1]
+            .iter()
+            .map(|&idx| &resources[idx].source)
+            .collect();
+        assert_eq!(order, expected);
+    }
+
+    /// Replaces the content of every text node
+    struct ReplaceText(&'static str);
+
+    impl NodeTransformer for ReplaceText {
+        fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            match node {
+                DarkMatterNode::Text(_) => Some(vec![DarkMatterNode::Text(self.0.to_string())]),
+                _ => None,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_parsed_document_applies_transformers() {
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let doc = Document::new(Resource::memory("doc", "")).with_content(vec![
+            DarkMatterNode::Text("original".to_string()),
+            DarkMatterNode::Markdown(MarkdownContent {
+                raw: "**kept**".to_string(),
+                frontmatter: None,
+            }),
+        ]);
+        let transformers: Vec<Arc<dyn NodeTransformer>> =
+            vec![Arc::new(ReplaceText("first")), Arc::new(ReplaceText("replaced"))];
+
+        let (doc, _timings, _report) = render_parsed_document(
+            doc,
+            &Frontmatter::new(),
+            &cache,
+            &RenderPass::default_passes(),
+            None,
+            &transformers,
+            &RenderSettings::default(),
+        )
+        .await
+        .unwrap();
+
+        let html = crate::render::to_html(&doc.content).unwrap();
+        assert!(html.contains("replaced"), "{}", html);
+        assert!(!html.contains("original") && !html.contains("first"), "{}", html);
+        assert!(html.contains("<strong>kept</strong>"), "{}", html);
+    }
+
+    /// Tracks how many documents are being transformed at the same time
+    #[derive(Default)]
+    struct ConcurrencyProbe {
+        active: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    impl NodeTransformer for ConcurrencyProbe {
+        fn transform(&self, _node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            use std::sync::atomic::Ordering;
+
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            None
+        }
+    }
+
+    /// Render one parallel layer of documents, returning the peak concurrency
+    async fn peak_concurrency(max_parallelism: Option<usize>) -> usize {
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = Arc::new(CacheOperations::new(db));
+        let mut plan = WorkPlan::new();
+        plan.add_layer(WorkLayer {
+            resources: (0..6).map(|i| Resource::memory(format!("doc-{}", i), "text")).collect(),
+            parallelizable: true,
+        });
+        let probe = Arc::new(ConcurrencyProbe::default());
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![probe.clone()];
+
+        let settings = RenderSettings { max_parallelism, retry: RetryPolicy::none(), ..RenderSettings::default() };
+
+        let documents = execute_workplan(
+            &plan,
+            &Frontmatter::new(),
+            &cache,
+            &RenderPass::default_passes(),
+            None,
+            &transformers,
+            &settings,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(documents.len(), 6);
+        probe.peak.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_max_parallelism_limits_concurrent_documents() {
+        assert_eq!(peak_concurrency(Some(1)).await, 1);
+        assert!(peak_concurrency(Some(2)).await <= 2);
+        assert!(peak_concurrency(None).await > 1);
+    }
+
+    /// Records the markdown of each document in the order they're transformed
+    #[derive(Default)]
+    struct RenderOrder(std::sync::Mutex<Vec<String>>);
+
+    impl NodeTransformer for RenderOrder {
+        fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            if let DarkMatterNode::Markdown(content) = node {
+                self.0.lock().unwrap().push(content.raw.trim().to_string());
+            }
+            None
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_priority_orders_parallel_layer_under_a_limit() {
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = Arc::new(CacheOperations::new(db));
+        let resources: Vec<Resource> = ["a", "b", "index", "c"]
+            .iter()
+            .map(|name| Resource::memory(*name, *name))
+            .collect();
+        let mut plan = WorkPlan::new();
+        plan.with_priority(&compute_resource_hash(&resources[2], None), 200)
+            .with_priority(&compute_resource_hash(&resources[3], None), 50);
+        plan.add_layer(WorkLayer { resources, parallelizable: true });
+        let order = Arc::new(RenderOrder::default());
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![order.clone()];
+        let settings = RenderSettings { max_parallelism: Some(1), ..RenderSettings::default() };
+
+        execute_workplan(&plan, &Frontmatter::new(), &cache, &RenderPass::default_passes(), None, &transformers, &settings)
+            .await
+            .unwrap();
+
+        assert_eq!(*order.0.lock().unwrap(), ["index", "c", "a", "b"]);
+    }
+
+    /// Panics on markdown that says "boom"
+    struct PanicOnBoom;
+
+    impl NodeTransformer for PanicOnBoom {
+        fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            match node {
+                DarkMatterNode::Markdown(content) if content.raw.contains("boom") => panic!("decoder exploded"),
+                _ => None,
+            }
+        }
+    }
+
+    /// A plan with one layer of a document that panics between two that don't
+    fn plan_with_panicking_document(parallelizable: bool) -> WorkPlan {
+        let mut plan = WorkPlan::new();
+        plan.add_layer(WorkLayer {
+            resources: vec![
+                Resource::memory("first", "fine"),
+                Resource::memory("bad", "boom"),
+                Resource::memory("last", "fine"),
+            ],
+            parallelizable,
+        });
+        plan
+    }
+
+    async fn execute(plan: &WorkPlan, partial_results: bool) -> Result<(Vec<Document>, RenderMetrics), RenderError> {
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = Arc::new(CacheOperations::new(db));
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![Arc::new(PanicOnBoom)];
+        let passes = [RenderPass::Final];
+
+        let settings = RenderSettings { partial_results, ..RenderSettings::default() };
+
+        execute_workplan_with_metrics(plan, &Frontmatter::new(), &cache, &passes, None, &transformers, &settings).await
+    }
+
+    #[tokio::test]
+    async fn test_single_document_fails_with_its_own_error() {
+        let mut plan = WorkPlan::new();
+        plan.add_layer(WorkLayer { resources: vec![Resource::memory("bad", "boom")], parallelizable: false });
+
+        let error = execute(&plan, false).await.unwrap_err();
+
+        assert!(matches!(&error, RenderError::TaskPanicked(message) if message == "decoder exploded"), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_document_fails_alone() {
+        for parallelizable in [false, true] {
+            let plan = plan_with_panicking_document(parallelizable);
+
+            let error = execute(&plan, false).await.unwrap_err();
+            let RenderError::TasksFailed(failures) = &error else {
+                panic!("Expected TasksFailed, got {:?}", error);
+            };
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].resource.label(), "bad");
+            // A panic isn't retried
+            assert_eq!(failures[0].attempts, 1);
+            assert!(error.to_string().contains("bad: Render task panicked: decoder exploded"), "{}", error);
+
+            let (documents, metrics) = execute(&plan, true).await.unwrap();
+            let mut rendered: Vec<_> = documents.iter().map(|doc| doc.resource.label()).collect();
+            rendered.sort();
+            assert_eq!(rendered, ["first", "last"]);
+            assert_eq!(metrics.failures.len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        // Nothing listens on port 1, so every fetch fails at once
+        let resource = Resource::remote(url::Url::parse("http://127.0.0.1:1/doc.md").unwrap());
+        let settings = RenderSettings {
+            retry: RetryPolicy { attempts: 3, backoff: Duration::from_millis(1) },
+            ..RenderSettings::default()
+        };
+
+        let failure = render_task(&resource, &Frontmatter::new(), &cache, &[], None, &[], &settings)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(failure.attempts, 3);
+        assert!(failure.to_string().ends_with("(after 3 attempts)"), "{}", failure);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_remote_document_is_not_retried() {
+        use crate::audio::metadata::tests::{http_response, serve_http};
+
+        let db = crate::cache::init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let (base_url, requests) =
+            serve_http(vec![http_response("200 OK", &[("Content-Type", "text/markdown")], &[b'a'; 2048])]);
+        let resource = Resource::remote(url::Url::parse(&format!("{}/doc.md", base_url)).unwrap());
+        let settings = RenderSettings {
+            max_remote_bytes: 1024,
+            retry: RetryPolicy { attempts: 3, backoff: Duration::from_millis(1) },
+            ..RenderSettings::default()
+        };
+
+        let failure = render_task(&resource, &Frontmatter::new(), &cache, &[], None, &[], &settings)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(failure.attempts, 1);
+        assert!(
+            matches!(failure.error, RenderError::RemoteFetchError { kind: FetchFailure::TooLarge, .. }),
+            "{}",
+            failure
+        );
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_doubles() {
+        let retry = RetryPolicy { attempts: 4, backoff: Duration::from_millis(100) };
+
+        assert_eq!(retry.delay(1), Duration::from_millis(100));
+        assert_eq!(retry.delay(3), Duration::from_millis(400));
+        assert_eq!(RetryPolicy { attempts: 2, backoff: Duration::MAX }.delay(5), Duration::MAX);
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let fetch = |kind: FetchFailure| RenderError::RemoteFetchError {
+            url: "https://example.com".to_string(),
+            kind,
+            reason: String::new(),
+        };
+
+        assert!(fetch(FetchFailure::Request).is_transient());
+        assert!(fetch(FetchFailure::Status(503)).is_transient());
+        assert!(fetch(FetchFailure::Status(429)).is_transient());
+        assert!(!fetch(FetchFailure::Status(404)).is_transient());
+        assert!(!fetch(FetchFailure::TooLarge).is_transient());
+        assert!(RenderError::Ai(AIError::RateLimitExceeded("openai".to_string())).is_transient());
+        assert!(!RenderError::AiOperationFailed("Rate limit exceeded for provider: openai".to_string()).is_transient());
+        assert!(!RenderError::TaskPanicked("oops".to_string()).is_transient());
+        assert!(!RenderError::Parse(ParseError::InvalidFrontmatter("bad".to_string())).is_transient());
+    }
+
     // Note: Full integration tests for execute_workplan would require
     // setting up test fixtures and a database, which is better suited
     // for integration tests in the tests/ directory
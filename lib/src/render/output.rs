@@ -0,0 +1,489 @@
+use crate::graph::utils::compute_resource_hash;
+use crate::types::{Resource, ResourceHash, ResourceSource};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use url::Url;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Template used for remote resources when no `output_template` is configured
+const DEFAULT_REMOTE_TEMPLATE: &str = "{stem}-{hash}.html";
+
+/// Tags that load an asset, and inline scripts with their body
+static ASSET_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<(img|source|audio|track|link)\b([^>]*)>|<script\b([^>]*)>(.*?)</script>").unwrap()
+});
+
+/// A double-quoted attribute
+static ATTR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\s([\w-]+)="([^"]*)""#).unwrap()
+});
+
+/// A static ES module import, as the mermaid and KaTeX scripts use
+static MODULE_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\bimport\s+[\w\s{},*]+\s+from\s+"([^"]+)""#).unwrap()
+});
+
+/// Extensions of audio files, for `<source>` tags without a `type`
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "ogg", "oga", "m4a", "aac", "opus"];
+
+/// Assigns output paths to rendered documents
+///
+/// Paths are derived from an optional filename template with these placeholders:
+///
+/// - `{stem}` - file name without extension (the host for remote URLs without a path)
+/// - `{hash}` - 16 hex digits identifying the source; local files are hashed
+///   relative to the project root (see [`with_project_root`](Self::with_project_root)),
+///   and for remote URLs this hashes the host and path
+/// - `{host}` - host of a remote URL, empty otherwise
+///
+/// Local and git resources are placed next to their source. Without a template
+/// they keep their source path, and remote resources use `{stem}-{hash}.html`.
+/// A path already handed out gets a `-2`, `-3`, ... suffix instead of being
+/// reused.
+#[derive(Debug, Default)]
+pub struct OutputPaths {
+    template: Option<String>,
+    project_root: Option<PathBuf>,
+    used: HashSet<PathBuf>,
+}
+
+impl OutputPaths {
+    pub fn new(template: Option<String>) -> Self {
+        Self {
+            template,
+            project_root: None,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Hash local files for `{hash}` relative to `project_root`, as the graph
+    /// they were rendered from was
+    pub fn with_project_root(mut self, project_root: Option<PathBuf>) -> Self {
+        self.project_root = project_root;
+        self
+    }
+
+    /// Output path for a resource, unique among all paths assigned so far
+    pub fn assign(&mut self, resource: &Resource) -> PathBuf {
+        let candidate = self.candidate(resource);
+        let path = disambiguate(candidate, &self.used);
+        self.used.insert(path.clone());
+        path
+    }
+
+    fn candidate(&self, resource: &Resource) -> PathBuf {
+        let source = match &resource.source {
+            ResourceSource::Local(path) => path.as_path(),
+            ResourceSource::Git { path, .. } => Path::new(path),
+            ResourceSource::Memory { name, .. } => Path::new(name),
+            ResourceSource::Remote(url) => {
+                let template = self.template.as_deref().unwrap_or(DEFAULT_REMOTE_TEMPLATE);
+                let host = url.host_str().unwrap_or_default();
+                return PathBuf::from(fill(template, &remote_stem(url), remote_hash(url), host));
+            }
+        };
+
+        match &self.template {
+            Some(template) => {
+                let hash = compute_resource_hash(resource, self.project_root.as_deref());
+                let filename = fill(template, &stem_of(source), hash, "");
+                beside(source, &filename)
+            }
+            None => source.to_path_buf(),
+        }
+    }
+}
+
+fn fill(template: &str, stem: &str, hash: ResourceHash, host: &str) -> String {
+    template
+        .replace("{stem}", stem)
+        .replace("{hash}", &hash.to_string())
+        .replace("{host}", host)
+}
+
+fn beside(source: &Path, filename: &str) -> PathBuf {
+    match source.parent() {
+        Some(parent) => parent.join(filename),
+        None => PathBuf::from(filename),
+    }
+}
+
+fn stem_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn remote_stem(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        .map(|segment| stem_of(Path::new(segment)))
+        .filter(|stem| !stem.is_empty())
+        .or_else(|| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "remote".to_string())
+}
+
+/// Hash of a remote URL's host and path, ignoring scheme, query and fragment
+fn remote_hash(url: &Url) -> ResourceHash {
+    let key = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+    ResourceHash(xxh3_64(key.as_bytes()))
+}
+
+/// A pre-compression format for HTML output, for static hosts that serve
+/// `page.html.gz` or `page.html.br` to clients that accept it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Compression {
+    /// gzip at the highest level
+    Gzip,
+    /// Brotli at the highest quality
+    Brotli,
+}
+
+impl Compression {
+    /// The extension added after `.html`, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Brotli => "br",
+        }
+    }
+
+    /// Compress `bytes`; decompressing the result gives `bytes` back exactly
+    pub fn compress(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(bytes).expect("writing to a Vec can't fail");
+                encoder.finish().expect("writing to a Vec can't fail")
+            }
+            Self::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+                encoder.write_all(bytes).expect("writing to a Vec can't fail");
+                encoder.into_inner()
+            }
+        }
+    }
+
+    /// `path` with this format's extension appended, e.g. `page.html.gz`
+    pub fn path_for(self, path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".");
+        name.push(self.extension());
+        PathBuf::from(name)
+    }
+}
+
+/// Append `-2`, `-3`, ... to the file stem until the path is unused
+fn disambiguate(path: PathBuf, used: &HashSet<PathBuf>) -> PathBuf {
+    if !used.contains(&path) {
+        return path;
+    }
+
+    let stem = stem_of(&path);
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+
+    (2..)
+        .map(|n| {
+            let filename = match &extension {
+                Some(ext) => format!("{}-{}.{}", stem, n, ext),
+                None => format!("{}-{}", stem, n),
+            };
+            path.with_file_name(filename)
+        })
+        .find(|candidate| !used.contains(candidate))
+        .expect("an unused suffix always exists")
+}
+
+/// What kind of file an [`AssetRef`] points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Image,
+    Audio,
+    Style,
+    Script,
+}
+
+/// A file that generated HTML loads
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AssetRef {
+    pub kind: AssetKind,
+    /// The URL as written in the HTML, relative or absolute
+    pub path: String,
+    /// The `v` cache-busting query parameter, when the URL has one
+    pub hash: Option<String>,
+}
+
+impl AssetRef {
+    fn new(kind: AssetKind, path: String) -> Self {
+        let hash = path.split_once('?').and_then(|(_, query)| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("v="))
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        });
+        Self { kind, path, hash }
+    }
+}
+
+/// Images, audio, stylesheets and scripts an HTML page loads, in order of
+/// first use
+///
+/// Every `srcset` candidate is listed, as are modules imported by inline
+/// scripts. Inline `data:` URIs, inline styles and `<video>` sources are
+/// left out.
+pub(crate) fn linked_assets(html: &str) -> Vec<AssetRef> {
+    let mut seen = HashSet::new();
+    let mut assets = Vec::new();
+    let mut push = |kind: AssetKind, url: &str| {
+        let url = url.trim().replace("&amp;", "&");
+        if !url.is_empty() && !url.starts_with("data:") && seen.insert(url.clone()) {
+            assets.push(AssetRef::new(kind, url));
+        }
+    };
+
+    for tag in ASSET_TAG.captures_iter(html) {
+        let (name, attrs) = match tag.get(1) {
+            Some(name) => (name.as_str(), &tag[2]),
+            None => ("script", &tag[3]),
+        };
+        let attrs: Vec<(&str, &str)> = ATTR
+            .captures_iter(attrs)
+            .map(|attr| {
+                let (_, [name, value]) = attr.extract();
+                (name, value)
+            })
+            .collect();
+        let attr = |name: &str| attrs.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+        match name {
+            "link" => {
+                if attr("rel").is_some_and(|rel| rel.split_whitespace().any(|r| r == "stylesheet")) {
+                    if let Some(href) = attr("href") {
+                        push(AssetKind::Style, href);
+                    }
+                }
+            }
+            "script" => {
+                if let Some(src) = attr("src") {
+                    push(AssetKind::Script, src);
+                }
+                for import in MODULE_IMPORT.captures_iter(&tag[4]) {
+                    push(AssetKind::Script, &import[1]);
+                }
+            }
+            name => {
+                let kind = match (name, attr("type")) {
+                    ("img", _) => AssetKind::Image,
+                    ("audio" | "track", _) => AssetKind::Audio,
+                    (_, Some(t)) if t.starts_with("audio/") => AssetKind::Audio,
+                    (_, Some(t)) if t.starts_with("image/") => AssetKind::Image,
+                    (_, Some(_)) => continue,
+                    (_, None) => match attr("src") {
+                        Some(src) if is_audio_path(src) => AssetKind::Audio,
+                        _ => AssetKind::Image,
+                    },
+                };
+                for (name, value) in &attrs {
+                    match *name {
+                        "src" => push(kind, value),
+                        "srcset" => value
+                            .split(',')
+                            .filter_map(|candidate| candidate.split_whitespace().next())
+                            .for_each(|url| push(kind, url)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    assets
+}
+
+fn is_audio_path(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// The words of markdown's prose, with markup, headings, code blocks and
+/// image alt text left out
+pub(crate) fn prose_words(markdown: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut skipping = 0usize;
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Heading { .. } | Tag::CodeBlock(_) | Tag::Image { .. }) => skipping += 1,
+            Event::End(TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::Image) => {
+                skipping = skipping.saturating_sub(1)
+            }
+            Event::Text(t) | Event::Code(t) if skipping == 0 => text.push_str(&t),
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(TagEnd::Paragraph | TagEnd::Item | TagEnd::TableCell | TagEnd::BlockQuote(_))
+                if skipping == 0 =>
+            {
+                text.push(' ')
+            }
+            _ => {}
+        }
+    }
+
+    text.split_whitespace().map(str::to_string).collect()
+}
+
+/// The first `limit` words, followed by `…` when there were more
+pub(crate) fn excerpt(words: &[String], limit: usize) -> String {
+    let mut excerpt = words[..words.len().min(limit)].join(" ");
+    if words.len() > limit {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn remote(url: &str) -> Resource {
+        Resource::remote(Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_remote_urls_get_distinct_paths() {
+        let mut paths = OutputPaths::new(None);
+
+        let a = paths.assign(&remote("https://a.example.com/docs/index.md"));
+        let b = paths.assign(&remote("https://b.example.com/docs/index.md"));
+
+        assert_ne!(a, b);
+        assert!(a.to_string_lossy().starts_with("index-"));
+        assert!(a.to_string_lossy().ends_with(".html"));
+    }
+
+    #[test]
+    fn test_remote_without_path_uses_host() {
+        let mut paths = OutputPaths::new(None);
+        let path = paths.assign(&remote("https://example.com/"));
+        assert!(path.to_string_lossy().starts_with("example.com-"));
+    }
+
+    #[test]
+    fn test_local_default_keeps_source_path() {
+        let mut paths = OutputPaths::new(None);
+        let path = paths.assign(&Resource::local(PathBuf::from("/docs/guide.md")));
+        assert_eq!(path, PathBuf::from("/docs/guide.md"));
+    }
+
+    #[test]
+    fn test_local_template() {
+        let mut paths = OutputPaths::new(Some("{stem}.html".to_string()));
+        let path = paths.assign(&Resource::local(PathBuf::from("/docs/guide.md")));
+        assert_eq!(path, PathBuf::from("/docs/guide.html"));
+    }
+
+    #[test]
+    fn test_collisions_get_suffix() {
+        let mut paths = OutputPaths::new(Some("{stem}.html".to_string()));
+
+        let first = paths.assign(&Resource::local(PathBuf::from("/docs/guide.md")));
+        let second = paths.assign(&Resource::local(PathBuf::from("/docs/guide.markdown")));
+        let third = paths.assign(&Resource::local(PathBuf::from("/docs/guide.txt")));
+
+        assert_eq!(first, PathBuf::from("/docs/guide.html"));
+        assert_eq!(second, PathBuf::from("/docs/guide-2.html"));
+        assert_eq!(third, PathBuf::from("/docs/guide-3.html"));
+    }
+
+    #[test]
+    fn test_remote_template_with_host() {
+        let mut paths = OutputPaths::new(Some("{host}/{stem}.html".to_string()));
+        let path = paths.assign(&remote("https://example.com/a/readme.md"));
+        assert_eq!(path, PathBuf::from("example.com/readme.html"));
+    }
+
+    fn decompress(compression: Compression, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        match compression {
+            Compression::Gzip => flate2::read::GzDecoder::new(bytes).read_to_string(&mut text),
+            Compression::Brotli => brotli::Decompressor::new(bytes, 4096).read_to_string(&mut text),
+        }
+        .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_compression_round_trips() {
+        let html = "<article>\n".to_string()
+            + &"  <p class=\"dm-text\">Pre-compressed pages load faster on static hosts. ü</p>\n".repeat(40)
+            + "</article>\n";
+
+        for compression in [Compression::Gzip, Compression::Brotli] {
+            let compressed = compression.compress(html.as_bytes());
+            assert!(compressed.len() < html.len() / 4, "{:?}: {} bytes", compression, compressed.len());
+            assert_eq!(decompress(compression, &compressed), html);
+            assert_eq!(decompress(compression, &compression.compress(b"")), "");
+        }
+    }
+
+    #[test]
+    fn test_compressed_path() {
+        assert_eq!(Compression::Gzip.path_for(Path::new("out/guide.html")), PathBuf::from("out/guide.html.gz"));
+        assert_eq!(Compression::Brotli.path_for(Path::new("guide.html")), PathBuf::from("guide.html.br"));
+    }
+
+    #[test]
+    fn test_linked_assets() {
+        let html = concat!(
+            r#"<picture><source type="image/webp" srcset="img/a-320w.webp 320w, img/a-640w.webp 640w" sizes="100vw">"#,
+            r#"<img src="data:image/png;base64,AAAA" alt=""></picture>"#,
+            r#"<audio controls><source src="audio/ep.mp3?x=2&amp;v=ab12" type="audio/mpeg"></audio>"#,
+            r#"<iframe src="https://www.youtube.com/embed/xyz"></iframe><img src="img/a-320w.webp">"#,
+            r#"<video><source src="clip.mp4" type="video/mp4"></video><style id="dm-theme">p {}</style>"#,
+            r#"<link rel="stylesheet" href="https://cdn.example/katex.min.css"><script id="dm-clipboard">copy()</script>"#,
+            "<script type=\"module\">import katex from \"https://cdn.example/katex.mjs\";\nkatex.render();</script>",
+        );
+
+        let assets = linked_assets(html);
+        let listed: Vec<(AssetKind, &str)> = assets.iter().map(|a| (a.kind, a.path.as_str())).collect();
+        assert_eq!(
+            listed,
+            vec![
+                (AssetKind::Image, "img/a-320w.webp"),
+                (AssetKind::Image, "img/a-640w.webp"),
+                (AssetKind::Audio, "audio/ep.mp3?x=2&v=ab12"),
+                (AssetKind::Style, "https://cdn.example/katex.min.css"),
+                (AssetKind::Script, "https://cdn.example/katex.mjs"),
+            ]
+        );
+        assert_eq!(assets[2].hash.as_deref(), Some("ab12"));
+        assert!(assets.iter().filter(|a| a.kind != AssetKind::Audio).all(|a| a.hash.is_none()));
+    }
+
+    #[test]
+    fn test_source_without_type_uses_extension() {
+        let assets = linked_assets(r#"<audio><source src="ep.FLAC#t=10"></audio><picture><source srcset="a.avif"></picture>"#);
+        assert_eq!(assets[0].kind, AssetKind::Audio);
+        assert_eq!(assets[1].kind, AssetKind::Image);
+    }
+
+    #[test]
+    fn test_prose_words_and_excerpt() {
+        let words = prose_words("# Title\n\nSome *emphasized* text with `code`\nand [a link](x.md).\n\n![alt text](a.png)\n\n```rust\nfn skipped() {}\n```\n\nEnd.\n");
+        assert_eq!(words, vec!["Some", "emphasized", "text", "with", "code", "and", "a", "link.", "End."]);
+
+        assert_eq!(excerpt(&words, 3), "Some emphasized text…");
+        assert_eq!(excerpt(&words, 20), "Some emphasized text with code and a link. End.");
+    }
+}
@@ -0,0 +1,953 @@
+use crate::ai::queue::{is_ai_operation, AsyncAIQueue};
+use crate::audio::AudioBatchOptions;
+use crate::cache::CacheOperations;
+use crate::error::{AIError, CompositionError, ParseWarning, ParseWarningCode, RenderError};
+use crate::graph::load_resource_candidate;
+use crate::parse::{extract_frontmatter, parse_imports};
+use crate::types::{
+    AxisScale, ChartData, ChartSeries, DarkMatterNode, DataSeries, Frontmatter, Resource, ResourceHash,
+    ResourceRequirement,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+use super::audio::process_audio_nodes_with_warnings;
+use super::interpolation::{interpolate_frontmatter_values, process_nodes_interpolation_with_warnings};
+use super::transclusion::{load_csv_data, load_resource, resolve_resource_path, resolve_transclusion};
+
+/// A stage of the render pipeline
+///
+/// Each pass transforms a document's node tree and sees the output of every
+/// pass before it, so AI operations work on fully transcluded content and
+/// interpolation sees AI-generated text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RenderPass {
+    /// Expand `::file` transclusions and external table sources
+    Transclusion,
+    /// Resolve summarize/consolidate/topic directives
+    AiOperations,
+    /// Substitute `{{variable}}` references from frontmatter
+    Interpolation,
+    /// Load external chart data into series
+    Charts,
+    /// Process `::audio` directives into players
+    Audio,
+    /// Normalize the tree ahead of HTML generation
+    Final,
+}
+
+impl RenderPass {
+    /// All passes in their standard order
+    pub fn default_passes() -> Vec<RenderPass> {
+        vec![
+            RenderPass::Transclusion,
+            RenderPass::AiOperations,
+            RenderPass::Interpolation,
+            RenderPass::Charts,
+            RenderPass::Audio,
+            RenderPass::Final,
+        ]
+    }
+
+    /// Short name used in tracing spans and logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderPass::Transclusion => "transclusion",
+            RenderPass::AiOperations => "ai_operations",
+            RenderPass::Interpolation => "interpolation",
+            RenderPass::Charts => "charts",
+            RenderPass::Audio => "audio",
+            RenderPass::Final => "final",
+        }
+    }
+}
+
+/// Timings collected while executing a work plan
+#[derive(Debug, Clone, Default)]
+pub struct RenderMetrics {
+    /// Time spent in each pass, summed over all documents, in pipeline order
+    pub pass_durations: Vec<(RenderPass, Duration)>,
+    /// Number of documents rendered
+    pub documents: usize,
+    /// Wall-clock time for the whole work plan
+    pub total: Duration,
+    /// Files that matched a glob pattern but weren't rendered
+    pub excluded: Vec<ExcludedFile>,
+    /// Problems that didn't stop rendering, by document: unknown directives,
+    /// undefined variables, missing optional resources, files decoded lossily
+    /// and the other [`ParseWarningCode`](crate::error::ParseWarningCode)s
+    pub parse_warnings: RenderWarnings,
+    /// Resources loaded from a fallback rather than their first candidate
+    pub fallbacks: Vec<FallbackUsed>,
+    /// The file each document and `::file` transclusion was rendered from,
+    /// when rendering in a language
+    pub language_variants: Vec<LanguageVariant>,
+    /// Documents that failed to render, when the work plan was executed for
+    /// partial results
+    pub failures: Vec<TaskFailure>,
+}
+
+/// A document of a work plan that failed to render
+#[derive(Debug, Clone)]
+pub struct TaskFailure {
+    pub resource: Resource,
+    /// The last attempt's error
+    pub error: RenderError,
+    /// Attempts made, including retries after transient failures
+    pub attempts: u32,
+}
+
+impl fmt::Display for TaskFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.resource.label(), self.error)?;
+        if self.attempts > 1 {
+            write!(f, " (after {} attempts)", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+/// A resource that was loaded from one of its fallbacks
+#[derive(Debug, Clone)]
+pub struct FallbackUsed {
+    /// The resource as referenced, with its whole fallback chain
+    pub resource: Resource,
+    /// The candidate that was loaded
+    pub used: Resource,
+}
+
+/// Which file a resource was rendered from in a language: its translation,
+/// or the resource itself when it has none
+#[derive(Debug, Clone)]
+pub struct LanguageVariant {
+    pub language: String,
+    /// The resource as referenced
+    pub resource: Resource,
+    /// The resource that was rendered, e.g. `intro.de.md` for `intro.md`
+    pub used: Resource,
+}
+
+impl LanguageVariant {
+    /// Whether a translation was found
+    pub fn is_translated(&self) -> bool {
+        self.resource.source != self.used.source
+    }
+}
+
+/// What the render passes found out about a document besides its content
+#[derive(Debug, Default)]
+pub struct PassReport {
+    /// Problems that didn't stop the document from rendering
+    pub warnings: Vec<ParseWarning>,
+    /// Transclusions loaded from a fallback
+    pub fallbacks: Vec<FallbackUsed>,
+    /// The variant each transclusion was loaded from
+    pub language_variants: Vec<LanguageVariant>,
+}
+
+/// The [`ParseWarning`]s of every rendered document
+#[derive(Debug, Clone, Default)]
+pub struct RenderWarnings {
+    /// Each document with warnings and its warnings, in render order
+    pub documents: Vec<(Resource, Vec<ParseWarning>)>,
+}
+
+impl RenderWarnings {
+    /// Whether no document had a warning
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Number of warnings over all documents
+    pub fn len(&self) -> usize {
+        self.documents.iter().map(|(_, warnings)| warnings.len()).sum()
+    }
+
+    /// Every warning along with the document it was found in
+    pub fn iter(&self) -> impl Iterator<Item = (&Resource, &ParseWarning)> {
+        self.documents
+            .iter()
+            .flat_map(|(resource, warnings)| warnings.iter().map(move |warning| (resource, warning)))
+    }
+
+    /// Add the warnings of one document
+    pub(crate) fn record(&mut self, resource: &Resource, warnings: Vec<ParseWarning>) {
+        if !warnings.is_empty() {
+            self.documents.push((resource.clone(), warnings));
+        }
+    }
+}
+
+/// A file matched by a glob pattern that was left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedFile {
+    pub path: PathBuf,
+    pub reason: ExclusionReason,
+}
+
+/// Why a file matched by a glob pattern was left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// Ignored by the project's `.gitignore`, `.ignore` or `.git/info/exclude`
+    Ignored,
+    /// Matched one of the configured `exclude_patterns`
+    ExcludePattern(String),
+    /// A translation into one of the configured languages, rendered in
+    /// place of its original when rendering in that language
+    Translation(String),
+}
+
+impl fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionReason::Ignored => write!(f, "ignored by the project's ignore files"),
+            ExclusionReason::ExcludePattern(pattern) => write!(f, "matches exclude pattern '{}'", pattern),
+            ExclusionReason::Translation(language) => write!(f, "is a translation into '{}'", language),
+        }
+    }
+}
+
+impl RenderMetrics {
+    /// Total time spent in the given pass, if it ran
+    pub fn pass_duration(&self, pass: RenderPass) -> Option<Duration> {
+        self.pass_durations
+            .iter()
+            .find(|(p, _)| *p == pass)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Add the timings of one rendered document
+    pub(crate) fn record_document(&mut self, timings: &[(RenderPass, Duration)]) {
+        for (pass, duration) in timings {
+            match self.pass_durations.iter_mut().find(|(p, _)| p == pass) {
+                Some((_, total)) => *total += *duration,
+                None => self.pass_durations.push((*pass, *duration)),
+            }
+        }
+        self.documents += 1;
+    }
+}
+
+/// Everything a pass needs besides the nodes themselves
+pub(crate) struct PassContext<'a> {
+    pub frontmatter: &'a Frontmatter,
+    pub cache: &'a CacheOperations,
+    pub base_path: Option<&'a PathBuf>,
+    /// Queue for AI operations; without one AI nodes are left unresolved
+    pub ai_queue: Option<&'a AsyncAIQueue>,
+    /// Hash of the document being rendered
+    pub document_hash: ResourceHash,
+    /// Environment variables interpolation may read as `{{env.NAME}}`
+    pub allowed_env_vars: &'a [String],
+    /// Largest remote response a transclusion, import or chart may load
+    pub max_remote_bytes: u64,
+    /// Language whose translations `::file` transclusions prefer
+    pub language: Option<&'a str>,
+    /// Where `::audio` files are copied, or whether they are inlined
+    pub audio: &'a AudioBatchOptions,
+    /// Root local files are hashed relative to
+    pub project_root: Option<&'a Path>,
+    /// Warnings and fallbacks the passes ran into
+    pub report: &'a Mutex<PassReport>,
+}
+
+impl PassContext<'_> {
+    /// Record problems that didn't stop the document from rendering
+    fn warn(&self, warnings: Vec<ParseWarning>) {
+        self.report.lock().unwrap_or_else(|e| e.into_inner()).warnings.extend(warnings);
+    }
+}
+
+/// Run a single pass over a document's nodes
+pub(crate) async fn run_pass(
+    pass: RenderPass,
+    nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    match pass {
+        RenderPass::Transclusion => transclusion_pass(nodes, ctx).await,
+        RenderPass::AiOperations => ai_operations_pass(nodes, ctx).await,
+        RenderPass::Interpolation => interpolation_pass(nodes, ctx).await,
+        RenderPass::Charts => charts_pass(nodes, ctx).await,
+        // Boxed: audio processing nests deep enough to overflow the type
+        // layout limit of callers' futures
+        RenderPass::Audio => Box::pin(audio_pass(nodes, ctx)).await,
+        RenderPass::Final => final_pass(nodes),
+    }
+}
+
+/// Recursively resolve transclusions
+async fn transclusion_pass(
+    nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    let mut resolved = Vec::with_capacity(nodes.len());
+    for node in &nodes {
+        resolved.extend(resolve_transclusion(
+            node,
+            ctx.frontmatter,
+            ctx.cache,
+            ctx.base_path,
+            ctx.max_remote_bytes,
+            ctx.language,
+            ctx.report,
+        )
+        .await?);
+    }
+    Ok(resolved)
+}
+
+/// Resolve AI operations
+///
+/// Every AI node is submitted to the queue before any result is awaited, so
+/// the operations run concurrently with each other and with other documents.
+/// The `model` frontmatter property picks the completion model; without it
+/// the queue's default is used. Each node is replaced by the generated
+/// markdown, or by a review layout for a `--review` topic extraction. Without
+/// a queue (no completion model configured) AI nodes are left in place.
+async fn ai_operations_pass(
+    mut nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    let Some(queue) = ctx.ai_queue else {
+        let pending = nodes.iter().filter(|node| is_ai_operation(node)).count();
+        if pending > 0 {
+            debug!("{} AI operations left unresolved (no completion model configured)", pending);
+        }
+        return Ok(nodes);
+    };
+
+    let model = ctx.frontmatter.get_string("model").map(str::to_string);
+    let mut pending = Vec::new();
+    for (index, node) in nodes.iter().enumerate() {
+        if !is_ai_operation(node) {
+            continue;
+        }
+        let node = resolve_ai_resources(node, ctx)?;
+        let receiver = queue
+            .submit_with_model(node, ctx.document_hash, model.clone())
+            .await
+            .map_err(ai_failure)?;
+        pending.push((index, receiver));
+    }
+
+    for (index, receiver) in pending {
+        nodes[index] = receiver
+            .await
+            .map_err(|_| RenderError::Ai(AIError::QueueClosed))?
+            .map_err(ai_failure)?;
+    }
+
+    Ok(nodes)
+}
+
+/// The render error of a failed AI operation, keeping the [`AIError`] that
+/// tells whether it is worth retrying
+fn ai_failure(error: CompositionError) -> RenderError {
+    match error {
+        CompositionError::AI(error) => RenderError::Ai(error),
+        other => RenderError::AiOperationFailed(other.to_string()),
+    }
+}
+
+/// Resolve an AI node's resources relative to the document being rendered
+fn resolve_ai_resources(
+    node: &DarkMatterNode,
+    ctx: &PassContext<'_>,
+) -> Result<DarkMatterNode, RenderError> {
+    let resolve = |resource| resolve_resource_path(resource, ctx.base_path);
+
+    Ok(match node {
+        DarkMatterNode::Summarize { resource } => DarkMatterNode::Summarize {
+            resource: resolve(resource)?,
+        },
+        DarkMatterNode::Consolidate { resources } => DarkMatterNode::Consolidate {
+            resources: resources.iter().map(resolve).collect::<Result<_, _>>()?,
+        },
+        DarkMatterNode::Topic { topic, resources, review } => DarkMatterNode::Topic {
+            topic: topic.clone(),
+            resources: resources.iter().map(resolve).collect::<Result<_, _>>()?,
+            review: *review,
+        },
+        other => other.clone(),
+    })
+}
+
+/// Apply frontmatter interpolation, warning about variables without a value
+///
+/// Documents named in the `imports` frontmatter property are loaded first,
+/// and their frontmatter added under its namespace.
+async fn interpolation_pass(
+    nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    let imported = import_frontmatter(ctx).await?;
+    let frontmatter = imported.as_ref().unwrap_or(ctx.frontmatter);
+    let (nodes, warnings) = process_nodes_interpolation_with_warnings(&nodes, frontmatter, ctx.allowed_env_vars)
+        .map_err(|e| RenderError::HtmlGenerationFailed(e.to_string()))?;
+    ctx.warn(warnings);
+    Ok(nodes)
+}
+
+/// The document's frontmatter with each import's frontmatter added under its
+/// namespace, or `None` when it imports nothing
+///
+/// An imported document's values are interpolated from its own frontmatter,
+/// one level deep; its own imports aren't loaded. An import replaces a value
+/// of the same name. A missing optional import is skipped with a warning.
+async fn import_frontmatter(ctx: &PassContext<'_>) -> Result<Option<Frontmatter>, RenderError> {
+    let imports = parse_imports(ctx.frontmatter)?;
+    if imports.is_empty() {
+        return Ok(None);
+    }
+
+    let mut frontmatter = ctx.frontmatter.clone();
+    for (namespace, resource) in imports {
+        let resource = resolve_resource_path(&resource, ctx.base_path)?;
+        let loaded = load_resource_candidate(&resource, |candidate| {
+            load_resource(candidate, ctx.cache, None, ctx.max_remote_bytes)
+        })
+        .await;
+        let (used, content) = match loaded {
+            Err(e @ (RenderError::ResourceNotFound(..) | RenderError::RemoteFetchError { .. }))
+                if matches!(resource.requirement, ResourceRequirement::Optional) =>
+            {
+                ctx.warn(vec![ParseWarning {
+                    span: None,
+                    message: format!("Optional import {} not found: {}", resource.label(), e),
+                    code: ParseWarningCode::OptionalResourceNotFound,
+                }]);
+                continue;
+            }
+            result => result?,
+        };
+        let (imported, _) = extract_frontmatter(&content)
+            .map_err(|e| RenderError::ParseError(format!("{}: {}", used.label(), e)))?;
+        let values = interpolate_frontmatter_values(&imported)?;
+        frontmatter.custom.insert(namespace, serde_json::Value::Object(values));
+    }
+    Ok(Some(frontmatter))
+}
+
+/// Replace external chart data with series loaded from CSV
+///
+/// The `chart_tables` frontmatter property gives every chart a data table,
+/// as if it had the `--with-table` flag.
+async fn charts_pass(
+    nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    let tables_by_default = ctx.frontmatter.get_bool("chart_tables").unwrap_or(false);
+    let mut result = Vec::with_capacity(nodes.len());
+
+    for node in nodes {
+        let node = match node {
+            DarkMatterNode::BarChart { data, with_table } => DarkMatterNode::BarChart {
+                data: inline_chart_data(data, ctx).await?,
+                with_table: with_table || tables_by_default,
+            },
+            DarkMatterNode::LineChart { data, with_table } => DarkMatterNode::LineChart {
+                data: inline_chart_data(data, ctx).await?,
+                with_table: with_table || tables_by_default,
+            },
+            DarkMatterNode::PieChart { data, with_table, options } => DarkMatterNode::PieChart {
+                data: inline_chart_data(data, ctx).await?,
+                with_table: with_table || tables_by_default,
+                options,
+            },
+            DarkMatterNode::AreaChart { data, with_table } => DarkMatterNode::AreaChart {
+                data: inline_chart_data(data, ctx).await?,
+                with_table: with_table || tables_by_default,
+            },
+            DarkMatterNode::BubbleChart { data, with_table } => DarkMatterNode::BubbleChart {
+                data: inline_chart_data(data, ctx).await?,
+                with_table: with_table || tables_by_default,
+            },
+            other => other,
+        };
+        result.push(node);
+    }
+
+    Ok(result)
+}
+
+async fn inline_chart_data(data: ChartData, ctx: &PassContext<'_>) -> Result<ChartData, RenderError> {
+    match data {
+        ChartData::External(resource) => {
+            let resolved = resolve_resource_path(&resource, ctx.base_path)?;
+            let rows = load_csv_data(&resolved, ctx.max_remote_bytes).await?;
+            Ok(ChartData::Series(rows_to_chart_series(&rows)?))
+        }
+        loaded @ (ChartData::Inline(_) | ChartData::Series(_)) => Ok(loaded),
+    }
+}
+
+/// Convert CSV rows to chart series
+///
+/// The first column holds the x labels and every other column a series. A
+/// first row with a non-numeric value is the header, naming the x axis and
+/// the series; without one the series are numbered.
+fn rows_to_chart_series(rows: &[Vec<String>]) -> Result<ChartSeries, RenderError> {
+    let is_value = |cell: &String| cell.trim().parse::<f64>().is_ok();
+    let header = rows.first().filter(|row| !row.iter().skip(1).all(is_value));
+    let columns = rows.first().map_or(0, Vec::len);
+    if rows.is_empty() {
+        return Ok(ChartSeries {
+            x_title: None,
+            x_labels: Vec::new(),
+            x_axis: AxisScale::Categorical,
+            series: Vec::new(),
+        });
+    }
+    if columns < 2 {
+        return Err(RenderError::ChartError("Chart data row 1 needs a label and a value".to_string()));
+    }
+
+    let mut series: Vec<DataSeries> = (1..columns)
+        .map(|column| DataSeries {
+            name: match header {
+                Some(header) => header[column].trim().to_string(),
+                None => format!("Series {}", column),
+            },
+            values: Vec::with_capacity(rows.len()),
+        })
+        .collect();
+    let mut x_labels = Vec::with_capacity(rows.len());
+
+    let skip = usize::from(header.is_some());
+    for (index, row) in rows.iter().enumerate().skip(skip) {
+        if row.len() != columns {
+            return Err(RenderError::ChartError(format!(
+                "Chart data row {} has {} columns, expected {}",
+                index + 1,
+                row.len(),
+                columns
+            )));
+        }
+        for (series, value) in series.iter_mut().zip(&row[1..]) {
+            let parsed = value.trim().parse::<f64>().map_err(|_| {
+                RenderError::ChartError(format!(
+                    "Chart data row {} has a non-numeric value: {}",
+                    index + 1,
+                    value
+                ))
+            })?;
+            series.values.push(parsed);
+        }
+        x_labels.push(row[0].trim().to_string());
+    }
+
+    Ok(ChartSeries {
+        x_title: header.map(|header| header[0].trim().to_string()),
+        x_axis: AxisScale::infer(&x_labels),
+        x_labels,
+        series,
+    })
+}
+
+/// Replace `::audio` directives with players
+///
+/// Each file is copied into the audio output directory, or embedded when
+/// inlining. Problems that don't stop a file from playing, such as inline
+/// audio over `max_inline_size`, are reported as warnings; a file that
+/// can't be processed renders as an error box.
+async fn audio_pass(
+    nodes: Vec<DarkMatterNode>,
+    ctx: &PassContext<'_>,
+) -> Result<Vec<DarkMatterNode>, RenderError> {
+    if !nodes.iter().any(|node| matches!(node, DarkMatterNode::Audio { .. })) {
+        return Ok(nodes);
+    }
+
+    let (nodes, warnings) = process_audio_nodes_with_warnings(
+        &nodes,
+        &ctx.audio.output_dir,
+        ctx.cache,
+        ctx.audio.inline_mode,
+        ctx.base_path,
+        &ctx.audio.config,
+        ctx.project_root,
+    )
+    .await?;
+    ctx.warn(warnings);
+    Ok(nodes)
+}
+
+/// Merge adjacent text nodes and drop empty ones
+fn final_pass(nodes: Vec<DarkMatterNode>) -> Result<Vec<DarkMatterNode>, RenderError> {
+    let mut result: Vec<DarkMatterNode> = Vec::with_capacity(nodes.len());
+
+    for node in nodes {
+        match (result.last_mut(), node) {
+            (_, DarkMatterNode::Text(text)) if text.is_empty() => {}
+            (Some(DarkMatterNode::Text(previous)), DarkMatterNode::Text(text)) => {
+                previous.push_str(&text);
+            }
+            (_, node) => result.push(node),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::init_memory_database;
+    use crate::http::DEFAULT_MAX_REMOTE_BYTES;
+    use crate::types::{PieChartOptions, Resource};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn text(s: &str) -> DarkMatterNode {
+        DarkMatterNode::Text(s.to_string())
+    }
+
+    fn as_text(node: &DarkMatterNode) -> &str {
+        match node {
+            DarkMatterNode::Text(text) => text,
+            other => panic!("expected text node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_default_pass_order() {
+        assert_eq!(
+            RenderPass::default_passes(),
+            vec![
+                RenderPass::Transclusion,
+                RenderPass::AiOperations,
+                RenderPass::Interpolation,
+                RenderPass::Charts,
+                RenderPass::Audio,
+                RenderPass::Final,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_final_pass_merges_text() {
+        let nodes = vec![
+            text("Hello, "),
+            text(""),
+            text("world"),
+            DarkMatterNode::Interpolation { variable: "x".to_string() },
+            text("!"),
+        ];
+
+        let result = final_pass(nodes).unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(as_text(&result[0]), "Hello, world");
+        assert_eq!(as_text(&result[2]), "!");
+    }
+
+    fn csv_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn test_rows_to_chart_series_skips_heading() {
+        let rows = csv_rows(&[&["Region", "Sales"], &["North", " 12.5"], &["South", "7"]]);
+
+        let data = rows_to_chart_series(&rows).unwrap();
+        assert_eq!(data.x_title.as_deref(), Some("Region"));
+        assert_eq!(data.x_labels, vec!["North", "South"]);
+        assert_eq!(data.x_axis, AxisScale::Categorical);
+        assert_eq!(data.series[0].name, "Sales");
+        assert_eq!(data.series[0].values, vec![12.5, 7.0]);
+    }
+
+    #[test]
+    fn test_rows_to_chart_series_reads_every_column() {
+        let rows = csv_rows(&[&["Year", "Revenue", "Costs"], &["2023", "10", "4"], &["2024", "12", "5"]]);
+
+        let data = rows_to_chart_series(&rows).unwrap();
+        assert_eq!(data.x_axis, AxisScale::Numeric);
+        let names: Vec<&str> = data.series.iter().map(|series| series.name.as_str()).collect();
+        assert_eq!(names, vec!["Revenue", "Costs"]);
+        assert_eq!(data.series[1].values, vec![4.0, 5.0]);
+
+        // Without a header the series are numbered
+        let data = rows_to_chart_series(&rows[1..]).unwrap();
+        assert_eq!(data.x_title, None);
+        assert_eq!(data.series[1].name, "Series 2");
+    }
+
+    #[test]
+    fn test_rows_to_chart_series_rejects_bad_rows() {
+        let bad_value = csv_rows(&[&["North", "12"], &["South", "lots"]]);
+        let short_row = csv_rows(&[&["Year", "Revenue", "Costs"], &["2023", "10"]]);
+
+        for rows in [bad_value, short_row] {
+            assert!(matches!(rows_to_chart_series(&rows), Err(RenderError::ChartError(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interpolation_sees_transcluded_content() {
+        let mut included = NamedTempFile::new().unwrap();
+        write!(included, "Hello {{{{name}}}}").unwrap();
+
+        let db = init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let mut frontmatter = Frontmatter::new();
+        frontmatter
+            .custom
+            .insert("name".to_string(), serde_json::json!("Composition"));
+        let ctx = PassContext {
+            frontmatter: &frontmatter,
+            cache: &cache,
+            base_path: None,
+            ai_queue: None,
+            document_hash: ResourceHash(0),
+            allowed_env_vars: &[],
+            max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            audio: &AudioBatchOptions::default(),
+            project_root: None,
+            report: &Mutex::default(),
+        };
+
+        let mut nodes = vec![DarkMatterNode::File {
+            resource: Resource::local(included.path().to_path_buf()),
+            range: None,
+        }];
+        for pass in RenderPass::default_passes() {
+            nodes = run_pass(pass, nodes, &ctx).await.unwrap();
+        }
+
+        let html = crate::render::to_html(&nodes).unwrap();
+        assert!(html.contains("Hello Composition"), "{}", html);
+    }
+
+    #[tokio::test]
+    async fn test_ai_operations_pass_uses_queue() {
+        use crate::ai::MockCompletionModel;
+        use std::sync::Arc;
+
+        let mut notes = NamedTempFile::new().unwrap();
+        write!(notes, "Notes to summarize").unwrap();
+
+        let db = init_memory_database().await.unwrap();
+        crate::cache::apply_schema(&db).await.unwrap();
+        let cache = Arc::new(CacheOperations::new(db));
+        let model = Arc::new(MockCompletionModel::new(vec!["**Summary**".to_string()]));
+        let queue = AsyncAIQueue::new(model, Arc::clone(&cache), 2, Duration::from_secs(1));
+        let frontmatter = Frontmatter::new();
+        let ctx = PassContext {
+            frontmatter: &frontmatter,
+            cache: &cache,
+            base_path: None,
+            ai_queue: Some(&queue),
+            document_hash: ResourceHash(0),
+            allowed_env_vars: &[],
+            max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            audio: &AudioBatchOptions::default(),
+            project_root: None,
+            report: &Mutex::default(),
+        };
+
+        let nodes = vec![
+            text("Before"),
+            DarkMatterNode::Summarize {
+                resource: Resource::local(notes.path().to_path_buf()),
+            },
+            text("After"),
+        ];
+        let result = run_pass(RenderPass::AiOperations, nodes, &ctx).await.unwrap();
+
+        assert_eq!(result.len(), 3);
+        match &result[1] {
+            DarkMatterNode::Markdown(content) => assert_eq!(content.raw, "**Summary**"),
+            other => panic!("expected markdown, got {:?}", other),
+        }
+        assert_eq!(as_text(&result[2]), "After");
+    }
+
+    #[tokio::test]
+    async fn test_ai_operations_pass_uses_frontmatter_model() {
+        use crate::ai::{MockCompletionModel, ModelRegistry};
+        use std::sync::Arc;
+
+        let mut notes = NamedTempFile::new().unwrap();
+        write!(notes, "Notes to summarize").unwrap();
+
+        let openai = MockCompletionModel::new(vec!["from gpt".to_string()]).with_name("gpt-4o-mini");
+        let anthropic =
+            MockCompletionModel::new(vec!["from claude".to_string()]).with_name("claude-3-haiku");
+        let mut registry = ModelRegistry::new();
+        registry
+            .register("gpt-", Box::new(openai.clone()))
+            .register("claude-", Box::new(anthropic.clone()));
+
+        let db = init_memory_database().await.unwrap();
+        crate::cache::apply_schema(&db).await.unwrap();
+        let cache = Arc::new(CacheOperations::new(db));
+        let queue = AsyncAIQueue::with_registry(
+            Arc::new(registry.with_default("gpt-4o-mini")),
+            Arc::clone(&cache),
+            2,
+            Duration::from_secs(1),
+        );
+
+        let summarize = |model: Option<&str>| {
+            let mut frontmatter = Frontmatter::new();
+            if let Some(model) = model {
+                frontmatter.custom.insert("model".to_string(), model.into());
+            }
+            let nodes = vec![DarkMatterNode::Summarize {
+                resource: Resource::local(notes.path().to_path_buf()),
+            }];
+            let queue = &queue;
+            let cache = &cache;
+            async move {
+                let ctx = PassContext {
+                    frontmatter: &frontmatter,
+                    cache,
+                    base_path: None,
+                    ai_queue: Some(queue),
+                    document_hash: ResourceHash(0),
+                    allowed_env_vars: &[],
+                    max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+                    language: None,
+                    audio: &AudioBatchOptions::default(),
+            project_root: None,
+                    report: &Mutex::default(),
+                };
+                run_pass(RenderPass::AiOperations, nodes, &ctx).await
+            }
+        };
+
+        let result = summarize(Some("claude-3-haiku")).await.unwrap();
+        match &result[0] {
+            DarkMatterNode::Markdown(content) => assert_eq!(content.raw, "from claude"),
+            other => panic!("expected markdown, got {:?}", other),
+        }
+        assert_eq!((openai.call_count(), anthropic.call_count()), (0, 1));
+
+        // No model in frontmatter falls back to the registry default
+        let result = summarize(None).await.unwrap();
+        match &result[0] {
+            DarkMatterNode::Markdown(content) => assert_eq!(content.raw, "from gpt"),
+            other => panic!("expected markdown, got {:?}", other),
+        }
+        assert_eq!((openai.call_count(), anthropic.call_count()), (1, 1));
+
+        let error = summarize(Some("llama-3")).await.unwrap_err();
+        assert!(error.to_string().contains("Model not found: llama-3"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_charts_pass_inlines_external_data() {
+        let mut csv = NamedTempFile::new().unwrap();
+        writeln!(csv, "label,value\nA,1\nB,2").unwrap();
+
+        let db = init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let frontmatter = Frontmatter::new();
+        let ctx = PassContext {
+            frontmatter: &frontmatter,
+            cache: &cache,
+            base_path: None,
+            ai_queue: None,
+            document_hash: ResourceHash(0),
+            allowed_env_vars: &[],
+            max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            audio: &AudioBatchOptions::default(),
+            project_root: None,
+            report: &Mutex::default(),
+        };
+
+        let nodes = vec![DarkMatterNode::BarChart {
+            data: ChartData::External(Resource::local(csv.path().to_path_buf())),
+            with_table: false,
+        }];
+        let result = run_pass(RenderPass::Charts, nodes, &ctx).await.unwrap();
+
+        match &result[0] {
+            DarkMatterNode::BarChart { data: ChartData::Series(data), .. } => {
+                assert_eq!(data.x_labels, vec!["A", "B"]);
+                assert_eq!(data.series.len(), 1);
+                assert_eq!(data.series[0].name, "value");
+                assert_eq!(data.series[0].values, vec![1.0, 2.0]);
+            }
+            other => panic!("expected inline bar chart, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_series_csv_chart_has_legend() {
+        use crate::render::charts::{render_bar_chart, render_line_chart};
+
+        let mut csv = NamedTempFile::new().unwrap();
+        writeln!(csv, "Month,Revenue,Costs,Profit\nJan,10,6,4\nFeb,12,7,5\nMar,15,8,7").unwrap();
+
+        let db = init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let frontmatter = Frontmatter::new();
+        let ctx = PassContext {
+            frontmatter: &frontmatter,
+            cache: &cache,
+            base_path: None,
+            ai_queue: None,
+            document_hash: ResourceHash(0),
+            allowed_env_vars: &[],
+            max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            audio: &AudioBatchOptions::default(),
+            project_root: None,
+            report: &Mutex::default(),
+        };
+
+        let nodes = vec![DarkMatterNode::LineChart {
+            data: ChartData::External(Resource::local(csv.path().to_path_buf())),
+            with_table: false,
+        }];
+        let result = run_pass(RenderPass::Charts, nodes, &ctx).await.unwrap();
+        let DarkMatterNode::LineChart { data, .. } = &result[0] else {
+            panic!("expected a line chart, got {:?}", result[0]);
+        };
+
+        for svg in [render_line_chart(data, 800, 400).unwrap(), render_bar_chart(data, 800, 400).unwrap()] {
+            let legend = svg.split(r#"<g class="legend">"#).nth(1).expect("chart has a legend");
+            for name in ["Revenue", "Costs", "Profit"] {
+                assert!(legend.contains(&format!(r#"class="legend-label">{}</text>"#, name)), "{}", svg);
+            }
+            assert!(svg.contains(r#"class="axis-title">Month</text>"#));
+            assert!(svg.contains(r#"class="label">Feb</text>"#));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_charts_pass_chart_tables_default() {
+        let db = init_memory_database().await.unwrap();
+        let cache = CacheOperations::new(db);
+        let mut frontmatter = Frontmatter::new();
+        frontmatter.custom.insert("chart_tables".to_string(), true.into());
+        let ctx = PassContext {
+            frontmatter: &frontmatter,
+            cache: &cache,
+            base_path: None,
+            ai_queue: None,
+            document_hash: ResourceHash(0),
+            allowed_env_vars: &[],
+            max_remote_bytes: DEFAULT_MAX_REMOTE_BYTES,
+            language: None,
+            audio: &AudioBatchOptions::default(),
+            project_root: None,
+            report: &Mutex::default(),
+        };
+
+        let options = PieChartOptions { inner_radius: 0.5, show_percentages: true };
+        let nodes = vec![DarkMatterNode::PieChart { data: ChartData::Inline(Vec::new()), with_table: false, options }];
+        let result = run_pass(RenderPass::Charts, nodes, &ctx).await.unwrap();
+
+        assert!(matches!(result[0], DarkMatterNode::PieChart { with_table: true, options: kept, .. } if kept == options));
+    }
+}
@@ -0,0 +1,220 @@
+use ammonia::Builder;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+/// Tags used by the crate's components, on top of ammonia's defaults
+const COMPONENT_TAGS: &[&str] = &[
+    "audio", "source", "track", "picture", "iframe", "button", "input",
+    "svg", "g", "path", "circle", "rect", "line", "polyline", "polygon", "text", "title", "desc",
+];
+
+/// SVG elements that take presentation attributes
+const SVG_TAGS: &[&str] = &["svg", "g", "path", "circle", "rect", "line", "polyline", "polygon", "text"];
+
+/// Presentation attributes used by charts and icons
+const SVG_ATTRIBUTES: &[&str] = &[
+    "viewBox", "xmlns", "width", "height", "d", "x", "y", "x1", "y1", "x2", "y2",
+    "cx", "cy", "r", "points", "transform", "fill", "fill-opacity", "stroke",
+    "stroke-width", "stroke-linecap", "stroke-linejoin", "text-anchor", "dominant-baseline", "font-size",
+];
+
+/// Attributes allowed on every tag
+const GENERIC_ATTRIBUTES: &[&str] = &[
+    "class", "id", "role", "aria-label", "aria-labelledby", "aria-describedby", "aria-hidden",
+];
+
+/// CSS properties an aspect ratio or chart container may set in its `style`
+const CONTAINER_STYLE_PROPERTIES: &[&str] = &["position", "top", "left", "width", "height", "padding-bottom", "overflow"];
+
+/// Embed URLs an iframe may load; any other iframe loses its `src`
+const IFRAME_ORIGINS: &[&str] = &[
+    "https://www.youtube.com/embed/",
+    "https://www.youtube-nocookie.com/embed/",
+];
+
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_tags(COMPONENT_TAGS)
+        .add_generic_attributes(GENERIC_ATTRIBUTES)
+        .add_generic_attribute_prefixes(&["data-"])
+        .add_tag_attributes("audio", &["controls", "preload"])
+        .add_tag_attributes("source", &["src", "srcset", "sizes", "type", "media"])
+        .add_tag_attributes("track", &["kind", "src", "srclang", "label", "default"])
+        .add_tag_attributes("img", &["srcset", "sizes", "loading", "decoding"])
+        .add_tag_attributes("iframe", &["src", "frameborder", "allow", "allowfullscreen"])
+        // Aspect ratio and chart containers; see container_style
+        .add_tag_attributes("div", &["style"])
+        .add_tag_attributes("details", &["open"])
+        .add_tag_attributes("button", &["type"])
+        // Task list checkboxes; other input types lose their type in filter_attribute
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        // Inline audio and images are data URIs; see filter_attribute
+        .add_url_schemes(&["data"])
+        .attribute_filter(filter_attribute);
+    for tag in SVG_TAGS {
+        builder.add_tag_attributes(tag, SVG_ATTRIBUTES);
+    }
+    builder
+});
+
+/// Remove unsafe markup from rendered HTML
+///
+/// Uses an allowlist: scripts, event handlers and unknown tags are stripped,
+/// while the markup of the crate's own components (YouTube containers, audio
+/// players, pictures, charts, task list checkboxes and the layout blocks) is
+/// kept. Inputs may only be checkboxes, iframes may only load YouTube embeds,
+/// and data URIs are only allowed as the `src` of images, audio and caption
+/// tracks. Only `div`s keep a `style`, and only the layout properties of
+/// the containers.
+pub fn sanitize_html(html: &str) -> String {
+    SANITIZER.clean(html).to_string()
+}
+
+/// Narrow the URLs that ammonia's scheme check lets through
+fn filter_attribute<'u>(element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+    let is_data_uri = value
+        .trim_start()
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"));
+
+    match (element, attribute) {
+        ("div", "style") => container_style(value),
+        ("input", "type") if !value.trim().eq_ignore_ascii_case("checkbox") => None,
+        ("iframe", "src") if !IFRAME_ORIGINS.iter().any(|origin| value.starts_with(origin)) => None,
+        ("img" | "source", "src") if is_data_uri => {
+            let media = value.trim_start()[5..].to_ascii_lowercase();
+            (media.starts_with("image/") || media.starts_with("audio/")).then_some(Cow::Borrowed(value))
+        }
+        // Inline captions
+        ("track", "src") if is_data_uri => {
+            value.trim_start()[5..].to_ascii_lowercase().starts_with("text/vtt").then_some(Cow::Borrowed(value))
+        }
+        _ if is_data_uri => None,
+        _ => Some(Cow::Borrowed(value)),
+    }
+}
+
+/// The declarations of a container's `style` that set a property of
+/// [`CONTAINER_STYLE_PROPERTIES`] to a plain length or keyword
+///
+/// `calc()` is the only function allowed, and `position` can't take an
+/// element out of its container.
+fn container_style(style: &str) -> Option<Cow<'_, str>> {
+    let plain = |property: &str, value: &str| {
+        let value = value.to_ascii_lowercase();
+        match property {
+            "position" => value == "relative" || value == "absolute",
+            _ => {
+                !value.is_empty()
+                    && value.chars().all(|c| c.is_ascii_alphanumeric() || " .%()/*+-".contains(c))
+                    && !value.replace("calc(", "").contains('(')
+            }
+        }
+    };
+    let kept: Vec<String> = style
+        .split(';')
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            let (property, value) = (property.trim().to_ascii_lowercase(), value.trim());
+            (CONTAINER_STYLE_PROPERTIES.contains(&property.as_str()) && plain(&property, value))
+                .then(|| format!("{}: {};", property, value))
+        })
+        .collect();
+
+    match kept.join(" ") {
+        kept if kept.is_empty() => None,
+        kept if kept == style => Some(Cow::Borrowed(style)),
+        kept => Some(Cow::Owned(kept)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_scripts_and_handlers() {
+        let html = sanitize_html(r#"<p onclick="steal()">Hi</p><script>alert(1)</script>"#);
+
+        assert_eq!(html, "<p>Hi</p>");
+    }
+
+    #[test]
+    fn test_sanitize_keeps_component_markup() {
+        let components = [
+            r#"<div class="audio-player"><audio controls="" preload="metadata"><source src="audio/abc.mp3" type="audio/mpeg"></audio></div>"#,
+            r#"<picture><source type="image/webp" srcset="img/a-400.webp 400w" sizes="100vw"><img src="img/a.jpg" alt="A" loading="lazy" decoding="async"></picture>"#,
+            r##"<svg viewBox="0 0 800 400" xmlns="http://www.w3.org/2000/svg" class="composition-bar-chart" role="img" aria-labelledby="c-title" aria-describedby="c-desc"><title id="c-title">Bar chart</title><desc id="c-desc">1 series of 1 value.</desc><rect x="10" y="20" width="30" height="40" fill="#3b82f6"></rect><text x="5" y="5" text-anchor="middle" dominant-baseline="middle">50%</text></svg>"##,
+            r#"<details class="composition-disclosure" open=""><summary>More</summary></details>"#,
+        ];
+
+        for component in components {
+            assert_eq!(sanitize_html(component), component);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_keeps_container_layout_styles_only() {
+        let html = crate::render::render_aspect_ratio(16, 9, &[]).unwrap();
+        let hostile = sanitize_html(
+            r#"<div style="position: fixed; top: 0; background: url(https://evil.example/x.png); width: expression(alert(1)); height: calc(9 / 16 * 100%)">x</div><p style="color: red">y</p>"#,
+        );
+
+        assert_eq!(sanitize_html(&html), html);
+        assert_eq!(hostile, r#"<div style="top: 0; height: calc(9 / 16 * 100%);">x</div><p>y</p>"#);
+        assert_eq!(sanitize_html(r#"<div style="color: red">z</div>"#), "<div>z</div>");
+    }
+
+    #[test]
+    fn test_sanitize_keeps_caption_tracks() {
+        let track = r#"<audio controls=""><source src="audio/abc.mp3" type="audio/mpeg"><track kind="captions" src="audio/def.vtt" srclang="en" default=""></audio>"#;
+        let inline = sanitize_html(r#"<audio><track kind="captions" src="data:text/vtt;base64,V0VCVlRU" srclang="en"></audio>"#);
+
+        assert_eq!(sanitize_html(track), track);
+        assert!(inline.contains(r#"src="data:text/vtt;base64,V0VCVlRU""#), "{}", inline);
+    }
+
+    #[test]
+    fn test_sanitize_keeps_task_list_checkboxes() {
+        use crate::render::render_data_list;
+        use crate::types::ListItem;
+
+        let items = [
+            ListItem { text: "Write".to_string(), checked: true },
+            ListItem { text: "Ship".to_string(), checked: false },
+        ];
+        let html = sanitize_html(&render_data_list(&items, false, true));
+
+        assert!(html.contains(r#"<input type="checkbox" disabled="" checked=""> Write"#), "{}", html);
+        assert!(html.contains(r#"<input type="checkbox" disabled=""> Ship"#), "{}", html);
+        assert_eq!(sanitize_html(r#"<input type="password" name="pin">"#), "<input>");
+    }
+
+    #[test]
+    fn test_sanitize_keeps_audio_chapter_starts() {
+        let html = sanitize_html(r##"<ol class="audio-chapters"><li><a href="#" data-start="90.5">Q&amp;A</a></li></ol>"##);
+
+        assert!(html.contains(r#"data-start="90.5""#), "{}", html);
+    }
+
+    #[test]
+    fn test_sanitize_only_allows_youtube_iframes() {
+        let youtube = sanitize_html(r#"<iframe class="dm-youtube-player" src="https://www.youtube.com/embed/abc?enablejsapi=1"></iframe>"#);
+        let other = sanitize_html(r#"<iframe src="https://evil.example/embed"></iframe>"#);
+
+        assert!(youtube.contains(r#"src="https://www.youtube.com/embed/abc?enablejsapi=1""#));
+        assert!(!other.contains("evil.example"));
+    }
+
+    #[test]
+    fn test_sanitize_limits_data_uris_to_media() {
+        let audio = sanitize_html(r#"<audio><source src="data:audio/mpeg;base64,AAAA" type="audio/mpeg"></audio>"#);
+        let link = sanitize_html(r#"<a href="data:text/html;base64,PHNjcmlwdD4=">x</a>"#);
+        let image = sanitize_html(r#"<img src="DATA:text/html,<script>alert(1)</script>">"#);
+
+        assert!(audio.contains(r#"src="data:audio/mpeg;base64,AAAA""#));
+        assert!(!link.contains("data:"));
+        assert!(!image.contains("text/html"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Heading sections of rendered documents
+//!
+//! A section is a heading and everything after it up to the next heading of
+//! the same or a higher level, found by the heading's slug. Headings only
+//! exist in markdown, so the nodes between them, such as a table, go with
+//! whichever section they fall in.
+
+use crate::types::{DarkMatterNode, MarkdownContent};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Anchor slug of a heading's text, as GitHub makes them: lowercased, with
+/// spaces turned into `-` and other punctuation dropped
+pub(crate) fn heading_slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A heading found in a markdown chunk
+pub(crate) struct MarkdownHeading {
+    /// Byte offset of the heading's first line
+    pub offset: usize,
+    pub level: u8,
+    /// The heading's text, without markup
+    pub text: String,
+    /// The explicit `{#id}`, or else the [`heading_slug`] of the text
+    pub slug: String,
+}
+
+/// Headings in a markdown chunk, in order
+pub(crate) fn markdown_headings(raw: &str) -> Vec<MarkdownHeading> {
+    let mut found = Vec::new();
+    let mut current: Option<(usize, u8, Option<String>)> = None;
+    let mut text = String::new();
+
+    for (event, range) in Parser::new_ext(raw, Options::ENABLE_HEADING_ATTRIBUTES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                current = Some((range.start, level as u8, id.map(|id| id.to_string())));
+                text.clear();
+            }
+            Event::Text(t) | Event::Code(t) if current.is_some() => text.push_str(&t),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((offset, level, id)) = current.take() {
+                    let text = text.trim().to_string();
+                    let slug = id.unwrap_or_else(|| heading_slug(&text));
+                    found.push(MarkdownHeading { offset, level, text, slug });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    found
+}
+
+/// The section under the heading whose slug is `slug` (a leading `#` is
+/// ignored), heading included; `None` when no heading has that slug
+pub(crate) fn extract_section(nodes: &[DarkMatterNode], slug: &str) -> Option<Vec<DarkMatterNode>> {
+    let slug = slug.trim_start_matches('#');
+    let mut section = Vec::new();
+    // Level of the selected heading, once found
+    let mut level: Option<u8> = None;
+
+    for node in nodes {
+        let DarkMatterNode::Markdown(content) = node else {
+            if level.is_some() {
+                section.push(node.clone());
+            }
+            continue;
+        };
+
+        let headings = markdown_headings(&content.raw);
+        let (start, selected) = match level {
+            Some(selected) => (0, selected),
+            None => match headings.iter().find(|heading| heading.slug == slug) {
+                Some(heading) => (heading.offset, heading.level),
+                None => continue,
+            },
+        };
+        level = Some(selected);
+
+        let end = headings
+            .iter()
+            .find(|heading| heading.offset > start && heading.level <= selected)
+            .map(|heading| heading.offset);
+        section.push(DarkMatterNode::Markdown(MarkdownContent {
+            raw: content.raw[start..end.unwrap_or(content.raw.len())].to_string(),
+            frontmatter: content.frontmatter.clone(),
+        }));
+        if end.is_some() {
+            break;
+        }
+    }
+
+    level.map(|_| section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(raw: &str) -> DarkMatterNode {
+        DarkMatterNode::Markdown(MarkdownContent { raw: raw.to_string(), frontmatter: None })
+    }
+
+    fn raw(nodes: &[DarkMatterNode]) -> Vec<&str> {
+        nodes
+            .iter()
+            .map(|node| match node {
+                DarkMatterNode::Markdown(content) => content.raw.as_str(),
+                DarkMatterNode::Text(text) => text.as_str(),
+                other => panic!("unexpected node {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_heading_slug() {
+        assert_eq!(heading_slug("Getting Started"), "getting-started");
+        assert_eq!(heading_slug(" What's new in v2.0? "), "whats-new-in-v20");
+        assert_eq!(heading_slug("snake_case-and-dashes"), "snake_case-and-dashes");
+    }
+
+    #[test]
+    fn test_markdown_headings() {
+        let headings = markdown_headings("# The `lib` Crate\n\ntext\n\n## *Setup* {#install}\n\n```\n# comment\n```\n");
+        let found: Vec<_> = headings.iter().map(|h| (h.level, h.text.as_str(), h.slug.as_str())).collect();
+        assert_eq!(found, vec![(1, "The lib Crate", "the-lib-crate"), (2, "Setup", "install")]);
+        assert_eq!(headings[1].offset, 25);
+    }
+
+    #[test]
+    fn test_extract_section_stops_at_same_level() {
+        let nodes = vec![markdown("# Intro\n\nHello\n\n## Setup\n\nInstall it\n\n### Linux\n\napt\n\n## Usage\n\nRun it\n")];
+
+        let section = extract_section(&nodes, "setup").unwrap();
+        assert_eq!(raw(&section), vec!["## Setup\n\nInstall it\n\n### Linux\n\napt\n\n"]);
+        assert_eq!(raw(&extract_section(&nodes, "#usage").unwrap()), vec!["## Usage\n\nRun it\n"]);
+        assert!(extract_section(&nodes, "missing").is_none());
+    }
+
+    #[test]
+    fn test_extract_section_spans_nodes() {
+        let nodes = vec![
+            markdown("## One\n\nfirst\n"),
+            DarkMatterNode::Text("between".to_string()),
+            markdown("more\n\n## Two {#second}\n\nsecond\n"),
+        ];
+
+        assert_eq!(raw(&extract_section(&nodes, "one").unwrap()), vec!["## One\n\nfirst\n", "between", "more\n\n"]);
+        assert_eq!(raw(&extract_section(&nodes, "second").unwrap()), vec!["## Two {#second}\n\nsecond\n"]);
+    }
+
+    #[test]
+    fn test_extract_section_ignores_code_blocks() {
+        let nodes = vec![markdown("## Shell\n\n```sh\n# not a heading\n```\n\nafter\n")];
+        assert_eq!(raw(&extract_section(&nodes, "shell").unwrap()), vec!["## Shell\n\n```sh\n# not a heading\n```\n\nafter\n"]);
+        assert!(extract_section(&nodes, "not-a-heading").is_none());
+    }
+}
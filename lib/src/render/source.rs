@@ -0,0 +1,524 @@
+//! DarkMatter Markdown source of parsed documents
+//!
+//! The reverse of parsing: each node is written in the syntax it is parsed
+//! from, so a document edited as nodes, for example by an AI pass, can be
+//! saved as a Markdown file again. Nodes are written as they are, unresolved,
+//! so `::summarize` and the other AI directives stay directives.
+
+use crate::error::RenderError;
+use crate::parse::darkmatter::{
+    is_footnote_id, parse_terminal_line, COLUMN_BREAK, DETAILS, DIFF_CONTEXT_LINES, END_ASPECT_RATIO, END_BLOCK,
+    END_TERMINAL, MATH_DELIMITER, MERMAID, SUMMARY,
+};
+use crate::parse::frontmatter_to_yaml;
+use crate::types::{
+    Breakpoint, ChartData, DarkMatterNode, Frontmatter, LineRange, ListSource, Resource, ResourceRequirement,
+    ResourceSource, TableSource, TerminalLine, TerminalShell,
+};
+use url::Url;
+
+/// Write frontmatter and nodes as the DarkMatter Markdown they parse from
+///
+/// The frontmatter is written as YAML between `---` lines, and left out when
+/// it is empty. Fails with [`RenderError::MarkdownFailed`] for a node that
+/// has no source syntax, such as a chart holding data loaded from its file
+/// or a transclusion of an in-memory resource.
+pub(crate) fn write_markdown(frontmatter: &Frontmatter, nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
+    let body = write_nodes(nodes)?;
+
+    let mut markdown = String::new();
+    if !frontmatter.is_empty() {
+        markdown.push_str(&frontmatter_to_yaml(frontmatter));
+        markdown.push_str("\n---\n");
+    } else if ["---", "+++", ";;;"].iter().any(|fence| body.starts_with(fence)) {
+        // Keep a leading rule from being read as the opening fence
+        markdown.push_str("---\n---\n");
+    }
+    if !body.is_empty() {
+        markdown.push_str(&body);
+        markdown.push('\n');
+    }
+    Ok(markdown)
+}
+
+/// Write a run of nodes, each block on lines of its own and inline nodes
+/// run together with the text around them
+fn write_nodes(nodes: &[DarkMatterNode]) -> Result<String, RenderError> {
+    let mut markdown = String::new();
+    let mut previous: Option<&DarkMatterNode> = None;
+    for node in nodes {
+        if let Some(previous) = previous {
+            let flows = |node: &DarkMatterNode| is_inline(node) || matches!(node, DarkMatterNode::Markdown(_));
+            let joined = flows(previous) && flows(node) && (is_inline(previous) || is_inline(node));
+            if !joined {
+                markdown.push('\n');
+            }
+        }
+        markdown.push_str(&write_node(node)?);
+        previous = Some(node);
+    }
+    Ok(markdown)
+}
+
+/// Whether a node is written inside a line of text rather than on its own
+fn is_inline(node: &DarkMatterNode) -> bool {
+    match node {
+        DarkMatterNode::Text(_)
+        | DarkMatterNode::Interpolation { .. }
+        | DarkMatterNode::Popover { .. }
+        | DarkMatterNode::FootnoteRef { .. } => true,
+        DarkMatterNode::Math { display, .. } => !display,
+        DarkMatterNode::Footnote { id, .. } => id.is_empty(),
+        _ => false,
+    }
+}
+
+fn write_node(node: &DarkMatterNode) -> Result<String, RenderError> {
+    Ok(match node {
+        DarkMatterNode::Markdown(content) => content.raw.clone(),
+        // An escaped `\$` is parsed into a text node of its own
+        DarkMatterNode::Text(text) if text == "$" => "\\$".to_string(),
+        DarkMatterNode::Text(text) => text.clone(),
+        DarkMatterNode::Interpolation { variable } => format!("{{{{{}}}}}", variable),
+
+        DarkMatterNode::File { resource, range } => {
+            let suffix = requirement_suffix(resource);
+            let path = match range {
+                Some(LineRange::Named(region)) => {
+                    if !matches!(resource.source, ResourceSource::Local(_)) {
+                        return Err(unwritable(format!("region '{}' of {}", region, resource.label())));
+                    }
+                    argument(&format!("{}#{}{}", reference(resource)?, region, suffix))?
+                }
+                _ => argument(&format!("{}{}", reference(resource)?, suffix))?,
+            };
+            match range {
+                Some(LineRange::Lines(start, end)) => {
+                    let end = end.map_or(String::new(), |end| end.to_string());
+                    format!("::file {} {}-{}", path, start, end)
+                }
+                _ => format!("::file {}", path),
+            }
+        }
+
+        DarkMatterNode::Summarize { resource } => format!("::summarize {}", resource_argument(resource)?),
+        DarkMatterNode::Consolidate { resources } => {
+            if resources.is_empty() {
+                return Err(unwritable("::consolidate without resources"));
+            }
+            format!("::consolidate {}", resource_arguments(resources)?)
+        }
+        DarkMatterNode::Topic { topic, resources, review } => {
+            if resources.is_empty() {
+                return Err(unwritable("::topic without resources"));
+            }
+            let review = if *review { " --review" } else { "" };
+            format!("::topic {} {}{}", quoted(topic)?, resource_arguments(resources)?, review)
+        }
+
+        DarkMatterNode::Table { source, has_heading } => {
+            // The path of `::table` is read as it is, quotes and all
+            let mut line = "::table".to_string();
+            match source {
+                TableSource::External(resource) => {
+                    line.push(' ');
+                    line.push_str(&reference(resource)?);
+                    line.push_str(requirement_suffix(resource));
+                }
+                TableSource::Inline(rows) if rows.is_empty() && *has_heading => {}
+                TableSource::Inline(_) => return Err(unwritable("an inline table")),
+            }
+            if *has_heading {
+                line.push_str(" --with-heading-row");
+            }
+            line
+        }
+        DarkMatterNode::BarChart { data, with_table } => write_chart("bar-chart", data, *with_table)?,
+        DarkMatterNode::LineChart { data, with_table } => write_chart("line-chart", data, *with_table)?,
+        DarkMatterNode::PieChart { data, with_table, options } => {
+            let mut line = write_chart("pie-chart", data, *with_table)?;
+            if options.inner_radius > 0.0 {
+                line.push_str(&format!(" --inner-radius {}", options.inner_radius));
+            }
+            if options.show_percentages {
+                line.push_str(" --show-percentages");
+            }
+            line
+        }
+        DarkMatterNode::AreaChart { data, with_table } => write_chart("area-chart", data, *with_table)?,
+        DarkMatterNode::BubbleChart { data, with_table } => write_chart("bubble-chart", data, *with_table)?,
+        DarkMatterNode::EnvTable { filter, sort, show_values } => {
+            let mut line = "::include-env".to_string();
+            if let Some(filter) = filter {
+                line.push_str(" --filter ");
+                line.push_str(&quoted(filter)?);
+            }
+            if *show_values {
+                line.push_str(" --show-values true");
+            }
+            if *sort {
+                line.push_str(" --sort");
+            }
+            line
+        }
+        DarkMatterNode::RequireEnv { vars } => format!("::require-env {}", vars.join(" ")),
+        DarkMatterNode::DataList { source, ordered, task_list } => {
+            let ListSource::External { resource, columns } = source else {
+                return Err(unwritable("an inline list"));
+            };
+            let mut line = format!("::list {}", resource_argument(resource)?);
+            for column in columns {
+                line.push_str(" --column ");
+                line.push_str(&argument(column)?);
+            }
+            if *ordered {
+                line.push_str(" --ordered");
+            }
+            if *task_list {
+                line.push_str(" --as tasklist");
+            }
+            line
+        }
+
+        DarkMatterNode::Popover { trigger, content } => {
+            let DarkMatterNode::Text(trigger) = trigger.as_ref() else {
+                return Err(unwritable("a popover triggered by anything but text"));
+            };
+            let content = write_nodes(content)?;
+            if trigger.is_empty() || trigger.contains([']', '\n']) || content.is_empty() || content.contains([')', '\n']) {
+                return Err(unwritable(format!("popover '{}'", trigger)));
+            }
+            format!("[{}](popover:{})", trigger, content)
+        }
+        DarkMatterNode::Columns { breakpoints, sections } => {
+            let counts: Vec<String> = Breakpoint::ALL
+                .iter()
+                .filter_map(|breakpoint| breakpoints.get(breakpoint).map(|count| format!("{}: {}", breakpoint, count)))
+                .collect();
+            if counts.len() < breakpoints.len() {
+                return Err(unwritable("::columns with a custom breakpoint"));
+            }
+            let opener = match counts.is_empty() {
+                true => "::columns".to_string(),
+                false => format!("::columns {}", counts.join(", ")),
+            };
+            let parts: Vec<&[DarkMatterNode]> = sections.iter().map(Vec::as_slice).collect();
+            write_block(opener, &parts, COLUMN_BREAK, END_BLOCK)?
+        }
+        DarkMatterNode::Disclosure { summary, details } => {
+            write_block(SUMMARY.to_string(), &[summary, details], DETAILS, END_BLOCK)?
+        }
+        DarkMatterNode::AspectRatio { numerator, denominator, content } => write_block(
+            format!("::aspect-ratio {}:{}", numerator, denominator),
+            &[content],
+            "",
+            END_ASPECT_RATIO,
+        )?,
+
+        DarkMatterNode::Footnote { id, content } => {
+            let text = write_nodes(content)?;
+            if id.is_empty() {
+                if text.is_empty() || text.contains([']', '\n']) || is_footnote_id(&text) {
+                    return Err(unwritable(format!("inline footnote '{}'", text)));
+                }
+                format!("^[{}]", text)
+            } else {
+                if text.trim().is_empty() || text.contains('\n') {
+                    return Err(unwritable(format!("footnote [{}]", id)));
+                }
+                format!("::footnote[{}] {}", id, text)
+            }
+        }
+        DarkMatterNode::FootnoteRef { id } => format!("^[{}]", id),
+
+        DarkMatterNode::Audio { source, name, captions } => {
+            let mut line = format!("::audio {}", argument(source)?);
+            if let Some(name) = name {
+                line.push_str(&format!(" \"{}\"", name));
+            }
+            if let Some(captions) = captions {
+                line.push_str(&format!(" --captions {} --srclang {}", argument(&captions.path)?, captions.language));
+            }
+            line
+        }
+        DarkMatterNode::YouTube { video_id, width, captions } => match captions {
+            Some(language) => format!("::youtube {} {} cc={}", video_id, width, language),
+            None => format!("::youtube {} {}", video_id, width),
+        },
+        DarkMatterNode::Mermaid { source } => {
+            if source.lines().any(|line| line.trim() == END_BLOCK) {
+                return Err(unwritable(format!("Mermaid diagram with a {} line", END_BLOCK)));
+            }
+            raw_block(MERMAID.to_string(), source, END_BLOCK)
+        }
+        DarkMatterNode::Terminal { lines, shell } => {
+            let opener = match shell {
+                TerminalShell::Bash => "::terminal".to_string(),
+                shell => format!("::terminal --shell {}", shell),
+            };
+            let mut written = Vec::with_capacity(lines.len());
+            for line in lines {
+                let text = match line {
+                    TerminalLine::Command(command) => format!("$ {}", command).trim_end().to_string(),
+                    TerminalLine::Comment(comment) => format!("# {}", comment).trim_end().to_string(),
+                    TerminalLine::Output(output) => output.clone(),
+                };
+                // Output that looks like a prompt or a comment would be read
+                // back as one
+                if parse_terminal_line(&text, *shell) != *line || text.trim() == END_TERMINAL {
+                    return Err(unwritable(format!("terminal line '{}'", text)));
+                }
+                written.push(text);
+            }
+            raw_block(opener, &written.join("\n"), END_TERMINAL)
+        }
+        DarkMatterNode::Math { source, display: true } => {
+            format!("{}\n{}\n{}", MATH_DELIMITER, source, MATH_DELIMITER)
+        }
+        DarkMatterNode::Math { source, display: false } => format!("${}$", source),
+        DarkMatterNode::Diff { old, new, language, context_lines } => {
+            let mut line = match (&old.source, &new.source) {
+                (ResourceSource::Memory { content: old, .. }, ResourceSource::Memory { content: new, .. }) => {
+                    if old.contains('\n') || new.contains('\n') {
+                        return Err(unwritable("::diff --inline of multi-line text"));
+                    }
+                    format!("::diff --inline {} {}", quoted(old)?, quoted(new)?)
+                }
+                _ => format!("::diff {} {}", resource_argument(old)?, resource_argument(new)?),
+            };
+            if let Some(language) = language {
+                line.push_str(" --lang ");
+                line.push_str(&argument(language)?);
+            }
+            if *context_lines != DIFF_CONTEXT_LINES {
+                line.push_str(&format!(" --context {}", context_lines));
+            }
+            line
+        }
+    })
+}
+
+fn write_chart(name: &str, data: &ChartData, with_table: bool) -> Result<String, RenderError> {
+    let ChartData::External(resource) = data else {
+        return Err(unwritable(format!("::{} holding its data", name)));
+    };
+    let with_table = if with_table { " --with-table" } else { "" };
+    Ok(format!("::{} {}{}", name, resource_argument(resource)?, with_table))
+}
+
+/// Write a block directive: its opening line, its parts split by
+/// `separator` lines and its closing line
+fn write_block(opener: String, parts: &[&[DarkMatterNode]], separator: &str, end: &str) -> Result<String, RenderError> {
+    let mut lines = vec![opener];
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            lines.push(separator.to_string());
+        }
+        if !part.is_empty() {
+            lines.push(write_nodes(part)?);
+        }
+    }
+    lines.push(end.to_string());
+    Ok(lines.join("\n"))
+}
+
+/// Write a block whose lines are kept as they are
+fn raw_block(opener: String, body: &str, end: &str) -> String {
+    match body.is_empty() {
+        true => format!("{}\n{}", opener, end),
+        false => format!("{}\n{}\n{}", opener, body, end),
+    }
+}
+
+/// A resource as a directive argument, quoted when it needs to be
+fn resource_argument(resource: &Resource) -> Result<String, RenderError> {
+    argument(&format!("{}{}", reference(resource)?, requirement_suffix(resource)))
+}
+
+/// Resources as space-separated directive arguments
+fn resource_arguments(resources: &[Resource]) -> Result<String, RenderError> {
+    Ok(resources.iter().map(resource_argument).collect::<Result<Vec<_>, _>>()?.join(" "))
+}
+
+/// The reference [`parse_resource`](crate::parse::parse_resource) reads back
+/// as `resource`, fallbacks included but without the requirement suffix
+fn reference(resource: &Resource) -> Result<String, RenderError> {
+    let mut candidates = Vec::new();
+    let mut candidate = Some(resource);
+    while let Some(current) = candidate {
+        candidates.push(match (&current.source, &current.section) {
+            (ResourceSource::Local(path), None) => path.display().to_string(),
+            (ResourceSource::Local(path), Some(section)) => {
+                let url = Url::from_file_path(path).map_err(|_| unwritable(format!("section of {}", path.display())))?;
+                format!("{}#{}", url, section)
+            }
+            (ResourceSource::Remote(url), None) => url.to_string(),
+            (ResourceSource::Remote(url), Some(section)) => format!("{}#{}", url, section),
+            (ResourceSource::Git { repo_url, ref_, path }, _) => {
+                let host = repo_url
+                    .strip_prefix("https://")
+                    .ok_or_else(|| unwritable(format!("git repository {}", repo_url)))?;
+                format!("git://{}@{}:{}", host, ref_, path)
+            }
+            (ResourceSource::Memory { name, .. }, _) => {
+                return Err(unwritable(format!("in-memory resource '{}'", name)))
+            }
+        });
+        candidate = current.fallback.as_deref();
+    }
+    Ok(candidates.join(" || "))
+}
+
+/// The suffix giving a reference its requirement, which applies to its
+/// whole fallback chain
+fn requirement_suffix(resource: &Resource) -> &'static str {
+    match resource.requirement {
+        ResourceRequirement::Required => "!",
+        ResourceRequirement::Optional => "?",
+        ResourceRequirement::Default => "",
+    }
+}
+
+/// A directive argument, quoted when it is empty, holds whitespace or would
+/// be read as a flag
+fn argument(value: &str) -> Result<String, RenderError> {
+    if value.is_empty() || value.starts_with("--") || value.contains(char::is_whitespace) {
+        quoted(value)
+    } else if value.contains('"') {
+        Err(unwritable(format!("argument '{}'", value)))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// A directive argument in double quotes, which can't themselves be quoted
+fn quoted(value: &str) -> Result<String, RenderError> {
+    match value.contains('"') {
+        true => Err(unwritable(format!("argument '{}'", value))),
+        false => Ok(format!("\"{}\"", value)),
+    }
+}
+
+fn unwritable(what: impl std::fmt::Display) -> RenderError {
+    RenderError::MarkdownFailed(format!("{} has no Markdown syntax", what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_document, ParseOptions};
+    use crate::types::Document;
+    use proptest::prelude::*;
+
+    fn parse(content: &str) -> Document {
+        parse_document(content, Resource::memory("doc.md", content), ParseOptions::default()).unwrap().0
+    }
+
+    /// Write `content`'s document and parse it again, checking nothing changed
+    fn round_trip(content: &str) -> String {
+        let document = parse(content);
+        let markdown = write_markdown(&document.frontmatter, &document.content).unwrap();
+        let reparsed = parse(&markdown);
+        assert!(document.diff(&reparsed).unwrap().is_empty(), "{:?} became {:?}", content, markdown);
+        markdown
+    }
+
+    #[test]
+    fn test_directives_are_written_in_their_syntax() {
+        let content = "\
+# Guide
+
+::file ./intro.md
+::file \"./my notes.md\" 10-
+::file ./utils.rs#auth!
+::file https://example.com/spec.md#install || ./vendor/spec.md?
+::summarize git://github.com/org/repo.git@main:docs/api.md
+::consolidate ./a.md ./b.md
+::topic \"error handling\" ./a.md ./b.md --review
+::table ./data.csv --with-heading-row
+::bar-chart ./sales.csv --with-table
+::pie-chart ./share.csv --inner-radius 0.6 --show-percentages
+::list ./tasks.json --column title --column done --as tasklist
+::include-env --filter \"APP_\" --show-values true --sort
+::require-env PATH
+::diff ./old.rs ./new.rs --lang rust --context 5
+::diff --inline \"let x = 1;\" \"let x = 2;\"
+::audio \"./my talk.mp3\" \"The Talk\" --captions ./talk.vtt --srclang fr
+::youtube dQw4w9WgXcQ 80%
+::footnote[note] See {{source}}.";
+
+        let markdown = round_trip(content);
+
+        assert!(markdown.contains("::file ./utils.rs#auth!\n"));
+        assert!(markdown.contains("::file \"https://example.com/spec.md#install || ./vendor/spec.md?\"\n"));
+        assert!(markdown.contains("::summarize git://github.com/org/repo.git@main:docs/api.md\n"));
+        assert!(markdown.contains("::youtube dQw4w9WgXcQ 80%\n"));
+        assert!(markdown.contains("::topic \"error handling\" ./a.md ./b.md --review\n"));
+    }
+
+    #[test]
+    fn test_blocks_and_frontmatter_round_trip() {
+        let markdown = round_trip(
+            "---\ntitle: \"Guide: part 2\"\nversion: 3\ntags: [rust, docs]\nlist_expansion: collapsed\n---\n\
+Intro with $x^2$ and a [term](popover:Definition of {{term}}).\n\
+::summary\nMore\n::details\n::columns md: 2, xl: 3\nLeft\n::break\n::file ./right.md\n::end\n::end\n\
+::aspect-ratio 16:9\n::youtube dQw4w9WgXcQ auto cc=en\n::endaspect-ratio\n\
+::mermaid\ngraph TD\n  A --> B\n::end\n\
+::terminal --shell zsh\n# Build it\n$ cargo build\n   Compiling lib\n::endterminal\n\
+$$\n\\int_0^1 x\\,dx\n$$\n",
+        );
+
+        assert!(markdown.starts_with("---\nlist_expansion: collapsed\ntags:\n"));
+        assert!(markdown.contains("::terminal --shell zsh\n# Build it\n$ cargo build\n   Compiling lib\n::endterminal"));
+    }
+
+    #[test]
+    fn test_unwritable_nodes_fail() {
+        let chart = DarkMatterNode::PieChart {
+            data: ChartData::Inline(Vec::new()),
+            with_table: false,
+            options: Default::default(),
+        };
+        let transclusion = DarkMatterNode::File { resource: Resource::memory("snippet", "text"), range: None };
+
+        for node in [chart, transclusion] {
+            assert!(matches!(
+                write_markdown(&Frontmatter::default(), &[node]),
+                Err(RenderError::MarkdownFailed(_))
+            ));
+        }
+    }
+
+    /// Lines of a document: markdown, directives and whole blocks
+    fn source_line() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[A-Za-z][A-Za-z ,.]{0,30}",
+            "#{1,3} [A-Za-z]{1,12}",
+            "[a-z]{1,8}".prop_map(|name| format!("::file ./{}.md", name)),
+            ("[a-z]{1,8}", 1usize..50, 50usize..99).prop_map(|(name, start, end)| format!("::file ./{}.rs {}-{}", name, start, end)),
+            "[a-z]{1,8}( [a-z]{1,8})?".prop_map(|name| format!("::summarize \"./{}.md\"", name)),
+            ("[a-z ]{1,12}", any::<bool>()).prop_map(|(topic, review)| {
+                format!("::topic \"{}\" ./a.md{}", topic, if review { " --review" } else { "" })
+            }),
+            ("(bar|line|pie)-chart", any::<bool>()).prop_map(|(chart, table)| {
+                format!("::{} ./data.csv{}", chart, if table { " --with-table" } else { "" })
+            }),
+            "[A-Za-z0-9_-]{11}".prop_map(|id| format!("::youtube {} 32.5rem", id)),
+            "[A-Za-z]{1,10}".prop_map(|text| format!("::summary\n{}\n::details\n{{{{{}}}}}\n::end", text, text)),
+            "[a-z]{1,10}".prop_map(|text| format!("::columns lg: 2\n{}\n::break\n::mermaid\n{}\n::end\n::end", text, text)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_written_documents_parse_back(lines in prop::collection::vec(source_line(), 1..8), title in "[A-Za-z ]{1,20}") {
+            let content = format!("---\ntitle: \"{}\"\n---\n{}", title, lines.join("\n"));
+            let document = parse(&content);
+
+            let markdown = write_markdown(&document.frontmatter, &document.content).unwrap();
+            let reparsed = parse(&markdown);
+
+            prop_assert!(document.diff(&reparsed).unwrap().is_empty(), "{:?} became {:?}", content, markdown);
+        }
+    }
+}
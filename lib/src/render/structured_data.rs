@@ -0,0 +1,213 @@
+//! schema.org structured data (JSON-LD) for rendered pages
+//!
+//! Search engines read a page's `<script type="application/ld+json">` to
+//! learn what it is about. The block is built from a fixed set of
+//! frontmatter properties; an author can add to or override it with a
+//! `jsonld` object of their own.
+
+use crate::error::{ParseWarning, ParseWarningCode};
+use crate::types::Frontmatter;
+use serde_json::{json, Map, Value};
+
+/// Frontmatter property holding the author's own JSON-LD
+const JSONLD_KEY: &str = "jsonld";
+
+/// An image the page is about, with its size when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PageImage {
+    pub url: String,
+    pub size: Option<(u32, u32)>,
+}
+
+/// The JSON-LD `<script>` of a page, and a warning when the frontmatter's
+/// `jsonld` couldn't be used
+///
+/// The page is an `Article` when its frontmatter has a `date`, and a
+/// `WebPage` otherwise. `headline` is the page's title, `description`,
+/// `datePublished` (`date`), `dateModified` (`updated`) and `inLanguage`
+/// (`language`) come from the frontmatter properties of those names,
+/// `author` from `author` (a name, a list of names or objects) and
+/// `keywords` from `tags`. The keys of a `jsonld` object, or of a string
+/// holding one, are merged over the top, replacing those built here. Any
+/// other `jsonld` value is ignored with an
+/// [`InvalidStructuredData`](ParseWarningCode::InvalidStructuredData) warning.
+pub(crate) fn structured_data(
+    frontmatter: &Frontmatter,
+    title: Option<&str>,
+    image: Option<PageImage>,
+) -> (String, Option<ParseWarning>) {
+    let custom = &frontmatter.custom;
+    let text = |key: &str| custom.get(key).and_then(Value::as_str).filter(|value| !value.trim().is_empty());
+
+    let mut data = Map::new();
+    data.insert("@context".to_string(), json!("https://schema.org"));
+    let kind = if custom.contains_key("date") { "Article" } else { "WebPage" };
+    data.insert("@type".to_string(), json!(kind));
+    if let Some(title) = title {
+        data.insert("headline".to_string(), json!(title));
+    }
+    for (property, key) in [
+        ("description", "description"),
+        ("datePublished", "date"),
+        ("dateModified", "updated"),
+        ("inLanguage", "language"),
+    ] {
+        if let Some(value) = text(key) {
+            data.insert(property.to_string(), json!(value));
+        }
+    }
+    if let Some(author) = custom.get("author").and_then(authors) {
+        data.insert("author".to_string(), author);
+    }
+    if let Some(tags) = custom.get("tags").and_then(keywords) {
+        data.insert("keywords".to_string(), json!(tags));
+    }
+    if let Some(image) = image {
+        let value = match image.size {
+            Some((width, height)) => {
+                json!({ "@type": "ImageObject", "url": image.url, "width": width, "height": height })
+            }
+            None => json!(image.url),
+        };
+        data.insert("image".to_string(), value);
+    }
+
+    let mut warning = None;
+    if let Some(value) = custom.get(JSONLD_KEY) {
+        match own_jsonld(value) {
+            Some(own) => data.extend(own),
+            None => {
+                warning = Some(ParseWarning {
+                    span: None,
+                    message: format!("'{}' frontmatter isn't a JSON object; it is ignored", JSONLD_KEY),
+                    code: ParseWarningCode::InvalidStructuredData,
+                })
+            }
+        }
+    }
+
+    (script(&Value::Object(data)), warning)
+}
+
+/// A `<script>` holding `data`, with every `<` escaped so no `</script`
+/// or `<!--` in a value can end the block early
+fn script(data: &Value) -> String {
+    let json = data.to_string().replace('<', "\\u003c");
+    format!(r#"<script type="application/ld+json">{}</script>"#, json)
+}
+
+/// The `author` as schema.org people: a name becomes a `Person`, objects
+/// are kept as they are
+fn authors(value: &Value) -> Option<Value> {
+    let person = |value: &Value| match value {
+        Value::String(name) if !name.trim().is_empty() => Some(json!({ "@type": "Person", "name": name })),
+        Value::Object(_) => Some(value.clone()),
+        _ => None,
+    };
+    match value {
+        Value::Array(items) => {
+            let people: Vec<Value> = items.iter().filter_map(person).collect();
+            (!people.is_empty()).then_some(Value::Array(people))
+        }
+        other => person(other),
+    }
+}
+
+/// `tags` joined with `, `, as [`HtmlOutput::metadata`](crate::HtmlOutput::metadata) has them
+fn keywords(value: &Value) -> Option<String> {
+    match value {
+        Value::String(tags) if !tags.trim().is_empty() => Some(tags.clone()),
+        Value::Array(tags) if !tags.is_empty() => Some(
+            tags.iter()
+                .map(|tag| tag.as_str().map(str::to_string).unwrap_or_else(|| tag.to_string()))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        _ => None,
+    }
+}
+
+/// The author's own JSON-LD: an object, or a string of JSON holding one
+fn own_jsonld(value: &Value) -> Option<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Some(map.clone()),
+        Value::String(text) => match serde_json::from_str(text) {
+            Ok(Value::Object(map)) => Some(map),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontmatter(values: Value) -> Frontmatter {
+        let Value::Object(map) = values else { unreachable!() };
+        Frontmatter { custom: map.into_iter().collect(), ..Frontmatter::default() }
+    }
+
+    /// The JSON inside a `<script>`
+    fn parse(script: &str) -> Value {
+        let json = script
+            .strip_prefix(r#"<script type="application/ld+json">"#)
+            .and_then(|rest| rest.strip_suffix("</script>"))
+            .unwrap();
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_article_from_frontmatter() {
+        let frontmatter = frontmatter(json!({
+            "description": "All about composing",
+            "date": "2024-03-01",
+            "author": ["Ada", { "@type": "Organization", "name": "Acme" }],
+            "tags": ["rust", "docs"],
+        }));
+        let image = PageImage { url: "images/abc-1280w.jpg".to_string(), size: Some((1280, 720)) };
+
+        let (script, warning) = structured_data(&frontmatter, Some("Composing"), Some(image));
+
+        assert!(warning.is_none());
+        assert_eq!(
+            parse(&script),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "Article",
+                "headline": "Composing",
+                "description": "All about composing",
+                "datePublished": "2024-03-01",
+                "author": [{ "@type": "Person", "name": "Ada" }, { "@type": "Organization", "name": "Acme" }],
+                "keywords": "rust, docs",
+                "image": { "@type": "ImageObject", "url": "images/abc-1280w.jpg", "width": 1280, "height": 720 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_author_jsonld_wins_and_script_is_escaped() {
+        let frontmatter = frontmatter(json!({
+            "jsonld": { "@type": "TechArticle", "about": "</script><script>alert(1)</script>" },
+        }));
+
+        let (script, warning) = structured_data(&frontmatter, Some("Guide"), None);
+
+        assert!(warning.is_none());
+        assert_eq!(script.matches("</script").count(), 1);
+        let data = parse(&script);
+        assert_eq!(data["@type"], "TechArticle");
+        assert_eq!(data["headline"], "Guide");
+        assert_eq!(data["about"], "</script><script>alert(1)</script>");
+    }
+
+    #[test]
+    fn test_invalid_jsonld_is_a_warning() {
+        for jsonld in [json!("{not json"), json!(["a"])] {
+            let (script, warning) = structured_data(&frontmatter(json!({ "jsonld": jsonld })), None, None);
+
+            assert_eq!(warning.unwrap().code, ParseWarningCode::InvalidStructuredData);
+            assert_eq!(parse(&script)["@type"], "WebPage");
+        }
+    }
+}
@@ -1,19 +1,18 @@
 use crate::error::RenderError;
-use crate::types::{Resource, ResourceSource, TableSource};
-use std::fs;
+use crate::types::TableSource;
 use tracing::instrument;
 
 /// Render a table to HTML
 ///
-/// Supports both inline table data and external CSV files
+/// External CSV files are loaded by the transclusion pass, which turns them
+/// into inline tables; one that reaches this point is an error.
 #[instrument]
 pub fn render_table(source: &TableSource, has_heading: bool) -> Result<String, RenderError> {
     match source {
         TableSource::Inline(rows) => render_inline_table(rows, has_heading),
-        TableSource::External(resource) => {
-            let csv_data = load_csv(resource)?;
-            render_csv_table(&csv_data, has_heading)
-        }
+        TableSource::External(_) => Err(RenderError::TableError(
+            "External tables must be resolved before HTML generation".to_string()
+        )),
     }
 }
 
@@ -62,37 +61,8 @@ fn render_inline_table(rows: &[Vec<String>], has_heading: bool) -> Result<String
     Ok(html)
 }
 
-/// Load CSV data from a resource
-fn load_csv(resource: &Resource) -> Result<Vec<Vec<String>>, RenderError> {
-    let content = match &resource.source {
-        ResourceSource::Local(path) => {
-            fs::read_to_string(path)
-                .map_err(|e| RenderError::ResourceNotFound(
-                    path.display().to_string(),
-                    e.to_string()
-                ))?
-        }
-        ResourceSource::Remote(url) => {
-            // For remote CSV, we'd need to fetch it
-            // Using blocking reqwest for simplicity
-            reqwest::blocking::get(url.clone())
-                .map_err(|e| RenderError::RemoteFetchError(
-                    url.to_string(),
-                    e.to_string()
-                ))?
-                .text()
-                .map_err(|e| RenderError::RemoteFetchError(
-                    url.to_string(),
-                    e.to_string()
-                ))?
-        }
-    };
-
-    parse_csv(&content)
-}
-
 /// Parse CSV content into rows
-fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, RenderError> {
+pub(super) fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, RenderError> {
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false) // Don't treat first row as headers
         .from_reader(content.as_bytes());
@@ -107,10 +77,113 @@ fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, RenderError> {
     Ok(rows)
 }
 
-/// Render CSV data to HTML table
-fn render_csv_table(rows: &[Vec<String>], has_heading: bool) -> Result<String, RenderError> {
-    // CSV rendering is the same as inline table rendering
-    render_inline_table(rows, has_heading)
+/// Styles of environment tables, highlighting unset variables
+const ENV_TABLE_CSS: &str = r#"
+.composition-env-table .composition-env-unset {
+  background-color: var(--dm-danger-bg);
+  color: var(--dm-danger-fg);
+}
+"#;
+
+/// Render a table of environment variables to HTML
+///
+/// The rows are those of [`env_table_rows`] for the process environment.
+/// Only the variables in `allowed_env_vars` have their value shown, and only
+/// with `show_values`; every other value is redacted to `[SET]`. Rows of
+/// unset variables have the `composition-env-unset` class.
+#[instrument(skip(allowed_env_vars, required))]
+pub fn render_env_table(
+    filter: Option<&str>,
+    sort: bool,
+    show_values: bool,
+    allowed_env_vars: &[String],
+    required: &[String],
+) -> Result<String, RenderError> {
+    let rows = env_table_rows(std::env::vars().collect(), filter, sort, show_values, allowed_env_vars, required);
+    Ok(render_env_rows(&rows))
+}
+
+/// Render the rows of [`env_table_rows`], marking those of unset variables
+fn render_env_rows(rows: &[Vec<String>]) -> String {
+    let mut html = String::from("<table class=\"composition-env-table\">\n");
+    let Some((heading, body)) = rows.split_first() else {
+        html.push_str("</table>");
+        return html;
+    };
+
+    html.push_str("  <thead>\n    <tr>\n");
+    for cell in heading {
+        html.push_str(&format!("      <th>{}</th>\n", escape_html(cell)));
+    }
+    html.push_str("    </tr>\n  </thead>\n");
+
+    html.push_str("  <tbody>\n");
+    for row in body {
+        if row.last().is_some_and(|status| status == "unset") {
+            html.push_str("    <tr class=\"composition-env-unset\">\n");
+        } else {
+            html.push_str("    <tr>\n");
+        }
+        for cell in row {
+            html.push_str(&format!("      <td>{}</td>\n", escape_html(cell)));
+        }
+        html.push_str("    </tr>\n");
+    }
+    html.push_str("  </tbody>\n");
+
+    html.push_str("</table>");
+    html
+}
+
+/// Returns the CSS for environment tables (registered as a component asset)
+pub fn env_table_css() -> &'static str {
+    ENV_TABLE_CSS
+}
+
+/// The `Variable`, `Value` and `Status` rows of an environment table, heading
+/// row first
+///
+/// Lists every variable in `vars` whose name starts with `filter` (or all of
+/// them when no filter is given), and every `required` variable whatever its
+/// name. Variables missing from `vars` show `[NOT SET]` and the status
+/// `unset`; one set to an empty value is still `set`. A value is shown when
+/// `show_values` is set and the variable is in `allowed_env_vars`; otherwise
+/// it is redacted to `[SET]`.
+pub(crate) fn env_table_rows(
+    vars: Vec<(String, String)>,
+    filter: Option<&str>,
+    sort: bool,
+    show_values: bool,
+    allowed_env_vars: &[String],
+    required: &[String],
+) -> Vec<Vec<String>> {
+    let mut listed: Vec<(String, Option<String>)> = vars
+        .iter()
+        .filter(|(name, _)| filter.is_none_or(|prefix| name.starts_with(prefix)))
+        .map(|(name, value)| (name.clone(), Some(value.clone())))
+        .collect();
+    for name in required {
+        if !listed.iter().any(|(listed_name, _)| listed_name == name) {
+            let value = vars.iter().find(|(var, _)| var == name).map(|(_, value)| value.clone());
+            listed.push((name.clone(), value));
+        }
+    }
+
+    if sort {
+        listed.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    let heading = ["Variable", "Value", "Status"].map(str::to_string).to_vec();
+    let rows = listed.into_iter().map(|(name, value)| {
+        let shown = show_values && allowed_env_vars.contains(&name);
+        let (display_value, status) = match (value, shown) {
+            (None, _) => ("[NOT SET]".to_string(), "unset"),
+            (Some(value), true) => (value, "set"),
+            (Some(_), false) => ("[SET]".to_string(), "set"),
+        };
+        vec![name, display_value, status.to_string()]
+    });
+    std::iter::once(heading).chain(rows).collect()
 }
 
 /// Escape HTML special characters
@@ -169,6 +242,14 @@ mod tests {
         assert_eq!(html, "<table></table>");
     }
 
+    #[test]
+    fn test_render_unresolved_external_table() {
+        let source = TableSource::External(crate::types::Resource::local("data.csv".into()));
+        let err = render_table(&source, true).unwrap_err();
+
+        assert!(matches!(err, RenderError::TableError(_)));
+    }
+
     #[test]
     fn test_escape_html() {
         assert_eq!(escape_html("Hello"), "Hello");
@@ -177,6 +258,75 @@ mod tests {
         assert_eq!(escape_html("\"quote\""), "&quot;quote&quot;");
     }
 
+    fn env_fixture() -> Vec<(String, String)> {
+        vec![
+            ("APP_PORT".to_string(), "8080".to_string()),
+            ("OTHER".to_string(), "ignored".to_string()),
+            ("APP_EMPTY".to_string(), String::new()),
+            ("APP_HOST".to_string(), "<localhost>".to_string()),
+        ]
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_env_table_rows_filter_and_sort() {
+        let all = strings(&["APP_PORT", "APP_EMPTY", "APP_HOST"]);
+        let rows = env_table_rows(env_fixture(), Some("APP_"), true, true, &all, &[]);
+
+        assert_eq!(rows[0], ["Variable", "Value", "Status"]);
+        assert_eq!(rows[1], ["APP_EMPTY", "", "set"]);
+        assert_eq!(rows[2], ["APP_HOST", "<localhost>", "set"]);
+        assert_eq!(rows[3], ["APP_PORT", "8080", "set"]);
+        assert_eq!(rows.len(), 4);
+
+        let html = render_env_rows(&rows);
+        assert!(html.contains("<th>Variable</th>"));
+        assert!(html.contains("&lt;localhost&gt;"));
+    }
+
+    #[test]
+    fn test_env_table_rows_redacted() {
+        let all = strings(&["APP_PORT", "APP_HOST"]);
+        let rows = env_table_rows(env_fixture(), Some("APP_"), false, false, &all, &[]);
+
+        assert!(rows.iter().all(|row| row[1] != "8080"));
+        assert_eq!(rows.iter().filter(|row| row[1] == "[SET]").count(), 3);
+    }
+
+    #[test]
+    fn test_env_table_marks_unset_rows() {
+        let required = strings(&["APP_MISSING"]);
+        let rows = env_table_rows(env_fixture(), Some("APP_"), true, false, &[], &required);
+        let html = render_env_rows(&rows);
+
+        assert!(html.starts_with(r#"<table class="composition-env-table">"#));
+        assert_eq!(html.matches(r#"<tr class="composition-env-unset">"#).count(), 1);
+        assert!(html.contains("<tr class=\"composition-env-unset\">\n      <td>APP_MISSING</td>"), "{}", html);
+    }
+
+    #[test]
+    fn test_env_table_rows_only_show_allowed_values() {
+        let rows = env_table_rows(env_fixture(), Some("APP_"), true, true, &strings(&["APP_PORT"]), &[]);
+
+        assert!(rows.contains(&strings(&["APP_PORT", "8080", "set"])));
+        assert!(rows.contains(&strings(&["APP_HOST", "[SET]", "set"])));
+        assert!(rows.iter().flatten().all(|cell| cell != "<localhost>"));
+    }
+
+    #[test]
+    fn test_env_table_rows_list_required_vars() {
+        let required = strings(&["OTHER", "APP_MISSING", "APP_PORT"]);
+        let rows = env_table_rows(env_fixture(), Some("APP_"), true, false, &[], &required);
+
+        assert!(rows.contains(&strings(&["OTHER", "[SET]", "set"])));
+        assert!(rows.contains(&strings(&["APP_MISSING", "[NOT SET]", "unset"])));
+        assert_eq!(rows.iter().filter(|row| row[0] == "APP_PORT").count(), 1);
+        assert_eq!(rows.len(), 6);
+    }
+
     #[test]
     fn test_parse_csv_simple() {
         let csv = "a,b,c\n1,2,3";
@@ -0,0 +1,197 @@
+//! Terminal session rendering
+//!
+//! A session renders as a `<div class="dm-terminal">` styled like a terminal
+//! window. Commands follow the shell's prompt and have a copy button, output
+//! is gray and comments are dimmed further. The stylesheet and the copy
+//! script are added once per document by the orchestration layer.
+
+use crate::types::{TerminalLine, TerminalShell};
+
+const TERMINAL_CSS: &str = r#"
+.dm-terminal {
+  margin: 1rem 0;
+  border-radius: 8px;
+  overflow: hidden;
+  background-color: #1e1e1e;
+  color: #e5e7eb;
+  font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace;
+  font-size: 0.875rem;
+  box-shadow: 0 4px 12px rgba(0, 0, 0, 0.25);
+}
+.dm-terminal-bar {
+  display: flex;
+  align-items: center;
+  gap: 6px;
+  padding: 8px 12px;
+  background-color: #323232;
+}
+.dm-terminal-dot {
+  width: 12px;
+  height: 12px;
+  border-radius: 50%;
+  background-color: #ff5f56;
+}
+.dm-terminal-dot + .dm-terminal-dot {
+  background-color: #ffbd2e;
+}
+.dm-terminal-dot + .dm-terminal-dot + .dm-terminal-dot {
+  background-color: #27c93f;
+}
+.dm-terminal-title {
+  margin-left: 8px;
+  color: #9ca3af;
+  font-size: 0.75rem;
+}
+.dm-terminal-body {
+  padding: 12px 16px;
+  overflow-x: auto;
+  white-space: pre-wrap;
+}
+.dm-terminal-body > div {
+  min-height: 1.5em;
+}
+.dm-terminal-command {
+  display: flex;
+  align-items: baseline;
+  gap: 0.5em;
+}
+.dm-terminal-prompt {
+  color: #4ade80;
+  user-select: none;
+}
+.dm-terminal-command code {
+  flex: 1;
+  background: none;
+  color: inherit;
+  font: inherit;
+}
+.dm-terminal-output {
+  color: #9ca3af;
+}
+.dm-terminal-comment {
+  color: #6b7280;
+  font-style: italic;
+}
+.dm-terminal-output-only .dm-terminal-body {
+  opacity: 0.7;
+}
+.dm-copy {
+  padding: 0 6px;
+  border: 1px solid #4b5563;
+  border-radius: 4px;
+  background: none;
+  color: #9ca3af;
+  font: inherit;
+  font-size: 0.75rem;
+  cursor: pointer;
+  opacity: 0;
+}
+.dm-terminal-command:hover .dm-copy,
+.dm-copy:focus {
+  opacity: 1;
+}
+"#;
+
+const CLIPBOARD_JS: &str = r#"
+document.addEventListener("click", (event) => {
+  const button = event.target.closest(".dm-copy");
+  if (!button) return;
+  navigator.clipboard.writeText(button.dataset.copy).then(() => {
+    button.textContent = "Copied";
+    setTimeout(() => { button.textContent = "Copy"; }, 1500);
+  });
+});
+"#;
+
+/// Render a terminal session
+///
+/// A session without any command is output only and is dimmed as a whole.
+pub fn render_terminal(lines: &[TerminalLine], shell: TerminalShell) -> String {
+    let output_only = !lines.iter().any(|line| matches!(line, TerminalLine::Command(_)));
+    let class = if output_only { "dm-terminal dm-terminal-output-only" } else { "dm-terminal" };
+
+    let mut html = format!(
+        r#"<div class="{}" data-shell="{}"><div class="dm-terminal-bar" aria-hidden="true"><span class="dm-terminal-dot"></span><span class="dm-terminal-dot"></span><span class="dm-terminal-dot"></span><span class="dm-terminal-title">{}</span></div><div class="dm-terminal-body">"#,
+        class, shell, shell
+    );
+    for line in lines {
+        html.push_str(&match line {
+            TerminalLine::Command(command) => {
+                let command = escape_html(command);
+                format!(
+                    r#"<div class="dm-terminal-command"><span class="dm-terminal-prompt">{}</span><code>{}</code><button type="button" class="dm-copy" data-copy="{}" aria-label="Copy command">Copy</button></div>"#,
+                    escape_html(shell.prompt()),
+                    command,
+                    command
+                )
+            }
+            TerminalLine::Output(output) => {
+                format!(r#"<div class="dm-terminal-output">{}</div>"#, escape_html(output))
+            }
+            TerminalLine::Comment(comment) => {
+                format!(r#"<div class="dm-terminal-comment"># {}</div>"#, escape_html(comment))
+            }
+        });
+    }
+    html.push_str("</div></div>");
+
+    html
+}
+
+/// Returns the CSS for terminal sessions (called by orchestration layer)
+pub fn terminal_css() -> &'static str {
+    TERMINAL_CSS
+}
+
+/// Returns the script behind the copy buttons (called by orchestration layer)
+///
+/// Clicking a `.dm-copy` button copies its `data-copy` text.
+pub fn clipboard_js() -> &'static str {
+    CLIPBOARD_JS
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_terminal_lines() {
+        let lines = vec![
+            TerminalLine::Comment("list files".to_string()),
+            TerminalLine::Command("ls -la | grep \"rs\"".to_string()),
+            TerminalLine::Output("main.rs".to_string()),
+        ];
+
+        let html = render_terminal(&lines, TerminalShell::Zsh);
+
+        assert!(html.starts_with(r#"<div class="dm-terminal" data-shell="zsh">"#));
+        assert!(html.contains(r#"<div class="dm-terminal-comment"># list files</div>"#));
+        assert!(html.contains(
+            r#"<span class="dm-terminal-prompt">$</span><code>ls -la | grep &quot;rs&quot;</code><button type="button" class="dm-copy" data-copy="ls -la | grep &quot;rs&quot;""#
+        ));
+        assert!(html.contains(r#"<div class="dm-terminal-output">main.rs</div>"#));
+    }
+
+    #[test]
+    fn test_render_terminal_powershell_prompt() {
+        let html = render_terminal(&[TerminalLine::Command("Get-ChildItem".to_string())], TerminalShell::PowerShell);
+
+        assert!(html.contains(r#"<span class="dm-terminal-prompt">PS&gt;</span><code>Get-ChildItem</code>"#));
+    }
+
+    #[test]
+    fn test_render_terminal_output_only() {
+        let html = render_terminal(&[TerminalLine::Output("done".to_string())], TerminalShell::Bash);
+
+        assert!(html.starts_with(r#"<div class="dm-terminal dm-terminal-output-only""#));
+        assert!(!html.contains("dm-copy"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Shared color theme for generated components
+//!
+//! Component stylesheets take their colors from CSS custom properties
+//! instead of hard-coding them, so a page can restyle every component at once
+//! by overriding the variables, e.g. `:root { --dm-accent: rebeccapurple; }`.
+//! The theme defines light values and, under `prefers-color-scheme: dark`,
+//! dark ones. The stylesheet is added once per document by the orchestration
+//! layer, ahead of the first component that uses it.
+
+/// CSS custom properties read by component stylesheets
+pub const THEME_VARIABLES: &[&str] = &[
+    "--dm-bg",
+    "--dm-fg",
+    "--dm-muted",
+    "--dm-border",
+    "--dm-accent",
+    "--dm-media-bg",
+    "--dm-overlay",
+    "--dm-overlay-hover",
+    "--dm-overlay-fg",
+    "--dm-backdrop",
+    "--dm-danger-bg",
+    "--dm-danger-fg",
+    "--dm-danger-border",
+];
+
+const THEME_CSS: &str = r#"
+:root {
+  --dm-bg: #f9fafb;
+  --dm-fg: #1f2937;
+  --dm-muted: #6b7280;
+  --dm-border: #d1d5db;
+  --dm-accent: #3b82f6;
+  --dm-media-bg: #000;
+  --dm-overlay: rgba(0, 0, 0, 0.7);
+  --dm-overlay-hover: rgba(0, 0, 0, 0.9);
+  --dm-overlay-fg: #fff;
+  --dm-backdrop: rgba(0, 0, 0, 0.8);
+  --dm-danger-bg: #fee2e2;
+  --dm-danger-fg: #991b1b;
+  --dm-danger-border: #ef4444;
+}
+
+@media (prefers-color-scheme: dark) {
+  :root {
+    --dm-bg: #1f2937;
+    --dm-fg: #f9fafb;
+    --dm-muted: #9ca3af;
+    --dm-border: #374151;
+    --dm-accent: #60a5fa;
+    --dm-danger-bg: #7f1d1d;
+    --dm-danger-fg: #fecaca;
+  }
+}
+"#;
+
+/// Returns the theme's CSS custom properties (called by orchestration layer)
+///
+/// Pages that restyle components override the variables after this
+/// stylesheet, or with a more specific selector.
+pub fn component_theme_css() -> &'static str {
+    THEME_CSS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::html::audio_player_css;
+    use crate::render::youtube::youtube_css;
+
+    #[test]
+    fn test_theme_defines_every_variable_in_both_schemes() {
+        let css = component_theme_css();
+        let (light, dark) = css.split_once("@media (prefers-color-scheme: dark)").unwrap();
+        for variable in THEME_VARIABLES {
+            assert!(light.contains(&format!("{}:", variable)), "{} isn't defined", variable);
+        }
+        assert!(dark.contains("--dm-bg:") && dark.contains("--dm-fg:"));
+    }
+
+    #[test]
+    fn test_component_css_uses_theme_variables() {
+        for css in [youtube_css(), audio_player_css()] {
+            assert!(css.contains("var(--dm-"));
+            // Every variable a component reads is one the theme defines
+            for (_, rest) in css.match_indices("var(").map(|(i, _)| css.split_at(i + 4)) {
+                let name = &rest[..rest.find([')', ',']).unwrap()];
+                if name.starts_with("--dm-") {
+                    assert!(THEME_VARIABLES.contains(&name), "{} isn't a theme variable", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_component_css_has_no_hard_coded_colors() {
+        for css in [youtube_css(), audio_player_css()] {
+            assert!(!css.contains('#') && !css.contains("rgba("), "{}", css);
+        }
+    }
+}
@@ -1,16 +1,34 @@
 use crate::cache::CacheOperations;
-use crate::error::RenderError;
-use crate::parse::parse_document;
-use crate::types::{DarkMatterNode, Frontmatter, LineRange, Resource, ResourceSource};
+use crate::http::fetch_text;
+use crate::error::{ParseWarning, ParseWarningCode, RenderError};
+use crate::graph::{git_location, load_resource_candidate};
+use crate::graph::utils::decode_text;
+use crate::parse::parse_document_unchecked;
+use crate::parse::regions::extract_region;
+use super::list::read_list_items;
+use super::passes::{FallbackUsed, LanguageVariant, PassReport};
+use crate::types::{DarkMatterNode, Frontmatter, LineRange, ListSource, Resource, ResourceRequirement, ResourceSource};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::future::Future;
+use std::sync::Mutex;
 use tracing::instrument;
-use csv;
 
 /// Resolve a resource path relative to a base path
-fn resolve_resource_path(
+pub(super) fn resolve_resource_path(
+    resource: &Resource,
+    base_path: Option<&PathBuf>,
+) -> Result<Resource, RenderError> {
+    let mut resolved = resolve_candidate_path(resource, base_path)?;
+    if let Some(fallback) = &resource.fallback {
+        resolved.fallback = Some(Box::new(resolve_resource_path(fallback, base_path)?));
+    }
+    Ok(resolved)
+}
+
+/// Resolve a single candidate's path, ignoring its fallbacks
+fn resolve_candidate_path(
     resource: &Resource,
     base_path: Option<&PathBuf>,
 ) -> Result<Resource, RenderError> {
@@ -33,6 +51,8 @@ fn resolve_resource_path(
                     source: ResourceSource::Local(resolved_path),
                     requirement: resource.requirement,
                     cache_duration: resource.cache_duration,
+                    section: resource.section.clone(),
+                    fallback: None,
                 })
             } else {
                 // No base path, resolve relative to current directory
@@ -44,11 +64,13 @@ fn resolve_resource_path(
                     source: ResourceSource::Local(resolved_path),
                     requirement: resource.requirement,
                     cache_duration: resource.cache_duration,
+                    section: resource.section.clone(),
+                    fallback: None,
                 })
             }
         }
         // Already absolute or remote - return as-is
-        _ => Ok(resource.clone())
+        _ => Ok(Resource { fallback: None, ..resource.clone() })
     }
 }
 
@@ -57,32 +79,81 @@ fn resolve_resource_path(
 /// This function:
 /// 1. Resolves relative resource paths
 /// 2. Loads the resource (from cache for remote, or filesystem for local)
-/// 3. Applies line range filtering if specified
+/// 3. Applies line range or region filtering if specified
 /// 4. Parses the transcluded content as a DarkMatter document
 /// 5. Recursively resolves nested transclusions
 /// 6. Resolves external table sources to inline tables
-#[instrument(skip(_cache, _frontmatter))]
+///
+/// Remote resources are refused when their response is over
+/// `max_remote_bytes` or isn't text, with [`RenderError::RemoteFetchError`].
+///
+/// With a `language`, a `::file` transclusion loads its translation into
+/// that language where one exists (see [`Resource::localized`]), and the
+/// file it loaded is recorded in `report`.
+///
+/// Fallback candidates (`a || b`) are tried in order and the one used is
+/// recorded in `report`. An optional (`?`) resource none of whose candidates
+/// can be loaded transcludes nothing and adds a warning to `report`.
+/// Warnings in the transcluded content itself are reported when that
+/// resource is rendered as a document.
+#[instrument(skip(_cache, _frontmatter, report))]
 pub fn resolve_transclusion<'a>(
     node: &'a DarkMatterNode,
     _frontmatter: &'a Frontmatter,
     _cache: &'a CacheOperations,
     base_path: Option<&'a PathBuf>,
+    max_remote_bytes: u64,
+    language: Option<&'a str>,
+    report: &'a Mutex<PassReport>,
 ) -> Pin<Box<dyn Future<Output = Result<Vec<DarkMatterNode>, RenderError>> + Send + 'a>> {
     Box::pin(async move {
     match node {
         DarkMatterNode::File { resource, range } => {
-            // 1. Resolve the resource path if relative
-            let resolved_resource = resolve_resource_path(resource, base_path)?;
+            // 1. Resolve the resource path if relative, and to its
+            //    translation when rendering in a language
+            let mut resolved_resource = resolve_resource_path(resource, base_path)?;
+            if let Some(language) = language {
+                let localized = resolved_resource.localized(language);
+                report.lock().unwrap_or_else(|e| e.into_inner()).language_variants.push(LanguageVariant {
+                    language: language.to_string(),
+                    resource: resolved_resource,
+                    used: localized.clone(),
+                });
+                resolved_resource = localized;
+            }
 
-            // 2. Load resource content using the resolved path
-            let content = load_resource(&resolved_resource, _cache, None).await?;
+            // 2. Load resource content from the first candidate that loads
+            let loaded = load_resource_candidate(&resolved_resource, |candidate| {
+                load_resource(candidate, _cache, None, max_remote_bytes)
+            })
+            .await;
+            let (used, content) = match loaded {
+                Err(e @ (RenderError::ResourceNotFound(..) | RenderError::RemoteFetchError { .. }))
+                    if matches!(resolved_resource.requirement, ResourceRequirement::Optional) =>
+                {
+                    report.lock().unwrap_or_else(|e| e.into_inner()).warnings.push(ParseWarning {
+                        span: None,
+                        message: format!("Optional resource {} not found: {}", resource.label(), e),
+                        code: ParseWarningCode::OptionalResourceNotFound,
+                    });
+                    return Ok(Vec::new());
+                }
+                result => result?,
+            };
+            if !std::ptr::eq(used, &resolved_resource) {
+                report.lock().unwrap_or_else(|e| e.into_inner()).fallbacks.push(FallbackUsed {
+                    resource: resolved_resource.clone(),
+                    used: used.clone(),
+                });
+            }
 
-            // 3. Apply line range if specified
-            let content = apply_line_range(&content, range)?;
+            // 3. Apply line range or region if specified
+            let mut range_warnings = Vec::new();
+            let content = apply_line_range(&content, range, used, &mut range_warnings)?;
+            report.lock().unwrap_or_else(|e| e.into_inner()).warnings.extend(range_warnings);
 
             // 4. Parse the transcluded content
-            let doc = parse_document(&content, resolved_resource.clone())
-                .map_err(|e| RenderError::ParseError(e.to_string()))?;
+            let (doc, _warnings) = parse_document_unchecked(&content, used.clone())?;
 
             // 5. Recursively resolve transclusions in the transcluded content
             //    Now use the resolved resource as the base path
@@ -92,7 +163,10 @@ pub fn resolve_transclusion<'a>(
                     child,
                     &doc.frontmatter,
                     _cache,
-                    extract_base_path(&resolved_resource),
+                    extract_base_path(used),
+                    max_remote_bytes,
+                    language,
+                    report,
                 )
                 .await?;
                 resolved.extend(resolved_children);
@@ -110,7 +184,7 @@ pub fn resolve_transclusion<'a>(
                     let resolved_resource = resolve_resource_path(resource, base_path)?;
 
                     // Load and parse CSV
-                    let csv_data = load_csv_data(&resolved_resource).await?;
+                    let csv_data = load_csv_data(&resolved_resource, max_remote_bytes).await?;
 
                     // Return as inline table
                     Ok(vec![DarkMatterNode::Table {
@@ -124,6 +198,45 @@ pub fn resolve_transclusion<'a>(
                 }
             }
         }
+        DarkMatterNode::DataList { source: ListSource::External { resource, columns }, ordered, task_list } => {
+            // Read the list's items so it renders without further I/O
+            let resolved_resource = resolve_resource_path(resource, base_path)?;
+            let (used, content) = load_resource_candidate(&resolved_resource, |candidate| {
+                load_resource(candidate, _cache, None, max_remote_bytes)
+            })
+            .await?;
+            if !std::ptr::eq(used, &resolved_resource) {
+                report.lock().unwrap_or_else(|e| e.into_inner()).fallbacks.push(FallbackUsed {
+                    resource: resolved_resource.clone(),
+                    used: used.clone(),
+                });
+            }
+
+            let json = source_extension(used).eq_ignore_ascii_case("json");
+            let items = read_list_items(&content, json, columns, *task_list, &resource.label())?;
+            if items.is_empty() {
+                report.lock().unwrap_or_else(|e| e.into_inner()).warnings.push(ParseWarning {
+                    span: None,
+                    message: format!("Data file {} has no rows; its list is empty", resource.label()),
+                    code: ParseWarningCode::EmptyData,
+                });
+            }
+
+            Ok(vec![DarkMatterNode::DataList {
+                source: ListSource::Inline(items),
+                ordered: *ordered,
+                task_list: *task_list,
+            }])
+        }
+        DarkMatterNode::Diff { old, new, language, context_lines } => {
+            // Load both sides so the diff renders without further I/O
+            Ok(vec![DarkMatterNode::Diff {
+                old: load_diff_side(old, _cache, base_path, max_remote_bytes).await?,
+                new: load_diff_side(new, _cache, base_path, max_remote_bytes).await?,
+                language: language.clone(),
+                context_lines: *context_lines,
+            }])
+        }
         // Pass through other nodes unchanged
         other => Ok(vec![other.clone()]),
     }
@@ -131,10 +244,11 @@ pub fn resolve_transclusion<'a>(
 }
 
 /// Load resource content from filesystem or cache
-async fn load_resource(
+pub(super) async fn load_resource(
     resource: &Resource,
     _cache: &CacheOperations,
     base_path: Option<&PathBuf>,
+    max_remote_bytes: u64,
 ) -> Result<String, RenderError> {
     match &resource.source {
         ResourceSource::Local(path) => {
@@ -158,90 +272,134 @@ async fn load_resource(
                 .collect();
             full_path = normalized;
 
-            // Read from filesystem
-            fs::read_to_string(&full_path)
-                .map_err(|e| RenderError::ResourceNotFound(full_path.display().to_string(), e.to_string()))
+            // Read from filesystem; the lossy-decoding warning reaches the
+            // render report when this file is rendered as a document itself
+            let bytes = fs::read(&full_path)
+                .map_err(|e| RenderError::ResourceNotFound(full_path.display().to_string(), e.to_string()))?;
+            let (content, _warning) = decode_text(&full_path, bytes, resource.requirement)?;
+            Ok(content)
         }
-        ResourceSource::Remote(url) => {
-            // Check cache first
-            let url_str = url.to_string();
-
-            // For now, we'll use reqwest to fetch remote content
-            // In a full implementation, this would check cache first
-            let response = reqwest::get(url.clone())
+        ResourceSource::Remote(url) => fetch_text(url, max_remote_bytes).await,
+        ResourceSource::Git { repo_url, ref_, path } => {
+            crate::graph::git::read_git_file(repo_url, ref_, path)
                 .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str.clone(), e.to_string()))?;
-
-            if !response.status().is_success() {
-                return Err(RenderError::RemoteFetchError(
-                    url_str,
-                    format!("HTTP {}", response.status()),
-                ));
-            }
-
-            response
-                .text()
-                .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str, e.to_string()))
+                .map_err(|e| RenderError::ResourceNotFound(
+                    git_location(repo_url, ref_, path),
+                    e.to_string()
+                ))
         }
+        ResourceSource::Memory { content, .. } => Ok(content.to_string()),
     }
 }
 
+/// Load one side of a diff into memory
+///
+/// The memory resource is named after the original reference, which the
+/// diff's file header shows.
+async fn load_diff_side(
+    side: &Resource,
+    cache: &CacheOperations,
+    base_path: Option<&PathBuf>,
+    max_remote_bytes: u64,
+) -> Result<Resource, RenderError> {
+    if side.is_memory() {
+        return Ok(side.clone());
+    }
+    let resolved_resource = resolve_resource_path(side, base_path)?;
+    let content = load_resource(&resolved_resource, cache, None, max_remote_bytes).await?;
+    Ok(Resource::memory(side.label(), content))
+}
+
 /// Apply line range filtering to content
-fn apply_line_range(content: &str, range: &Option<LineRange>) -> Result<String, RenderError> {
-    let Some(range) = range else {
-        return Ok(content.to_string());
+///
+/// A start line past the end of the content is an error. An end line past the
+/// end is clamped to the last line, with a warning added to `warnings`. A
+/// named region is found with the comment syntax of `resource`'s file
+/// extension.
+fn apply_line_range(
+    content: &str,
+    range: &Option<LineRange>,
+    resource: &Resource,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<String, RenderError> {
+    let (start, end) = match range {
+        None => return Ok(content.to_string()),
+        Some(LineRange::Named(name)) => {
+            return extract_region(content, name, source_extension(resource)).map(str::to_string);
+        }
+        Some(LineRange::Lines(start, end)) => (*start, *end),
     };
 
     let lines: Vec<&str> = content.lines().collect();
     let total_lines = lines.len();
 
     // Validate start line
-    if range.start == 0 {
+    if start == 0 {
         return Err(RenderError::InvalidLineRange(
             "Line numbers are 1-indexed, cannot start at 0".to_string(),
         ));
     }
 
-    if range.start > total_lines {
+    if start > total_lines {
         return Err(RenderError::InvalidLineRange(format!(
             "Start line {} exceeds document length {}",
-            range.start, total_lines
+            start, total_lines
         )));
     }
 
     // Determine end line
-    let end = range.end.unwrap_or(total_lines);
+    let mut end = end.unwrap_or(total_lines);
 
-    if end < range.start {
+    if end < start {
         return Err(RenderError::InvalidLineRange(format!(
             "End line {} is before start line {}",
-            end, range.start
+            end, start
         )));
     }
 
     if end > total_lines {
-        return Err(RenderError::InvalidLineRange(format!(
-            "End line {} exceeds document length {}",
-            end, total_lines
-        )));
+        warnings.push(ParseWarning {
+            span: None,
+            message: format!(
+                "End line {} of {} exceeds its length {}; using line {} instead",
+                end,
+                resource.label(),
+                total_lines,
+                total_lines
+            ),
+            code: ParseWarningCode::LineRangeClamped,
+        });
+        end = total_lines;
     }
 
     // Extract range (converting from 1-indexed to 0-indexed)
-    let selected_lines = &lines[(range.start - 1)..end];
+    let selected_lines = &lines[(start - 1)..end];
     Ok(selected_lines.join("\n"))
 }
 
+/// The file extension of a resource's path, or `""` when it has none
+fn source_extension(resource: &Resource) -> &str {
+    let path = match &resource.source {
+        ResourceSource::Local(path) => path.to_str(),
+        ResourceSource::Remote(url) => Some(url.path()),
+        ResourceSource::Git { path, .. } => Some(path.as_str()),
+        ResourceSource::Memory { .. } => None,
+    };
+    path.and_then(|path| Path::new(path).extension())
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+}
+
 /// Extract base path from a resource for resolving relative paths
 fn extract_base_path(resource: &Resource) -> Option<&PathBuf> {
     match &resource.source {
         ResourceSource::Local(path) => Some(path),
-        ResourceSource::Remote(_) => None,
+        ResourceSource::Remote(_) | ResourceSource::Git { .. } | ResourceSource::Memory { .. } => None,
     }
 }
 
 /// Load and parse CSV data from a resource
-async fn load_csv_data(resource: &Resource) -> Result<Vec<Vec<String>>, RenderError> {
+pub(super) async fn load_csv_data(resource: &Resource, max_remote_bytes: u64) -> Result<Vec<Vec<String>>, RenderError> {
     // Load the CSV content
     let content = match &resource.source {
         ResourceSource::Local(path) => {
@@ -251,115 +409,108 @@ async fn load_csv_data(resource: &Resource) -> Result<Vec<Vec<String>>, RenderEr
                     e.to_string()
                 ))?
         }
-        ResourceSource::Remote(url) => {
-            let url_str = url.to_string();
-            let response = reqwest::get(url.clone())
-                .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str.clone(), e.to_string()))?;
-
-            if !response.status().is_success() {
-                return Err(RenderError::RemoteFetchError(
-                    url_str,
-                    format!("HTTP {}", response.status()),
-                ));
-            }
-
-            response
-                .text()
+        ResourceSource::Remote(url) => fetch_text(url, max_remote_bytes).await?,
+        ResourceSource::Git { repo_url, ref_, path } => {
+            crate::graph::git::read_git_file(repo_url, ref_, path)
                 .await
-                .map_err(|e| RenderError::RemoteFetchError(url_str, e.to_string()))?
+                .map_err(|e| RenderError::ResourceNotFound(
+                    git_location(repo_url, ref_, path),
+                    e.to_string()
+                ))?
         }
+        ResourceSource::Memory { content, .. } => content.to_string(),
     };
 
-    // Parse the CSV
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(false)
-        .from_reader(content.as_bytes());
-
-    let mut rows = Vec::new();
-    for result in reader.records() {
-        let record = result.map_err(|e| RenderError::CsvError(e.to_string()))?;
-        let row: Vec<String> = record.iter().map(|s| s.to_string()).collect();
-        rows.push(row);
-    }
-
-    Ok(rows)
+    super::table::parse_csv(&content)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn resource() -> Resource {
+        Resource::local(PathBuf::from("doc.md"))
+    }
+
+    #[test]
+    fn test_apply_line_range_named_region() {
+        let content = "import os\n# region: setup\nos.chdir('/')\n# endregion: setup\n";
+        let range = Some(LineRange::Named("setup".to_string()));
+
+        let script = Resource::local(PathBuf::from("./tools/run.py"));
+        let result = apply_line_range(content, &range, &script, &mut Vec::new()).unwrap();
+        assert_eq!(result, "os.chdir('/')\n");
+
+        // Markdown files mark regions with HTML comments
+        assert!(apply_line_range(content, &range, &resource(), &mut Vec::new()).is_err());
+    }
+
     #[test]
     fn test_apply_line_range_full() {
         let content = "line1\nline2\nline3\nline4";
-        let result = apply_line_range(content, &None).unwrap();
+        let result = apply_line_range(content, &None, &resource(), &mut Vec::new()).unwrap();
         assert_eq!(result, content);
     }
 
     #[test]
     fn test_apply_line_range_partial() {
         let content = "line1\nline2\nline3\nline4";
-        let range = Some(LineRange {
-            start: 2,
-            end: Some(3),
-        });
-        let result = apply_line_range(content, &range).unwrap();
+        let range = Some(LineRange::Lines(2, Some(3)));
+        let result = apply_line_range(content, &range, &resource(), &mut Vec::new()).unwrap();
         assert_eq!(result, "line2\nline3");
     }
 
     #[test]
     fn test_apply_line_range_from_start() {
         let content = "line1\nline2\nline3\nline4";
-        let range = Some(LineRange {
-            start: 1,
-            end: Some(2),
-        });
-        let result = apply_line_range(content, &range).unwrap();
+        let range = Some(LineRange::Lines(1, Some(2)));
+        let result = apply_line_range(content, &range, &resource(), &mut Vec::new()).unwrap();
         assert_eq!(result, "line1\nline2");
     }
 
     #[test]
     fn test_apply_line_range_to_end() {
         let content = "line1\nline2\nline3\nline4";
-        let range = Some(LineRange {
-            start: 3,
-            end: None,
-        });
-        let result = apply_line_range(content, &range).unwrap();
+        let range = Some(LineRange::Lines(3, None));
+        let result = apply_line_range(content, &range, &resource(), &mut Vec::new()).unwrap();
         assert_eq!(result, "line3\nline4");
     }
 
     #[test]
     fn test_apply_line_range_invalid_zero() {
         let content = "line1\nline2";
-        let range = Some(LineRange {
-            start: 0,
-            end: Some(1),
-        });
-        let result = apply_line_range(content, &range);
+        let range = Some(LineRange::Lines(0, Some(1)));
+        let result = apply_line_range(content, &range, &resource(), &mut Vec::new());
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_apply_line_range_out_of_bounds() {
+    fn test_apply_line_range_end_past_eof_is_clamped() {
         let content = "line1\nline2";
-        let range = Some(LineRange {
-            start: 1,
-            end: Some(10),
-        });
-        let result = apply_line_range(content, &range);
-        assert!(result.is_err());
+        let range = Some(LineRange::Lines(1, Some(10)));
+        let mut warnings = Vec::new();
+        let result = apply_line_range(content, &range, &resource(), &mut warnings).unwrap();
+        assert_eq!(result, "line1\nline2");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, ParseWarningCode::LineRangeClamped);
+        assert!(warnings[0].message.contains("End line 10"), "{}", warnings[0].message);
+    }
+
+    #[test]
+    fn test_apply_line_range_start_past_eof() {
+        let content = (1..=50).map(|i| format!("line{}", i)).collect::<Vec<_>>().join("\n");
+        let range = Some(LineRange::Lines(500, Some(600)));
+        match apply_line_range(&content, &range, &resource(), &mut Vec::new()) {
+            Err(RenderError::InvalidLineRange(message)) => assert!(message.contains("length 50")),
+            other => panic!("Expected InvalidLineRange, got: {:?}", other),
+        }
     }
 
     #[test]
     fn test_apply_line_range_reversed() {
         let content = "line1\nline2\nline3";
-        let range = Some(LineRange {
-            start: 3,
-            end: Some(1),
-        });
-        let result = apply_line_range(content, &range);
+        let range = Some(LineRange::Lines(3, Some(1)));
+        let result = apply_line_range(content, &range, &resource(), &mut Vec::new());
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,169 @@
+use crate::types::DarkMatterNode;
+use std::sync::Arc;
+
+/// A caller-supplied rewrite of document nodes
+///
+/// Transformers are registered on the API (see
+/// [`CompositionApi::with_transformer`](crate::CompositionApi::with_transformer))
+/// and run over every rendered document after the render passes, so they see
+/// transcluded and interpolated content and their output goes straight to
+/// HTML generation. This is the place for project-specific directives.
+pub trait NodeTransformer: Send + Sync {
+    /// Replace `node` with the returned nodes, or leave it as is with `None`
+    ///
+    /// Returning an empty `Vec` removes the node.
+    fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>>;
+}
+
+/// Run the transformers over a node tree
+///
+/// Transformers run in order, each one over every node the previous one
+/// produced. The children of layout nodes are transformed the same way once
+/// their parent has been.
+pub(crate) fn apply_transformers(
+    nodes: Vec<DarkMatterNode>,
+    transformers: &[Arc<dyn NodeTransformer>],
+) -> Vec<DarkMatterNode> {
+    if transformers.is_empty() {
+        return nodes;
+    }
+
+    let mut result = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let mut current = vec![node];
+        for transformer in transformers {
+            current = current
+                .into_iter()
+                .flat_map(|node| transformer.transform(&node).unwrap_or_else(|| vec![node]))
+                .collect();
+        }
+        result.extend(current.into_iter().map(|node| transform_children(node, transformers)));
+    }
+    result
+}
+
+/// Transform the children of a layout node
+fn transform_children(node: DarkMatterNode, transformers: &[Arc<dyn NodeTransformer>]) -> DarkMatterNode {
+    match node {
+        DarkMatterNode::Popover { trigger, content } => DarkMatterNode::Popover {
+            trigger: Box::new(
+                apply_transformers(vec![*trigger], transformers)
+                    .into_iter()
+                    .next()
+                    .unwrap_or(DarkMatterNode::Text(String::new())),
+            ),
+            content: apply_transformers(content, transformers),
+        },
+        DarkMatterNode::Columns { breakpoints, sections } => DarkMatterNode::Columns {
+            breakpoints,
+            sections: sections
+                .into_iter()
+                .map(|section| apply_transformers(section, transformers))
+                .collect(),
+        },
+        DarkMatterNode::Disclosure { summary, details } => DarkMatterNode::Disclosure {
+            summary: apply_transformers(summary, transformers),
+            details: apply_transformers(details, transformers),
+        },
+        DarkMatterNode::AspectRatio { numerator, denominator, content } => DarkMatterNode::AspectRatio {
+            numerator,
+            denominator,
+            content: apply_transformers(content, transformers),
+        },
+        DarkMatterNode::Footnote { id, content } => DarkMatterNode::Footnote {
+            id,
+            content: apply_transformers(content, transformers),
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Upper-cases text nodes
+    struct Shout;
+
+    impl NodeTransformer for Shout {
+        fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            match node {
+                DarkMatterNode::Text(text) => Some(vec![DarkMatterNode::Text(text.to_uppercase())]),
+                _ => None,
+            }
+        }
+    }
+
+    /// Splits text nodes into one node per word
+    struct SplitWords;
+
+    impl NodeTransformer for SplitWords {
+        fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+            match node {
+                DarkMatterNode::Text(text) => Some(
+                    text.split_whitespace()
+                        .map(|word| DarkMatterNode::Text(word.to_string()))
+                        .collect(),
+                ),
+                _ => None,
+            }
+        }
+    }
+
+    fn text(s: &str) -> DarkMatterNode {
+        DarkMatterNode::Text(s.to_string())
+    }
+
+    fn as_text(node: &DarkMatterNode) -> &str {
+        match node {
+            DarkMatterNode::Text(text) => text,
+            other => panic!("expected text node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_transformers_run_in_registration_order() {
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![Arc::new(SplitWords), Arc::new(Shout)];
+
+        let nodes = apply_transformers(vec![text("hello world")], &transformers);
+
+        assert_eq!(nodes.iter().map(as_text).collect::<Vec<_>>(), vec!["HELLO", "WORLD"]);
+    }
+
+    #[test]
+    fn test_transformers_leave_unmatched_nodes_alone() {
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![Arc::new(Shout)];
+        let node = DarkMatterNode::Interpolation { variable: "name".to_string() };
+
+        let nodes = apply_transformers(vec![node], &transformers);
+
+        assert!(matches!(
+            nodes.as_slice(),
+            [DarkMatterNode::Interpolation { variable }] if variable == "name"
+        ));
+    }
+
+    #[test]
+    fn test_transformers_reach_nested_content() {
+        let transformers: Vec<Arc<dyn NodeTransformer>> = vec![Arc::new(Shout)];
+        let node = DarkMatterNode::Disclosure {
+            summary: vec![text("more")],
+            details: vec![DarkMatterNode::AspectRatio {
+                numerator: 16,
+                denominator: 9,
+                content: vec![text("inside")],
+            }],
+        };
+
+        let nodes = apply_transformers(vec![node], &transformers);
+
+        let [DarkMatterNode::Disclosure { summary, details }] = nodes.as_slice() else {
+            panic!("expected a disclosure, got {:?}", nodes);
+        };
+        assert_eq!(as_text(&summary[0]), "MORE");
+        let [DarkMatterNode::AspectRatio { content, .. }] = details.as_slice() else {
+            panic!("expected an aspect ratio container, got {:?}", details);
+        };
+        assert_eq!(as_text(&content[0]), "INSIDE");
+    }
+}
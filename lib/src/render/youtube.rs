@@ -34,10 +34,21 @@ use std::sync::LazyLock;
 ///
 /// HTML string containing iframe, maximize button, and backdrop elements
 pub fn render_youtube_embed(video_id: &str, width: &WidthSpec) -> String {
-    generate_container_html(video_id, width)
+    generate_container_html(video_id, width, None)
+}
+
+/// Renders a YouTube embed that shows YouTube's captions in `language` from
+/// the start
+///
+/// An iframe can't take a `<track>`, so the captions are the ones YouTube
+/// has for the video; viewers can still turn them off.
+pub fn render_youtube_embed_with_captions(video_id: &str, width: &WidthSpec, language: &str) -> String {
+    generate_container_html(video_id, width, Some(language))
 }
 
 /// Returns the CSS required for YouTube embeds (called by orchestration layer)
+///
+/// Colors come from the [component theme](super::theme::component_theme_css).
 pub fn youtube_css() -> &'static str {
     &YOUTUBE_CSS
 }
@@ -48,15 +59,18 @@ pub fn youtube_js() -> &'static str {
 }
 
 /// Generate the container HTML with iframe and controls
-fn generate_container_html(video_id: &str, width: &WidthSpec) -> String {
+fn generate_container_html(video_id: &str, width: &WidthSpec, captions: Option<&str>) -> String {
     let width_css = width_to_css(width);
+    let captions_params = captions
+        .map(|language| format!("&amp;cc_load_policy=1&amp;cc_lang_pref={}", language))
+        .unwrap_or_default();
 
     format!(
         r#"<div class="dm-youtube-container" data-video-id="{}" data-width="{}">
   <div class="dm-youtube-wrapper">
     <iframe
       class="dm-youtube-player"
-      src="https://www.youtube.com/embed/{}?enablejsapi=1"
+      src="https://www.youtube.com/embed/{}?enablejsapi=1{}"
       frameborder="0"
       allow="accelerometer; autoplay; clipboard-write; encrypted-media; gyroscope; picture-in-picture"
       allowfullscreen
@@ -72,7 +86,8 @@ fn generate_container_html(video_id: &str, width: &WidthSpec) -> String {
 <div class="dm-youtube-backdrop" style="display: none;"></div>"#,
         video_id,
         width_css,
-        video_id
+        video_id,
+        captions_params
     )
 }
 
@@ -98,7 +113,7 @@ static YOUTUBE_CSS: LazyLock<String> = LazyLock::new(|| {
   padding-bottom: 56.25%; /* 16:9 aspect ratio */
   overflow: hidden;
   border-radius: 8px;
-  background: #000;
+  background: var(--dm-media-bg);
 }
 
 .dm-youtube-player {
@@ -114,10 +129,10 @@ static YOUTUBE_CSS: LazyLock<String> = LazyLock::new(|| {
   position: absolute;
   top: 12px;
   right: 12px;
-  background: rgba(0, 0, 0, 0.7);
+  background: var(--dm-overlay);
   border: none;
   border-radius: 4px;
-  color: white;
+  color: var(--dm-overlay-fg);
   cursor: pointer;
   padding: 8px;
   display: flex;
@@ -133,12 +148,12 @@ static YOUTUBE_CSS: LazyLock<String> = LazyLock::new(|| {
 }
 
 .dm-youtube-maximize:hover {
-  background: rgba(0, 0, 0, 0.9);
+  background: var(--dm-overlay-hover);
 }
 
 .dm-youtube-maximize:focus {
   opacity: 1;
-  outline: 2px solid #3b82f6;
+  outline: 2px solid var(--dm-accent);
   outline-offset: 2px;
 }
 
@@ -169,7 +184,7 @@ static YOUTUBE_CSS: LazyLock<String> = LazyLock::new(|| {
   left: 0;
   width: 100vw;
   height: 100vh;
-  background: rgba(0, 0, 0, 0.8);
+  background: var(--dm-backdrop);
   backdrop-filter: blur(8px);
   z-index: 9998;
   cursor: pointer;
@@ -398,6 +413,12 @@ mod tests {
         assert_eq!(width_to_css(&width), "80%");
     }
 
+    #[test]
+    fn test_width_to_css_viewport_and_auto() {
+        assert_eq!(width_to_css(&WidthSpec::Viewport(100)), "100vw");
+        assert_eq!(width_to_css(&WidthSpec::Auto), "auto");
+    }
+
     #[test]
     fn test_youtube_css_contains_container_styles() {
         let css = youtube_css();
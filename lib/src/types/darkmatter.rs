@@ -1,6 +1,9 @@
 use super::{Resource, Frontmatter};
-use serde::{Deserialize, Serialize};
+use crate::error::ParseError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 /// DarkMatter AST node representing various DSL elements
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,18 +35,50 @@ pub enum DarkMatterNode {
     },
     BarChart {
         data: ChartData,
+        /// Follow the chart with a visually hidden table of its data
+        with_table: bool,
     },
     LineChart {
         data: ChartData,
+        /// Follow the chart with a visually hidden table of its data
+        with_table: bool,
     },
     PieChart {
         data: ChartData,
+        /// Follow the chart with a visually hidden table of its data
+        with_table: bool,
+        #[serde(default)]
+        options: PieChartOptions,
     },
     AreaChart {
         data: ChartData,
+        /// Follow the chart with a visually hidden table of its data
+        with_table: bool,
     },
     BubbleChart {
         data: ChartData,
+        /// Follow the chart with a visually hidden table of its data
+        with_table: bool,
+    },
+    /// A table of environment variables, rendered into a Markdown node when
+    /// the document is interpolated
+    EnvTable {
+        filter: Option<String>,
+        sort: bool,
+        /// Show the values of variables in `allowed_env_vars` rather than
+        /// `[SET]`
+        show_values: bool,
+    },
+    /// Environment variables from `::require-env`, listed in every env table
+    RequireEnv {
+        vars: Vec<String>,
+    },
+    /// A list of the values in a data file's columns, one item per row
+    DataList {
+        source: ListSource,
+        ordered: bool,
+        /// Render GFM task items, checked by the row's last listed column
+        task_list: bool,
     },
 
     // Layout
@@ -59,15 +94,65 @@ pub enum DarkMatterNode {
         summary: Vec<DarkMatterNode>,
         details: Vec<DarkMatterNode>,
     },
+    /// Responsive container keeping its content at `numerator:denominator`
+    AspectRatio {
+        numerator: u32,
+        denominator: u32,
+        content: Vec<DarkMatterNode>,
+    },
+
+    // Footnotes
+    /// Footnote text, listed at the end of the rendered document
+    ///
+    /// Footnotes from `::footnote[id]` are defined where the directive
+    /// appears. Inline `^[text]` footnotes are also referenced there; their
+    /// `id` is empty until the renderer numbers them.
+    Footnote {
+        id: String,
+        content: Vec<DarkMatterNode>,
+    },
+    /// A `^[id]` reference to a footnote
+    FootnoteRef {
+        id: String,
+    },
 
     // Media
     Audio {
         source: String,
         name: Option<String>,
+        captions: Option<Captions>,
     },
     YouTube {
         video_id: String,
         width: super::youtube::WidthSpec,
+        /// Language of YouTube's own captions, shown from the start
+        captions: Option<String>,
+    },
+    /// A Mermaid diagram, rendered in the browser from its source
+    Mermaid {
+        source: String,
+    },
+    /// A terminal session: commands with their output
+    Terminal {
+        lines: Vec<TerminalLine>,
+        shell: TerminalShell,
+    },
+    /// A TeX formula, typeset in the browser; display math is set on its own
+    /// line
+    Math {
+        source: String,
+        display: bool,
+    },
+    /// A unified diff from `old` to `new`, showing `context_lines` unchanged
+    /// lines around each change
+    ///
+    /// `::diff --inline` compares two strings, which are held as memory
+    /// resources.
+    Diff {
+        old: Resource,
+        new: Resource,
+        language: Option<String>,
+        context_lines: usize,
     },
 
     // Text/content
@@ -78,11 +163,24 @@ pub enum DarkMatterNode {
     Markdown(MarkdownContent),
 }
 
-/// Line range for partial file transclusion
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LineRange {
-    pub start: usize,
-    pub end: Option<usize>,
+/// A WebVTT captions file for a media player's `<track>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Captions {
+    /// Path of the `.vtt` file
+    pub path: String,
+    /// Language of the captions (BCP 47, e.g. `en`), the track's `srclang`
+    pub language: String,
+}
+
+/// The part of a file to transclude
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineRange {
+    /// Lines `start` to `end`, 1-indexed and inclusive; without an end the
+    /// range runs to the end of the file
+    Lines(usize, Option<usize>),
+    /// The lines between a file's `region: name` and `endregion: name`
+    /// comments
+    Named(String),
 }
 
 /// Source for table data
@@ -92,13 +190,104 @@ pub enum TableSource {
     External(Resource),
 }
 
+/// Source for a data list's items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ListSource {
+    Inline(Vec<ListItem>),
+    /// A CSV file with a header row, or a JSON array, and the columns to
+    /// list from it; with no columns the first one is listed
+    External {
+        resource: Resource,
+        columns: Vec<String>,
+    },
+}
+
+/// One item of a data list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListItem {
+    pub text: String,
+    /// Whether a task item is done; always false for other lists
+    pub checked: bool,
+}
+
+impl DarkMatterNode {
+    /// The node lists a container holds: a popover's trigger and content,
+    /// each column, a disclosure's summary and details, an aspect-ratio
+    /// block's content and a footnote's text
+    ///
+    /// Other nodes have none.
+    pub fn children(&self) -> Vec<&[DarkMatterNode]> {
+        match self {
+            DarkMatterNode::Popover { trigger, content } => vec![std::slice::from_ref(trigger.as_ref()), content],
+            DarkMatterNode::Columns { sections, .. } => sections.iter().map(Vec::as_slice).collect(),
+            DarkMatterNode::Disclosure { summary, details } => vec![summary, details],
+            DarkMatterNode::AspectRatio { content, .. } | DarkMatterNode::Footnote { content, .. } => vec![content],
+            _ => Vec::new(),
+        }
+    }
+}
+
 /// Chart data source
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChartData {
     Inline(Vec<DataPoint>),
+    /// Named series over shared x labels, as loaded from a CSV file
+    Series(ChartSeries),
     External(Resource),
 }
 
+/// How a pie chart is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct PieChartOptions {
+    /// Radius of the hole in the middle, as a fraction of the chart's
+    /// radius: 0 draws a pie, anything above it a donut
+    pub inner_radius: f64,
+    /// Label each slice with its share of the total, in whole percents
+    /// that add up to 100
+    pub show_percentages: bool,
+}
+
+/// One or more named series of values over shared x-axis labels
+///
+/// Loaded from a CSV file whose first column holds the x labels and whose
+/// other columns each hold a series, named by the header row if there is one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChartSeries {
+    /// Heading of the x labels' column, drawn as the x-axis title
+    pub x_title: Option<String>,
+    pub x_labels: Vec<String>,
+    pub x_axis: AxisScale,
+    pub series: Vec<DataSeries>,
+}
+
+/// A named series of values, one per x label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataSeries {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// How the x labels of a chart are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AxisScale {
+    /// Labels are numbers, placed in proportion to their value
+    Numeric,
+    /// Labels are names, evenly spaced in order
+    #[default]
+    Categorical,
+}
+
+impl AxisScale {
+    /// Numeric when every label is a number
+    pub fn infer(labels: &[String]) -> Self {
+        if !labels.is_empty() && labels.iter().all(|label| label.trim().parse::<f64>().is_ok()) {
+            AxisScale::Numeric
+        } else {
+            AxisScale::Categorical
+        }
+    }
+}
+
 /// Data point for charts
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPoint {
@@ -108,7 +297,12 @@ pub struct DataPoint {
 }
 
 /// Responsive breakpoints (Tailwind-based)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Projects with their own design system add [`Custom`](Breakpoint::Custom)
+/// breakpoints through
+/// [`CompositionConfig::breakpoints`](crate::CompositionConfig::breakpoints);
+/// directives only ever produce the Tailwind ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Breakpoint {
     Micro, // 320px - mobile portrait
     Xs,    // 640px - mobile landscape (matches sm)
@@ -117,6 +311,215 @@ pub enum Breakpoint {
     Lg,    // 1024px
     Xl,    // 1280px
     Xxl,   // 1536px
+    /// A named breakpoint and its max-width in pixels, e.g. `("mobile", 480)`
+    Custom(String, u32),
+}
+
+impl Breakpoint {
+    /// Every breakpoint, smallest first
+    pub const ALL: [Breakpoint; 7] = [
+        Breakpoint::Micro,
+        Breakpoint::Xs,
+        Breakpoint::Sm,
+        Breakpoint::Md,
+        Breakpoint::Lg,
+        Breakpoint::Xl,
+        Breakpoint::Xxl,
+    ];
+
+    /// Tailwind-style name, as used in directives (`md`, `2xl`, ...), or a
+    /// custom breakpoint's own name
+    pub fn name(&self) -> &str {
+        match self {
+            Breakpoint::Micro => "micro",
+            Breakpoint::Xs => "xs",
+            Breakpoint::Sm => "sm",
+            Breakpoint::Md => "md",
+            Breakpoint::Lg => "lg",
+            Breakpoint::Xl => "xl",
+            Breakpoint::Xxl => "2xl",
+            Breakpoint::Custom(name, _) => name,
+        }
+    }
+
+    /// Width in pixels this breakpoint starts at
+    pub fn pixels(&self) -> u32 {
+        match self {
+            Breakpoint::Micro => 320,
+            Breakpoint::Xs => 640,
+            Breakpoint::Sm => 640,
+            Breakpoint::Md => 768,
+            Breakpoint::Lg => 1024,
+            Breakpoint::Xl => 1280,
+            Breakpoint::Xxl => 1536,
+            Breakpoint::Custom(_, pixels) => *pixels,
+        }
+    }
+
+    /// Media condition matching viewports up to this breakpoint, e.g.
+    /// `(max-width: 768px)`, as used in `<source media="...">`
+    pub fn media_condition(&self) -> String {
+        format!("(max-width: {}px)", self.pixels())
+    }
+
+    /// CSS media query matching viewports up to this breakpoint, e.g.
+    /// `@media (max-width: 768px)`
+    pub fn to_css_media_query(&self) -> String {
+        format!("@media {}", self.media_condition())
+    }
+}
+
+/// Tailwind breakpoints serialize by variant name (`"Md"`) and custom ones as
+/// a `[name, pixels]` pair, so a TOML config can list
+/// `[["mobile", 480], ["tablet", 900]]`
+impl Serialize for Breakpoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Breakpoint::Micro => serializer.serialize_unit_variant("Breakpoint", 0, "Micro"),
+            Breakpoint::Xs => serializer.serialize_unit_variant("Breakpoint", 1, "Xs"),
+            Breakpoint::Sm => serializer.serialize_unit_variant("Breakpoint", 2, "Sm"),
+            Breakpoint::Md => serializer.serialize_unit_variant("Breakpoint", 3, "Md"),
+            Breakpoint::Lg => serializer.serialize_unit_variant("Breakpoint", 4, "Lg"),
+            Breakpoint::Xl => serializer.serialize_unit_variant("Breakpoint", 5, "Xl"),
+            Breakpoint::Xxl => serializer.serialize_unit_variant("Breakpoint", 6, "Xxl"),
+            Breakpoint::Custom(name, pixels) => (name, pixels).serialize(serializer),
+        }
+    }
+}
+
+/// Accepts variant names (`"Md"`), directive names (`"md"`, `"2xl"`) and
+/// `[name, pixels]` pairs, which are always custom
+impl<'de> Deserialize<'de> for Breakpoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Name(String),
+            Custom(String, u32),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Name(name) => name.parse().map_err(serde::de::Error::custom),
+            Repr::Custom(name, pixels) => Ok(Breakpoint::Custom(name, pixels)),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = ParseError;
+
+    /// Parse a Tailwind-style name, ignoring case; `xxl` is accepted for `2xl`
+    ///
+    /// Unknown names fail with `ParseError::InvalidDirective` at line 0;
+    /// directive parsers report their own line instead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        if name == "xxl" {
+            return Ok(Breakpoint::Xxl);
+        }
+
+        Breakpoint::ALL
+            .into_iter()
+            .find(|bp| bp.name() == name)
+            .ok_or_else(|| ParseError::InvalidDirective {
+                line: 0,
+                directive: format!(
+                    "Unknown breakpoint '{}' (expected one of: {})",
+                    s,
+                    Breakpoint::ALL.iter().map(Breakpoint::name).collect::<Vec<_>>().join(", ")
+                ),
+                span: None,
+            })
+    }
+}
+
+/// A line of a `::terminal` session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TerminalLine {
+    /// A command, without its prompt
+    Command(String),
+    /// Output printed by a command
+    Output(String),
+    /// A `#` comment, without the `#`
+    Comment(String),
+}
+
+/// The shell a `::terminal` session runs in, which sets its prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TerminalShell {
+    #[default]
+    Bash,
+    Zsh,
+    PowerShell,
+    Fish,
+}
+
+impl TerminalShell {
+    /// Every shell, in the order they are listed in errors
+    pub const ALL: [TerminalShell; 4] = [
+        TerminalShell::Bash,
+        TerminalShell::Zsh,
+        TerminalShell::PowerShell,
+        TerminalShell::Fish,
+    ];
+
+    /// Name used by `--shell` (`bash`, `powershell`, ...)
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerminalShell::Bash => "bash",
+            TerminalShell::Zsh => "zsh",
+            TerminalShell::PowerShell => "powershell",
+            TerminalShell::Fish => "fish",
+        }
+    }
+
+    /// The prompt shown before commands
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            TerminalShell::PowerShell => "PS>",
+            TerminalShell::Bash | TerminalShell::Zsh | TerminalShell::Fish => "$",
+        }
+    }
+}
+
+impl fmt::Display for TerminalShell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TerminalShell {
+    type Err = ParseError;
+
+    /// Parse a shell name, ignoring case; `pwsh` is accepted for `powershell`
+    ///
+    /// Unknown names fail with `ParseError::InvalidDirective` at line 0;
+    /// directive parsers report their own line instead.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        if name == "pwsh" {
+            return Ok(TerminalShell::PowerShell);
+        }
+
+        TerminalShell::ALL
+            .into_iter()
+            .find(|shell| shell.name() == name)
+            .ok_or_else(|| ParseError::InvalidDirective {
+                line: 0,
+                directive: format!(
+                    "Unknown shell '{}' (expected one of: {})",
+                    s,
+                    TerminalShell::ALL.map(|shell| shell.name()).join(", ")
+                ),
+                span: None,
+            })
+    }
 }
 
 /// Markdown content wrapper
@@ -125,3 +528,74 @@ pub struct MarkdownContent {
     pub raw: String,
     pub frontmatter: Option<Frontmatter>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint_round_trip() {
+        let expected = ["micro", "xs", "sm", "md", "lg", "xl", "2xl"];
+        for (bp, name) in Breakpoint::ALL.into_iter().zip(expected) {
+            assert_eq!(bp.to_string(), name);
+            assert_eq!(name.parse::<Breakpoint>().unwrap(), bp);
+            assert_eq!(bp.to_string().parse::<Breakpoint>().unwrap(), bp);
+        }
+    }
+
+    #[test]
+    fn test_breakpoint_from_str_aliases() {
+        assert_eq!("xxl".parse::<Breakpoint>().unwrap(), Breakpoint::Xxl);
+        assert_eq!("MD".parse::<Breakpoint>().unwrap(), Breakpoint::Md);
+        assert_eq!(" lg ".parse::<Breakpoint>().unwrap(), Breakpoint::Lg);
+    }
+
+    #[test]
+    fn test_breakpoint_media_queries() {
+        assert_eq!(Breakpoint::Md.to_css_media_query(), "@media (max-width: 768px)");
+        assert_eq!(Breakpoint::Custom("mobile".into(), 480).to_css_media_query(), "@media (max-width: 480px)");
+        assert_eq!(Breakpoint::Custom("mobile".into(), 480).media_condition(), "(max-width: 480px)");
+        assert_eq!(Breakpoint::Custom("mobile".into(), 480).to_string(), "mobile");
+    }
+
+    #[test]
+    fn test_breakpoint_serde() {
+        let breakpoints: Vec<Breakpoint> = serde_json::from_str(r#"[["mobile", 480], ["tablet", 900], "Md", "2xl"]"#).unwrap();
+        assert_eq!(
+            breakpoints,
+            vec![
+                Breakpoint::Custom("mobile".into(), 480),
+                Breakpoint::Custom("tablet".into(), 900),
+                Breakpoint::Md,
+                Breakpoint::Xxl,
+            ]
+        );
+
+        let json = serde_json::to_string(&breakpoints).unwrap();
+        assert_eq!(json, r#"[["mobile",480],["tablet",900],"Md","Xxl"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Breakpoint>>(&json).unwrap(), breakpoints);
+        assert!(serde_json::from_str::<Breakpoint>(r#""huge""#).is_err());
+    }
+
+    #[test]
+    fn test_terminal_shell_from_str() {
+        for shell in TerminalShell::ALL {
+            assert_eq!(shell.to_string().parse::<TerminalShell>().unwrap(), shell);
+        }
+        assert_eq!("PWSH".parse::<TerminalShell>().unwrap(), TerminalShell::PowerShell);
+        assert!(matches!("cmd".parse::<TerminalShell>(), Err(ParseError::InvalidDirective { .. })));
+    }
+
+    #[test]
+    fn test_breakpoint_from_str_unknown() {
+        for name in ["", "3xl", "medium", "m d"] {
+            match name.parse::<Breakpoint>() {
+                Err(ParseError::InvalidDirective { directive, .. }) => {
+                    assert!(directive.contains("Unknown breakpoint"), "{}", directive);
+                    assert!(directive.contains("2xl"), "{}", directive);
+                }
+                other => panic!("'{}' should be rejected, got {:?}", name, other),
+            }
+        }
+    }
+}
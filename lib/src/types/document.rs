@@ -1,6 +1,11 @@
 use super::{DarkMatterNode, Frontmatter, Resource};
+use crate::error::RenderError;
+use crate::graph::utils::compute_json_hash;
+use crate::render::{markdown_headings, write_markdown};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use std::collections::BTreeSet;
 
 /// A parsed DarkMatter document
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,4 +42,246 @@ impl Document {
         self.dependencies = dependencies;
         self
     }
+
+    /// What changed from this version of the document to `other`
+    ///
+    /// Top-level nodes are matched by a hash of their structure, so a node
+    /// only counts as changed when its parsed form differs. Where nodes were
+    /// replaced, they are paired up in order as changed and any surplus is
+    /// added or removed.
+    ///
+    /// Fails when a node or the frontmatter can't be represented as JSON,
+    /// such as a map with non-string keys.
+    pub fn diff(&self, other: &Document) -> serde_json::Result<DocumentDiff> {
+        let hashes = |content: &[DarkMatterNode]| {
+            content.iter().map(compute_json_hash).collect::<serde_json::Result<Vec<u64>>>()
+        };
+        let (old, new) = (hashes(&self.content)?, hashes(&other.content)?);
+
+        let mut diff = DocumentDiff::default();
+        for op in capture_diff_slices(Algorithm::Myers, &old, &new) {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            match tag {
+                DiffTag::Equal => {}
+                DiffTag::Delete => diff.removed.extend(old_range),
+                DiffTag::Insert => diff.added.extend(new_range),
+                DiffTag::Replace => {
+                    let paired = old_range.len().min(new_range.len());
+                    diff.changed.extend(old_range.clone().zip(new_range.clone()));
+                    diff.removed.extend(old_range.skip(paired));
+                    diff.added.extend(new_range.skip(paired));
+                }
+            }
+        }
+        diff.frontmatter = frontmatter_changes(&self.frontmatter, &other.frontmatter)?;
+
+        Ok(diff)
+    }
+
+    /// The document as DarkMatter Markdown, which parses back into an
+    /// equivalent document
+    ///
+    /// The frontmatter is written as YAML between `---` lines and each node
+    /// in the syntax it is parsed from. Nodes are written unresolved, so AI
+    /// directives such as `::summarize` stay directives. Nodes with no source
+    /// syntax, such as charts holding their data or transclusions of
+    /// in-memory resources, fail with [`RenderError::MarkdownFailed`].
+    pub fn to_markdown(&self) -> Result<String, RenderError> {
+        write_markdown(&self.frontmatter, &self.content)
+    }
+
+    /// The document's markdown headings as a tree, each heading holding the
+    /// deeper headings that follow it
+    ///
+    /// A heading whose level is skipped (a `###` straight after a `#`) is
+    /// still nested under the nearest shallower heading.
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut roots: Vec<Heading> = Vec::new();
+        // Headings still open for children, shallowest first
+        let mut stack: Vec<Heading> = Vec::new();
+
+        let close = |heading: Heading, stack: &mut Vec<Heading>, roots: &mut Vec<Heading>| match stack.last_mut() {
+            Some(parent) => parent.children.push(heading),
+            None => roots.push(heading),
+        };
+
+        for heading in self.flat_headings() {
+            while stack.last().is_some_and(|open| open.level >= heading.level) {
+                let done = stack.pop().expect("stack is not empty");
+                close(done, &mut stack, &mut roots);
+            }
+            stack.push(heading);
+        }
+        while let Some(done) = stack.pop() {
+            close(done, &mut stack, &mut roots);
+        }
+
+        roots
+    }
+
+    /// The document's markdown headings in document order, without children
+    pub fn flat_headings(&self) -> Vec<Heading> {
+        self.content
+            .iter()
+            .filter_map(|node| match node {
+                DarkMatterNode::Markdown(content) => Some(markdown_headings(&content.raw)),
+                _ => None,
+            })
+            .flatten()
+            .map(|heading| Heading { level: heading.level, text: heading.text, id: heading.slug, children: Vec::new() })
+            .collect()
+    }
+}
+
+/// A markdown heading, from [`Document::headings`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heading {
+    /// 1 for `#` through 6 for `######`
+    pub level: u8,
+    /// The heading's text, without markup
+    pub text: String,
+    /// Anchor id: an explicit `{#id}`, or else the slug that section
+    /// transclusion matches against
+    pub id: String,
+    /// Deeper headings up to the next heading of this level or higher
+    pub children: Vec<Heading>,
+}
+
+/// The changes between two versions of a document, from [`Document::diff`]
+///
+/// Nodes are referred to by their index in the `content` of the old or the
+/// new version.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentDiff {
+    /// Indices of nodes only in the new version
+    pub added: Vec<usize>,
+    /// Indices of nodes only in the old version
+    pub removed: Vec<usize>,
+    /// Old and new indices of nodes that were edited
+    pub changed: Vec<(usize, usize)>,
+    /// Frontmatter keys that were added, removed or given another value
+    pub frontmatter: Vec<FrontmatterChange>,
+}
+
+impl DocumentDiff {
+    /// Whether the two versions are the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.frontmatter.is_empty()
+    }
+}
+
+/// A change to one frontmatter key, reserved keys included
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrontmatterChange {
+    Added { key: String, value: serde_json::Value },
+    Removed { key: String, value: serde_json::Value },
+    Changed { key: String, old: serde_json::Value, new: serde_json::Value },
+}
+
+/// The key changes between two frontmatters, in key order
+fn frontmatter_changes(old: &Frontmatter, new: &Frontmatter) -> serde_json::Result<Vec<FrontmatterChange>> {
+    let as_map = |frontmatter: &Frontmatter| {
+        serde_json::to_value(frontmatter).map(|value| match value {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        })
+    };
+    let (old, new) = (as_map(old)?, as_map(new)?);
+
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| match (old.get(key), new.get(key)) {
+            (Some(value), None) => Some(FrontmatterChange::Removed { key: key.clone(), value: value.clone() }),
+            (None, Some(value)) => Some(FrontmatterChange::Added { key: key.clone(), value: value.clone() }),
+            (Some(old), Some(new)) if old != new => Some(FrontmatterChange::Changed {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_document, ParseOptions};
+    use serde_json::json;
+
+    fn parse(content: &str) -> Document {
+        parse_document(content, Resource::memory("doc.md", content), ParseOptions::default()).unwrap().0
+    }
+
+    #[test]
+    fn test_diff_categorizes_changed_and_added_nodes() {
+        let old = parse("---\ntitle: Guide\ndraft: true\n---\nFirst\n::summarize ./a.md\nSecond");
+        let new = parse(
+            "---\ntitle: Guide, revised\ntags: [rust]\n---\nFirst, edited\n::summarize ./a.md\nSecond\n::summarize ./b.md\nThird",
+        );
+
+        let diff = old.diff(&new).unwrap();
+
+        // The first paragraph was edited; a directive and the paragraph
+        // after it were added
+        assert_eq!(diff.changed, vec![(0, 0)]);
+        assert_eq!(diff.added, vec![3, 4]);
+        assert!(diff.removed.is_empty());
+        assert!(matches!(&new.content[4], DarkMatterNode::Markdown(md) if md.raw.contains("Third")));
+
+        assert_eq!(
+            diff.frontmatter,
+            vec![
+                FrontmatterChange::Removed { key: "draft".to_string(), value: json!(true) },
+                FrontmatterChange::Added { key: "tags".to_string(), value: json!(["rust"]) },
+                FrontmatterChange::Changed {
+                    key: "title".to_string(),
+                    old: json!("Guide"),
+                    new: json!("Guide, revised"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_of_reparsed_document_is_empty() {
+        let content = "Intro\n::summarize ./a.md\nOutro";
+
+        assert!(parse(content).diff(&parse(content)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_headings_nest_by_level() {
+        let doc = parse("# Guide\n\n## Install {#setup}\n\n### Linux\n\n::summarize ./a.md\n\n## `Usage`\n\n# Appendix\n");
+
+        let flat: Vec<_> = doc.flat_headings().into_iter().map(|h| (h.level, h.id)).collect();
+        assert_eq!(
+            flat,
+            vec![(1, "guide".into()), (2, "setup".into()), (3, "linux".into()), (2, "usage".into()), (1, "appendix".into())]
+        );
+
+        let tree = doc.headings();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].children.iter().map(|h| h.text.as_str()).collect::<Vec<_>>(), vec!["Install", "Usage"]);
+        assert_eq!(tree[0].children[0].children[0].id, "linux");
+        assert!(tree[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_headings_with_skipped_levels() {
+        let tree = parse("### Deep\n\n# Top\n\n### Child\n\n## Sibling\n").headings();
+
+        assert_eq!(tree.iter().map(|h| h.text.as_str()).collect::<Vec<_>>(), vec!["Deep", "Top"]);
+        let children: Vec<_> = tree[1].children.iter().map(|h| (h.level, h.text.as_str())).collect();
+        assert_eq!(children, vec![(3, "Child"), (2, "Sibling")]);
+    }
+
+    #[test]
+    fn test_diff_reports_removed_nodes() {
+        let diff = parse("Intro\n::summarize ./a.md\nOutro").diff(&parse("Intro\n")).unwrap();
+
+        assert_eq!(diff.removed, vec![1, 2]);
+        assert!(diff.added.is_empty());
+    }
 }
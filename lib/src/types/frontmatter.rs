@@ -1,5 +1,8 @@
+use crate::error::ParseError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 /// Frontmatter metadata for DarkMatter documents
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -45,11 +48,110 @@ pub struct Breakpoints {
     pub xxl: Option<u32>,
 }
 
+/// JSON type of a frontmatter value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontmatterType {
+    String,
+    Number,
+    Boolean,
+    Array,
+    Object,
+    Null,
+}
+
+impl FrontmatterType {
+    /// The type of a JSON value
+    pub fn of(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(_) => Self::String,
+            serde_json::Value::Number(_) => Self::Number,
+            serde_json::Value::Bool(_) => Self::Boolean,
+            serde_json::Value::Array(_) => Self::Array,
+            serde_json::Value::Object(_) => Self::Object,
+            serde_json::Value::Null => Self::Null,
+        }
+    }
+}
+
+impl fmt::Display for FrontmatterType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+            Self::Array => "array",
+            Self::Object => "object",
+            Self::Null => "null",
+        })
+    }
+}
+
+/// Keys a document's frontmatter is expected to have (see [`Frontmatter::validate`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrontmatterSchema {
+    /// Required keys and the type of their values, in the order they are checked
+    pub required: Vec<(String, FrontmatterType)>,
+}
+
+impl FrontmatterSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `key`, holding a value of type `ty`
+    pub fn require(mut self, key: impl Into<String>, ty: FrontmatterType) -> Self {
+        self.required.push((key.into(), ty));
+        self
+    }
+}
+
 impl Frontmatter {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Whether no property, user-defined or reserved, is set
+    pub fn is_empty(&self) -> bool {
+        self.custom.is_empty()
+            && self.list_expansion.is_none()
+            && self.replace.is_none()
+            && self.summarize_model.is_none()
+            && self.consolidate_model.is_none()
+            && self.breakpoints.is_none()
+    }
+
+    /// Check the frontmatter against a schema
+    ///
+    /// Reserved properties are checked under their document names, like
+    /// user-defined ones. Fails with `ParseError::InvalidFrontmatter` listing
+    /// every required key that is missing or holds a value of another type.
+    pub fn validate(&self, schema: &FrontmatterSchema) -> Result<(), ParseError> {
+        let values = match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(values)) => values,
+            Ok(_) => serde_json::Map::new(),
+            Err(e) => return Err(ParseError::InvalidFrontmatter(e.to_string())),
+        };
+
+        let problems: Vec<String> = schema
+            .required
+            .iter()
+            .filter_map(|(key, expected)| match values.get(key).map(FrontmatterType::of) {
+                None => Some(format!("missing required key '{}'", key)),
+                Some(found) if found != *expected => {
+                    Some(format!("key '{}' should be {} but is {}", key, expected, found))
+                }
+                Some(_) => None,
+            })
+            .collect();
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseError::InvalidFrontmatter(problems.join("; ")))
+        }
+    }
+
     pub fn merge(&mut self, other: Frontmatter) {
         // Merge custom fields (other takes precedence)
         for (key, value) in other.custom {
@@ -78,7 +180,154 @@ impl Frontmatter {
         self.custom.get(key).and_then(|v| v.as_str())
     }
 
+    /// Deserialize a user-defined field into `T`
+    ///
+    /// Returns `None` when the key is missing and `Some(Err(..))` when the value
+    /// can't be deserialized as `T`.
+    pub fn get_typed<T: DeserializeOwned>(&self, key: &str) -> Option<Result<T, ParseError>> {
+        self.custom.get(key).map(|value| {
+            serde_json::from_value(value.clone()).map_err(|e| {
+                ParseError::InvalidFrontmatter(format!("field '{}': {}", key, e))
+            })
+        })
+    }
+
+    /// Like [`get_typed`](Self::get_typed), but a missing field is an error too
+    pub fn require_typed<T: DeserializeOwned>(&self, key: &str) -> Result<T, ParseError> {
+        self.get_typed(key).unwrap_or_else(|| {
+            Err(ParseError::InvalidFrontmatter(format!(
+                "missing required field '{}'",
+                key
+            )))
+        })
+    }
+
     pub fn get_bool(&self, key: &str) -> Option<bool> {
-        self.custom.get(key).and_then(|v| v.as_bool())
+        self.get_typed(key)?.ok()
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get_typed(key)?.ok()
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.get_typed(key)?.ok()
+    }
+
+    pub fn get_string_array(&self, key: &str) -> Option<Vec<String>> {
+        self.get_typed(key)?.ok()
+    }
+
+    /// Deserialize the whole frontmatter into a user-defined config struct
+    ///
+    /// Both user-defined and reserved properties are visible to `T`, under the
+    /// names they have in the document (e.g. `summarize_model`).
+    pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T, ParseError> {
+        serde_json::to_value(self)
+            .and_then(serde_json::from_value)
+            .map_err(|e| ParseError::InvalidFrontmatter(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn frontmatter() -> Frontmatter {
+        let mut fm = Frontmatter::new();
+        fm.custom.insert("tags".into(), json!(["rust", "docs"]));
+        fm.custom.insert("draft".into(), json!(true));
+        fm.custom.insert("weight".into(), json!(3));
+        fm.custom.insert("title".into(), json!("Guide"));
+        fm
+    }
+
+    #[test]
+    fn test_get_typed() {
+        let fm = frontmatter();
+
+        let tags: Vec<String> = fm.get_typed("tags").unwrap().unwrap();
+        assert_eq!(tags, vec!["rust", "docs"]);
+        assert!(fm.get_typed::<Vec<String>>("missing").is_none());
+        assert!(fm.get_typed::<Vec<String>>("title").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_require_typed() {
+        let fm = frontmatter();
+
+        assert_eq!(fm.require_typed::<u32>("weight").unwrap(), 3);
+        assert!(matches!(
+            fm.require_typed::<u32>("missing"),
+            Err(ParseError::InvalidFrontmatter(_))
+        ));
+        assert!(matches!(
+            fm.require_typed::<bool>("weight"),
+            Err(ParseError::InvalidFrontmatter(_))
+        ));
+    }
+
+    #[test]
+    fn test_convenience_getters() {
+        let fm = frontmatter();
+
+        assert_eq!(fm.get_bool("draft"), Some(true));
+        assert_eq!(fm.get_i64("weight"), Some(3));
+        assert_eq!(fm.get_f64("weight"), Some(3.0));
+        assert_eq!(fm.get_string_array("tags"), Some(vec!["rust".into(), "docs".into()]));
+        // Wrong types read as absent
+        assert_eq!(fm.get_bool("title"), None);
+        assert_eq!(fm.get_string_array("weight"), None);
+    }
+
+    #[test]
+    fn test_deserialize_into() {
+        #[derive(Deserialize)]
+        struct Config {
+            title: String,
+            #[serde(default)]
+            draft: bool,
+            summarize_model: Option<String>,
+        }
+
+        let mut fm = frontmatter();
+        fm.summarize_model = Some("gpt-4o".into());
+
+        let config: Config = fm.deserialize_into().unwrap();
+        assert_eq!(config.title, "Guide");
+        assert!(config.draft);
+        assert_eq!(config.summarize_model.as_deref(), Some("gpt-4o"));
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Strict {
+            title: u32,
+        }
+        assert!(fm.deserialize_into::<Strict>().is_err());
+    }
+
+    #[test]
+    fn test_validate_lists_every_problem() {
+        let fm = frontmatter();
+        let schema = FrontmatterSchema::new()
+            .require("title", FrontmatterType::String)
+            .require("tags", FrontmatterType::Array)
+            .require("author", FrontmatterType::String)
+            .require("draft", FrontmatterType::String);
+
+        match fm.validate(&schema) {
+            Err(ParseError::InvalidFrontmatter(message)) => assert_eq!(
+                message,
+                "missing required key 'author'; key 'draft' should be string but is boolean"
+            ),
+            other => panic!("Expected InvalidFrontmatter, got {:?}", other),
+        }
+
+        let mut reserved = Frontmatter::new();
+        reserved.summarize_model = Some("openai/gpt-4o-mini".to_string());
+        assert!(reserved
+            .validate(&FrontmatterSchema::new().require("summarize_model", FrontmatterType::String))
+            .is_ok());
     }
 }
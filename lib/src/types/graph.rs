@@ -1,6 +1,10 @@
 use super::{Resource, ResourceHash};
+use crate::error::{CacheError, CompositionError, Result};
+use crate::graph::FileStat;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 
 /// Dependency graph for a document tree
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,6 +12,17 @@ pub struct DependencyGraph {
     pub root: Resource,
     pub nodes: HashMap<ResourceHash, GraphNode>,
     pub edges: Vec<(ResourceHash, ResourceHash)>,
+    /// Resources whose cached document entry was stale when the graph was
+    /// built, plus everything that depends on them
+    #[serde(default)]
+    pub invalidated: HashSet<ResourceHash>,
+    /// Size and mtime of local files, taken before they were read
+    #[serde(default)]
+    pub file_stats: HashMap<ResourceHash, FileStat>,
+    /// Root that local resources were hashed relative to (see
+    /// [`compute_resource_hash`](crate::graph::compute_resource_hash))
+    #[serde(default)]
+    pub project_root: Option<PathBuf>,
 }
 
 /// Node in the dependency graph
@@ -23,6 +38,14 @@ pub struct GraphNode {
 pub struct WorkPlan {
     pub layers: Vec<WorkLayer>,
     pub total_tasks: usize,
+    /// Render priority of each task, 0 (lowest) to 255 (highest); tasks
+    /// without an entry have priority 0
+    #[serde(default)]
+    pub priorities: HashMap<ResourceHash, u8>,
+    /// Root that local resources were hashed relative to, taken from the
+    /// graph the plan was generated from
+    #[serde(default)]
+    pub project_root: Option<PathBuf>,
 }
 
 /// A layer of work that can be executed in parallel
@@ -38,9 +61,17 @@ impl DependencyGraph {
             root,
             nodes: HashMap::new(),
             edges: Vec::new(),
+            invalidated: HashSet::new(),
+            file_stats: HashMap::new(),
+            project_root: None,
         }
     }
 
+    /// Hash of a resource, relative to the graph's project root
+    pub fn resource_hash(&self, resource: &Resource) -> ResourceHash {
+        crate::graph::compute_resource_hash(resource, self.project_root.as_deref())
+    }
+
     pub fn add_node(&mut self, hash: ResourceHash, node: GraphNode) {
         self.nodes.insert(hash, node);
     }
@@ -48,6 +79,95 @@ impl DependencyGraph {
     pub fn add_edge(&mut self, from: ResourceHash, to: ResourceHash) {
         self.edges.push((from, to));
     }
+
+    /// The part of the graph within `depth` dependency hops of `root`
+    ///
+    /// Nodes exactly `depth` hops away are included without their outgoing
+    /// edges. Nodes keep all of their metadata from this graph. Fails with
+    /// `CacheError::NotFound` when `root` isn't in the graph.
+    pub fn subgraph(&self, root: &ResourceHash, depth: usize) -> Result<DependencyGraph> {
+        self.neighbourhood(root, depth, Direction::Dependencies)
+    }
+
+    /// The documents that include `leaf`, up to `depth` hops away
+    ///
+    /// The reverse of [`subgraph()`](Self::subgraph): dependency edges are
+    /// walked backwards, answering "what includes this file?". Edges keep
+    /// their original direction, and nodes exactly `depth` hops away are
+    /// included without the edges leading to them. Fails with
+    /// `CacheError::NotFound` when `leaf` isn't in the graph.
+    pub fn subgraph_ancestors(&self, leaf: &ResourceHash, depth: usize) -> Result<DependencyGraph> {
+        self.neighbourhood(leaf, depth, Direction::Dependents)
+    }
+
+    /// Breadth-first walk from `start`, keeping nodes within `depth` hops
+    fn neighbourhood(
+        &self,
+        start: &ResourceHash,
+        depth: usize,
+        direction: Direction,
+    ) -> Result<DependencyGraph> {
+        let start_node = self.nodes.get(start).ok_or_else(|| {
+            CompositionError::Cache(CacheError::NotFound(format!(
+                "Resource {} is not in the dependency graph",
+                start
+            )))
+        })?;
+
+        // Orient each edge as (node walked from, node walked to)
+        let oriented = |&(from, to): &(ResourceHash, ResourceHash)| match direction {
+            Direction::Dependencies => (from, to),
+            Direction::Dependents => (to, from),
+        };
+
+        let mut next: HashMap<ResourceHash, Vec<ResourceHash>> = HashMap::new();
+        for edge in &self.edges {
+            let (near, far) = oriented(edge);
+            next.entry(near).or_default().push(far);
+        }
+
+        let mut distance: HashMap<ResourceHash, usize> = HashMap::from([(*start, 0)]);
+        let mut queue = VecDeque::from([*start]);
+        while let Some(hash) = queue.pop_front() {
+            let hops = distance[&hash];
+            if hops == depth {
+                continue;
+            }
+            for &neighbour in next.get(&hash).into_iter().flatten() {
+                if let Entry::Vacant(entry) = distance.entry(neighbour) {
+                    entry.insert(hops + 1);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        let mut result = DependencyGraph::new(start_node.resource.clone());
+        result.project_root = self.project_root.clone();
+        for hash in distance.keys() {
+            if let Some(node) = self.nodes.get(hash) {
+                result.add_node(*hash, node.clone());
+            }
+            if let Some(stat) = self.file_stats.get(hash) {
+                result.file_stats.insert(*hash, *stat);
+            }
+        }
+        for edge in &self.edges {
+            let (near, far) = oriented(edge);
+            let within = distance.get(&near).is_some_and(|&hops| hops < depth);
+            if within && distance.contains_key(&far) {
+                result.add_edge(edge.0, edge.1);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Which way [`DependencyGraph::neighbourhood`] walks the edges
+#[derive(Clone, Copy)]
+enum Direction {
+    Dependencies,
+    Dependents,
 }
 
 impl WorkPlan {
@@ -55,13 +175,45 @@ impl WorkPlan {
         Self {
             layers: Vec::new(),
             total_tasks: 0,
+            priorities: HashMap::new(),
+            project_root: None,
         }
     }
 
+    /// Hash of a resource, relative to the plan's project root
+    pub fn resource_hash(&self, resource: &Resource) -> ResourceHash {
+        crate::graph::compute_resource_hash(resource, self.project_root.as_deref())
+    }
+
     pub fn add_layer(&mut self, layer: WorkLayer) {
         self.total_tasks += layer.resources.len();
         self.layers.push(layer);
     }
+
+    /// Set the priority of a task, from 0 (lowest) to 255 (highest)
+    ///
+    /// Within a layer, higher-priority tasks are dispatched first. That only
+    /// decides which tasks render first when they can't all start at once:
+    /// in a sequential layer, or with a render `max_parallelism`. Without a
+    /// limit, every task of a parallel layer starts together.
+    pub fn with_priority(&mut self, hash: &ResourceHash, priority: u8) -> &mut Self {
+        self.priorities.insert(*hash, priority);
+        self
+    }
+
+    /// Priority of a task, 0 when none was set
+    pub fn priority(&self, hash: &ResourceHash) -> u8 {
+        self.priorities.get(hash).copied().unwrap_or(0)
+    }
+
+    /// Number of tasks at each priority
+    pub fn total_tasks_by_priority(&self) -> BTreeMap<u8, usize> {
+        let mut histogram = BTreeMap::new();
+        for resource in self.layers.iter().flat_map(|layer| &layer.resources) {
+            *histogram.entry(self.priority(&self.resource_hash(resource))).or_insert(0) += 1;
+        }
+        histogram
+    }
 }
 
 impl Default for WorkPlan {
@@ -69,3 +221,96 @@ impl Default for WorkPlan {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ResourceSource;
+    use std::path::PathBuf;
+
+    fn hash(name: &str) -> ResourceHash {
+        ResourceHash(name.bytes().map(u64::from).sum())
+    }
+
+    /// root -> a -> b -> c, and root -> d -> c
+    fn graph() -> DependencyGraph {
+        let edges = [("root", "a"), ("a", "b"), ("b", "c"), ("root", "d"), ("d", "c")];
+        let mut graph = DependencyGraph::new(Resource::local(PathBuf::from("root.md")));
+
+        for name in ["root", "a", "b", "c", "d"] {
+            graph.add_node(
+                hash(name),
+                GraphNode {
+                    resource: Resource::local(PathBuf::from(format!("{}.md", name))),
+                    content_hash: Some(format!("content-{}", name)),
+                    dependencies: edges
+                        .iter()
+                        .filter(|(from, _)| *from == name)
+                        .map(|(_, to)| hash(to))
+                        .collect(),
+                },
+            );
+        }
+        for (from, to) in edges {
+            graph.add_edge(hash(from), hash(to));
+        }
+
+        graph
+    }
+
+    fn sorted_edges(graph: &DependencyGraph) -> Vec<(ResourceHash, ResourceHash)> {
+        let mut edges = graph.edges.clone();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn test_subgraph_limits_depth() {
+        let sub = graph().subgraph(&hash("root"), 1).unwrap();
+
+        assert_eq!(sub.nodes.len(), 3);
+        assert!(sub.nodes.contains_key(&hash("a")) && sub.nodes.contains_key(&hash("d")));
+        // Nodes at the depth limit keep their metadata but not their outgoing edges
+        assert_eq!(sub.nodes[&hash("a")].content_hash.as_deref(), Some("content-a"));
+        let mut expected = vec![(hash("root"), hash("a")), (hash("root"), hash("d"))];
+        expected.sort();
+        assert_eq!(sorted_edges(&sub), expected);
+    }
+
+    #[test]
+    fn test_subgraph_from_inner_node() {
+        let sub = graph().subgraph(&hash("a"), 5).unwrap();
+
+        assert_eq!(sub.nodes.len(), 3);
+        assert_eq!(sub.root.source, ResourceSource::Local(PathBuf::from("a.md")));
+        assert_eq!(sub.edges.len(), 2);
+    }
+
+    #[test]
+    fn test_subgraph_ancestors() {
+        let up = graph().subgraph_ancestors(&hash("c"), 1).unwrap();
+        let mut expected = vec![(hash("b"), hash("c")), (hash("d"), hash("c"))];
+        expected.sort();
+        assert_eq!(up.nodes.len(), 3);
+        assert_eq!(sorted_edges(&up), expected);
+
+        let all = graph().subgraph_ancestors(&hash("c"), 10).unwrap();
+        assert_eq!(all.nodes.len(), 5);
+        assert_eq!(all.edges.len(), 5);
+    }
+
+    #[test]
+    fn test_subgraph_unknown_root() {
+        let graph = graph();
+
+        for result in [
+            graph.subgraph(&hash("missing"), 1),
+            graph.subgraph_ancestors(&hash("missing"), 1),
+        ] {
+            assert!(matches!(
+                result,
+                Err(CompositionError::Cache(CacheError::NotFound(_)))
+            ));
+        }
+    }
+}
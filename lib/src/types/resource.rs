@@ -1,5 +1,11 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use crate::error::ParseError;
+use crate::graph::utils::{normalize_path, normalize_url};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 
@@ -9,13 +15,71 @@ pub struct Resource {
     pub source: ResourceSource,
     pub requirement: ResourceRequirement,
     pub cache_duration: Option<Duration>,
+    /// Section of the document selected by a URL fragment (`doc.md#intro`)
+    #[serde(default)]
+    pub section: Option<String>,
+    /// Resource loaded instead when this one can't be, written
+    /// `primary || fallback`; a fallback may have a fallback of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Box<Resource>>,
 }
 
 /// The source location of a resource
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+///
+/// Sources compare and hash by their normalized form, as resource hashes do:
+/// local paths after resolving `.` and `..` (see
+/// [`normalize_path`](crate::graph::utils::normalize_path)) and URLs after
+/// [`normalize_url`](crate::graph::utils::normalize_url).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ResourceSource {
     Local(PathBuf),
     Remote(Url),
+    /// A file inside a git repository, checked out at `ref_`
+    Git {
+        repo_url: String,
+        ref_: String,
+        path: String,
+    },
+    /// Content held in memory rather than read from anywhere; `name` is only
+    /// used to identify it in logs and errors
+    Memory {
+        content: Arc<str>,
+        name: String,
+    },
+}
+
+impl ResourceSource {
+    /// The form sources are compared and hashed by
+    fn identity(&self) -> SourceIdentity<'_> {
+        match self {
+            Self::Local(path) => SourceIdentity::Local(normalize_path(path)),
+            Self::Remote(url) => SourceIdentity::Remote(normalize_url(url)),
+            Self::Git { repo_url, ref_, path } => SourceIdentity::Git(repo_url, ref_, path),
+            Self::Memory { content, name } => SourceIdentity::Memory(content, name),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum SourceIdentity<'a> {
+    Local(PathBuf),
+    Remote(String),
+    Git(&'a str, &'a str, &'a str),
+    Memory(&'a str, &'a str),
+}
+
+impl PartialEq for ResourceSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Eq for ResourceSource {}
+
+impl Hash for ResourceSource {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
 }
 
 /// Requirement level for a resource (based on suffix syntax)
@@ -36,6 +100,8 @@ impl Resource {
             source: ResourceSource::Local(path),
             requirement: ResourceRequirement::Default,
             cache_duration: None,
+            section: None,
+            fallback: None,
         }
     }
 
@@ -44,6 +110,52 @@ impl Resource {
             source: ResourceSource::Remote(url),
             requirement: ResourceRequirement::Default,
             cache_duration: Some(Duration::from_secs(86400)), // 1 day default
+            section: None,
+            fallback: None,
+        }
+    }
+
+    pub fn git(repo_url: impl Into<String>, ref_: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            source: ResourceSource::Git {
+                repo_url: repo_url.into(),
+                ref_: ref_.into(),
+                path: path.into(),
+            },
+            requirement: ResourceRequirement::Default,
+            cache_duration: None,
+            section: None,
+            fallback: None,
+        }
+    }
+
+    /// An in-memory resource; it is never stored in the cache
+    pub fn memory(name: impl Into<String>, content: impl Into<Arc<str>>) -> Self {
+        Self {
+            source: ResourceSource::Memory {
+                content: content.into(),
+                name: name.into(),
+            },
+            requirement: ResourceRequirement::Default,
+            cache_duration: None,
+            section: None,
+            fallback: None,
+        }
+    }
+
+    /// Whether the resource is held in memory (see [`Resource::memory`])
+    pub fn is_memory(&self) -> bool {
+        matches!(self.source, ResourceSource::Memory { .. })
+    }
+
+    /// A human-readable name for the resource: its path, URL, `repo@ref:path`
+    /// or in-memory name
+    pub fn label(&self) -> String {
+        match &self.source {
+            ResourceSource::Local(path) => path.display().to_string(),
+            ResourceSource::Remote(url) => url.to_string(),
+            ResourceSource::Git { repo_url, ref_, path } => crate::graph::git_location(repo_url, ref_, path),
+            ResourceSource::Memory { name, .. } => name.clone(),
         }
     }
 
@@ -56,7 +168,195 @@ impl Resource {
         self.cache_duration = duration;
         self
     }
+
+    pub fn with_section(mut self, section: Option<String>) -> Self {
+        self.section = section;
+        self
+    }
+
+    /// Add `fallback` to the end of the resource's fallback chain
+    pub fn with_fallback(mut self, fallback: Resource) -> Self {
+        self.fallback = Some(Box::new(match self.fallback.take() {
+            Some(existing) => existing.with_fallback(fallback),
+            None => fallback,
+        }));
+        self
+    }
+
+    /// The resource followed by each of its fallbacks, in the order they are
+    /// tried
+    pub fn candidates(&self) -> impl Iterator<Item = &Resource> {
+        std::iter::successors(Some(self), |resource| resource.fallback.as_deref())
+    }
+
+    /// The resource with each local candidate replaced by its translation
+    /// into `language`, e.g. `intro.de.md` for `intro.md`, where that file
+    /// exists
+    ///
+    /// Candidates without a translation, and those that aren't local files,
+    /// are kept as they are.
+    pub fn localized(&self, language: &str) -> Resource {
+        let source = match &self.source {
+            ResourceSource::Local(path) => {
+                let variant = language_variant_path(path, language);
+                if variant.is_file() {
+                    ResourceSource::Local(variant)
+                } else {
+                    self.source.clone()
+                }
+            }
+            other => other.clone(),
+        };
+        Resource {
+            source,
+            fallback: self.fallback.as_ref().map(|fallback| Box::new(fallback.localized(language))),
+            ..self.clone()
+        }
+    }
+}
+
+/// The path of a file's translation into `language`, with the language
+/// before the extension: `intro.md` becomes `intro.de.md`
+pub fn language_variant_path(path: &Path, language: &str) -> PathBuf {
+    let Some(stem) = path.file_stem() else {
+        return path.to_path_buf();
+    };
+    let mut name = stem.to_os_string();
+    name.push(".");
+    name.push(language);
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// Which of `languages` a file is a translation into, such as `de` for
+/// `intro.de.md`, if any
+pub fn translation_language<'l>(path: &Path, languages: &'l [String]) -> Option<&'l str> {
+    let language = Path::new(path.file_stem()?).extension()?.to_str()?;
+    languages.iter().find(|l| *l == language).map(String::as_str)
 }
 
 /// Hash type for resource identification
-pub type ResourceHash = u64;
+///
+/// Displayed, parsed and serialized as 16 lowercase hex digits so that log
+/// lines, cache keys and JSON exports all agree on a single form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceHash(pub u64);
+
+impl ResourceHash {
+    /// The raw 64-bit hash value
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for ResourceHash {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for ResourceHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for ResourceHash {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.len() > 16 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseError::InvalidResource(format!(
+                "invalid resource hash '{}': expected up to 16 hex digits",
+                s
+            )));
+        }
+
+        u64::from_str_radix(s, 16)
+            .map(Self)
+            .map_err(|e| ParseError::InvalidResource(format!("invalid resource hash '{}': {}", s, e)))
+    }
+}
+
+impl Serialize for ResourceHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_variant_path() {
+        assert_eq!(language_variant_path(Path::new("docs/intro.md"), "de"), PathBuf::from("docs/intro.de.md"));
+        assert_eq!(language_variant_path(Path::new("README"), "fr"), PathBuf::from("README.fr"));
+
+        let languages = vec!["de".to_string(), "fr".to_string()];
+        assert_eq!(translation_language(Path::new("docs/intro.de.md"), &languages), Some("de"));
+        assert_eq!(translation_language(Path::new("docs/intro.md"), &languages), None);
+        assert_eq!(translation_language(Path::new("docs/v1.2.md"), &languages), None);
+    }
+
+    #[test]
+    fn test_localized_prefers_existing_translation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("intro.md"), "Hello").unwrap();
+        std::fs::write(dir.path().join("intro.de.md"), "Hallo").unwrap();
+        std::fs::write(dir.path().join("outro.md"), "Bye").unwrap();
+
+        let intro = Resource::local(dir.path().join("intro.md")).with_fallback(Resource::local(dir.path().join("outro.md")));
+        let localized = intro.localized("de");
+        assert_eq!(localized.source, ResourceSource::Local(dir.path().join("intro.de.md")));
+        assert_eq!(localized.fallback.unwrap().source, ResourceSource::Local(dir.path().join("outro.md")));
+        assert_eq!(intro.localized("fr").source, intro.source);
+    }
+
+    #[test]
+    fn test_resource_hash_display_parse_roundtrip() {
+        let hash = ResourceHash(0x1234_abcd_ef01_5678);
+        let displayed = hash.to_string();
+
+        assert_eq!(displayed, "1234abcdef015678");
+        assert_eq!(displayed.parse::<ResourceHash>().unwrap(), hash);
+        assert_eq!("1234ABCDEF015678".parse::<ResourceHash>().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_resource_hash_zero_is_padded() {
+        assert_eq!(ResourceHash(0).to_string(), "0000000000000000");
+        assert_eq!(ResourceHash(0xff).to_string(), "00000000000000ff");
+    }
+
+    #[test]
+    fn test_resource_hash_rejects_invalid_strings() {
+        assert!("".parse::<ResourceHash>().is_err());
+        assert!("xyz".parse::<ResourceHash>().is_err());
+        assert!("+1".parse::<ResourceHash>().is_err());
+        assert!("00000000000000001".parse::<ResourceHash>().is_err());
+    }
+
+    #[test]
+    fn test_resource_hash_serde_as_string() {
+        let hash = ResourceHash(42);
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, "\"000000000000002a\"");
+        assert_eq!(serde_json::from_str::<ResourceHash>(&json).unwrap(), hash);
+
+        // Usable as a JSON map key
+        let map = std::collections::HashMap::from([(hash, 1)]);
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, "{\"000000000000002a\":1}");
+    }
+}
@@ -1,12 +1,16 @@
+use crate::error::ParseError;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 
 /// Width specification for YouTube embeds
 ///
-/// Supports three formats:
+/// Supports five formats:
 /// - Pixels: `512px` (default if not specified)
 /// - Rems: `32rem`
 /// - Percentage: `80%` (validated 0-100 range)
+/// - Viewport width: `80vw` (validated 0-100 range)
+/// - Auto: `auto`, sized by the container
 ///
 /// # Examples
 ///
@@ -21,6 +25,10 @@ use std::fmt::{self, Display, Formatter};
 ///
 /// let percentage = WidthSpec::Percentage(80);
 /// assert_eq!(percentage.to_string(), "80%");
+///
+/// let viewport: WidthSpec = "100vw".parse().unwrap();
+/// assert_eq!(viewport, WidthSpec::Viewport(100));
+/// assert_eq!(WidthSpec::Auto.to_string(), "auto");
 /// ```
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WidthSpec {
@@ -30,6 +38,10 @@ pub enum WidthSpec {
     Rems(f32),
     /// Width as percentage 0-100 (e.g., 80%)
     Percentage(u8),
+    /// Width as percentage 0-100 of the viewport width (e.g., 100vw)
+    Viewport(u8),
+    /// Width left to the container (`auto`)
+    Auto,
 }
 
 impl Default for WidthSpec {
@@ -44,7 +56,71 @@ impl Display for WidthSpec {
             WidthSpec::Pixels(px) => write!(f, "{}px", px),
             WidthSpec::Rems(rem) => write!(f, "{}rem", rem),
             WidthSpec::Percentage(pct) => write!(f, "{}%", pct),
+            WidthSpec::Viewport(vw) => write!(f, "{}vw", vw),
+            WidthSpec::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl FromStr for WidthSpec {
+    type Err = ParseError;
+
+    /// Parse the CSS form [`Display`] writes, e.g. `512px` or `80vw`
+    ///
+    /// Returns `ParseError::InvalidDirective` if the width format is invalid
+    /// or a percentage or viewport width is out of range.
+    fn from_str(width_str: &str) -> Result<Self, Self::Err> {
+        let invalid = |directive: String| ParseError::InvalidDirective { line: 0, directive, span: None };
+
+        if width_str == "auto" {
+            return Ok(WidthSpec::Auto);
+        }
+
+        if let Some(px_str) = width_str.strip_suffix("px") {
+            let px = px_str.parse::<u32>().map_err(|_| {
+                invalid(format!("Invalid pixel width '{}'. Width must be a positive integer", width_str))
+            })?;
+            if px == 0 {
+                return Err(invalid("Width must be positive".to_string()));
+            }
+            return Ok(WidthSpec::Pixels(px));
+        }
+
+        if let Some(rem_str) = width_str.strip_suffix("rem") {
+            let rem = rem_str.parse::<f32>().map_err(|_| {
+                invalid(format!("Invalid rem width '{}'. Width must be a positive number", width_str))
+            })?;
+            if rem <= 0.0 {
+                return Err(invalid("Width must be positive".to_string()));
+            }
+            return Ok(WidthSpec::Rems(rem));
+        }
+
+        if let Some(pct_str) = width_str.strip_suffix('%') {
+            let pct = pct_str.parse::<u8>().map_err(|_| {
+                invalid(format!("Invalid percentage width '{}'. Percentage must be 0-100", width_str))
+            })?;
+            if pct > 100 {
+                return Err(invalid(format!("Invalid percentage '{}'. Must be 0-100%", pct)));
+            }
+            return Ok(WidthSpec::Percentage(pct));
+        }
+
+        if let Some(vw_str) = width_str.strip_suffix("vw") {
+            let vw = vw_str.parse::<u8>().map_err(|_| {
+                invalid(format!("Invalid viewport width '{}'. Viewport width must be 0-100", width_str))
+            })?;
+            if vw > 100 {
+                return Err(invalid(format!("Invalid viewport width '{}'. Must be 0-100vw", vw)));
+            }
+            return Ok(WidthSpec::Viewport(vw));
         }
+
+        Err(invalid(format!(
+            "Invalid width format '{}'. Width must be pixels (512px), rems (32rem), percentage (0-100%), \
+             viewport width (0-100vw) or auto",
+            width_str
+        )))
     }
 }
 
@@ -96,4 +172,22 @@ mod tests {
         assert!(debug_str.contains("Pixels"));
         assert!(debug_str.contains("512"));
     }
+
+    #[test]
+    fn test_width_spec_display_viewport_and_auto() {
+        assert_eq!(WidthSpec::Viewport(100).to_string(), "100vw");
+        assert_eq!(WidthSpec::Auto.to_string(), "auto");
+    }
+
+    #[test]
+    fn test_width_spec_from_str() {
+        assert_eq!("80vw".parse::<WidthSpec>().unwrap(), WidthSpec::Viewport(80));
+        assert_eq!("0vw".parse::<WidthSpec>().unwrap(), WidthSpec::Viewport(0));
+        assert_eq!("auto".parse::<WidthSpec>().unwrap(), WidthSpec::Auto);
+        assert_eq!("512px".parse::<WidthSpec>().unwrap(), WidthSpec::Pixels(512));
+
+        for invalid in ["101vw", "-5vw", "12.5vw", "vw", "Auto", "512"] {
+            assert!(invalid.parse::<WidthSpec>().is_err(), "{} should fail", invalid);
+        }
+    }
 }
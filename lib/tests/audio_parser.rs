@@ -1,5 +1,5 @@
-use lib::parse::parse_document;
-use lib::types::{DarkMatterNode, Resource, ResourceSource, ResourceRequirement};
+use lib::parse::{parse_document, ParseOptions};
+use lib::types::{Captions, DarkMatterNode, Resource, ResourceSource, ResourceRequirement};
 use std::path::PathBuf;
 
 #[test]
@@ -17,9 +17,11 @@ More content here.
         source: ResourceSource::Local(PathBuf::from("test.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
-    let doc = parse_document(markdown, resource).unwrap();
+    let (doc, _) = parse_document(markdown, resource, ParseOptions::default()).unwrap();
 
     // Find the Audio node
     let audio_node = doc
@@ -29,7 +31,7 @@ More content here.
 
     assert!(audio_node.is_some(), "Audio node should be parsed");
 
-    if let Some(DarkMatterNode::Audio { source, name }) = audio_node {
+    if let Some(DarkMatterNode::Audio { source, name, .. }) = audio_node {
         assert_eq!(source, "./test.mp3");
         assert!(name.is_none());
     }
@@ -43,9 +45,11 @@ fn test_parse_audio_directive_with_name_in_document() {
         source: ResourceSource::Local(PathBuf::from("test.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
-    let doc = parse_document(markdown, resource).unwrap();
+    let (doc, _) = parse_document(markdown, resource, ParseOptions::default()).unwrap();
 
     // Should have at least one Audio node
     let audio_node = doc
@@ -55,7 +59,7 @@ fn test_parse_audio_directive_with_name_in_document() {
 
     assert!(audio_node.is_some(), "Audio node should be parsed");
 
-    if let Some(DarkMatterNode::Audio { source, name }) = audio_node {
+    if let Some(DarkMatterNode::Audio { source, name, .. }) = audio_node {
         assert_eq!(source, "./podcast.mp3");
         assert_eq!(name, &Some("Episode 42".to_string()));
     }
@@ -79,9 +83,11 @@ More text.
         source: ResourceSource::Local(PathBuf::from("test.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
-    let doc = parse_document(markdown, resource).unwrap();
+    let (doc, _) = parse_document(markdown, resource, ParseOptions::default()).unwrap();
 
     // Count Audio nodes
     let audio_count = doc
@@ -101,9 +107,11 @@ fn test_parse_audio_with_quoted_path() {
         source: ResourceSource::Local(PathBuf::from("test.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
-    let doc = parse_document(markdown, resource).unwrap();
+    let (doc, _) = parse_document(markdown, resource, ParseOptions::default()).unwrap();
 
     let audio_node = doc
         .content
@@ -112,7 +120,7 @@ fn test_parse_audio_with_quoted_path() {
 
     assert!(audio_node.is_some(), "Audio node with quoted path should be parsed");
 
-    if let Some(DarkMatterNode::Audio { source, name }) = audio_node {
+    if let Some(DarkMatterNode::Audio { source, name, .. }) = audio_node {
         assert_eq!(source, "./path with spaces.mp3");
         assert_eq!(name, &Some("My Audio".to_string()));
     }
@@ -125,6 +133,7 @@ fn test_html_renderer_rejects_unprocessed_audio() {
     let nodes = vec![DarkMatterNode::Audio {
         source: "./test.mp3".to_string(),
         name: None,
+        captions: None,
     }];
 
     let result = to_html(&nodes);
@@ -139,3 +148,42 @@ fn test_html_renderer_rejects_unprocessed_audio() {
         );
     }
 }
+
+#[test]
+fn test_parse_audio_directive_with_captions() {
+    let markdown = r#"::audio ./talk.mp3 "Keynote" --captions ./talk.vtt
+
+::audio ./interview.mp3 --captions "./captions/interview fr.vtt" --srclang fr
+"#;
+
+    let resource = Resource::local(PathBuf::from("test.md"));
+    let (doc, _) = parse_document(markdown, resource, ParseOptions::default()).unwrap();
+
+    let captions: Vec<_> = doc
+        .content
+        .iter()
+        .filter_map(|node| match node {
+            DarkMatterNode::Audio { captions, .. } => Some(captions.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        captions,
+        [
+            Some(Captions { path: "./talk.vtt".to_string(), language: "en".to_string() }),
+            Some(Captions { path: "./captions/interview fr.vtt".to_string(), language: "fr".to_string() }),
+        ]
+    );
+    assert!(matches!(&doc.content[0], DarkMatterNode::Audio { name: Some(name), .. } if name == "Keynote"));
+
+    // The captions files are dependencies of the document
+    let dependencies: Vec<_> = doc
+        .dependencies
+        .iter()
+        .map(|dependency| match &dependency.source {
+            ResourceSource::Local(path) => path.clone(),
+            other => panic!("Expected a local dependency, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(dependencies, [PathBuf::from("./talk.vtt"), PathBuf::from("./captions/interview fr.vtt")]);
+}
@@ -0,0 +1,85 @@
+use lib::cache::{init_database, init_database_with_lock_wait};
+use lib::{init_with_config, CacheBackend, CacheError, CompositionConfig, CompositionError};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// A second handle on the same path gets the typed lock error
+#[tokio::test]
+async fn test_second_handle_gets_database_locked() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join(".composition.db");
+
+    let _first = init_database(&db_path).await.unwrap();
+    let second = init_database(&db_path).await;
+
+    match second {
+        Err(CompositionError::Cache(CacheError::DatabaseLocked { path, holder_hint })) => {
+            assert_eq!(path, db_path);
+            assert!(holder_hint.contains("LOCK"));
+        }
+        other => panic!("expected DatabaseLocked, got {:?}", other.map(|_| ())),
+    }
+}
+
+/// Lock acquisition is retried for `lock_wait` and then gives up
+#[tokio::test]
+async fn test_lock_wait_retries_then_gives_up() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join(".composition.db");
+
+    let _first = init_database(&db_path).await.unwrap();
+
+    let start = Instant::now();
+    let second = init_database_with_lock_wait(&db_path, Some(Duration::from_millis(300))).await;
+
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(matches!(
+        second,
+        Err(CompositionError::Cache(CacheError::DatabaseLocked { .. }))
+    ));
+}
+
+/// Lock acquisition succeeds once the first handle is released
+#[tokio::test]
+async fn test_lock_wait_succeeds_after_release() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join(".composition.db");
+
+    let first = init_database(&db_path).await.unwrap();
+    let release = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(first);
+    });
+
+    let second = init_database_with_lock_wait(&db_path, Some(Duration::from_secs(5))).await;
+    release.await.unwrap();
+
+    assert!(second.is_ok());
+}
+
+/// With the fallback enabled, init succeeds with caching disabled
+#[tokio::test]
+async fn test_read_only_fallback_disables_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join(".composition.db");
+
+    let _first = init_database(&db_path).await.unwrap();
+
+    let locked = init_with_config(
+        Some(temp_dir.path()),
+        None,
+        CompositionConfig::new(CacheBackend::RocksDb(db_path.clone())),
+    )
+    .await;
+    assert!(matches!(
+        locked,
+        Err(CompositionError::Cache(CacheError::DatabaseLocked { .. }))
+    ));
+
+    let mut config = CompositionConfig::new(CacheBackend::RocksDb(db_path));
+    config.read_only_fallback = true;
+    let api = init_with_config(Some(temp_dir.path()), None, config).await.unwrap();
+
+    assert!(!api.cache().is_enabled());
+    assert_eq!(api.config().backend, CacheBackend::Disabled);
+}
@@ -1,9 +1,12 @@
+use lib::error::ParseError;
+use lib::parse::parse_markdown;
 use lib::types::{ChartData, DataPoint, DarkMatterNode, Breakpoint};
 use lib::render::{
     render_bar_chart, render_line_chart, render_pie_chart, render_area_chart, render_bubble_chart,
     render_popover, render_inline_popover,
     render_disclosure, render_disclosure_open,
     render_columns, generate_columns_styles,
+    to_html,
 };
 use std::collections::HashMap;
 
@@ -398,3 +401,46 @@ fn test_responsive_breakpoint_order() {
     // Should successfully render regardless of insertion order
     assert!(result.contains("composition-columns"));
 }
+
+// Block Round Trips
+
+/// Parse markdown and render it to HTML
+fn round_trip(markdown: &str) -> String {
+    let nodes = parse_markdown(markdown).unwrap();
+    to_html(&nodes).unwrap()
+}
+
+#[test]
+fn test_round_trip_disclosure_inside_columns() {
+    let html = round_trip(
+        "::columns md: 2\n::summary\n**More**\n::details\nHidden text\n::end\n::break\nRight column\n::end\n\nAfter",
+    );
+
+    let columns = html.find("composition-columns").unwrap();
+    let details = html.find("<details").unwrap();
+    let right = html.find("Right column").unwrap();
+    let after = html.find("After").unwrap();
+    assert!(columns < details && details < right && right < after, "{}", html);
+    assert_eq!(html.matches(r#"<div class="composition-column">"#).count(), 2, "{}", html);
+    assert!(html.contains("<strong>More</strong>"), "{}", html);
+    assert!(!html.contains("::"), "{}", html);
+}
+
+#[test]
+fn test_round_trip_columns_inside_disclosure() {
+    let html = round_trip("::summary\nLayout\n::details\n::columns\n*Left*\n::break\nRight\n::end\n::end");
+
+    let details = html.find("<details").unwrap();
+    let columns = html.find("composition-columns").unwrap();
+    let closing = html.rfind("</details>").unwrap();
+    assert!(details < columns && columns < closing, "{}", html);
+    assert!(html.contains("<em>Left</em>"), "{}", html);
+    assert_eq!(html.matches(r#"<div class="composition-column">"#).count(), 2, "{}", html);
+}
+
+#[test]
+fn test_round_trip_unterminated_block() {
+    let result = parse_markdown("Intro\n\n::summary\nMore\n::details\nNo end");
+
+    assert!(matches!(result, Err(ParseError::InvalidDirective { line: 3, .. })));
+}
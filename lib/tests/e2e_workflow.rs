@@ -76,6 +76,8 @@ This is a subsection included in chapter 1.
         source: ResourceSource::Local(base_path.join("index.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
     let graph = api.graph(resource.clone()).await?;
@@ -88,7 +90,7 @@ This is a subsection included in chapter 1.
     assert!(workplan.total_tasks > 0);
 
     // Test rendering
-    let rendered = api.render(vec![resource.clone()], None).await?;
+    let (rendered, _warnings) = api.render(vec![resource.clone()], None).await?;
     assert_eq!(rendered.len(), 1);
 
     let doc = &rendered[0];
@@ -131,6 +133,8 @@ This document should be cached.
         source: ResourceSource::Local(base_path.join("cached.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: Some(std::time::Duration::from_secs(3600)),
+        section: None,
+        fallback: None,
     };
 
     // First render - cache miss
@@ -172,6 +176,8 @@ async fn test_error_propagation() -> Result<()> {
         source: ResourceSource::Local(base_path.join("broken.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
     // Should fail with meaningful error
@@ -218,6 +224,8 @@ Written by {{author}}, version {{version}}.
         source: ResourceSource::Local(base_path.join("interpolated.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
     let html_output = api
@@ -268,6 +276,8 @@ async fn test_cycle_detection() -> Result<()> {
         source: ResourceSource::Local(base_path.join("a.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
     // Should detect cycle
@@ -308,12 +318,14 @@ async fn test_concurrent_rendering() -> Result<()> {
             source: ResourceSource::Local(base_path.join(format!("doc{}.md", i))),
             requirement: ResourceRequirement::Required,
             cache_duration: None,
+            section: None,
+            fallback: None,
         })
         .collect();
 
     // Render all documents
     let start = std::time::Instant::now();
-    let rendered = api.render(resources.clone(), None).await?;
+    let (rendered, _warnings) = api.render(resources.clone(), None).await?;
     let _duration = start.elapsed();
 
     assert_eq!(rendered.len(), 10);
@@ -402,6 +414,8 @@ async fn test_workplan_optimization() -> Result<()> {
         source: ResourceSource::Local(base_path.join("main.md")),
         requirement: ResourceRequirement::Required,
         cache_duration: None,
+        section: None,
+        fallback: None,
     };
 
     // First render to populate cache
@@ -415,3 +429,1229 @@ async fn test_workplan_optimization() -> Result<()> {
 
     Ok(())
 }
+
+/// Test configurable render passes and per-pass timings
+#[tokio::test]
+async fn test_render_passes_and_metrics() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+
+    std::fs::write(
+        base_path.join("main.md"),
+        r#"---
+name: Composition
+---
+
+::file ./greeting.md
+"#,
+    )
+    .unwrap();
+    std::fs::write(base_path.join("greeting.md"), "Hello {{name}}").unwrap();
+
+    let resource = Resource::local(base_path.join("main.md"));
+
+    // Default passes: interpolation sees the transcluded text
+    let api = init_with_backend(Some(base_path), None, CacheBackend::Memory).await?;
+    let (documents, metrics) = api.render_with_metrics(vec![resource.clone()], None).await?;
+
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Hello Composition"), "{}", html);
+    // The transcluded dependency is rendered too
+    assert_eq!(metrics.documents, 2);
+    for pass in RenderPass::default_passes() {
+        assert!(metrics.pass_duration(pass).is_some(), "missing timing for {:?}", pass);
+    }
+
+    // Every rendered document is recorded in the cache
+    let main_hash = graph::compute_resource_hash(&resource, Some(base_path)).to_string();
+    let greeting_hash =
+        graph::compute_resource_hash(&Resource::local(base_path.join("greeting.md")), Some(base_path)).to_string();
+    let cached = api.cache().get_documents(&[&main_hash, &greeting_hash]).await?;
+    assert_eq!(cached.len(), 2);
+
+    // Skipping interpolation leaves the variable in place
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.render_passes = vec![RenderPass::Transclusion, RenderPass::Final];
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let (documents, metrics) = api.render_with_metrics(vec![resource], None).await?;
+
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("{{name}}"), "{}", html);
+    assert!(metrics.pass_duration(RenderPass::Interpolation).is_none());
+
+    Ok(())
+}
+
+/// AI directives are resolved through the queue and cached for later renders
+#[tokio::test]
+async fn test_render_resolves_ai_operations() -> Result<()> {
+    use lib::ai::MockCompletionModel;
+    use std::sync::Arc;
+
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+
+    std::fs::write(
+        base_path.join("main.md"),
+        "# Overview\n\n::summarize ./notes.md\n\nAfter the summary.\n",
+    )
+    .unwrap();
+    std::fs::write(base_path.join("notes.md"), "Long notes that need summarizing.").unwrap();
+
+    let model = Arc::new(MockCompletionModel::new(vec!["The notes, briefly.".to_string()]));
+    let api = init_with_backend(Some(base_path), None, CacheBackend::Memory)
+        .await?
+        .with_completion_model(model.clone());
+    let resource = Resource::local(base_path.join("main.md"));
+
+    let (documents, _warnings) = api.render(vec![resource.clone()], None).await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("The notes, briefly."), "{}", html);
+    assert!(html.contains("After the summary."), "{}", html);
+    assert_eq!(model.call_count(), 1);
+
+    // The second render is served from the LLM cache
+    api.render(vec![resource], None).await?;
+    assert_eq!(model.call_count(), 1);
+
+    Ok(())
+}
+
+/// Incremental renders only re-render changed documents and their dependents
+#[tokio::test]
+async fn test_render_incremental() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+
+    std::fs::write(base_path.join("root.md"), "# Root\n\n::file ./a.md\n\n::file ./b.md\n").unwrap();
+    std::fs::write(base_path.join("a.md"), "Section A").unwrap();
+    std::fs::write(base_path.join("b.md"), "Section B").unwrap();
+
+    let api = init_with_backend(Some(base_path), None, CacheBackend::Memory).await?;
+    let root = Resource::local(base_path.join("root.md"));
+
+    api.render(vec![root.clone()], None).await?;
+    let prev_graph = api.graph(root.clone()).await?;
+
+    let hash_of = |name: &str| {
+        let hash = graph::compute_resource_hash(&Resource::local(base_path.join(name)), Some(base_path));
+        render::rendered_cache_key(hash, None, api.frontmatter())
+    };
+    let (root_hash, a_hash, b_hash) = (hash_of("root.md"), hash_of("a.md"), hash_of("b.md"));
+    let before = api.cache().get_rendered_documents(&[&root_hash, &a_hash, &b_hash]).await?;
+    assert_eq!(before.len(), 3);
+
+    // Nothing changed: everything comes from the cache
+    let documents = api.render_incremental(&prev_graph, vec![root.clone()]).await?;
+    assert_eq!(documents.len(), 1);
+    let after = api.cache().get_rendered_documents(&[&root_hash]).await?;
+    assert_eq!(after[&root_hash].rendered_at, before[&root_hash].rendered_at);
+
+    // Changing B re-renders B and the root, but not A
+    std::fs::write(base_path.join("b.md"), "Section B, revised").unwrap();
+    let documents = api.render_incremental(&prev_graph, vec![root]).await?;
+
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Section B, revised"), "{}", html);
+
+    let after = api.cache().get_rendered_documents(&[&root_hash, &a_hash, &b_hash]).await?;
+    assert_eq!(after[&a_hash].rendered_at, before[&a_hash].rendered_at);
+    assert!(after[&b_hash].rendered_at > before[&b_hash].rendered_at);
+    assert!(after[&root_hash].rendered_at > before[&root_hash].rendered_at);
+
+    Ok(())
+}
+
+/// Rendering from a stream resolves transclusions relative to the given source
+#[tokio::test]
+async fn test_render_reader() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("included.md"), "Included from disk").unwrap();
+
+    let api = init_in_memory(None).await?;
+    let content = "---\ntitle: Streamed\n---\n# Streamed document\n\n::file ./included.md\n";
+
+    let document = api
+        .render_reader(content.as_bytes(), Resource::local(base_path.join("stream.md")), None)
+        .await?;
+
+    assert_eq!(document.frontmatter.get_string("title"), Some("Streamed"));
+    let html = render::to_html(&document.content)?;
+    assert!(html.contains("Included from disk"), "{}", html);
+
+    Ok(())
+}
+
+/// Rendering a string interpolates it and resolves its transclusions, without
+/// caching anything for the string itself
+#[tokio::test]
+async fn test_render_string() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let included = temp_dir.path().join("included.md");
+    std::fs::write(&included, "Included from disk").unwrap();
+
+    let api = init_in_memory(None).await?;
+    let mut frontmatter = Frontmatter::new();
+    frontmatter.custom.insert("name".to_string(), "World".into());
+    let content = format!("# Hello {{{{name}}}}\n\n::file {}\n", included.display());
+
+    let document = api.render_string(&content, frontmatter).await?;
+
+    let html = render::to_html(&document.content)?;
+    assert!(html.contains("Hello World"), "{}", html);
+    assert!(html.contains("Included from disk"), "{}", html);
+    assert!(document.resource.is_memory());
+    assert_eq!(document.dependencies.len(), 1);
+    assert_eq!(document.dependencies[0].source, ResourceSource::Local(included));
+
+    Ok(())
+}
+
+/// In-memory resources rendered through the work plan are never cached
+#[tokio::test]
+async fn test_render_memory_resource_is_not_cached() -> Result<()> {
+    let api = init_in_memory(None).await?;
+    let resource = Resource::memory("inline", "# Inline document");
+    let hash = lib::graph::compute_resource_hash(&resource, None).to_string();
+
+    let (documents, _warnings) = api.render(vec![resource], None).await?;
+    assert_eq!(documents.len(), 1);
+
+    assert!(api.cache().get_rendered_documents(&[&hash]).await?.is_empty());
+    assert!(api.cache().get_document(&hash).await?.is_none());
+
+    Ok(())
+}
+
+/// Replaces every markdown block with the given markdown
+struct ReplaceMarkdown(&'static str);
+
+impl NodeTransformer for ReplaceMarkdown {
+    fn transform(&self, node: &DarkMatterNode) -> Option<Vec<DarkMatterNode>> {
+        match node {
+            DarkMatterNode::Markdown(_) => Some(vec![DarkMatterNode::Markdown(MarkdownContent {
+                raw: self.0.to_string(),
+                frontmatter: None,
+            })]),
+            _ => None,
+        }
+    }
+}
+
+/// Registered transformers rewrite nodes before HTML generation, in order
+#[tokio::test]
+async fn test_node_transformers() -> Result<()> {
+    let api = init_in_memory(None)
+        .await?
+        .with_transformer(std::sync::Arc::new(ReplaceMarkdown("first")))
+        .with_transformer(std::sync::Arc::new(ReplaceMarkdown("**rewritten**")));
+
+    let document = api.render_string("Original text", Frontmatter::new()).await?;
+
+    let html = render::to_html(&document.content)?;
+    assert!(html.contains("<strong>rewritten</strong>"), "{}", html);
+    assert!(!html.contains("Original text"), "{}", html);
+    assert!(!html.contains("first"), "{}", html);
+
+    Ok(())
+}
+
+/// Test that sanitized HTML output drops transcluded scripts but keeps components
+#[tokio::test]
+async fn test_to_html_sanitizes_transcluded_content() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+
+    std::fs::write(
+        base_path.join("page.md"),
+        "# Page\n\n::youtube dQw4w9WgXcQ\n\n::file ./untrusted.md\n",
+    )
+    .unwrap();
+    std::fs::write(
+        base_path.join("untrusted.md"),
+        "Some text\n\n<script>alert(1)</script>\n",
+    )
+    .unwrap();
+    let pattern = base_path.join("page.md").to_string_lossy().to_string();
+
+    // Without the option the script is passed through
+    let api = init_with_backend(Some(base_path), None, CacheBackend::Memory).await?;
+    let outputs = api.to_html(vec![pattern.clone()]).await?;
+    assert!(outputs[0].html.contains("<script>alert(1)</script>"));
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.sanitize_html = true;
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let outputs = api.to_html(vec![pattern]).await?;
+    let html = &outputs[0].html;
+
+    assert!(!html.contains("alert(1)"), "{}", html);
+    assert!(html.contains("Some text"));
+    assert!(html.contains(r#"class="dm-youtube-container""#), "{}", html);
+    assert!(html.contains(r#"src="https://www.youtube.com/embed/dQw4w9WgXcQ?enablejsapi=1""#), "{}", html);
+    // The crate's own player script survives
+    assert!(html.contains(r#"<script id="dm-youtube">"#));
+
+    Ok(())
+}
+
+/// Test BOM-prefixed frontmatter and files that aren't valid UTF-8
+#[tokio::test]
+async fn test_render_bom_and_non_utf8_files() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    let fixtures = std::path::Path::new("tests/fixtures");
+    std::fs::copy(fixtures.join("bom_frontmatter.md"), base_path.join("bom.md")).unwrap();
+    std::fs::copy(fixtures.join("latin1.md"), base_path.join("latin1.md")).unwrap();
+    std::fs::write(base_path.join("lenient.md"), "# Lenient\n\n::file ./latin1.md\n").unwrap();
+    std::fs::write(base_path.join("strict.md"), "# Strict\n\n::file ./latin1.md!\n").unwrap();
+
+    let api = init_with_backend(Some(base_path), None, CacheBackend::Memory).await?;
+
+    // The BOM no longer hides the frontmatter
+    let (documents, _) = api
+        .render_with_metrics(vec![Resource::local(base_path.join("bom.md"))], None)
+        .await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Saved With BOM"), "{}", html);
+
+    // An optional file is decoded lossily and reported
+    let (documents, metrics) = api
+        .render_with_metrics(vec![Resource::local(base_path.join("lenient.md"))], None)
+        .await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Caf\u{fffd}"), "{}", html);
+    let lossy: Vec<_> = metrics
+        .parse_warnings
+        .iter()
+        .filter(|(_, warning)| warning.code == ParseWarningCode::LossyDecoding)
+        .collect();
+    assert_eq!(lossy.len(), 1, "{:?}", metrics.parse_warnings);
+    assert!(lossy[0].0.label().contains("latin1.md"));
+    assert!(lossy[0].1.message.contains("latin1.md"));
+
+    // A required one fails, naming the file and where the bad bytes start
+    let error = api
+        .render_with_metrics(vec![Resource::local(base_path.join("strict.md"))], None)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("latin1.md"), "{}", error);
+    assert!(error.contains("offset 28"), "{}", error);
+
+    Ok(())
+}
+
+/// A file matched by several glob patterns is converted once
+#[tokio::test]
+async fn test_to_html_dedupes_overlapping_globs() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::create_dir(base_path.join("guide")).unwrap();
+    std::fs::write(base_path.join("intro.md"), "# Intro").unwrap();
+    std::fs::write(base_path.join("guide/setup.md"), "# Setup").unwrap();
+
+    let api = init(Some(base_path), None).await?;
+    let base = base_path.to_string_lossy();
+    let outputs = api
+        .to_html(vec![
+            format!("{}/*.md", base),
+            format!("{}/**/*.md", base),
+            format!("{}/./guide/*.md", base),
+        ])
+        .await?;
+
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs.iter().filter(|o| o.html.contains("Intro")).count(), 1);
+    assert_eq!(outputs.iter().filter(|o| o.html.contains("Setup")).count(), 1);
+
+    Ok(())
+}
+
+/// Repeated resources are rendered once and documents follow the input order
+#[tokio::test]
+async fn test_render_dedupes_resources_in_input_order() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    for name in ["a", "b", "c"] {
+        std::fs::write(base_path.join(format!("{}.md", name)), format!("# {}", name)).unwrap();
+    }
+
+    let api = init(Some(base_path), None).await?;
+    let resource = |name: &str| Resource::local(base_path.join(format!("{}.md", name)));
+    let (documents, _warnings) = api
+        .render(vec![resource("c"), resource("a"), resource("c"), resource("b"), resource("a")], None)
+        .await?;
+
+    let order: Vec<_> = documents.iter().map(|doc| format!("{:?}", doc.resource.source)).collect();
+    assert_eq!(order.len(), 3);
+    assert!(order[0].contains("c.md") && order[1].contains("a.md") && order[2].contains("b.md"), "{:?}", order);
+
+    Ok(())
+}
+
+/// `::diff` compares files relative to the document
+#[tokio::test]
+async fn test_diff_rendering_e2e() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::create_dir(base_path.join("src")).unwrap();
+    std::fs::write(base_path.join("src/old.rs"), "fn answer() -> u32 {\n    41\n}\n").unwrap();
+    std::fs::write(base_path.join("src/new.rs"), "fn answer() -> u32 {\n    42\n}\n").unwrap();
+    std::fs::write(
+        base_path.join("changes.md"),
+        "# Changes\n\n::diff ./src/old.rs ./src/new.rs --lang rust\n",
+    )
+    .unwrap();
+
+    let api = init(Some(base_path), None).await?;
+    let outputs = api
+        .to_html(vec![base_path.join("changes.md").to_string_lossy().to_string()])
+        .await?;
+
+    let html = &outputs[0].html;
+    assert!(html.contains(r#"<span class="diff-file">--- ./src/old.rs</span>"#), "{}", html);
+    assert!(html.contains(r#"<span class="diff-del">-    41</span>"#), "{}", html);
+    assert!(html.contains(r#"<span class="diff-add">+    42</span>"#), "{}", html);
+    assert!(html.contains(r#"<style id="dm-diff">"#), "{}", html);
+
+    Ok(())
+}
+
+/// Frontmatter with the given string values
+fn state(values: &[(&str, &str)]) -> Frontmatter {
+    let mut frontmatter = Frontmatter::new();
+    for (key, value) in values {
+        frontmatter.custom.insert(key.to_string(), (*value).into());
+    }
+    frontmatter
+}
+
+/// Per-request states override the shared state, which overrides the
+/// instance frontmatter; document frontmatter wins over all of them
+#[tokio::test]
+async fn test_render_with_states_precedence() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    let page = "{{site}} / {{audience}} / {{tone}}";
+    std::fs::write(base_path.join("a.md"), page).unwrap();
+    std::fs::write(base_path.join("b.md"), format!("---\naudience: readers of b\n---\n{}", page)).unwrap();
+
+    let api = init_in_memory(Some(state(&[("site", "Instance"), ("audience", "instance"), ("tone", "plain")]))).await?;
+    let resource = |name: &str| Resource::local(base_path.join(name));
+    let documents = api
+        .render_with_states(
+            vec![
+                (resource("a.md"), Some(state(&[("audience", "developers")]))),
+                (resource("b.md"), Some(state(&[("audience", "executives")]))),
+                (resource("a.md"), None),
+            ],
+            Some(state(&[("site", "Docs"), ("audience", "everyone")])),
+        )
+        .await?;
+
+    let html: Vec<String> = documents.iter().map(|doc| render::to_html(&doc.content).unwrap()).collect();
+    assert_eq!(html.len(), 3);
+    assert!(html[0].contains("Docs / developers / plain"), "{}", html[0]);
+    assert!(html[1].contains("Docs / readers of b / plain"), "{}", html[1]);
+    assert!(html[2].contains("Docs / everyone / plain"), "{}", html[2]);
+
+    Ok(())
+}
+
+/// A dependency shared by two documents takes the state of each
+#[tokio::test]
+async fn test_render_with_states_renders_shared_dependency_per_root() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("greeting.md"), "Hello {{audience}}").unwrap();
+    std::fs::write(base_path.join("dev.md"), "# Developers\n\n::file ./greeting.md\n").unwrap();
+    std::fs::write(base_path.join("exec.md"), "# Executives\n\n::file ./greeting.md\n").unwrap();
+
+    let api = init_in_memory(None).await?;
+    let documents = api
+        .render_with_states(
+            vec![
+                (Resource::local(base_path.join("dev.md")), Some(state(&[("audience", "developers")]))),
+                (Resource::local(base_path.join("exec.md")), Some(state(&[("audience", "executives")]))),
+            ],
+            None,
+        )
+        .await?;
+
+    let dev = render::to_html(&documents[0].content)?;
+    let exec = render::to_html(&documents[1].content)?;
+    assert!(dev.contains("Hello developers") && !dev.contains("executives"), "{}", dev);
+    assert!(exec.contains("Hello executives") && !exec.contains("developers"), "{}", exec);
+
+    Ok(())
+}
+
+/// Output rendered with a request's state isn't reused for renders without it
+#[tokio::test]
+async fn test_render_incremental_ignores_output_rendered_with_a_state() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("page.md"), "Hello {{audience}}").unwrap();
+
+    let api = init_in_memory(Some(state(&[("audience", "everyone")]))).await?;
+    let page = Resource::local(base_path.join("page.md"));
+    api.render(vec![page.clone()], None).await?;
+    let prev_graph = api.graph(page.clone()).await?;
+
+    api.render_with_states(vec![(page.clone(), Some(state(&[("audience", "developers")])))], None)
+        .await?;
+    let documents = api.render_incremental(&prev_graph, vec![page]).await?;
+
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Hello everyone"), "{}", html);
+
+    Ok(())
+}
+
+/// A configured frontmatter schema rejects documents with missing or mistyped
+/// keys, but not fragments without frontmatter
+#[tokio::test]
+async fn test_frontmatter_schema_is_checked_when_rendering() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("snippet.md"), "Shared text").unwrap();
+    std::fs::write(base_path.join("good.md"), "---\ntitle: Good\n---\n::file ./snippet.md\n").unwrap();
+    std::fs::write(base_path.join("bad.md"), "---\ntitle: 3\n---\n# Bad\n").unwrap();
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.frontmatter_schema = Some(FrontmatterSchema::new().require("title", FrontmatterType::String));
+    let api = init_with_config(Some(base_path), None, config).await?;
+
+    let (documents, _warnings) = api.render(vec![Resource::local(base_path.join("good.md"))], None).await?;
+    assert!(render::to_html(&documents[0].content).unwrap().contains("Shared text"));
+
+    match api.render(vec![Resource::local(base_path.join("bad.md"))], None).await {
+        Err(CompositionError::Parse(ParseError::InvalidFrontmatter(message))) => {
+            assert!(message.ends_with("bad.md: key 'title' should be string but is number"), "{}", message)
+        }
+        other => panic!("Expected InvalidFrontmatter, got {:?}", other.map(|(docs, _)| docs.len())),
+    }
+
+    let error = api.render_string("---\nauthor: me\n---\n# Hi", Frontmatter::new()).await.unwrap_err();
+    assert!(error.to_string().contains("missing required key 'title'"), "{}", error);
+
+    Ok(())
+}
+
+/// Embeds text by counting mentions of a few keywords
+struct KeywordEmbeddings;
+
+#[async_trait::async_trait]
+impl lib::ai::EmbeddingModel for KeywordEmbeddings {
+    async fn embed(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let text = text.to_lowercase();
+                ["cache", "deploy"].iter().map(|keyword| text.matches(keyword).count() as f32).collect()
+            })
+            .collect())
+    }
+
+    fn model_name(&self) -> &str {
+        "test/keywords"
+    }
+
+    fn dimensions(&self) -> usize {
+        2
+    }
+}
+
+/// Semantic search ranks documents and picks the closest sentence as excerpt
+#[tokio::test]
+async fn test_semantic_search_across_documents() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("deploy.md"), "---\ntitle: Shipping\n---\nWe deploy on Mondays. The cache is warmed first.").unwrap();
+    std::fs::write(base_path.join("cache.md"), "# Caching\n\nThe cache keeps pages. Entries expire after a cache day.").unwrap();
+
+    let api = init(Some(base_path), None).await?;
+    let pattern = format!("{}/*.md", base_path.display());
+    assert!(matches!(
+        api.semantic_search("deploy", vec![pattern.clone()], 2).await,
+        Err(CompositionError::AI(_))
+    ));
+
+    let api = api.with_embedding_model(std::sync::Arc::new(KeywordEmbeddings));
+    let results = api.semantic_search("deployments", vec![pattern], 2).await?;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].path, base_path.join("deploy.md"));
+    assert_eq!(results[0].title.as_deref(), Some("Shipping"));
+    assert_eq!(results[0].excerpt, "We deploy on Mondays.");
+    assert_eq!(results[1].title.as_deref(), Some("Caching"));
+    assert!(results[0].score > results[1].score);
+
+    Ok(())
+}
+
+/// Batches return one result per item, in order, and report progress
+#[tokio::test]
+async fn test_asset_batches_without_markdown() -> Result<()> {
+    use lib::ai::MockCompletionModel;
+    use lib::audio::{AudioBatchOptions, AudioInput, AudioSource};
+    use std::sync::{Arc, Mutex};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.max_parallelism = Some(2);
+    let model = Arc::new(MockCompletionModel::new(vec!["Short.".to_string()]));
+    let api = init_with_config(Some(temp_dir.path()), None, config)
+        .await?
+        .with_completion_model(model.clone());
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    ::image::RgbaImage::from_pixel(700, 400, ::image::Rgba([0, 0, 255, 255]))
+        .write_to(&mut png, ::image::ImageFormat::Png)
+        .unwrap();
+    let png = png.into_inner();
+    let sources = vec![
+        ImageSource::Bytes { data: png.clone(), name: "a.png".to_string() },
+        ImageSource::Local(temp_dir.path().join("missing.png")),
+        ImageSource::Bytes { data: png, name: "b.png".to_string() },
+    ];
+
+    let updates = Mutex::new(Vec::new());
+    let record = |progress: BatchProgress| updates.lock().unwrap().push(progress);
+    let images = api.optimize_images(sources, Some(&record)).await;
+
+    assert_eq!(images.len(), 3);
+    assert_eq!(images[0].as_ref().unwrap().original_width, 700);
+    assert!(images[1].is_err());
+    assert_ne!(images[0].as_ref().unwrap().resource_hash, images[2].as_ref().unwrap().resource_hash);
+    let updates = updates.into_inner().unwrap();
+    assert_eq!(updates.iter().map(|p| p.completed).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert!(updates.iter().all(|p| p.total == 3 && p.succeeded == (p.index != 1)));
+
+    let inputs = vec![
+        AudioInput { source: AudioSource::Local("../tests/fixtures/audio/test.wav".into()), name: None },
+        AudioInput { source: AudioSource::Local(temp_dir.path().join("missing.wav")), name: None },
+    ];
+    let audio = api.process_audio_batch(inputs, &AudioBatchOptions::new(temp_dir.path().join("out")), None).await;
+
+    assert_eq!(audio.len(), 2);
+    let output = audio[0].as_ref().unwrap();
+    assert!(temp_dir.path().join("out").join(&output.path).exists());
+    assert!(audio[1].is_err());
+
+    assert_eq!(api.summarize_text("Long notes about caching.").await?, "Short.");
+    assert_eq!(api.summarize_text("Long notes about caching.").await?, "Short.");
+    assert_eq!(model.call_count(), 1);
+
+    Ok(())
+}
+
+/// Glob expansion in `to_html` skips ignored and excluded files and reports why
+#[tokio::test]
+async fn test_to_html_skips_ignored_and_excluded_files() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join(".gitignore"), "node_modules/\n").unwrap();
+    std::fs::write(base_path.join(".ignore"), "vendor/\n").unwrap();
+    for (path, content) in [
+        ("docs/guide.md", "# Guide"),
+        ("docs/notes.draft.md", "# Notes"),
+        ("drafts/wip.md", "# WIP"),
+        ("node_modules/pkg/README.md", "# Package"),
+        ("vendor/lib/README.md", "# Vendored"),
+    ] {
+        let path = base_path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    let pattern = format!("{}/**/*.md", base_path.display());
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.exclude_patterns = vec!["drafts/**".to_string(), "*.draft.md".to_string()];
+    let api = init_with_config(Some(base_path), None, config.clone()).await?;
+    let (outputs, metrics) = api.to_html_with_metrics(vec![pattern.clone()]).await?;
+
+    assert_eq!(outputs.len(), 1);
+    assert!(outputs[0].html.contains("Guide"));
+    let mut excluded: Vec<(String, ExclusionReason)> = metrics
+        .excluded
+        .iter()
+        .map(|file| {
+            let relative = file.path.strip_prefix(base_path).unwrap();
+            (relative.to_string_lossy().replace('\\', "/"), file.reason.clone())
+        })
+        .collect();
+    excluded.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        excluded,
+        vec![
+            ("docs/notes.draft.md".to_string(), ExclusionReason::ExcludePattern("*.draft.md".to_string())),
+            ("drafts/wip.md".to_string(), ExclusionReason::ExcludePattern("drafts/**".to_string())),
+            ("node_modules/pkg/README.md".to_string(), ExclusionReason::Ignored),
+            ("vendor/lib/README.md".to_string(), ExclusionReason::Ignored),
+        ]
+    );
+
+    // Ignored files can be opted back in; exclude patterns still apply
+    config.include_ignored = true;
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let (outputs, metrics) = api.to_html_with_metrics(vec![pattern]).await?;
+
+    assert_eq!(outputs.len(), 3);
+    assert_eq!(metrics.excluded.len(), 2);
+
+    Ok(())
+}
+
+/// Unknown directives, undefined variables and missing optional transclusions
+/// don't stop a render; they come back as warnings, or fail it in strict mode
+#[tokio::test]
+async fn test_to_html_writes_precompressed_output() -> Result<()> {
+    use std::io::Read;
+
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::create_dir(base_path.join("docs")).unwrap();
+    std::fs::write(base_path.join("docs/guide.md"), "# Guide\n\nStatic hosts serve this pre-compressed.\n").unwrap();
+    let out_dir = TempDir::new().unwrap();
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.output_dir = Some(out_dir.path().to_path_buf());
+    config.precompress = vec![Compression::Gzip, Compression::Brotli];
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let outputs = api.to_html(vec![format!("{}/docs/*.md", base_path.display())]).await?;
+    assert_eq!(outputs.len(), 1);
+
+    let html_path = out_dir.path().join("docs/guide.html");
+    assert_eq!(std::fs::read_to_string(&html_path).unwrap(), outputs[0].html);
+
+    let mut gzip = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(out_dir.path().join("docs/guide.html.gz")).unwrap())
+        .read_to_string(&mut gzip)
+        .unwrap();
+    assert_eq!(gzip, outputs[0].html);
+
+    let mut brotli = String::new();
+    brotli::Decompressor::new(std::fs::File::open(out_dir.path().join("docs/guide.html.br")).unwrap(), 4096)
+        .read_to_string(&mut brotli)
+        .unwrap();
+    assert_eq!(brotli, outputs[0].html);
+    assert_eq!(std::fs::read(out_dir.path().join("docs/guide.html.br")).unwrap(), outputs[0].compressed(Compression::Brotli));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_render_collects_warnings() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(
+        base_path.join("doc.md"),
+        "---\ntitle: Notes\n---\n# {{title}} for {{audience}}\n\n::fiel ./other.md\n\n::file ./missing.md?\n\nDone.\n",
+    )
+    .unwrap();
+    let resource = Resource::local(base_path.join("doc.md"));
+
+    let api = init_in_memory(None).await?;
+    let (documents, warnings) = api.render(vec![resource.clone()], None).await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Notes for {{audience}}") && html.contains("Done."), "{}", html);
+
+    assert_eq!(warnings.documents.len(), 1);
+    let codes: Vec<_> = warnings.iter().map(|(_, warning)| warning.code).collect();
+    assert_eq!(
+        codes,
+        [
+            ParseWarningCode::UnknownDirective,
+            ParseWarningCode::OptionalResourceNotFound,
+            ParseWarningCode::UndefinedVariable,
+        ]
+    );
+    let (document, unknown) = warnings.iter().next().unwrap();
+    assert_eq!(document.label(), resource.label());
+    assert_eq!(unknown.span.map(|span| (span.line, span.column)), Some((6, 1)));
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.parse_options = ParseOptions::strict();
+    let strict = init_with_config(Some(base_path), None, config).await?;
+    let error = strict.render(vec![resource], None).await.unwrap_err();
+    assert!(error.to_string().contains("Unknown directive ::fiel"), "{}", error);
+
+    Ok(())
+}
+
+/// A line range running past the end of its file is clamped, and the render
+/// says so in its warnings
+#[tokio::test]
+async fn test_render_warns_about_clamped_line_range() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("part.md"), "First.\n\nSecond.\n").unwrap();
+    std::fs::write(base_path.join("doc.md"), "# Parts\n\n::file ./part.md 3-40\n").unwrap();
+
+    let api = init_in_memory(None).await?;
+    let (documents, warnings) = api.render(vec![Resource::local(base_path.join("doc.md"))], None).await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Second.") && !html.contains("First."), "{}", html);
+
+    let reported: Vec<_> = warnings.iter().map(|(resource, warning)| (resource.label(), warning.code)).collect();
+    assert_eq!(reported.len(), 1, "{:?}", reported);
+    assert!(reported[0].0.ends_with("doc.md"));
+    assert_eq!(reported[0].1, ParseWarningCode::LineRangeClamped);
+
+    Ok(())
+}
+
+/// Inline audio over `max_inline_size` is embedded anyway, and the render
+/// says so in its warnings
+#[tokio::test]
+async fn test_render_warns_about_oversized_inline_audio() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::copy("../tests/fixtures/audio/test.wav", base_path.join("clip.wav")).unwrap();
+    std::fs::write(base_path.join("doc.md"), "# Listen\n\n::audio ./clip.wav\n").unwrap();
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.inline_audio = true;
+    config.audio.max_inline_size = 1;
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let (documents, warnings) = api.render(vec![Resource::local(base_path.join("doc.md"))], None).await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("<audio") && html.contains("data:audio/"), "{}", html);
+
+    let reported: Vec<_> = warnings.iter().map(|(resource, warning)| (resource.label(), warning.code)).collect();
+    assert_eq!(reported.len(), 1, "{:?}", reported);
+    assert!(reported[0].0.ends_with("doc.md"));
+    assert_eq!(reported[0].1, ParseWarningCode::OversizedInlineAudio);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_render_imports_frontmatter_from_another_document() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(
+        base_path.join("versions.md"),
+        "---\nlatest: 2.1.0\nlabel: \"v{{latest}}\"\nupstream: \"{{deps.core}}\"\nimports:\n  deps: ./deps.md\n---\n# Versions\n",
+    )
+    .unwrap();
+    std::fs::write(base_path.join("deps.md"), "---\ncore: 0.9.0\n---\n").unwrap();
+    std::fs::write(
+        base_path.join("changelog.md"),
+        "---\nimports:\n  versions: ./versions.md\n---\n# Changelog\n\nLatest is {{versions.latest}} ({{versions.label}}), \
+         upstream {{versions.upstream}}.\n",
+    )
+    .unwrap();
+
+    let api = init_in_memory(None).await?;
+    let changelog = Resource::local(base_path.join("changelog.md"));
+    let graph = api.graph(changelog.clone()).await?;
+    assert_eq!(graph.nodes.len(), 3);
+
+    let (documents, warnings) = api.render(vec![changelog], None).await?;
+    let document = documents.iter().find(|doc| doc.resource.label().ends_with("changelog.md")).unwrap();
+    let html = render::to_html(&document.content)?;
+
+    // Imported values are resolved one level deep
+    assert!(html.contains("Latest is 2.1.0 (v2.1.0), upstream {{deps.core}}."), "{}", html);
+    assert!(warnings.iter().all(|(_, warning)| warning.code != ParseWarningCode::UndefinedVariable), "{:?}", warnings);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_circular_imports_report_the_chain() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("a.md"), "---\nimports:\n  b: ./b.md\n---\nA\n").unwrap();
+    std::fs::write(base_path.join("b.md"), "---\nimports:\n  a: ./a.md\n---\nB\n").unwrap();
+
+    let api = init_in_memory(None).await?;
+    let error = api.graph(Resource::local(base_path.join("a.md"))).await.unwrap_err();
+
+    let CompositionError::Parse(ParseError::CircularDependency { cycle }) = &error else {
+        panic!("Expected circular dependency error, got: {:?}", error);
+    };
+    let chain: Vec<_> = cycle.split(" -> ").map(|label| label.rsplit(['/', '\\']).next().unwrap()).collect();
+    assert_eq!(chain, ["a.md", "b.md", "a.md"], "{}", cycle);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_render_with_fallback_chain() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::create_dir(base_path.join("vendor")).unwrap();
+    std::fs::write(base_path.join("vendor/spec.md"), "Vendored spec.\n").unwrap();
+    std::fs::write(
+        base_path.join("doc.md"),
+        "# Spec\n\n::file ./spec.md || ./vendor/spec.md\n\n::file ./a.md || ./b.md?\n",
+    )
+    .unwrap();
+
+    let api = init_in_memory(None).await?;
+    let (documents, metrics) = api
+        .render_with_metrics(vec![Resource::local(base_path.join("doc.md"))], None)
+        .await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("Vendored spec."), "{}", html);
+
+    // Reported both for the transclusion and for the chain rendered as a
+    // dependency of the document
+    assert!(!metrics.fallbacks.is_empty());
+    for fallback in &metrics.fallbacks {
+        assert!(fallback.resource.label().ends_with("/spec.md"));
+        assert!(fallback.used.label().ends_with("vendor/spec.md"));
+    }
+
+    // An optional chain none of whose candidates exist transcludes nothing
+    let codes: Vec<_> = metrics.parse_warnings.iter().map(|(_, warning)| warning.code).collect();
+    assert_eq!(codes, [ParseWarningCode::OptionalResourceNotFound]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_render_data_lists() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("items.csv"), "id,name\n1,Alpha\n2,Beta\n").unwrap();
+    std::fs::write(base_path.join("tasks.json"), r#"[{"title": "Plan", "done": true}, {"title": "Build", "done": false}]"#)
+        .unwrap();
+    std::fs::write(base_path.join("empty.csv"), "name\n").unwrap();
+    std::fs::write(
+        base_path.join("doc.md"),
+        "# Lists\n\n::list ./items.csv --column name --ordered\n\n\
+         ::list ./tasks.json --columns \"title,done\" --as tasklist\n\n::list ./empty.csv\n",
+    )
+    .unwrap();
+
+    let api = init_in_memory(None).await?;
+    let (documents, warnings) = api.render(vec![Resource::local(base_path.join("doc.md"))], None).await?;
+    let html = render::to_html(&documents[0].content)?;
+    assert!(html.contains("<ol class=\"data-list\">\n  <li>Alpha</li>\n  <li>Beta</li>\n</ol>"), "{}", html);
+    assert!(html.contains("disabled checked> Plan</li>") && html.contains("disabled> Build</li>"), "{}", html);
+    assert!(html.contains("<ul class=\"data-list\">\n</ul>"), "{}", html);
+
+    // The empty data file renders an empty list and a warning, not an error
+    let codes: Vec<_> = warnings.iter().map(|(_, warning)| warning.code).collect();
+    assert_eq!(codes, [ParseWarningCode::EmptyData]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transclude_section_extracts_a_middle_section() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("install.md"), "Install it with cargo install {{tool}}.\n").unwrap();
+    std::fs::write(
+        base_path.join("guide.md"),
+        "---\ntool: composition\n---\n# Overview\n\nWhat it is.\n\n## Getting Started\n\n::file ./install.md\n\n\
+         ### From Source\n\nClone it.\n\n## Reference\n\nEvery option.\n",
+    )
+    .unwrap();
+
+    let api = init_in_memory(None).await?;
+    let guide = Resource::local(base_path.join("guide.md"));
+    let document = api.transclude_section(guide.clone(), "getting-started").await?;
+    let html = render::to_html(&document.content)?;
+
+    assert!(html.contains("Getting Started") && html.contains("From Source"), "{}", html);
+    assert!(html.contains("cargo install composition"), "{}", html);
+    assert!(!html.contains("What it is") && !html.contains("Every option"), "{}", html);
+
+    let error = api.transclude_section(guide, "missing").await.unwrap_err();
+    assert!(
+        matches!(&error, CompositionError::Render(RenderError::TransclusionFailed { resource }) if resource.ends_with("guide.md#missing")),
+        "{:?}",
+        error
+    );
+
+    Ok(())
+}
+
+/// Headings brought in by transclusion show up in the outline, and their ids
+/// select the same sections as `transclude_section`
+#[tokio::test]
+async fn test_document_headings_include_transcluded_headings() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("install.md"), "### From Source\n\nClone it.\n").unwrap();
+    std::fs::write(
+        base_path.join("guide.md"),
+        "# Overview\n\n## Getting Started\n\n::file ./install.md\n\n## Reference {#options}\n\nEvery option.\n",
+    )
+    .unwrap();
+
+    let api = init_in_memory(None).await?;
+    let guide = Resource::local(base_path.join("guide.md"));
+    let headings = api.document_headings(guide.clone()).await?;
+
+    assert_eq!(headings.len(), 1);
+    let sections: Vec<_> = headings[0].children.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(sections, vec!["getting-started", "options"]);
+    assert_eq!(headings[0].children[0].children[0].text, "From Source");
+
+    let json = serde_json::to_value(&headings).unwrap();
+    assert_eq!(json[0]["children"][0]["children"][0]["id"], "from-source");
+    assert_eq!(json[0]["children"][0]["children"][0]["level"], 3);
+
+    let section = api.transclude_section(guide, &headings[0].children[1].id).await?;
+    assert!(render::to_html(&section.content)?.contains("Every option"));
+
+    Ok(())
+}
+
+/// Audio processed through the API reports cache hits and per-input failures
+#[tokio::test]
+async fn test_batch_process_audio_reports_cache_hits() -> Result<()> {
+    use lib::audio::{AudioInput, AudioProcessingConfig, AudioSource};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.audio_output_dir = temp_dir.path().join("out");
+    let api = init_with_config(Some(temp_dir.path()), None, config).await?;
+
+    let inputs = vec![
+        AudioInput { source: AudioSource::Local("../tests/fixtures/audio/test.wav".into()), name: None },
+        AudioInput { source: AudioSource::Local(temp_dir.path().join("missing.wav")), name: None },
+        AudioInput { source: AudioSource::Local("../tests/fixtures/audio/test.flac".into()), name: Some("Episode".into()) },
+    ];
+    let audio_config = AudioProcessingConfig { max_concurrent: 2, ..Default::default() };
+
+    let report = api.batch_process_audio(inputs.clone(), audio_config.clone()).await?;
+    assert_eq!((report.processed, report.cached), (2, 0), "{:?}", report.failed);
+    assert_eq!(report.outputs.len(), 2);
+    assert_eq!(report.outputs[1].display_name, "Episode");
+    assert!(temp_dir.path().join("out").join(&report.outputs[0].path).exists());
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0.source, inputs[1].source);
+
+    let report = api.batch_process_audio(inputs, audio_config.clone()).await?;
+    assert_eq!((report.processed, report.cached, report.failed.len()), (0, 2, 1));
+
+    let single = api
+        .process_audio(
+            AudioInput { source: AudioSource::Local("../tests/fixtures/audio/test.wav".into()), name: None },
+            audio_config,
+        )
+        .await?;
+    assert_eq!(single.path, report.outputs[0].path);
+
+    Ok(())
+}
+
+/// `to_html` outputs carry the document's metadata for feeds and manifests
+#[tokio::test]
+async fn test_to_html_output_includes_document_metadata() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::write(base_path.join("intro.md"), "Welcome to the **show notes**.\n").unwrap();
+    std::fs::write(
+        base_path.join("episode.md"),
+        "---\ntitle: Episode One\nauthor: Sam\ntags: [rust, audio]\n---\n# Ignored Heading\n\n::file ./intro.md\n\n![Cover](images/cover.png)\n\n\
+         ```sh\nnot counted\n```\n\nThanks for listening.\n",
+    )
+    .unwrap();
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.excerpt_words = 4;
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let before = chrono::Utc::now();
+    let outputs = api.to_html(vec![format!("{}/episode.md", base_path.display())]).await?;
+    let output = &outputs[0];
+
+    assert_eq!(output.title.as_deref(), Some("Episode One"));
+    assert_eq!(output.frontmatter.get_string("title"), Some("Episode One"));
+    assert!(output.resource.label().ends_with("episode.md"));
+    assert!(output.dependencies.iter().any(|dep| dep.label().ends_with("intro.md")), "{:?}", output.dependencies);
+    assert_eq!(output.excerpt, "Welcome to the show…");
+    assert_eq!(output.word_count, 8);
+    assert_eq!(output.metadata.get("author").map(String::as_str), Some("Sam"));
+    assert_eq!(output.metadata.get("tags").map(String::as_str), Some("rust, audio"));
+    assert!(!output.metadata.contains_key("description"));
+    assert_eq!(
+        output.linked_assets,
+        vec![AssetRef { kind: AssetKind::Image, path: "images/cover.png".to_string(), hash: None }]
+    );
+    assert_eq!(
+        output.to_asset_manifest(),
+        serde_json::json!([{ "kind": "image", "path": "images/cover.png", "hash": null }])
+    );
+    assert!(output.parsed_at <= output.rendered_at && before <= output.rendered_at);
+
+    let json = serde_json::to_value(output).unwrap();
+    assert_eq!(json["title"], "Episode One");
+    assert_eq!(json["word_count"], 8);
+
+    Ok(())
+}
+
+/// `to_feed` publishes dated, non-draft documents newest first with absolute URLs
+#[tokio::test]
+async fn test_to_feed_writes_a_valid_atom_feed() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    let posts = base_path.join("posts");
+    std::fs::create_dir(&posts).unwrap();
+    std::fs::write(posts.join("first.md"), "---\ntitle: First Post\ndate: 2024-01-05\n---\nHello ![Cover](images/cover.png)\n").unwrap();
+    std::fs::write(
+        posts.join("second.md"),
+        "---\ntitle: Second Post\nauthor: Sam\ndate: 2024-02-10T09:00:00Z\n---\nSee [the first](first.html).\n",
+    )
+    .unwrap();
+    std::fs::write(posts.join("draft.md"), "---\ntitle: Draft\ndate: 2024-03-01\ndraft: true\n---\nNot yet.\n").unwrap();
+    std::fs::write(posts.join("undated.md"), "---\ntitle: Undated\n---\nNo date.\n").unwrap();
+
+    let api = init_with_config(Some(base_path), None, CompositionConfig::new(CacheBackend::Memory)).await?;
+    let pattern = vec![format!("{}/posts/*.md", base_path.display())];
+    let options = FeedOptions::new("Blog", "https://example.com/blog".parse().unwrap());
+    let xml = api.to_feed(pattern.clone(), options.clone()).await?;
+
+    let doc = roxmltree::Document::parse(&xml).expect("feed is well-formed XML");
+    let feed = doc.root_element();
+    assert_eq!(feed.tag_name().namespace(), Some("http://www.w3.org/2005/Atom"));
+    assert_eq!(feed.tag_name().name(), "feed");
+    let text_of = |node: roxmltree::Node, name: &str| {
+        node.children().find(|n| n.tag_name().name() == name).and_then(|n| n.text()).map(str::to_string)
+    };
+    assert_eq!(text_of(feed, "updated").as_deref(), Some("2024-02-10T09:00:00Z"));
+
+    let entries: Vec<_> = feed.children().filter(|n| n.tag_name().name() == "entry").collect();
+    let titles: Vec<_> = entries.iter().map(|e| text_of(*e, "title").unwrap()).collect();
+    assert_eq!(titles, vec!["Second Post", "First Post"]);
+    assert_eq!(text_of(entries[0], "id").as_deref(), Some("https://example.com/blog/posts/second.html"));
+
+    let second = text_of(entries[0], "content").unwrap();
+    assert!(second.contains(r#"href="https://example.com/blog/posts/first.html""#), "{}", second);
+    let first = text_of(entries[1], "content").unwrap();
+    assert!(first.contains(r#"src="https://example.com/blog/posts/images/cover.png""#), "{}", first);
+
+    let mut strict = options;
+    strict.strict = true;
+    let err = api.to_feed(pattern, strict).await.unwrap_err();
+    assert!(matches!(err, CompositionError::Render(RenderError::FeedFailed(ref msg)) if msg.contains("undated.md")), "{}", err);
+
+    Ok(())
+}
+
+/// Rendering in a language reads translations where they exist, for the
+/// document and its transclusions, and reports which file was used
+#[tokio::test]
+async fn test_language_renders_prefer_translations() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    let parts = base_path.join("parts");
+    std::fs::create_dir(&parts).unwrap();
+    std::fs::write(parts.join("note.md"), "English note\n").unwrap();
+    std::fs::write(parts.join("note.de.md"), "Deutsche Notiz\n").unwrap();
+    std::fs::write(base_path.join("intro.md"), "# Intro\n\nHello in {{language}}\n\n::file ./parts/note.md\n").unwrap();
+    std::fs::write(base_path.join("intro.de.md"), "# Einleitung\n\nHallo auf {{language}}\n\n::file ./parts/note.md\n").unwrap();
+    std::fs::write(base_path.join("outro.md"), "# Outro\n\n::file ./parts/note.md\n").unwrap();
+    let pattern = vec![format!("{}/*.md", base_path.display())];
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.languages = vec!["en".to_string(), "de".to_string()];
+    let api = init_with_config(Some(base_path), None, config.clone()).await?;
+    let outputs = api.to_html_languages(pattern.clone()).await?;
+
+    let found: Vec<_> = outputs
+        .iter()
+        .map(|o| (o.language.as_deref().unwrap(), o.path.file_name().unwrap().to_string_lossy().to_string()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("en", "intro.en.md".to_string()),
+            ("en", "outro.en.md".to_string()),
+            ("de", "intro.de.md".to_string()),
+            ("de", "outro.de.md".to_string()),
+        ]
+    );
+    assert!(outputs[0].html.contains("Hello in en") && outputs[0].html.contains("English note"), "{}", outputs[0].html);
+    assert!(outputs[2].html.contains("Hallo auf de") && outputs[2].html.contains("Deutsche Notiz"), "{}", outputs[2].html);
+    assert!(outputs[3].html.contains("Outro") && outputs[3].html.contains("Deutsche Notiz"), "{}", outputs[3].html);
+
+    // A single language, with the report of what was read
+    config.languages = Vec::new();
+    config.language = Some("de".to_string());
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let (outputs, metrics) = api.to_html_with_metrics(pattern).await?;
+
+    assert_eq!(outputs.len(), 2);
+    assert!(metrics
+        .excluded
+        .iter()
+        .any(|file| file.path.ends_with("intro.de.md") && file.reason == ExclusionReason::Translation("de".to_string())));
+    let translated = |name: &str| {
+        metrics
+            .language_variants
+            .iter()
+            .find(|variant| variant.resource.label().ends_with(name))
+            .map(LanguageVariant::is_translated)
+    };
+    assert_eq!(translated("intro.md"), Some(true));
+    assert_eq!(translated("outro.md"), Some(false));
+    assert_eq!(translated("note.md"), Some(true));
+
+    // The graph has the translation as the node, so only German output
+    // depends on it
+    let graph = api.graph(Resource::local(base_path.join("outro.md"))).await?;
+    let labels: Vec<String> = graph.nodes.values().map(|node| node.resource.label()).collect();
+    assert!(labels.iter().any(|label| label.ends_with("note.de.md")), "{:?}", labels);
+    assert!(!labels.iter().any(|label| label.ends_with("note.md")), "{:?}", labels);
+
+    Ok(())
+}
+
+/// `structured_data` starts the HTML with JSON-LD built from frontmatter,
+/// pointing at the hero image's optimized variant once there is one
+#[tokio::test]
+async fn test_to_html_structured_data() -> Result<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path();
+    std::fs::create_dir_all(base_path.join("images")).unwrap();
+    ::image::RgbImage::from_pixel(1000, 500, ::image::Rgb([0, 0, 255]))
+        .save(base_path.join("images").join("hero.png"))
+        .unwrap();
+    std::fs::write(
+        base_path.join("post.md"),
+        "---\ntitle: Launch\ndescription: We shipped\nauthor: Ada\ndate: 2024-03-01\nimage: images/hero.png\n---\n\n# Launch\n\nText.\n",
+    )
+    .unwrap();
+    std::fs::write(base_path.join("broken.md"), "---\njsonld: \"{oops\"\n---\n\n# Broken\n").unwrap();
+
+    let mut config = CompositionConfig::new(CacheBackend::Memory);
+    config.structured_data = true;
+    let api = init_with_config(Some(base_path), None, config).await?;
+    let post = base_path.join("post.md").to_string_lossy().to_string();
+
+    let outputs = api.to_html(vec![post.clone()]).await?;
+    let html = &outputs[0].html;
+    assert!(html.starts_with(r#"<script type="application/ld+json">{"@context":"https://schema.org","@type":"Article","headline":"Launch""#), "{}", html);
+    assert!(html.contains(r#""author":{"@type":"Person","name":"Ada"}"#), "{}", html);
+    assert!(html.contains(r#""image":"images/hero.png""#), "{}", html);
+
+    let image = api.optimize_image(ImageSource::Local(base_path.join("images").join("hero.png"))).await?;
+    let largest = image.variants.iter().filter(|v| v.format == lib::image::ImageFormat::Jpeg).max_by_key(|v| v.width).unwrap();
+    let outputs = api.to_html(vec![post]).await?;
+    let expected = format!(
+        r#""image":{{"@type":"ImageObject","url":"images/{}","width":{},"height":{}}}"#,
+        largest.path.as_deref().unwrap(),
+        largest.width,
+        largest.height
+    );
+    assert!(outputs[0].html.contains(&expected), "{}", outputs[0].html);
+
+    let (outputs, metrics) = api.to_html_with_metrics(vec![base_path.join("broken.md").to_string_lossy().to_string()]).await?;
+    assert!(outputs[0].html.contains(r#""@type":"WebPage""#));
+    let codes: Vec<_> = metrics.parse_warnings.iter().map(|(_, warning)| warning.code).collect();
+    assert_eq!(codes, vec![ParseWarningCode::InvalidStructuredData]);
+
+    Ok(())
+}
@@ -1,16 +1,18 @@
 use image::{Rgba, RgbaImage, ImageFormat as ImgFormat};
 use lib::cache::database::init_database;
+use lib::cache::CacheOperations;
 use lib::image::{
     get_or_process_image, ImageOptions, ImageSource,
 };
 use lib::image::html::{HtmlOptions, LayoutMode, Loading, Decoding};
 use tempfile::TempDir;
+use url::Url;
 
-async fn setup_test_db() -> (surrealdb::Surreal<surrealdb::engine::local::Db>, TempDir) {
+async fn setup_test_db() -> (CacheOperations, TempDir) {
     let temp_dir = TempDir::new().unwrap();
     let db_path = temp_dir.path().join("test.db");
     let db = init_database(&db_path).await.unwrap();
-    (db, temp_dir)
+    (CacheOperations::new(db), temp_dir)
 }
 
 fn create_test_image(width: u32, height: u32, has_transparency: bool) -> RgbaImage {
@@ -27,7 +29,7 @@ fn create_test_image(width: u32, height: u32, has_transparency: bool) -> RgbaIma
 
 #[tokio::test]
 async fn test_process_local_image_creates_variants() {
-    let (db, temp_dir) = setup_test_db().await;
+    let (cache, temp_dir) = setup_test_db().await;
 
     // Create a test image
     let img = create_test_image(1200, 800, false);
@@ -38,7 +40,7 @@ async fn test_process_local_image_creates_variants() {
     let options = ImageOptions::default();
     let html_options = HtmlOptions::default();
 
-    let result = get_or_process_image(&source, options, html_options, &db).await;
+    let result = get_or_process_image(&source, options, html_options, &cache, None).await;
     assert!(result.is_ok());
 
     let output = result.unwrap();
@@ -53,7 +55,7 @@ async fn test_process_local_image_creates_variants() {
 
 #[tokio::test]
 async fn test_transparent_image_uses_correct_formats() {
-    let (db, temp_dir) = setup_test_db().await;
+    let (cache, temp_dir) = setup_test_db().await;
 
     // Create a transparent test image
     let img = create_test_image(640, 480, true);
@@ -64,7 +66,7 @@ async fn test_transparent_image_uses_correct_formats() {
     let options = ImageOptions::default();
     let html_options = HtmlOptions::default();
 
-    let result = get_or_process_image(&source, options, html_options, &db).await;
+    let result = get_or_process_image(&source, options, html_options, &cache, None).await;
     assert!(result.is_ok());
 
     let output = result.unwrap();
@@ -79,7 +81,7 @@ async fn test_transparent_image_uses_correct_formats() {
 
 #[tokio::test]
 async fn test_small_image_no_upscaling() {
-    let (db, temp_dir) = setup_test_db().await;
+    let (cache, temp_dir) = setup_test_db().await;
 
     // Create a small image (smaller than smallest breakpoint)
     let img = create_test_image(400, 300, false);
@@ -90,7 +92,7 @@ async fn test_small_image_no_upscaling() {
     let options = ImageOptions::default();
     let html_options = HtmlOptions::default();
 
-    let result = get_or_process_image(&source, options, html_options, &db).await;
+    let result = get_or_process_image(&source, options, html_options, &cache, None).await;
     assert!(result.is_ok());
 
     let output = result.unwrap();
@@ -103,7 +105,7 @@ async fn test_small_image_no_upscaling() {
 
 #[tokio::test]
 async fn test_html_generation_with_alt_text() {
-    let (db, temp_dir) = setup_test_db().await;
+    let (cache, temp_dir) = setup_test_db().await;
 
     let img = create_test_image(800, 600, false);
     let temp_path = temp_dir.path().join("test_alt.png");
@@ -117,9 +119,10 @@ async fn test_html_generation_with_alt_text() {
         loading: Loading::Eager,
         decoding: Decoding::Sync,
         blur_placeholder: None,
+        ..Default::default()
     };
 
-    let result = get_or_process_image(&source, options, html_options, &db).await;
+    let result = get_or_process_image(&source, options, html_options, &cache, None).await;
     assert!(result.is_ok());
 
     let output = result.unwrap();
@@ -130,7 +133,7 @@ async fn test_html_generation_with_alt_text() {
 
 #[tokio::test]
 async fn test_image_caching() {
-    let (db, temp_dir) = setup_test_db().await;
+    let (cache, temp_dir) = setup_test_db().await;
 
     let img = create_test_image(640, 480, false);
     let temp_path = temp_dir.path().join("test_cache.png");
@@ -141,11 +144,11 @@ async fn test_image_caching() {
     let html_options = HtmlOptions::default();
 
     // Process first time - should create cache entry
-    let result1 = get_or_process_image(&source, options.clone(), html_options.clone(), &db).await;
+    let result1 = get_or_process_image(&source, options.clone(), html_options.clone(), &cache, None).await;
     assert!(result1.is_ok());
 
     // Process second time - should hit cache (though currently still processes)
-    let result2 = get_or_process_image(&source, options, html_options, &db).await;
+    let result2 = get_or_process_image(&source, options, html_options, &cache, None).await;
     assert!(result2.is_ok());
 
     // Both results should be identical
@@ -156,9 +159,83 @@ async fn test_image_caching() {
     assert_eq!(output1.original_height, output2.original_height);
 }
 
+#[tokio::test]
+async fn test_optimize_in_memory_image() {
+    let temp_dir = TempDir::new().unwrap();
+    let api = lib::init_with_backend(Some(temp_dir.path()), None, lib::CacheBackend::Memory)
+        .await
+        .unwrap();
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    create_test_image(1000, 600, false)
+        .write_to(&mut png, ImgFormat::Png)
+        .unwrap();
+    let source = ImageSource::Bytes {
+        data: png.into_inner(),
+        name: "uploads/banner.png".to_string(),
+    };
+
+    let first = api.optimize_image(source.clone()).await.unwrap();
+    let second = api.optimize_image(source).await.unwrap();
+
+    assert_eq!((first.original_width, first.original_height), (1000, 600));
+    assert!(!first.variants.is_empty());
+    assert_eq!(first.resource_hash, second.resource_hash);
+    let paths = |output: &lib::image::SmartImageOutput| {
+        output.variants.iter().map(|v| v.path.clone().unwrap()).collect::<Vec<_>>()
+    };
+    assert_eq!(paths(&first), paths(&second));
+    assert!(paths(&first)[0].starts_with(&first.resource_hash));
+}
+
+#[tokio::test]
+async fn test_find_similar_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let api = lib::init_with_backend(Some(temp_dir.path()), None, lib::CacheBackend::Memory)
+        .await
+        .unwrap();
+
+    // A gradient with a bright square, a smaller copy under another name,
+    // and the same picture mirrored
+    let scene = |width: u32, height: u32| {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x as f64 / width as f64, y as f64 / height as f64);
+            if (0.55..0.85).contains(&fx) && (0.15..0.45).contains(&fy) {
+                Rgba([250, 240, 230, 255])
+            } else {
+                let shade = ((fx + fy) * 100.0) as u8;
+                Rgba([shade, shade / 2, 255 - shade, 255])
+            }
+        })
+    };
+    let original = temp_dir.path().join("hero.png");
+    let copy = temp_dir.path().join("hero-final-v2.png");
+    let mirrored = temp_dir.path().join("hero-mirrored.png");
+    scene(800, 600).save_with_format(&original, ImgFormat::Png).unwrap();
+    scene(700, 525).save_with_format(&copy, ImgFormat::Png).unwrap();
+    image::imageops::flip_horizontal(&scene(800, 600)).save_with_format(&mirrored, ImgFormat::Png).unwrap();
+
+    let mut hashes = Vec::new();
+    for path in [&original, &copy, &mirrored] {
+        let output = api.optimize_image(ImageSource::Local(path.clone())).await.unwrap();
+        hashes.push(output.resource_hash);
+    }
+
+    let similar = api.find_similar_images(lib::Resource::local(original.clone()), 10).await.unwrap();
+
+    let found: Vec<String> = similar.iter().map(|(hash, _)| hash.to_string()).collect();
+    assert_eq!(found, [hashes[1].clone()]);
+    assert!(similar[0].1 <= 10);
+
+    // Every cached image is within 64 bits, but the picture itself isn't listed
+    let all = api.find_similar_images(lib::Resource::local(original), 64).await.unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all[0].1 <= all[1].1);
+}
+
 #[tokio::test]
 async fn test_quality_setting() {
-    let (_db, temp_dir) = setup_test_db().await;
+    let (_cache, temp_dir) = setup_test_db().await;
 
     let img_rgba = create_test_image(200, 200, false);
     let temp_path = temp_dir.path().join("test_quality.png");
@@ -202,3 +279,48 @@ fn test_breakpoints_correct_order() {
     assert_eq!(BREAKPOINTS[3].1, 1280); // xl
     assert_eq!(BREAKPOINTS[4].1, 1536); // xxl
 }
+
+#[tokio::test]
+async fn test_picture_html_with_cdn() {
+    let (cache, temp_dir) = setup_test_db().await;
+
+    let img = create_test_image(800, 600, false);
+    let temp_path = temp_dir.path().join("cdn.png");
+    img.save_with_format(&temp_path, ImgFormat::Png).unwrap();
+    let source = ImageSource::Local(temp_path);
+
+    let inline = get_or_process_image(&source, ImageOptions::default(), HtmlOptions::default(), &cache, None)
+        .await
+        .unwrap();
+
+    let cdn_options = HtmlOptions {
+        cdn_base_url: Some(Url::parse("https://cdn.example.com").unwrap()),
+        cdn_path_prefix: Some("/images/".to_string()),
+        cache_bust: true,
+        ..Default::default()
+    };
+    let cdn = get_or_process_image(&source, ImageOptions::default(), cdn_options, &cache, None)
+        .await
+        .unwrap();
+
+    // Without a CDN every variant is inlined
+    assert!(inline.html.contains("data:image/"));
+    assert!(!inline.html.contains("https://cdn.example.com"));
+
+    // With a CDN every variant links to its file, and nothing is inlined
+    assert!(!cdn.html.contains("data:"));
+    for variant in &cdn.variants {
+        let path = variant.path.as_deref().unwrap();
+        assert!(path.starts_with(&cdn.resource_hash), "{}", path);
+        assert!(
+            cdn.html.contains(&format!("https://cdn.example.com/images/{}?v=", path)),
+            "missing {} in {}",
+            path,
+            cdn.html
+        );
+    }
+    assert!(cdn.html.contains(r#"<img src="https://cdn.example.com/images/"#));
+
+    // The blur placeholder is a BlurHash on the fallback image
+    assert!(cdn.html.contains(&format!(r#"data-blurhash="{}""#, cdn.blur_placeholder)));
+}
@@ -3,7 +3,7 @@ mod common;
 use chrono::Utc;
 use common::fixtures::{temp_dir, test_frontmatter, test_local_resource};
 use common::helpers::{compute_test_hash, init_test_db};
-use lib::cache::{apply_schema, DocumentCacheEntry, ImageCacheEntry, LlmCacheEntry};
+use lib::cache::{apply_schema, DocumentCacheEntry, EmbeddingCacheEntry, ImageCacheEntry, LlmCacheEntry};
 use lib::init;
 
 /// Test basic database initialization
@@ -103,6 +103,8 @@ async fn test_document_cache_operations() {
         file_path: Some("/tmp/test.md".to_string()),
         url: None,
         last_validated: Utc::now(),
+        file_size: None,
+        modified_at: None,
     };
 
     // Upsert
@@ -120,6 +122,284 @@ async fn test_document_cache_operations() {
     assert_eq!(retrieved.content_hash, entry.content_hash);
 }
 
+/// Repeated upserts replace the existing row instead of adding another
+#[tokio::test]
+async fn test_repeated_upserts_keep_one_row() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db.clone());
+
+    for i in 0..3 {
+        cache
+            .upsert_document(DocumentCacheEntry {
+                id: None,
+                resource_hash: "repeat_doc".to_string(),
+                content_hash: format!("content_v{}", i),
+                file_path: Some("/tmp/repeat.md".to_string()),
+                url: None,
+                last_validated: Utc::now(),
+                file_size: None,
+                modified_at: None,
+            })
+            .await
+            .unwrap();
+
+        cache
+            .upsert_image(ImageCacheEntry {
+                id: None,
+                resource_hash: "repeat_image".to_string(),
+                content_hash: format!("image_v{}", i),
+                created_at: Utc::now(),
+                expires_at: None,
+                source_type: "local".to_string(),
+                source: "/tmp/repeat.png".to_string(),
+                has_transparency: false,
+                original_width: 100,
+                original_height: 100,
+                perceptual_hash: None,
+            })
+            .await
+            .unwrap();
+
+        cache
+            .upsert_llm(LlmCacheEntry {
+                id: None,
+                operation: "summarize".to_string(),
+                input_hash: "repeat_input".to_string(),
+                model: "test/model".to_string(),
+                response: format!("summary_v{}", i),
+                created_at: Utc::now(),
+                expires_at: Utc::now() + chrono::Duration::days(30),
+                tokens_used: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    for table in ["document", "image_cache", "llm_cache"] {
+        let count: Option<usize> = db
+            .query(format!("RETURN count(SELECT id FROM {})", table))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(count, Some(1), "{} should hold a single row", table);
+    }
+
+    let document = cache.get_document("repeat_doc").await.unwrap().unwrap();
+    assert_eq!(document.content_hash, "content_v2");
+    let image = cache.get_image("repeat_image").await.unwrap().unwrap();
+    assert_eq!(image.content_hash, "image_v2");
+    let llm = cache
+        .get_llm("summarize", "repeat_input", "test/model")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(llm.response, "summary_v2");
+}
+
+/// Test bulk document upsert with a large batch
+#[tokio::test]
+async fn test_bulk_document_upsert() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let entries: Vec<DocumentCacheEntry> = (0..500)
+        .map(|i| DocumentCacheEntry {
+            id: None,
+            resource_hash: format!("bulk_hash_{}", i),
+            content_hash: format!("content_{}", i),
+            file_path: Some(format!("/tmp/doc_{}.md", i)),
+            url: None,
+            last_validated: Utc::now(),
+            file_size: None,
+            modified_at: None,
+        })
+        .collect();
+
+    cache.upsert_documents(entries.clone()).await.unwrap();
+
+    for entry in &entries {
+        let retrieved = cache.get_document(&entry.resource_hash).await.unwrap().unwrap();
+        assert_eq!(retrieved.content_hash, entry.content_hash);
+    }
+
+    // A second batch replaces existing entries rather than duplicating them
+    let updated: Vec<DocumentCacheEntry> = entries
+        .iter()
+        .take(10)
+        .map(|entry| DocumentCacheEntry {
+            content_hash: format!("{}_v2", entry.content_hash),
+            ..entry.clone()
+        })
+        .collect();
+    cache.upsert_documents(updated).await.unwrap();
+
+    let retrieved = cache.get_document("bulk_hash_3").await.unwrap().unwrap();
+    assert_eq!(retrieved.content_hash, "content_3_v2");
+    let retrieved = cache.get_document("bulk_hash_42").await.unwrap().unwrap();
+    assert_eq!(retrieved.content_hash, "content_42");
+}
+
+/// Test batch document lookup, including keys that aren't cached
+#[tokio::test]
+async fn test_batch_document_lookup() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let entries: Vec<DocumentCacheEntry> = (0..500)
+        .map(|i| DocumentCacheEntry {
+            id: None,
+            resource_hash: format!("node_{}", i),
+            content_hash: format!("content_{}", i),
+            file_path: Some(format!("/tmp/node_{}.md", i)),
+            url: None,
+            last_validated: Utc::now(),
+            file_size: None,
+            modified_at: None,
+        })
+        .collect();
+    cache.upsert_documents(entries).await.unwrap();
+
+    let hashes: Vec<String> = (0..500).map(|i| format!("node_{}", i)).collect();
+    let keys: Vec<&str> = hashes.iter().map(String::as_str).collect();
+
+    // Every key comes back from the one query
+    let found = cache.get_documents(&keys).await.unwrap();
+    assert_eq!(found.len(), 500);
+    assert_eq!(found["node_42"].content_hash, "content_42");
+
+    // Absent keys are simply missing from the map
+    let found = cache
+        .get_documents(&["node_1", "missing_a", "node_2", "missing_b"])
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+    assert!(found.contains_key("node_1"));
+    assert!(found.contains_key("node_2"));
+    assert!(!found.contains_key("missing_a"));
+
+    assert!(cache.get_documents(&[]).await.unwrap().is_empty());
+}
+
+/// Test batch image cache operations
+#[tokio::test]
+async fn test_batch_image_cache_operations() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let image = |hash: &str, width: i64| ImageCacheEntry {
+        id: None,
+        resource_hash: hash.to_string(),
+        content_hash: format!("{}_content", hash),
+        created_at: Utc::now(),
+        expires_at: None,
+        source_type: "local".to_string(),
+        source: format!("/tmp/{}.png", hash),
+        has_transparency: false,
+        original_width: width,
+        original_height: 100,
+        perceptual_hash: None,
+    };
+
+    cache
+        .upsert_images(vec![image("img_a", 100), image("img_b", 200), image("img_a", 300)])
+        .await
+        .unwrap();
+
+    let found = cache.get_images(&["img_a", "img_b", "img_c"]).await.unwrap();
+    assert_eq!(found.len(), 2);
+    // The last entry for a repeated hash wins
+    assert_eq!(found["img_a"].original_width, 300);
+    assert_eq!(found["img_b"].original_width, 200);
+}
+
+/// Test batch LLM cache operations
+#[tokio::test]
+async fn test_batch_llm_cache_operations() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let llm = |input_hash: &str, response: &str, expires_in_days: i64| LlmCacheEntry {
+        id: None,
+        operation: "summarize".to_string(),
+        input_hash: input_hash.to_string(),
+        model: "test/model".to_string(),
+        response: response.to_string(),
+        created_at: Utc::now(),
+        expires_at: Utc::now() + chrono::Duration::days(expires_in_days),
+        tokens_used: None,
+    };
+
+    cache
+        .upsert_llms(vec![llm("in_a", "first", 30), llm("in_b", "stale", -1)])
+        .await
+        .unwrap();
+    // Replaces the existing entry for the same operation, input and model
+    cache.upsert_llms(vec![llm("in_a", "second", 30)]).await.unwrap();
+
+    let found = cache
+        .get_llms("summarize", "test/model", &["in_a", "in_b", "in_c"])
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found["in_a"].response, "second");
+
+    // Different model: nothing matches
+    let found = cache
+        .get_llms("summarize", "other/model", &["in_a"])
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+}
+
+/// Test batch embedding cache operations
+#[tokio::test]
+async fn test_batch_embedding_cache_operations() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let embedding = |content_hash: &str, model: &str, vector: Vec<f32>| EmbeddingCacheEntry {
+        content_hash: content_hash.to_string(),
+        model: model.to_string(),
+        vector,
+        created_at: Utc::now(),
+    };
+
+    cache
+        .upsert_embeddings(vec![
+            embedding("text_a", "test/embed", vec![1.0, 0.0]),
+            embedding("text_b", "test/embed", vec![0.0, 1.0]),
+            embedding("text_a", "other/embed", vec![0.5, 0.5]),
+        ])
+        .await
+        .unwrap();
+    // Replaces the existing entry for the same text and model
+    cache
+        .upsert_embeddings(vec![embedding("text_a", "test/embed", vec![0.25, 0.75])])
+        .await
+        .unwrap();
+
+    let found = cache
+        .get_embeddings("test/embed", &["text_a", "text_b", "text_c"])
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(found["text_a"].vector, vec![0.25, 0.75]);
+    assert_eq!(found["text_b"].vector, vec![0.0, 1.0]);
+}
+
 /// Test image cache operations
 #[tokio::test]
 async fn test_image_cache_operations() {
@@ -140,6 +420,7 @@ async fn test_image_cache_operations() {
         has_transparency: true,
         original_width: 1920,
         original_height: 1080,
+        perceptual_hash: None,
     };
 
     // Upsert
@@ -223,6 +504,195 @@ async fn test_llm_cache_expiration() {
     assert!(retrieved.is_none());
 }
 
+/// Test cache statistics and hit/miss tracking
+#[tokio::test]
+async fn test_cache_stats() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db).with_metrics();
+
+    let empty = cache.stats().await.unwrap();
+    assert_eq!(empty.document_count, 0);
+    assert_eq!(empty.oldest_entry, None);
+    assert_eq!(empty.hit_rate(), None);
+
+    let oldest = Utc::now() - chrono::Duration::days(60);
+    let llm_entry = |input_hash: &str, created_at, expires_at| LlmCacheEntry {
+        id: None,
+        operation: "summarize".to_string(),
+        input_hash: input_hash.to_string(),
+        model: "test/model".to_string(),
+        response: "summary".to_string(),
+        created_at,
+        expires_at,
+        tokens_used: None,
+    };
+    cache
+        .upsert_llms(vec![
+            llm_entry("expired", oldest, Utc::now() - chrono::Duration::days(30)),
+            llm_entry("live", Utc::now(), Utc::now() + chrono::Duration::days(30)),
+        ])
+        .await
+        .unwrap();
+    cache
+        .upsert_image(ImageCacheEntry {
+            id: None,
+            resource_hash: "stats_image".to_string(),
+            content_hash: "content".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            source_type: "local".to_string(),
+            source: "/tmp/stats.png".to_string(),
+            has_transparency: false,
+            original_width: 10,
+            original_height: 10,
+            perceptual_hash: None,
+        })
+        .await
+        .unwrap();
+
+    assert!(cache.get_image("stats_image").await.unwrap().is_some());
+    assert_eq!(cache.get_images(&["stats_image", "missing_1", "missing_2"]).await.unwrap().len(), 1);
+
+    let stats = cache.stats().await.unwrap();
+    assert_eq!(stats.llm_count, 2);
+    assert_eq!(stats.image_count, 1);
+    assert_eq!(stats.document_count, 0);
+    assert_eq!(stats.expired_count, 1);
+    assert!(stats.total_size_estimate_bytes > 0);
+    assert_eq!(stats.oldest_entry.map(|d| d.timestamp()), Some(oldest.timestamp()));
+    assert!(stats.newest_entry.unwrap() > oldest);
+    assert_eq!((stats.hits, stats.misses), (Some(2), Some(2)));
+    assert_eq!(stats.hit_rate(), Some(0.5));
+}
+
+/// Test that the hit rate is unavailable without tracking
+#[tokio::test]
+async fn test_cache_stats_without_tracking() {
+    let api = lib::init_in_memory(None).await.unwrap();
+
+    api.cache().get_image("missing").await.unwrap();
+    let stats = api.cache_stats().await.unwrap();
+
+    assert_eq!(stats.hits, None);
+    assert_eq!(stats.hit_rate(), None);
+}
+
+/// Test exporting the cache to a file and importing it elsewhere
+#[tokio::test]
+async fn test_cache_export_import() {
+    use lib::audio::{AudioCache, AudioFormat, AudioMetadata, AudioSource, NewAudioCacheEntry};
+
+    let (db, temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+    let cache = lib::cache::CacheOperations::new(db.clone());
+
+    let document = DocumentCacheEntry {
+        id: None,
+        resource_hash: "doc".to_string(),
+        content_hash: "doc_content".to_string(),
+        file_path: Some("docs/a.md".to_string()),
+        url: None,
+        last_validated: Utc::now(),
+        file_size: Some(12),
+        modified_at: None,
+    };
+    cache.upsert_document(document.clone()).await.unwrap();
+    cache
+        .upsert_image(ImageCacheEntry {
+            id: None,
+            resource_hash: "img".to_string(),
+            content_hash: "img_content".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            source_type: "local".to_string(),
+            source: "a.png".to_string(),
+            has_transparency: true,
+            original_width: 64,
+            original_height: 32,
+            perceptual_hash: None,
+        })
+        .await
+        .unwrap();
+    cache
+        .upsert_llm(LlmCacheEntry {
+            id: None,
+            operation: "summarize".to_string(),
+            input_hash: "input".to_string(),
+            model: "test/model".to_string(),
+            response: "A summary".to_string(),
+            created_at: Utc::now(),
+            expires_at: Utc::now() + chrono::Duration::days(30),
+            tokens_used: Some(7),
+        })
+        .await
+        .unwrap();
+    AudioCache::new(db)
+        .upsert(NewAudioCacheEntry {
+            resource_hash: "audio".to_string(),
+            content_hash: "audio_content".to_string(),
+            source: AudioSource::Local("a.wav".into()),
+            format: AudioFormat::Wav,
+            metadata: AudioMetadata { duration_secs: Some(1.5), ..AudioMetadata::default() },
+        })
+        .await
+        .unwrap();
+
+    let path = temp_dir.path().join("cache.ndjson.gz");
+    let exported = cache.export(&path).await.unwrap();
+    assert_eq!(
+        (exported.document_count, exported.image_count, exported.audio_count, exported.llm_count),
+        (1, 1, 1, 1)
+    );
+    assert_eq!(exported.file_size_bytes, std::fs::metadata(&path).unwrap().len());
+
+    let (target_db, _target_dir) = init_test_db().await.unwrap();
+    apply_schema(&target_db).await.unwrap();
+    let target = lib::cache::CacheOperations::new(target_db.clone());
+
+    let imported = target.import(&path).await.unwrap();
+    assert_eq!((imported.document_count, imported.llm_count, imported.skipped_count), (1, 1, 0));
+
+    let restored = target.get_document("doc").await.unwrap().unwrap();
+    assert_eq!(restored.content_hash, document.content_hash);
+    assert_eq!(restored.file_size, Some(12));
+    assert_eq!(restored.last_validated.timestamp_micros(), document.last_validated.timestamp_micros());
+    assert!(target.get_image("img").await.unwrap().unwrap().has_transparency);
+    let llm = target.get_llm("summarize", "input", "test/model").await.unwrap().unwrap();
+    assert_eq!(llm.response, "A summary");
+    let audio = AudioCache::new(target_db).get("audio", "audio_content").await.unwrap().unwrap();
+    assert_eq!(audio.metadata.duration_secs, Some(1.5));
+
+    // Importing again finds everything already cached
+    let again = target.import(&path).await.unwrap();
+    assert_eq!(again.skipped_count, 4);
+    assert_eq!(again.document_count + again.image_count + again.audio_count + again.llm_count, 0);
+}
+
+/// Test that exports from another schema version are refused
+#[tokio::test]
+async fn test_cache_import_checks_schema_version() {
+    use std::io::Write;
+
+    let (db, temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let path = temp_dir.path().join("old.ndjson.gz");
+    let mut writer = flate2::write::GzEncoder::new(std::fs::File::create(&path).unwrap(), flate2::Compression::default());
+    writeln!(writer, "{{\"schema_version\":1}}").unwrap();
+    writer.finish().unwrap();
+
+    match cache.import(&path).await {
+        Err(lib::CompositionError::Cache(lib::CacheError::SchemaVersionMismatch { found, expected })) => {
+            assert_eq!(found, 1);
+            assert_eq!(expected, lib::cache::SCHEMA_VERSION);
+        }
+        other => panic!("Expected SchemaVersionMismatch, got {:?}", other),
+    }
+}
+
 /// Test cache invalidation
 #[tokio::test]
 async fn test_cache_invalidation() {
@@ -243,6 +713,7 @@ async fn test_cache_invalidation() {
         has_transparency: false,
         original_width: 100,
         original_height: 100,
+        perceptual_hash: None,
     };
 
     cache.upsert_image(entry.clone()).await.unwrap();
@@ -259,6 +730,125 @@ async fn test_cache_invalidation() {
     assert!(retrieved.is_none());
 }
 
+/// Test that a delete the database rejects fails the invalidation
+#[tokio::test]
+async fn test_failed_invalidation_is_an_error() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+    db.query("DEFINE EVENT reject_delete ON image_cache WHEN $event = 'DELETE' THEN { THROW 'read only' }")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+    db.query("DEFINE EVENT reject_delete ON document WHEN $event = 'DELETE' THEN { THROW 'read only' }")
+        .await
+        .unwrap()
+        .check()
+        .unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+    cache
+        .upsert_image(ImageCacheEntry {
+            id: None,
+            resource_hash: "locked".to_string(),
+            content_hash: "content".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            source_type: "local".to_string(),
+            source: "/tmp/locked.png".to_string(),
+            has_transparency: false,
+            original_width: 100,
+            original_height: 100,
+            perceptual_hash: None,
+        })
+        .await
+        .unwrap();
+    cache
+        .upsert_document(DocumentCacheEntry {
+            id: None,
+            resource_hash: "locked".to_string(),
+            content_hash: "content".to_string(),
+            file_path: Some("/tmp/locked.md".to_string()),
+            url: None,
+            last_validated: Utc::now(),
+            file_size: None,
+            modified_at: None,
+        })
+        .await
+        .unwrap();
+
+    assert!(cache.invalidate_image("locked").await.is_err());
+    assert!(cache.get_image("locked").await.unwrap().is_some());
+    assert!(cache.invalidate_document_cascade("locked").await.is_err());
+    assert!(cache.get_document("locked").await.unwrap().is_some());
+}
+
+/// Test clearing the document, image and LLM caches
+#[tokio::test]
+async fn test_clear_cache_tables() {
+    let (db, _temp_dir) = init_test_db().await.unwrap();
+    apply_schema(&db).await.unwrap();
+
+    let cache = lib::cache::CacheOperations::new(db);
+
+    let document = DocumentCacheEntry {
+        id: None,
+        resource_hash: "clear_doc".to_string(),
+        content_hash: "content".to_string(),
+        file_path: Some("/tmp/clear.md".to_string()),
+        url: None,
+        last_validated: Utc::now(),
+        file_size: None,
+        modified_at: None,
+    };
+    let image = ImageCacheEntry {
+        id: None,
+        resource_hash: "clear_image".to_string(),
+        content_hash: "content".to_string(),
+        created_at: Utc::now(),
+        expires_at: None,
+        source_type: "local".to_string(),
+        source: "/tmp/clear.png".to_string(),
+        has_transparency: false,
+        original_width: 10,
+        original_height: 10,
+        perceptual_hash: None,
+    };
+    let llm = LlmCacheEntry {
+        id: None,
+        operation: "summarize".to_string(),
+        input_hash: "clear_input".to_string(),
+        model: "test/model".to_string(),
+        response: "cleared".to_string(),
+        created_at: Utc::now(),
+        expires_at: Utc::now() + chrono::Duration::days(30),
+        tokens_used: None,
+    };
+
+    let populate = || async {
+        cache.upsert_document(document.clone()).await.unwrap();
+        cache.upsert_image(image.clone()).await.unwrap();
+        cache.upsert_llm(llm.clone()).await.unwrap();
+    };
+
+    // Per-table clears leave the other tables alone
+    populate().await;
+    cache.clear_documents().await.unwrap();
+    assert!(cache.get_document("clear_doc").await.unwrap().is_none());
+    assert!(cache.get_image("clear_image").await.unwrap().is_some());
+    cache.clear_images().await.unwrap();
+    assert!(cache.get_image("clear_image").await.unwrap().is_none());
+    assert!(cache.get_llm("summarize", "clear_input", "test/model").await.unwrap().is_some());
+    cache.clear_llm().await.unwrap();
+    assert!(cache.get_llm("summarize", "clear_input", "test/model").await.unwrap().is_none());
+
+    populate().await;
+    cache.clear_all().await.unwrap();
+    assert!(cache.get_document("clear_doc").await.unwrap().is_none());
+    assert!(cache.get_image("clear_image").await.unwrap().is_none());
+    assert!(cache.get_llm("summarize", "clear_input", "test/model").await.unwrap().is_none());
+}
+
 /// Test project scope detection (git vs non-git)
 #[tokio::test]
 async fn test_project_scope_detection() {
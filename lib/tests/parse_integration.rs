@@ -1,5 +1,5 @@
-use lib::parse::parse_document;
-use lib::types::{DarkMatterNode, Resource};
+use lib::parse::{parse_document, ParseOptions};
+use lib::types::{DarkMatterNode, LineRange, Resource};
 use std::path::PathBuf;
 
 #[test]
@@ -38,7 +38,7 @@ Some regular markdown content with **bold** and *italic* text.
 "#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     // Verify frontmatter
     assert_eq!(doc.frontmatter.get_string("title"), Some("Integration Test Document"));
@@ -88,7 +88,7 @@ greeting: Hello
 {{greeting}} {{name}}, welcome to our {{name}}'s page!"#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     // Count interpolations
     let interpolation_count = doc.content.iter()
@@ -109,7 +109,7 @@ fn test_resource_requirements() {
 "#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     assert_eq!(doc.dependencies.len(), 3);
 
@@ -141,7 +141,7 @@ fn test_remote_resources() {
 "#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     assert_eq!(doc.dependencies.len(), 2);
 
@@ -166,7 +166,7 @@ fn test_line_range_parsing() {
     let content = "::file ./code.rs 10-50";
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     let file_node = doc.content.iter()
         .find_map(|n| match n {
@@ -175,9 +175,7 @@ fn test_line_range_parsing() {
         })
         .expect("Should have found file node");
 
-    let range = file_node.as_ref().expect("Should have range");
-    assert_eq!(range.start, 10);
-    assert_eq!(range.end, Some(50));
+    assert_eq!(file_node, &Some(LineRange::Lines(10, Some(50))));
 }
 
 #[test]
@@ -201,7 +199,7 @@ This has ~~strikethrough~~ text.
 "#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let result = parse_document(content, resource);
+    let result = parse_document(content, resource, ParseOptions::default());
 
     assert!(result.is_ok(), "GFM extensions should parse without error");
 }
@@ -229,7 +227,7 @@ breakpoints:
 Content here"#;
 
     let resource = Resource::local(PathBuf::from("test.md"));
-    let doc = parse_document(content, resource).unwrap();
+    let (doc, _) = parse_document(content, resource, ParseOptions::default()).unwrap();
 
     assert_eq!(doc.frontmatter.get_string("title"), Some("Complex Document"));
 
@@ -8,7 +8,7 @@
 //! - Asset deduplication across multiple embeds
 
 use lib::parse::parse_directive;
-use lib::render::{render_youtube_embed, youtube_css, youtube_js};
+use lib::render::{render_youtube_embed, to_html, youtube_css, youtube_js};
 use lib::types::{DarkMatterNode, WidthSpec};
 
 #[test]
@@ -21,7 +21,7 @@ fn test_single_youtube_embed_full_pipeline() {
     assert!(node.is_some());
 
     match node.unwrap() {
-        DarkMatterNode::YouTube { video_id, width } => {
+        DarkMatterNode::YouTube { video_id, width, .. } => {
             assert_eq!(video_id, "dQw4w9WgXcQ");
             assert_eq!(width, WidthSpec::Pixels(800));
 
@@ -60,7 +60,7 @@ fn test_multiple_youtube_embeds_in_document() {
     let mut html_output = String::new();
     for node in &nodes {
         match node {
-            DarkMatterNode::YouTube { video_id, width } => {
+            DarkMatterNode::YouTube { video_id, width, .. } => {
                 html_output.push_str(&render_youtube_embed(video_id, width));
                 html_output.push('\n');
             }
@@ -147,7 +147,7 @@ fn test_youtube_with_different_url_formats() {
         assert!(node.is_some(), "No node for: {}", directive);
 
         match node.unwrap() {
-            DarkMatterNode::YouTube { video_id, width: _ } => {
+            DarkMatterNode::YouTube { video_id, width: _, .. } => {
                 assert_eq!(
                     video_id, expected_id,
                     "Wrong video ID for directive: {}",
@@ -179,7 +179,7 @@ fn test_youtube_with_different_width_formats() {
         assert!(node.is_some(), "No node for: {}", directive);
 
         match node.unwrap() {
-            DarkMatterNode::YouTube { video_id: _, width } => {
+            DarkMatterNode::YouTube { video_id: _, width, .. } => {
                 assert_eq!(
                     width, expected_width,
                     "Wrong width for directive: {}",
@@ -358,7 +358,7 @@ fn test_url_with_query_parameters() {
     assert!(result.is_ok());
 
     match result.unwrap().unwrap() {
-        DarkMatterNode::YouTube { video_id, width: _ } => {
+        DarkMatterNode::YouTube { video_id, width: _, .. } => {
             assert_eq!(video_id, "dQw4w9WgXcQ");
         }
         _ => panic!("Expected YouTube node"),
@@ -488,3 +488,21 @@ fn test_concurrent_rendering() {
         assert!(html.contains(video_ids[i]));
     }
 }
+
+#[test]
+fn test_youtube_captions_language() {
+    let node = parse_directive("::youtube https://youtu.be/dQw4w9WgXcQ 40rem cc=pt-BR", 1).unwrap().unwrap();
+    assert!(matches!(&node, DarkMatterNode::YouTube { captions: Some(language), .. } if language == "pt-BR"));
+
+    let html = to_html(&[node]).unwrap();
+    assert!(
+        html.contains("embed/dQw4w9WgXcQ?enablejsapi=1&amp;cc_load_policy=1&amp;cc_lang_pref=pt-BR\""),
+        "{}",
+        html
+    );
+
+    // Without cc= YouTube's own caption settings apply
+    let node = parse_directive("::youtube dQw4w9WgXcQ", 1).unwrap().unwrap();
+    assert!(matches!(node, DarkMatterNode::YouTube { captions: None, .. }));
+    assert!(!render_youtube_embed("dQw4w9WgXcQ", &WidthSpec::default()).contains("cc_load_policy"));
+}